@@ -13,7 +13,7 @@ use ethereum_types::{Address, H256, U256};
 use evm_arithmetization::cpu::kernel::aggregator::KERNEL;
 use evm_arithmetization::cpu::kernel::opcodes::{get_opcode, get_push_opcode};
 use evm_arithmetization::generation::mpt::{AccountRlp, LegacyReceiptRlp};
-use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::generation::{ChainProfile, GenerationInputs, TrieInputs};
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
 use evm_arithmetization::prover::testing::simulate_execution;
 use evm_arithmetization::testing_utils::{
@@ -179,9 +179,11 @@ fn prepare_setup() -> anyhow::Result<GenerationInputs> {
     Ok(GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals: vec![],
         tries: tries_before,
         trie_roots_after,
+        expected_tries_after: None,
         contract_code,
         checkpoint_state_trie_root: H256(hex!(
             "fe07ff6d1ab215df17884b89112ccf2373597285a56c5902150313ad1a53ee57"
@@ -1,12 +1,83 @@
+//! Assembles the given files and prints the resulting code in hex.
+//!
+//! With `--symbolize <offset>`, prints instead the location in the assembly
+//! source of the code at this offset, within the given files or within the
+//! kernel if no file is given.
+//!
+//! With `--dead-code`, reports instead the unreachable routines and the unused
+//! macros and constants of the given files, or of the kernel if no file is
+//! given.
+
 use std::{env, fs};
 
-use evm_arithmetization::cpu::kernel::assemble_to_bytes;
+use evm_arithmetization::cpu::kernel::aggregator::{KERNEL, KERNEL_FILES};
+use evm_arithmetization::cpu::kernel::dead_code::{find_dead_code, KERNEL_ENTRY_POINTS};
+use evm_arithmetization::cpu::kernel::{assemble_files, assemble_to_bytes};
 use hex::encode;
 
 fn main() {
     let mut args = env::args();
     args.next();
-    let file_contents: Vec<_> = args.map(|path| fs::read_to_string(path).unwrap()).collect();
-    let assembled = assemble_to_bytes(&file_contents[..]);
-    println!("{}", encode(assembled));
+
+    let mut symbolize = None;
+    let mut dead_code = false;
+    let mut paths = vec![];
+    while let Some(arg) = args.next() {
+        if arg == "--dead-code" {
+            dead_code = true;
+        } else if arg == "--symbolize" {
+            let offset = args.next().expect("Missing offset to symbolize");
+            symbolize = Some(parse_offset(&offset));
+        } else {
+            paths.push(arg);
+        }
+    }
+
+    if dead_code {
+        let report = if paths.is_empty() {
+            find_dead_code(&KERNEL_FILES, &KERNEL_ENTRY_POINTS)
+        } else {
+            let files = read_files(paths);
+            let files: Vec<_> = files
+                .iter()
+                .map(|(path, contents)| (path.as_str(), contents.as_str()))
+                .collect();
+            find_dead_code(&files, &KERNEL_ENTRY_POINTS)
+        };
+        print!("{report}");
+        return;
+    }
+
+    match symbolize {
+        Some(offset) if paths.is_empty() => println!("{}", KERNEL.symbolize(offset)),
+        Some(offset) => println!("{}", assemble_files(&read_files(paths)).symbolize(offset)),
+        None => {
+            let file_contents: Vec<_> = paths
+                .into_iter()
+                .map(|path| fs::read_to_string(path).unwrap())
+                .collect();
+            let assembled = assemble_to_bytes(&file_contents[..]);
+            println!("{}", encode(assembled));
+        }
+    }
+}
+
+/// Reads the given files, returning their paths along with their contents.
+fn read_files(paths: Vec<String>) -> Vec<(String, String)> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = fs::read_to_string(&path).unwrap();
+            (path, contents)
+        })
+        .collect()
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal offset.
+fn parse_offset(offset: &str) -> usize {
+    match offset.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => offset.parse(),
+    }
+    .unwrap_or_else(|_| panic!("Invalid offset: {offset}"))
 }
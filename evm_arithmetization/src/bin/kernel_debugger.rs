@@ -0,0 +1,21 @@
+//! Runs the kernel on the given `GenerationInputs`, such as the ones saved to
+//! disk by the prover on failure, within an interactive debugger reading its
+//! commands from the standard input. Type `help` for a list of commands.
+//!
+//! Usage: `kernel_debugger <inputs.json>`
+
+use std::{env, fs, io};
+
+use evm_arithmetization::cpu::kernel::debugger::KernelDebugger;
+use evm_arithmetization::GenerationInputs;
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+fn main() -> anyhow::Result<()> {
+    let Some(inputs_path) = env::args().nth(1) else {
+        anyhow::bail!("Usage: kernel_debugger <inputs.json>");
+    };
+
+    let inputs: GenerationInputs = serde_json::from_slice(&fs::read(inputs_path)?)?;
+    let mut debugger = KernelDebugger::<GoldilocksField>::new(&inputs);
+    debugger.run(io::stdin().lock(), io::stdout().lock(), true)
+}
@@ -0,0 +1,47 @@
+//! Profiles the kernel execution of the given `GenerationInputs`, such as the
+//! ones saved to disk by the prover on failure, and writes a folded-stack file
+//! for each profiled metric, to be rendered by flame graph tools.
+//!
+//! Usage: `profile_kernel <inputs.json> <output_dir> [max_cpu_len_log]`
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use evm_arithmetization::cpu::kernel::profiler::ProfileMetric;
+use evm_arithmetization::prover::testing::profile_execution_all_segments;
+use evm_arithmetization::GenerationInputs;
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+const DEFAULT_MAX_CPU_LEN_LOG: usize = 20;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = env::args().skip(1);
+    let (Some(inputs_path), Some(output_dir)) = (args.next(), args.next()) else {
+        anyhow::bail!("Usage: profile_kernel <inputs.json> <output_dir> [max_cpu_len_log]");
+    };
+    let max_cpu_len_log = match args.next() {
+        Some(arg) => arg.parse()?,
+        None => DEFAULT_MAX_CPU_LEN_LOG,
+    };
+
+    let inputs: GenerationInputs = serde_json::from_slice(&fs::read(inputs_path)?)?;
+    let profile = profile_execution_all_segments::<GoldilocksField>(inputs, max_cpu_len_log)?;
+
+    let output_dir = PathBuf::from(output_dir);
+    fs::create_dir_all(&output_dir)?;
+    for metric in ProfileMetric::ALL {
+        let path = output_dir.join(format!("{}.folded", metric.name()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        profile.write_folded(metric, &mut writer)?;
+        writer.flush()?;
+    }
+
+    println!("{:#?}", profile.total());
+    for (mnemonic, count) in profile.opcode_counts() {
+        println!("{mnemonic}: {count}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Traces the user code executed by the transactions of the given
+//! `GenerationInputs`, and writes an EIP-3155 trace for each of them. If geth
+//! struct logs are given, as saved from `debug_traceTransaction`, the trace of
+//! the given transaction, the first one by default, is compared against them.
+//!
+//! Usage: `trace_user_code <inputs.json> <output_dir> [struct_logs.json
+//! [txn_index]]`
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use evm_arithmetization::cpu::kernel::tracer::parse_geth_struct_logs;
+use evm_arithmetization::prover::testing::trace_execution_all_segments;
+use evm_arithmetization::GenerationInputs;
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+const MAX_CPU_LEN_LOG: usize = 20;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = env::args().skip(1);
+    let (Some(inputs_path), Some(output_dir)) = (args.next(), args.next()) else {
+        anyhow::bail!(
+            "Usage: trace_user_code <inputs.json> <output_dir> [struct_logs.json [txn_index]]"
+        );
+    };
+    let struct_logs_path = args.next();
+    let txn_index: usize = match args.next() {
+        Some(arg) => arg.parse()?,
+        None => 0,
+    };
+
+    let inputs: GenerationInputs = serde_json::from_slice(&fs::read(inputs_path)?)?;
+    let traces = trace_execution_all_segments::<GoldilocksField>(inputs, MAX_CPU_LEN_LOG)?;
+
+    let output_dir = PathBuf::from(output_dir);
+    fs::create_dir_all(&output_dir)?;
+    for trace in &traces {
+        let path = output_dir.join(format!("{}_{:?}.jsonl", trace.txn_index, trace.txn_hash));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        trace.write_jsonl(&mut writer)?;
+        writer.flush()?;
+        println!("{}: {} steps", path.display(), trace.steps.len());
+    }
+
+    if let Some(struct_logs_path) = struct_logs_path {
+        let struct_logs = parse_geth_struct_logs(&fs::read(struct_logs_path)?)?;
+        let Some(trace) = traces.iter().find(|trace| trace.txn_index == txn_index) else {
+            anyhow::bail!("Transaction {txn_index} executed no user code");
+        };
+        match trace.diff(&struct_logs) {
+            Some(divergence) => anyhow::bail!("Traces diverge at {divergence}"),
+            None => println!("Traces match over {} steps", trace.steps.len()),
+        }
+    }
+
+    Ok(())
+}
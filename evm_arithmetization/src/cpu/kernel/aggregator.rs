@@ -2,192 +2,208 @@
 
 use std::collections::HashSet;
 
-use itertools::Itertools;
 use once_cell::sync::Lazy;
 
 use super::assembler::{assemble, Kernel};
 use crate::cpu::kernel::constants::evm_constants;
 use crate::cpu::kernel::parser::parse;
 
-pub const NUMBER_KERNEL_FILES: usize = 159;
+pub const NUMBER_KERNEL_FILES: usize = 169;
 
-pub static KERNEL_FILES: [&str; NUMBER_KERNEL_FILES] = [
-    "global jumped_to_0: PANIC",
-    "global jumped_to_1: PANIC",
-    include_str!("asm/beacon_roots.asm"),
-    include_str!("asm/bignum/add.asm"),
-    include_str!("asm/bignum/addmul.asm"),
-    include_str!("asm/bignum/cmp.asm"),
-    include_str!("asm/bignum/isone.asm"),
-    include_str!("asm/bignum/iszero.asm"),
-    include_str!("asm/bignum/modexp.asm"),
-    include_str!("asm/bignum/modmul.asm"),
-    include_str!("asm/bignum/mul.asm"),
-    include_str!("asm/bignum/shr.asm"),
-    include_str!("asm/bignum/util.asm"),
-    include_str!("asm/core/call.asm"),
-    include_str!("asm/core/call_gas.asm"),
-    include_str!("asm/core/create.asm"),
-    include_str!("asm/core/create_addresses.asm"),
-    include_str!("asm/core/create_contract_account.asm"),
-    include_str!("asm/core/exception.asm"),
-    include_str!("asm/core/create_receipt.asm"),
-    include_str!("asm/core/gas.asm"),
-    include_str!("asm/core/intrinsic_gas.asm"),
-    include_str!("asm/core/jumpdest_analysis.asm"),
-    include_str!("asm/core/nonce.asm"),
-    include_str!("asm/core/process_txn.asm"),
-    include_str!("asm/core/syscall.asm"),
-    include_str!("asm/core/terminate.asm"),
-    include_str!("asm/core/transfer.asm"),
-    include_str!("asm/core/util.asm"),
-    include_str!("asm/core/access_lists.asm"),
-    include_str!("asm/core/log.asm"),
-    include_str!("asm/core/selfdestruct_list.asm"),
-    include_str!("asm/core/touched_addresses.asm"),
-    include_str!("asm/core/withdrawals.asm"),
-    include_str!("asm/core/precompiles/main.asm"),
-    include_str!("asm/core/precompiles/ecrec.asm"),
-    include_str!("asm/core/precompiles/sha256.asm"),
-    include_str!("asm/core/precompiles/rip160.asm"),
-    include_str!("asm/core/precompiles/id.asm"),
-    include_str!("asm/core/precompiles/expmod.asm"),
-    include_str!("asm/core/precompiles/bn_add.asm"),
-    include_str!("asm/core/precompiles/bn_mul.asm"),
-    include_str!("asm/core/precompiles/snarkv.asm"),
-    include_str!("asm/core/precompiles/blake2_f.asm"),
-    include_str!("asm/core/precompiles/kzg_peval.asm"),
-    include_str!("asm/curve/bls381/util.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/constants.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/curve_add.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/curve_mul.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/final_exponent.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/glv.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/miller_loop.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/msm.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/pairing.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/precomputation.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/twisted_curve_add.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/twisted_curve_checks.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/twisted_curve_endomorphism.asm"),
-    include_str!("asm/curve/bn254/curve_arithmetic/twisted_curve_mul.asm"),
-    include_str!("asm/curve/bn254/field_arithmetic/degree_6_mul.asm"),
-    include_str!("asm/curve/bn254/field_arithmetic/degree_12_mul.asm"),
-    include_str!("asm/curve/bn254/field_arithmetic/frobenius.asm"),
-    include_str!("asm/curve/bn254/field_arithmetic/inverse.asm"),
-    include_str!("asm/curve/bn254/field_arithmetic/util.asm"),
-    include_str!("asm/curve/common.asm"),
-    include_str!("asm/curve/secp256k1/curve_add.asm"),
-    include_str!("asm/curve/secp256k1/ecrecover.asm"),
-    include_str!("asm/curve/secp256k1/inverse_scalar.asm"),
-    include_str!("asm/curve/secp256k1/lift_x.asm"),
-    include_str!("asm/curve/secp256k1/moddiv.asm"),
-    include_str!("asm/curve/secp256k1/glv.asm"),
-    include_str!("asm/curve/secp256k1/precomputation.asm"),
-    include_str!("asm/curve/wnaf.asm"),
-    include_str!("asm/exp.asm"),
-    include_str!("asm/halt.asm"),
-    include_str!("asm/hash/blake2/addresses.asm"),
-    include_str!("asm/hash/blake2/blake2_f.asm"),
-    // include_str!("asm/hash/blake2/blake2b.asm"),
-    // include_str!("asm/hash/blake2/compression.asm"),
-    include_str!("asm/hash/blake2/g_functions.asm"),
-    include_str!("asm/hash/blake2/hash.asm"),
-    include_str!("asm/hash/blake2/iv.asm"),
-    include_str!("asm/hash/blake2/ops.asm"),
-    include_str!("asm/hash/blake2/permutations.asm"),
-    include_str!("asm/hash/ripemd/box.asm"),
-    include_str!("asm/hash/ripemd/compression.asm"),
-    include_str!("asm/hash/ripemd/constants.asm"),
-    include_str!("asm/hash/ripemd/functions.asm"),
-    include_str!("asm/hash/ripemd/main.asm"),
-    include_str!("asm/hash/ripemd/update.asm"),
-    include_str!("asm/hash/sha2/compression.asm"),
-    include_str!("asm/hash/sha2/constants.asm"),
-    include_str!("asm/hash/sha2/main.asm"),
-    include_str!("asm/hash/sha2/message_schedule.asm"),
-    include_str!("asm/hash/sha2/ops.asm"),
-    include_str!("asm/hash/sha2/temp_words.asm"),
-    include_str!("asm/hash/sha2/write_length.asm"),
-    include_str!("asm/main.asm"),
-    include_str!("asm/memory/core.asm"),
-    include_str!("asm/memory/memcpy.asm"),
-    include_str!("asm/memory/memset.asm"),
-    include_str!("asm/memory/metadata.asm"),
-    include_str!("asm/memory/packing.asm"),
-    include_str!("asm/memory/syscalls.asm"),
-    include_str!("asm/memory/txn_fields.asm"),
-    include_str!("asm/memory/transient_storage.asm"),
-    include_str!("asm/mpt/accounts.asm"),
-    include_str!("asm/mpt/delete/delete.asm"),
-    include_str!("asm/mpt/delete/delete_branch.asm"),
-    include_str!("asm/mpt/delete/delete_extension.asm"),
-    include_str!("asm/mpt/hash/hash.asm"),
-    include_str!("asm/mpt/hash/hash_trie_specific.asm"),
-    include_str!("asm/mpt/hex_prefix.asm"),
-    include_str!("asm/mpt/insert/insert.asm"),
-    include_str!("asm/mpt/insert/insert_extension.asm"),
-    include_str!("asm/mpt/insert/insert_leaf.asm"),
-    include_str!("asm/mpt/insert/insert_trie_specific.asm"),
-    include_str!("asm/mpt/linked_list/linked_list.asm"),
-    include_str!("asm/mpt/linked_list/initial_tries.asm"),
-    include_str!("asm/mpt/linked_list/final_tries.asm"),
-    include_str!("asm/mpt/read.asm"),
-    include_str!("asm/mpt/storage/storage_read.asm"),
-    include_str!("asm/mpt/storage/storage_write.asm"),
-    include_str!("asm/mpt/util.asm"),
-    include_str!("asm/rlp/decode.asm"),
-    include_str!("asm/rlp/encode.asm"),
-    include_str!("asm/rlp/encode_rlp_scalar.asm"),
-    include_str!("asm/rlp/encode_rlp_string.asm"),
-    include_str!("asm/rlp/increment_bounded_rlp.asm"),
-    include_str!("asm/rlp/num_bytes.asm"),
-    include_str!("asm/rlp/read_to_memory.asm"),
-    include_str!("asm/shift.asm"),
-    include_str!("asm/signed.asm"),
-    include_str!("asm/journal/journal.asm"),
-    include_str!("asm/journal/account_loaded.asm"),
-    include_str!("asm/journal/account_destroyed.asm"),
-    include_str!("asm/journal/account_touched.asm"),
-    include_str!("asm/journal/balance_transfer.asm"),
-    include_str!("asm/journal/nonce_change.asm"),
-    include_str!("asm/journal/storage_change.asm"),
-    include_str!("asm/journal/storage_loaded.asm"),
-    include_str!("asm/journal/code_change.asm"),
-    include_str!("asm/journal/refund.asm"),
-    include_str!("asm/journal/account_created.asm"),
-    include_str!("asm/journal/revert.asm"),
-    include_str!("asm/journal/log.asm"),
-    include_str!("asm/journal/transient_storage_change.asm"),
-    include_str!("asm/transactions/common_decoding.asm"),
-    include_str!("asm/transactions/router.asm"),
-    include_str!("asm/transactions/type_0.asm"),
-    include_str!("asm/transactions/type_1.asm"),
-    include_str!("asm/transactions/type_2.asm"),
-    include_str!("asm/transactions/type_3.asm"),
-    include_str!("asm/util/assertions.asm"),
-    include_str!("asm/util/basic_macros.asm"),
-    include_str!("asm/util/keccak.asm"),
-    include_str!("asm/util/math.asm"),
-    include_str!("asm/account_code.asm"),
-    include_str!("asm/balance.asm"),
-    include_str!("asm/bloom_filter.asm"),
-    include_str!("asm/global_exit_root.asm"),
+/// Pairs the path of a kernel assembly file with its contents.
+macro_rules! asm_file {
+    ($path:literal) => {
+        ($path, include_str!($path))
+    };
+}
+
+/// The name and contents of each kernel assembly file.
+pub static KERNEL_FILES: [(&str, &str); NUMBER_KERNEL_FILES] = [
+    ("<builtin>", "global jumped_to_0: PANIC"),
+    ("<builtin>", "global jumped_to_1: PANIC"),
+    asm_file!("asm/beacon_roots.asm"),
+    asm_file!("asm/bignum/add.asm"),
+    asm_file!("asm/bignum/addmul.asm"),
+    asm_file!("asm/bignum/cmp.asm"),
+    asm_file!("asm/bignum/isone.asm"),
+    asm_file!("asm/bignum/iszero.asm"),
+    asm_file!("asm/bignum/modexp.asm"),
+    asm_file!("asm/bignum/modmul.asm"),
+    asm_file!("asm/bignum/mul.asm"),
+    asm_file!("asm/bignum/shr.asm"),
+    asm_file!("asm/bignum/util.asm"),
+    asm_file!("asm/core/call.asm"),
+    asm_file!("asm/core/call_gas.asm"),
+    asm_file!("asm/core/create.asm"),
+    asm_file!("asm/core/create_addresses.asm"),
+    asm_file!("asm/core/create_contract_account.asm"),
+    asm_file!("asm/core/exception.asm"),
+    asm_file!("asm/core/create_receipt.asm"),
+    asm_file!("asm/core/gas.asm"),
+    asm_file!("asm/core/intrinsic_gas.asm"),
+    asm_file!("asm/core/jumpdest_analysis.asm"),
+    asm_file!("asm/core/nonce.asm"),
+    asm_file!("asm/core/process_txn.asm"),
+    asm_file!("asm/core/syscall.asm"),
+    asm_file!("asm/core/terminate.asm"),
+    asm_file!("asm/core/transfer.asm"),
+    asm_file!("asm/core/util.asm"),
+    asm_file!("asm/core/access_lists.asm"),
+    asm_file!("asm/core/log.asm"),
+    asm_file!("asm/core/selfdestruct_list.asm"),
+    asm_file!("asm/core/touched_addresses.asm"),
+    asm_file!("asm/core/withdrawals.asm"),
+    asm_file!("asm/core/precompiles/main.asm"),
+    asm_file!("asm/core/precompiles/ecrec.asm"),
+    asm_file!("asm/core/precompiles/sha256.asm"),
+    asm_file!("asm/core/precompiles/rip160.asm"),
+    asm_file!("asm/core/precompiles/id.asm"),
+    asm_file!("asm/core/precompiles/expmod.asm"),
+    asm_file!("asm/core/precompiles/bn_add.asm"),
+    asm_file!("asm/core/precompiles/bn_mul.asm"),
+    asm_file!("asm/core/precompiles/snarkv.asm"),
+    asm_file!("asm/core/precompiles/blake2_f.asm"),
+    asm_file!("asm/core/precompiles/kzg_peval.asm"),
+    asm_file!("asm/core/precompiles/bls12_381.asm"),
+    asm_file!("asm/core/precompiles/p256_verify.asm"),
+    asm_file!("asm/curve/bls381/fp12.asm"),
+    asm_file!("asm/curve/bls381/g1.asm"),
+    asm_file!("asm/curve/bls381/g2.asm"),
+    asm_file!("asm/curve/bls381/map_to_curve.asm"),
+    asm_file!("asm/curve/bls381/pairing.asm"),
+    asm_file!("asm/curve/bls381/util.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/constants.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/curve_add.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/curve_mul.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/final_exponent.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/glv.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/miller_loop.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/msm.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/pairing.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/precomputation.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/twisted_curve_add.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/twisted_curve_checks.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/twisted_curve_endomorphism.asm"),
+    asm_file!("asm/curve/bn254/curve_arithmetic/twisted_curve_mul.asm"),
+    asm_file!("asm/curve/bn254/field_arithmetic/degree_6_mul.asm"),
+    asm_file!("asm/curve/bn254/field_arithmetic/degree_12_mul.asm"),
+    asm_file!("asm/curve/bn254/field_arithmetic/frobenius.asm"),
+    asm_file!("asm/curve/bn254/field_arithmetic/inverse.asm"),
+    asm_file!("asm/curve/bn254/field_arithmetic/util.asm"),
+    asm_file!("asm/curve/common.asm"),
+    asm_file!("asm/curve/secp256k1/curve_add.asm"),
+    asm_file!("asm/curve/secp256k1/ecrecover.asm"),
+    asm_file!("asm/curve/secp256k1/inverse_scalar.asm"),
+    asm_file!("asm/curve/secp256k1/lift_x.asm"),
+    asm_file!("asm/curve/secp256k1/moddiv.asm"),
+    asm_file!("asm/curve/secp256k1/glv.asm"),
+    asm_file!("asm/curve/secp256k1/precomputation.asm"),
+    asm_file!("asm/curve/secp256r1/curve_add.asm"),
+    asm_file!("asm/curve/secp256r1/ecdsa_verify.asm"),
+    asm_file!("asm/curve/wnaf.asm"),
+    asm_file!("asm/exp.asm"),
+    asm_file!("asm/halt.asm"),
+    asm_file!("asm/hash/blake2/addresses.asm"),
+    asm_file!("asm/hash/blake2/blake2_f.asm"),
+    // asm_file!("asm/hash/blake2/blake2b.asm"),
+    // asm_file!("asm/hash/blake2/compression.asm"),
+    asm_file!("asm/hash/blake2/g_functions.asm"),
+    asm_file!("asm/hash/blake2/hash.asm"),
+    asm_file!("asm/hash/blake2/iv.asm"),
+    asm_file!("asm/hash/blake2/ops.asm"),
+    asm_file!("asm/hash/blake2/permutations.asm"),
+    asm_file!("asm/hash/ripemd/box.asm"),
+    asm_file!("asm/hash/ripemd/compression.asm"),
+    asm_file!("asm/hash/ripemd/constants.asm"),
+    asm_file!("asm/hash/ripemd/functions.asm"),
+    asm_file!("asm/hash/ripemd/main.asm"),
+    asm_file!("asm/hash/ripemd/update.asm"),
+    asm_file!("asm/hash/sha2/compression.asm"),
+    asm_file!("asm/hash/sha2/constants.asm"),
+    asm_file!("asm/hash/sha2/main.asm"),
+    asm_file!("asm/hash/sha2/message_schedule.asm"),
+    asm_file!("asm/hash/sha2/ops.asm"),
+    asm_file!("asm/hash/sha2/temp_words.asm"),
+    asm_file!("asm/hash/sha2/write_length.asm"),
+    asm_file!("asm/main.asm"),
+    asm_file!("asm/memory/core.asm"),
+    asm_file!("asm/memory/memcpy.asm"),
+    asm_file!("asm/memory/memset.asm"),
+    asm_file!("asm/memory/metadata.asm"),
+    asm_file!("asm/memory/packing.asm"),
+    asm_file!("asm/memory/syscalls.asm"),
+    asm_file!("asm/memory/txn_fields.asm"),
+    asm_file!("asm/memory/transient_storage.asm"),
+    asm_file!("asm/mpt/accounts.asm"),
+    asm_file!("asm/mpt/delete/delete.asm"),
+    asm_file!("asm/mpt/delete/delete_branch.asm"),
+    asm_file!("asm/mpt/delete/delete_extension.asm"),
+    asm_file!("asm/mpt/hash/hash.asm"),
+    asm_file!("asm/mpt/hash/hash_trie_specific.asm"),
+    asm_file!("asm/mpt/hex_prefix.asm"),
+    asm_file!("asm/mpt/insert/insert.asm"),
+    asm_file!("asm/mpt/insert/insert_extension.asm"),
+    asm_file!("asm/mpt/insert/insert_leaf.asm"),
+    asm_file!("asm/mpt/insert/insert_trie_specific.asm"),
+    asm_file!("asm/mpt/linked_list/linked_list.asm"),
+    asm_file!("asm/mpt/linked_list/initial_tries.asm"),
+    asm_file!("asm/mpt/linked_list/final_tries.asm"),
+    asm_file!("asm/mpt/read.asm"),
+    asm_file!("asm/mpt/storage/storage_read.asm"),
+    asm_file!("asm/mpt/storage/storage_write.asm"),
+    asm_file!("asm/mpt/util.asm"),
+    asm_file!("asm/rlp/decode.asm"),
+    asm_file!("asm/rlp/encode.asm"),
+    asm_file!("asm/rlp/encode_rlp_scalar.asm"),
+    asm_file!("asm/rlp/encode_rlp_string.asm"),
+    asm_file!("asm/rlp/increment_bounded_rlp.asm"),
+    asm_file!("asm/rlp/num_bytes.asm"),
+    asm_file!("asm/rlp/read_to_memory.asm"),
+    asm_file!("asm/shift.asm"),
+    asm_file!("asm/signed.asm"),
+    asm_file!("asm/journal/journal.asm"),
+    asm_file!("asm/journal/account_loaded.asm"),
+    asm_file!("asm/journal/account_destroyed.asm"),
+    asm_file!("asm/journal/account_touched.asm"),
+    asm_file!("asm/journal/balance_transfer.asm"),
+    asm_file!("asm/journal/nonce_change.asm"),
+    asm_file!("asm/journal/storage_change.asm"),
+    asm_file!("asm/journal/storage_loaded.asm"),
+    asm_file!("asm/journal/code_change.asm"),
+    asm_file!("asm/journal/refund.asm"),
+    asm_file!("asm/journal/account_created.asm"),
+    asm_file!("asm/journal/revert.asm"),
+    asm_file!("asm/journal/log.asm"),
+    asm_file!("asm/journal/transient_storage_change.asm"),
+    asm_file!("asm/transactions/common_decoding.asm"),
+    asm_file!("asm/transactions/router.asm"),
+    asm_file!("asm/transactions/type_0.asm"),
+    asm_file!("asm/transactions/type_1.asm"),
+    asm_file!("asm/transactions/type_2.asm"),
+    asm_file!("asm/transactions/type_3.asm"),
+    asm_file!("asm/transactions/type_4.asm"),
+    asm_file!("asm/util/assertions.asm"),
+    asm_file!("asm/util/basic_macros.asm"),
+    asm_file!("asm/util/keccak.asm"),
+    asm_file!("asm/util/math.asm"),
+    asm_file!("asm/account_code.asm"),
+    asm_file!("asm/balance.asm"),
+    asm_file!("asm/bloom_filter.asm"),
+    asm_file!("asm/global_exit_root.asm"),
 ];
 
 pub static KERNEL: Lazy<Kernel> = Lazy::new(combined_kernel);
 
-pub(crate) fn combined_kernel_from_files<const N: usize>(files: [&str; N]) -> Kernel {
-    let mut active_features = HashSet::new();
-    if cfg!(feature = "cdk_erigon") {
-        active_features.insert("cdk_erigon");
-    }
+pub(crate) fn combined_kernel_from_files<const N: usize>(files: [(&str, &str); N]) -> Kernel {
+    // Chain-specific behaviour is selected at runtime through global metadata,
+    // so no kernel feature is currently enabled.
+    let active_features = HashSet::new();
 
-    let parsed_files = files
+    let (file_names, parsed_files): (Vec<_>, Vec<_>) = files
         .iter()
-        .map(|f| parse(f, &active_features))
-        .collect_vec();
-    assemble(parsed_files, evm_constants(), true)
+        .map(|&(name, contents)| (name.to_string(), parse(contents, &active_features)))
+        .unzip();
+    assemble(parsed_files, file_names, evm_constants(), true)
 }
 
 pub(crate) fn combined_kernel() -> Kernel {
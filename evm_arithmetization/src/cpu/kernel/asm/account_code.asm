@@ -138,3 +138,142 @@ load_code_padded_ctd:
     MSTORE_GENERAL
     // stack: retdest, code_size
     JUMP
+
+// Identical to load_code_padded, but if the code at `address` is an EIP-7702 delegation designator,
+// the code of the delegate is loaded instead, and the delegate is added to the accessed addresses.
+// Delegation designators pointing to another designator are not followed further.
+// Pre stack: address, ctx, retdest
+// Post stack: code_size
+global load_delegated_code_padded:
+    %stack (address, ctx, retdest) -> (address, ctx, load_delegated_code_padded_ctd, ctx, retdest)
+    %jump(load_code_padded)
+
+load_delegated_code_padded_ctd:
+    // stack: code_size, ctx, retdest
+    // Only load the code of the delegate if the code we just loaded has both the length and the
+    // prefix of a delegation designator.
+    DUP2 DUP2
+    // stack: code_size, ctx, code_size, ctx, retdest
+    %is_delegation_designator
+    // stack: is_designator, code_size, ctx, retdest
+    ISZERO %jumpi(load_delegated_code_padded_no_delegate)
+    // stack: code_size, ctx, retdest
+    POP DUP1 %read_delegate
+    // stack: delegate, ctx, retdest
+    DUP1 %insert_accessed_addresses_no_return
+    // stack: delegate, ctx, retdest
+    %jump(load_code_padded)
+
+load_delegated_code_padded_no_delegate:
+    // stack: code_size, ctx, retdest
+    %stack (code_size, ctx, retdest) -> (retdest, code_size)
+    JUMP
+
+// Returns the address an account delegates its code to, as per EIP-7702, or 0 if its code
+// is not a delegation designator.
+// Pre stack: address, retdest
+// Post stack: delegate
+global get_delegate:
+    // stack: address, retdest
+    DUP1 %extcodehash
+    // stack: codehash, address, retdest
+    DUP1 ISZERO SWAP1 %eq_const(@EMPTY_STRING_HASH) OR
+    // stack: has_no_code, address, retdest
+    %jumpi(get_delegate_no_code)
+    // stack: address, retdest
+    %next_context_id
+    %stack (ctx, address, retdest) -> (address, ctx, get_delegate_ctd, ctx, retdest)
+    %jump(load_code)
+get_delegate_ctd:
+    // stack: code_size, ctx, retdest
+    DUP2 SWAP1
+    // stack: code_size, ctx, ctx, retdest
+    %delegate_from_code
+    // stack: delegate, ctx, retdest
+    SWAP1 %prune_context
+    // stack: delegate, retdest
+    SWAP1 JUMP
+get_delegate_no_code:
+    %stack (address, retdest) -> (retdest, 0)
+    JUMP
+
+%macro get_delegate
+    %stack (address) -> (address, %%after)
+    %jump(get_delegate)
+%%after:
+%endmacro
+
+// Reads the delegate from a delegation designator `0xef0100 || delegate` loaded at offset 0 of
+// the code segment of context `ctx`, or returns 0 if the loaded code is not a delegation designator.
+// Pre stack: code_size, ctx
+// Post stack: delegate
+%macro delegate_from_code
+    // stack: code_size, ctx
+    DUP2 SWAP1
+    // stack: code_size, ctx, ctx
+    %is_delegation_designator
+    // stack: is_designator, ctx
+    ISZERO %jumpi(%%not_delegated)
+    // stack: ctx
+    %read_delegate
+    // stack: delegate
+    %jump(%%after)
+%%not_delegated:
+    // stack: ctx
+    POP PUSH 0
+%%after:
+    // stack: delegate
+%endmacro
+
+// Returns whether the code of size `code_size` loaded at offset 0 of the code segment of context
+// `ctx` is a delegation designator, i.e. whether it is exactly @DELEGATION_DESIGNATOR_LEN bytes
+// long and starts with @DELEGATION_DESIGNATOR_PREFIX. The prefix is only read if the length matches.
+// Pre stack: code_size, ctx
+// Post stack: is_designator
+%macro is_delegation_designator
+    // stack: code_size, ctx
+    %eq_const(@DELEGATION_DESIGNATOR_LEN) ISZERO %jumpi(%%wrong_length)
+    // SEGMENT_CODE == 0, hence ctx == addr.
+    // stack: addr
+    PUSH 3 SWAP1
+    // stack: addr, 3
+    MLOAD_32BYTES
+    // stack: prefix
+    %eq_const(@DELEGATION_DESIGNATOR_PREFIX)
+    %jump(%%after)
+%%wrong_length:
+    // stack: ctx
+    POP PUSH 0
+%%after:
+    // stack: is_designator
+%endmacro
+
+// Reads the delegate from a delegation designator loaded at offset 0 of the code segment of
+// context `ctx`. The caller must have checked the designator with `%is_delegation_designator`.
+// Pre stack: ctx
+// Post stack: delegate
+%macro read_delegate
+    // SEGMENT_CODE == 0, hence ctx == addr.
+    // stack: addr
+    %add_const(3)
+    PUSH 20 SWAP1
+    // stack: addr', 20
+    MLOAD_32BYTES
+    // stack: delegate
+%endmacro
+
+// This should be called whenever an account's code is set to an EIP-7702 delegation designator.
+// It does nothing, but just provides a single hook where code can react to new delegation designators.
+// When called, the delegation designator should be stored at the start of @SEGMENT_KERNEL_GENERAL.
+// Pre stack: codehash, retdest
+// Post stack: codehash
+global observe_delegation_designator:
+    // stack codehash, retdest
+    SWAP1 JUMP
+
+%macro observe_delegation_designator
+    %stack (codehash) -> (codehash, %%after)
+    %jump(observe_delegation_designator)
+%%after:
+    // stack: codehash
+%endmacro
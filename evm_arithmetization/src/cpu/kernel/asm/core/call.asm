@@ -23,6 +23,7 @@ global sys_call:
     // stack: address, gas, kexit_info, value, args_offset, args_size, ret_offset, ret_size
     %u256_to_addr // Truncate to 160 bits
     DUP1 %insert_accessed_addresses
+    %charge_delegation_access
 
     %call_charge_gas(1, 1)
     %check_depth
@@ -76,6 +77,7 @@ global sys_callcode:
     // stack: address, gas, kexit_info, value, args_offset, args_size, ret_offset, ret_size
     %u256_to_addr // Truncate to 160 bits
     DUP1 %insert_accessed_addresses
+    %charge_delegation_access
 
     %call_charge_gas(1, 0)
     %check_depth
@@ -132,6 +134,7 @@ global sys_staticcall:
     // stack: address, gas, kexit_info, args_offset, args_size, ret_offset, ret_size
     %u256_to_addr // Truncate to 160 bits
     DUP1 %insert_accessed_addresses
+    %charge_delegation_access
 
     // Add a value of 0 to the stack. Slightly inefficient but that way we can reuse %call_charge_gas.
     %stack (cold_access, address, gas, kexit_info) -> (cold_access, address, gas, kexit_info, 0)
@@ -187,6 +190,7 @@ global sys_delegatecall:
     // stack: address, gas, kexit_info, args_offset, args_size, ret_offset, ret_size
     %u256_to_addr // Truncate to 160 bits
     DUP1 %insert_accessed_addresses
+    %charge_delegation_access
 
     // Add a value of 0 to the stack. Slightly inefficient but that way we can reuse %call_charge_gas.
     %stack (cold_access, address, gas, kexit_info) -> (cold_access, address, gas, kexit_info, 0)
@@ -373,12 +377,38 @@ call_too_deep:
 
 %macro set_new_ctx_code
     %stack (address, new_ctx) -> (address, new_ctx, %%after, new_ctx)
-    %jump(load_code_padded)
+    %jump(load_delegated_code_padded)
 %%after:
     %set_new_ctx_code_size
     // stack: new_ctx
 %endmacro
 
+// EIP-7702: If the called account delegates its code, charges the cost of accessing
+// the delegate and adds it to the accessed addresses.
+// The code of the called account only counts as a delegation if it has both the length and
+// the prefix of a delegation designator, see `get_delegate`.
+%macro charge_delegation_access
+    // stack: cold_access, address, gas, kexit_info
+    DUP2 %get_delegate
+    // stack: delegate, cold_access, address, gas, kexit_info
+    DUP1 ISZERO %jumpi(%%no_delegate)
+    %insert_accessed_addresses
+    // stack: delegate_cold_access, cold_access, address, gas, kexit_info
+    PUSH @GAS_COLDACCOUNTACCESS_MINUS_WARMACCESS
+    MUL
+    PUSH @GAS_WARMACCESS
+    ADD
+    %stack (cost, cold_access, address, gas, kexit_info) -> (cost, kexit_info, cold_access, address, gas)
+    %charge_gas
+    %stack (kexit_info, cold_access, address, gas) -> (cold_access, address, gas, kexit_info)
+    %jump(%%after)
+%%no_delegate:
+    // stack: 0, cold_access, address, gas, kexit_info
+    POP
+%%after:
+    // stack: cold_access, address, gas, kexit_info
+%endmacro
+
 %macro enter_new_ctx
     // stack: new_ctx
     // Switch to the new context and go to usermode with PC=0.
@@ -71,6 +71,11 @@ count_zeros_finish:
     %mload_global_metadata(@GLOBAL_METADATA_ACCESS_LIST_DATA_COST)
     ADD
 
+    // EIP-7702: Each authorization tuple is charged as if it created a new account.
+    %mload_global_metadata(@GLOBAL_METADATA_AUTHORIZATION_LIST_LEN)
+    %mul_const(@GAS_PER_EMPTY_ACCOUNT)
+    ADD
+
     SWAP1
     JUMP
 
@@ -0,0 +1,441 @@
+// EIP-2537 precompiles for operations on the BLS12-381 curve.
+//
+// Each precompile checks the input length and charges gas, then decodes and
+// validates its inputs, faulting on any invalid encoding or point. The curve
+// arithmetic is implemented in `curve/bls381`, and the results are written to
+// the parent's return data, with each Fp element encoded as 64 big-endian
+// bytes.
+// Unlike most precompiles, they are not active on every chain: see
+// `%is_bls12_381_precompile`.
+
+// Returns whether `addr` is one of the BLS12-381 precompiles and the current chain enables them.
+%macro is_bls12_381_precompile
+    // stack: addr
+    DUP1 %ge_const(@BLS12_G1ADD) SWAP1 %le_const(@BLS12_MAP_FP2_TO_G2)
+    MUL // Cheaper than AND
+    %mload_global_metadata(@GLOBAL_METADATA_BLS12_381_ENABLED)
+    MUL // Cheaper than AND
+%endmacro
+
+%macro bls12_381_precompile_prelude
+    // stack: address, retdest, new_ctx, (old stack)
+    %pop2
+    // stack: new_ctx, (old stack)
+    %set_new_ctx_parent_pc(after_precompile)
+    // stack: new_ctx, (old stack)
+    DUP1
+    SET_CONTEXT
+    %checkpoint // Checkpoint
+    %increment_call_depth
+    // stack: (empty)
+    PUSH @IS_KERNEL // true
+    // stack: kexit_info
+%endmacro
+
+// Faults unless the call data is exactly `len` bytes long.
+%macro bls12_381_check_calldata_size(len)
+    // stack: kexit_info
+    %calldatasize
+    %eq_const($len) ISZERO %jumpi(fault_exception)
+    // stack: kexit_info
+%endmacro
+
+// Faults unless the call data consists of a nonzero number of `len`-byte chunks.
+%macro bls12_381_calldata_chunks(len)
+    // stack: kexit_info
+    PUSH $len %calldatasize DUP2 DUP2
+    // stack: calldata_size, len, calldata_size, len, kexit_info
+    MOD %jumpi(fault_exception) // calldata_size should be a multiple of len
+    DIV
+    // stack: k, kexit_info
+    DUP1 ISZERO %jumpi(fault_exception) // the input cannot be empty
+    // stack: k, kexit_info
+%endmacro
+
+// Computes the gas cost of a multi-scalar multiplication over `k` pairs,
+// i.e. `k * mul_gas * discount(k) / 1000`.
+%macro bls12_381_msm_gas(discounts, mul_gas)
+    // stack: k
+    DUP1 %min_const(128) %decrement
+    // stack: min(k, 128) - 1, k
+    %mul_const(2)
+    PUSH $discounts ADD
+    // stack: discount_addr, k
+    %stack (addr) -> (addr, 2)
+    MLOAD_32BYTES
+    // stack: discount, k
+    MUL
+    %mul_const($mul_gas)
+    %div_const(1000)
+    // stack: gas
+%endmacro
+
+// Loads the Fp element encoded at the given offset of the call data, faulting
+// unless it is smaller than p. This also rejects a nonzero top 16 bytes.
+%macro bls12_381_load_fp
+    // stack: offset
+    %stack (offset) -> (@SEGMENT_CALLDATA, offset, 32, offset)
+    GET_CONTEXT
+    %build_address
+    MLOAD_32BYTES
+    // stack: x1, offset
+    SWAP1 %add_const(32)
+    %stack (offset) -> (@SEGMENT_CALLDATA, offset, 32)
+    GET_CONTEXT
+    %build_address
+    MLOAD_32BYTES
+    // stack: x0, x1
+    %is_canonical_fp381
+    ISZERO %jumpi(fault_exception)
+    // stack: x
+%endmacro
+
+%macro bls12_381_load_fp2
+    // stack: offset
+    DUP1 %add_const(64) %bls12_381_load_fp
+    DUP3 %bls12_381_load_fp
+    // stack: x_re, x_im, offset
+    %stack (x: 4, offset) -> (x)
+%endmacro
+
+// Loads the G1 point encoded at the given offset of the call data, faulting
+// unless it is on the curve or the point at infinity.
+%macro bls12_381_load_g1
+    // stack: offset
+    DUP1 %add_const(64) %bls12_381_load_fp
+    DUP3 %bls12_381_load_fp
+    // stack: x, y, offset
+    %bls381_g1_is_on_curve
+    ISZERO %jumpi(fault_exception)
+    %stack (p: 4, offset) -> (p)
+    // stack: p
+%endmacro
+
+// Stores the G2 point encoded at the given offset of the call data at addr,
+// faulting unless it is on the curve or the point at infinity.
+%macro bls12_381_load_g2
+    // stack: offset, addr
+    DUP1 %bls12_381_load_fp2
+    DUP6 %store_fp381_2
+    %add_const(128) %bls12_381_load_fp2
+    DUP5 %add_const(4) %store_fp381_2
+    // stack: addr
+    %bls381_g2_is_on_curve
+    ISZERO %jumpi(fault_exception)
+    // stack: (empty)
+%endmacro
+
+// Sets the size of the parent's return data to `len` bytes, and returns its
+// address.
+%macro bls12_381_returndata(len)
+    %mstore_parent_context_metadata(@CTX_METADATA_RETURNDATA_SIZE, $len)
+    PUSH @SEGMENT_RETURNDATA
+    %mload_context_metadata(@CTX_METADATA_PARENT_CONTEXT)
+    %build_address_no_offset
+    // stack: addr
+%endmacro
+
+// Writes the Fp element x as 64 big-endian bytes at addr.
+%macro bls12_381_store_fp
+    // stack: addr, x
+    %stack (addr, x0, x1) -> (addr, x1, x0)
+    MSTORE_32BYTES_32
+    MSTORE_32BYTES_32
+    // stack: addr + 64
+%endmacro
+
+global precompile_bls12_g1add:
+    %bls12_381_precompile_prelude
+global bls12_g1add:
+    // stack: kexit_info
+    %bls12_381_check_calldata_size(256)
+    %charge_gas_const(@BLS12_G1ADD_GAS)
+    PUSH 128 %bls12_381_load_g1
+    PUSH 0 %bls12_381_load_g1
+    // stack: a, b, kexit_info
+    %bls381_g1_add
+    // stack: a + b, kexit_info
+    %jump(bls12_381_return_g1)
+
+global precompile_bls12_g1msm:
+    %bls12_381_precompile_prelude
+global bls12_g1msm:
+    // stack: kexit_info
+    %bls12_381_calldata_chunks(160)
+    // stack: k, kexit_info
+    DUP1 %bls12_381_msm_gas(bls12_g1msm_discounts, @BLS12_G1MSM_MUL_GAS)
+    // stack: gas, k, kexit_info
+    %stack (gas, k, kexit_info) -> (gas, kexit_info, k)
+    %charge_gas
+    SWAP1 %mul_const(160)
+    // stack: offset, kexit_info
+    PUSH 0 PUSH 0 PUSH 0 PUSH 0
+bls12_g1msm_loop:
+    // stack: acc, offset, kexit_info
+    DUP5 ISZERO %jumpi(bls12_g1msm_end)
+    %stack (acc: 4, offset) -> (offset, acc)
+    %sub_const(160)
+    // stack: offset, acc, kexit_info
+    DUP1 %add_const(128)
+    %stack (scalar_offset) -> (@SEGMENT_CALLDATA, scalar_offset, 32)
+    GET_CONTEXT
+    %build_address
+    MLOAD_32BYTES
+    // stack: s, offset, acc, kexit_info
+    DUP2 %bls12_381_load_g1
+    // stack: p, s, offset, acc, kexit_info
+    PUSH bls12_g1msm_checked DUP5 DUP5 DUP5 DUP5
+    %jump(bls381_g1_is_in_subgroup)
+bls12_g1msm_checked:
+    // stack: is_in_subgroup, p, s, offset, acc, kexit_info
+    ISZERO %jumpi(fault_exception)
+    %bls381_g1_mul
+    // stack: s * p, offset, acc, kexit_info
+    %stack (q: 4, offset, acc: 4) -> (q, acc, offset)
+    %bls381_g1_add
+    // stack: acc, offset, kexit_info
+    %jump(bls12_g1msm_loop)
+bls12_g1msm_end:
+    // stack: acc, 0, kexit_info
+    %stack (acc: 4, offset) -> (acc)
+    %jump(bls12_381_return_g1)
+
+global precompile_bls12_g2add:
+    %bls12_381_precompile_prelude
+global bls12_g2add:
+    // stack: kexit_info
+    %bls12_381_check_calldata_size(512)
+    %charge_gas_const(@BLS12_G2ADD_GAS)
+    %bls381_address(64) PUSH 0 %bls12_381_load_g2
+    %bls381_address(72) PUSH 256 %bls12_381_load_g2
+    PUSH bls12_g2add_done %bls381_address(64) %bls381_address(72) DUP2
+    // stack: a, b, a, bls12_g2add_done, kexit_info
+    %jump(bls381_g2_add)
+bls12_g2add_done:
+    // stack: lambda, kexit_info
+    %pop4
+    %bls381_address(64)
+    %jump(bls12_381_return_g2)
+
+global precompile_bls12_g2msm:
+    %bls12_381_precompile_prelude
+global bls12_g2msm:
+    // stack: kexit_info
+    %bls12_381_calldata_chunks(288)
+    // stack: k, kexit_info
+    DUP1 %bls12_381_msm_gas(bls12_g2msm_discounts, @BLS12_G2MSM_MUL_GAS)
+    // stack: gas, k, kexit_info
+    %stack (gas, k, kexit_info) -> (gas, kexit_info, k)
+    %charge_gas
+    SWAP1 %mul_const(288)
+    // stack: offset, kexit_info
+    // The sum is accumulated at offset 80 of the scratch segment, and each
+    // point is decoded at offset 64.
+    %bls381_address(80) %bls381_g2_set_infinity
+bls12_g2msm_loop:
+    // stack: offset, kexit_info
+    DUP1 ISZERO %jumpi(bls12_g2msm_end)
+    %sub_const(288)
+    %bls381_address(64) DUP2 %bls12_381_load_g2
+    PUSH bls12_g2msm_checked %bls381_address(64)
+    %jump(bls381_g2_is_in_subgroup)
+bls12_g2msm_checked:
+    // stack: is_in_subgroup, offset, kexit_info
+    ISZERO %jumpi(fault_exception)
+    DUP1 %add_const(256)
+    %stack (scalar_offset) -> (@SEGMENT_CALLDATA, scalar_offset, 32)
+    GET_CONTEXT
+    %build_address
+    MLOAD_32BYTES
+    // stack: s, offset, kexit_info
+    %stack (s) -> (s, bls12_g2msm_multiplied)
+    %bls381_address(64)
+    %stack (p, s) -> (p, s, p)
+    // stack: p, s, p, bls12_g2msm_multiplied, offset, kexit_info
+    %jump(bls381_g2_mul)
+bls12_g2msm_multiplied:
+    // stack: offset, kexit_info
+    PUSH bls12_g2msm_added %bls381_address(80) %bls381_address(64) DUP2
+    // stack: acc, s * p, acc, bls12_g2msm_added, offset, kexit_info
+    %jump(bls381_g2_add)
+bls12_g2msm_added:
+    // stack: lambda, offset, kexit_info
+    %pop4
+    %jump(bls12_g2msm_loop)
+bls12_g2msm_end:
+    // stack: 0, kexit_info
+    POP
+    %bls381_address(80)
+    %jump(bls12_381_return_g2)
+
+global precompile_bls12_pairing:
+    %bls12_381_precompile_prelude
+global bls12_pairing:
+    // stack: kexit_info
+    %bls12_381_calldata_chunks(384)
+    // stack: k, kexit_info
+    DUP1 %mul_const(@BLS12_PAIRING_DYNAMIC_GAS) %add_const(@BLS12_PAIRING_STATIC_GAS)
+    // stack: gas, k, kexit_info
+    %stack (gas, k, kexit_info) -> (gas, kexit_info, k)
+    %charge_gas
+    SWAP1 %mul_const(384)
+    // stack: offset, kexit_info
+    // The Miller loops are accumulated at offset 248 of the scratch segment,
+    // and each pair is decoded with P at offset 104 and Q at offset 64.
+    %bls381_address(248) %bls381_fp12_set_one
+bls12_pairing_loop:
+    // stack: offset, kexit_info
+    DUP1 ISZERO %jumpi(bls12_pairing_end)
+    %sub_const(384)
+    DUP1 %bls12_381_load_g1
+    // stack: p, offset, kexit_info
+    PUSH bls12_pairing_checked_p DUP5 DUP5 DUP5 DUP5
+    %jump(bls381_g1_is_in_subgroup)
+bls12_pairing_checked_p:
+    // stack: is_in_subgroup, p, offset, kexit_info
+    ISZERO %jumpi(fault_exception)
+    %bls381_address(104) %store_fp381_2
+    // stack: offset, kexit_info
+    %bls381_address(64) DUP2 %add_const(128) %bls12_381_load_g2
+    PUSH bls12_pairing_checked_q %bls381_address(64)
+    %jump(bls381_g2_is_in_subgroup)
+bls12_pairing_checked_q:
+    // stack: is_in_subgroup, offset, kexit_info
+    ISZERO %jumpi(fault_exception)
+    // Pairs with a point at infinity do not contribute to the product.
+    %bls381_address(104) %load_fp381_2 %is_zero_fp381_2
+    %bls381_address(64) %bls381_g2_is_infinity
+    OR %jumpi(bls12_pairing_loop)
+    PUSH bls12_pairing_loop
+    %jump(bls381_miller_loop)
+bls12_pairing_end:
+    // stack: 0, kexit_info
+    POP
+    PUSH bls12_pairing_checked
+    %jump(bls381_final_exp_is_one)
+bls12_pairing_checked:
+    // stack: is_one, kexit_info
+    %bls12_381_returndata(32)
+    MSTORE_32BYTES_32
+    // stack: addr, kexit_info
+    %jump(pop_and_return_success)
+
+global precompile_bls12_map_fp_to_g1:
+    %bls12_381_precompile_prelude
+global bls12_map_fp_to_g1:
+    // stack: kexit_info
+    %bls12_381_check_calldata_size(64)
+    %charge_gas_const(@BLS12_MAP_FP_TO_G1_GAS)
+    PUSH bls12_381_return_g1
+    PUSH 0 %bls12_381_load_fp
+    // stack: u, bls12_381_return_g1, kexit_info
+    %jump(bls381_map_fp_to_g1)
+
+global precompile_bls12_map_fp2_to_g2:
+    %bls12_381_precompile_prelude
+global bls12_map_fp2_to_g2:
+    // stack: kexit_info
+    %bls12_381_check_calldata_size(128)
+    %charge_gas_const(@BLS12_MAP_FP2_TO_G2_GAS)
+    PUSH bls12_map_fp2_to_g2_done %bls381_address(64)
+    PUSH 0 %bls12_381_load_fp2
+    // stack: u, out, bls12_map_fp2_to_g2_done, kexit_info
+    %jump(bls381_map_fp2_to_g2)
+bls12_map_fp2_to_g2_done:
+    // stack: kexit_info
+    %bls381_address(64)
+    %jump(bls12_381_return_g2)
+
+bls12_381_return_g1:
+    // stack: p, kexit_info
+    %bls12_381_returndata(128)
+    %bls12_381_store_fp
+    %bls12_381_store_fp
+    // stack: addr, kexit_info
+    %jump(pop_and_return_success)
+
+bls12_381_return_g2:
+    // stack: p, kexit_info
+    %bls12_381_returndata(256)
+    // stack: addr, p, kexit_info
+    %rep 4
+        DUP2 %load_fp381
+        %stack (x: 2, addr) -> (addr, x)
+        %bls12_381_store_fp
+        SWAP1 %add_const(2) SWAP1
+    %endrep
+    // stack: addr, p, kexit_info
+    POP
+    %jump(pop_and_return_success)
+
+// Discounts applied to the G1 MSM gas cost, indexed by `min(k, 128) - 1`,
+// stored as big-endian 16-bit values.
+bls12_g1msm_discounts:
+    BYTES 3, 232, 3, 181, 3, 80, 3, 29 // 1000, 949, 848, 797
+    BYTES 2, 252, 2, 238, 2, 226, 2, 216 // 764, 750, 738, 728
+    BYTES 2, 207, 2, 200, 2, 193, 2, 186 // 719, 712, 705, 698
+    BYTES 2, 180, 2, 175, 2, 170, 2, 165 // 692, 687, 682, 677
+    BYTES 2, 161, 2, 157, 2, 153, 2, 149 // 673, 669, 665, 661
+    BYTES 2, 146, 2, 142, 2, 139, 2, 136 // 658, 654, 651, 648
+    BYTES 2, 133, 2, 130, 2, 128, 2, 125 // 645, 642, 640, 637
+    BYTES 2, 123, 2, 120, 2, 118, 2, 115 // 635, 632, 630, 627
+    BYTES 2, 113, 2, 111, 2, 109, 2, 107 // 625, 623, 621, 619
+    BYTES 2, 105, 2, 103, 2, 101, 2, 99 // 617, 615, 613, 611
+    BYTES 2, 97, 2, 96, 2, 94, 2, 92 // 609, 608, 606, 604
+    BYTES 2, 91, 2, 89, 2, 87, 2, 86 // 603, 601, 599, 598
+    BYTES 2, 84, 2, 83, 2, 81, 2, 80 // 596, 595, 593, 592
+    BYTES 2, 79, 2, 77, 2, 76, 2, 74 // 591, 589, 588, 586
+    BYTES 2, 73, 2, 72, 2, 70, 2, 69 // 585, 584, 582, 581
+    BYTES 2, 68, 2, 67, 2, 65, 2, 64 // 580, 579, 577, 576
+    BYTES 2, 63, 2, 62, 2, 61, 2, 60 // 575, 574, 573, 572
+    BYTES 2, 58, 2, 57, 2, 56, 2, 55 // 570, 569, 568, 567
+    BYTES 2, 54, 2, 53, 2, 52, 2, 51 // 566, 565, 564, 563
+    BYTES 2, 50, 2, 49, 2, 48, 2, 47 // 562, 561, 560, 559
+    BYTES 2, 46, 2, 45, 2, 44, 2, 43 // 558, 557, 556, 555
+    BYTES 2, 42, 2, 41, 2, 40, 2, 39 // 554, 553, 552, 551
+    BYTES 2, 38, 2, 37, 2, 36, 2, 35 // 550, 549, 548, 547
+    BYTES 2, 35, 2, 34, 2, 33, 2, 32 // 547, 546, 545, 544
+    BYTES 2, 31, 2, 30, 2, 29, 2, 28 // 543, 542, 541, 540
+    BYTES 2, 28, 2, 27, 2, 26, 2, 25 // 540, 539, 538, 537
+    BYTES 2, 24, 2, 24, 2, 23, 2, 22 // 536, 536, 535, 534
+    BYTES 2, 21, 2, 20, 2, 20, 2, 19 // 533, 532, 532, 531
+    BYTES 2, 18, 2, 17, 2, 16, 2, 16 // 530, 529, 528, 528
+    BYTES 2, 15, 2, 14, 2, 13, 2, 13 // 527, 526, 525, 525
+    BYTES 2, 12, 2, 11, 2, 10, 2, 10 // 524, 523, 522, 522
+    BYTES 2, 9, 2, 8, 2, 8, 2, 7 // 521, 520, 520, 519
+
+// Discounts applied to the G2 MSM gas cost, indexed by `min(k, 128) - 1`,
+// stored as big-endian 16-bit values.
+bls12_g2msm_discounts:
+    BYTES 3, 232, 3, 232, 3, 155, 3, 116 // 1000, 1000, 923, 884
+    BYTES 3, 87, 3, 64, 3, 44, 3, 28 // 855, 832, 812, 796
+    BYTES 3, 14, 3, 2, 2, 247, 2, 237 // 782, 770, 759, 749
+    BYTES 2, 228, 2, 220, 2, 212, 2, 205 // 740, 732, 724, 717
+    BYTES 2, 199, 2, 192, 2, 187, 2, 181 // 711, 704, 699, 693
+    BYTES 2, 176, 2, 171, 2, 167, 2, 162 // 688, 683, 679, 674
+    BYTES 2, 158, 2, 154, 2, 151, 2, 147 // 670, 666, 663, 659
+    BYTES 2, 143, 2, 140, 2, 137, 2, 134 // 655, 652, 649, 646
+    BYTES 2, 131, 2, 128, 2, 125, 2, 122 // 643, 640, 637, 634
+    BYTES 2, 120, 2, 117, 2, 115, 2, 112 // 632, 629, 627, 624
+    BYTES 2, 110, 2, 108, 2, 106, 2, 103 // 622, 620, 618, 615
+    BYTES 2, 101, 2, 99, 2, 97, 2, 95 // 613, 611, 609, 607
+    BYTES 2, 94, 2, 92, 2, 90, 2, 88 // 606, 604, 602, 600
+    BYTES 2, 86, 2, 85, 2, 83, 2, 81 // 598, 597, 595, 593
+    BYTES 2, 80, 2, 78, 2, 77, 2, 75 // 592, 590, 589, 587
+    BYTES 2, 74, 2, 72, 2, 71, 2, 70 // 586, 584, 583, 582
+    BYTES 2, 68, 2, 67, 2, 66, 2, 64 // 580, 579, 578, 576
+    BYTES 2, 63, 2, 62, 2, 61, 2, 59 // 575, 574, 573, 571
+    BYTES 2, 58, 2, 57, 2, 56, 2, 55 // 570, 569, 568, 567
+    BYTES 2, 54, 2, 53, 2, 51, 2, 50 // 566, 565, 563, 562
+    BYTES 2, 49, 2, 48, 2, 47, 2, 46 // 561, 560, 559, 558
+    BYTES 2, 45, 2, 44, 2, 43, 2, 42 // 557, 556, 555, 554
+    BYTES 2, 41, 2, 40, 2, 40, 2, 39 // 553, 552, 552, 551
+    BYTES 2, 38, 2, 37, 2, 36, 2, 35 // 550, 549, 548, 547
+    BYTES 2, 34, 2, 33, 2, 33, 2, 32 // 546, 545, 545, 544
+    BYTES 2, 31, 2, 30, 2, 29, 2, 29 // 543, 542, 541, 541
+    BYTES 2, 28, 2, 27, 2, 26, 2, 25 // 540, 539, 538, 537
+    BYTES 2, 25, 2, 24, 2, 23, 2, 23 // 537, 536, 535, 535
+    BYTES 2, 22, 2, 21, 2, 20, 2, 20 // 534, 533, 532, 532
+    BYTES 2, 19, 2, 18, 2, 18, 2, 17 // 531, 530, 530, 529
+    BYTES 2, 16, 2, 16, 2, 15, 2, 14 // 528, 528, 527, 526
+    BYTES 2, 14, 2, 13, 2, 12, 2, 12 // 526, 525, 524, 524
@@ -10,19 +10,36 @@
 
 global handle_precompiles:
     // stack: address, retdest, new_ctx, (old stack)
-    DUP1 %eq_const(@ECREC)    %jumpi(precompile_ecrec)
-    DUP1 %eq_const(@SHA256)   %jumpi(precompile_sha256)
-    DUP1 %eq_const(@RIP160)   %jumpi(precompile_rip160)
-    DUP1 %eq_const(@ID)       %jumpi(precompile_id)
-    DUP1 %eq_const(@EXPMOD)   %jumpi(precompile_expmod)
-    DUP1 %eq_const(@BN_ADD)   %jumpi(precompile_bn_add)
-    DUP1 %eq_const(@BN_MUL)   %jumpi(precompile_bn_mul)
-    DUP1 %eq_const(@SNARKV)   %jumpi(precompile_snarkv)
-    DUP1 %eq_const(@BLAKE2_F) %jumpi(precompile_blake2_f)
-    %eq_const(@KZG_PEVAL)     %jumpi(precompile_kzg_peval)
+    DUP1 %eq_const(@ECREC)               %jumpi(precompile_ecrec)
+    DUP1 %eq_const(@SHA256)              %jumpi(precompile_sha256)
+    DUP1 %eq_const(@RIP160)              %jumpi(precompile_rip160)
+    DUP1 %eq_const(@ID)                  %jumpi(precompile_id)
+    DUP1 %eq_const(@EXPMOD)              %jumpi(precompile_expmod)
+    DUP1 %eq_const(@BN_ADD)              %jumpi(precompile_bn_add)
+    DUP1 %eq_const(@BN_MUL)              %jumpi(precompile_bn_mul)
+    DUP1 %eq_const(@SNARKV)              %jumpi(precompile_snarkv)
+    DUP1 %eq_const(@BLAKE2_F)            %jumpi(precompile_blake2_f)
+    %mload_global_metadata(@GLOBAL_METADATA_BLS12_381_ENABLED)
+    %jumpi(handle_bls12_381_precompiles)
+handle_remaining_precompiles:
+    // stack: address, retdest, new_ctx, (old stack)
+    DUP1 %is_p256_verify                 %jumpi(precompile_p256_verify)
+    %eq_const(@KZG_PEVAL)                %jumpi(precompile_kzg_peval)
     // stack: retdest
     JUMP
 
+// The BLS12-381 precompiles are only dispatched to on chains enabling them.
+handle_bls12_381_precompiles:
+    // stack: address, retdest, new_ctx, (old stack)
+    DUP1 %eq_const(@BLS12_G1ADD)         %jumpi(precompile_bls12_g1add)
+    DUP1 %eq_const(@BLS12_G1MSM)         %jumpi(precompile_bls12_g1msm)
+    DUP1 %eq_const(@BLS12_G2ADD)         %jumpi(precompile_bls12_g2add)
+    DUP1 %eq_const(@BLS12_G2MSM)         %jumpi(precompile_bls12_g2msm)
+    DUP1 %eq_const(@BLS12_PAIRING)       %jumpi(precompile_bls12_pairing)
+    DUP1 %eq_const(@BLS12_MAP_FP_TO_G1)  %jumpi(precompile_bls12_map_fp_to_g1)
+    DUP1 %eq_const(@BLS12_MAP_FP2_TO_G2) %jumpi(precompile_bls12_map_fp2_to_g2)
+    %jump(handle_remaining_precompiles)
+
 global pop_and_return_success:
     // stack: _unused, kexit_info
     POP
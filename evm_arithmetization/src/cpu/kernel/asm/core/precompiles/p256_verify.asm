@@ -0,0 +1,84 @@
+// RIP-7212 precompile, verifying ECDSA signatures over the P-256 (secp256r1) curve.
+// Unlike the other precompiles, it is not active on every chain: see `%is_p256_verify`.
+
+// Returns whether `addr` is the P256VERIFY precompile and the current chain enables it.
+%macro is_p256_verify
+    // stack: addr
+    %eq_const(@P256_VERIFY)
+    %mload_global_metadata(@GLOBAL_METADATA_P256_VERIFY_ENABLED)
+    MUL // Cheaper than AND
+%endmacro
+
+global precompile_p256_verify:
+    // stack: address, retdest, new_ctx, (old stack)
+    %pop2
+    // stack: new_ctx, (old stack)
+    %set_new_ctx_parent_pc(after_precompile)
+    // stack: new_ctx, (old stack)
+    DUP1
+    SET_CONTEXT
+    %checkpoint // Checkpoint
+    %increment_call_depth
+    // stack: (empty)
+    PUSH @IS_KERNEL // true
+global p256_verify:
+    // stack: kexit_info
+    %charge_gas_const(@P256_VERIFY_GAS)
+
+    // The input must be exactly 160 bytes long.
+    %calldatasize %eq_const(160)
+    // stack: calldatasize == 160, kexit_info
+    DUP1 ISZERO %jumpi(p256_verify_bad_input)
+    POP
+
+    // Load hash, r, s, Qx, Qy from the call data using `MLOAD_32BYTES`.
+    PUSH p256_verify_return
+    // stack: p256_verify_return, kexit_info
+    %stack () -> (@SEGMENT_CALLDATA, 128, 32)
+    GET_CONTEXT
+    // stack: ctx, @SEGMENT_CALLDATA, 128, 32, p256_verify_return, kexit_info
+    %build_address
+    MLOAD_32BYTES
+    // stack: Qy, p256_verify_return, kexit_info
+    %stack () -> (@SEGMENT_CALLDATA, 96, 32)
+    GET_CONTEXT
+    // stack: ctx, @SEGMENT_CALLDATA, 96, 32, Qy, p256_verify_return, kexit_info
+    %build_address
+    MLOAD_32BYTES
+    // stack: Qx, Qy, p256_verify_return, kexit_info
+    %stack () -> (@SEGMENT_CALLDATA, 64, 32)
+    GET_CONTEXT
+    // stack: ctx, @SEGMENT_CALLDATA, 64, 32, Qx, Qy, p256_verify_return, kexit_info
+    %build_address
+    MLOAD_32BYTES
+    // stack: s, Qx, Qy, p256_verify_return, kexit_info
+    %stack () -> (@SEGMENT_CALLDATA, 32, 32)
+    GET_CONTEXT
+    // stack: ctx, @SEGMENT_CALLDATA, 32, 32, s, Qx, Qy, p256_verify_return, kexit_info
+    %build_address
+    MLOAD_32BYTES
+    // stack: r, s, Qx, Qy, p256_verify_return, kexit_info
+    %stack () -> (@SEGMENT_CALLDATA, 32)
+    GET_CONTEXT
+    // stack: ctx, @SEGMENT_CALLDATA, 32, r, s, Qx, Qy, p256_verify_return, kexit_info
+    %build_address_no_offset
+    MLOAD_32BYTES
+    // stack: hash, r, s, Qx, Qy, p256_verify_return, kexit_info
+    %jump(p256_ecdsa_verify)
+p256_verify_return:
+    // stack: is_valid, kexit_info
+    DUP1 ISZERO %jumpi(p256_verify_bad_input)
+
+    // Store 1 as a 32-byte word to the parent's return data using `MSTORE_32BYTES_32`.
+    %mstore_parent_context_metadata(@CTX_METADATA_RETURNDATA_SIZE, 32)
+    %mload_context_metadata(@CTX_METADATA_PARENT_CONTEXT)
+    %stack (parent_ctx, is_valid) -> (parent_ctx, @SEGMENT_RETURNDATA, is_valid)
+    %build_address_no_offset
+    MSTORE_32BYTES_32
+    %jump(pop_and_return_success)
+
+// On bad input or invalid signature, return empty return data but still return success.
+p256_verify_bad_input:
+    // stack: _unused, kexit_info
+    %mstore_parent_context_metadata(@CTX_METADATA_RETURNDATA_SIZE, 0)
+    %jump(pop_and_return_success)
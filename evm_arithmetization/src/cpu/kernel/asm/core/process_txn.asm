@@ -35,8 +35,10 @@ global process_normalized_txn:
     %assert_eq(invalid_txn_1)
     // stack: sender, retdest
 
-    // Assert sender has no code.
-    DUP1 %ext_code_empty %assert_nonzero(invalid_txn_1)
+    // Assert sender has no code, other than an EIP-7702 delegation designator.
+    DUP1 %ext_code_empty %jumpi(sender_code_checked)
+    DUP1 %get_delegate %assert_nonzero(invalid_txn_1)
+sender_code_checked:
     // stack: sender, retdest
 
     // Assert sender balance >= gas_limit * gas_price + value.
@@ -75,11 +77,8 @@ global buy_gas:
     // stack: deduct_eth_status, retdest
     %jumpi(panic)
     // stack: retdest
-    #[cfg(feature = cdk_erigon)]
-    {
-        %add_max_burnt_eth
-        // stack: retdest
-    }
+    %add_max_burnt_eth
+    // stack: retdest
 
 global increment_sender_nonce:
     %mload_txn_field(@TXN_FIELD_ORIGIN)
@@ -101,12 +100,31 @@ global warm_precompiles:
     PUSH @SNARKV %insert_accessed_addresses_no_return
     PUSH @BLAKE2_F %insert_accessed_addresses_no_return
     PUSH @KZG_PEVAL %insert_accessed_addresses_no_return
+    %mload_global_metadata(@GLOBAL_METADATA_BLS12_381_ENABLED)
+    ISZERO %jumpi(warm_p256_verify)
+    PUSH @BLS12_G1ADD %insert_accessed_addresses_no_return
+    PUSH @BLS12_G1MSM %insert_accessed_addresses_no_return
+    PUSH @BLS12_G2ADD %insert_accessed_addresses_no_return
+    PUSH @BLS12_G2MSM %insert_accessed_addresses_no_return
+    PUSH @BLS12_PAIRING %insert_accessed_addresses_no_return
+    PUSH @BLS12_MAP_FP_TO_G1 %insert_accessed_addresses_no_return
+    PUSH @BLS12_MAP_FP2_TO_G2 %insert_accessed_addresses_no_return
+warm_p256_verify:
+    %mload_global_metadata(@GLOBAL_METADATA_P256_VERIFY_ENABLED)
+    ISZERO %jumpi(warm_coinbase)
+    PUSH @P256_VERIFY %insert_accessed_addresses_no_return
 
 // EIP-3651
 global warm_coinbase:
     %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BENEFICIARY)
     %insert_accessed_addresses_no_return
 
+// EIP-7702
+global process_authorizations:
+    // stack: retdest
+    PUSH process_based_on_type
+    %jump(process_authorization_list)
+
 global process_based_on_type:
     %is_contract_creation
     %jumpi(process_contract_creation_txn)
@@ -246,7 +264,7 @@ global process_message_txn:
     // stack: code_empty, retdest
     %jumpi(process_message_txn_return)
 
-    // Otherwise, load to's code and execute it in a new context.
+    // Otherwise, load to's code (or the code of its EIP-7702 delegate) and execute it in a new context.
     // stack: retdest
     %create_context
     // stack: new_ctx, retdest
@@ -254,7 +272,7 @@ global process_message_txn:
     DUP2 // new_ctx
     %mload_txn_field(@TXN_FIELD_TO)
     // stack: address, new_ctx, process_message_txn_code_loaded, new_ctx, retdest
-    %jump(load_code_padded)
+    %jump(load_delegated_code_padded)
 
 global process_message_txn_insufficient_balance:
     // stack: retdest
@@ -368,36 +386,30 @@ process_message_txn_fail:
     %add_eth
     // stack: leftover_gas'
 
-    #[cfg(feature = cdk_erigon)]
-    {
-        %deduct_extra_burn_fees
-    }
+    %deduct_extra_burn_fees
 %endmacro
 
-#[cfg(feature = cdk_erigon)]
-{
-    // Deduct the extra burn fees from the burn target.
-    %macro deduct_extra_burn_fees
-        // stack: leftover_gas'
-        %mload_global_metadata(@GLOBAL_METADATA_BURN_ADDR) DUP1
-        %eq_const(@U256_MAX) %jumpi(%%deduct_extra_burn_fees_pop)
-        // stack: burn_target, leftover_gas'
-        DUP2
-        %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BASE_FEE)
-        MUL
-        // stack: refund_base_cost, burn_target, leftover_gas'
-        SWAP1
-        %deduct_eth
-        // stack: deduct_status, leftover_gas'
-        %jumpi(panic)
-        %jump(%%deduct_extra_burn_fees_end)
-    %%deduct_extra_burn_fees_pop:
-        // stack: burn_target, leftover_gas'
-        POP
-    %%deduct_extra_burn_fees_end:
-        // stack: leftover_gas'
-    %endmacro
-}
+// Deduct the extra burn fees from the burn target.
+%macro deduct_extra_burn_fees
+    // stack: leftover_gas'
+    %mload_global_metadata(@GLOBAL_METADATA_BURN_ADDR) DUP1
+    %eq_const(@U256_MAX) %jumpi(%%deduct_extra_burn_fees_pop)
+    // stack: burn_target, leftover_gas'
+    DUP2
+    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BASE_FEE)
+    MUL
+    // stack: refund_base_cost, burn_target, leftover_gas'
+    SWAP1
+    %deduct_eth
+    // stack: deduct_status, leftover_gas'
+    %jumpi(panic)
+    %jump(%%deduct_extra_burn_fees_end)
+%%deduct_extra_burn_fees_pop:
+    // stack: burn_target, leftover_gas'
+    POP
+%%deduct_extra_burn_fees_end:
+    // stack: leftover_gas'
+%endmacro
 
 // Sets @TXN_FIELD_MAX_FEE_PER_GAS and @TXN_FIELD_MAX_PRIORITY_FEE_PER_GAS.
 %macro compute_fees
@@ -430,29 +442,26 @@ process_message_txn_fail:
     // stack: gas_limit - intrinsic_gas
 %endmacro
 
-#[cfg(feature = cdk_erigon)]
-{
-    // Refund extra burn fees to the burn target.
-    %macro add_max_burnt_eth
-        // stack: (empty)
-        %mload_global_metadata(@GLOBAL_METADATA_BURN_ADDR)
-        // If there is no burn target, we skip the transfer.
-        DUP1 %eq_const(@U256_MAX) %jumpi(%%add_max_burnt_eth_pop)
-        // stack: burn_target
-        %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BASE_FEE)
-        %mload_txn_field(@TXN_FIELD_GAS_LIMIT)
-        MUL
-        // stack: max_burnt_cost, burn_target
-        SWAP1 
-        %add_eth
-        %jump(%%add_max_burnt_eth_end)
-    %%add_max_burnt_eth_pop:
-        // stack: burn_target
-        POP
-    %%add_max_burnt_eth_end:
-        // stack: (empty)
-    %endmacro
-}
+// Refund extra burn fees to the burn target.
+%macro add_max_burnt_eth
+    // stack: (empty)
+    %mload_global_metadata(@GLOBAL_METADATA_BURN_ADDR)
+    // If there is no burn target, we skip the transfer.
+    DUP1 %eq_const(@U256_MAX) %jumpi(%%add_max_burnt_eth_pop)
+    // stack: burn_target
+    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BASE_FEE)
+    %mload_txn_field(@TXN_FIELD_GAS_LIMIT)
+    MUL
+    // stack: max_burnt_cost, burn_target
+    SWAP1 
+    %add_eth
+    %jump(%%add_max_burnt_eth_end)
+%%add_max_burnt_eth_pop:
+    // stack: burn_target
+    POP
+%%add_max_burnt_eth_end:
+    // stack: (empty)
+%endmacro
     
 create_contract_account_fault:
     %revert_checkpoint
@@ -37,9 +37,14 @@
 
 %macro is_precompile
     // stack: addr
+    DUP1 %is_p256_verify SWAP1
+    // stack: addr, is_p256_verify
+    DUP1 %is_bls12_381_precompile SWAP1
+    // stack: addr, is_bls12_381_precompile, is_p256_verify
     DUP1 %ge_const(@ECREC) SWAP1 %le_const(@KZG_PEVAL)
-    // stack: addr>=1, addr<=10
+    // stack: addr>=1, addr<=10, is_bls12_381_precompile, is_p256_verify
     MUL // Cheaper than AND
+    ADD ADD // Cheaper than OR, all conditions being exclusive
 %endmacro
 
 // Returns 1 if the account is non-existent, 0 otherwise.
@@ -0,0 +1,649 @@
+// Arithmetic in the degree 12 extension of the BLS12-381 base field, built as
+// the tower Fp6 = Fp2[v] / (v^3 - (1 + i)) and Fp12 = Fp6[w] / (w^2 - v).
+//
+// Elements are kept in the BLS12-381 scratch segment and passed around by
+// address. An Fp6 element a0 + a1 * v + a2 * v^2 takes up twelve words, its
+// Fp2 coefficients in the layout of `store_fp381_2`, and an Fp12 element
+// a0 + a1 * w takes up 24 words, a0 then a1.
+
+// Scratch space for `bls381_fp12_mul`: the sums a0 + a1 and b0 + b1, followed
+// by the products (a0 + a1) * (b0 + b1), a0 * b0 and a1 * b1.
+%macro bls381_fp12_mul_scratch
+    %bls381_address(112)
+%endmacro
+
+// Scratch space for the product checked in `bls381_fp12_inv`.
+%macro bls381_fp12_inv_scratch
+    %bls381_address(176)
+%endmacro
+
+%macro bls381_fp12_set_zero
+    // stack: addr
+    %rep 24
+        DUP1 PUSH 0 MSTORE_GENERAL
+        %increment
+    %endrep
+    POP
+    // stack: (empty)
+%endmacro
+
+%macro bls381_fp12_set_one
+    // stack: addr
+    DUP1 %bls381_fp12_set_zero
+    PUSH 1 MSTORE_GENERAL
+    // stack: (empty)
+%endmacro
+
+%macro bls381_fp12_is_one
+    // stack: addr
+    DUP1 MLOAD_GENERAL %eq_const(1)
+    %rep 23
+        SWAP1 %increment SWAP1
+        DUP2 MLOAD_GENERAL ISZERO MUL
+    %endrep
+    // stack: is_one, addr
+    SWAP1 POP
+%endmacro
+
+// The following macros call the Fp12 routines on fixed offsets of the scratch
+// segment.
+
+%macro bls381_fp12_mul(a, b, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($b)
+    %bls381_address($a)
+    %jump(bls381_fp12_mul)
+%%after:
+%endmacro
+
+%macro bls381_fp12_sqr(a, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($a)
+    %jump(bls381_fp12_sqr)
+%%after:
+%endmacro
+
+%macro bls381_fp12_mul_by_line(a, l, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($l)
+    %bls381_address($a)
+    %jump(bls381_fp12_mul_by_line)
+%%after:
+%endmacro
+
+%macro bls381_fp12_conj(a, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($a)
+    %jump(bls381_fp12_conj)
+%%after:
+%endmacro
+
+%macro bls381_fp12_frob1(a, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($a)
+    %jump(bls381_fp12_frob1)
+%%after:
+%endmacro
+
+%macro bls381_fp12_frob2(a, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($a)
+    %jump(bls381_fp12_frob2)
+%%after:
+%endmacro
+
+%macro bls381_fp12_inv(a, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($a)
+    %jump(bls381_fp12_inv)
+%%after:
+%endmacro
+
+%macro bls381_fp12_exp_by_x(a, out)
+    PUSH %%after
+    %bls381_address($out)
+    %bls381_address($a)
+    %jump(bls381_fp12_exp_by_x)
+%%after:
+%endmacro
+
+%macro bls381_fp6_mul_term(i, j)
+    // stack: a, b
+    DUP1 %add_const($i) %load_fp381_2
+    DUP6 %add_const($j) %load_fp381_2
+    %mul_fp381_2
+    // stack: a_i * b_j, a, b
+%endmacro
+
+%macro bls381_fp6_mul_add_term(i, j)
+    // stack: x, a, b
+    DUP5 %add_const($i) %load_fp381_2
+    DUP10 %add_const($j) %load_fp381_2
+    %mul_fp381_2
+    %add_fp381_2
+    // stack: x + a_i * b_j, a, b
+%endmacro
+
+// Stores a * b at out, which must not overlap a or b.
+global bls381_fp6_mul:
+    // stack: a, b, out, retdest
+    %bls381_fp6_mul_term(4, 8)
+    %bls381_fp6_mul_add_term(8, 4)
+    %mul_fp381_2_by_xi
+    %bls381_fp6_mul_add_term(0, 0)
+    // stack: a0 * b0 + xi * (a1 * b2 + a2 * b1), a, b, out, retdest
+    DUP7 %store_fp381_2
+    %bls381_fp6_mul_term(8, 8)
+    %mul_fp381_2_by_xi
+    %bls381_fp6_mul_add_term(0, 4)
+    %bls381_fp6_mul_add_term(4, 0)
+    // stack: a0 * b1 + a1 * b0 + xi * a2 * b2, a, b, out, retdest
+    DUP7 %add_const(4) %store_fp381_2
+    %bls381_fp6_mul_term(0, 8)
+    %bls381_fp6_mul_add_term(4, 4)
+    %bls381_fp6_mul_add_term(8, 0)
+    // stack: a0 * b2 + a1 * b1 + a2 * b0, a, b, out, retdest
+    DUP7 %add_const(8) %store_fp381_2
+    %pop3
+    JUMP
+
+// Stores a * b at out, which must not overlap a or b, for b = b0 + b1 * v.
+global bls381_fp6_mul_by_01:
+    // stack: a, b, out, retdest
+    %bls381_fp6_mul_term(8, 4)
+    %mul_fp381_2_by_xi
+    %bls381_fp6_mul_add_term(0, 0)
+    // stack: a0 * b0 + xi * a2 * b1, a, b, out, retdest
+    DUP7 %store_fp381_2
+    %bls381_fp6_mul_term(0, 4)
+    %bls381_fp6_mul_add_term(4, 0)
+    // stack: a0 * b1 + a1 * b0, a, b, out, retdest
+    DUP7 %add_const(4) %store_fp381_2
+    %bls381_fp6_mul_term(4, 4)
+    %bls381_fp6_mul_add_term(8, 0)
+    // stack: a1 * b1 + a2 * b0, a, b, out, retdest
+    DUP7 %add_const(8) %store_fp381_2
+    %pop3
+    JUMP
+
+// Stores a + b at out.
+global bls381_fp6_add:
+    // stack: a, b, out, retdest
+    %rep 3
+        DUP2 %load_fp381_2
+        DUP5 %load_fp381_2
+        %add_fp381_2
+        DUP7 %store_fp381_2
+        %add_const(4)
+        SWAP1 %add_const(4) SWAP1
+        SWAP2 %add_const(4) SWAP2
+    %endrep
+    %pop3
+    JUMP
+
+// Stores a - b at out.
+global bls381_fp6_sub:
+    // stack: a, b, out, retdest
+    %rep 3
+        DUP2 %load_fp381_2
+        DUP5 %load_fp381_2
+        %sub_fp381_2
+        DUP7 %store_fp381_2
+        %add_const(4)
+        SWAP1 %add_const(4) SWAP1
+        SWAP2 %add_const(4) SWAP2
+    %endrep
+    %pop3
+    JUMP
+
+// Stores a * b at out, which may overlap a or b. The product is computed as
+// (a0 * b0 + v * a1 * b1) + ((a0 + a1) * (b0 + b1) - a0 * b0 - a1 * b1) * w.
+global bls381_fp12_mul:
+    // stack: a, b, out, retdest
+    %bls381_fp12_mul_scratch
+    // stack: s, a, b, out, retdest
+    PUSH bls381_fp12_mul_1 DUP2 DUP4 %add_const(12) DUP5
+    // stack: a, a + 12, s, bls381_fp12_mul_1, s, a, b, out, retdest
+    %jump(bls381_fp6_add)
+bls381_fp12_mul_1:
+    // stack: s, a, b, out, retdest
+    PUSH bls381_fp12_mul_2 DUP2 %add_const(12) DUP5 %add_const(12) DUP6
+    // stack: b, b + 12, s + 12, bls381_fp12_mul_2, s, a, b, out, retdest
+    %jump(bls381_fp6_add)
+bls381_fp12_mul_2:
+    // stack: s, a, b, out, retdest
+    PUSH bls381_fp12_mul_3 DUP2 %add_const(36) DUP5 DUP5
+    // stack: a, b, s + 36, bls381_fp12_mul_3, s, a, b, out, retdest
+    %jump(bls381_fp6_mul)
+bls381_fp12_mul_3:
+    // stack: s, a, b, out, retdest
+    PUSH bls381_fp12_mul_4 DUP2 %add_const(48) DUP5 %add_const(12) DUP5 %add_const(12)
+    // stack: a + 12, b + 12, s + 48, bls381_fp12_mul_4, s, a, b, out, retdest
+    %jump(bls381_fp6_mul)
+bls381_fp12_mul_4:
+    // stack: s, a, b, out, retdest
+    PUSH bls381_fp12_mul_combine DUP2 %add_const(24) DUP3 %add_const(12) DUP4
+    // stack: s, s + 12, s + 24, bls381_fp12_mul_combine, s, a, b, out, retdest
+    %jump(bls381_fp6_mul)
+bls381_fp12_mul_combine:
+    // stack: s, a, b, out, retdest
+    // The scratch space holds the products (a0 + a1) * (b0 + b1), a0 * b0 and
+    // a1 * b1 at s + 24, s + 36 and s + 48.
+    PUSH bls381_fp12_mul_6 DUP2 %add_const(24) DUP3 %add_const(36) DUP2
+    // stack: s + 24, s + 36, s + 24, bls381_fp12_mul_6, s, a, b, out, retdest
+    %jump(bls381_fp6_sub)
+bls381_fp12_mul_6:
+    // stack: s, a, b, out, retdest
+    PUSH bls381_fp12_mul_7 DUP5 %add_const(12) DUP3 %add_const(48) DUP4 %add_const(24)
+    // stack: s + 24, s + 48, out + 12, bls381_fp12_mul_7, s, a, b, out, retdest
+    %jump(bls381_fp6_sub)
+bls381_fp12_mul_7:
+    // stack: s, a, b, out, retdest
+    // The first half of the product is a0 * b0 + v * a1 * b1, where the
+    // multiplication by v maps (c0, c1, c2) to (xi * c2, c0, c1).
+    DUP1 %add_const(56) %load_fp381_2
+    %mul_fp381_2_by_xi
+    DUP5 %add_const(36) %load_fp381_2
+    %add_fp381_2
+    DUP8 %store_fp381_2
+    DUP1 %add_const(48) %load_fp381_2
+    DUP5 %add_const(40) %load_fp381_2
+    %add_fp381_2
+    DUP8 %add_const(4) %store_fp381_2
+    DUP1 %add_const(52) %load_fp381_2
+    DUP5 %add_const(44) %load_fp381_2
+    %add_fp381_2
+    DUP8 %add_const(8) %store_fp381_2
+    // stack: s, a, b, out, retdest
+    %pop4
+    JUMP
+
+// Stores the coefficient of a at a + i times l4 at s + j.
+%macro bls381_fp12_mul_by_line_term(i, j)
+    // stack: s, a, l, out
+    DUP3 %add_const(16) %load_fp381
+    DUP4 %add_const($i) %load_fp381_2
+    %mul_fp381_2_by_fp381
+    DUP5 %add_const($j) %store_fp381_2
+    // stack: s, a, l, out
+%endmacro
+
+// Stores a * l at out, which may be a, for l = (l0 + l1 * v) + l4 * v * w with
+// l4 in Fp, which is the shape of the lines in the Miller loop.
+global bls381_fp12_mul_by_line:
+    // stack: a, l, out, retdest
+    %bls381_fp12_mul_scratch
+    // stack: s, a, l, out, retdest
+    PUSH bls381_fp12_mul_by_line_1 DUP2 DUP4 %add_const(12) DUP5
+    // stack: a, a + 12, s, bls381_fp12_mul_by_line_1, s, a, l, out, retdest
+    %jump(bls381_fp6_add)
+bls381_fp12_mul_by_line_1:
+    // stack: s, a, l, out, retdest
+    // Store l0 + (l1 + l4) * v at s + 12.
+    DUP3 %load_fp381_2
+    DUP5 %add_const(12) %store_fp381_2
+    DUP3 %add_const(4) %load_fp381_2
+    PUSH 0 PUSH 0
+    DUP9 %add_const(16) %load_fp381
+    %add_fp381_2
+    DUP5 %add_const(16) %store_fp381_2
+    // stack: s, a, l, out, retdest
+    PUSH bls381_fp12_mul_by_line_2 DUP2 %add_const(36) DUP5 DUP5
+    // stack: a, l, s + 36, bls381_fp12_mul_by_line_2, s, a, l, out, retdest
+    %jump(bls381_fp6_mul_by_01)
+bls381_fp12_mul_by_line_2:
+    // stack: s, a, l, out, retdest
+    PUSH bls381_fp12_mul_by_line_3 DUP2 %add_const(24) DUP3 %add_const(12) DUP4
+    // stack: s, s + 12, s + 24, bls381_fp12_mul_by_line_3, s, a, l, out, retdest
+    %jump(bls381_fp6_mul_by_01)
+bls381_fp12_mul_by_line_3:
+    // stack: s, a, l, out, retdest
+    // Store a1 * l4 * v at s + 48, where the multiplication by v maps
+    // (c0, c1, c2) to (xi * c2, c0, c1).
+    %bls381_fp12_mul_by_line_term(12, 52)
+    %bls381_fp12_mul_by_line_term(16, 56)
+    DUP3 %add_const(16) %load_fp381
+    DUP4 %add_const(20) %load_fp381_2
+    %mul_fp381_2_by_fp381
+    %mul_fp381_2_by_xi
+    DUP5 %add_const(48) %store_fp381_2
+    // stack: s, a, l, out, retdest
+    %jump(bls381_fp12_mul_combine)
+
+// Stores the square of a at out, which may be a, as
+// (a0 + a1 * v) * (a0 + a1) - (1 + v) * a0 * a1 + 2 * a0 * a1 * w.
+global bls381_fp12_sqr:
+    // stack: a, out, retdest
+    %bls381_fp12_mul_scratch
+    // stack: s, a, out, retdest
+    PUSH bls381_fp12_sqr_1 DUP2 %add_const(36) DUP4 %add_const(12) DUP5
+    // stack: a, a + 12, s + 36, bls381_fp12_sqr_1, s, a, out, retdest
+    %jump(bls381_fp6_mul)
+bls381_fp12_sqr_1:
+    // stack: s, a, out, retdest
+    PUSH bls381_fp12_sqr_2 DUP2 DUP4 %add_const(12) DUP5
+    // stack: a, a + 12, s, bls381_fp12_sqr_2, s, a, out, retdest
+    %jump(bls381_fp6_add)
+bls381_fp12_sqr_2:
+    // stack: s, a, out, retdest
+    // Store a0 + a1 * v at s + 12, where the multiplication by v maps
+    // (c0, c1, c2) to (xi * c2, c0, c1).
+    DUP2 %add_const(20) %load_fp381_2
+    %mul_fp381_2_by_xi
+    DUP6 %load_fp381_2
+    %add_fp381_2
+    DUP5 %add_const(12) %store_fp381_2
+    DUP2 %add_const(12) %load_fp381_2
+    DUP6 %add_const(4) %load_fp381_2
+    %add_fp381_2
+    DUP5 %add_const(16) %store_fp381_2
+    DUP2 %add_const(16) %load_fp381_2
+    DUP6 %add_const(8) %load_fp381_2
+    %add_fp381_2
+    DUP5 %add_const(20) %store_fp381_2
+    // stack: s, a, out, retdest
+    PUSH bls381_fp12_sqr_3 DUP2 %add_const(24) DUP3 %add_const(12) DUP4
+    // stack: s, s + 12, s + 24, bls381_fp12_sqr_3, s, a, out, retdest
+    %jump(bls381_fp6_mul)
+bls381_fp12_sqr_3:
+    // stack: s, a, out, retdest
+    PUSH bls381_fp12_sqr_4 DUP4 %add_const(12) DUP3 %add_const(36) DUP1
+    // stack: s + 36, s + 36, out + 12, bls381_fp12_sqr_4, s, a, out, retdest
+    %jump(bls381_fp6_add)
+bls381_fp12_sqr_4:
+    // stack: s, a, out, retdest
+    PUSH bls381_fp12_sqr_5 DUP2 %add_const(24) DUP3 %add_const(36) DUP2
+    // stack: s + 24, s + 36, s + 24, bls381_fp12_sqr_5, s, a, out, retdest
+    %jump(bls381_fp6_sub)
+bls381_fp12_sqr_5:
+    // stack: s, a, out, retdest
+    DUP1 %add_const(44) %load_fp381_2
+    %mul_fp381_2_by_xi
+    DUP5 %add_const(24) %load_fp381_2
+    %sub_fp381_2
+    DUP7 %store_fp381_2
+    DUP1 %add_const(36) %load_fp381_2
+    DUP5 %add_const(28) %load_fp381_2
+    %sub_fp381_2
+    DUP7 %add_const(4) %store_fp381_2
+    DUP1 %add_const(40) %load_fp381_2
+    DUP5 %add_const(32) %load_fp381_2
+    %sub_fp381_2
+    DUP7 %add_const(8) %store_fp381_2
+    // stack: s, a, out, retdest
+    %pop3
+    JUMP
+
+// Stores the conjugate a0 - a1 * w of a at out, which may be a. This is the
+// Frobenius map x -> x^(p^6), and the inverse of a in the cyclotomic subgroup.
+global bls381_fp12_conj:
+    // stack: a, out, retdest
+    %rep 3
+        DUP1 %load_fp381_2
+        DUP6 %store_fp381_2
+        %add_const(4)
+        SWAP1 %add_const(4) SWAP1
+    %endrep
+    %rep 3
+        DUP1 %load_fp381_2
+        %neg_fp381_2
+        DUP6 %store_fp381_2
+        %add_const(4)
+        SWAP1 %add_const(4) SWAP1
+    %endrep
+    %pop2
+    JUMP
+
+// Writing a = sum(c_j * w^j) with c_j in Fp2, the Frobenius map x -> x^(p^k)
+// sends c_j * w^j to conj^k(c_j) * gamma_(k, j) * w^j, where
+// gamma_(k, j) = (1 + i)^(j * (p^k - 1) / 6). The coefficient c_j sits at
+// offset 2 * j for even j, and at offset 12 + 2 * (j - 1) for odd j.
+
+%macro bls381_fp12_frob1_coeff(offset, c_im_hi, c_im_lo, c_re_hi, c_re_lo)
+    // stack: a, out
+    PUSH $c_im_hi PUSH $c_im_lo PUSH $c_re_hi PUSH $c_re_lo
+    DUP5 %add_const($offset) %load_fp381_2
+    %conj_fp381_2
+    %mul_fp381_2
+    DUP6 %add_const($offset) %store_fp381_2
+    // stack: a, out
+%endmacro
+
+%macro bls381_fp12_frob2_coeff(offset, c_hi, c_lo)
+    // stack: a, out
+    PUSH $c_hi PUSH $c_lo
+    DUP3 %add_const($offset) %load_fp381_2
+    %mul_fp381_2_by_fp381
+    DUP6 %add_const($offset) %store_fp381_2
+    // stack: a, out
+%endmacro
+
+// Stores a^p at out, which may be a.
+global bls381_fp12_frob1:
+    // stack: a, out, retdest
+    DUP1 %load_fp381_2
+    %conj_fp381_2
+    DUP6 %store_fp381_2
+    %bls381_fp12_frob1_coeff(12,
+        0xfc3e2b36c4e03288e9e902231f9fb8,
+        0x54a14787b6c7b36fec0c8ec971f63c5f282d5ac14d6c7ec22cf78a126ddc4af3,
+        0x1904d3bf02bb0667c231beb4202c0d1f,
+        0xfd603fd3cbd5f4f7b2443d784bab9c4f67ea53d63e7813d8d0775ed92235fb8)
+    %bls381_fp12_frob1_coeff(4,
+        0x1a0111ea397fe699ec02408663d4de85,
+        0xaa0d857d89759ad4897d29650fb85f9b409427eb4f49fffd8bfd00000000aaac,
+        0,
+        0)
+    %bls381_fp12_frob1_coeff(16,
+        0x6af0e0437ff400b6831e36d6bd17ffe,
+        0x48395dabc2d3435e77f76e17009241c5ee67992f72ec05f4c81084fbede3cc09,
+        0x6af0e0437ff400b6831e36d6bd17ffe,
+        0x48395dabc2d3435e77f76e17009241c5ee67992f72ec05f4c81084fbede3cc09)
+    %bls381_fp12_frob1_coeff(8,
+        0,
+        0,
+        0x1a0111ea397fe699ec02408663d4de85,
+        0xaa0d857d89759ad4897d29650fb85f9b409427eb4f49fffd8bfd00000000aaad)
+    %bls381_fp12_frob1_coeff(20,
+        0x144e4211384586c16bd3ad4afa99cc91,
+        0x70df3560e77982d0db45f3536814f0bd5871c1908bd478cd1ee605167ff82995,
+        0x5b2cfd9013a5fd8df47fa6b48b1e045,
+        0xf39816240c0b8fee8beadf4d8e9c0566c63a3e6e257f87329b18fae980078116)
+    %pop2
+    JUMP
+
+// Stores a^(p^2) at out, which may be a. The constants gamma_(2, j) all lie
+// in Fp.
+global bls381_fp12_frob2:
+    // stack: a, out, retdest
+    DUP1 %load_fp381_2
+    DUP6 %store_fp381_2
+    %bls381_fp12_frob2_coeff(12,
+        0x5f19672fdf76ce51,
+        0xba69c6076a0f77eaddb3a93be6f89688de17d813620a00022e01fffffffeffff)
+    %bls381_fp12_frob2_coeff(4,
+        0x5f19672fdf76ce51,
+        0xba69c6076a0f77eaddb3a93be6f89688de17d813620a00022e01fffffffefffe)
+    // gamma_(2, 3) = -1
+    DUP1 %add_const(16) %load_fp381_2
+    %neg_fp381_2
+    DUP6 %add_const(16) %store_fp381_2
+    %bls381_fp12_frob2_coeff(8,
+        0x1a0111ea397fe699ec02408663d4de85,
+        0xaa0d857d89759ad4897d29650fb85f9b409427eb4f49fffd8bfd00000000aaac)
+    %bls381_fp12_frob2_coeff(20,
+        0x1a0111ea397fe699ec02408663d4de85,
+        0xaa0d857d89759ad4897d29650fb85f9b409427eb4f49fffd8bfd00000000aaad)
+    %pop2
+    JUMP
+
+// Stores the inverse of the nonzero element a at out, which must not overlap
+// a. The inverse is given by the prover, and checked to be canonical and to
+// give one when multiplied by a.
+global bls381_fp12_inv:
+    // stack: a, out, retdest
+    PUSH 0
+bls381_fp12_inv_loop:
+    // stack: i, a, out, retdest
+    PROVER_INPUT(ffe::bls381_base::inverse)
+    DUP2 DUP5 ADD
+    // stack: out + i, a^-1[i], i, a, out, retdest
+    SWAP1 MSTORE_GENERAL
+    %increment
+    DUP1 %lt_const(24) %jumpi(bls381_fp12_inv_loop)
+    // stack: 24, a, out, retdest
+    POP
+    DUP2
+    %rep 12
+        DUP1 %load_fp381
+        %check_fp381
+        %pop2
+        %add_const(2)
+    %endrep
+    POP
+    // stack: a, out, retdest
+    PUSH bls381_fp12_inv_check %bls381_fp12_inv_scratch DUP4 DUP4
+    // stack: a, out, s, bls381_fp12_inv_check, a, out, retdest
+    %jump(bls381_fp12_mul)
+bls381_fp12_inv_check:
+    // stack: a, out, retdest
+    %bls381_fp12_inv_scratch
+    %bls381_fp12_is_one
+    %assert_nonzero
+    %pop2
+    JUMP
+
+// Stores at s + i and s + j the coefficients t0, t1 of the square of
+// x + y * w^3, where x and y are the coefficients at a + i and a + j. Since
+// w^6 = xi, t0 = x^2 + xi * y^2 = (x + y) * (x + xi * y) - (1 + xi) * x * y
+// and t1 = 2 * x * y.
+%macro bls381_fp4_sqr(i, j)
+    // stack: a, s
+    DUP1 %add_const($j) %load_fp381_2
+    DUP5 %add_const($i) %load_fp381_2
+    %mul_fp381_2
+    // stack: x * y, a, s
+    DUP4 DUP4 DUP4 DUP4
+    DUP4 DUP4 DUP4 DUP4
+    %add_fp381_2
+    DUP10 %add_const($j) %store_fp381_2
+    // stack: x * y, a, s
+    DUP4 DUP4 DUP4 DUP4
+    %mul_fp381_2_by_xi
+    %add_fp381_2
+    %neg_fp381_2
+    // stack: -(1 + xi) * x * y, a, s
+    DUP5 %add_const($j) %load_fp381_2
+    %mul_fp381_2_by_xi
+    DUP9 %add_const($i) %load_fp381_2
+    %add_fp381_2
+    // stack: x + xi * y, -(1 + xi) * x * y, a, s
+    DUP9 %add_const($j) %load_fp381_2
+    DUP13 %add_const($i) %load_fp381_2
+    %add_fp381_2
+    %mul_fp381_2
+    %add_fp381_2
+    // stack: t0, a, s
+    DUP6 %add_const($i) %store_fp381_2
+    // stack: a, s
+%endmacro
+
+%macro bls381_fp12_cyclotomic_sqr_sub(offset)
+    // stack: t, s, a, out
+    DUP6 %add_const($offset) %load_fp381_2
+    DUP8 DUP8 DUP8 DUP8
+    %sub_fp381_2
+    // stack: t - z, t, s, a, out
+    DUP4 DUP4 DUP4 DUP4
+    %add_fp381_2
+    %add_fp381_2
+    // stack: 3 * t - 2 * z, s, a, out
+    DUP7 %add_const($offset) %store_fp381_2
+    // stack: s, a, out
+%endmacro
+
+%macro bls381_fp12_cyclotomic_sqr_add(offset)
+    // stack: t, s, a, out
+    DUP6 %add_const($offset) %load_fp381_2
+    DUP8 DUP8 DUP8 DUP8
+    %add_fp381_2
+    // stack: t + z, t, s, a, out
+    DUP4 DUP4 DUP4 DUP4
+    %add_fp381_2
+    %add_fp381_2
+    // stack: 3 * t + 2 * z, s, a, out
+    DUP7 %add_const($offset) %store_fp381_2
+    // stack: s, a, out
+%endmacro
+
+// Stores the square of a at out, which may be a, for a in the cyclotomic
+// subgroup, using the squaring formula of Granger and Scott. Over
+// Fp4 = Fp2[w^3], a is made of the three coefficients z0 + z1 * w^3,
+// z2 + z3 * w^3 and z4 + z5 * w^3, whose squares are computed first.
+global bls381_fp12_cyclotomic_sqr:
+    // stack: a, out, retdest
+    %bls381_fp12_mul_scratch
+    DUP2
+    // stack: a, s, a, out, retdest
+    %bls381_fp4_sqr(0, 16)
+    %bls381_fp4_sqr(12, 8)
+    %bls381_fp4_sqr(4, 20)
+    POP
+    // stack: s, a, out, retdest
+    DUP1 %load_fp381_2
+    %bls381_fp12_cyclotomic_sqr_sub(0)
+    DUP1 %add_const(16) %load_fp381_2
+    %bls381_fp12_cyclotomic_sqr_add(16)
+    DUP1 %add_const(12) %load_fp381_2
+    %bls381_fp12_cyclotomic_sqr_sub(4)
+    DUP1 %add_const(8) %load_fp381_2
+    %bls381_fp12_cyclotomic_sqr_add(20)
+    DUP1 %add_const(20) %load_fp381_2
+    %mul_fp381_2_by_xi
+    %bls381_fp12_cyclotomic_sqr_add(12)
+    DUP1 %add_const(4) %load_fp381_2
+    %bls381_fp12_cyclotomic_sqr_sub(8)
+    // stack: s, a, out, retdest
+    %pop3
+    JUMP
+
+// Stores a^u at out, which must not overlap a, where u = -0xd201000000010000
+// is the curve parameter. This assumes that a is in the cyclotomic subgroup,
+// where the inverse is the conjugate.
+global bls381_fp12_exp_by_x:
+    // stack: a, out, retdest
+    DUP2 DUP2
+    // stack: a, out, a, out, retdest
+    %rep 24
+        DUP2 DUP2 MLOAD_GENERAL MSTORE_GENERAL
+        %increment SWAP1 %increment SWAP1
+    %endrep
+    %pop2
+    // Square and multiply over the bits of -u following the leading one.
+    PUSH 63
+bls381_fp12_exp_by_x_loop:
+    // stack: i, a, out, retdest
+    PUSH bls381_fp12_exp_by_x_squared DUP4 DUP1
+    // stack: out, out, bls381_fp12_exp_by_x_squared, i, a, out, retdest
+    %jump(bls381_fp12_cyclotomic_sqr)
+bls381_fp12_exp_by_x_squared:
+    // stack: i, a, out, retdest
+    PUSH 0xd201000000010000 DUP2 %decrement SHR
+    %mod_const(2) ISZERO %jumpi(bls381_fp12_exp_by_x_next)
+    PUSH bls381_fp12_exp_by_x_next DUP4 DUP4 DUP2
+    // stack: out, a, out, bls381_fp12_exp_by_x_next, i, a, out, retdest
+    %jump(bls381_fp12_mul)
+bls381_fp12_exp_by_x_next:
+    // stack: i, a, out, retdest
+    %decrement
+    DUP1 %jumpi(bls381_fp12_exp_by_x_loop)
+    // stack: 0, a, out, retdest
+    %stack (i, a, out) -> (out, out)
+    %jump(bls381_fp12_conj)
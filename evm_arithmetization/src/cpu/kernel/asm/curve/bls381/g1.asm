@@ -0,0 +1,188 @@
+// Arithmetic on the BLS12-381 curve E(Fp): y^2 = x^3 + 4.
+//
+// A point (x, y) takes up four stack terms x, y, with x on top. The point at
+// infinity is represented by (0, 0), which is not on the curve.
+
+%macro bls381_g1_add
+    // stack: a, b
+    %stack (a: 4, b: 4) -> (a, b, %%after)
+    %jump(bls381_g1_add)
+%%after:
+    // stack: a + b
+%endmacro
+
+%macro bls381_g1_mul
+    // stack: a, k
+    %stack (a: 4, k) -> (a, k, %%after)
+    %jump(bls381_g1_mul)
+%%after:
+    // stack: k * a
+%endmacro
+
+// Returns whether a is on the curve or is the point at infinity, leaving a on
+// the stack.
+%macro bls381_g1_is_on_curve
+    // stack: x, y
+    DUP4 DUP4 DUP4 DUP4 OR OR OR ISZERO
+    %jumpi(%%infinity)
+    // stack: x, y
+    DUP4 DUP4 DUP2 DUP2
+    %mul_fp381
+    // stack: y^2, x, y
+    DUP4 DUP4 DUP2 DUP2
+    %mul_fp381
+    DUP6 DUP6
+    %mul_fp381
+    // stack: x^3, y^2, x, y
+    PUSH 0 PUSH 4
+    %add_fp381
+    // stack: x^3 + 4, y^2, x, y
+    %eq_fp381
+    // stack: is_on_curve, x, y
+    %jump(%%done)
+%%infinity:
+    PUSH 1
+%%done:
+    // stack: is_on_curve, x, y
+%endmacro
+
+global bls381_g1_add:
+    // stack: a, b, retdest
+    DUP4 DUP4 DUP4 DUP4 OR OR OR ISZERO
+    %jumpi(bls381_g1_add_return_b)
+    DUP8 DUP8 DUP8 DUP8 OR OR OR ISZERO
+    %jumpi(bls381_g1_add_return_a)
+    // stack: x_a, y_a, x_b, y_b, retdest
+    DUP6 DUP6 DUP4 DUP4 %eq_fp381
+    %jumpi(bls381_g1_add_equal_x)
+
+    // lambda = (y_b - y_a) / (x_b - x_a)
+    DUP2 DUP2 DUP8 DUP8 %sub_fp381
+    // stack: x_b - x_a, x_a, y_a, x_b, y_b, retdest
+    DUP6 DUP6 DUP12 DUP12 %sub_fp381
+    // stack: y_b - y_a, x_b - x_a, x_a, y_a, x_b, y_b, retdest
+    %div_fp381
+    // stack: lambda, x_a, y_a, x_b, y_b, retdest
+
+    // x_c = lambda^2 - x_a - x_b
+    DUP8 DUP8 DUP6 DUP6 %add_fp381
+    // stack: x_a + x_b, lambda, x_a, y_a, x_b, y_b, retdest
+    DUP4 DUP4 DUP2 DUP2 %mul_fp381
+    // stack: lambda^2, x_a + x_b, lambda, x_a, y_a, x_b, y_b, retdest
+    %sub_fp381
+    // stack: x_c, lambda, x_a, y_a, x_b, y_b, retdest
+
+    // y_c = lambda * (x_a - x_c) - y_a
+    DUP8 DUP8 DUP4 DUP4 DUP10 DUP10 %sub_fp381
+    // stack: x_a - x_c, y_a, x_c, lambda, x_a, y_a, x_b, y_b, retdest
+    DUP8 DUP8 %mul_fp381
+    %sub_fp381
+    // stack: y_c, x_c, lambda, x_a, y_a, x_b, y_b, retdest
+    %stack (y_c: 2, x_c: 2, unused: 10, retdest) -> (retdest, x_c, y_c)
+    JUMP
+
+bls381_g1_add_equal_x:
+    // stack: x_a, y_a, x_b, y_b, retdest
+    DUP8 DUP8 DUP6 DUP6 %eq_fp381
+    // stack: y_a == y_b, x_a, y_a, x_b, y_b, retdest
+    %jumpi(bls381_g1_add_double)
+    // a == -b, so the sum is the point at infinity.
+    %stack (a: 4, b: 4, retdest) -> (retdest, 0, 0, 0, 0)
+    JUMP
+bls381_g1_add_double:
+    // stack: a, b, retdest
+    %stack (a: 4, b: 4) -> (a)
+    %jump(bls381_g1_double)
+bls381_g1_add_return_a:
+    // stack: a, b, retdest
+    %stack (a: 4, b: 4, retdest) -> (retdest, a)
+    JUMP
+bls381_g1_add_return_b:
+    // stack: a, b, retdest
+    %stack (a: 4, b: 4, retdest) -> (retdest, b)
+    JUMP
+
+global bls381_g1_double:
+    // stack: a, retdest
+    DUP4 DUP4 DUP4 DUP4 OR OR OR ISZERO
+    %jumpi(bls381_g1_double_infinity)
+    // The curve has no point of order 2, so y != 0 here.
+    // lambda = 3 * x^2 / (2 * y)
+    // stack: x, y, retdest
+    DUP4 DUP4 DUP2 DUP2 %add_fp381
+    // stack: 2 * y, x, y, retdest
+    DUP4 DUP4 DUP2 DUP2 %mul_fp381
+    DUP2 DUP2 DUP2 DUP2 %add_fp381 %add_fp381
+    // stack: 3 * x^2, 2 * y, x, y, retdest
+    %div_fp381
+    // stack: lambda, x, y, retdest
+
+    // x' = lambda^2 - 2 * x
+    DUP4 DUP4 DUP2 DUP2 %add_fp381
+    // stack: 2 * x, lambda, x, y, retdest
+    DUP4 DUP4 DUP2 DUP2 %mul_fp381
+    %sub_fp381
+    // stack: x', lambda, x, y, retdest
+
+    // y' = lambda * (x - x') - y
+    DUP8 DUP8 DUP4 DUP4 DUP10 DUP10 %sub_fp381
+    // stack: x - x', y, x', lambda, x, y, retdest
+    DUP8 DUP8 %mul_fp381
+    %sub_fp381
+    // stack: y', x', lambda, x, y, retdest
+    %stack (y_: 2, x_: 2, unused: 6, retdest) -> (retdest, x_, y_)
+    JUMP
+bls381_g1_double_infinity:
+    // stack: a, retdest
+    %stack (a: 4, retdest) -> (retdest, a)
+    JUMP
+
+// Computes k * a by double-and-add, from the least significant bit of k.
+global bls381_g1_mul:
+    // stack: a, k, retdest
+    %stack (a: 4, k) -> (k, a, 0, 0, 0, 0)
+bls381_g1_mul_loop:
+    // stack: k, a, acc, retdest
+    DUP1 ISZERO %jumpi(bls381_g1_mul_end)
+    DUP1 %mod_const(2) ISZERO %jumpi(bls381_g1_mul_double)
+    %stack (k, a: 4, acc: 4) -> (acc, bls381_g1_mul_added, k, a)
+    DUP10 DUP10 DUP10 DUP10
+    // stack: a, acc, bls381_g1_mul_added, k, a, retdest
+    %jump(bls381_g1_add)
+bls381_g1_mul_added:
+    // stack: acc, k, a, retdest
+    %stack (acc: 4, k, a: 4) -> (k, a, acc)
+bls381_g1_mul_double:
+    // stack: k, a, acc, retdest
+    %shr_const(1)
+    %stack (k, a: 4) -> (a, bls381_g1_mul_doubled, k)
+    %jump(bls381_g1_double)
+bls381_g1_mul_doubled:
+    // stack: a, k, acc, retdest
+    %stack (a: 4, k) -> (k, a)
+    %jump(bls381_g1_mul_loop)
+bls381_g1_mul_end:
+    // stack: k, a, acc, retdest
+    %stack (k, a: 4, acc: 4, retdest) -> (retdest, acc)
+    JUMP
+
+// Returns whether a point a on the curve lies in the subgroup G1, using the
+// endomorphism phi(x, y) = (beta * x, y): a is in G1 iff phi(a) == -[u^2] a,
+// where u is the curve parameter.
+global bls381_g1_is_in_subgroup:
+    // stack: a, retdest
+    DUP4 DUP4 DUP4 DUP4
+    PUSH 0xac45a4010001a4020000000100000000
+    %stack (u2, a: 4) -> (a, u2)
+    %bls381_g1_mul
+    // stack: [u^2] a, a, retdest
+    %stack (b: 4, x: 2, y: 2) -> (y, x, b)
+    %neg_fp381
+    // stack: -y, x, [u^2] a, retdest
+    %stack (y: 2, x: 2) -> (x, 0xba69c6076a0f77eaddb3a93be6f89688de17d813620a00022e01fffffffefffe, 0x5f19672fdf76ce51, y)
+    %mul_fp381
+    // stack: beta * x, -y, [u^2] a, retdest
+    %eq_fp381_2
+    // stack: is_in_subgroup, retdest
+    SWAP1
+    JUMP
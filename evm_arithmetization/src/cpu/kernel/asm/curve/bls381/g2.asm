@@ -0,0 +1,393 @@
+// Arithmetic on the BLS12-381 twist E'(Fp2): y^2 = x^3 + 4 * (1 + i), whose
+// subgroup of order r is G2.
+//
+// Points are kept in the BLS12-381 scratch segment as eight consecutive words
+// x, y, each in the layout of `store_fp381_2`, and are passed around by address.
+// The point at infinity is represented by zeros. The addition and doubling
+// routines leave the slope of the line through their inputs on the stack, for
+// use in the Miller loop.
+
+// Scratch space for the running multiple in `bls381_g2_mul`.
+%macro bls381_g2_mul_point
+    %bls381_address(0)
+%endmacro
+
+// Scratch space for the points compared in `bls381_g2_is_in_subgroup`.
+%macro bls381_g2_subgroup_points
+    %bls381_address(8)
+%endmacro
+
+%macro bls381_g2_is_infinity
+    // stack: addr
+    DUP1 MLOAD_GENERAL
+    %rep 7
+        SWAP1 %increment SWAP1
+        DUP2 MLOAD_GENERAL OR
+    %endrep
+    // stack: x | y, addr
+    SWAP1 POP ISZERO
+    // stack: is_infinity
+%endmacro
+
+%macro bls381_g2_set_infinity
+    // stack: addr
+    %rep 8
+        DUP1 PUSH 0 MSTORE_GENERAL
+        %increment
+    %endrep
+    POP
+    // stack: (empty)
+%endmacro
+
+%macro bls381_g2_copy
+    // stack: src, dst
+    %rep 8
+        DUP2 DUP2 MLOAD_GENERAL MSTORE_GENERAL
+        %increment SWAP1 %increment SWAP1
+    %endrep
+    %pop2
+    // stack: (empty)
+%endmacro
+
+%macro bls381_g2_neg
+    // stack: addr
+    %add_const(4)
+    DUP1 %load_fp381_2
+    %neg_fp381_2
+    DUP5 %store_fp381_2
+    POP
+    // stack: (empty)
+%endmacro
+
+// Returns whether the point at addr is on the curve or is the point at
+// infinity.
+%macro bls381_g2_is_on_curve
+    // stack: addr
+    DUP1 %bls381_g2_is_infinity
+    %jumpi(%%infinity)
+    DUP1 %add_const(4) %load_fp381_2
+    %sqr_fp381_2
+    // stack: y^2, addr
+    DUP5 %load_fp381_2
+    DUP4 DUP4 DUP4 DUP4
+    %sqr_fp381_2
+    %mul_fp381_2
+    // stack: x^3, y^2, addr
+    PUSH 0 PUSH 4 PUSH 0 PUSH 4
+    %add_fp381_2
+    // stack: x^3 + 4 * (1 + i), y^2, addr
+    %eq_fp381_2
+    // stack: is_on_curve, addr
+    SWAP1 POP
+    %jump(%%done)
+%%infinity:
+    // stack: addr
+    POP PUSH 1
+%%done:
+    // stack: is_on_curve
+%endmacro
+
+global bls381_g2_add:
+    // stack: a, b, out, retdest
+    DUP1 %bls381_g2_is_infinity
+    %jumpi(bls381_g2_add_return_b)
+    DUP2 %bls381_g2_is_infinity
+    %jumpi(bls381_g2_add_return_a)
+    DUP1 %load_fp381_2
+    DUP6 %load_fp381_2
+    // stack: x_b, x_a, a, b, out, retdest
+    DUP8 DUP8 DUP8 DUP8
+    DUP8 DUP8 DUP8 DUP8
+    %eq_fp381_2
+    %jumpi(bls381_g2_add_equal_x)
+
+    // lambda = (y_b - y_a) / (x_b - x_a)
+    %sub_fp381_2
+    // stack: x_b - x_a, a, b, out, retdest
+    DUP5 %add_const(4) %load_fp381_2
+    DUP10 %add_const(4) %load_fp381_2
+    %sub_fp381_2
+    // stack: y_b - y_a, x_b - x_a, a, b, out, retdest
+    %div_fp381_2
+    // stack: lambda, a, b, out, retdest
+
+    // x_c = lambda^2 - x_a - x_b
+    DUP5 %load_fp381_2
+    DUP10 %load_fp381_2
+    %add_fp381_2
+    // stack: x_a + x_b, lambda, a, b, out, retdest
+    DUP8 DUP8 DUP8 DUP8
+    %sqr_fp381_2
+    %sub_fp381_2
+    // stack: x_c, lambda, a, b, out, retdest
+
+    // y_c = lambda * (x_a - x_c) - y_a
+    DUP4 DUP4 DUP4 DUP4
+    DUP13 %load_fp381_2
+    %sub_fp381_2
+    // stack: x_a - x_c, x_c, lambda, a, b, out, retdest
+    DUP12 DUP12 DUP12 DUP12
+    %mul_fp381_2
+    DUP13 %add_const(4) %load_fp381_2
+    %stack (y_a: 4, v: 4) -> (v, y_a)
+    %sub_fp381_2
+    // stack: y_c, x_c, lambda, a, b, out, retdest
+
+    DUP15 %add_const(4) %store_fp381_2
+    DUP11 %store_fp381_2
+    // stack: lambda, a, b, out, retdest
+    %stack (lambda: 4, a, b, out, retdest) -> (retdest, lambda)
+    JUMP
+
+bls381_g2_add_equal_x:
+    // stack: x_b, x_a, a, b, out, retdest
+    %pop8
+    DUP1 %add_const(4) %load_fp381_2
+    DUP6 %add_const(4) %load_fp381_2
+    %eq_fp381_2
+    // stack: y_a == y_b, a, b, out, retdest
+    %jumpi(bls381_g2_add_double)
+    // a == -b, so the sum is the point at infinity.
+    %stack (a, b, out, retdest) -> (out, retdest, 0, 0, 0, 0)
+    %bls381_g2_set_infinity
+    JUMP
+bls381_g2_add_double:
+    // stack: a, b, out, retdest
+    %stack (a, b, out) -> (a, out)
+    %jump(bls381_g2_double)
+bls381_g2_add_return_a:
+    // stack: a, b, out, retdest
+    %stack (a, b, out, retdest) -> (a, out, retdest, 0, 0, 0, 0)
+    %bls381_g2_copy
+    JUMP
+bls381_g2_add_return_b:
+    // stack: a, b, out, retdest
+    %stack (a, b, out, retdest) -> (b, out, retdest, 0, 0, 0, 0)
+    %bls381_g2_copy
+    JUMP
+
+global bls381_g2_double:
+    // stack: a, out, retdest
+    DUP1 %bls381_g2_is_infinity
+    %jumpi(bls381_g2_double_infinity)
+    // The twist has no point of order 2, so y != 0 here.
+    // lambda = 3 * x^2 / (2 * y)
+    DUP1 %add_const(4) %load_fp381_2
+    DUP4 DUP4 DUP4 DUP4
+    %add_fp381_2
+    // stack: 2 * y, a, out, retdest
+    DUP5 %load_fp381_2
+    %sqr_fp381_2
+    DUP4 DUP4 DUP4 DUP4
+    DUP4 DUP4 DUP4 DUP4
+    %add_fp381_2
+    %add_fp381_2
+    // stack: 3 * x^2, 2 * y, a, out, retdest
+    %div_fp381_2
+    // stack: lambda, a, out, retdest
+
+    // x' = lambda^2 - 2 * x
+    DUP5 %load_fp381_2
+    DUP4 DUP4 DUP4 DUP4
+    %add_fp381_2
+    // stack: 2 * x, lambda, a, out, retdest
+    DUP8 DUP8 DUP8 DUP8
+    %sqr_fp381_2
+    %sub_fp381_2
+    // stack: x', lambda, a, out, retdest
+
+    // y' = lambda * (x - x') - y
+    DUP4 DUP4 DUP4 DUP4
+    DUP13 %load_fp381_2
+    %sub_fp381_2
+    // stack: x - x', x', lambda, a, out, retdest
+    DUP12 DUP12 DUP12 DUP12
+    %mul_fp381_2
+    DUP13 %add_const(4) %load_fp381_2
+    %stack (y: 4, v: 4) -> (v, y)
+    %sub_fp381_2
+    // stack: y', x', lambda, a, out, retdest
+
+    DUP14 %add_const(4) %store_fp381_2
+    DUP10 %store_fp381_2
+    // stack: lambda, a, out, retdest
+    %stack (lambda: 4, a, out, retdest) -> (retdest, lambda)
+    JUMP
+bls381_g2_double_infinity:
+    // stack: a, out, retdest
+    %stack (a, out, retdest) -> (out, retdest, 0, 0, 0, 0)
+    %bls381_g2_set_infinity
+    JUMP
+
+// Stores k * a at out, computed by double-and-add from the least significant
+// bit of k.
+global bls381_g2_mul:
+    // stack: a, k, out, retdest
+    %bls381_g2_mul_point
+    DUP1 SWAP2
+    // stack: a, t, t, k, out, retdest
+    %bls381_g2_copy
+    // stack: t, k, out, retdest
+    DUP3 %bls381_g2_set_infinity
+bls381_g2_mul_loop:
+    // stack: t, k, out, retdest
+    DUP2 ISZERO %jumpi(bls381_g2_mul_end)
+    DUP2 %mod_const(2) ISZERO %jumpi(bls381_g2_mul_double)
+    PUSH bls381_g2_mul_added DUP4 DUP3 DUP6
+    // stack: out, t, out, bls381_g2_mul_added, t, k, out, retdest
+    %jump(bls381_g2_add)
+bls381_g2_mul_added:
+    // stack: lambda, t, k, out, retdest
+    %pop4
+bls381_g2_mul_double:
+    // stack: t, k, out, retdest
+    SWAP1 %shr_const(1) SWAP1
+    PUSH bls381_g2_mul_doubled DUP2 DUP1
+    // stack: t, t, bls381_g2_mul_doubled, t, k, out, retdest
+    %jump(bls381_g2_double)
+bls381_g2_mul_doubled:
+    // stack: lambda, t, k, out, retdest
+    %pop4
+    %jump(bls381_g2_mul_loop)
+bls381_g2_mul_end:
+    // stack: t, k, out, retdest
+    %stack (t, k, out, retdest) -> (retdest)
+    JUMP
+
+// Stores psi(a) at out, where psi is the untwist-Frobenius-twist endomorphism
+// psi(x, y) = (conj(x) * c_x, conj(y) * c_y).
+global bls381_g2_psi:
+    // stack: a, out, retdest
+    DUP1 %load_fp381_2
+    %conj_fp381_2
+    PUSH 0x1a0111ea397fe699ec02408663d4de85
+    PUSH 0xaa0d857d89759ad4897d29650fb85f9b409427eb4f49fffd8bfd00000000aaad
+    PUSH 0 PUSH 0
+    %mul_fp381_2
+    // stack: x', a, out, retdest
+    DUP5 %add_const(4) %load_fp381_2
+    %conj_fp381_2
+    PUSH 0x6af0e0437ff400b6831e36d6bd17ffe
+    PUSH 0x48395dabc2d3435e77f76e17009241c5ee67992f72ec05f4c81084fbede3cc09
+    PUSH 0x135203e60180a68ee2e9c448d77a2cd9
+    PUSH 0x1c3dedd930b1cf60ef396489f61eb45e304466cf3e67fa0af1ee7b04121bdea2
+    %mul_fp381_2
+    // stack: y', x', a, out, retdest
+    DUP10 %add_const(4) %store_fp381_2
+    DUP6 %store_fp381_2
+    // stack: a, out, retdest
+    %pop2
+    JUMP
+
+// Stores psi^2(a) = (x * c, -y) at out, where c is a primitive cube root of
+// unity in Fp.
+global bls381_g2_psi2:
+    // stack: a, out, retdest
+    PUSH 0x1a0111ea397fe699ec02408663d4de85
+    PUSH 0xaa0d857d89759ad4897d29650fb85f9b409427eb4f49fffd8bfd00000000aaac
+    DUP3 %load_fp381_2
+    %mul_fp381_2_by_fp381
+    // stack: x', a, out, retdest
+    DUP5 %add_const(4) %load_fp381_2
+    %neg_fp381_2
+    // stack: y', x', a, out, retdest
+    DUP10 %add_const(4) %store_fp381_2
+    DUP6 %store_fp381_2
+    // stack: a, out, retdest
+    %pop2
+    JUMP
+
+// Returns whether a point a on the curve lies in the subgroup G2: this is the
+// case iff psi(a) == [u] a, where u = -0xd201000000010000 is the curve
+// parameter.
+global bls381_g2_is_in_subgroup:
+    // stack: a, retdest
+    %bls381_g2_subgroup_points
+    // stack: q, a, retdest
+    PUSH bls381_g2_is_in_subgroup_mul DUP2 PUSH 0xd201000000010000 DUP5
+    // stack: a, -u, q, bls381_g2_is_in_subgroup_mul, q, a, retdest
+    %jump(bls381_g2_mul)
+bls381_g2_is_in_subgroup_mul:
+    // stack: q, a, retdest
+    PUSH bls381_g2_is_in_subgroup_psi DUP2 %add_const(8) DUP4
+    // stack: a, q + 8, bls381_g2_is_in_subgroup_psi, q, a, retdest
+    %jump(bls381_g2_psi)
+bls381_g2_is_in_subgroup_psi:
+    // stack: q, a, retdest
+    // Check that psi(a), stored at q + 8, is the opposite of q = [-u] a.
+    DUP1 %load_fp381_2
+    DUP5 %add_const(8) %load_fp381_2
+    %eq_fp381_2
+    // stack: x_psi == x_q, q, a, retdest
+    DUP2 %add_const(4) %load_fp381_2
+    %neg_fp381_2
+    DUP6 %add_const(12) %load_fp381_2
+    %eq_fp381_2
+    // stack: y_psi == -y_q, x_psi == x_q, q, a, retdest
+    MUL
+    %stack (is_in_subgroup, q, a, retdest) -> (retdest, is_in_subgroup)
+    JUMP
+
+// Multiplies the point a by the effective cofactor h_eff of G2 in place, using
+// h_eff * a = [u^2 - u - 1] a + [u - 1] psi(a) + psi^2(2 * a) as in RFC 9380.
+// The multiples t1, t2 and t3 are kept at offsets 24, 32 and 40 of the scratch
+// segment, so a must lie outside offsets 0 to 48.
+global bls381_g2_clear_cofactor:
+    // stack: a, retdest
+    // t1 = [u] a
+    PUSH bls381_g2_clear_cofactor_1 %bls381_address(24) PUSH 0xd201000000010000 DUP4
+    // stack: a, -u, t1, bls381_g2_clear_cofactor_1, a, retdest
+    %jump(bls381_g2_mul)
+bls381_g2_clear_cofactor_1:
+    %bls381_address(24) %bls381_g2_neg
+    // t2 = -psi(a)
+    PUSH bls381_g2_clear_cofactor_2 %bls381_address(32) DUP3
+    %jump(bls381_g2_psi)
+bls381_g2_clear_cofactor_2:
+    %bls381_address(32) %bls381_g2_neg
+    // t3 = psi^2(2 * a) - psi(a)
+    PUSH bls381_g2_clear_cofactor_3 %bls381_address(40) DUP3
+    %jump(bls381_g2_double)
+bls381_g2_clear_cofactor_3:
+    %pop4
+    PUSH bls381_g2_clear_cofactor_4 %bls381_address(40) DUP1
+    %jump(bls381_g2_psi2)
+bls381_g2_clear_cofactor_4:
+    PUSH bls381_g2_clear_cofactor_5 %bls381_address(40) %bls381_address(32) DUP2
+    // stack: t3, t2, t3, bls381_g2_clear_cofactor_5, a, retdest
+    %jump(bls381_g2_add)
+bls381_g2_clear_cofactor_5:
+    %pop4
+    // t2 = [u] (t1 + psi(a))
+    %bls381_address(32) %bls381_g2_neg
+    PUSH bls381_g2_clear_cofactor_6 %bls381_address(32) DUP1 %bls381_address(24)
+    // stack: t1, t2, t2, bls381_g2_clear_cofactor_6, a, retdest
+    %jump(bls381_g2_add)
+bls381_g2_clear_cofactor_6:
+    %pop4
+    PUSH bls381_g2_clear_cofactor_7 %bls381_address(32) PUSH 0xd201000000010000 DUP2
+    // stack: t2, -u, t2, bls381_g2_clear_cofactor_7, a, retdest
+    %jump(bls381_g2_mul)
+bls381_g2_clear_cofactor_7:
+    %bls381_address(32) %bls381_g2_neg
+    // a = t3 + t2 - t1 - a
+    DUP1 %bls381_g2_neg
+    PUSH bls381_g2_clear_cofactor_8 DUP2 DUP1 %bls381_address(40)
+    // stack: t3, a, a, bls381_g2_clear_cofactor_8, a, retdest
+    %jump(bls381_g2_add)
+bls381_g2_clear_cofactor_8:
+    %pop4
+    PUSH bls381_g2_clear_cofactor_9 DUP2 DUP1 %bls381_address(32)
+    // stack: t2, a, a, bls381_g2_clear_cofactor_9, a, retdest
+    %jump(bls381_g2_add)
+bls381_g2_clear_cofactor_9:
+    %pop4
+    %bls381_address(24) %bls381_g2_neg
+    PUSH bls381_g2_clear_cofactor_10 DUP2 DUP1 %bls381_address(24)
+    // stack: t1, a, a, bls381_g2_clear_cofactor_10, a, retdest
+    %jump(bls381_g2_add)
+bls381_g2_clear_cofactor_10:
+    // stack: lambda, a, retdest
+    %pop4
+    POP
+    JUMP
@@ -0,0 +1,469 @@
+// Maps field elements to points of G1 and G2, as specified for the
+// `BLS12_MAP_FP_TO_G1` and `BLS12_MAP_FP2_TO_G2` precompiles: the simplified
+// SWU map to a curve isogenous to E or E', the isogeny to E or E', and the
+// clearing of the cofactor.
+//
+// The square roots are given by the prover, who could claim that a square has
+// no root. At most one of the two candidates g(x1) and g(x2) of the SWU map is
+// a non-square, so a root is always required for one of them, and the map
+// fails if the prover denies both.
+
+// Replaces acc with acc * x + c, for the Fp constant c.
+%macro bls381_horner_fp381(c_hi, c_lo)
+    // stack: acc, x
+    DUP4 DUP4
+    %mul_fp381
+    PUSH $c_hi PUSH $c_lo
+    %add_fp381
+    // stack: acc * x + c, x
+%endmacro
+
+// Replaces acc with acc * x + c, for the Fp2 constant c.
+%macro bls381_horner_fp381_2(c_im_hi, c_im_lo, c_re_hi, c_re_lo)
+    // stack: acc, x
+    DUP8 DUP8 DUP8 DUP8
+    %mul_fp381_2
+    PUSH $c_im_hi PUSH $c_im_lo PUSH $c_re_hi PUSH $c_re_lo
+    %add_fp381_2
+    // stack: acc * x + c, x
+%endmacro
+
+// Returns g(x) = x^3 + A * x + B for the curve y^2 = g(x) isogenous to E.
+%macro bls381_iso_g1_rhs
+    // stack: x
+    DUP2 DUP2 DUP2 DUP2
+    %mul_fp381
+    PUSH 0x144698a3b8e9433d693a02c96d4982 PUSH 0xb0ea985383ee66a8d8e8981aefd881ac98936f8da0e0f97f5cf428082d584c1d
+    %add_fp381
+    %mul_fp381
+    PUSH 0x12e2908d11688030018b12e8753eee3b PUSH 0x2016c1f0f24f4070a0b9c14fcef35ef55a23215a316ceaa5d1cc48e98e172be0
+    %add_fp381
+    // stack: g(x)
+%endmacro
+
+// Returns g(x) = x^3 + A * x + B for the curve y^2 = g(x) isogenous to E'.
+%macro bls381_iso_g2_rhs
+    // stack: x
+    DUP4 DUP4 DUP4 DUP4
+    %sqr_fp381_2
+    PUSH 0 PUSH 240 PUSH 0 PUSH 0
+    %add_fp381_2
+    %mul_fp381_2
+    PUSH 0 PUSH 1012 PUSH 0 PUSH 1012
+    %add_fp381_2
+    // stack: g(x)
+%endmacro
+
+// Returns the sign sgn0(x) of an Fp2 element, as defined in RFC 9380, leaving
+// x on the stack.
+%macro bls381_sgn0_fp381_2
+    // stack: x
+    DUP1 %mod_const(2)
+    DUP3 DUP3 OR ISZERO
+    DUP5 %mod_const(2)
+    MUL OR
+    // stack: sgn0(x), x
+%endmacro
+
+%macro bls381_swap_fp381
+    // stack: a, b
+    %stack (a: 2, b: 2) -> (b, a)
+%endmacro
+
+// Maps u to a point of G1, returned on the stack.
+global bls381_map_fp_to_g1:
+    // stack: u, retdest
+    DUP1 %mod_const(2)
+    %stack (s, u: 2) -> (u, u, s)
+    %mul_fp381
+    PUSH 0 PUSH 11
+    %mul_fp381
+    // stack: Z * u^2, sgn0(u), retdest
+    DUP2 DUP2 DUP2 DUP2
+    %mul_fp381
+    DUP4 DUP4
+    %add_fp381
+    // stack: tv1 = Z^2 * u^4 + Z * u^2, Z * u^2, sgn0(u), retdest
+    DUP2 DUP2 %is_zero_fp381
+    %jumpi(bls381_map_fp_to_g1_exceptional)
+    // x1 = -B / A * (1 + 1 / tv1)
+    DUP2 DUP2 PUSH 0 PUSH 1
+    %add_fp381
+    %div_fp381
+    PUSH 0x793154fd85631d966ef2470460c78f6 PUSH 0xa928ad9f5bdbfac21df39753aa278ba751bdfcf95a84188e29d670675e4c9c7c
+    %mul_fp381
+    %jump(bls381_map_fp_to_g1_x1)
+bls381_map_fp_to_g1_exceptional:
+    // x1 = B / (Z * A)
+    %pop2
+    PUSH 0x123939a31626a32de772bc7a591ea140 PUSH 0x683bca0c62efb105310d5ce1d27d1aadf79a5d5cbe8e2c4ff7d4816af76d2814
+bls381_map_fp_to_g1_x1:
+    // stack: x1, Z * u^2, sgn0(u), retdest
+    DUP2 DUP2 %bls381_iso_g1_rhs
+    %sqrt_fp381
+    %jumpi(bls381_map_fp_to_g1_square)
+    // g(x1) is not a square, so g(x2) must be one, for x2 = Z * u^2 * x1.
+    %pop2
+    %mul_fp381
+    DUP2 DUP2 %bls381_iso_g1_rhs
+    %sqrt_fp381
+    %assert_nonzero
+    %jump(bls381_map_fp_to_g1_sign)
+bls381_map_fp_to_g1_square:
+    // stack: y, x1, Z * u^2, sgn0(u), retdest
+    %stack (y: 2, x: 2, zu2: 2) -> (y, x)
+bls381_map_fp_to_g1_sign:
+    // stack: y, x, sgn0(u), retdest
+    DUP1 DUP6 XOR %mod_const(2)
+    ISZERO %jumpi(bls381_map_fp_to_g1_iso)
+    %neg_fp381
+bls381_map_fp_to_g1_iso:
+    // stack: y, x, sgn0(u), retdest
+    %stack (y: 2, x: 2, s) -> (x, y)
+    // Evaluate the rational maps of the isogeny.
+    // stack: x
+    PUSH 0 PUSH 1
+    %bls381_horner_fp381(
+        0xe0fa1d816ddc03e6b24255e0d7819c1,
+        0x71c40f65e273b853324efcd6356caa205ca2f570f13497804415473a1d634b8f)
+    %bls381_horner_fp381(
+        0x2660400eb2e4f3b628bdd0d53cd76f2,
+        0xbf565b94e72927c1cb748df27942480e420517bd8714cc80d1fadc1326ed06f7)
+    %bls381_horner_fp381(
+        0xad6b9514c767fe3c3613144b45f1496,
+        0x543346d98adf02267d5ceef9a00d9b8693000763e3b90ac11e99b138573345cc)
+    %bls381_horner_fp381(
+        0xaccbb67481d033ff5852c1e48c50c47,
+        0x7f94ff8aefce42d28c0f9a88cea7913516f968986f7ebbea9684b529e2561092)
+    %bls381_horner_fp381(
+        0x4d2f259eea405bd48f010a01ad2911d,
+        0x9c6dd039bb61a6290e591b36e636a5c871a5c29f4f83060400f8b49cba8f6aa8)
+    %bls381_horner_fp381(
+        0x167a55cda70a6e1cea820597d94a8490,
+        0x3216f763e13d87bb5308592e7ea7d4fbc7385ea3d529b35e346ef48bb8913f55)
+    %bls381_horner_fp381(
+        0x1866c8ed336c61231a1be54fd1d74cc4,
+        0xf9fb0ce4c6af5920abc5750c4bf39b4852cfe2f7bb9248836b233d9d55535d4a)
+    %bls381_horner_fp381(
+        0x16a3ef08be3ea7ea03bcddfabba6ff6e,
+        0xe5a4375efa1f4fd7feb34fd206357132b920f5b00801dee460ee415a15812ed9)
+    %bls381_horner_fp381(
+        0x166007c08a99db2fc3ba8734ace9824b,
+        0x5eecfdfa8d0cf8ef5dd365bc400a0051d5fa9c01a58b1fb93d1a1399126a775c)
+    %bls381_horner_fp381(
+        0x8d9e5297186db2d9fb266eaac783182,
+        0xb70152c65550d881c5ecd87b6f0f5a6449f38db9dfa9cce202c6477faaf9b7ac)
+    %bls381_horner_fp381(
+        0xbe0e079545f43e4b00cc912f8228ddc,
+        0xc6d19c9f0f69bbb0542eda0fc9dec916a20b15dc0fd2ededda39142311a5001d)
+    %bls381_horner_fp381(
+        0x16b7d288798e5395f20d23bf89edb4d1,
+        0xd115c5dbddbcd30e123da489e726af41727364f2c28297ada8d26d98445f5416)
+    %bls381_horner_fp381(
+        0x58df3306640da276faaae7d6e8eb157,
+        0x78c4855551ae7f310c35a5dd279cd2eca6757cd636f96f891e2538b53dbf67f2)
+    %bls381_horner_fp381(
+        0x1962d75c2381201e1a0cbd6c43c348b8,
+        0x85c84ff731c4d59ca4a10356f453e01f78a4260763529e3532f6102c2e49a03d)
+    %bls381_horner_fp381(
+        0x16112c4c3a9c98b252181140fad0eae9,
+        0x601a6de578980be6eec3232b5be72e7a07f3688ef60c206d01479253b03663c1)
+    // stack: y_den(x), x
+    %bls381_swap_fp381
+    // stack: x
+    PUSH 0x15e6be4e990f03ce4ea50b3b42df2eb5 PUSH 0xcb181d8f84965a3957add4fa95af01b2b665027efec01c7704b456be69c8b604
+    %bls381_horner_fp381(
+        0x5c129645e44cf1102a159f748c4a3fc,
+        0x5e673d81d7e86568d9ab0f5d396a7ce46ba1049b6579afb7866b1e715475224b)
+    %bls381_horner_fp381(
+        0x245a394ad1eca9b72fc00ae7be315dc,
+        0x757b3b080d4c158013e6632d3c40659cc6cf90ad1c232a6442d9d3f5db980133)
+    %bls381_horner_fp381(
+        0xb182cac101b9399d155096004f53f44,
+        0x7aa7b12a3426b08ec02710e807b4633f06c851c1919211f20d4c04f00b971ef8)
+    %bls381_horner_fp381(
+        0x18b46a908f36f6deb918c143fed2edcc,
+        0x523559b8aaf0c2462e6bfe7f911f643249d9cdf41b44d606ce07c8a4d0074d8e)
+    %bls381_horner_fp381(
+        0x19713e47937cd1be0dfd0b8f1d43fb93,
+        0xcd2fcbcb6caf493fd1183e416389e61031bf3a5cce3fbafce813711ad011c132)
+    %bls381_horner_fp381(
+        0xe1bba7a1186bdb5223abde7ada14a23,
+        0xc42a0ca7915af6fe06985e7ed1e4d43b9b3f7055dd4eba6f2bafaaebca731c30)
+    %bls381_horner_fp381(
+        0x9fc4018bd96684be88c9e221e4da1bb,
+        0x8f3abd16679dc26c1e8b6e6a1f20cabe69d65201c78607a360370e577bdba587)
+    %bls381_horner_fp381(
+        0x987c8d5333ab86fde9926bd2ca6c674,
+        0x170a05bfe3bdd81ffd038da6c26c842642f64550fedfe935a15e4ca31870fb29)
+    %bls381_horner_fp381(
+        0x4ab0b9bcfac1bbcb2c977d027796b3c,
+        0xe75bb8ca2be184cb5231413c4d634f3747a87ac2460f415ec961f8855fe9d6f2)
+    %bls381_horner_fp381(
+        0x16603fca40634b6a2211e11db8f0a6a0,
+        0x74a7d0d4afadb7bd76505c3d3ad5544e203f6326c95a807299b23ab13633a5f0)
+    %bls381_horner_fp381(
+        0x8cc03fdefe0ff135caf4fe2a21529c4,
+        0x195536fbe3ce50b879833fd221351adc2ee7f8dc099040a841b6daecf2e8fedb)
+    %bls381_horner_fp381(
+        0x1f86376e8981c217898751ad8746757,
+        0xd42aa7b90eeb791c09e4a3ec03251cf9de405aba9ec61deca6355c77b0e5f4cb)
+    %bls381_horner_fp381(
+        0xcc786baa966e66f4a384c86a3b4994,
+        0x2552e2d658a31ce2c344be4b91400da7d26d521628b00523b8dfe240c72de1f6)
+    %bls381_horner_fp381(
+        0x134996a104ee5811d51036d776fb4683,
+        0x1223e96c254f383d0f906343eb67ad34d6c56711962fa8bfe097e75a2e41c696)
+    %bls381_horner_fp381(
+        0x90d97c81ba24ee0259d1f094980dcfa,
+        0x11ad138e48a869522b52af6c956543d3cd0c7aee9b3ba3c2be9845719707bb33)
+    // stack: y_num(x), x
+    %bls381_swap_fp381
+    // stack: x
+    PUSH 0 PUSH 1
+    %bls381_horner_fp381(
+        0x95fc13ab9e92ad4476d6e3eb3a56680,
+        0xf682b4ee96f7d03776df533978f31c1593174e4b4b7865002d6384d168ecdd0a)
+    %bls381_horner_fp381(
+        0xa10ecf6ada54f825e920b3dafc7a3cc,
+        0xe07f8d1d7161366b74100da67f39883503826692abba43704776ec3a79a1d641)
+    %bls381_horner_fp381(
+        0x14a7ac2a9d64a8b230b3f5b074cf0199,
+        0x6e7f63c21bca68a81996e1cdf9822c580fa5b9489d11e2d311f7d99bbdcc5a5e)
+    %bls381_horner_fp381(
+        0x772caacf16936190f3e0c63e0596721,
+        0x570f5799af53a1894e2e073062aede9cea73b3538f0de06cec2574496ee84a3a)
+    %bls381_horner_fp381(
+        0xe7355f8e4e667b955390f7f0506c6e9,
+        0x395735e9ce9cad4d0a43bcef24b8982f7400d24bc4228f11c02df9a29f6304a5)
+    %bls381_horner_fp381(
+        0x13a8e162022914a80a6f1d5f43e7a07d,
+        0xffdfc759a12062bb8d6b44e833b306da9bd29ba81f35781d539d395b3532a21e)
+    %bls381_horner_fp381(
+        0x3425581a58ae2fec83aafef7c40eb54,
+        0x5b08243f16b1655154cca8abc28d6fd04976d5243eecf5c4130de8938dc62cd8)
+    %bls381_horner_fp381(
+        0xb2962fe57a3225e8137e629bff2991f,
+        0x6f89416f5a718cd1fca64e00b11aceacd6a3d0967c94fedcfcc239ba5cb83e19)
+    %bls381_horner_fp381(
+        0x12561a5deb559c4348b4711298e53636,
+        0x7041e8ca0cf0800c0126c2588c48bf5713daa8846cb026e9e5c8276ec82b3bff)
+    %bls381_horner_fp381(
+        0x8ca8d548cff19ae18b2e62f4bd3fa6f,
+        0x1d5ef4ba35b48ba9c9588617fc8ac62b558d681be343df8993cf9fa40d21b1c)
+    // stack: x_den(x), x
+    %bls381_swap_fp381
+    // stack: x
+    PUSH 0x6e08c248e260e70bd1e962381edee3d PUSH 0x31d79d7e22c837bc23c0bf1bc24c6b68c24b1b80b64d391fa9c8ba2e8ba2d229
+    %bls381_horner_fp381(
+        0x10321da079ce07e272d8ec09d2565b0d,
+        0xfa7dccdde6787f96d50af36003b14866f69b771f8c285decca67df3f1605fb7b)
+    %bls381_horner_fp381(
+        0x169b1f8e1bcfa7c42e0c37515d138f22,
+        0xdd2ecb803a0c5c99676314baf4bb1b7fa3190b2edc0327797f241067be390c9e)
+    %bls381_horner_fp381(
+        0x80d3cf1f9a78fc47b90b33563be990d,
+        0xc43b756ce79f5574a2c596c928c5d1de4fa295f296b74e956d71986a8497e317)
+    %bls381_horner_fp381(
+        0x17b81e7701abdbe2e8743884d1117e53,
+        0x356de5ab275b4db1a682c62ef0f2753339b7c8f8c8f475af9ccb5618e3f0c88e)
+    %bls381_horner_fp381(
+        0xd6ed6553fe44d296a3726c38ae652bf,
+        0xb11586264f0f8ce19008e218f9c86b2a8da25128c1052ecaddd7f225a139ed84)
+    %bls381_horner_fp381(
+        0x1630c3250d7313ff01d1201bf7a74ab5,
+        0xdb3cb17dd952799b9ed3ab9097e68f90a0870d2dcae73d19cd13c1c66f652983)
+    %bls381_horner_fp381(
+        0xe99726a3199f4436642b4b3e4118e54,
+        0x99db995a1257fb3f086eeb65982fac18985a286f301e77c451154ce9ac8895d9)
+    %bls381_horner_fp381(
+        0x1778e7166fcc6db74e0609d307e55412,
+        0xd7f5e4656a8dbf25f1b33289f1b330835336e25ce3107193c5b388641d9b6861)
+    %bls381_horner_fp381(
+        0xd54005db97678ec1d1048c5d10a9a1b,
+        0xce032473295983e56878e501ec68e25c958c3e3d2a09729fe0179f9dac9edcb0)
+    %bls381_horner_fp381(
+        0x17294ed3e943ab2f0588bab22147a81c,
+        0x7c17e75b2f6a8417f565e33c70d1e86b4838f2a6f318c356e834eef1b3cb83bb)
+    %bls381_horner_fp381(
+        0x11a05f2b1e833340b809101dd9981585,
+        0x6b303e88a2d7005ff2627b56cdb4e2c85610c2d5f2e62d6eaeac1662734649b7)
+    // stack: x_num(x), x
+    %stack (x_num: 2, x: 2) -> (x_num)
+    // stack: x_num, x_den, y_num, y_den, y, retdest
+    DUP4 DUP4 %is_zero_fp381
+    DUP9 DUP9 %is_zero_fp381
+    OR %jumpi(bls381_map_fp_to_g1_infinity)
+    %div_fp381
+    %stack (x: 2, y_num: 2, y_den: 2, y: 2) -> (y, y_num, y_den, x)
+    %mul_fp381
+    %div_fp381
+    %bls381_swap_fp381
+    // stack: x, y, retdest
+    PUSH 0xd201000000010001
+    %stack (h, p: 4) -> (p, h)
+    %bls381_g1_mul
+    // stack: p, retdest
+    %stack (p: 4, retdest) -> (retdest, p)
+    JUMP
+bls381_map_fp_to_g1_infinity:
+    // stack: x_num, x_den, y_num, y_den, y, retdest
+    %stack (unused: 10, retdest) -> (retdest, 0, 0, 0, 0)
+    JUMP
+
+// Maps u to a point of G2, stored at out. The SWU map keeps its point y at
+// offset 368 of the scratch segment and the values of the rational maps of the
+// isogeny at offsets 372 to 387.
+global bls381_map_fp2_to_g2:
+    // stack: u, out, retdest
+    %bls381_sgn0_fp381_2
+    %stack (s, u: 4) -> (u, s)
+    %sqr_fp381_2
+    PUSH 0x1a0111ea397fe69a4b1ba7b6434bacd7 PUSH 0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaaa
+    PUSH 0x1a0111ea397fe69a4b1ba7b6434bacd7 PUSH 0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaa9
+    %mul_fp381_2
+    // stack: Z * u^2, sgn0(u), out, retdest
+    DUP4 DUP4 DUP4 DUP4
+    %sqr_fp381_2
+    DUP8 DUP8 DUP8 DUP8
+    %add_fp381_2
+    // stack: tv1 = Z^2 * u^4 + Z * u^2, Z * u^2, sgn0(u), out, retdest
+    DUP4 DUP4 DUP4 DUP4 %is_zero_fp381_2
+    %jumpi(bls381_map_fp2_to_g2_exceptional)
+    // x1 = -B / A * (1 + 1 / tv1)
+    DUP4 DUP4 DUP4 DUP4
+    PUSH 0 PUSH 0 PUSH 0 PUSH 1
+    %add_fp381_2
+    %div_fp381_2
+    PUSH 0x11c4ff711ec210c74cec7f673684c72c PUSH 0xc8eb1e458445999c64615cbacab4a8324828bbbad70a777747a173333332f8e8
+    PUSH 0x83c12791abdd5d2fe2f284f0cc6e5aa PUSH 0x9b8c2d3f6f3f792302cf75e62bfc4df1d6834443da498888725d8cccccccb1c3
+    %mul_fp381_2
+    %jump(bls381_map_fp2_to_g2_x1)
+bls381_map_fp2_to_g2_exceptional:
+    // x1 = B / (Z * A)
+    %pop4
+    PUSH 0x15103a07f641331bb298f5ed3ba1230a PUSH 0xa0bcc9f87d923077324df24a0f7ffa93045d3d6f94c17ae10efa11eb851e7336
+    PUSH 0x1a59d4b6bbf912a32d63b43028e2dee PUSH 0xebe8d5d97ca64b6d66f64ac7a265a9305e1a40da5edb81b4e3ac4f5c28f5bd27
+bls381_map_fp2_to_g2_x1:
+    // stack: x1, Z * u^2, sgn0(u), out, retdest
+    DUP4 DUP4 DUP4 DUP4 %bls381_iso_g2_rhs
+    %sqrt_fp381_2
+    %jumpi(bls381_map_fp2_to_g2_square)
+    // g(x1) is not a square, so g(x2) must be one, for x2 = Z * u^2 * x1.
+    %pop4
+    %mul_fp381_2
+    DUP4 DUP4 DUP4 DUP4 %bls381_iso_g2_rhs
+    %sqrt_fp381_2
+    %assert_nonzero
+    %jump(bls381_map_fp2_to_g2_sign)
+bls381_map_fp2_to_g2_square:
+    // stack: y, x1, Z * u^2, sgn0(u), out, retdest
+    %stack (y: 4, x: 4, zu2: 4) -> (y, x)
+bls381_map_fp2_to_g2_sign:
+    // stack: y, x, sgn0(u), out, retdest
+    %bls381_sgn0_fp381_2
+    DUP10 XOR
+    ISZERO %jumpi(bls381_map_fp2_to_g2_iso)
+    %neg_fp381_2
+bls381_map_fp2_to_g2_iso:
+    // stack: y, x, sgn0(u), out, retdest
+    %bls381_address(368) %store_fp381_2
+    // stack: x, sgn0(u), out, retdest
+    // Evaluate the rational maps of the isogeny.
+    // stack: x
+    PUSH 0 PUSH 0
+    PUSH 0x171d6541fa38ccfaed6dea691f5fb614 PUSH 0xcb14b4e7f4e810aa22d6108f142b85757098e38d0f671c7188e2aaaaaaaa5ed1
+    %bls381_horner_fp381_2(
+        0x8ab05f8bdd54cde190937e76bc3e447,
+        0xcc27c3d6fbd7063fcd104635a790520c0a395554e5c6aaaa9354ffffffffe38d,
+        0x11560bf17baa99bc32126fced787c88f,
+        0x984f87adf7ae0c7f9a208c6b4f20a4181472aaa9cb8d555526a9ffffffffc71e)
+    %bls381_horner_fp381_2(
+        0x11560bf17baa99bc32126fced787c88f,
+        0x984f87adf7ae0c7f9a208c6b4f20a4181472aaa9cb8d555526a9ffffffffc71a,
+        0,
+        0)
+    %bls381_horner_fp381_2(
+        0x5c759507e8e333ebb5b7a9a47d7ed85,
+        0x32c52d39fd3a042a88b58423c50ae15d5c2638e343d9c71c6238aaaaaaaa97d6,
+        0x5c759507e8e333ebb5b7a9a47d7ed85,
+        0x32c52d39fd3a042a88b58423c50ae15d5c2638e343d9c71c6238aaaaaaaa97d6)
+    // stack: x_num(x), x
+    %bls381_address(372) %store_fp381_2
+    // stack: x
+    PUSH 0 PUSH 0
+    PUSH 0 PUSH 1
+    %bls381_horner_fp381_2(
+        0x1a0111ea397fe69a4b1ba7b6434bacd7,
+        0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaa9f,
+        0,
+        12)
+    %bls381_horner_fp381_2(
+        0x1a0111ea397fe69a4b1ba7b6434bacd7,
+        0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaa63,
+        0,
+        0)
+    // stack: x_den(x), x
+    %bls381_address(376) %store_fp381_2
+    // stack: x
+    PUSH 0 PUSH 0
+    PUSH 0x124c9ad43b6cf79bfbf7043de3811ad0 PUSH 0x761b0f37a1e26286b0e977c69aa274524e79097a56dc4bd9e1b371c71c718b10
+    %bls381_horner_fp381_2(
+        0x8ab05f8bdd54cde190937e76bc3e447,
+        0xcc27c3d6fbd7063fcd104635a790520c0a395554e5c6aaaa9354ffffffffe38f,
+        0x11560bf17baa99bc32126fced787c88f,
+        0x984f87adf7ae0c7f9a208c6b4f20a4181472aaa9cb8d555526a9ffffffffc71c)
+    %bls381_horner_fp381_2(
+        0x5c759507e8e333ebb5b7a9a47d7ed85,
+        0x32c52d39fd3a042a88b58423c50ae15d5c2638e343d9c71c6238aaaaaaaa97be,
+        0,
+        0)
+    %bls381_horner_fp381_2(
+        0x1530477c7ab4113b59a4c18b076d1193,
+        0xf7da5d4a07f649bf54439d87d27e500fc8c25ebf8c92f6812cfc71c71c6d706,
+        0x1530477c7ab4113b59a4c18b076d1193,
+        0xf7da5d4a07f649bf54439d87d27e500fc8c25ebf8c92f6812cfc71c71c6d706)
+    // stack: y_num(x), x
+    %bls381_address(380) %store_fp381_2
+    // stack: x
+    PUSH 0 PUSH 0
+    PUSH 0 PUSH 1
+    %bls381_horner_fp381_2(
+        0x1a0111ea397fe69a4b1ba7b6434bacd7,
+        0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaa99,
+        0,
+        18)
+    %bls381_horner_fp381_2(
+        0x1a0111ea397fe69a4b1ba7b6434bacd7,
+        0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffa9d3,
+        0,
+        0)
+    %bls381_horner_fp381_2(
+        0x1a0111ea397fe69a4b1ba7b6434bacd7,
+        0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffa8fb,
+        0x1a0111ea397fe69a4b1ba7b6434bacd7,
+        0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffa8fb)
+    // stack: y_den(x), x
+    %bls381_address(384) %store_fp381_2
+    // stack: x, sgn0(u), out, retdest
+    %pop4 POP
+    // stack: out, retdest
+    %bls381_address(376) %load_fp381_2 %is_zero_fp381_2
+    %bls381_address(384) %load_fp381_2 %is_zero_fp381_2
+    OR %jumpi(bls381_map_fp2_to_g2_infinity)
+    %bls381_address(376) %load_fp381_2
+    %bls381_address(372) %load_fp381_2
+    %div_fp381_2
+    DUP5 %store_fp381_2
+    %bls381_address(384) %load_fp381_2
+    %bls381_address(380) %load_fp381_2
+    %bls381_address(368) %load_fp381_2
+    %mul_fp381_2
+    %div_fp381_2
+    DUP5 %add_const(4) %store_fp381_2
+    // stack: out, retdest
+    %jump(bls381_g2_clear_cofactor)
+bls381_map_fp2_to_g2_infinity:
+    // stack: out, retdest
+    %bls381_g2_set_infinity
+    JUMP
@@ -0,0 +1,112 @@
+// The optimal ate pairing on BLS12-381, for the pairing check precompile.
+//
+// The Miller loops of all pairs are multiplied into an accumulator at offset
+// 248 of the BLS12-381 scratch segment, whose final exponentiation is then
+// compared with one. A Miller loop keeps the running multiple T of Q at offset
+// 96, the line function at offset 200 and its result at offset 224.
+
+// Evaluates at P the line of slope lambda through the previous value of T,
+// and multiplies it into the Miller loop result. For both doubling and
+// addition, lambda * x_T - y_T = lambda * x_T' + y_T' where T' is the updated
+// T, so the line is (lambda * x_T' + y_T') - lambda * x_P * w^2 + y_P * w^3,
+// of which only the first two coefficients change from step to step.
+%macro bls381_miller_line
+    // stack: lambda
+    %bls381_address(100) %load_fp381_2
+    %bls381_address(96) %load_fp381_2
+    DUP12 DUP12 DUP12 DUP12
+    %mul_fp381_2
+    %add_fp381_2
+    // stack: lambda * x_T' + y_T', lambda
+    %bls381_address(200) %store_fp381_2
+    %bls381_address(104) %load_fp381
+    // stack: x_P, lambda
+    %stack (x: 2, lambda: 4) -> (lambda, x)
+    %mul_fp381_2_by_fp381
+    %neg_fp381_2
+    // stack: -lambda * x_P
+    %bls381_address(204) %store_fp381_2
+    %bls381_fp12_mul_by_line(224, 200, 224)
+%endmacro
+
+// Multiplies the accumulator by the Miller loop of the pair (P, Q), for finite
+// points P at offset 104 and Q at offset 64. As the curve parameter u is
+// negative, this is the conjugate of the Miller loop over -u.
+global bls381_miller_loop:
+    // stack: retdest
+    %bls381_address(224) %bls381_fp12_set_one
+    %bls381_address(200) %bls381_fp12_set_zero
+    %bls381_address(106) %load_fp381
+    %bls381_address(216) %store_fp381
+    %bls381_address(96) %bls381_address(64)
+    %bls381_g2_copy
+    // Loop over the bits of -u following the leading one.
+    PUSH 63
+bls381_miller_loop_step:
+    // stack: i, retdest
+    %bls381_fp12_sqr(224, 224)
+    PUSH bls381_miller_loop_doubled %bls381_address(96) DUP1
+    // stack: T, T, bls381_miller_loop_doubled, i, retdest
+    %jump(bls381_g2_double)
+bls381_miller_loop_doubled:
+    // stack: lambda, i, retdest
+    %bls381_miller_line
+    // stack: i, retdest
+    PUSH 0xd201000000010000 DUP2 %decrement SHR
+    %mod_const(2) ISZERO %jumpi(bls381_miller_loop_next)
+    PUSH bls381_miller_loop_added %bls381_address(96) %bls381_address(64) DUP2
+    // stack: T, Q, T, bls381_miller_loop_added, i, retdest
+    %jump(bls381_g2_add)
+bls381_miller_loop_added:
+    // stack: lambda, i, retdest
+    %bls381_miller_line
+bls381_miller_loop_next:
+    // stack: i, retdest
+    %decrement
+    DUP1 %jumpi(bls381_miller_loop_step)
+    POP
+    %bls381_fp12_conj(224, 224)
+    %bls381_fp12_mul(248, 224, 248)
+    // stack: retdest
+    JUMP
+
+// Returns whether the final exponentiation f^((p^12 - 1) / r) of the
+// accumulator f is one. As 3 does not divide r, it is equivalent to check
+// f^(3 * (p^12 - 1) / r), whose exponent decomposes as
+// (p^6 - 1) * (p^2 + 1) * ((u - 1)^2 * (u + p) * (u^2 + p^2 - 1) + 3)
+// following Hayashida, Hayasaka and Teruya. Offsets 272, 296, 320 and 344 hold
+// the intermediate values m, a, b and c, and a temporary.
+global bls381_final_exp_is_one:
+    // stack: retdest
+    // m = f^((p^6 - 1) * (p^2 + 1)), in the cyclotomic subgroup
+    %bls381_fp12_inv(248, 344)
+    %bls381_fp12_conj(248, 272)
+    %bls381_fp12_mul(272, 344, 272)
+    %bls381_fp12_frob2(272, 344)
+    %bls381_fp12_mul(344, 272, 272)
+    // a = m^((u - 1)^2)
+    %bls381_fp12_exp_by_x(272, 296)
+    %bls381_fp12_conj(272, 344)
+    %bls381_fp12_mul(296, 344, 296)
+    %bls381_fp12_exp_by_x(296, 344)
+    %bls381_fp12_conj(296, 320)
+    %bls381_fp12_mul(344, 320, 296)
+    // b = a^(u + p)
+    %bls381_fp12_exp_by_x(296, 344)
+    %bls381_fp12_frob1(296, 320)
+    %bls381_fp12_mul(344, 320, 320)
+    // c = b^(u^2 + p^2 - 1)
+    %bls381_fp12_exp_by_x(320, 344)
+    %bls381_fp12_exp_by_x(344, 296)
+    %bls381_fp12_frob2(320, 344)
+    %bls381_fp12_mul(296, 344, 296)
+    %bls381_fp12_conj(320, 344)
+    %bls381_fp12_mul(296, 344, 296)
+    // c * m^3
+    %bls381_fp12_mul(272, 272, 344)
+    %bls381_fp12_mul(344, 272, 344)
+    %bls381_fp12_mul(296, 344, 296)
+    %bls381_address(296) %bls381_fp12_is_one
+    // stack: is_one, retdest
+    SWAP1
+    JUMP
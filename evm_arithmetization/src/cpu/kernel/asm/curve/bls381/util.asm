@@ -1,43 +1,232 @@
+// Arithmetic over the BLS12-381 base field Fp and its quadratic extension Fp2.
+//
+// An Fp element x takes up two stack terms x0, x1, with x0 on top, such that
+// x = x1 * 2^256 + x0. All the operations below expect canonical inputs, i.e.
+// x < p, and produce canonical outputs.
+//
+// Additions and subtractions are computed directly. Multiplications are given
+// by the prover together with their quotient by p, and checked in the kernel
+// with a CRT argument (see `mul_fp381`). Quotients and square roots are given
+// by the prover and checked with a multiplication.
+//
+// An Fp2 element x = x_re + x_im * i takes up four stack terms x_re, x_im, with
+// x_re on top.
+
+%macro bls381_p_lo
+    PUSH 0x64774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab
+%endmacro
+
+%macro bls381_p_hi
+    PUSH 0x1a0111ea397fe69a4b1ba7b6434bacd7
+%endmacro
+
+// Reduces x < 2p to x mod p.
+%macro reduce_once_fp381
+    // stack: x0, x1
+    %bls381_p_lo DUP2 SUB
+    // stack: y0 = x0 - p0, x0, x1
+    %bls381_p_lo DUP3 LT
+    // stack: borrow, y0, x0, x1
+    %bls381_p_hi ADD DUP4 SUB
+    // stack: y1 = x1 - p1 - borrow, y0, x0, x1
+    DUP1 %shr_const(255)
+    // stack: x < p, y1, y0, x0, x1
+    %jumpi(%%keep)
+    %stack (y1, y0, x0, x1) -> (y0, y1)
+    %jump(%%done)
+%%keep:
+    // stack: y1, y0, x0, x1
+    %pop2
+%%done:
+    // stack: x mod p
+%endmacro
+
+// Returns whether x < p, leaving x on the stack.
+%macro is_canonical_fp381
+    // stack: x0, x1
+    %bls381_p_lo DUP2 LT
+    // stack: x0 < p0, x0, x1
+    %bls381_p_hi DUP4 EQ MUL
+    // stack: x1 == p1 && x0 < p0, x0, x1
+    %bls381_p_hi DUP4 LT ADD
+    // stack: x < p, x0, x1
+%endmacro
+
+// Checks that x < p, leaving x on the stack.
+%macro check_fp381
+    // stack: x0, x1
+    %is_canonical_fp381
+    %assert_nonzero
+    // stack: x0, x1
+%endmacro
+
 %macro add_fp381
-    // stack:         x0, x1, y0, y1
-    PROVER_INPUT(sf::bls381_base::add_hi)
-    // stack:     z1, x0, x1, y0, y1
-    SWAP4
-    // stack:     y1, x0, x1, y0, z1
-    PROVER_INPUT(sf::bls381_base::add_lo)
-    // stack: z0, y1, x0, x1, y0, z1
-    SWAP4
-    // stack: y0, y1, x0, x1, z0, z1
-    %pop4
-    // stack:                 z0, z1
+    // stack: x0, x1, y0, y1
+    DUP3 DUP2 ADD
+    // stack: z0, x0, x1, y0, y1
+    DUP2 DUP2 LT
+    // stack: carry, z0, x0, x1, y0, y1
+    DUP4 ADD DUP6 ADD
+    // stack: z1, z0, x0, x1, y0, y1
+    %stack (z1, z0, x0, x1, y0, y1) -> (z0, z1)
+    // stack: z0, z1
+    %reduce_once_fp381
+    // stack: z0, z1
 %endmacro
 
 %macro sub_fp381
-    // stack:         x0, x1, y0, y1
-    PROVER_INPUT(sf::bls381_base::sub_hi)
-    // stack:     z1, x0, x1, y0, y1
-    SWAP4
-    // stack:     y1, x0, x1, y0, z1
-    PROVER_INPUT(sf::bls381_base::sub_lo)
-    // stack: z0, y1, x0, x1, y0, z1
-    SWAP4
-    // stack: y0, y1, x0, x1, z0, z1
-    %pop4
-    // stack:                 z0, z1
+    // stack: x0, x1, y0, y1
+    DUP3 DUP2 SUB
+    // stack: z0, x0, x1, y0, y1
+    DUP4 DUP3 LT
+    // stack: borrow, z0, x0, x1, y0, y1
+    DUP6 ADD DUP4 SUB
+    // stack: z1, z0, x0, x1, y0, y1
+    %stack (z1, z0, x0, x1, y0, y1) -> (z1, z0, z1)
+    %shr_const(255)
+    // stack: x < y, z0, z1
+    ISZERO %jumpi(%%done)
+    // stack: z0, z1
+    %bls381_p_lo ADD
+    // stack: z0 + p0, z1
+    %bls381_p_lo DUP2 LT
+    // stack: carry, z0 + p0, z1
+    %bls381_p_hi ADD
+    // stack: p1 + carry, z0 + p0, z1
+    SWAP1 SWAP2 ADD SWAP1
+%%done:
+    // stack: z0, z1
+%endmacro
+
+%macro neg_fp381
+    // stack: x
+    %stack (x: 2) -> (0, 0, x)
+    %sub_fp381
+    // stack: -x
 %endmacro
 
 %macro mul_fp381
-    // stack:         x0, x1, y0, y1
+    // stack: x, y
+    %stack (x: 2, y: 2) -> (x, y, %%after)
+    %jump(mul_fp381)
+%%after:
+    // stack: x * y
+%endmacro
+
+// Multiplies x and y. The prover gives z = x * y mod p and q such that
+// x * y = q * p + z over the integers. Both sides are smaller than 2^763 when
+// q < 2^381 and z < p, so the equality is checked modulo 2^256, 2^256 - 1 and
+// 2^256 - 3, whose product is greater than 2^767.
+global mul_fp381:
+    // stack: x0, x1, y0, y1, retdest
     PROVER_INPUT(sf::bls381_base::mul_hi)
-    // stack:     z1, x0, x1, y0, y1
+    // stack: z1, x0, x1, y0, y1, retdest
     SWAP4
-    // stack:     y1, x0, x1, y0, z1
+    // stack: y1, x0, x1, y0, z1, retdest
     PROVER_INPUT(sf::bls381_base::mul_lo)
+    // stack: z0, y1, x0, x1, y0, z1, retdest
+    SWAP4
+    // stack: y0, y1, x0, x1, z0, z1, retdest
+    PROVER_INPUT(sf::bls381_base::quo_hi)
+    // stack: q1, y0, y1, x0, x1, z0, z1, retdest
+    SWAP4
+    // stack: x1, y0, y1, x0, q1, z0, z1, retdest
+    PROVER_INPUT(sf::bls381_base::quo_lo)
+    // stack: q0, x1, y0, y1, x0, q1, z0, z1, retdest
+    SWAP4
+    // stack: x0, x1, y0, y1, q0, q1, z0, z1, retdest
+
+    // Range check the hints.
+    DUP6 %shr_const(125) %assert_zero
+    %stack (x: 2, y: 2, q: 2, z: 2) -> (z, x, y, q, z)
+    %check_fp381
+    %pop2
+    // stack: x0, x1, y0, y1, q0, q1, z0, z1, retdest
+
+    // Check the equality modulo 2^256, i.e. x0 * y0 == q0 * p0 + z0.
+    DUP3 DUP2 MUL
+    // stack: x0 * y0, x0, x1, y0, y1, q0, q1, z0, z1, retdest
+    %bls381_p_lo DUP7 MUL DUP9 ADD
+    // stack: q0 * p0 + z0, x0 * y0, x0, x1, y0, y1, q0, q1, z0, z1, retdest
+    %assert_eq
+
+    %mul_fp381_crt_check(0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff, 1, 0x64774b84f38512bf6730d2a0f6b0f62438ad11e8ead3e69a051aa7b6434b5782)
+    %mul_fp381_crt_check(0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd, 3, 0x64774b84f38512bf6730d2a0f6b0f6246caf35bd5dd3b3ce9b51f722c9e2b130)
+
+    %stack (x: 2, y: 2, q: 2, z: 2, retdest) -> (retdest, z)
+    JUMP
+
+// Checks that x * y == q * p + z modulo m, given c = 2^256 mod m and p mod m.
+// Each value a is first reduced as a1 * c + a0 mod m.
+%macro mul_fp381_crt_check(m, c, p_mod_m)
+    // stack: x0, x1, y0, y1, q0, q1, z0, z1
+    PUSH $m
+    PUSH $m PUSH $m PUSH $c DUP12 MULMOD DUP10 ADDMOD
+    // stack: z mod m, m, x0, x1, y0, y1, q0, q1, z0, z1
+    PUSH $m PUSH $p_mod_m
+    PUSH $m PUSH $m PUSH $c DUP13 MULMOD DUP11 ADDMOD
+    // stack: q mod m, p mod m, m, z mod m, m, x0, x1, y0, y1, q0, q1, z0, z1
+    MULMOD ADDMOD
+    // stack: q * p + z mod m, x0, x1, y0, y1, q0, q1, z0, z1
+    PUSH $m
+    PUSH $m PUSH $m PUSH $c DUP9 MULMOD DUP7 ADDMOD
+    // stack: y mod m, m, q * p + z mod m, x0, x1, y0, y1, q0, q1, z0, z1
+    PUSH $m PUSH $m PUSH $c DUP8 MULMOD DUP6 ADDMOD
+    // stack: x mod m, y mod m, m, q * p + z mod m, x0, x1, y0, y1, q0, q1, z0, z1
+    MULMOD
+    // stack: x * y mod m, q * p + z mod m, x0, x1, y0, y1, q0, q1, z0, z1
+    %assert_eq
+%endmacro
+
+// Divides x by a nonzero y.
+%macro div_fp381
+    // stack: x0, x1, y0, y1
+    PROVER_INPUT(sf::bls381_base::div_hi)
+    // stack: z1, x0, x1, y0, y1
+    SWAP4
+    // stack: y1, x0, x1, y0, z1
+    PROVER_INPUT(sf::bls381_base::div_lo)
     // stack: z0, y1, x0, x1, y0, z1
     SWAP4
     // stack: y0, y1, x0, x1, z0, z1
-    %pop4
-    // stack:                 z0, z1
+    DUP2 DUP2 %is_zero_fp381 %assert_zero
+    DUP6 DUP6 %check_fp381
+    // stack: z, y, x, z
+    %mul_fp381
+    %eq_fp381 %assert_nonzero
+    // stack: z
+%endmacro
+
+// Returns whether the prover gave a square root y of x, and y. An honest
+// prover always gives one when x is a square, so callers can only rely on a
+// positive answer.
+%macro sqrt_fp381
+    // stack: x0, x1
+    PROVER_INPUT(sf::bls381_base::sqrt_hi)
+    // stack: y1, x0, x1
+    PROVER_INPUT(sf::bls381_base::sqrt_lo)
+    // stack: y0, y1, x0, x1
+    %check_fp381
+    %stack (y: 2, x: 2) -> (y, y, x, y)
+    %mul_fp381
+    // stack: y^2, x, y
+    %eq_fp381
+    // stack: is_square, y
+%endmacro
+
+// Returns whether x == y.
+%macro eq_fp381
+    // stack: x0, x1, y0, y1
+    SWAP1 SWAP2 EQ
+    // stack: x0 == y0, x1, y1
+    SWAP2 EQ MUL
+    // stack: x == y
+%endmacro
+
+%macro is_zero_fp381
+    // stack: x0, x1
+    OR ISZERO
+    // stack: x == 0
 %endmacro
 
 %macro add_fp381_2
@@ -64,6 +253,116 @@
     // stack:             z_re, z_im
 %endmacro
 
+%macro neg_fp381_2
+    // stack: x
+    %stack (x: 4) -> (0, 0, 0, 0, x)
+    %sub_fp381_2
+    // stack: -x
+%endmacro
+
+%macro conj_fp381_2
+    // stack: x_re, x_im
+    %stack (x_re: 2, x_im: 2) -> (x_im, x_re)
+    %neg_fp381
+    %stack (x_im: 2, x_re: 2) -> (x_re, x_im)
+    // stack: x_re, -x_im
+%endmacro
+
+// Multiplies x by (1 + i), the non-residue defining Fp6 over Fp2.
+%macro mul_fp381_2_by_xi
+    // stack: x_re, x_im
+    DUP4 DUP4 DUP4 DUP4
+    %sub_fp381
+    // stack: x_re - x_im, x_re, x_im
+    %stack (z_re: 2, x: 4) -> (x, z_re)
+    %add_fp381
+    // stack: x_re + x_im, x_re - x_im
+    %stack (z_im: 2, z_re: 2) -> (z_re, z_im)
+    // stack: x_re - x_im, x_re + x_im
+%endmacro
+
+// Multiplies x by the Fp element c.
+%macro mul_fp381_2_by_fp381
+    // stack: x_re, x_im, c
+    %stack (x_re: 2, x_im: 2, c: 2) -> (x_im, c, x_re, c)
+    %mul_fp381
+    %stack (z_im: 2, x_re: 2, c: 2) -> (x_re, c, z_im)
+    %mul_fp381
+    // stack: z_re, z_im
+%endmacro
+
+%macro mul_fp381_2
+    // stack: x, y
+    %stack (x: 4, y: 4) -> (x, y, %%after)
+    %jump(mul_fp381_2)
+%%after:
+    // stack: x * y
+%endmacro
+
+%macro sqr_fp381_2
+    // stack: x
+    DUP4 DUP4 DUP4 DUP4
+    %mul_fp381_2
+    // stack: x^2
+%endmacro
+
+// Divides x by a nonzero y.
+%macro div_fp381_2
+    // stack: x, y
+    PROVER_INPUT(sf::bls381_base::div_2_im_hi)
+    PROVER_INPUT(sf::bls381_base::div_2_im_lo)
+    PROVER_INPUT(sf::bls381_base::div_2_re_hi)
+    PROVER_INPUT(sf::bls381_base::div_2_re_lo)
+    // stack: z, x, y
+    %check_fp381
+    DUP4 DUP4 %check_fp381 %pop2
+    DUP12 DUP12 DUP12 DUP12 %is_zero_fp381_2 %assert_zero
+    DUP12 DUP12 DUP12 DUP12
+    DUP8 DUP8 DUP8 DUP8
+    // stack: z, y, z, x, y
+    %mul_fp381_2
+    %stack (zy: 4, z: 4, x: 4, y: 4) -> (zy, x, z)
+    %eq_fp381_2 %assert_nonzero
+    // stack: z
+%endmacro
+
+// Returns whether the prover gave a square root y of x, and y. As for
+// `sqrt_fp381`, callers can only rely on a positive answer.
+%macro sqrt_fp381_2
+    // stack: x
+    PROVER_INPUT(sf::bls381_base::sqrt_2_im_hi)
+    PROVER_INPUT(sf::bls381_base::sqrt_2_im_lo)
+    PROVER_INPUT(sf::bls381_base::sqrt_2_re_hi)
+    PROVER_INPUT(sf::bls381_base::sqrt_2_re_lo)
+    // stack: y, x
+    %check_fp381
+    DUP4 DUP4 %check_fp381 %pop2
+    DUP4 DUP4 DUP4 DUP4
+    DUP4 DUP4 DUP4 DUP4
+    %mul_fp381_2
+    // stack: y^2, y, x
+    %stack (y2: 4, y: 4, x: 4) -> (y2, x, y)
+    %eq_fp381_2
+    // stack: is_square, y
+%endmacro
+
+// Returns whether x == y.
+%macro eq_fp381_2
+    // stack: x_re, x_im, y_re, y_im
+    %stack (x_re: 2, x_im: 2, y_re: 2, y_im: 2) -> (x_re, y_re, x_im, y_im)
+    %eq_fp381
+    %stack (b, x_im: 2, y_im: 2) -> (x_im, y_im, b)
+    %eq_fp381
+    MUL
+    // stack: x == y
+%endmacro
+
+%macro is_zero_fp381_2
+    // stack: x
+    OR OR OR ISZERO
+    // stack: x == 0
+%endmacro
+
 // note that {x,y}_{re,im} all take up two stack terms
 global mul_fp381_2:
     // stack:                          x_re, x_im, y_re, y_im, jumpdest
@@ -99,3 +398,50 @@ global mul_fp381_2:
     // stack:                                      z_re, z_im, jumpdest
     %stack (z_re: 2, z_im: 2, jumpdest) -> (jumpdest, z_re, z_im)
     JUMP
+
+// Returns the address of the given offset in the BLS12-381 scratch segment.
+%macro bls381_address(offset)
+    PUSH $offset
+    PUSH @SEGMENT_BLS12_381
+    GET_CONTEXT
+    %build_address
+    // stack: addr
+%endmacro
+
+// Loads the Fp element stored at addr, as two consecutive words in stack
+// order.
+%macro load_fp381
+    // stack: addr
+    DUP1 %increment MLOAD_GENERAL
+    SWAP1 MLOAD_GENERAL
+    // stack: x
+%endmacro
+
+// Stores the Fp element x at addr.
+%macro store_fp381
+    // stack: addr, x
+    DUP1 SWAP2 MSTORE_GENERAL
+    %increment SWAP1 MSTORE_GENERAL
+    // stack: (empty)
+%endmacro
+
+// Loads the Fp2 element stored at addr, as four consecutive words in stack
+// order.
+%macro load_fp381_2
+    // stack: addr
+    DUP1 %add_const(3) MLOAD_GENERAL SWAP1
+    DUP1 %add_const(2) MLOAD_GENERAL SWAP1
+    DUP1 %increment MLOAD_GENERAL SWAP1
+    MLOAD_GENERAL
+    // stack: x
+%endmacro
+
+// Stores the Fp2 element x at addr.
+%macro store_fp381_2
+    // stack: addr, x
+    DUP1 SWAP2 MSTORE_GENERAL %increment
+    DUP1 SWAP2 MSTORE_GENERAL %increment
+    DUP1 SWAP2 MSTORE_GENERAL %increment
+    SWAP1 MSTORE_GENERAL
+    // stack: (empty)
+%endmacro
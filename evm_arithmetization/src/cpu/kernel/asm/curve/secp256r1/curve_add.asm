@@ -0,0 +1,162 @@
+// #define N 0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff // P-256 base field order
+// The point at infinity is represented by (0,0), which is not on the curve since its `b` coefficient is nonzero.
+
+// P-256 elliptic curve addition.
+// Assumption: (x0,y0) and (x1,y1) are valid points.
+global p256_add_valid_points:
+    // stack: x0, y0, x1, y1, retdest
+
+    // Check if one of the points is the identity.
+    DUP2 DUP2 %ec_isidentity
+    // stack: (x0,y0)==(0,0), x0, y0, x1, y1, retdest
+    %jumpi(p256_add_first_zero)
+    DUP4 DUP4 %ec_isidentity
+    // stack: (x1,y1)==(0,0), x0, y0, x1, y1, retdest
+    %jumpi(p256_add_snd_zero)
+
+    // Check if both points have the same x-coordinate.
+    DUP3 DUP2 EQ
+    // stack: x0 == x1, x0, y0, x1, y1, retdest
+    %jumpi(p256_add_equal_first_coord)
+
+    // Standard affine addition formula.
+    // Compute lambda = (y0 - y1)/(x0 - x1)
+    %stack (x0, y0, x1, y1) -> (y0, y1, @P256_BASE, x0, y0, x1, y1)
+    SUBMOD
+    // stack: y0 - y1, x0, y0, x1, y1, retdest
+    PUSH @P256_BASE DUP5 DUP4
+    // stack: x0, x1, N, y0 - y1, x0, y0, x1, y1, retdest
+    SUBMOD
+    // stack: x0 - x1, y0 - y1, x0, y0, x1, y1, retdest
+    %moddiv_p256_base
+    // stack: lambda, x0, y0, x1, y1, retdest
+    %jump(p256_add_valid_points_with_lambda)
+
+// P-256 elliptic curve addition.
+// Assumption: (x0,y0) == (0,0)
+p256_add_first_zero:
+    // stack: x0, y0, x1, y1, retdest
+    %stack (x0, y0, x1, y1, retdest) -> (retdest, x1, y1)
+    JUMP
+
+// P-256 elliptic curve addition.
+// Assumption: (x1,y1) == (0,0)
+p256_add_snd_zero:
+    // stack: x0, y0, x1, y1, retdest
+    %stack (x0, y0, x1, y1, retdest) -> (retdest, x0, y0)
+    JUMP
+
+// P-256 elliptic curve addition.
+// Assumption: x0 == x1
+p256_add_equal_first_coord:
+    // stack: x0, y0, x1, y1, retdest
+    DUP4 DUP3 EQ
+    // stack: y0 == y1, x0, y0, x1, y1, retdest
+    %jumpi(p256_add_equal_points)
+
+    // Otherwise, one is the negation of the other so we can return (0,0).
+    %stack (x0, y0, x1, y1, retdest) -> (retdest, 0, 0)
+    JUMP
+
+// P-256 elliptic curve addition.
+// Assumption: x0 == x1 and y0 == y1
+p256_add_equal_points:
+    // stack: x0, y0, x1, y1, retdest
+    %stack (x0, y0, x1, y1) -> (x0, y0)
+    %jump(p256_double)
+
+// P-256 elliptic curve addition.
+// Assumption: lambda is the slope of the line through (x0,y0) and (x1,y1).
+p256_add_valid_points_with_lambda:
+    // stack: lambda, x0, y0, x1, y1, retdest
+
+    // Compute x2 = lambda^2 - x1 - x0
+    %stack (lambda, x0, y0, x1, y1) -> (lambda, lambda, @P256_BASE, x1, @P256_BASE, x0, @P256_BASE, lambda, x1, y1)
+    MULMOD
+    // stack: lambda^2, x1, N, x0, N, lambda, x1, y1, retdest
+    SUBMOD
+    // stack: lambda^2 - x1, x0, N, lambda, x1, y1, retdest
+    SUBMOD
+    // stack: x2, lambda, x1, y1, retdest
+
+    // Compute y2 = lambda*(x1 - x2) - y1
+    %stack (x2, lambda, x1, y1) -> (x1, x2, @P256_BASE, lambda, @P256_BASE, y1, @P256_BASE, x2)
+    SUBMOD
+    // stack: x1 - x2, lambda, N, y1, N, x2, retdest
+    MULMOD
+    // stack: lambda * (x1 - x2), y1, N, x2, retdest
+    SUBMOD
+    // stack: y2, x2, retdest
+
+    %stack (y2, x2, retdest) -> (retdest, x2, y2)
+    JUMP
+
+// P-256 elliptic curve doubling.
+// Assumption: (x,y) is a valid point.
+// Standard doubling formula, with the curve coefficient a = -3.
+global p256_double:
+    // stack: x, y, retdest
+    DUP2 DUP2 %ec_isidentity
+    // stack: (x,y)==(0,0), x, y, retdest
+    %jumpi(ec_double_retself)
+
+    // Compute lambda = 3/2 * (x^2 - 1) / y
+    %stack (x, y) -> (x, x, @P256_BASE, 1, @P256_BASE, @P256_BASE, x, y, x, y)
+    MULMOD
+    // stack: x^2, 1, N, N, x, y, x, y, retdest
+    SUBMOD
+    // stack: x^2 - 1, N, x, y, x, y, retdest
+    PUSH 0x7fffffff80000000800000000000000000000000800000000000000000000001 // 3/2 in the base field
+    MULMOD
+    // stack: 3/2 * (x^2 - 1), x, y, x, y, retdest
+    DUP3
+    %moddiv_p256_base
+    // stack: lambda, x, y, x, y, retdest
+    %jump(p256_add_valid_points_with_lambda)
+
+// Check if (x,y) is a valid curve point, i.e. y^2 == x^3 - 3x + b with x < N and y < N.
+// Note that this excludes the point at infinity.
+%macro p256_check
+    // stack: x, y
+    %stack (x, y) -> (x, x, @P256_BASE, 3, @P256_BASE, x, @P256_BASE, @P256_B, @P256_BASE, x, y)
+    MULMOD
+    // stack: x^2, 3, N, x, N, b, N, x, y
+    SUBMOD
+    // stack: x^2 - 3, x, N, b, N, x, y
+    MULMOD
+    // stack: x^3 - 3x, b, N, x, y
+    ADDMOD
+    // stack: x^3 - 3x + b, x, y
+    %stack (rhs, x, y) -> (y, y, @P256_BASE, rhs, x, y)
+    MULMOD
+    // stack: y^2, x^3 - 3x + b, x, y
+    EQ
+    // stack: is_on_curve, x, y
+    PUSH @P256_BASE DUP3 LT MUL
+    // stack: is_on_curve & (x < N), x, y
+    PUSH @P256_BASE DUP4 LT MUL
+    // stack: is_on_curve & (x < N) & (y < N), x, y
+    %stack (is_valid, x, y) -> (is_valid)
+%endmacro
+
+// Returns y * (x^-1) where the inverse is taken modulo N
+%macro moddiv_p256_base
+    // stack: x, y
+    %inverse_p256_base
+    // stack: x^-1, y
+    %stack (inv, y) -> (inv, y, @P256_BASE)
+    MULMOD
+%endmacro
+
+// Non-deterministically provide the inverse modulo N.
+%macro inverse_p256_base
+    // stack: x
+    PROVER_INPUT(ff::secp256r1_base::inverse)
+    // stack: x^-1, x
+    %stack (inv, x) -> (inv, x, @P256_BASE, inv)
+    // stack: x^-1, x, N, x^-1
+    MULMOD
+    // stack: x^-1 * x, x^-1
+    %assert_eq_const(1)
+    // stack: x^-1
+%endmacro
@@ -0,0 +1,194 @@
+// ECDSA signature verification over the P-256 curve, as specified by RIP-7212.
+// Returns 1 if `(r, s)` is a valid signature of the message hash `hash` for the public key `(Qx, Qy)`, and 0 otherwise.
+// Pseudo-code:
+// if not (0 < r < n and 0 < s < n and is_on_curve(Qx, Qy)):
+//     return 0
+// let s_inv = s.inverse();
+// let u1 = hash * s_inv;
+// let u2 = r * s_inv;
+// let R = u1*GENERATOR + u2*Q;
+// return R.x % n == r
+global p256_ecdsa_verify:
+    // stack: hash, r, s, Qx, Qy, retdest
+
+    // Check if inputs are valid.
+    %p256_ecdsa_verify_input_check
+    // stack: isValid(r, s, Qx, Qy), hash, r, s, Qx, Qy, retdest
+    %jumpi(p256_ecdsa_verify_valid_input)
+    // stack: hash, r, s, Qx, Qy, retdest
+    %stack (hash, r, s, Qx, Qy, retdest) -> (retdest, 0)
+    JUMP
+
+p256_ecdsa_verify_valid_input:
+    // stack: hash, r, s, Qx, Qy, retdest
+
+    // Compute u1 = hash * s^(-1) and u2 = r * s^(-1).
+    DUP3
+    %inverse_p256_scalar
+    // stack: s^(-1), hash, r, s, Qx, Qy, retdest
+    %stack (sinv, hash, r, s) -> (sinv, hash, sinv, r, r)
+    %mulmodn_p256_scalar
+    // stack: u1, s^(-1), r, r, Qx, Qy, retdest
+    %stack (u1, sinv, r) -> (sinv, r, u1)
+    %mulmodn_p256_scalar
+    // stack: u2, u1, r, Qx, Qy, retdest
+    %stack (u2, u1, r, Qx, Qy) -> (u1, u2, Qx, Qy, p256_ecdsa_verify_after_msm, r)
+    %jump(p256_msm)
+p256_ecdsa_verify_after_msm:
+    // stack: Rx, Ry, r, retdest
+    // If R is the point at infinity, we have Rx = 0 != r and the check below fails as expected.
+    %stack (Rx, Ry, r, retdest) -> (Rx, @P256_SCALAR, r, retdest)
+    MOD
+    // stack: Rx % n, r, retdest
+    EQ
+    // stack: Rx % n == r, retdest
+    SWAP1
+    JUMP
+
+// Computes `a * G + b * Q`, where `G` is the P-256 generator and `Q` is a point on the curve.
+// P-256 has no efficient endomorphism to apply GLV with, so the full 256-bit scalars are expanded
+// in wNAF with w=5 and processed simultaneously, as in `bn_msm`.
+// The BN254 wNAF segments are reused as scratch space for the expansions of `a` and `b`.
+global p256_msm:
+    // stack: a, b, Qx, Qy, retdest
+    %stack (a) -> (@P256_SCALAR, @SEGMENT_BN_WNAF_A, a, 256, p256_msm_after_wnaf_a)
+    %jump(wnaf_with_top)
+p256_msm_after_wnaf_a:
+    // stack: b, Qx, Qy, retdest
+    %stack (b) -> (@P256_SCALAR, @SEGMENT_BN_WNAF_B, b, 256, p256_msm_after_wnaf_b)
+    %jump(wnaf_with_top)
+p256_msm_after_wnaf_b:
+    // stack: Qx, Qy, retdest
+    %stack (Qx, Qy) -> (@P256_GX, @P256_GY, 0, p256_msm_after_precompute_g, Qx, Qy)
+    %jump(p256_precompute_table)
+p256_msm_after_precompute_g:
+    // stack: Qx, Qy, retdest
+    %stack (Qx, Qy) -> (Qx, Qy, 32, p256_msm_after_precompute_q)
+    %jump(p256_precompute_table)
+p256_msm_after_precompute_q:
+    // stack: retdest
+    PUSH 0 PUSH 0 PUSH 0
+p256_msm_loop:
+    // stack: accx, accy, i, retdest
+    DUP3 %mload_current(@SEGMENT_BN_WNAF_A)
+    // stack: w, accx, accy, i, retdest
+    DUP1 %jumpi(p256_msm_loop_add_a_nonzero)
+    POP
+p256_msm_loop_add_b:
+    // stack: accx, accy, i, retdest
+    DUP3 %mload_current(@SEGMENT_BN_WNAF_B)
+    // stack: w, accx, accy, i, retdest
+    DUP1 %jumpi(p256_msm_loop_add_b_nonzero)
+    POP
+p256_msm_loop_contd:
+    %stack (accx, accy, i, retdest) -> (i, i, accx, accy, retdest)
+    %eq_const(256) %jumpi(p256_msm_end)
+    %increment
+    // stack: i+1, accx, accy, retdest
+    %stack (i, accx, accy, retdest) -> (accx, accy, p256_msm_loop, i, retdest)
+    %jump(p256_double)
+
+p256_msm_end:
+    %stack (i, accx, accy, retdest) -> (retdest, accx, accy)
+    JUMP
+
+p256_msm_loop_add_a_nonzero:
+    %stack (w, accx, accy, i, retdest) -> (w, accx, accy, p256_msm_loop_add_b, i, retdest)
+    %p256_mload_point(0)
+    // stack: px, py, accx, accy, p256_msm_loop_add_b, i, retdest
+    %jump(p256_add_valid_points)
+
+p256_msm_loop_add_b_nonzero:
+    %stack (w, accx, accy, i, retdest) -> (w, accx, accy, p256_msm_loop_contd, i, retdest)
+    %p256_mload_point(32)
+    // stack: px, py, accx, accy, p256_msm_loop_contd, i, retdest
+    %jump(p256_add_valid_points)
+
+// Loads the point of the table starting at `base` corresponding to the wNAF digit `w`.
+%macro p256_mload_point(base)
+    // stack: w
+    %add_const($base)
+    DUP1
+    %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: Py, base+w
+    SWAP1 %decrement %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: Px, Py
+%endmacro
+
+// Precompute a table of multiples of the P-256 point `P = (Px, Py)`, in the same layout as `bn_precompute_table`.
+// Let `(Pxi, Pyi) = i * P`, then store in the `SEGMENT_ECDSA_TABLE` segment of memory the values
+// `base+i-1 => Pxi`, `base+i => Pyi if i < 16 else -Py(32-i)` for `i in range(1, 32, 2)`.
+p256_precompute_table:
+    // stack: Px, Py, base, retdest
+    PUSH p256_precompute_table_contd DUP3 DUP3
+    %jump(p256_double)
+p256_precompute_table_contd:
+    // stack: Px2, Py2, Px, Py, base, retdest
+    PUSH 1
+p256_precompute_table_loop:
+    // stack: i, Px2, Py2, Px, Py, base, retdest
+    DUP6 DUP2 ADD
+    %stack (bi, i, Px2, Py2, Px, Py) -> (bi, Py, bi, Px, i, Px2, Py2, Px, Py)
+    %mstore_current(@SEGMENT_ECDSA_TABLE)
+    %decrement %mstore_current(@SEGMENT_ECDSA_TABLE)
+    // stack: i, Px2, Py2, Px, Py, base, retdest
+    DUP1 DUP7 %add_const(32) SUB
+    // stack: base+32-i, i, Px2, Py2, Px, Py, base, retdest
+    DUP6 PUSH @P256_BASE SUB
+    %stack (negy, bi, i, Px2, Py2, Px, Py) -> (bi, negy, bi, Px, i, Px2, Py2, Px, Py)
+    %mstore_current(@SEGMENT_ECDSA_TABLE)
+    %decrement %mstore_current(@SEGMENT_ECDSA_TABLE)
+    // stack: i, Px2, Py2, Px, Py, base, retdest
+    %add_const(2)
+    // stack: i+2, Px2, Py2, Px, Py, base, retdest
+    DUP1 PUSH 16 LT %jumpi(p256_precompute_table_end)
+    %stack (i, Px2, Py2, Px, Py) -> (Px, Py, Px2, Py2, p256_precompute_table_loop_contd, i, Px2, Py2)
+    %jump(p256_add_valid_points)
+p256_precompute_table_loop_contd:
+    %stack (Px, Py, i, Px2, Py2) -> (i, Px2, Py2, Px, Py)
+    %jump(p256_precompute_table_loop)
+
+p256_precompute_table_end:
+    // stack: i, Px2, Py2, Px, Py, base, retdest
+    %pop6 JUMP
+
+// Check if r, s, Qx and Qy are in correct form.
+// Returns 0 < r < n & 0 < s < n & isValid(Qx, Qy).
+%macro p256_ecdsa_verify_input_check
+    // stack: hash, r, s, Qx, Qy
+    DUP5 DUP5 %p256_check
+    // stack: isValid(Qx, Qy), hash, r, s, Qx, Qy
+    DUP3 %p256_is_valid_scalar MUL // cheaper than AND
+    // stack: isValid(Qx, Qy) & isValid(r), hash, r, s, Qx, Qy
+    DUP4 %p256_is_valid_scalar MUL // cheaper than AND
+    // stack: isValid(r, s, Qx, Qy), hash, r, s, Qx, Qy
+%endmacro
+
+// Returns 0 < x < n.
+%macro p256_is_valid_scalar
+    // stack: x
+    DUP1 ISZERO ISZERO
+    // stack: x != 0, x
+    SWAP1 %lt_const(@P256_SCALAR)
+    // stack: x < n, x != 0
+    MUL // cheaper than AND
+%endmacro
+
+%macro mulmodn_p256_scalar
+    // stack: x, y
+    %stack (x, y) -> (x, y, @P256_SCALAR)
+    MULMOD
+%endmacro
+
+// Non-deterministically provide the inverse modulo n.
+%macro inverse_p256_scalar
+    // stack: x
+    PROVER_INPUT(ff::secp256r1_scalar::inverse)
+    // stack: x^-1, x
+    %stack (inv, x) -> (inv, x, @P256_SCALAR, inv)
+    // stack: x^-1, x, n, x^-1
+    MULMOD
+    // stack: x^-1 * x, x^-1
+    %assert_eq_const(1)
+    // stack: x^-1
+%endmacro
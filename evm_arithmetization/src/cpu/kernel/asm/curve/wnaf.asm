@@ -1,6 +1,7 @@
 // wNAF expansion with w=5.
 // Stores the reversed expansion of the given scalar in memory at the given segment and offsets 0..130.
 // Should be called with scalars of bit length <= 129, which is the case when using GLV.
+// `wnaf_with_top` stores the expansion at offsets 0..top+1 instead, for scalars of bit length <= top.
 // Pseudo-code:
 // def wnaf(n):
 //     ans = [0 for _ in range(130)]
@@ -17,42 +18,45 @@
 //     return ans
 global wnaf:
     // stack: N, segment, n, retdest (N is the size of the group in which the mul is taking place)
+    %stack (N, segment, n) -> (N, segment, n, 129)
+global wnaf_with_top:
+    // stack: N, segment, n, top, retdest
     DUP3 MOD ISZERO %jumpi(wnaf_zero_scalar)
     PUSH 0
 wnaf_loop:
-    %stack (o, segment, n, retdest) -> (n, wnaf_loop_contd, o, segment, retdest)
+    %stack (o, segment, n, top, retdest) -> (n, wnaf_loop_contd, o, segment, top, retdest)
     %jump(trailing_zeros)
 wnaf_loop_contd:
-    %stack (n, i, o, segment, retdest) -> (o, i, n, segment, retdest)
+    %stack (n, i, o, segment, top, retdest) -> (o, i, n, segment, top, retdest)
     ADD
-    %stack (o, n, segment, retdest) -> (n, segment, o, retdest)
+    %stack (o, n, segment, top, retdest) -> (n, segment, o, top, retdest)
     DUP1 %and_const(31) SWAP1
     PUSH 16 DUP3 GT
-    // stack: m>16, n, m, segment, o, retdest
+    // stack: m>16, n, m, segment, o, top, retdest
     %mul_const(32) ADD
-    // stack: n, m, segment, o, retdest
+    // stack: n, m, segment, o, top, retdest
     DUP2 SWAP1 SUB
-    %stack (n, m, segment, o, retdest) -> (129, o, m, o, segment, n, retdest)
+    %stack (n, m, segment, o, top, retdest) -> (top, o, m, o, segment, n, top, retdest)
     SUB
-    // stack:  i, m, o, segment, n, retdest
+    // stack:  i, m, o, segment, n, top, retdest
     DUP4
     GET_CONTEXT
     %build_address
-    // stack:  addr, m, o, segment, n, retdest
+    // stack:  addr, m, o, segment, n, top, retdest
     SWAP1
     MSTORE_GENERAL
-    // stack: o, segment, n, retdest
+    // stack: o, segment, n, top, retdest
     DUP3 ISZERO %jumpi(wnaf_end)
-    // stack: o, segment, n, retdest
+    // stack: o, segment, n, top, retdest
     %jump(wnaf_loop)
 
 wnaf_end:
-    // stack: o, segment, n, retdest
-    %pop3 JUMP
+    // stack: o, segment, n, top, retdest
+    %pop4 JUMP
 
 wnaf_zero_scalar:
-    // stack: segment, n, retdest
-    %pop2 JUMP
+    // stack: segment, n, top, retdest
+    %pop3 JUMP
 
 
 
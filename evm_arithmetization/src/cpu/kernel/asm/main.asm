@@ -223,7 +223,8 @@ global check_final_state_trie:
     %set_final_tries
     PUSH @INITIAL_RLP_ADDR
     // stack: rlp_start, dummy_trie_len
-    %mpt_hash_state_trie   %mload_global_metadata(@GLOBAL_METADATA_STATE_TRIE_DIGEST_AFTER)     %assert_eq
+    %mpt_hash_state_trie   %mload_global_metadata(@GLOBAL_METADATA_STATE_TRIE_DIGEST_AFTER)
+    SUB %jumpi(final_state_root_mismatch)
     // We don't need the trie data length here.
     POP
 
@@ -233,6 +234,11 @@ global check_final_state_trie:
     
     %jump(halt)
 
+// Reached when the final state trie doesn't hash to the expected root. This is
+// kept apart from `panic` so that the prover can tell which accounts diverge.
+global final_state_root_mismatch:
+    PANIC
+
 %macro reinitialize_memory_pre_txn
     // Reinitialize accessed addresses and storage keys lists
     %init_access_lists
@@ -256,4 +262,5 @@ global check_final_state_trie:
     PUSH 0 %mstore_txn_field(@TXN_FIELD_TO)
 
     %reset_blob_versioned_hashes
+    %reset_authorization_list
 %endmacro
@@ -187,6 +187,61 @@ decode_and_store_blob_versioned_hashes_finish:
     %stack (rlp_addr, end_rlp_addr, store_addr, retdest) -> (retdest, rlp_addr)
     JUMP
 
+%macro decode_and_store_authorization_list
+    // stack: rlp_addr
+    %decode_rlp_list_len
+    %stack (rlp_addr, len) -> (len, len, rlp_addr, %%after)
+
+    // EIP-7702: Set code transactions should have at least 1 authorization
+    %assert_nonzero(invalid_txn_2)
+
+    // stack: len, rlp_addr, %%after
+    %jump(decode_and_store_authorization_list)
+%%after:
+%endmacro
+
+// The authorization list is of the form `[[chain_id, address, nonce, y_parity, r, s]...]`.
+// Each tuple is stored as 6 consecutive words in @SEGMENT_TXN_AUTHORIZATIONS.
+global decode_and_store_authorization_list:
+    // stack: len, rlp_addr
+    DUP2 ADD
+    // stack: end_rlp_addr, rlp_addr
+    PUSH @SEGMENT_TXN_AUTHORIZATIONS // initial address to write to
+    SWAP2
+decode_and_store_authorization_list_loop:
+    // stack: rlp_addr, end_rlp_addr, store_addr
+    DUP2 DUP2 EQ %jumpi(decode_and_store_authorization_list_finish)
+    // stack: rlp_addr, end_rlp_addr, store_addr
+    %decode_rlp_list_len
+    // We don't actually need the length of the tuple.
+    %stack (rlp_addr, len) -> (rlp_addr)
+    %decode_and_store_authorization_field // chain_id
+    %decode_and_store_authorization_field // address
+    %decode_and_store_authorization_field // nonce
+    %decode_and_store_authorization_field // y_parity
+    %decode_and_store_authorization_field // r
+    %decode_and_store_authorization_field // s
+    // stack: rlp_addr, end_rlp_addr, store_addr'
+    %jump(decode_and_store_authorization_list_loop)
+decode_and_store_authorization_list_finish:
+    // stack: rlp_addr, end_rlp_addr, store_addr, retdest
+    // Store the number of authorization tuples.
+    DUP3 PUSH @SEGMENT_TXN_AUTHORIZATIONS SWAP1 SUB
+    %div_const(6)
+    %mstore_global_metadata(@GLOBAL_METADATA_AUTHORIZATION_LIST_LEN)
+    %stack (rlp_addr, end_rlp_addr, store_addr, retdest) -> (retdest, rlp_addr)
+    JUMP
+
+%macro decode_and_store_authorization_field
+    // stack: rlp_addr, end_rlp_addr, store_addr
+    %decode_rlp_scalar
+    %stack (rlp_addr, field, end_rlp_addr, store_addr) -> (field, store_addr, rlp_addr, end_rlp_addr, store_addr)
+    MSTORE_GENERAL
+    // stack: rlp_addr, end_rlp_addr, store_addr
+    SWAP2 %increment SWAP2
+    // stack: rlp_addr, end_rlp_addr, store_addr'
+%endmacro
+
 %macro decode_and_store_y_parity
     // stack: rlp_addr
     %decode_rlp_scalar
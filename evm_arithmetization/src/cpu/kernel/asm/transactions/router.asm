@@ -14,7 +14,7 @@ read_txn_from_memory:
     // stack: retdest
 
     // We will peak at the first byte to determine what type of transaction this is.
-    // Note that type 1, 2 and 3 transactions have a first byte of 1, 2 and 3, respectively.
+    // Note that type 1, 2, 3 and 4 transactions have a first byte of 1, 2, 3 and 4, respectively.
     // Type 0 (legacy) transactions have no such prefix, but their RLP will have a
     // first byte >= 0xc0, so there is no overlap.
 
@@ -40,7 +40,14 @@ read_txn_from_memory:
     %jumpi(process_type_3_txn)
     // stack: rlp_start_addr, retdest
 
-    // At this point, since it's not a type 1, 2 or 3 transaction,
+    DUP1
+    MLOAD_GENERAL
+    %eq_const(4)
+    // stack: first_byte == 4, rlp_start_addr, retdest
+    %jumpi(process_type_4_txn)
+    // stack: rlp_start_addr, retdest
+
+    // At this point, since it's not a type 1, 2, 3 or 4 transaction,
     // it must be a legacy (aka type 0) transaction.
     %jump(process_type_0_txn)
 
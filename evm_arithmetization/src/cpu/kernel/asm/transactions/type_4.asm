@@ -0,0 +1,311 @@
+// Type 4 transactions, introduced by EIP 7702, have the format
+//     0x04 || rlp([chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, value,
+//                  data, access_list, authorization_list, y_parity, r, s])
+// where the authorization list is of the form `[[chain_id, address, nonce, y_parity, r, s]...]`.
+//
+// The signed data is
+//     keccak256(0x04 || rlp([chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit,
+//                       to, value, data, access_list, authorization_list]))
+
+global process_type_4_txn:
+    // stack: rlp_addr, retdest
+    // Store txn type.
+    PUSH 4
+    %mstore_txn_field(@TXN_FIELD_TYPE)
+
+    // stack: rlp_addr, retdest
+    // Initial rlp address offset of 1 (skipping over the 0x04 byte)
+    %add_const(1)
+    // stack: rlp_addr, retdest
+    %decode_rlp_list_len
+    // We don't actually need the length.
+    %stack (rlp_addr, len) -> (rlp_addr)
+
+    // stack: rlp_addr, retdest
+    %store_chain_id_present_true
+    // stack: rlp_addr, retdest
+    // Keep track of the chain id position.
+    DUP1
+    // stack: rlp_addr, chain_id_addr, retdest
+    %decode_and_store_chain_id
+    %decode_and_store_nonce
+    %decode_and_store_max_priority_fee
+    %decode_and_store_max_fee
+    %decode_and_store_gas_limit
+    %decode_and_store_to
+
+    // EIP-7702: Set code transactions cannot be contract creations.
+    %mload_global_metadata(@GLOBAL_METADATA_CONTRACT_CREATION)
+    // stack: is_contract_creation, rlp_addr, chain_id_addr, retdest
+    %jumpi(invalid_txn_2)
+
+    // stack: rlp_addr, chain_id_addr, retdest
+    %decode_and_store_value
+    %decode_and_store_data
+    %decode_and_store_access_list
+    %decode_and_store_authorization_list
+    // stack: rlp_addr, chain_id_addr, retdest
+    DUP1
+    // stack: rlp_addr, after_authorization_list_addr, chain_id_addr, retdest
+    %decode_and_store_y_parity
+    %decode_and_store_r
+    %decode_and_store_s
+
+    // stack: rlp_addr, after_authorization_list_addr, chain_id_addr, retdest
+    POP
+    // stack: after_authorization_list_addr, chain_id_addr, retdest
+
+// From EIP-7702:
+// The signature_y_parity, signature_r, signature_s elements of this transaction represent a secp256k1 signature over
+// keccak256(0x04 || rlp([chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, destination, value, data, access_list, authorization_list]))
+// We know that [chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, destination, value, data, access_list, authorization_list] is already encoded
+// at `chain_id_addr`; we just need to overwrite the existing RLP prefix. This is fine since we don't need the original encoding anymore.
+type_4_compute_signed_data:
+    // stack: after_authorization_list_addr, chain_id_addr, retdest
+    %prepend_rlp_list_prefix
+    // stack: prefix_start_pos, rlp_len, retdest
+
+    // Store a `4` in front of the RLP
+    %decrement
+    %stack (rlp_addr) -> (4, rlp_addr, rlp_addr)
+    MSTORE_GENERAL
+    // stack: rlp_addr, rlp_len, retdest
+
+    // Hash the RLP + the leading `4`
+    SWAP1 %increment SWAP1
+    // stack: ADDR, len, retdest
+    KECCAK_GENERAL
+    // stack: hash, retdest
+
+    %mload_txn_field(@TXN_FIELD_S)
+    %mload_txn_field(@TXN_FIELD_R)
+    %mload_txn_field(@TXN_FIELD_Y_PARITY) %add_const(27) // ecrecover interprets v as y_parity + 27
+
+    PUSH store_origin
+    // stack: store_origin, v, r, s, hash, retdest
+    SWAP4
+    // stack: hash, v, r, s, store_origin, retdest
+    %jump(ecrecover)
+
+store_origin:
+    // stack: address, retdest
+    // If ecrecover returned u256::MAX, that indicates failure.
+    DUP1
+    %eq_const(0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff)
+    %jumpi(panic)
+
+    // stack: address, retdest
+    %mstore_txn_field(@TXN_FIELD_ORIGIN)
+    // stack: retdest
+    %jump(process_normalized_txn)
+
+// Applies the authorization list of the current transaction, as per EIP-7702.
+// Invalid authorization tuples are skipped, and do not invalidate the transaction.
+// Pre stack: retdest
+// Post stack: (empty)
+global process_authorization_list:
+    // stack: retdest
+    %mload_global_metadata(@GLOBAL_METADATA_AUTHORIZATION_LIST_LEN)
+    %mul_const(6)
+    PUSH @SEGMENT_TXN_AUTHORIZATIONS // ctx 0
+    DUP1 SWAP2 ADD
+    // stack: end_addr, tuple_addr, retdest
+    SWAP1
+process_authorization_list_loop:
+    // stack: tuple_addr, end_addr, retdest
+    DUP2 DUP2 EQ %jumpi(process_authorization_list_finish)
+    // stack: tuple_addr, end_addr, retdest
+    PUSH process_authorization_list_next DUP2
+    // stack: tuple_addr, process_authorization_list_next, tuple_addr, end_addr, retdest
+    %jump(process_authorization)
+process_authorization_list_next:
+    // stack: tuple_addr, end_addr, retdest
+    %add_const(6)
+    %jump(process_authorization_list_loop)
+process_authorization_list_finish:
+    // stack: tuple_addr, end_addr, retdest
+    %pop2
+    JUMP
+
+// Applies the authorization tuple stored at `tuple_addr`, or skips it if it is invalid.
+// Pre stack: tuple_addr, retdest
+// Post stack: (empty)
+global process_authorization:
+    // stack: tuple_addr, retdest
+    // Verify the chain id is either 0 or the chain's current ID.
+    DUP1 MLOAD_GENERAL
+    // stack: chain_id, tuple_addr, retdest
+    DUP1 ISZERO
+    SWAP1 %mload_global_metadata(@GLOBAL_METADATA_BLOCK_CHAIN_ID) EQ
+    // stack: chain_id == block_chain_id, chain_id == 0, tuple_addr, retdest
+    OR ISZERO %jumpi(process_authorization_skip)
+
+    // stack: tuple_addr, retdest
+    // Verify the nonce is less than 2**64 - 1.
+    DUP1 %add_const(2) MLOAD_GENERAL
+    // stack: nonce, tuple_addr, retdest
+    %lt_const(@MAX_NONCE) ISZERO %jumpi(process_authorization_skip)
+
+    // stack: tuple_addr, retdest
+    // Verify y_parity is either 0 or 1.
+    DUP1 %add_const(3) MLOAD_GENERAL
+    // stack: y_parity, tuple_addr, retdest
+    %gt_const(1) %jumpi(process_authorization_skip)
+
+    // stack: tuple_addr, retdest
+    // EIP-2: Check that s is within valid range.
+    DUP1 %add_const(5) MLOAD_GENERAL
+    %secp_scalar_half
+    // stack: ceil(N/2), s, tuple_addr, retdest
+    GT ISZERO %jumpi(process_authorization_skip)
+
+    // stack: tuple_addr, retdest
+    // The authority is recovered from keccak256(0x05 || rlp([chain_id, address, nonce])).
+    PUSH @INITIAL_RLP_ADDR %add_const(@MAX_RLP_PREFIX_SIZE)
+    // stack: rlp_start, tuple_addr, retdest
+    DUP2 MLOAD_GENERAL DUP2
+    // stack: rlp_start, chain_id, rlp_start, tuple_addr, retdest
+    %encode_rlp_scalar
+    // stack: rlp_pos, rlp_start, tuple_addr, retdest
+    DUP3 %increment MLOAD_GENERAL SWAP1
+    // stack: rlp_pos, address, rlp_start, tuple_addr, retdest
+    %encode_rlp_160
+    // stack: rlp_pos, rlp_start, tuple_addr, retdest
+    DUP3 %add_const(2) MLOAD_GENERAL SWAP1
+    // stack: rlp_pos, nonce, rlp_start, tuple_addr, retdest
+    %encode_rlp_scalar
+    // stack: rlp_pos, rlp_start, tuple_addr, retdest
+    %prepend_rlp_list_prefix
+    // stack: prefix_start_pos, rlp_len, tuple_addr, retdest
+
+    // Store a `5` in front of the RLP
+    %decrement
+    %stack (rlp_addr) -> (5, rlp_addr, rlp_addr)
+    MSTORE_GENERAL
+    // stack: rlp_addr, rlp_len, tuple_addr, retdest
+
+    // Hash the RLP + the leading `5`
+    SWAP1 %increment SWAP1
+    // stack: ADDR, len, tuple_addr, retdest
+    KECCAK_GENERAL
+    // stack: hash, tuple_addr, retdest
+
+    PUSH process_authorization_authority_recovered
+    DUP3 %add_const(5) MLOAD_GENERAL
+    DUP4 %add_const(4) MLOAD_GENERAL
+    DUP5 %add_const(3) MLOAD_GENERAL %add_const(27) // ecrecover interprets v as y_parity + 27
+    // stack: v, r, s, process_authorization_authority_recovered, hash, tuple_addr, retdest
+    %stack (v, r, s, ret, hash) -> (hash, v, r, s, ret)
+    %jump(ecrecover)
+
+process_authorization_authority_recovered:
+    // stack: authority, tuple_addr, retdest
+    // If ecrecover returned u256::MAX, that indicates failure.
+    DUP1
+    %eq_const(0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff)
+    %jumpi(process_authorization_skip_authority)
+
+    // stack: authority, tuple_addr, retdest
+    // Add the authority to the accessed addresses.
+    DUP1 %insert_accessed_addresses_no_return
+
+    // Verify the code of the authority is either empty or already delegated.
+    DUP1 %extcodehash
+    // stack: codehash, authority, tuple_addr, retdest
+    DUP1 ISZERO SWAP1 %eq_const(@EMPTY_STRING_HASH) OR
+    // stack: has_no_code, authority, tuple_addr, retdest
+    %jumpi(process_authorization_check_nonce)
+    DUP1 %get_delegate
+    // stack: delegate, authority, tuple_addr, retdest
+    ISZERO %jumpi(process_authorization_skip_authority)
+
+process_authorization_check_nonce:
+    // stack: authority, tuple_addr, retdest
+    // Verify the nonce of the authority is equal to the tuple nonce.
+    DUP1 %nonce
+    DUP3 %add_const(2) MLOAD_GENERAL
+    // stack: nonce, authority_nonce, authority, tuple_addr, retdest
+    EQ ISZERO %jumpi(process_authorization_skip_authority)
+
+    // stack: authority, tuple_addr, retdest
+    // Create the authority account if it doesn't exist. Otherwise, refund
+    // the difference between the empty account cost charged in the intrinsic gas
+    // and the base cost of an authorization.
+    DUP1 %is_non_existent %jumpi(process_authorization_create_authority)
+    PUSH @GAS_PER_AUTH_BASE PUSH @GAS_PER_EMPTY_ACCOUNT SUB
+    // stack: refund, authority, tuple_addr, retdest
+    %refund_gas
+    %jump(process_authorization_set_code)
+
+process_authorization_create_authority:
+    // stack: authority, tuple_addr, retdest
+    DUP1 PUSH 0
+    // stack: is_eoa, authority, authority, tuple_addr, retdest
+    %journal_add_account_created
+    %get_trie_data_size // pointer to new account we're about to create
+    // stack: new_account_ptr, authority, tuple_addr, retdest
+    PUSH 0 %append_to_trie_data // nonce
+    PUSH 0 %append_to_trie_data // balance
+    PUSH 0 %append_to_trie_data // storage root pointer
+    PUSH @EMPTY_STRING_HASH %append_to_trie_data // code hash
+    // stack: new_account_ptr, authority, tuple_addr, retdest
+    DUP2 %addr_to_state_key
+    // stack: key, new_account_ptr, authority, tuple_addr, retdest
+    %stack (key, new_account_ptr) -> (key, new_account_ptr, process_authorization_set_code)
+    %jump(mpt_insert_state_trie)
+
+process_authorization_set_code:
+    // stack: authority, tuple_addr, retdest
+    // Set the code of the authority to the delegation designator `0xef0100 || address`,
+    // or clear it if `address` is 0.
+    DUP2 %increment MLOAD_GENERAL
+    // stack: address, authority, tuple_addr, retdest
+    DUP1 ISZERO %jumpi(process_authorization_clear_code)
+    PUSH @SEGMENT_KERNEL_GENERAL // ctx 0
+    PUSH @DELEGATION_DESIGNATOR_PREFIX SWAP1
+    // stack: addr, prefix, address, authority, tuple_addr, retdest
+    MSTORE_32BYTES_3
+    // stack: addr', address, authority, tuple_addr, retdest
+    MSTORE_32BYTES_20
+    // stack: addr'', authority, tuple_addr, retdest
+    POP
+    PUSH @DELEGATION_DESIGNATOR_LEN PUSH @SEGMENT_KERNEL_GENERAL // ctx 0
+    // stack: addr, len, authority, tuple_addr, retdest
+    KECCAK_GENERAL
+    // stack: codehash, authority, tuple_addr, retdest
+    %observe_delegation_designator
+    %jump(process_authorization_store_code)
+
+process_authorization_clear_code:
+    // stack: address, authority, tuple_addr, retdest
+    POP PUSH @EMPTY_STRING_HASH
+
+process_authorization_store_code:
+    // stack: codehash, authority, tuple_addr, retdest
+    DUP2 %set_codehash
+    // stack: authority, tuple_addr, retdest
+    // Increase the nonce of the authority by one.
+    %increment_nonce
+    // stack: tuple_addr, retdest
+    POP
+    JUMP
+
+process_authorization_skip_authority:
+    // stack: authority, tuple_addr, retdest
+    POP
+process_authorization_skip:
+    // stack: tuple_addr, retdest
+    POP
+    JUMP
+
+%macro reset_authorization_list
+    // stack: (empty)
+    // Reset the stored authorization tuples
+    %mload_global_metadata(@GLOBAL_METADATA_AUTHORIZATION_LIST_LEN)
+    %mul_const(6)
+    PUSH @SEGMENT_TXN_AUTHORIZATIONS // ctx 0
+    %memset
+    // Reset the global metadata
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_AUTHORIZATION_LIST_LEN)
+    // stack: (empty)
+%endmacro
@@ -8,11 +8,12 @@ use keccak_hash::keccak;
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-use super::ast::{BytesTarget, PushTarget};
+use super::ast::{BytesTarget, PushTarget, SourceFrame};
 use crate::cpu::kernel::ast::Item::LocalLabelDeclaration;
 use crate::cpu::kernel::ast::{File, Item, StackReplacement};
 use crate::cpu::kernel::opcodes::{get_opcode, get_push_opcode};
 use crate::cpu::kernel::optimizer::optimize_asm;
+use crate::cpu::kernel::source_map::{SourceBacktrace, SourceMap, SourceMapBuilder};
 use crate::cpu::kernel::stack::stack_manipulation::expand_stack_manipulation;
 use crate::cpu::kernel::utils::u256_to_trimmed_be_bytes;
 use crate::generation::prover_input::ProverInputFn;
@@ -35,6 +36,10 @@ pub struct Kernel {
 
     /// Map from `PROVER_INPUT` offsets to their corresponding `ProverInputFn`.
     pub(crate) prover_inputs: HashMap<usize, ProverInputFn>,
+
+    /// Map from offsets to the assembly source they were generated from.
+    #[serde(default)]
+    pub(crate) source_map: SourceMap,
 }
 
 impl Kernel {
@@ -42,6 +47,7 @@ impl Kernel {
         code: Vec<u8>,
         global_labels: HashMap<String, usize>,
         prover_inputs: HashMap<usize, ProverInputFn>,
+        source_map: SourceMap,
     ) -> Self {
         let code_hash = keccak(&code);
         let ordered_labels = global_labels
@@ -56,6 +62,7 @@ impl Kernel {
             global_labels,
             ordered_labels,
             prover_inputs,
+            source_map,
         }
     }
 
@@ -87,6 +94,21 @@ impl Kernel {
         }
     }
 
+    /// Returns the location in the assembly source of the code at `offset`,
+    /// along with the macro expansions it comes from.
+    pub fn backtrace(&self, offset: usize) -> Option<SourceBacktrace<'_>> {
+        self.source_map.backtrace(offset)
+    }
+
+    /// Get a string representation of the current offset, followed by its
+    /// backtrace in the assembly source if known, for debugging purposes.
+    pub fn symbolize(&self, offset: usize) -> String {
+        match self.backtrace(offset) {
+            Some(backtrace) => format!("{}\n{backtrace}", self.offset_name(offset)),
+            None => self.offset_name(offset),
+        }
+    }
+
     pub(crate) fn offset_label(&self, offset: usize) -> Option<String> {
         self.global_labels
             .iter()
@@ -95,14 +117,14 @@ impl Kernel {
 }
 
 #[derive(Eq, PartialEq, Hash, Clone, Debug)]
-struct MacroSignature {
-    name: String,
-    num_params: usize,
+pub(crate) struct MacroSignature {
+    pub(crate) name: String,
+    pub(crate) num_params: usize,
 }
 
-struct Macro {
-    params: Vec<String>,
-    items: Vec<Item>,
+pub(crate) struct Macro {
+    pub(crate) params: Vec<String>,
+    pub(crate) items: Vec<Item>,
 }
 
 impl Macro {
@@ -114,14 +136,23 @@ impl Macro {
     }
 }
 
+/// Assembles the given files, whose names are used to build the source map of
+/// the kernel.
 pub(crate) fn assemble(
-    files: Vec<File>,
+    mut files: Vec<File>,
+    file_names: Vec<String>,
     constants: HashMap<String, U256>,
     optimize: bool,
 ) -> Kernel {
+    assert_eq!(files.len(), file_names.len(), "Missing file names.");
+    for (i, file) in files.iter_mut().enumerate() {
+        set_source_file(&mut file.body, i);
+    }
+
     let macros = find_macros(&files);
     let mut global_labels = HashMap::new();
     let mut prover_inputs = HashMap::new();
+    let mut source_map = SourceMapBuilder::new(file_names);
     let mut offset = 0;
     let mut expanded_files = Vec::with_capacity(files.len());
     let mut local_labels = Vec::with_capacity(files.len());
@@ -133,8 +164,15 @@ pub(crate) fn assemble(
         file = expand_macros(file, &macros, &mut macro_counter);
         file = inline_constants(file, &constants);
         file = expand_stack_manipulation(file);
+        let mut tagged_file = strip_source_markers(file, &mut source_map);
         if optimize {
-            optimize_asm(&mut file);
+            optimize_asm(&mut tagged_file);
+        }
+        let (file, stacks): (Vec<_>, Vec<_>) = tagged_file.into_iter().unzip();
+        let mut item_offset = offset;
+        for (item, stack) in izip!(&file, stacks) {
+            source_map.record(item_offset, stack);
+            item_offset += item_size(item);
         }
         local_labels.push(find_labels(
             &file,
@@ -154,10 +192,45 @@ pub(crate) fn assemble(
     }
     assert_eq!(code.len(), offset, "Code length doesn't match offset.");
     debug!("Total kernel size: {} bytes", code.len());
-    Kernel::new(code, global_labels, prover_inputs)
+    Kernel::new(code, global_labels, prover_inputs, source_map.build())
+}
+
+/// Sets the file index of all the source markers of a file.
+pub(crate) fn set_source_file(body: &mut [Item], file: usize) {
+    for item in body {
+        match item {
+            Item::SourceMarker(frames) => {
+                for frame in frames {
+                    frame.file = file;
+                }
+            }
+            Item::ConditionalBlock(_, items)
+            | Item::MacroDef(_, _, items)
+            | Item::Repeat(_, items) => set_source_file(items, file),
+            _ => {}
+        }
+    }
+}
+
+/// Removes the source markers of a fully expanded file, tagging each item with
+/// the index of its stack of source frames in the source map instead.
+fn strip_source_markers(
+    body: Vec<Item>,
+    source_map: &mut SourceMapBuilder,
+) -> Vec<(Item, Option<usize>)> {
+    let mut stack = None;
+    let mut tagged = Vec::with_capacity(body.len());
+    for item in body {
+        if let Item::SourceMarker(frames) = item {
+            stack = Some(source_map.intern(frames));
+        } else {
+            tagged.push((item, stack));
+        }
+    }
+    tagged
 }
 
-fn find_macros(files: &[File]) -> HashMap<MacroSignature, Macro> {
+pub(crate) fn find_macros(files: &[File]) -> HashMap<MacroSignature, Macro> {
     let mut macros = HashMap::new();
     for file in files {
         find_macros_internal(&file.body, &mut macros);
@@ -185,7 +258,7 @@ fn find_macros_internal(items: &[Item], macros: &mut HashMap<MacroSignature, Mac
     }
 }
 
-fn expand_conditional_blocks(body: Vec<Item>) -> Vec<Item> {
+pub(crate) fn expand_conditional_blocks(body: Vec<Item>) -> Vec<Item> {
     let mut expanded = vec![];
     for item in body {
         match item {
@@ -200,19 +273,30 @@ fn expand_conditional_blocks(body: Vec<Item>) -> Vec<Item> {
     expanded
 }
 
-fn expand_macros(
+pub(crate) fn expand_macros(
     body: Vec<Item>,
     macros: &HashMap<MacroSignature, Macro>,
     macro_counter: &mut u32,
 ) -> Vec<Item> {
     let mut expanded = vec![];
+    let mut call_site = vec![];
     for item in body {
         match item {
             Item::MacroDef(_, _, _) => {
                 // At this phase, we no longer need macro definitions.
             }
             Item::MacroCall(m, args) => {
-                expanded.extend(expand_macro_call(m, args, macros, macro_counter));
+                expanded.extend(expand_macro_call(
+                    m,
+                    args,
+                    &call_site,
+                    macros,
+                    macro_counter,
+                ));
+            }
+            Item::SourceMarker(frames) => {
+                call_site.clone_from(&frames);
+                expanded.push(Item::SourceMarker(frames));
             }
             Item::Repeat(count, body) => {
                 for _ in 0..count.as_usize() {
@@ -230,14 +314,15 @@ fn expand_macros(
     expanded
 }
 
-fn expand_macro_call(
+pub(crate) fn expand_macro_call(
     name: String,
     args: Vec<PushTarget>,
+    call_site: &[SourceFrame],
     macros: &HashMap<MacroSignature, Macro>,
     macro_counter: &mut u32,
 ) -> Vec<Item> {
     let signature = MacroSignature {
-        name,
+        name: name.clone(),
         num_params: args.len(),
     };
     let macro_ = macros
@@ -284,6 +369,18 @@ fn expand_macro_call(
                     .collect();
                 Item::StackManipulation(before.clone(), after)
             }
+            Item::SourceMarker(frames) => {
+                // Locate the body of the macro within this expansion.
+                let frames = frames
+                    .iter()
+                    .map(|frame| SourceFrame {
+                        in_macro: Some(name.clone()),
+                        ..frame.clone()
+                    })
+                    .chain(call_site.iter().cloned())
+                    .collect();
+                Item::SourceMarker(frames)
+            }
             _ => item.clone(),
         })
         .collect();
@@ -294,7 +391,7 @@ fn expand_macro_call(
     expand_macros(expanded_item, macros, macro_counter)
 }
 
-fn inline_constants(body: Vec<Item>, constants: &HashMap<String, U256>) -> Vec<Item> {
+pub(crate) fn inline_constants(body: Vec<Item>, constants: &HashMap<String, U256>) -> Vec<Item> {
     let resolve_const = |c| {
         *constants
             .get(&c)
@@ -359,6 +456,9 @@ fn find_labels(
             | Item::MacroLabelDeclaration(_) => {
                 panic!("Item should have been expanded already: {item:?}");
             }
+            Item::SourceMarker(_) => {
+                panic!("Source markers should have been stripped already");
+            }
             Item::GlobalLabelDeclaration(label) => {
                 let old = global_labels.insert(label.clone(), *offset);
                 assert!(old.is_none(), "Duplicate global label: {label}");
@@ -367,19 +467,27 @@ fn find_labels(
                 let old = local_labels.insert(label.clone(), *offset);
                 assert!(old.is_none(), "Duplicate local label: {label}");
             }
-            Item::Push(target) => *offset += 1 + push_target_size(target) as usize,
             Item::ProverInput(prover_input_fn) => {
                 prover_inputs.insert(*offset, prover_input_fn.clone());
-                *offset += 1;
             }
-            Item::StandardOp(_) => *offset += 1,
-            Item::Bytes(bytes) => *offset += bytes.len(),
-            Item::Jumptable(labels) => *offset += labels.len() * (BYTES_PER_OFFSET as usize),
+            Item::Push(_) | Item::StandardOp(_) | Item::Bytes(_) | Item::Jumptable(_) => {}
         }
+        *offset += item_size(item);
     }
     local_labels
 }
 
+/// The size of an expanded item, in bytes.
+fn item_size(item: &Item) -> usize {
+    match item {
+        Item::Push(target) => 1 + push_target_size(target) as usize,
+        Item::ProverInput(_) | Item::StandardOp(_) => 1,
+        Item::Bytes(bytes) => bytes.len(),
+        Item::Jumptable(labels) => labels.len() * (BYTES_PER_OFFSET as usize),
+        _ => 0,
+    }
+}
+
 fn look_up_label(
     label: &String,
     local_labels: &HashMap<String, usize>,
@@ -414,6 +522,9 @@ fn assemble_file(
             | Item::MacroLabelDeclaration(_) => {
                 panic!("Item should have been expanded already: {item:?}");
             }
+            Item::SourceMarker(_) => {
+                panic!("Source markers should have been stripped already");
+            }
             Item::GlobalLabelDeclaration(_) | Item::LocalLabelDeclaration(_) => {
                 // Nothing to do; we processed labels in the prior phase.
             }
@@ -518,10 +629,22 @@ mod tests {
         expected_global_labels.insert("function_1".to_string(), 0);
         expected_global_labels.insert("function_2".to_string(), 3);
 
-        let expected_kernel = Kernel::new(expected_code, expected_global_labels, HashMap::new());
+        // The items were not parsed from source, so their location is unknown.
+        let mut expected_source_map = SourceMapBuilder::new(file_names(2));
+        expected_source_map.record(0, None);
+
+        let expected_kernel = Kernel::new(
+            expected_code,
+            expected_global_labels,
+            HashMap::new(),
+            expected_source_map.build(),
+        );
 
         let program = vec![file_1, file_2];
-        assert_eq!(assemble(program, HashMap::new(), false), expected_kernel);
+        assert_eq!(
+            assemble(program, file_names(2), HashMap::new(), false),
+            expected_kernel
+        );
     }
 
     #[test]
@@ -539,7 +662,7 @@ mod tests {
                 Item::StandardOp("JUMPDEST".to_string()),
             ],
         };
-        assemble(vec![file_1, file_2], HashMap::new(), false);
+        assemble(vec![file_1, file_2], file_names(2), HashMap::new(), false);
     }
 
     #[test]
@@ -553,7 +676,7 @@ mod tests {
                 Item::StandardOp("ADD".to_string()),
             ],
         };
-        assemble(vec![file], HashMap::new(), false);
+        assemble(vec![file], file_names(1), HashMap::new(), false);
     }
 
     #[test]
@@ -564,7 +687,7 @@ mod tests {
                 Item::Bytes(vec![BytesTarget::Literal(0xFE), BytesTarget::Literal(255)]),
             ],
         };
-        let code = assemble(vec![file], HashMap::new(), false).code;
+        let code = assemble(vec![file], file_names(1), HashMap::new(), false).code;
         assert_eq!(code, vec![0x12, 42, 0xfe, 255]);
     }
 
@@ -755,6 +878,59 @@ mod tests {
         assert_eq!(kernel.code, vec![pop, push_label, 0, 0, 0]);
     }
 
+    #[test]
+    fn source_map() {
+        let kernel = parse_and_assemble_ext(
+            &[
+                "%macro bar\nADD\n%endmacro",
+                "%macro foo\nMUL\n%bar\n%endmacro",
+                "global start:\nPUSH 1\n\n%foo\nSTOP",
+            ],
+            HashMap::new(),
+            false,
+        );
+        let backtrace = |offset| {
+            kernel
+                .backtrace(offset)
+                .unwrap()
+                .frames()
+                .collect::<Vec<_>>()
+        };
+
+        // PUSH 1
+        assert_eq!(backtrace(0), vec![("file_2.asm", 2)]);
+        assert_eq!(backtrace(1), vec![("file_2.asm", 2)]);
+        // MUL
+        assert_eq!(backtrace(2), vec![("file_1.asm", 2), ("file_2.asm", 4)]);
+        // ADD
+        assert_eq!(
+            backtrace(3),
+            vec![("file_0.asm", 2), ("file_1.asm", 3), ("file_2.asm", 4)]
+        );
+        // STOP
+        assert_eq!(backtrace(4), vec![("file_2.asm", 5)]);
+
+        assert_eq!(
+            kernel.symbolize(3),
+            "3, below start\n    at %bar (file_0.asm:2)\n    at %foo (file_1.asm:3)\n    at file_2.asm:4"
+        );
+    }
+
+    #[test]
+    fn source_map_after_optimization() {
+        // The two pushes and the addition are folded into a single push, which is
+        // attributed to the first line of the replaced code.
+        let kernel = parse_and_assemble(&["PUSH 1\nPUSH 2\nADD\nSTOP"]);
+        assert_eq!(kernel.code, vec![get_push_opcode(1), 3, get_opcode("STOP")]);
+        let line = |offset| kernel.backtrace(offset).unwrap().frames().next().unwrap().1;
+        assert_eq!(line(0), 1);
+        assert_eq!(line(2), 4);
+    }
+
+    fn file_names(num_files: usize) -> Vec<String> {
+        (0..num_files).map(|i| format!("file_{i}.asm")).collect()
+    }
+
     fn parse_and_assemble(files: &[&str]) -> Kernel {
         parse_and_assemble_ext(files, HashMap::new(), true)
     }
@@ -768,6 +944,6 @@ mod tests {
             .iter()
             .map(|f| parse(f, &HashSet::new()))
             .collect_vec();
-        assemble(parsed_files, constants, optimize)
+        assemble(parsed_files, file_names(files.len()), constants, optimize)
     }
 }
@@ -38,6 +38,21 @@ pub(crate) enum Item {
     Bytes(Vec<BytesTarget>),
     /// Creates a table of addresses from a list of labels.
     Jumptable(Vec<String>),
+    /// Marks the source position of the items following it, up to the next
+    /// marker. The frames are ordered from the innermost macro expansion to
+    /// the outermost call site.
+    SourceMarker(Vec<SourceFrame>),
+}
+
+/// A position in a kernel assembly file, within the body of a macro if
+/// `in_macro` is set.
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub(crate) struct SourceFrame {
+    /// The index of the file among the assembled files.
+    pub(crate) file: usize,
+    /// The line within the file, starting from 1.
+    pub(crate) line: usize,
+    pub(crate) in_macro: Option<String>,
 }
 
 /// The left hand side of a %stack stack-manipulation macro.
@@ -115,10 +115,21 @@ pub(crate) enum GlobalMetadata {
 
     /// Address where the base fee to be burnt is sent.
     BurnAddr,
+
+    /// Number of authorization tuples contained in the current type-4
+    /// transaction.
+    AuthorizationListLen,
+
+    /// Whether the RIP-7212 `P256VERIFY` precompile is enabled for the chain
+    /// being proven.
+    P256VerifyEnabled,
+    /// Whether the EIP-2537 BLS12-381 precompiles are enabled for the chain
+    /// being proven.
+    Bls12381Enabled,
 }
 
 impl GlobalMetadata {
-    pub(crate) const COUNT: usize = 55;
+    pub(crate) const COUNT: usize = 58;
 
     /// Unscales this virtual offset by their respective `Segment` value.
     pub(crate) const fn unscale(&self) -> usize {
@@ -182,6 +193,9 @@ impl GlobalMetadata {
             Self::TransientStorageLen,
             Self::BlobVersionedHashesLen,
             Self::BurnAddr,
+            Self::AuthorizationListLen,
+            Self::P256VerifyEnabled,
+            Self::Bls12381Enabled,
         ]
     }
 
@@ -249,6 +263,9 @@ impl GlobalMetadata {
             Self::TransientStorageLen => "GLOBAL_METADATA_TRANSIENT_STORAGE_LEN",
             Self::BlobVersionedHashesLen => "GLOBAL_METADATA_BLOB_VERSIONED_HASHES_LEN",
             Self::BurnAddr => "GLOBAL_METADATA_BURN_ADDR",
+            Self::AuthorizationListLen => "GLOBAL_METADATA_AUTHORIZATION_LIST_LEN",
+            Self::P256VerifyEnabled => "GLOBAL_METADATA_P256_VERIFY_ENABLED",
+            Self::Bls12381Enabled => "GLOBAL_METADATA_BLS12_381_ENABLED",
         }
     }
 }
@@ -59,6 +59,10 @@ pub(crate) fn evm_constants() -> HashMap<String, U256> {
         c.insert(name.into(), U256::from(value));
     }
 
+    for (name, value) in DELEGATION_DESIGNATOR {
+        c.insert(name.into(), U256::from(value));
+    }
+
     c.insert(MAX_NONCE.0.into(), U256::from(MAX_NONCE.1));
     c.insert(CALL_STACK_LIMIT.0.into(), U256::from(CALL_STACK_LIMIT.1));
     c.insert(
@@ -159,7 +163,7 @@ const HASH_CONSTANTS: [(&str, [u8; 32]); 2] = [
     ),
 ];
 
-const EC_CONSTANTS: [(&str, [u8; 32]); 25] = [
+const EC_CONSTANTS: [(&str, [u8; 32]); 30] = [
     (
         "U256_MAX",
         hex!("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
@@ -263,9 +267,29 @@ const EC_CONSTANTS: [(&str, [u8; 32]); 25] = [
         "SECP_GLV_B2",
         hex!("000000000000000000000000000000003086d221a7d46bcde86c90e49284eb15"),
     ),
+    (
+        "P256_BASE",
+        hex!("ffffffff00000001000000000000000000000000ffffffffffffffffffffffff"),
+    ),
+    (
+        "P256_SCALAR",
+        hex!("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"),
+    ),
+    (
+        "P256_B",
+        hex!("5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b"),
+    ),
+    (
+        "P256_GX",
+        hex!("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"),
+    ),
+    (
+        "P256_GY",
+        hex!("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"),
+    ),
 ];
 
-const GAS_CONSTANTS: [(&str, u32); 38] = [
+const GAS_CONSTANTS: [(&str, u32); 40] = [
     ("GAS_ZERO", 0),
     ("GAS_JUMPDEST", 1),
     ("GAS_BASE", 2),
@@ -304,11 +328,13 @@ const GAS_CONSTANTS: [(&str, u32); 38] = [
     ("GAS_BLOCKHASH", 20),
     ("GAS_HASH_OPCODE", 3),
     ("GAS_PER_BLOB", 131_072),
+    ("GAS_PER_EMPTY_ACCOUNT", 25_000),
+    ("GAS_PER_AUTH_BASE", 12_500),
 ];
 
 const REFUND_CONSTANTS: [(&str, u16); 2] = [("REFUND_SCLEAR", 4_800), ("MAX_REFUND_QUOTIENT", 5)];
 
-const PRECOMPILES: [(&str, u16); 10] = [
+const PRECOMPILES: [(&str, u16); 18] = [
     ("ECREC", 1),
     ("SHA256", 2),
     ("RIP160", 3),
@@ -319,9 +345,18 @@ const PRECOMPILES: [(&str, u16); 10] = [
     ("SNARKV", 8),
     ("BLAKE2_F", 9),
     ("KZG_PEVAL", 10),
+    ("BLS12_G1ADD", 11),
+    ("BLS12_G1MSM", 12),
+    ("BLS12_G2ADD", 13),
+    ("BLS12_G2MSM", 14),
+    ("BLS12_PAIRING", 15),
+    ("BLS12_MAP_FP_TO_G1", 16),
+    ("BLS12_MAP_FP2_TO_G2", 17),
+    // RIP-7212, only enabled on some chains.
+    ("P256_VERIFY", 0x100),
 ];
 
-const PRECOMPILES_GAS: [(&str, u16); 14] = [
+const PRECOMPILES_GAS: [(&str, u16); 23] = [
     ("ECREC_GAS", 3_000),
     ("SHA256_STATIC_GAS", 60),
     ("SHA256_DYNAMIC_GAS", 12),
@@ -336,6 +371,15 @@ const PRECOMPILES_GAS: [(&str, u16); 14] = [
     ("SNARKV_DYNAMIC_GAS", 34_000),
     ("BLAKE2_F__GAS", 1),
     ("KZG_PEVAL_GAS", 50_000),
+    ("BLS12_G1ADD_GAS", 375),
+    ("BLS12_G1MSM_MUL_GAS", 12_000),
+    ("BLS12_G2ADD_GAS", 600),
+    ("BLS12_G2MSM_MUL_GAS", 22_500),
+    ("BLS12_PAIRING_STATIC_GAS", 37_700),
+    ("BLS12_PAIRING_DYNAMIC_GAS", 32_600),
+    ("BLS12_MAP_FP_TO_G1_GAS", 5_500),
+    ("BLS12_MAP_FP2_TO_G2_GAS", 23_800),
+    ("P256_VERIFY_GAS", 3_450),
 ];
 
 const SNARKV_POINTERS: [(&str, u64); 2] = [("SNARKV_INP", 112), ("SNARKV_OUT", 100)];
@@ -349,6 +393,17 @@ const CODE_SIZE_LIMIT: [(&str, u64); 3] = [
     ("INITCODE_WORD_COST", 2),
 ];
 
+/// Length of an EIP-7702 delegation designator `0xef0100 || address`.
+pub(crate) const DELEGATION_DESIGNATOR_LEN: usize = 23;
+
+const DELEGATION_DESIGNATOR: [(&str, u64); 2] = [
+    ("DELEGATION_DESIGNATOR_PREFIX", 0xef0100),
+    (
+        "DELEGATION_DESIGNATOR_LEN",
+        DELEGATION_DESIGNATOR_LEN as u64,
+    ),
+];
+
 const MAX_NONCE: (&str, u64) = ("MAX_NONCE", 0xffffffffffffffff);
 const CALL_STACK_LIMIT: (&str, u64) = ("CALL_STACK_LIMIT", 1024);
 
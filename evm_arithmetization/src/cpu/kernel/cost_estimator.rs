@@ -1,37 +1,297 @@
+//! Estimates the proving cost of kernel code, to decide whether the optimizer
+//! should rewrite it.
+//!
+//! The cost of some code is the number of rows it adds to the tables of the
+//! zkEVM when executed once in kernel mode, assuming the stack is not empty.
+//! Code with as many rows is cheaper if it is smaller, since each byte of the
+//! kernel adds a row to the tables holding the initial memory of each segment.
+
+use core::iter::Sum;
+use core::ops::Add;
+
+use ethereum_types::U256;
+
+use crate::arithmetic::{BinaryOperator, Operation, TernaryOperator};
 use crate::cpu::kernel::assembler::BYTES_PER_OFFSET;
 use crate::cpu::kernel::ast::Item;
 use crate::cpu::kernel::ast::Item::*;
 use crate::cpu::kernel::ast::PushTarget::*;
 use crate::cpu::kernel::utils::u256_to_trimmed_be_bytes;
+use crate::keccak::keccak_stark::NUM_ROUNDS;
+use crate::witness::traces::{arithmetic_op_rows, keccak_sponge_op_rows};
+
+/// The number of bytes assumed to be read by the operations whose length is
+/// only known at runtime.
+const DYNAMIC_LENGTH: u32 = 32;
+
+/// The number of rows added to the tables of the zkEVM by some code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct TableRows {
+    cpu: u32,
+    memory: u32,
+    arithmetic: u32,
+    logic: u32,
+    byte_packing: u32,
+    keccak_sponge: u32,
+    keccak: u32,
+}
+
+impl TableRows {
+    /// Rows of an operation taking one CPU row and the given number of memory
+    /// operations.
+    const fn cpu_and_memory(memory: u32) -> Self {
+        Self {
+            cpu: 1,
+            memory,
+            arithmetic: 0,
+            logic: 0,
+            byte_packing: 0,
+            keccak_sponge: 0,
+            keccak: 0,
+        }
+    }
+
+    /// Rows of an operation going through the arithmetic table, which takes as
+    /// many rows there as when generating the traces.
+    fn arithmetic(operation: &Operation, memory: u32) -> Self {
+        Self {
+            arithmetic: arithmetic_op_rows(operation) as u32,
+            ..Self::cpu_and_memory(memory)
+        }
+    }
+
+    fn binary(operator: BinaryOperator, memory: u32) -> Self {
+        Self::arithmetic(
+            &Operation::binary(operator, U256::one(), U256::one()),
+            memory,
+        )
+    }
+
+    fn ternary(operator: TernaryOperator, memory: u32) -> Self {
+        Self::arithmetic(
+            &Operation::ternary(operator, U256::one(), U256::one(), U256::one()),
+            memory,
+        )
+    }
+
+    const fn total(&self) -> u32 {
+        self.cpu
+            + self.memory
+            + self.arithmetic
+            + self.logic
+            + self.byte_packing
+            + self.keccak_sponge
+            + self.keccak
+    }
+}
+
+impl Add for TableRows {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            cpu: self.cpu + rhs.cpu,
+            memory: self.memory + rhs.memory,
+            arithmetic: self.arithmetic + rhs.arithmetic,
+            logic: self.logic + rhs.logic,
+            byte_packing: self.byte_packing + rhs.byte_packing,
+            keccak_sponge: self.keccak_sponge + rhs.keccak_sponge,
+            keccak: self.keccak + rhs.keccak,
+        }
+    }
+}
+
+impl Sum for TableRows {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The estimated cost of some code, ordered by the number of rows first and by
+/// the size of the code next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Cost {
+    rows: u32,
+    bytes: u32,
+}
 
 pub(crate) fn is_code_improved(before: &[Item], after: &[Item]) -> bool {
     cost_estimate(after) < cost_estimate(before)
 }
 
-fn cost_estimate(code: &[Item]) -> u32 {
-    code.iter().map(cost_estimate_item).sum()
+fn cost_estimate(code: &[Item]) -> Cost {
+    Cost {
+        rows: code
+            .iter()
+            .map(rows_estimate_item)
+            .sum::<TableRows>()
+            .total(),
+        bytes: code.iter().map(size_item).sum(),
+    }
 }
 
-fn cost_estimate_item(item: &Item) -> u32 {
+/// Returns the rows added by an item each time it is executed.
+fn rows_estimate_item(item: &Item) -> TableRows {
     match item {
-        MacroDef(_, _, _) => 0,
-        GlobalLabelDeclaration(_) => 0,
-        LocalLabelDeclaration(_) => 0,
-        Push(Literal(n)) => cost_estimate_push(u256_to_trimmed_be_bytes(n).len()),
-        Push(Label(_)) => cost_estimate_push(BYTES_PER_OFFSET as usize),
-        ProverInput(_) => 1,
-        StandardOp(op) => cost_estimate_standard_op(op.as_str()),
+        MacroDef(_, _, _) => TableRows::default(),
+        GlobalLabelDeclaration(_) => TableRows::default(),
+        LocalLabelDeclaration(_) => TableRows::default(),
+        // Pushes write the previous stack top to memory. Kernel code is read
+        // without going through the byte packing table.
+        Push(Literal(_)) | Push(Label(_)) => TableRows::cpu_and_memory(1),
+        // Prover inputs are range-checked in the arithmetic table.
+        ProverInput(_) => TableRows::arithmetic(
+            &Operation::range_check(
+                U256::zero(),
+                U256::zero(),
+                U256::zero(),
+                U256::zero(),
+                U256::zero(),
+            ),
+            1,
+        ),
+        StandardOp(op) => rows_estimate_standard_op(op.as_str()),
         _ => panic!("Unexpected item: {item:?}"),
     }
 }
 
-const fn cost_estimate_standard_op(_op: &str) -> u32 {
-    // For now we just treat any standard operation as having the same cost. This is
-    // pretty naive, but should work fine with our current set of simple
-    // optimization rules.
-    1
+/// Returns the rows added by a standard operation. All operations take a CPU
+/// row. The top of the stack is kept in a register, so that popping `n > 0`
+/// items reads `n - 1` of them from memory, and pushing an item without
+/// popping any writes the previous top to memory, while popping items without
+/// pushing any reads the new top.
+fn rows_estimate_standard_op(op: &str) -> TableRows {
+    if let Some(num_bytes) = op.strip_prefix("MSTORE_32BYTES_") {
+        let num_bytes: u32 = num_bytes.parse().expect("Invalid MSTORE_32BYTES");
+        return TableRows {
+            byte_packing: 1,
+            ..TableRows::cpu_and_memory(1 + num_bytes)
+        };
+    }
+    if op.starts_with("DUP") || op.starts_with("SWAP") {
+        return TableRows::cpu_and_memory(2);
+    }
+
+    match op {
+        "ADD" => TableRows::binary(BinaryOperator::Add, 1),
+        "MUL" => TableRows::binary(BinaryOperator::Mul, 1),
+        "SUB" => TableRows::binary(BinaryOperator::Sub, 1),
+        "DIV" => TableRows::binary(BinaryOperator::Div, 1),
+        "MOD" => TableRows::binary(BinaryOperator::Mod, 1),
+        "LT" => TableRows::binary(BinaryOperator::Lt, 1),
+        "GT" => TableRows::binary(BinaryOperator::Gt, 1),
+        "BYTE" => TableRows::binary(BinaryOperator::Byte, 1),
+        "ADDFP254" => TableRows::binary(BinaryOperator::AddFp254, 1),
+        "MULFP254" => TableRows::binary(BinaryOperator::MulFp254, 1),
+        "SUBFP254" => TableRows::binary(BinaryOperator::SubFp254, 1),
+        "ADDMOD" => TableRows::ternary(TernaryOperator::AddMod, 2),
+        "MULMOD" => TableRows::ternary(TernaryOperator::MulMod, 2),
+        "SUBMOD" => TableRows::ternary(TernaryOperator::SubMod, 2),
+        // Shifts also read a power of two from the shift table.
+        "SHL" => TableRows::binary(BinaryOperator::Shl, 2),
+        "SHR" => TableRows::binary(BinaryOperator::Shr, 2),
+        "AND" | "OR" | "XOR" => TableRows {
+            logic: 1,
+            ..TableRows::cpu_and_memory(1)
+        },
+        "ISZERO" | "NOT" | "JUMPDEST" => TableRows::cpu_and_memory(0),
+        "EQ" | "POP" | "JUMP" | "MLOAD_GENERAL" | "GETPC" | "GET_CONTEXT" => {
+            TableRows::cpu_and_memory(1)
+        }
+        "JUMPI" => TableRows::cpu_and_memory(2),
+        "MSTORE_GENERAL" | "SET_CONTEXT" => TableRows::cpu_and_memory(3),
+        "MLOAD_32BYTES" => TableRows {
+            byte_packing: 1,
+            ..TableRows::cpu_and_memory(1 + DYNAMIC_LENGTH)
+        },
+        // Each block of the sponge is absorbed by a keccak permutation.
+        "KECCAK_GENERAL" => {
+            let blocks = keccak_sponge_op_rows(DYNAMIC_LENGTH as usize) as u32;
+            TableRows {
+                keccak_sponge: blocks,
+                keccak: blocks * NUM_ROUNDS as u32,
+                ..TableRows::cpu_and_memory(1 + DYNAMIC_LENGTH)
+            }
+        }
+        _ => TableRows::cpu_and_memory(0),
+    }
+}
+
+/// Returns the size in bytes of an item.
+fn size_item(item: &Item) -> u32 {
+    match item {
+        Push(Literal(n)) => 1 + u256_to_trimmed_be_bytes(n).len() as u32,
+        Push(Label(_)) => 1 + BYTES_PER_OFFSET as u32,
+        ProverInput(_) | StandardOp(_) => 1,
+        _ => 0,
+    }
 }
 
-const fn cost_estimate_push(num_bytes: usize) -> u32 {
-    num_bytes as u32
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fewer_rows_beat_smaller_code() {
+        // Folding a subtraction saves two CPU rows and an arithmetic row, at the
+        // cost of a larger push.
+        let before = [
+            Push(Literal(1.into())),
+            Push(Literal(0.into())),
+            StandardOp("SUB".into()),
+        ];
+        let after = [Push(Literal(!ethereum_types::U256::zero()))];
+        assert!(is_code_improved(&before, &after));
+
+        // With as many rows, the smaller code wins.
+        let before = [Push(Literal(0x1234.into()))];
+        let after = [Push(Literal(0x12.into()))];
+        assert!(is_code_improved(&before, &after));
+        assert!(!is_code_improved(&after, &before));
+    }
+
+    #[test]
+    fn rows_of_standard_ops() {
+        assert_eq!(
+            rows_estimate_standard_op("ADDMOD"),
+            TableRows {
+                cpu: 1,
+                memory: 2,
+                arithmetic: 2,
+                logic: 0,
+                byte_packing: 0,
+                keccak_sponge: 0,
+                keccak: 0,
+            }
+        );
+        assert_eq!(
+            rows_estimate_standard_op("MSTORE_32BYTES_4"),
+            TableRows {
+                cpu: 1,
+                memory: 5,
+                arithmetic: 0,
+                logic: 0,
+                byte_packing: 1,
+                keccak_sponge: 0,
+                keccak: 0,
+            }
+        );
+        assert_eq!(
+            rows_estimate_standard_op("KECCAK_GENERAL"),
+            TableRows {
+                cpu: 1,
+                memory: 33,
+                arithmetic: 0,
+                logic: 0,
+                byte_packing: 0,
+                keccak_sponge: 1,
+                keccak: 24,
+            }
+        );
+        assert_eq!(rows_estimate_standard_op("SWAP3").total(), 3);
+        // Divisions and right shifts take two rows of the arithmetic table.
+        assert_eq!(rows_estimate_standard_op("DIV").arithmetic, 2);
+        assert_eq!(rows_estimate_standard_op("SHR").arithmetic, 2);
+        assert_eq!(rows_estimate_standard_op("SHL").arithmetic, 1);
+    }
 }
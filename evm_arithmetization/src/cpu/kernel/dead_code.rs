@@ -0,0 +1,401 @@
+//! Finds the parts of the kernel assembly that are never executed or never
+//! assembled, so that they can be removed to shrink the kernel: routines that
+//! are unreachable from the kernel entry points, and macros and constants that
+//! are never used.
+//!
+//! A routine is the code following a global label, up to the next one. It is
+//! reachable if it is an entry point, if its label (or one of its local labels)
+//! is pushed or listed in a jump table by a reachable routine, or if the
+//! previous routine of the same file falls through to it.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+
+use crate::cpu::kernel::assembler::{
+    assemble, expand_conditional_blocks, expand_macros, find_macros, inline_constants,
+    set_source_file, MacroSignature,
+};
+use crate::cpu::kernel::ast::{BytesTarget, File, Item, PushTarget, StackReplacement};
+use crate::cpu::kernel::constants::evm_constants;
+use crate::cpu::kernel::parser::parse;
+use crate::cpu::kernel::stack::stack_manipulation::expand_stack_manipulation;
+
+/// The global labels at which the CPU starts executing kernel code: the
+/// initialization routine of each segment, the main routine at which the first
+/// segment exits the initialization, the syscall and exception jump tables, and
+/// the handlers of jumps to the first two offsets of the kernel.
+pub const KERNEL_ENTRY_POINTS: [&str; 6] = [
+    "init",
+    "main",
+    "syscall_jumptable",
+    "exception_jumptable",
+    "jumped_to_0",
+    "jumped_to_1",
+];
+
+/// The dead code found in a set of assembly files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeadCodeReport {
+    /// The global labels unreachable from the entry points, in the order of
+    /// the kernel, along with the size in bytes of their routine.
+    pub unreachable_routines: Vec<(String, usize)>,
+    /// The names and numbers of parameters of the macros which are never
+    /// called, sorted by name.
+    pub unused_macros: Vec<(String, usize)>,
+    /// The constants which are never referenced, sorted by name.
+    pub unused_constants: Vec<String>,
+}
+
+impl DeadCodeReport {
+    /// The total size in bytes of the unreachable routines.
+    pub fn unreachable_size(&self) -> usize {
+        self.unreachable_routines.iter().map(|(_, size)| size).sum()
+    }
+}
+
+impl Display for DeadCodeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Unreachable routines ({} bytes):",
+            self.unreachable_size()
+        )?;
+        for (label, size) in &self.unreachable_routines {
+            writeln!(f, "    {label} ({size} bytes)")?;
+        }
+        writeln!(f, "Unused macros:")?;
+        for (name, num_params) in &self.unused_macros {
+            writeln!(f, "    %{name} ({num_params} parameters)")?;
+        }
+        writeln!(f, "Unused constants:")?;
+        for name in &self.unused_constants {
+            writeln!(f, "    @{name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The code following a global label, or the start of a file, up to the next
+/// global label.
+struct Routine {
+    label: Option<String>,
+    /// The index of the file of the routine.
+    file: usize,
+    /// The labels pushed or listed in a jump table by the routine.
+    references: Vec<String>,
+    falls_through: bool,
+}
+
+/// Finds the dead code of the given assembly files, given by their names and
+/// contents, assuming that execution starts at the given global labels.
+pub fn find_dead_code(files: &[(&str, &str)], entry_points: &[&str]) -> DeadCodeReport {
+    // Analyze the code as assembled into the kernel, which enables no feature.
+    let active_features = HashSet::new();
+    let file_names: Vec<_> = files.iter().map(|&(name, _)| name.to_string()).collect();
+    let parsed: Vec<File> = files
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, contents))| {
+            let mut file = parse(contents, &active_features);
+            set_source_file(&mut file.body, i);
+            file
+        })
+        .collect();
+
+    let constants = evm_constants();
+    let macros = find_macros(&parsed);
+
+    let mut used_macros = HashSet::new();
+    let mut used_constants = HashSet::new();
+    for file in &parsed {
+        scan_items(&file.body, &mut used_macros, &mut used_constants);
+    }
+    // Macros called by used macros are used as well.
+    let mut pending: Vec<_> = used_macros.iter().cloned().collect();
+    while let Some(signature) = pending.pop() {
+        let Some(macro_) = macros.get(&signature) else {
+            continue;
+        };
+        let mut called = HashSet::new();
+        scan_items(&macro_.items, &mut called, &mut used_constants);
+        for signature in called {
+            if used_macros.insert(signature.clone()) {
+                pending.push(signature);
+            }
+        }
+    }
+
+    let mut routines = vec![];
+    let mut local_labels = vec![];
+    let mut macro_counter = 0;
+    for (i, file) in parsed.iter().enumerate() {
+        let mut body = file.body.clone();
+        body = expand_conditional_blocks(body);
+        body = expand_macros(body, &macros, &mut macro_counter);
+        body = inline_constants(body, &constants);
+        body = expand_stack_manipulation(body);
+        local_labels.push(split_routines(body, i, &mut routines));
+    }
+    let reachable = find_reachable(&routines, &local_labels, entry_points);
+
+    // The sizes are taken from the assembled code, where the routines follow
+    // each other in the same order.
+    let kernel = assemble(parsed, file_names, constants.clone(), true);
+    let labels: Vec<_> = routines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, routine)| Some((i, routine.label.as_ref()?)))
+        .collect();
+    let offsets: Vec<_> = labels
+        .iter()
+        .map(|(_, label)| kernel.global_labels[*label])
+        .chain([kernel.code.len()])
+        .collect();
+    let unreachable_routines = labels
+        .iter()
+        .zip(offsets.windows(2))
+        .filter(|((i, _), _)| !reachable.contains(i))
+        .map(|((_, label), window)| (label.to_string(), window[1] - window[0]))
+        .collect();
+
+    let mut unused_macros: Vec<_> = macros
+        .keys()
+        .filter(|signature| !used_macros.contains(*signature))
+        .map(|signature| (signature.name.clone(), signature.num_params))
+        .collect();
+    unused_macros.sort();
+    let mut unused_constants: Vec<_> = constants
+        .into_keys()
+        .filter(|name| !used_constants.contains(name))
+        .collect();
+    unused_constants.sort();
+
+    DeadCodeReport {
+        unreachable_routines,
+        unused_macros,
+        unused_constants,
+    }
+}
+
+/// Records the macros called and the constants referenced by the given items,
+/// outside of macro definitions.
+fn scan_items(
+    items: &[Item],
+    used_macros: &mut HashSet<MacroSignature>,
+    used_constants: &mut HashSet<String>,
+) {
+    for item in items {
+        match item {
+            Item::ConditionalBlock(_, items) | Item::Repeat(_, items) => {
+                scan_items(items, used_macros, used_constants)
+            }
+            Item::MacroCall(name, args) => {
+                used_macros.insert(MacroSignature {
+                    name: name.clone(),
+                    num_params: args.len(),
+                });
+                for arg in args {
+                    if let PushTarget::Constant(name) = arg {
+                        used_constants.insert(name.clone());
+                    }
+                }
+            }
+            Item::Push(PushTarget::Constant(name)) => {
+                used_constants.insert(name.clone());
+            }
+            Item::StackManipulation(_, replacements) => {
+                for replacement in replacements {
+                    if let StackReplacement::Constant(name) = replacement {
+                        used_constants.insert(name.clone());
+                    }
+                }
+            }
+            Item::Bytes(targets) => {
+                for target in targets {
+                    if let BytesTarget::Constant(name) = target {
+                        used_constants.insert(name.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Splits a fully expanded file into routines, returning the index of the
+/// routine declaring each local label of the file.
+fn split_routines(
+    body: Vec<Item>,
+    file: usize,
+    routines: &mut Vec<Routine>,
+) -> HashMap<String, usize> {
+    let new_routine = |label| Routine {
+        label,
+        file,
+        references: vec![],
+        falls_through: true,
+    };
+
+    let mut local_labels = HashMap::new();
+    let mut current = new_routine(None);
+    for item in body {
+        match item {
+            Item::GlobalLabelDeclaration(label) => {
+                routines.push(current);
+                current = new_routine(Some(label));
+            }
+            // The code following a label may be jumped to.
+            Item::LocalLabelDeclaration(label) => {
+                local_labels.insert(label, routines.len());
+                current.falls_through = true;
+            }
+            Item::SourceMarker(_) => {}
+            Item::Push(PushTarget::Label(label)) => {
+                current.references.push(label);
+                current.falls_through = true;
+            }
+            Item::Jumptable(labels) => {
+                current.references.extend(labels);
+                current.falls_through = false;
+            }
+            // Tables may be indexed past their end, into the following ones.
+            Item::Bytes(_) => current.falls_through = true,
+            Item::StandardOp(op) => current.falls_through = !ends_execution(&op),
+            _ => current.falls_through = true,
+        }
+    }
+    routines.push(current);
+    local_labels
+}
+
+/// Returns the indices of the routines reachable from the given entry points.
+fn find_reachable(
+    routines: &[Routine],
+    local_labels: &[HashMap<String, usize>],
+    entry_points: &[&str],
+) -> HashSet<usize> {
+    let global_labels: HashMap<_, _> = routines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, routine)| Some((routine.label.as_deref()?, i)))
+        .collect();
+
+    let mut reachable = HashSet::new();
+    let mut pending: Vec<_> = entry_points
+        .iter()
+        .filter_map(|label| global_labels.get(label).copied())
+        .collect();
+    while let Some(i) = pending.pop() {
+        if !reachable.insert(i) {
+            continue;
+        }
+        let routine = &routines[i];
+        // Local labels shadow global ones.
+        pending.extend(routine.references.iter().filter_map(|label| {
+            local_labels[routine.file]
+                .get(label)
+                .or_else(|| global_labels.get(label.as_str()))
+                .copied()
+        }));
+        // Falling off the end of a file isn't followed.
+        if routine.falls_through
+            && routines
+                .get(i + 1)
+                .is_some_and(|next| next.file == routine.file)
+        {
+            pending.push(i + 1);
+        }
+    }
+    reachable
+}
+
+/// Whether the opcode never continues to the next instruction.
+fn ends_execution(op: &str) -> bool {
+    matches!(
+        op,
+        "JUMP"
+            | "STOP"
+            | "RETURN"
+            | "REVERT"
+            | "INVALID"
+            | "SELFDESTRUCT"
+            | "PANIC"
+            | "EXIT_KERNEL"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::kernel::aggregator::{KERNEL, KERNEL_FILES};
+
+    #[test]
+    fn find_dead_code_in_file() {
+        let code = "
+            %macro helper
+                %inner
+            %endmacro
+
+            %macro inner
+                PUSH @SEGMENT_STACK
+                POP
+            %endmacro
+
+            %macro unused
+                ADD
+            %endmacro
+
+            global entry:
+                %helper
+                PUSH callee
+                JUMP
+            global dead:
+                PUSH 1
+                STOP
+            global callee:
+                PUSH 0
+            global fallen_into:
+                PUSH resume
+                JUMP
+            resume:
+                STOP
+            ";
+        let report = find_dead_code(&[("test.asm", code)], &["entry"]);
+
+        assert_eq!(report.unreachable_routines, vec![("dead".to_string(), 3)]);
+        assert_eq!(report.unused_macros, vec![("unused".to_string(), 0)]);
+        assert!(!report
+            .unused_constants
+            .contains(&"SEGMENT_STACK".to_string()));
+    }
+
+    #[test]
+    fn kernel_dead_code() {
+        let report = find_dead_code(&KERNEL_FILES, &KERNEL_ENTRY_POINTS);
+
+        // Entry points which aren't global labels of the kernel would be
+        // silently skipped.
+        for label in KERNEL_ENTRY_POINTS {
+            assert!(
+                KERNEL.global_labels.contains_key(label),
+                "{label} should be a global label"
+            );
+        }
+        // The entry points themselves, and routines reached through calls,
+        // fall-throughs and jump tables.
+        for label in KERNEL_ENTRY_POINTS.into_iter().chain([
+            "halt",
+            "halt_final",
+            "process_normalized_txn",
+            "sys_sload",
+        ]) {
+            assert!(
+                report
+                    .unreachable_routines
+                    .iter()
+                    .all(|(unreachable, _)| unreachable != label),
+                "{label} should be reachable"
+            );
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! An interactive debugger for the kernel, running the interpreter on a set of
+//! `GenerationInputs` one instruction at a time.
+//!
+//! It supports breakpoints on global kernel labels or on user code offsets,
+//! single-stepping, and the inspection of the stack, of the memory segments,
+//! of the global and context metadata, and of the accounts and storage linked
+//! lists.
+
+use std::fmt::Debug;
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, bail, Result};
+use ethereum_types::U256;
+use plonky2::hash::hash_types::RichField;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::generation::linked_list::LinkedList;
+use crate::generation::prover_input::{
+    ACCOUNTS_LINKED_LIST_NODE_SIZE, STORAGE_LINKED_LIST_NODE_SIZE,
+};
+use crate::generation::state::State;
+use crate::memory::segments::Segment;
+use crate::witness::memory::MemoryAddress;
+use crate::witness::util::stack_peek;
+use crate::GenerationInputs;
+
+const HELP: &str = "\
+Commands:
+  break <label>                 Break when the kernel reaches a global label.
+  break user <pc> [context]     Break when user code reaches `pc`, optionally
+                                only in the given context.
+  breakpoints                   List the breakpoints.
+  delete <n>                    Delete the n-th breakpoint.
+  step [n]                      Execute n instructions (default 1).
+  continue                      Execute until a breakpoint or the end.
+  regs                          Print the registers and the current location.
+  stack                         Print the stack, from its top.
+  mem <segment> <virt> [len] [context]
+                                Print `len` words of a memory segment.
+  global [field]                Print one or all global metadata fields.
+  ctx [field] [context]         Print one or all context metadata fields.
+  accounts                      Print the accounts linked list.
+  storage                       Print the storage linked list.
+  help                          Print this message.
+  quit                          Exit the debugger.
+Numbers can be given in decimal or in `0x`-prefixed hexadecimal.";
+
+/// A point at which the execution should pause.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Breakpoint {
+    /// Pause when the kernel reaches the given global label.
+    Label { label: String, offset: usize },
+    /// Pause when user code reaches `pc`, in the given context if any.
+    User { pc: usize, context: Option<usize> },
+}
+
+impl Breakpoint {
+    fn is_hit<F: RichField>(&self, interpreter: &Interpreter<F>) -> bool {
+        let registers = interpreter.get_registers();
+        match *self {
+            Breakpoint::Label { offset, .. } => {
+                registers.is_kernel && registers.program_counter == offset
+            }
+            Breakpoint::User { pc, context } => {
+                !registers.is_kernel
+                    && registers.program_counter == pc
+                    && context.is_none_or(|ctx| ctx == registers.context)
+            }
+        }
+    }
+}
+
+/// Why the debugger stopped executing instructions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum StopReason {
+    Steps,
+    Breakpoint(usize),
+    Halted,
+    Failed(String),
+}
+
+/// An interactive debugger over the kernel interpreter.
+pub struct KernelDebugger<F: RichField> {
+    interpreter: Interpreter<F>,
+    breakpoints: Vec<Breakpoint>,
+    /// The error which stopped the execution, if any.
+    error: Option<String>,
+}
+
+impl<F: RichField> KernelDebugger<F> {
+    /// Creates a debugger paused at the start of the kernel, about to process
+    /// the given inputs.
+    pub fn new(inputs: &GenerationInputs) -> Self {
+        let interpreter = Interpreter::new_with_generation_inputs(
+            KERNEL.global_labels["init"],
+            vec![],
+            inputs,
+            None,
+        );
+
+        Self {
+            interpreter,
+            breakpoints: vec![],
+            error: None,
+        }
+    }
+
+    /// Runs the debugger, reading commands from `input` until it is exhausted
+    /// or a `quit` command, and writing the results to `output`. If
+    /// `prompt` is set, a prompt is written before reading each command.
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        mut input: R,
+        mut output: W,
+        prompt: bool,
+    ) -> Result<()> {
+        let mut line = String::new();
+        loop {
+            if prompt {
+                write!(output, "(kdb) ")?;
+                output.flush()?;
+            }
+
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if matches!(words.first(), Some(&"quit" | &"q")) {
+                return Ok(());
+            }
+            if let Err(e) = self.execute(&words, &mut output) {
+                writeln!(output, "error: {e}")?;
+            }
+        }
+    }
+
+    /// Executes a single command, given as a list of words.
+    fn execute<W: Write>(&mut self, words: &[&str], out: &mut W) -> Result<()> {
+        let Some((&command, args)) = words.split_first() else {
+            return Ok(());
+        };
+
+        match command {
+            "help" | "h" => writeln!(out, "{HELP}")?,
+            "break" | "b" => {
+                let breakpoint = parse_breakpoint(args)?;
+                writeln!(
+                    out,
+                    "Breakpoint {}: {}",
+                    self.breakpoints.len(),
+                    describe_breakpoint(&breakpoint)
+                )?;
+                self.breakpoints.push(breakpoint);
+            }
+            "breakpoints" => {
+                for (i, breakpoint) in self.breakpoints.iter().enumerate() {
+                    writeln!(out, "{i}: {}", describe_breakpoint(breakpoint))?;
+                }
+            }
+            "delete" | "d" => {
+                let idx = parse_number(expect_arg(args, 0, "breakpoint index")?)?;
+                if idx >= self.breakpoints.len() {
+                    bail!("No breakpoint {idx}");
+                }
+                self.breakpoints.remove(idx);
+            }
+            "step" | "s" => {
+                let n = args.first().map(|n| parse_number(n)).transpose()?;
+                let reason = self.step(n.unwrap_or(1));
+                self.report(&reason, out)?;
+            }
+            "continue" | "c" => {
+                let reason = self.step(usize::MAX);
+                self.report(&reason, out)?;
+            }
+            "regs" | "where" => self.print_registers(out)?,
+            "stack" => {
+                let state = &self.interpreter.generation_state;
+                for i in 0..state.registers.stack_len {
+                    let value = stack_peek(state, i).map_err(|e| anyhow!("{e:?}"))?;
+                    writeln!(out, "{i}: {value:#x}")?;
+                }
+            }
+            "mem" => self.print_memory(args, out)?,
+            "global" => {
+                let fields = match args.first() {
+                    Some(name) => {
+                        vec![parse_named(&GlobalMetadata::all(), name, |f| f.var_name())?]
+                    }
+                    None => GlobalMetadata::all().to_vec(),
+                };
+                let memory = &self.interpreter.generation_state.memory;
+                for field in fields {
+                    let value = memory.read_global_metadata(field);
+                    writeln!(out, "{field:?}: {value:#x}")?;
+                }
+            }
+            "ctx" => {
+                let (fields, context) = match args {
+                    [] => (ContextMetadata::all().to_vec(), None),
+                    [name, rest @ ..] => {
+                        let field = parse_named(&ContextMetadata::all(), name, |f| f.var_name())?;
+                        let context = rest.first().map(|c| parse_number(c)).transpose()?;
+                        (vec![field], context)
+                    }
+                };
+                let context = context.unwrap_or(self.interpreter.get_registers().context);
+                let memory = &self.interpreter.generation_state.memory;
+                for field in fields {
+                    let address =
+                        MemoryAddress::new(context, Segment::ContextMetadata, field.unscale());
+                    let value = memory.get_with_init(address);
+                    writeln!(out, "{field:?}: {value:#x}")?;
+                }
+            }
+            "accounts" => self.print_accounts(out)?,
+            "storage" => self.print_storage(out)?,
+            _ => bail!("Unknown command `{command}`, try `help`"),
+        }
+
+        Ok(())
+    }
+
+    /// Executes up to `n` instructions, stopping early at a breakpoint, at the
+    /// end of the execution or on an error.
+    fn step(&mut self, n: usize) -> StopReason {
+        for i in 0..n {
+            if let Some(error) = &self.error {
+                return StopReason::Failed(error.clone());
+            }
+            if self.interpreter.at_halt() {
+                return StopReason::Halted;
+            }
+            // Do not stop on the breakpoint we are currently paused on.
+            if i > 0 {
+                if let Some(idx) = self
+                    .breakpoints
+                    .iter()
+                    .position(|b| b.is_hit(&self.interpreter))
+                {
+                    return StopReason::Breakpoint(idx);
+                }
+            }
+
+            if let Err(e) = self.interpreter.transition() {
+                let error = format!("{e:?}");
+                self.error = Some(error.clone());
+                return StopReason::Failed(error);
+            }
+        }
+
+        StopReason::Steps
+    }
+
+    fn report<W: Write>(&self, reason: &StopReason, out: &mut W) -> Result<()> {
+        match reason {
+            StopReason::Steps => {}
+            StopReason::Breakpoint(idx) => writeln!(out, "Hit breakpoint {idx}")?,
+            StopReason::Halted => writeln!(out, "The kernel has halted")?,
+            StopReason::Failed(error) => writeln!(out, "The execution failed: {error}")?,
+        }
+        self.print_location(out)
+    }
+
+    fn print_location<W: Write>(&self, out: &mut W) -> Result<()> {
+        let registers = self.interpreter.get_registers();
+        if registers.is_kernel {
+            writeln!(out, "{}", KERNEL.symbolize(registers.program_counter))?;
+        } else {
+            writeln!(
+                out,
+                "user code at pc={} in context {}",
+                registers.program_counter, registers.context
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_registers<W: Write>(&self, out: &mut W) -> Result<()> {
+        let registers = self.interpreter.get_registers();
+        writeln!(out, "clock: {}", self.interpreter.get_clock())?;
+        writeln!(out, "pc: {}", registers.program_counter)?;
+        writeln!(out, "is_kernel: {}", registers.is_kernel)?;
+        writeln!(out, "context: {}", registers.context)?;
+        writeln!(out, "stack_len: {}", registers.stack_len)?;
+        writeln!(out, "gas_used: {}", registers.gas_used)?;
+        self.print_location(out)
+    }
+
+    fn print_memory<W: Write>(&self, args: &[&str], out: &mut W) -> Result<()> {
+        let segment = parse_named(&Segment::all(), expect_arg(args, 0, "segment")?, |s| {
+            s.var_name()
+        })?;
+        let virt = parse_number(expect_arg(args, 1, "offset")?)?;
+        let len = args.get(2).map(|len| parse_number(len)).transpose()?;
+        let context = args.get(3).map(|ctx| parse_number(ctx)).transpose()?;
+        let context = context.unwrap_or(self.interpreter.get_registers().context);
+
+        let memory = &self.interpreter.generation_state.memory;
+        for virt in virt..virt + len.unwrap_or(1) {
+            let value = memory.get_with_init(MemoryAddress::new(context, segment, virt));
+            writeln!(out, "{segment:?}[{virt}]: {value:#x}")?;
+        }
+        Ok(())
+    }
+
+    fn print_accounts<W: Write>(&self, out: &mut W) -> Result<()> {
+        let mem = self
+            .interpreter
+            .generation_state
+            .memory
+            .get_preinit_memory(Segment::AccountsLinkedList);
+        let list = LinkedList::<ACCOUNTS_LINKED_LIST_NODE_SIZE>::from_mem_and_segment(
+            &mem,
+            Segment::AccountsLinkedList,
+        )
+        .map_err(|e| anyhow!("{e:?}"))?;
+
+        for [addr, ptr, initial_ptr, _] in list.take_while(|node| node[0] != U256::MAX) {
+            writeln!(
+                out,
+                "{addr:#x}: payload at {ptr}, initial payload at {initial_ptr}"
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_storage<W: Write>(&self, out: &mut W) -> Result<()> {
+        let mem = self
+            .interpreter
+            .generation_state
+            .memory
+            .get_preinit_memory(Segment::StorageLinkedList);
+        let list = LinkedList::<STORAGE_LINKED_LIST_NODE_SIZE>::from_mem_and_segment(
+            &mem,
+            Segment::StorageLinkedList,
+        )
+        .map_err(|e| anyhow!("{e:?}"))?;
+
+        for [addr, key, ptr, initial_ptr, _] in list.take_while(|node| node[0] != U256::MAX) {
+            writeln!(
+                out,
+                "{addr:#x} {key:#x}: value at {ptr}, initial value at {initial_ptr}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn describe_breakpoint(breakpoint: &Breakpoint) -> String {
+    match breakpoint {
+        Breakpoint::Label { label, offset } => format!("kernel label {label} (offset {offset})"),
+        Breakpoint::User { pc, context: None } => format!("user code at pc={pc}"),
+        Breakpoint::User {
+            pc,
+            context: Some(ctx),
+        } => format!("user code at pc={pc} in context {ctx}"),
+    }
+}
+
+fn parse_breakpoint(args: &[&str]) -> Result<Breakpoint> {
+    match args {
+        ["user", pc] => Ok(Breakpoint::User {
+            pc: parse_number(pc)?,
+            context: None,
+        }),
+        ["user", pc, context] => Ok(Breakpoint::User {
+            pc: parse_number(pc)?,
+            context: Some(parse_number(context)?),
+        }),
+        [label] => {
+            let offset = *KERNEL
+                .global_labels
+                .get(*label)
+                .ok_or_else(|| anyhow!("Unknown global label `{label}`"))?;
+            Ok(Breakpoint::Label {
+                label: label.to_string(),
+                offset,
+            })
+        }
+        _ => bail!("Usage: break <label> | break user <pc> [context]"),
+    }
+}
+
+fn expect_arg<'a>(args: &[&'a str], idx: usize, name: &str) -> Result<&'a str> {
+    args.get(idx)
+        .copied()
+        .ok_or_else(|| anyhow!("Missing {name}"))
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+fn parse_number(s: &str) -> Result<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| anyhow!("Invalid number `{s}`"))
+}
+
+/// Finds the value whose `Debug` name or assembly variable name matches
+/// `name`, ignoring case and underscores.
+fn parse_named<T: Copy + Debug>(
+    values: &[T],
+    name: &str,
+    var_name: impl Fn(&T) -> &'static str,
+) -> Result<T> {
+    let normalize = |s: &str| s.replace('_', "").to_lowercase();
+    let normalized = normalize(name);
+    values
+        .iter()
+        .copied()
+        .find(|value| {
+            normalize(&format!("{value:?}")) == normalized
+                || normalize(var_name(value)) == normalized
+        })
+        .ok_or_else(|| anyhow!("Unknown name `{name}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+    use super::*;
+
+    fn run_commands(debugger: &mut KernelDebugger<F>, commands: &str) -> String {
+        let mut output = vec![];
+        debugger
+            .run(commands.as_bytes(), &mut output, false)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn parse_names() {
+        let all = Segment::all();
+        let var_name = |s: &Segment| s.var_name();
+        assert_eq!(
+            parse_named(&all, "TrieData", var_name).unwrap(),
+            Segment::TrieData
+        );
+        assert_eq!(
+            parse_named(&all, "SEGMENT_TRIE_DATA", var_name).unwrap(),
+            Segment::TrieData
+        );
+        assert_eq!(
+            parse_named(&all, "trie_data", var_name).unwrap(),
+            Segment::TrieData
+        );
+        assert!(parse_named(&all, "foo", var_name).is_err());
+
+        assert_eq!(parse_number("0x1f").unwrap(), 31);
+        assert_eq!(parse_number("31").unwrap(), 31);
+        assert!(parse_number("1f").is_err());
+    }
+
+    #[test]
+    fn break_on_label() {
+        let mut debugger = KernelDebugger::<F>::new(&GenerationInputs::default());
+        let output = run_commands(&mut debugger, "break main\ncontinue\nregs\n");
+
+        assert!(output.starts_with("Breakpoint 0: kernel label main"));
+        assert!(output.contains("Hit breakpoint 0\nmain"));
+        let registers = debugger.interpreter.get_registers();
+        assert!(registers.is_kernel);
+        assert_eq!(registers.program_counter, KERNEL.global_labels["main"]);
+
+        // Stepping does not stop on the breakpoint it starts from.
+        let clock = debugger.interpreter.get_clock();
+        run_commands(&mut debugger, "step 3\n");
+        assert_eq!(debugger.interpreter.get_clock(), clock + 3);
+    }
+
+    #[test]
+    fn unknown_commands() {
+        let mut debugger = KernelDebugger::<F>::new(&GenerationInputs::default());
+        let output = run_commands(
+            &mut debugger,
+            "foo\nbreak no_such_label\nmem NoSegment 0\nquit\nregs\n",
+        );
+
+        assert_eq!(
+            output,
+            "error: Unknown command `foo`, try `help`\n\
+             error: Unknown global label `no_such_label`\n\
+             error: Unknown name `NoSegment`\n"
+        );
+    }
+}
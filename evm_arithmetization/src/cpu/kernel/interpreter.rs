@@ -18,6 +18,8 @@ use crate::byte_packing::byte_packing_stark::BytePackingOp;
 use crate::cpu::columns::CpuColumnsView;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::profiler::Profiler;
+use crate::cpu::kernel::tracer::Tracer;
 use crate::generation::debug_inputs;
 use crate::generation::mpt::{load_linked_lists_and_txn_and_receipt_mpts, TrieRootPtrs};
 use crate::generation::rlp::all_rlp_prover_inputs_reversed;
@@ -36,6 +38,7 @@ use crate::witness::memory::{
 };
 use crate::witness::operation::Operation;
 use crate::witness::state::RegistersState;
+use crate::witness::traces::arithmetic_op_rows;
 use crate::witness::transition::{
     decode, fill_op_flag, get_op_special_length, log_kernel_instruction, Transition,
 };
@@ -53,9 +56,13 @@ pub(crate) struct Interpreter<F: Field> {
     /// The interpreter will halt only if the current context matches
     /// halt_context
     pub(crate) halt_context: Option<usize>,
-    /// Counts the number of appearances of each opcode. For debugging purposes.
-    #[allow(unused)]
-    pub(crate) opcode_count: [usize; 0x100],
+    /// Attributes the costs of the execution to kernel routines, if profiling.
+    pub(crate) profiler: Option<Profiler>,
+    /// Records the user-mode instructions of the execution, if tracing.
+    pub(crate) tracer: Option<Tracer>,
+    /// The clock at each iteration of the transaction loop of the kernel, if
+    /// recorded.
+    pub(crate) txn_loop_clocks: Option<Vec<usize>>,
     jumpdest_table: HashMap<usize, BTreeSet<usize>>,
     /// `true` if the we are currently carrying out a jumpdest analysis.
     pub(crate) is_jumpdest_analysis: bool,
@@ -126,6 +133,9 @@ pub(crate) fn set_registers_and_run<F: Field>(
     interpreter.generation_state.registers.program_counter = KERNEL.global_labels["init"];
     interpreter.generation_state.registers.is_kernel = true;
     interpreter.clock = 0;
+    if let Some(profiler) = &mut interpreter.profiler {
+        profiler.start_segment(&registers);
+    }
 
     // Write initial registers.
     [
@@ -176,7 +186,9 @@ impl<F: Field> Interpreter<F> {
             // while the label `halt` is the halting label in the kernel.
             halt_offsets: vec![DEFAULT_HALT_OFFSET, KERNEL.global_labels["halt_final"]],
             halt_context: None,
-            opcode_count: [0; 256],
+            profiler: None,
+            tracer: None,
+            txn_loop_clocks: None,
             jumpdest_table: HashMap::new(),
             is_jumpdest_analysis: false,
             clock: 0,
@@ -207,7 +219,9 @@ impl<F: Field> Interpreter<F> {
             generation_state: state.soft_clone(),
             halt_offsets: vec![halt_offset],
             halt_context: Some(halt_context),
-            opcode_count: [0; 256],
+            profiler: None,
+            tracer: None,
+            txn_loop_clocks: None,
             jumpdest_table: HashMap::new(),
             is_jumpdest_analysis: true,
             clock: 0,
@@ -263,10 +277,7 @@ impl<F: Field> Interpreter<F> {
 
         // Set `GlobalMetadata` values.
         let metadata = &inputs.block_metadata;
-        #[cfg(feature = "cdk_erigon")]
-        let burn_addr = inputs
-            .burn_addr
-            .map_or_else(U256::max_value, |addr| U256::from_big_endian(&addr.0));
+        let burn_addr = inputs.chain_profile.burn_addr_metadata(inputs.burn_addr);
         let global_metadata_to_set = [
             (
                 GlobalMetadata::BlockBeneficiary,
@@ -332,8 +343,15 @@ impl<F: Field> Interpreter<F> {
             ),
             (GlobalMetadata::KernelHash, h2u(KERNEL.code_hash)),
             (GlobalMetadata::KernelLen, KERNEL.code.len().into()),
-            #[cfg(feature = "cdk_erigon")]
             (GlobalMetadata::BurnAddr, burn_addr),
+            (
+                GlobalMetadata::P256VerifyEnabled,
+                U256::from(inputs.chain_profile.has_p256_verify() as u8),
+            ),
+            (
+                GlobalMetadata::Bls12381Enabled,
+                U256::from(inputs.chain_profile.has_bls12_381() as u8),
+            ),
         ];
 
         self.set_global_metadata_multi_fields(&global_metadata_to_set);
@@ -410,20 +428,7 @@ impl<F: Field> Interpreter<F> {
     }
 
     pub(crate) fn run(&mut self) -> Result<(RegistersState, Option<MemoryState>), anyhow::Error> {
-        let (final_registers, final_mem) = self.run_cpu(self.max_cpu_len_log)?;
-
-        #[cfg(debug_assertions)]
-        {
-            println!("Opcode count:");
-            for i in 0..0x100 {
-                if self.opcode_count[i] > 0 {
-                    println!("{}: {}", get_mnemonic(i as u8), self.opcode_count[i])
-                }
-            }
-            println!("Total: {}", self.opcode_count.into_iter().sum::<usize>());
-        }
-
-        Ok((final_registers, final_mem))
+        self.run_cpu(self.max_cpu_len_log)
     }
 
     /// Returns the max number of CPU cycles.
@@ -559,11 +564,22 @@ impl<F: Field> State<F> for Interpreter<F> {
         // We don't push anything, but increment the clock to match
         // an actual proof generation.
         self.clock += 1;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(|costs| costs.cpu_cycles += 1);
+        }
     }
 
-    fn push_logic(&mut self, _op: logic::Operation) {}
+    fn push_logic(&mut self, _op: logic::Operation) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(|costs| costs.logic_rows += 1);
+        }
+    }
 
-    fn push_arithmetic(&mut self, _op: arithmetic::Operation) {}
+    fn push_arithmetic(&mut self, op: arithmetic::Operation) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(|costs| costs.arithmetic_rows += arithmetic_op_rows(&op) as u64);
+        }
+    }
 
     fn push_byte_packing(&mut self, _op: BytePackingOp) {}
 
@@ -571,9 +587,16 @@ impl<F: Field> State<F> for Interpreter<F> {
 
     fn push_keccak_bytes(&mut self, _input: [u8; KECCAK_WIDTH_BYTES], _clock: usize) {}
 
-    fn push_keccak_sponge(&mut self, _op: KeccakSpongeOp) {}
+    fn push_keccak_sponge(&mut self, _op: KeccakSpongeOp) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(|costs| costs.keccak_sponge_calls += 1);
+        }
+    }
 
     fn rollback(&mut self, checkpoint: GenerationStateCheckpoint) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.discard();
+        }
         self.clock = checkpoint.clock;
         self.generation_state.rollback(checkpoint)
     }
@@ -595,6 +618,11 @@ impl<F: Field> State<F> for Interpreter<F> {
     fn apply_ops(&mut self, _checkpoint: GenerationStateCheckpoint) {
         self.apply_memops()
             .expect("We should not have nonzero initial values in non-preinitialized segments");
+        if let Some(profiler) = &mut self.profiler {
+            let memory_ops = self.generation_state.traces.memory_ops.len() as u64;
+            profiler.record(|costs| costs.memory_ops += memory_ops);
+            profiler.commit();
+        }
     }
 
     fn get_stack(&self) -> Vec<U256> {
@@ -662,6 +690,23 @@ impl<F: Field> State<F> for Interpreter<F> {
         let registers = self.generation_state.registers;
         let (mut row, opcode) = self.base_row();
 
+        if let Some(profiler) = &mut self.profiler {
+            profiler.step(&registers, opcode);
+        }
+        if !registers.is_kernel
+            && let Some(tracer) = &mut self.tracer
+        {
+            tracer.step(&self.generation_state, opcode);
+        }
+        // The instruction may be executed again after a rollback, at the same clock.
+        if registers.is_kernel
+            && let Some(clocks) = &mut self.txn_loop_clocks
+            && registers.program_counter == KERNEL.global_labels["txn_loop"]
+            && clocks.last() != Some(&self.clock)
+        {
+            clocks.push(self.clock);
+        }
+
         let op = decode(registers, opcode)?;
 
         fill_op_flag(op, &mut row);
@@ -746,8 +791,8 @@ impl<F: Field> Transition<F> for Interpreter<F> {
     }
 }
 
-#[cfg(debug_assertions)]
-fn get_mnemonic(opcode: u8) -> &'static str {
+/// Returns the mnemonic of an opcode, or `INVALID` for unassigned opcodes.
+pub(crate) const fn get_mnemonic(opcode: u8) -> &'static str {
     match opcode {
         0x00 => "STOP",
         0x01 => "ADD",
@@ -939,7 +984,7 @@ fn get_mnemonic(opcode: u8) -> &'static str {
         0xfd => "REVERT",
         0xfe => "INVALID",
         0xff => "SELFDESTRUCT",
-        _ => panic!("Unrecognized opcode {opcode}"),
+        _ => "INVALID",
     }
 }
 
@@ -3,11 +3,18 @@ pub mod assembler;
 mod ast;
 pub(crate) mod constants;
 mod cost_estimator;
+pub mod dead_code;
+pub mod debugger;
 pub(crate) mod keccak_util;
 pub mod opcodes;
 mod optimizer;
 mod parser;
+pub mod profiler;
+pub mod source_map;
 pub mod stack;
+#[cfg(test)]
+mod stack_effect;
+pub mod tracer;
 mod utils;
 
 pub(crate) mod interpreter;
@@ -20,7 +27,7 @@ pub use constants::global_exit_root;
 #[cfg(test)]
 mod tests;
 
-use assembler::assemble;
+use assembler::{assemble, Kernel};
 use parser::parse;
 
 use crate::cpu::kernel::constants::evm_constants;
@@ -28,7 +35,20 @@ use crate::cpu::kernel::constants::evm_constants;
 /// Assemble files, outputting bytes.
 /// This is for debugging the kernel only.
 pub fn assemble_to_bytes(files: &[String]) -> Vec<u8> {
-    let parsed_files: Vec<_> = files.iter().map(|f| parse(f, &HashSet::new())).collect();
-    let kernel = assemble(parsed_files, evm_constants(), true);
-    kernel.code
+    let named_files: Vec<_> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (format!("<file {i}>"), f.clone()))
+        .collect();
+    assemble_files(&named_files).code
+}
+
+/// Assemble files, given along with their names, into a kernel.
+/// This is for debugging the kernel only.
+pub fn assemble_files(files: &[(String, String)]) -> Kernel {
+    let (file_names, parsed_files): (Vec<_>, Vec<_>) = files
+        .iter()
+        .map(|(name, f)| (name.clone(), parse(f, &HashSet::new())))
+        .unzip();
+    assemble(parsed_files, file_names, evm_constants(), true)
 }
@@ -1,3 +1,6 @@
+use core::fmt::Debug;
+use std::collections::{HashMap, HashSet};
+
 use ethereum_types::U256;
 use Item::{Push, StandardOp};
 use PushTarget::Literal;
@@ -8,29 +11,75 @@ use crate::cpu::kernel::ast::{Item, PushTarget};
 use crate::cpu::kernel::cost_estimator::is_code_improved;
 use crate::cpu::kernel::utils::{replace_windows, u256_from_bool};
 
-pub(crate) fn optimize_asm(code: &mut Vec<Item>) {
+/// Code which can be rewritten by the optimizer.
+pub(crate) trait Code: Clone + PartialEq {
+    /// Like `replace_windows`, but operating on the items of the code.
+    fn replace_windows<const W: usize, F>(&mut self, maybe_replace: F)
+    where
+        F: Fn([Item; W]) -> Option<Vec<Item>>;
+
+    /// Iterates over the items of the code.
+    fn items(&self) -> impl Iterator<Item = &Item>;
+}
+
+impl Code for Vec<Item> {
+    fn replace_windows<const W: usize, F>(&mut self, maybe_replace: F)
+    where
+        F: Fn([Item; W]) -> Option<Vec<Item>>,
+    {
+        replace_windows(self, maybe_replace)
+    }
+
+    fn items(&self) -> impl Iterator<Item = &Item> {
+        self.iter()
+    }
+}
+
+/// Items tagged with some metadata, such as their source location. Items
+/// replaced by the optimizer inherit the tag of the first item of the window
+/// they replace.
+impl<T: Clone + Debug + PartialEq> Code for Vec<(Item, T)> {
+    fn replace_windows<const W: usize, F>(&mut self, maybe_replace: F)
+    where
+        F: Fn([Item; W]) -> Option<Vec<Item>>,
+    {
+        replace_windows(self, |window: [(Item, T); W]| {
+            let tag = window[0].1.clone();
+            maybe_replace(window.map(|(item, _)| item))
+                .map(|items| items.into_iter().map(|item| (item, tag.clone())).collect())
+        })
+    }
+
+    fn items(&self) -> impl Iterator<Item = &Item> {
+        self.iter().map(|(item, _)| item)
+    }
+}
+
+pub(crate) fn optimize_asm(code: &mut impl Code) {
     // Run the optimizer until nothing changes.
     loop {
         let old_code = code.clone();
         optimize_asm_once(code);
-        if code == &old_code {
+        if *code == old_code {
             break;
         }
     }
 }
 
 /// A single optimization pass.
-fn optimize_asm_once(code: &mut Vec<Item>) {
+fn optimize_asm_once(code: &mut impl Code) {
     constant_propagation(code);
     identity_operations(code);
     no_op_jumps(code);
+    jump_threading(code);
     remove_swapped_pushes(code);
+    canonicalize_dups_and_swaps(code);
     remove_swaps_commutative(code);
     remove_ignored_values(code);
 }
 
 /// Constant propagation.
-fn constant_propagation(code: &mut Vec<Item>) {
+fn constant_propagation(code: &mut impl Code) {
     // Constant propagation for unary ops: `[PUSH x, UNARYOP] -> [PUSH UNARYOP(x)]`
     replace_windows_if_better(code, |window| {
         if let [Push(Literal(x)), StandardOp(op)] = window {
@@ -78,10 +127,10 @@ fn constant_propagation(code: &mut Vec<Item>) {
 }
 
 /// Remove identity operations, e.g. `[PUSH 1, MUL] -> []`.
-fn identity_operations(code: &mut Vec<Item>) {
+fn identity_operations(code: &mut impl Code) {
     let zero = U256::zero();
     let one = U256::one();
-    replace_windows(code, |window| {
+    code.replace_windows(|window| {
         if let [Push(Literal(x)), StandardOp(op)] = window {
             match op.as_str() {
                 "ADD" => (x == zero).then_some(vec![]),
@@ -97,8 +146,8 @@ fn identity_operations(code: &mut Vec<Item>) {
 }
 
 /// Remove no-op jumps: `[PUSH label, JUMP, label:] -> [label:]`.
-fn no_op_jumps(code: &mut Vec<Item>) {
-    replace_windows(code, |window| {
+fn no_op_jumps(code: &mut impl Code) {
+    code.replace_windows(|window| {
         if let [Push(Label(l)), StandardOp(jump), decl] = window
             && &jump == "JUMP"
             && (decl == LocalLabelDeclaration(l.clone()) || decl == GlobalLabelDeclaration(l))
@@ -110,10 +159,61 @@ fn no_op_jumps(code: &mut Vec<Item>) {
     });
 }
 
+/// Jump threading: a jump to a label whose code immediately jumps to another
+/// label is redirected to the latter, e.g. `[PUSH a, JUMP] -> [PUSH b, JUMP]`
+/// given `a: PUSH b, JUMP`.
+///
+/// Labels watched by the prover, such as `observe_new_address`, must not be
+/// trampolines, as jumps to them would otherwise bypass them.
+fn jump_threading(code: &mut impl Code) {
+    let items: Vec<_> = code.items().collect();
+    let mut trampolines = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        let (LocalLabelDeclaration(label) | GlobalLabelDeclaration(label)) = item else {
+            continue;
+        };
+        let mut next = items[i + 1..]
+            .iter()
+            .filter(|item| !matches!(item, LocalLabelDeclaration(_) | GlobalLabelDeclaration(_)));
+        if let (Some(Push(Label(target))), Some(StandardOp(jump))) = (next.next(), next.next())
+            && jump == "JUMP"
+        {
+            trampolines.insert(label.clone(), target.clone());
+        }
+    }
+
+    // Follow chains of trampolines, leaving out the ones looping forever.
+    let destinations: HashMap<_, _> = trampolines
+        .keys()
+        .filter_map(|label| {
+            let mut visited = HashSet::from([label]);
+            let mut destination = &trampolines[label];
+            while let Some(next) = trampolines.get(destination) {
+                if !visited.insert(destination) {
+                    return None;
+                }
+                destination = next;
+            }
+            Some((label.clone(), destination.clone()))
+        })
+        .collect();
+
+    code.replace_windows(|window| {
+        if let [Push(Label(l)), StandardOp(jump)] = window
+            && (jump == "JUMP" || jump == "JUMPI")
+            && let Some(destination) = destinations.get(&l)
+        {
+            Some(vec![Push(Label(destination.clone())), StandardOp(jump)])
+        } else {
+            None
+        }
+    });
+}
+
 /// Remove swaps: `[PUSH x, PUSH y, SWAP1] -> [PUSH y, PUSH x]`.
 // Could be generalized to recognize more than two pushes.
-fn remove_swapped_pushes(code: &mut Vec<Item>) {
-    replace_windows(code, |window| {
+fn remove_swapped_pushes(code: &mut impl Code) {
+    code.replace_windows(|window| {
         if let [Push(x), Push(y), StandardOp(swap1)] = window
             && &swap1 == "SWAP1"
         {
@@ -125,8 +225,8 @@ fn remove_swapped_pushes(code: &mut Vec<Item>) {
 }
 
 /// Remove SWAP1 before a commutative function.
-fn remove_swaps_commutative(code: &mut Vec<Item>) {
-    replace_windows(code, |window| {
+fn remove_swaps_commutative(code: &mut impl Code) {
+    code.replace_windows(|window| {
         if let [StandardOp(swap1), StandardOp(f)] = window
             && &swap1 == "SWAP1"
         {
@@ -138,11 +238,51 @@ fn remove_swaps_commutative(code: &mut Vec<Item>) {
     });
 }
 
+/// Rewrite sequences of stack operations into simpler equivalent ones.
+fn canonicalize_dups_and_swaps(code: &mut impl Code) {
+    // Swapping twice the same items is a no-op: `[SWAPn, SWAPn] -> []`.
+    code.replace_windows(|window| {
+        if let [StandardOp(a), StandardOp(b)] = window
+            && a.starts_with("SWAP")
+            && a == b
+        {
+            Some(vec![])
+        } else {
+            None
+        }
+    });
+
+    // The copy of the n-th item is swapped with the original: `[DUPn, SWAPn] ->
+    // [DUPn]`.
+    code.replace_windows(|window| {
+        if let [StandardOp(dup), StandardOp(swap)] = window
+            && let Some(n) = dup.strip_prefix("DUP")
+            && swap.strip_prefix("SWAP") == Some(n)
+        {
+            Some(vec![StandardOp(dup)])
+        } else {
+            None
+        }
+    });
+
+    // Replacing the top of the stack: `[PUSH x, SWAP1, POP] -> [POP, PUSH x]`.
+    code.replace_windows(|window| {
+        if let [Push(x), StandardOp(swap1), StandardOp(pop)] = window
+            && swap1 == "SWAP1"
+            && pop == "POP"
+        {
+            Some(vec![StandardOp(pop), Push(x)])
+        } else {
+            None
+        }
+    });
+}
+
 /// Remove push-pop type patterns, such as: `[DUP1, POP]`.
 // Could be extended to other non-side-effecting operations, e.g. [DUP1, ADD,
 // POP] -> [POP].
-fn remove_ignored_values(code: &mut Vec<Item>) {
-    replace_windows(code, |[a, b]| {
+fn remove_ignored_values(code: &mut impl Code) {
+    code.replace_windows(|[a, b]| {
         if let StandardOp(pop) = b
             && &pop == "POP"
         {
@@ -160,11 +300,11 @@ fn remove_ignored_values(code: &mut Vec<Item>) {
 /// Like `replace_windows`, but specifically for code, and only makes
 /// replacements if our cost estimator thinks that the new code is more
 /// efficient.
-fn replace_windows_if_better<const W: usize, F>(code: &mut Vec<Item>, maybe_replace: F)
+fn replace_windows_if_better<const W: usize, F>(code: &mut impl Code, maybe_replace: F)
 where
     F: Fn([Item; W]) -> Option<Vec<Item>>,
 {
-    replace_windows(code, |window| {
+    code.replace_windows(|window| {
         maybe_replace(window.clone()).filter(|suggestion| is_code_improved(&window, suggestion))
     })
 }
@@ -193,18 +333,15 @@ mod tests {
 
     #[test]
     fn test_constant_propagation_sub_underflowing() {
-        let original = vec![
+        let mut code = vec![
             Push(Literal(U256::one())),
             Push(Literal(U256::zero())),
             StandardOp("SUB".into()),
         ];
-        let mut code = original.clone();
         constant_propagation(&mut code);
-        // Constant propagation could replace the code with [PUSH U256::MAX], but that's
-        // actually more expensive, so the code shouldn't be changed.
-        // (The code could also be replaced with [PUSH 0; NOT], which would be an
-        // improvement, but our optimizer isn't smart enough yet.)
-        assert_eq!(code, original);
+        // The code is replaced with a 32-byte push, which is larger but adds
+        // fewer rows to the CPU, memory and arithmetic tables.
+        assert_eq!(code, vec![Push(Literal(U256::max_value()))]);
     }
 
     #[test]
@@ -251,6 +388,40 @@ mod tests {
         assert_eq!(code, vec![LocalLabelDeclaration("mylabel".into())]);
     }
 
+    #[test]
+    fn test_jump_threading() {
+        let mut code = vec![
+            Push(Label("a".into())),
+            StandardOp("JUMPI".into()),
+            LocalLabelDeclaration("a".into()),
+            GlobalLabelDeclaration("b".into()),
+            Push(Label("c".into())),
+            StandardOp("JUMP".into()),
+            LocalLabelDeclaration("c".into()),
+            Push(Label("d".into())),
+            StandardOp("JUMP".into()),
+        ];
+        jump_threading(&mut code);
+        assert_eq!(code[0], Push(Label("d".into())));
+        assert_eq!(code[4], Push(Label("d".into())));
+        assert_eq!(code[7], Push(Label("d".into())));
+    }
+
+    #[test]
+    fn test_jump_threading_loop() {
+        let original = vec![
+            LocalLabelDeclaration("a".into()),
+            Push(Label("b".into())),
+            StandardOp("JUMP".into()),
+            LocalLabelDeclaration("b".into()),
+            Push(Label("a".into())),
+            StandardOp("JUMP".into()),
+        ];
+        let mut code = original.clone();
+        jump_threading(&mut code);
+        assert_eq!(code, original);
+    }
+
     #[test]
     fn test_remove_swapped_pushes() {
         let mut code = vec![
@@ -272,6 +443,28 @@ mod tests {
         assert_eq!(code, vec![StandardOp("MUL".into())]);
     }
 
+    #[test]
+    fn test_canonicalize_dups_and_swaps() {
+        let mut code = vec![
+            StandardOp("SWAP3".into()),
+            StandardOp("SWAP3".into()),
+            StandardOp("DUP4".into()),
+            StandardOp("SWAP4".into()),
+            Push(Literal(1.into())),
+            StandardOp("SWAP1".into()),
+            StandardOp("POP".into()),
+        ];
+        canonicalize_dups_and_swaps(&mut code);
+        assert_eq!(
+            code,
+            vec![
+                StandardOp("DUP4".into()),
+                StandardOp("POP".into()),
+                Push(Literal(1.into())),
+            ]
+        );
+    }
+
     #[test]
     fn test_remove_push_pop() {
         let mut code = vec![Push(Literal("42".into())), StandardOp("POP".into())];
@@ -5,7 +5,7 @@ use itertools::Itertools;
 use pest::iterators::Pair;
 use pest::Parser;
 
-use super::ast::{BytesTarget, StackPlaceholder};
+use super::ast::{BytesTarget, SourceFrame, StackPlaceholder};
 use crate::cpu::kernel::ast::{File, Item, PushTarget, StackReplacement};
 
 /// Parses EVM assembly code.
@@ -18,13 +18,29 @@ pub(crate) fn parse(s: &str, active_features: &HashSet<&str>) -> File {
         .expect("Parsing failed")
         .next()
         .unwrap();
-    let body = file
-        .into_inner()
-        .map(|i| parse_item(i, active_features))
-        .collect();
+    let body = parse_items(file.into_inner(), active_features);
     File { body }
 }
 
+/// Parses a sequence of items, preceding each of them with a marker of its
+/// source line. The file index of the markers is set when assembling.
+fn parse_items<'a>(
+    items: impl Iterator<Item = Pair<'a, Rule>>,
+    active_features: &HashSet<&str>,
+) -> Vec<Item> {
+    items
+        .flat_map(|item| {
+            let (line, _) = item.line_col();
+            let marker = Item::SourceMarker(vec![SourceFrame {
+                file: 0,
+                line,
+                in_macro: None,
+            }]);
+            [marker, parse_item(item, active_features)]
+        })
+        .collect()
+}
+
 fn parse_item(item: Pair<Rule>, active_features: &HashSet<&str>) -> Item {
     assert_eq!(item.as_rule(), Rule::item);
     let item = item.into_inner().next().unwrap();
@@ -134,10 +150,7 @@ fn parse_conditional_block(item: Pair<Rule>, active_features: &HashSet<&str>) ->
     let feature_supported = is_supported(active_features, features, group_rule);
 
     if feature_supported {
-        Item::ConditionalBlock(
-            name.into(),
-            inner.map(|i| parse_item(i, active_features)).collect(),
-        )
+        Item::ConditionalBlock(name.into(), parse_items(inner, active_features))
     } else {
         Item::ConditionalBlock(name.into(), vec![])
     }
@@ -157,11 +170,7 @@ fn parse_macro_def(item: Pair<Rule>, active_features: &HashSet<&str>) -> Item {
         vec![]
     };
 
-    Item::MacroDef(
-        name,
-        params,
-        inner.map(|i| parse_item(i, active_features)).collect(),
-    )
+    Item::MacroDef(name, params, parse_items(inner, active_features))
 }
 
 fn parse_macro_call(item: Pair<Rule>) -> Item {
@@ -185,10 +194,7 @@ fn parse_repeat(item: Pair<Rule>, active_features: &HashSet<&str>) -> Item {
     assert_eq!(item.as_rule(), Rule::repeat);
     let mut inner = item.into_inner();
     let count = parse_literal_u256(inner.next().unwrap());
-    Item::Repeat(
-        count,
-        inner.map(|i| parse_item(i, active_features)).collect(),
-    )
+    Item::Repeat(count, parse_items(inner, active_features))
 }
 
 fn parse_stack(item: Pair<Rule>) -> Item {
@@ -384,7 +390,12 @@ mod tests {
         let active_features = HashSet::from(["feature_1"]);
 
         let parsed_code = parse(code, &active_features);
-        let final_code = assemble(vec![parsed_code], HashMap::new(), false);
+        let final_code = assemble(
+            vec![parsed_code],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         let expected_code = r#"
         %macro bar_foo
@@ -414,7 +425,12 @@ mod tests {
         "#;
 
         let parsed_expected = parse(expected_code, &HashSet::new());
-        let final_expected = assemble(vec![parsed_expected], HashMap::new(), false);
+        let final_expected = assemble(
+            vec![parsed_expected],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         assert_eq!(final_code.code, final_expected.code);
 
@@ -422,7 +438,12 @@ mod tests {
         let active_features = HashSet::from(["feature_2"]);
 
         let parsed_code = parse(code, &active_features);
-        let final_code = assemble(vec![parsed_code], HashMap::new(), false);
+        let final_code = assemble(
+            vec![parsed_code],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         let expected_code = r#"
         %macro bar_foo
@@ -449,7 +470,12 @@ mod tests {
         "#;
 
         let parsed_expected = parse(expected_code, &HashSet::new());
-        let final_expected = assemble(vec![parsed_expected], HashMap::new(), false);
+        let final_expected = assemble(
+            vec![parsed_expected],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         assert_eq!(final_code.code, final_expected.code);
 
@@ -457,7 +483,12 @@ mod tests {
         let active_features = HashSet::from(["feature_1", "feature_2"]);
 
         let parsed_code = parse(code, &active_features);
-        let final_code = assemble(vec![parsed_code], HashMap::new(), false);
+        let final_code = assemble(
+            vec![parsed_code],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         let expected_code = r#"
         %macro bar_foo
@@ -496,7 +527,12 @@ mod tests {
         "#;
 
         let parsed_expected = parse(expected_code, &HashSet::new());
-        let final_expected = assemble(vec![parsed_expected], HashMap::new(), false);
+        let final_expected = assemble(
+            vec![parsed_expected],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         assert_eq!(final_code.code, final_expected.code);
 
@@ -504,7 +540,12 @@ mod tests {
         let active_features = HashSet::new();
 
         let parsed_code = parse(code, &active_features);
-        let final_code = assemble(vec![parsed_code], HashMap::new(), false);
+        let final_code = assemble(
+            vec![parsed_code],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         let expected_code = r#"
         global foo_1:
@@ -521,7 +562,12 @@ mod tests {
         "#;
 
         let parsed_expected = parse(expected_code, &HashSet::new());
-        let final_expected = assemble(vec![parsed_expected], HashMap::new(), false);
+        let final_expected = assemble(
+            vec![parsed_expected],
+            vec!["test.asm".into()],
+            HashMap::new(),
+            false,
+        );
 
         assert_eq!(final_code.code, final_expected.code);
     }
@@ -0,0 +1,425 @@
+//! A profiler attributing the cost of an execution to the kernel routines
+//! being executed, to find out which ones dominate the proving cost.
+//!
+//! The kernel has no explicit call and return instructions, so calls are
+//! recovered from its calling convention: a jump to a global label is a call
+//! to the routine starting there, expected to return right after the jump,
+//! and a jump to the return address of a pending call returns from it, along
+//! with all the calls made since. User code is profiled as a single routine,
+//! under which the system calls and exceptions it triggers are attributed.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::interpreter::get_mnemonic;
+use crate::witness::state::RegistersState;
+
+/// The opcode of `JUMP`.
+const JUMP: u8 = 0x56;
+/// The opcode of `JUMPI`.
+const JUMPI: u8 = 0x57;
+
+/// The maximal depth of the profiled call stacks. Deeper calls are attributed
+/// to their caller.
+const MAX_CALL_DEPTH: usize = 256;
+
+/// The index of the root of the call tree, under which the outermost routines
+/// are called.
+const ROOT: usize = 0;
+
+/// The costs of an execution, in terms of the rows it adds to the tables of
+/// the zkEVM.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionCosts {
+    pub cpu_cycles: u64,
+    pub memory_ops: u64,
+    pub keccak_sponge_calls: u64,
+    pub arithmetic_rows: u64,
+    pub logic_rows: u64,
+}
+
+impl ExecutionCosts {
+    fn add(&mut self, other: &Self) {
+        self.cpu_cycles += other.cpu_cycles;
+        self.memory_ops += other.memory_ops;
+        self.keccak_sponge_calls += other.keccak_sponge_calls;
+        self.arithmetic_rows += other.arithmetic_rows;
+        self.logic_rows += other.logic_rows;
+    }
+}
+
+/// A cost to attribute to the profiled call stacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileMetric {
+    CpuCycles,
+    MemoryOps,
+    KeccakSpongeCalls,
+    ArithmeticRows,
+    LogicRows,
+}
+
+impl ProfileMetric {
+    pub const ALL: [Self; 5] = [
+        Self::CpuCycles,
+        Self::MemoryOps,
+        Self::KeccakSpongeCalls,
+        Self::ArithmeticRows,
+        Self::LogicRows,
+    ];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::CpuCycles => "cpu_cycles",
+            Self::MemoryOps => "memory_ops",
+            Self::KeccakSpongeCalls => "keccak_sponge_calls",
+            Self::ArithmeticRows => "arithmetic_rows",
+            Self::LogicRows => "logic_rows",
+        }
+    }
+
+    const fn of(&self, costs: &ExecutionCosts) -> u64 {
+        match self {
+            Self::CpuCycles => costs.cpu_cycles,
+            Self::MemoryOps => costs.memory_ops,
+            Self::KeccakSpongeCalls => costs.keccak_sponge_calls,
+            Self::ArithmeticRows => costs.arithmetic_rows,
+            Self::LogicRows => costs.logic_rows,
+        }
+    }
+}
+
+/// A profiled routine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Routine {
+    /// The kernel routine starting at the given offset.
+    Kernel(usize),
+    /// Any user code.
+    User,
+}
+
+impl Routine {
+    fn name(&self) -> String {
+        match self {
+            Routine::Kernel(offset) => KERNEL.offset_name(*offset),
+            Routine::User => "[user code]".into(),
+        }
+    }
+}
+
+/// A node of the call tree, standing for a call stack.
+#[derive(Debug)]
+struct Node {
+    routine: Option<Routine>,
+    parent: usize,
+    callees: HashMap<Routine, usize>,
+    /// The costs incurred by the routine itself, excluding its callees.
+    costs: ExecutionCosts,
+}
+
+/// The costs of an execution, attributed to the call stacks they were incurred
+/// in.
+#[derive(Debug)]
+pub struct KernelProfile {
+    nodes: Vec<Node>,
+    opcode_counts: [u64; 0x100],
+}
+
+impl Default for KernelProfile {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node {
+                routine: None,
+                parent: ROOT,
+                callees: HashMap::new(),
+                costs: ExecutionCosts::default(),
+            }],
+            opcode_counts: [0; 0x100],
+        }
+    }
+}
+
+impl KernelProfile {
+    /// Returns the total costs of the execution.
+    pub fn total(&self) -> ExecutionCosts {
+        let mut total = ExecutionCosts::default();
+        for node in &self.nodes {
+            total.add(&node.costs);
+        }
+        total
+    }
+
+    /// Returns the number of times each opcode was executed, by mnemonic.
+    pub fn opcode_counts(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        self.opcode_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(opcode, &count)| (get_mnemonic(opcode as u8), count))
+    }
+
+    /// Writes the given metric of each call stack in the folded format used by
+    /// flame graph tools, i.e. one `caller;callee;... value` line per stack.
+    pub fn write_folded<W: Write>(&self, metric: ProfileMetric, mut writer: W) -> io::Result<()> {
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let value = metric.of(&node.costs);
+            if value > 0 {
+                writeln!(writer, "{} {value}", self.stack_name(idx))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn stack_name(&self, mut idx: usize) -> String {
+        let mut names = vec![];
+        while let Some(routine) = self.nodes[idx].routine {
+            names.push(routine.name());
+            idx = self.nodes[idx].parent;
+        }
+        if names.is_empty() {
+            return "[unknown]".into();
+        }
+        names.reverse();
+        names.join(";")
+    }
+
+    fn callee(&mut self, caller: usize, routine: Routine) -> usize {
+        if let Some(&idx) = self.nodes[caller].callees.get(&routine) {
+            return idx;
+        }
+
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            routine: Some(routine),
+            parent: caller,
+            callees: HashMap::new(),
+            costs: ExecutionCosts::default(),
+        });
+        self.nodes[caller].callees.insert(routine, idx);
+        idx
+    }
+}
+
+/// A pending call.
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    routine: Routine,
+    /// The node of the call stack up to this call.
+    node: usize,
+    /// The offset this call is expected to return to, if any.
+    return_pc: Option<usize>,
+}
+
+/// The point at which the execution of a segment resumes, after the kernel has
+/// initialized it.
+#[derive(Debug)]
+struct Resumption {
+    program_counter: usize,
+    is_kernel: bool,
+    frames: Vec<Frame>,
+}
+
+/// Tracks the call stack of an execution, to build its [`KernelProfile`].
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    profile: KernelProfile,
+    frames: Vec<Frame>,
+    /// The costs of the current instruction, attributed once it is applied.
+    pending: ExecutionCosts,
+    /// The program counter, mode and opcode of the previous instruction.
+    previous: Option<(usize, bool, u8)>,
+    resumption: Option<Resumption>,
+    /// The sorted offsets of the global labels of the kernel.
+    label_offsets: Vec<usize>,
+}
+
+impl Profiler {
+    pub(crate) fn new() -> Self {
+        let mut label_offsets = KERNEL.global_labels.values().copied().collect::<Vec<_>>();
+        label_offsets.sort_unstable();
+        label_offsets.dedup();
+
+        Self {
+            profile: KernelProfile::default(),
+            frames: vec![],
+            pending: ExecutionCosts::default(),
+            previous: None,
+            resumption: None,
+            label_offsets,
+        }
+    }
+
+    pub(crate) fn into_profile(self) -> KernelProfile {
+        self.profile
+    }
+
+    /// Starts profiling a new segment, resuming at `registers` once the kernel
+    /// has initialized it. The initialization is attributed to its own stack.
+    pub(crate) fn start_segment(&mut self, registers: &RegistersState) {
+        self.resumption = Some(Resumption {
+            program_counter: registers.program_counter,
+            is_kernel: registers.is_kernel,
+            frames: std::mem::take(&mut self.frames),
+        });
+        self.previous = None;
+    }
+
+    /// Updates the call stack before executing the instruction at `registers`.
+    pub(crate) fn step(&mut self, registers: &RegistersState, opcode: u8) {
+        let pc = registers.program_counter;
+        let is_kernel = registers.is_kernel;
+
+        if let Some(resumption) = self
+            .resumption
+            .take_if(|r| r.program_counter == pc && r.is_kernel == is_kernel)
+        {
+            self.frames = resumption.frames;
+        } else if let Some((previous_pc, was_kernel, previous_opcode)) = self.previous {
+            match (was_kernel, is_kernel) {
+                (true, true) => {
+                    if matches!(previous_opcode, JUMP | JUMPI) && pc != previous_pc + 1 {
+                        self.jump(previous_pc, pc);
+                    }
+                }
+                // The kernel hands control back to user code.
+                (true, false) => match self.frames.iter().rposition(|f| f.routine == Routine::User)
+                {
+                    Some(idx) => self.frames.truncate(idx + 1),
+                    None => self.call(Routine::User, None),
+                },
+                // A system call or an exception.
+                (false, true) => self.call(Routine::Kernel(self.enclosing_label(pc)), None),
+                (false, false) => {}
+            }
+        }
+
+        if self.frames.is_empty() {
+            let routine = if is_kernel {
+                Routine::Kernel(self.enclosing_label(pc))
+            } else {
+                Routine::User
+            };
+            self.call(routine, None);
+        }
+
+        self.previous = Some((pc, is_kernel, opcode));
+        self.profile.opcode_counts[opcode as usize] += 1;
+    }
+
+    /// Records costs of the current instruction.
+    pub(crate) fn record(&mut self, record: impl FnOnce(&mut ExecutionCosts)) {
+        record(&mut self.pending);
+    }
+
+    /// Attributes the costs of the current instruction to the current stack.
+    pub(crate) fn commit(&mut self) {
+        let node = self.frames.last().map_or(ROOT, |frame| frame.node);
+        self.profile.nodes[node].costs.add(&self.pending);
+        self.pending = ExecutionCosts::default();
+    }
+
+    /// Drops the costs of the current instruction, which was rolled back.
+    pub(crate) fn discard(&mut self) {
+        self.pending = ExecutionCosts::default();
+    }
+
+    fn jump(&mut self, from: usize, to: usize) {
+        if let Some(idx) = self.frames.iter().rposition(|f| f.return_pc == Some(to)) {
+            self.frames.truncate(idx);
+        } else if self.label_offsets.binary_search(&to).is_ok() {
+            self.call(Routine::Kernel(to), Some(from + 1));
+        }
+    }
+
+    fn call(&mut self, routine: Routine, return_pc: Option<usize>) {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return;
+        }
+
+        let caller = self.frames.last().map_or(ROOT, |frame| frame.node);
+        let node = self.profile.callee(caller, routine);
+        self.frames.push(Frame {
+            routine,
+            node,
+            return_pc,
+        });
+    }
+
+    /// Returns the offset of the last global label at or before `pc`.
+    fn enclosing_label(&self, pc: usize) -> usize {
+        let idx = self.label_offsets.partition_point(|&offset| offset <= pc);
+        self.label_offsets[idx.saturating_sub(1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kernel_registers(program_counter: usize) -> RegistersState {
+        RegistersState {
+            program_counter,
+            is_kernel: true,
+            ..RegistersState::default()
+        }
+    }
+
+    fn cycle(profiler: &mut Profiler, program_counter: usize, opcode: u8) {
+        profiler.step(&kernel_registers(program_counter), opcode);
+        profiler.record(|costs| costs.cpu_cycles += 1);
+        profiler.commit();
+    }
+
+    fn folded(profiler: &Profiler) -> Vec<String> {
+        let mut out = vec![];
+        profiler
+            .profile
+            .write_folded(ProfileMetric::CpuCycles, &mut out)
+            .unwrap();
+        let mut lines: Vec<_> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        let main = KERNEL.global_labels["main"];
+        let callee = KERNEL.global_labels["init"];
+        let mut profiler = Profiler::new();
+
+        // Call `init` from `main`, and return right after the jump.
+        cycle(&mut profiler, main, 0);
+        cycle(&mut profiler, main + 1, JUMP);
+        cycle(&mut profiler, callee, 0);
+        cycle(&mut profiler, callee + 1, JUMP);
+        cycle(&mut profiler, main + 2, 0);
+
+        let main_name = KERNEL.offset_name(main);
+        let callee_name = KERNEL.offset_name(callee);
+        assert_eq!(
+            folded(&profiler),
+            vec![
+                format!("{main_name} 3"),
+                format!("{main_name};{callee_name} 1")
+            ]
+        );
+        assert_eq!(profiler.profile.total().cpu_cycles, 5);
+    }
+
+    #[test]
+    fn discarded_costs() {
+        let main = KERNEL.global_labels["main"];
+        let mut profiler = Profiler::new();
+
+        cycle(&mut profiler, main, 0);
+        profiler.step(&kernel_registers(main + 1), 0);
+        profiler.record(|costs| costs.cpu_cycles += 1);
+        profiler.discard();
+
+        assert_eq!(profiler.profile.total().cpu_cycles, 1);
+    }
+}
@@ -0,0 +1,177 @@
+//! Maps offsets of the assembled kernel back to the assembly source they were
+//! generated from.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::kernel::ast::SourceFrame;
+
+/// A position in an assembly file, within the body of a macro if `in_macro`
+/// is set.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+struct Frame {
+    file: usize,
+    line: usize,
+    in_macro: Option<String>,
+}
+
+/// A map from the offsets of the assembled code to their location in the
+/// assembly files, including the stack of macro expansions they come from.
+#[derive(PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// The names of the assembled files.
+    files: Vec<String>,
+    /// The distinct stacks of frames, from the innermost macro expansion to
+    /// the outermost call site.
+    stacks: Vec<Vec<Frame>>,
+    /// The start offset of each range of code assembled from the same stack,
+    /// sorted by offset, along with the index of this stack if known.
+    ranges: Vec<(usize, Option<usize>)>,
+}
+
+impl SourceMap {
+    /// Returns the backtrace of the source of the code at `offset`, if known.
+    pub fn backtrace(&self, offset: usize) -> Option<SourceBacktrace<'_>> {
+        let idx = self
+            .ranges
+            .partition_point(|&(start, _)| start <= offset)
+            .checked_sub(1)?;
+        let stack = self.ranges[idx].1?;
+
+        Some(SourceBacktrace {
+            files: &self.files,
+            frames: &self.stacks[stack],
+        })
+    }
+}
+
+/// The location of an offset in the assembly source, from the innermost macro
+/// expansion to the outermost call site.
+#[derive(Clone, Copy, Debug)]
+pub struct SourceBacktrace<'a> {
+    files: &'a [String],
+    frames: &'a [Frame],
+}
+
+impl<'a> SourceBacktrace<'a> {
+    /// Returns the file and line of each frame, from the innermost macro
+    /// expansion to the outermost call site.
+    pub fn frames(&self) -> impl Iterator<Item = (&'a str, usize)> + '_ {
+        self.frames
+            .iter()
+            .map(|frame| (self.files[frame.file].as_str(), frame.line))
+    }
+}
+
+impl Display for SourceBacktrace<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, frame) in self.frames.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let file = &self.files[frame.file];
+            match &frame.in_macro {
+                Some(name) => write!(f, "    at %{name} ({file}:{})", frame.line)?,
+                None => write!(f, "    at {file}:{}", frame.line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Incrementally builds a [`SourceMap`] while assembling the kernel.
+#[derive(Debug, Default)]
+pub(crate) struct SourceMapBuilder {
+    map: SourceMap,
+    stack_indices: HashMap<Vec<SourceFrame>, usize>,
+}
+
+impl SourceMapBuilder {
+    pub(crate) fn new(files: Vec<String>) -> Self {
+        Self {
+            map: SourceMap {
+                files,
+                ..Default::default()
+            },
+            stack_indices: HashMap::new(),
+        }
+    }
+
+    /// Returns the index of the given stack of frames, registering it if
+    /// needed.
+    pub(crate) fn intern(&mut self, frames: Vec<SourceFrame>) -> usize {
+        if let Some(&idx) = self.stack_indices.get(&frames) {
+            return idx;
+        }
+
+        let idx = self.map.stacks.len();
+        self.map.stacks.push(
+            frames
+                .iter()
+                .map(|frame| Frame {
+                    file: frame.file,
+                    line: frame.line,
+                    in_macro: frame.in_macro.clone(),
+                })
+                .collect(),
+        );
+        self.stack_indices.insert(frames, idx);
+        idx
+    }
+
+    /// Records that the code starting at `offset` comes from the given stack.
+    pub(crate) fn record(&mut self, offset: usize, stack: Option<usize>) {
+        match self.map.ranges.last_mut() {
+            Some((_, last_stack)) if *last_stack == stack => {}
+            Some((last_offset, last_stack)) if *last_offset == offset => *last_stack = stack,
+            _ => self.map.ranges.push((offset, stack)),
+        }
+    }
+
+    pub(crate) fn build(self) -> SourceMap {
+        self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(file: usize, line: usize, in_macro: Option<&str>) -> SourceFrame {
+        SourceFrame {
+            file,
+            line,
+            in_macro: in_macro.map(String::from),
+        }
+    }
+
+    #[test]
+    fn lookup_ranges() {
+        let mut builder = SourceMapBuilder::new(vec!["a.asm".into(), "b.asm".into()]);
+        let outer = builder.intern(vec![frame(0, 3, None)]);
+        let inner = builder.intern(vec![frame(1, 7, Some("foo")), frame(0, 4, None)]);
+        assert_eq!(builder.intern(vec![frame(0, 3, None)]), outer);
+
+        builder.record(0, Some(outer));
+        builder.record(2, Some(outer));
+        builder.record(5, Some(inner));
+        builder.record(8, None);
+        let map = builder.build();
+
+        assert_eq!(
+            map.ranges,
+            vec![(0, Some(outer)), (5, Some(inner)), (8, None)]
+        );
+        assert_eq!(
+            map.backtrace(4).unwrap().frames().collect::<Vec<_>>(),
+            vec![("a.asm", 3)]
+        );
+        assert_eq!(
+            map.backtrace(6).unwrap().to_string(),
+            "    at %foo (b.asm:7)\n    at a.asm:4"
+        );
+        assert!(map.backtrace(9).is_none());
+    }
+}
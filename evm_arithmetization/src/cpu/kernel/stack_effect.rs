@@ -0,0 +1,1002 @@
+//! A static analysis inferring the stack effect of the kernel's macros and
+//! global routines.
+//!
+//! The analysis runs an abstract interpretation of the expanded assembly,
+//! tracking for each stack item whether it is one of the items present on
+//! entry, the offset of a label, or an unknown value. A routine returns when
+//! it jumps to one of its inputs, and a jump to a global label is a call whose
+//! effect is the one inferred for this label, after which the execution goes
+//! on at the return address passed to the callee. The effect of a macro is the
+//! one of its body, up to the point where it falls through.
+//!
+//! Labels reached with different stack heights are reported, as well as
+//! inferred effects contradicting the `Pre stack` and `Post stack` comments
+//! preceding a macro or a global label, such as:
+//!
+//! ```text
+//! // Pre stack: address, retdest
+//! // Post stack: balance
+//! global balance:
+//! ```
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+
+use crate::cpu::kernel::assembler::{
+    expand_conditional_blocks, expand_macro_call, expand_macros, find_macros, inline_constants,
+    set_source_file,
+};
+use crate::cpu::kernel::ast::{File, Item, PushTarget, SourceFrame};
+use crate::cpu::kernel::constants::evm_constants;
+use crate::cpu::kernel::parser::parse;
+use crate::cpu::kernel::stack::stack_manipulation::expand_stack_manipulation;
+
+/// The maximum number of times the code following a label is analyzed within
+/// a routine, after which its effect is considered unknown.
+const MAX_VISITS: usize = 100_000;
+
+/// A value on the abstract stack.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Value {
+    /// The `i`-th item from the top of the stack on entry.
+    Input(usize),
+    /// The offset of a label local to a code unit, given by its index in the
+    /// unit.
+    Local {
+        unit: usize,
+        index: usize,
+    },
+    /// The offset of a global label.
+    Global(String),
+    Unknown,
+}
+
+/// The stack effect of a macro or a routine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum StackEffect {
+    /// The code replaces the top `inputs` items of the stack with `outputs`,
+    /// and then returns by jumping to the input at index `retdest` for
+    /// routines, or falls through for macros.
+    Returns {
+        inputs: usize,
+        outputs: Vec<Value>,
+        retdest: Option<usize>,
+    },
+    /// The code never returns nor falls through, e.g. because it halts or
+    /// jumps to user code.
+    Diverges,
+    /// The effect could not be inferred, e.g. because of a jump to a dynamic
+    /// destination.
+    Unknown,
+}
+
+impl Display for StackEffect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StackEffect::Returns {
+                inputs, outputs, ..
+            } => write!(f, "{inputs} -> {}", outputs.len()),
+            StackEffect::Diverges => write!(f, "diverges"),
+            StackEffect::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The stack effect documented by the `Pre stack` and `Post stack` comments of
+/// a macro or a routine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Annotation {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+impl Annotation {
+    /// Parses the annotation from the comment lines preceding the given
+    /// 1-based line, if any.
+    fn before_line(source: &str, line: usize) -> Option<Self> {
+        let mut pre = None;
+        let mut post = None;
+        let lines: Vec<_> = source.lines().take(line.saturating_sub(1)).collect();
+        for text in lines.into_iter().rev() {
+            let Some(comment) = text.trim().strip_prefix("//") else {
+                break;
+            };
+            let comment = comment.trim();
+            if let Some(items) = strip_prefix_ignore_case(comment, "pre stack:") {
+                pre.get_or_insert(items);
+            } else if let Some(items) = strip_prefix_ignore_case(comment, "post stack:") {
+                post.get_or_insert(items);
+            }
+        }
+
+        let mut inputs = parse_annotation_items(pre?)?;
+        let mut outputs = parse_annotation_items(post?)?;
+        // Items below an ellipsis are left untouched.
+        if inputs.last().is_some_and(|item| item == "...")
+            && outputs.last().is_some_and(|item| item == "...")
+        {
+            inputs.pop();
+            outputs.pop();
+        }
+        if inputs
+            .iter()
+            .chain(&outputs)
+            .any(|item| item.contains("..."))
+        {
+            return None;
+        }
+
+        Some(Self { inputs, outputs })
+    }
+
+    /// Checks that the given effect, if known, is the annotated one.
+    fn matches(&self, effect: &StackEffect) -> bool {
+        let StackEffect::Returns {
+            inputs,
+            outputs,
+            retdest,
+        } = effect
+        else {
+            return true;
+        };
+
+        let annotated_delta = self.outputs.len() as isize - self.inputs.len() as isize;
+        let inferred_delta = outputs.len() as isize - *inputs as isize;
+        let annotated_retdest = self.inputs.iter().position(|item| item == "retdest");
+        annotated_delta == inferred_delta
+            && *inputs <= self.inputs.len()
+            && (retdest.is_none() || annotated_retdest.is_none() || *retdest == annotated_retdest)
+    }
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}) -> ({})",
+            self.inputs.join(", "),
+            self.outputs.join(", ")
+        )
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &s[prefix.len()..])
+}
+
+/// Parses the comma-separated items of an annotation, returning `None` if they
+/// are not a plain list of names.
+fn parse_annotation_items(items: &str) -> Option<Vec<String>> {
+    let items = items.trim();
+    if items.is_empty() || items.eq_ignore_ascii_case("(empty)") {
+        return Some(vec![]);
+    }
+
+    let items: Vec<String> = items
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .collect();
+    items
+        .iter()
+        .all(|item| {
+            item == "..."
+                || !item.is_empty()
+                    && item
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        })
+        .then_some(items)
+}
+
+/// An inconsistency found by the analysis.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum StackEffectError {
+    /// A label is reached with different stack heights, relative to the entry
+    /// of the routine or macro being analyzed.
+    UnbalancedJoin {
+        label: String,
+        location: String,
+        heights: [isize; 2],
+    },
+    /// A routine or macro returns with different stack heights.
+    UnbalancedReturn {
+        name: String,
+        location: String,
+        heights: [isize; 2],
+    },
+    /// The inferred effect of a routine or macro contradicts its annotation.
+    WrongAnnotation {
+        name: String,
+        location: String,
+        annotation: Annotation,
+        inferred: StackEffect,
+    },
+}
+
+impl Display for StackEffectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StackEffectError::UnbalancedJoin {
+                label,
+                location,
+                heights: [a, b],
+            } => write!(
+                f,
+                "{location}: label {label} is reached with stack heights {a} and {b}"
+            ),
+            StackEffectError::UnbalancedReturn {
+                name,
+                location,
+                heights: [a, b],
+            } => write!(
+                f,
+                "{location}: {name} returns with stack heights {a} and {b}"
+            ),
+            StackEffectError::WrongAnnotation {
+                name,
+                location,
+                annotation,
+                inferred,
+            } => write!(
+                f,
+                "{location}: {name} is annotated with {annotation} but its inferred effect is {inferred}"
+            ),
+        }
+    }
+}
+
+/// The result of the analysis of a set of assembly files.
+#[derive(Debug, Default)]
+pub(crate) struct StackEffects {
+    /// The inferred effect of each macro, given by its name and arity.
+    pub(crate) macros: BTreeMap<(String, usize), StackEffect>,
+    /// The inferred effect of each routine, given by its global label.
+    pub(crate) routines: BTreeMap<String, StackEffect>,
+    /// The inconsistencies found, sorted by location.
+    pub(crate) errors: Vec<StackEffectError>,
+}
+
+/// Infers the stack effects of the macros and routines of the given assembly
+/// files, given by their names and contents, and checks them against their
+/// annotations.
+pub(crate) fn check_stack_effects(files: &[(&str, &str)]) -> StackEffects {
+    let file_names: Vec<_> = files.iter().map(|&(name, _)| name.to_string()).collect();
+    let parsed: Vec<File> = files
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, contents))| {
+            let mut file = parse(contents, &HashSet::new());
+            set_source_file(&mut file.body, i);
+            file
+        })
+        .collect();
+
+    let constants = evm_constants();
+    let macros = find_macros(&parsed);
+    let mut macro_counter = 0;
+    let expand = |items, macro_counter: &mut u32| {
+        let items = expand_conditional_blocks(items);
+        let items = expand_macros(items, &macros, macro_counter);
+        let items = inline_constants(items, &constants);
+        expand_stack_manipulation(items)
+    };
+
+    let mut units: Vec<Unit> = parsed
+        .iter()
+        .map(|file| Unit::new(expand(file.body.clone(), &mut macro_counter)))
+        .collect();
+    let mut globals = HashMap::new();
+    for (unit, file) in units.iter().enumerate() {
+        for (index, item) in file.items.iter().enumerate() {
+            if let Item::GlobalLabelDeclaration(label) = item {
+                globals.insert(label.clone(), (unit, index));
+            }
+        }
+    }
+
+    // Each macro is expanded on its own, with placeholder labels as arguments.
+    let mut macro_units = BTreeMap::new();
+    for (signature, macro_) in &macros {
+        let args = macro_
+            .params
+            .iter()
+            .map(|param| PushTarget::Label(format!("${param}")))
+            .collect();
+        let body = expand_macro_call(
+            signature.name.clone(),
+            args,
+            &[],
+            &macros,
+            &mut macro_counter,
+        );
+        macro_units.insert((signature.name.clone(), signature.num_params), units.len());
+        units.push(Unit::new(expand(body, &mut macro_counter)));
+    }
+
+    let mut analyzer = Analyzer {
+        units,
+        file_names,
+        globals,
+        label_effects: HashMap::new(),
+        errors: vec![],
+        reported_joins: HashSet::new(),
+    };
+
+    let mut effects = StackEffects::default();
+    for (signature, &unit) in &macro_units {
+        let effect = analyzer.analyze(unit, 0, Mode::Macro, &format!("%{}", signature.0));
+        effects.macros.insert(signature.clone(), effect);
+    }
+    let mut labels: Vec<_> = analyzer.globals.keys().cloned().collect();
+    labels.sort();
+    for label in labels {
+        let effect = analyzer.routine_effect(&label);
+        effects.routines.insert(label, effect);
+    }
+
+    // Check the effects against the annotations in the source files.
+    for (file, &(_, contents)) in parsed.iter().zip(files) {
+        for (line, item) in declarations(&file.body) {
+            let Some(annotation) = Annotation::before_line(contents, line.line) else {
+                continue;
+            };
+            let (name, effect) = match item {
+                Item::GlobalLabelDeclaration(label) => (label.clone(), &effects.routines[label]),
+                Item::MacroDef(name, params, _) => (
+                    format!("%{name}"),
+                    &effects.macros[&(name.clone(), params.len())],
+                ),
+                _ => unreachable!(),
+            };
+            if !annotation.matches(effect) {
+                analyzer.errors.push(StackEffectError::WrongAnnotation {
+                    name,
+                    location: format!("{}:{}", analyzer.file_names[line.file], line.line),
+                    annotation,
+                    inferred: effect.clone(),
+                });
+            }
+        }
+    }
+
+    effects.errors = analyzer.errors;
+    effects.errors.sort_by_key(|error| error.to_string());
+    effects
+}
+
+/// Returns the global labels and macro definitions of a parsed file, along with
+/// the source position of their declaration.
+fn declarations(items: &[Item]) -> Vec<(&SourceFrame, &Item)> {
+    let mut declarations = vec![];
+    let mut position = None;
+    for item in items {
+        match item {
+            Item::SourceMarker(frames) => position = frames.first(),
+            Item::GlobalLabelDeclaration(_) | Item::MacroDef(_, _, _) => {
+                if let Some(position) = position {
+                    declarations.push((position, item));
+                }
+            }
+            Item::ConditionalBlock(_, items) => declarations.extend(self::declarations(items)),
+            _ => {}
+        }
+    }
+    declarations
+}
+
+/// A piece of expanded code: either a file, or the body of a macro.
+struct Unit {
+    items: Vec<Item>,
+    /// The index of each local label in `items`.
+    labels: HashMap<String, usize>,
+}
+
+impl Unit {
+    fn new(items: Vec<Item>) -> Self {
+        let labels = items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| match item {
+                Item::LocalLabelDeclaration(label) => Some((label.clone(), index)),
+                _ => None,
+            })
+            .collect();
+        Self { items, labels }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// The code returns by jumping to one of its inputs.
+    Routine,
+    /// The code returns by falling through the end of the unit, and any jump
+    /// out of the unit leaves it.
+    Macro,
+}
+
+/// The abstract stack, made of `values` from bottom to top, on top of the
+/// inputs following the first `consumed` ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AbstractStack {
+    consumed: usize,
+    values: Vec<Value>,
+}
+
+impl AbstractStack {
+    const fn new() -> Self {
+        Self {
+            consumed: 0,
+            values: vec![],
+        }
+    }
+
+    /// The height of the stack, relative to its height on entry.
+    fn height(&self) -> isize {
+        self.values.len() as isize - self.consumed as isize
+    }
+
+    /// Ensures that at least `n` values are tracked, bringing inputs from the
+    /// bottom as needed.
+    fn ensure(&mut self, n: usize) {
+        while self.values.len() < n {
+            self.values.insert(0, Value::Input(self.consumed));
+            self.consumed += 1;
+        }
+    }
+
+    fn materialize(&mut self, consumed: usize) {
+        while self.consumed < consumed {
+            self.values.insert(0, Value::Input(self.consumed));
+            self.consumed += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.ensure(1);
+        self.values.pop().unwrap()
+    }
+
+    fn push(&mut self, value: Value) {
+        self.values.push(value);
+    }
+
+    fn dup(&mut self, n: usize) {
+        self.ensure(n);
+        let value = self.values[self.values.len() - n].clone();
+        self.values.push(value);
+    }
+
+    fn swap(&mut self, n: usize) {
+        self.ensure(n + 1);
+        let len = self.values.len();
+        self.values.swap(len - 1, len - 1 - n);
+    }
+
+    /// Merges `other`, of the same height, into this stack.
+    fn merge(&mut self, mut other: Self) {
+        self.materialize(other.consumed);
+        other.materialize(self.consumed);
+        for (value, other) in self.values.iter_mut().zip(other.values) {
+            if *value != other {
+                *value = Value::Unknown;
+            }
+        }
+    }
+}
+
+/// The state of the analysis of a single routine or macro.
+struct Walk {
+    unit: usize,
+    mode: Mode,
+    states: HashMap<usize, AbstractStack>,
+    worklist: Vec<usize>,
+    returns: Vec<(AbstractStack, Option<usize>)>,
+    unknown: bool,
+}
+
+struct Analyzer {
+    units: Vec<Unit>,
+    file_names: Vec<String>,
+    globals: HashMap<String, (usize, usize)>,
+    /// The effect of the code following each label, given by its unit and its
+    /// index in the unit, or `None` while it is being analyzed.
+    label_effects: HashMap<(usize, usize), Option<StackEffect>>,
+    errors: Vec<StackEffectError>,
+    reported_joins: HashSet<(usize, usize)>,
+}
+
+impl Analyzer {
+    fn routine_effect(&mut self, label: &str) -> StackEffect {
+        let (unit, index) = self.globals[label];
+        self.label_effect(unit, index)
+    }
+
+    /// Returns the effect of the code following the label at `index`, when
+    /// called as a routine.
+    fn label_effect(&mut self, unit: usize, index: usize) -> StackEffect {
+        match self.label_effects.get(&(unit, index)) {
+            Some(Some(effect)) => return effect.clone(),
+            // Recursive calls are not supported.
+            Some(None) => return StackEffect::Unknown,
+            None => {}
+        }
+
+        self.label_effects.insert((unit, index), None);
+        let name = match &self.units[unit].items[index] {
+            Item::GlobalLabelDeclaration(label) | Item::LocalLabelDeclaration(label) => {
+                label.clone()
+            }
+            item => unreachable!("Not a label: {item:?}"),
+        };
+        let effect = self.analyze(unit, index, Mode::Routine, &name);
+        self.label_effects
+            .insert((unit, index), Some(effect.clone()));
+        effect
+    }
+
+    /// Analyzes the code of `unit` starting at `start`.
+    fn analyze(&mut self, unit: usize, start: usize, mode: Mode, name: &str) -> StackEffect {
+        let mut walk = Walk {
+            unit,
+            mode,
+            states: HashMap::from([(start, AbstractStack::new())]),
+            worklist: vec![start],
+            returns: vec![],
+            unknown: false,
+        };
+
+        let mut visits = 0;
+        while let Some(block) = walk.worklist.pop() {
+            visits += 1;
+            if visits > MAX_VISITS {
+                walk.unknown = true;
+                break;
+            }
+            let stack = walk.states[&block].clone();
+            self.run_block(&mut walk, block, stack);
+        }
+
+        let mut returns = walk.returns.into_iter();
+        let Some((mut stack, retdest)) = returns.next() else {
+            return if walk.unknown {
+                StackEffect::Unknown
+            } else {
+                StackEffect::Diverges
+            };
+        };
+        for (mut other, other_retdest) in returns {
+            // Returning to different continuations, e.g. to the caller of the
+            // caller, is beyond the analysis.
+            if other_retdest != retdest {
+                return StackEffect::Unknown;
+            }
+            other.materialize(stack.consumed);
+            stack.materialize(other.consumed);
+            if other.height() != stack.height() {
+                self.errors.push(StackEffectError::UnbalancedReturn {
+                    name: name.to_string(),
+                    location: self.location(unit, start),
+                    heights: [stack.height(), other.height()],
+                });
+                return StackEffect::Unknown;
+            }
+            stack.merge(other);
+        }
+
+        if walk.unknown {
+            return StackEffect::Unknown;
+        }
+        StackEffect::Returns {
+            inputs: stack.consumed,
+            outputs: stack.values,
+            retdest,
+        }
+    }
+
+    /// Runs the straight-line code starting at `start`, up to the next jump or
+    /// label.
+    fn run_block(&mut self, walk: &mut Walk, start: usize, mut stack: AbstractStack) {
+        let len = self.units[walk.unit].items.len();
+        for index in start..len {
+            let item = self.units[walk.unit].items[index].clone();
+            match item {
+                Item::SourceMarker(_) => {}
+                Item::LocalLabelDeclaration(_) if index == start => {}
+                Item::GlobalLabelDeclaration(_) if index == start => {}
+                Item::LocalLabelDeclaration(_) => {
+                    let unit = walk.unit;
+                    self.jump(walk, Value::Local { unit, index }, stack);
+                    return;
+                }
+                // Falling through a global label is a tail call.
+                Item::GlobalLabelDeclaration(label) => {
+                    self.jump(walk, Value::Global(label), stack);
+                    return;
+                }
+                Item::Push(PushTarget::Label(label)) => {
+                    let value = self.resolve(walk.unit, &label);
+                    stack.push(value);
+                }
+                Item::Push(_) | Item::ProverInput(_) => stack.push(Value::Unknown),
+                Item::StandardOp(op) => match op.as_str() {
+                    "JUMP" => {
+                        let target = stack.pop();
+                        self.jump(walk, target, stack);
+                        return;
+                    }
+                    "JUMPI" => {
+                        let target = stack.pop();
+                        stack.pop();
+                        self.jump(walk, target, stack.clone());
+                    }
+                    _ => {
+                        if let Some(n) = op.strip_prefix("DUP") {
+                            stack.dup(n.parse().unwrap());
+                        } else if let Some(n) = op.strip_prefix("SWAP") {
+                            stack.swap(n.parse().unwrap());
+                        } else if is_terminal(&op) {
+                            return;
+                        } else if let Some((pops, pushes)) = op_stack_effect(&op) {
+                            for _ in 0..pops {
+                                stack.pop();
+                            }
+                            for _ in 0..pushes {
+                                stack.push(Value::Unknown);
+                            }
+                        } else {
+                            walk.unknown = true;
+                            return;
+                        }
+                    }
+                },
+                // Data is not meant to be executed.
+                Item::Bytes(_) | Item::Jumptable(_) => {
+                    walk.unknown = true;
+                    return;
+                }
+                _ => unreachable!("Item should have been expanded already: {item:?}"),
+            }
+        }
+
+        match walk.mode {
+            Mode::Macro => walk.returns.push((stack, None)),
+            Mode::Routine => walk.unknown = true,
+        }
+    }
+
+    /// Continues the analysis at the given jump destination.
+    fn jump(&mut self, walk: &mut Walk, target: Value, stack: AbstractStack) {
+        match target {
+            // A local label is called like a routine if its effect is known,
+            // and is part of the code being analyzed otherwise.
+            Value::Local { unit, index } if unit == walk.unit => {
+                match self.label_effect(unit, index) {
+                    StackEffect::Unknown => self.join(walk, index, stack),
+                    effect => self.call(walk, effect, stack),
+                }
+            }
+            Value::Global(label) => {
+                let effect = self.routine_effect(&label);
+                self.call(walk, effect, stack);
+            }
+            Value::Input(i) if walk.mode == Mode::Routine => walk.returns.push((stack, Some(i))),
+            _ => match walk.mode {
+                // The execution leaves the macro.
+                Mode::Macro => {}
+                Mode::Routine => walk.unknown = true,
+            },
+        }
+    }
+
+    /// Continues the analysis after calling a routine with the given effect.
+    fn call(&mut self, walk: &mut Walk, effect: StackEffect, mut stack: AbstractStack) {
+        match effect {
+            StackEffect::Returns {
+                inputs,
+                outputs,
+                retdest,
+            } => {
+                let args: Vec<_> = (0..inputs).map(|_| stack.pop()).collect();
+                for output in outputs {
+                    stack.push(match output {
+                        Value::Input(i) => args[i].clone(),
+                        value => value,
+                    });
+                }
+                let retdest = retdest.expect("Routines return to one of their inputs");
+                self.jump(walk, args[retdest].clone(), stack);
+            }
+            StackEffect::Diverges => {}
+            StackEffect::Unknown => walk.unknown = true,
+        }
+    }
+
+    /// Merges the stack reaching the label at `index` with the ones reaching
+    /// it from other paths.
+    fn join(&mut self, walk: &mut Walk, index: usize, stack: AbstractStack) {
+        match walk.states.entry(index) {
+            Entry::Vacant(entry) => {
+                entry.insert(stack);
+                walk.worklist.push(index);
+            }
+            Entry::Occupied(mut entry) => {
+                let mut merged = entry.get().clone();
+                let mut aligned = stack;
+                aligned.materialize(merged.consumed);
+                merged.materialize(aligned.consumed);
+                if merged.height() != aligned.height() {
+                    if self.reported_joins.insert((walk.unit, index)) {
+                        let Item::LocalLabelDeclaration(label) =
+                            &self.units[walk.unit].items[index]
+                        else {
+                            unreachable!()
+                        };
+                        self.errors.push(StackEffectError::UnbalancedJoin {
+                            label: label.clone(),
+                            location: self.location(walk.unit, index),
+                            heights: [merged.height(), aligned.height()],
+                        });
+                    }
+                    walk.unknown = true;
+                    return;
+                }
+
+                merged.merge(aligned);
+                if merged != *entry.get() {
+                    entry.insert(merged);
+                    walk.worklist.push(index);
+                }
+            }
+        }
+    }
+
+    fn resolve(&self, unit: usize, label: &str) -> Value {
+        if let Some(&index) = self.units[unit].labels.get(label) {
+            Value::Local { unit, index }
+        } else if self.globals.contains_key(label) {
+            Value::Global(label.to_string())
+        } else {
+            Value::Unknown
+        }
+    }
+
+    /// Returns the source position of the item at `index`, within the
+    /// innermost macro expansion.
+    fn location(&self, unit: usize, index: usize) -> String {
+        self.units[unit].items[..=index]
+            .iter()
+            .rev()
+            .find_map(|item| match item {
+                Item::SourceMarker(frames) => frames.first(),
+                _ => None,
+            })
+            .map_or_else(
+                || "<unknown>".to_string(),
+                |frame| format!("{}:{}", self.file_names[frame.file], frame.line),
+            )
+    }
+}
+
+/// Whether the opcode ends the execution of the current kernel code.
+fn is_terminal(op: &str) -> bool {
+    matches!(
+        op,
+        "STOP" | "RETURN" | "REVERT" | "INVALID" | "SELFDESTRUCT" | "PANIC" | "EXIT_KERNEL"
+    )
+}
+
+/// Returns the number of items popped and pushed by an opcode, other than
+/// `DUP`, `SWAP` and jumps.
+fn op_stack_effect(op: &str) -> Option<(usize, usize)> {
+    if op.starts_with("MSTORE_32BYTES_") {
+        return Some((2, 1));
+    }
+    if let Some(n) = op.strip_prefix("LOG") {
+        return Some((n.parse::<usize>().ok()? + 2, 0));
+    }
+
+    Some(match op {
+        "JUMPDEST" => (0, 0),
+        "ADDRESS" | "ORIGIN" | "CALLER" | "CALLVALUE" | "CALLDATASIZE" | "CODESIZE"
+        | "GASPRICE" | "RETURNDATASIZE" | "COINBASE" | "TIMESTAMP" | "NUMBER" | "DIFFICULTY"
+        | "GASLIMIT" | "CHAINID" | "BASEFEE" | "BLOBBASEFEE" | "GETPC" | "MSIZE" | "GAS"
+        | "GET_CONTEXT" => (0, 1),
+        "POP" | "SET_CONTEXT" => (1, 0),
+        "ISZERO" | "NOT" | "BALANCE" | "CALLDATALOAD" | "EXTCODESIZE" | "EXTCODEHASH"
+        | "BLOCKHASH" | "MLOAD" | "SLOAD" | "TLOAD" | "MLOAD_GENERAL" => (1, 1),
+        "MSTORE" | "MSTORE8" | "SSTORE" | "TSTORE" | "MSTORE_GENERAL" => (2, 0),
+        "ADD" | "MUL" | "SUB" | "DIV" | "SDIV" | "MOD" | "SMOD" | "EXP" | "SIGNEXTEND"
+        | "ADDFP254" | "MULFP254" | "SUBFP254" | "LT" | "GT" | "SLT" | "SGT" | "EQ" | "AND"
+        | "OR" | "XOR" | "BYTE" | "SHL" | "SHR" | "SAR" | "KECCAK256" | "KECCAK_GENERAL"
+        | "MLOAD_32BYTES" => (2, 1),
+        "CALLDATACOPY" | "CODECOPY" | "RETURNDATACOPY" | "MCOPY" => (3, 0),
+        "ADDMOD" | "MULMOD" | "SUBMOD" | "CREATE" => (3, 1),
+        "EXTCODECOPY" => (4, 0),
+        "CREATE2" => (4, 1),
+        "DELEGATECALL" | "STATICCALL" => (6, 1),
+        "CALL" | "CALLCODE" => (7, 1),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::kernel::aggregator::KERNEL_FILES;
+
+    fn check(code: &str) -> StackEffects {
+        check_stack_effects(&[("test.asm", code)])
+    }
+
+    fn summarize(effect: &StackEffect) -> Option<(usize, usize, Option<usize>)> {
+        match effect {
+            StackEffect::Returns {
+                inputs,
+                outputs,
+                retdest,
+            } => Some((*inputs, outputs.len(), *retdest)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn infer_effects() {
+        let effects = check(
+            "
+            %macro add3
+                ADD
+                ADD
+            %endmacro
+
+            %macro jump(dst)
+                PUSH $dst
+                JUMP
+            %endmacro
+
+            %macro jumpi(dst)
+                PUSH $dst
+                JUMPI
+            %endmacro
+
+            %macro max
+                // stack: a, b
+                DUP2
+                DUP2
+                LT
+                %jumpi(%%b_larger)
+                SWAP1
+            %%b_larger:
+                POP
+            %endmacro
+
+            global sum3:
+                // stack: a, b, c, retdest
+                %add3
+                // stack: sum, retdest
+                SWAP1
+                JUMP
+
+            global double_sum3:
+                // stack: a, b, c, retdest
+                PUSH after_sum
+                %stack (after_sum, a, b, c) -> (a, b, c, after_sum)
+                %jump(sum3)
+            after_sum:
+                // stack: sum, retdest
+                DUP1
+                ADD
+                SWAP1
+                JUMP
+
+            global fail:
+                PANIC
+            ",
+        );
+
+        assert!(effects.errors.is_empty(), "{:?}", effects.errors);
+        let macro_effect = |name: &str, arity| summarize(&effects.macros[&(name.into(), arity)]);
+        assert_eq!(macro_effect("add3", 0), Some((3, 1, None)));
+        assert_eq!(macro_effect("max", 0), Some((2, 1, None)));
+        assert_eq!(effects.macros[&("jump".into(), 1)], StackEffect::Diverges);
+        assert_eq!(summarize(&effects.routines["sum3"]), Some((4, 1, Some(3))));
+        assert_eq!(
+            summarize(&effects.routines["double_sum3"]),
+            Some((4, 1, Some(3)))
+        );
+        assert_eq!(effects.routines["fail"], StackEffect::Diverges);
+    }
+
+    #[test]
+    fn join_mismatch() {
+        // Each iteration leaves one more item on the stack.
+        let effects = check(
+            "
+            global push_zeros:
+                // stack: n, retdest
+            loop:
+                DUP1
+                ISZERO
+                PUSH done
+                JUMPI
+                PUSH 0
+                SWAP1
+                PUSH 1
+                SWAP1
+                SUB
+                // stack: n - 1, 0, retdest
+                PUSH loop
+                JUMP
+            done:
+                POP
+                JUMP
+            ",
+        );
+
+        assert_eq!(effects.routines["push_zeros"], StackEffect::Unknown);
+        assert_eq!(
+            effects.errors,
+            vec![StackEffectError::UnbalancedJoin {
+                label: "loop".into(),
+                location: "test.asm:4".into(),
+                heights: [0, 1],
+            }]
+        );
+    }
+
+    #[test]
+    fn annotation_mismatch() {
+        let effects = check(
+            "
+            // Pre stack: a, b, retdest
+            // Post stack: sum
+            global add:
+                ADD
+                SWAP1
+                JUMP
+
+            // Pre stack: a, b, c, retdest
+            // Post stack: sum
+            global add_wrong:
+                ADD
+                SWAP1
+                JUMP
+            ",
+        );
+
+        assert_eq!(effects.errors.len(), 1);
+        assert_eq!(
+            effects.errors[0].to_string(),
+            "test.asm:11: add_wrong is annotated with (a, b, c, retdest) -> (sum) \
+             but its inferred effect is 3 -> 1"
+        );
+    }
+
+    /// Kernel files whose loops deliberately keep a variable number of items
+    /// on the stack, such as the limbs of a big integer or the words of a
+    /// hash state.
+    const VARIABLE_HEIGHT_FILES: [&str; 4] = [
+        "asm/core/precompiles/expmod.asm",
+        "asm/hash/blake2/hash.asm",
+        "asm/hash/blake2/iv.asm",
+        "asm/hash/ripemd/compression.asm",
+    ];
+
+    #[test]
+    fn kernel_stack_effects() {
+        let effects = check_stack_effects(&KERNEL_FILES);
+        let errors: Vec<_> = effects
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .filter(|error| {
+                !VARIABLE_HEIGHT_FILES
+                    .iter()
+                    .any(|file| error.starts_with(&format!("{file}:")))
+            })
+            .collect();
+        assert!(errors.is_empty(), "\n{}", errors.join("\n"));
+    }
+}
@@ -302,6 +302,54 @@ fn test_extcodesize() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_get_delegate() -> Result<()> {
+    let delegate = Address::from_low_u64_be(0xaa);
+    let designator = [&hex!("ef0100")[..], delegate.as_bytes()].concat();
+
+    for (code, expected) in [
+        (
+            designator.clone(),
+            U256::from_big_endian(delegate.as_bytes()),
+        ),
+        // Wrong prefix.
+        (
+            [&hex!("ef0101")[..], delegate.as_bytes()].concat(),
+            U256::zero(),
+        ),
+        // Right prefix, but too long.
+        ([&designator[..], &[0x00]].concat(), U256::zero()),
+        // Right prefix, but too short.
+        (designator[..22].to_vec(), U256::zero()),
+    ] {
+        let account = test_account(&code);
+
+        let mut interpreter: Interpreter<F> = Interpreter::new(0, vec![], None);
+        let address: Address = thread_rng().gen();
+        // Prepare the interpreter by inserting the account in the state trie.
+        prepare_interpreter(&mut interpreter, address, &account)?;
+
+        interpreter.generation_state.registers.program_counter =
+            KERNEL.global_labels["get_delegate"];
+        interpreter.pop().expect("The stack should not be empty");
+        interpreter.pop().expect("The stack should not be empty");
+        assert!(interpreter.stack().is_empty());
+        interpreter
+            .push(0xDEADBEEFu32.into())
+            .expect("The stack should not overflow");
+        interpreter
+            .push(U256::from_big_endian(address.as_bytes()))
+            .expect("The stack should not overflow");
+        interpreter.generation_state.inputs.contract_code =
+            HashMap::from([(keccak(&code), code.clone())]);
+        interpreter.run()?;
+
+        assert_eq!(interpreter.stack(), vec![expected]);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_extcodecopy() -> Result<()> {
     let code = random_code();
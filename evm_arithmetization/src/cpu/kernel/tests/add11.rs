@@ -10,8 +10,9 @@ use plonky2::field::goldilocks_field::GoldilocksField as F;
 
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::interpreter::Interpreter;
+use crate::cpu::kernel::tracer::Tracer;
 use crate::generation::mpt::{AccountRlp, LegacyReceiptRlp};
-use crate::generation::TrieInputs;
+use crate::generation::{ChainProfile, TrieInputs};
 use crate::proof::{BlockHashes, BlockMetadata, TrieRoots};
 use crate::testing_utils::{
     beacon_roots_account_nibbles, beacon_roots_contract_from_storage, ger_account_nibbles,
@@ -182,10 +183,12 @@ fn test_add11_yml() {
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals: vec![],
         global_exit_roots: vec![],
         tries: tries_before,
         trie_roots_after,
+        expected_tries_after: None,
         contract_code: contract_code.clone(),
         block_metadata,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
@@ -204,7 +207,30 @@ fn test_add11_yml() {
         Interpreter::new_with_generation_inputs(initial_offset, initial_stack, &inputs, None);
 
     interpreter.set_is_kernel(true);
+    interpreter.tracer = Some(Tracer::new());
     interpreter.run().expect("Proving add11 failed.");
+
+    // PUSH1 1, PUSH1 1, ADD, PUSH1 0, SSTORE, STOP.
+    let traces = interpreter.tracer.take().unwrap().into_traces();
+    assert_eq!(traces.len(), 1);
+    let steps = &traces[0].steps;
+    let ops: Vec<_> = steps.iter().map(|step| (step.pc, step.op)).collect();
+    assert_eq!(
+        ops,
+        vec![
+            (0, 0x60),
+            (2, 0x60),
+            (4, 0x01),
+            (5, 0x60),
+            (7, 0x55),
+            (8, 0x00)
+        ]
+    );
+    let costs: Vec<_> = steps.iter().map(|step| step.gas_cost).collect();
+    assert_eq!(costs, vec![3, 3, 3, 3, 22100, 0]);
+    assert_eq!(steps[2].stack, vec![1.into(), 1.into()]);
+    assert_eq!(steps[4].stack, vec![2.into(), 0.into()]);
+    assert!(steps.iter().all(|step| step.depth == 1));
 }
 
 #[test]
@@ -364,10 +390,12 @@ fn test_add11_yml_with_exception() {
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals: vec![],
         global_exit_roots: vec![],
         tries: tries_before,
         trie_roots_after,
+        expected_tries_after: None,
         contract_code: contract_code.clone(),
         block_metadata,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
@@ -3,13 +3,18 @@ use ethereum_types::U256;
 use hex_literal::hex;
 use plonky2::field::goldilocks_field::GoldilocksField as F;
 use rand::Rng;
+use serde::Deserialize;
 
 use super::{run_interpreter_with_memory, InterpreterMemoryInitialization};
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::cancun_constants::POINT_EVALUATION_PRECOMPILE_RETURN_VALUE;
 use crate::cpu::kernel::constants::cancun_constants::KZG_VERSIONED_HASH;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
 use crate::cpu::kernel::interpreter::Interpreter;
 use crate::extension_tower::{Fp2, Stack, BLS381};
+use crate::generation::ChainProfile;
+use crate::memory::segments::Segment;
 use crate::memory::segments::Segment::KernelGeneral;
 use crate::util::sha2;
 
@@ -174,3 +179,231 @@ fn test_kzg_peval_precompile() -> Result<()> {
 
     Ok(())
 }
+
+/// A test vector for the EIP-2537 precompiles. Failure cases don't have an
+/// expected output.
+///
+/// The gas costs specified in the test vectors are not checked, as they
+/// predate the final gas schedule of EIP-2537.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Eip2537TestVector {
+    input: String,
+    expected: Option<String>,
+    name: String,
+}
+
+/// Runs the precompile starting at `label` on the given test vectors, taken
+/// from <https://github.com/ethereum/EIPs/tree/master/assets/eip-2537>.
+fn run_eip2537_test_vectors(label: &str, test_vectors: &str) -> Result<()> {
+    let test_vectors: Vec<Eip2537TestVector> = serde_json::from_str(test_vectors)?;
+
+    for test_vector in test_vectors {
+        let input = hex::decode(&test_vector.input)?;
+
+        let kexit_info = U256::one() << 32;
+        let mut interpreter: Interpreter<F> =
+            Interpreter::new(KERNEL.global_labels[label], vec![kexit_info], None);
+        interpreter.halt_offsets = vec![
+            KERNEL.global_labels["terminate_common"],
+            KERNEL.global_labels["fault_exception"],
+        ];
+        interpreter.set_context_metadata_field(0, ContextMetadata::GasLimit, 10_000_000.into());
+        interpreter.set_context_metadata_field(
+            0,
+            ContextMetadata::CalldataSize,
+            input.len().into(),
+        );
+        interpreter.set_memory_segment_bytes(Segment::Calldata, input);
+
+        interpreter.run()?;
+
+        let pc = interpreter.generation_state.registers.program_counter;
+        match test_vector.expected {
+            Some(expected) => {
+                let expected = hex::decode(expected)?;
+                assert_eq!(
+                    pc, KERNEL.global_labels["terminate_common"],
+                    "{} should succeed",
+                    test_vector.name
+                );
+                let returndata = interpreter.get_memory_segment_bytes(Segment::Returndata);
+                assert_eq!(
+                    returndata[..expected.len()],
+                    expected,
+                    "{}",
+                    test_vector.name
+                );
+            }
+            None => assert_eq!(
+                pc, KERNEL.global_labels["fault_exception"],
+                "{} should fail",
+                test_vector.name
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_bls12_g1add_precompile() -> Result<()> {
+    run_eip2537_test_vectors(
+        "bls12_g1add",
+        include_str!("eip2537_test_vectors/add_G1_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g1add",
+        include_str!("eip2537_test_vectors/fail-add_G1_bls.json"),
+    )
+}
+
+#[test]
+fn test_bls12_g2add_precompile() -> Result<()> {
+    run_eip2537_test_vectors(
+        "bls12_g2add",
+        include_str!("eip2537_test_vectors/add_G2_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g2add",
+        include_str!("eip2537_test_vectors/fail-add_G2_bls.json"),
+    )
+}
+
+// The multiplication test vectors are run against the MSM precompiles, as
+// scalar multiplications are MSMs over a single pair.
+#[test]
+fn test_bls12_g1msm_precompile() -> Result<()> {
+    run_eip2537_test_vectors(
+        "bls12_g1msm",
+        include_str!("eip2537_test_vectors/multiexp_G1_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g1msm",
+        include_str!("eip2537_test_vectors/fail-multiexp_G1_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g1msm",
+        include_str!("eip2537_test_vectors/mul_G1_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g1msm",
+        include_str!("eip2537_test_vectors/fail-mul_G1_bls.json"),
+    )
+}
+
+#[test]
+fn test_bls12_g2msm_precompile() -> Result<()> {
+    run_eip2537_test_vectors(
+        "bls12_g2msm",
+        include_str!("eip2537_test_vectors/multiexp_G2_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g2msm",
+        include_str!("eip2537_test_vectors/fail-multiexp_G2_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g2msm",
+        include_str!("eip2537_test_vectors/mul_G2_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_g2msm",
+        include_str!("eip2537_test_vectors/fail-mul_G2_bls.json"),
+    )
+}
+
+#[test]
+fn test_bls12_pairing_precompile() -> Result<()> {
+    run_eip2537_test_vectors(
+        "bls12_pairing",
+        include_str!("eip2537_test_vectors/pairing_check_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_pairing",
+        include_str!("eip2537_test_vectors/fail-pairing_check_bls.json"),
+    )
+}
+
+#[test]
+fn test_bls12_map_fp_to_g1_precompile() -> Result<()> {
+    run_eip2537_test_vectors(
+        "bls12_map_fp_to_g1",
+        include_str!("eip2537_test_vectors/map_fp_to_G1_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_map_fp_to_g1",
+        include_str!("eip2537_test_vectors/fail-map_fp_to_G1_bls.json"),
+    )
+}
+
+#[test]
+fn test_bls12_map_fp2_to_g2_precompile() -> Result<()> {
+    run_eip2537_test_vectors(
+        "bls12_map_fp2_to_g2",
+        include_str!("eip2537_test_vectors/map_fp2_to_G2_bls.json"),
+    )?;
+    run_eip2537_test_vectors(
+        "bls12_map_fp2_to_g2",
+        include_str!("eip2537_test_vectors/fail-map_fp2_to_G2_bls.json"),
+    )
+}
+
+/// On Cancun chains, the EIP-2537 addresses are plain accounts: they are not
+/// pre-warmed, so calling them is charged as a cold account access, and the
+/// call runs their (empty) code instead of a precompile.
+#[test]
+fn test_bls12_381_precompiles_disabled_on_cancun() -> Result<()> {
+    let retdest = U256::from(0xdeadbeefu32);
+    let mut interpreter = Interpreter::<F>::new(
+        KERNEL.global_labels["init_access_lists"],
+        vec![retdest],
+        None,
+    );
+    interpreter.set_global_metadata_multi_fields(&[(
+        GlobalMetadata::Bls12381Enabled,
+        U256::from(ChainProfile::L1.has_bls12_381() as u8),
+    )]);
+    interpreter.run()?;
+
+    interpreter.generation_state.registers.program_counter =
+        KERNEL.global_labels["warm_precompiles"];
+    interpreter.halt_offsets = vec![KERNEL.global_labels["warm_coinbase"]];
+    interpreter.run()?;
+    interpreter.halt_offsets = vec![retdest.as_usize()];
+
+    // `insert_accessed_addresses` returns whether the address was cold, which is
+    // what `CALL` charges the cold access cost on.
+    let mut is_cold = |address: u8| -> Result<bool> {
+        interpreter.generation_state.registers.program_counter =
+            KERNEL.global_labels["insert_accessed_addresses"];
+        interpreter
+            .push(retdest)
+            .expect("The stack should not overflow");
+        interpreter
+            .push(address.into())
+            .expect("The stack should not overflow");
+        interpreter.run()?;
+        Ok(interpreter.pop().expect("The stack can't be empty") == U256::one())
+    };
+    assert!(!is_cold(0x0a)?, "KZG_PEVAL should be warm");
+    assert!(is_cold(0x0b)?, "BLS12_G1ADD should be cold");
+
+    // A call to 0x0b goes straight back to the caller, which then runs the
+    // account's code.
+    let new_ctx = U256::from(1);
+    interpreter.generation_state.registers.program_counter =
+        KERNEL.global_labels["handle_precompiles"];
+    interpreter
+        .push(new_ctx)
+        .expect("The stack should not overflow");
+    interpreter
+        .push(retdest)
+        .expect("The stack should not overflow");
+    interpreter
+        .push(0x0b.into())
+        .expect("The stack should not overflow");
+    interpreter.run()?;
+    assert_eq!(interpreter.stack(), vec![new_ctx]);
+
+    Ok(())
+}
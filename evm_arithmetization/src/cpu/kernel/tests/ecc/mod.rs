@@ -1,2 +1,3 @@
 mod curve_ops;
 mod ecrecover;
+mod p256_verify;
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use ethereum_types::U256;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::cpu::kernel::tests::{run_interpreter, u256ify};
+use crate::memory::segments::Segment;
+
+/// Inputs of the `P256VERIFY` precompile, along with whether they hold a valid
+/// signature. The valid ones are taken from
+/// <https://github.com/daimo-eth/p256-verifier/tree/master/test-vectors>.
+const P256_VERIFY_TEST_VECTORS: [(&str, bool); 16] = [
+    ("4cee90eb86eaa050036147a12d49004b6b9c72bd725d39d4785011fe190f0b4da73bd4903f0ce3b639bbbf6e8e80d16931ff4bcf5993d58468e8fb19086e8cac36dbcd03009df8c59286b162af3bd7fcc0450c9aa81be5d10d312af6c66b1d604aebd3099c618202fcfe16ae7770b0c49ab5eadf74b754204a3bb6060e44eff37618b065f9832de4ca6ca971a7a1adc826d0f7c00181a5fb2ddf79ae00b4e10e", true),
+    ("3fec5769b5cf4e310a7d150508e82fb8e3eda1c2c94c61492d3bd8aea99e06c9e22466e928fdccef0de49e3503d2657d00494a00e764fd437bdafa05f5922b1fbbb77c6817ccf50748419477e843d5bac67e6a70e97dde5a57e0c983b777e1ad31a80482dadf89de6302b1988c82c29544c9c07bb910596158f6062517eb089a2f54c9a0f348752950094d3228d3b940258c75fe2a413cb70baa21dc2e352fc5", true),
+    ("e775723953ead4a90411a02908fd1a629db584bc600664c609061f221ef6bf7c440066c8626b49daaa7bf2bcc0b74be4f7a1e3dcf0e869f1542fe821498cbf2de73ad398194129f635de4424a07ca715838aefe8fe69d1a391cfa70470795a80dd056866e6e1125aff94413921880c437c9e2570a28ced7267c8beef7e9b2d8d1547d76dfcf4bee592f5fefe10ddfb6aeb0991c5b9dbbee6ec80d11b17c0eb1a", true),
+    ("b5a77e7a90aa14e0bf5f337f06f597148676424fae26e175c6e5621c34351955289f319789da424845c9eac935245fcddd805950e2f02506d09be7e411199556d262144475b1fa46ad85250728c600c53dfd10f8b3f4adf140e27241aec3c2da3a81046703fccf468b48b145f939efdbb96c3786db712b3113bb2488ef286cdcef8afe82d200a5bb36b5462166e8ce77f2d831a52ef2135b2af188110beaefb1", true),
+    ("858b991cfd78f16537fe6d1f4afd10273384db08bdfc843562a22b0626766686f6aec8247599f40bfe01bec0e0ecf17b4319559022d4d9bf007fe929943004eb4866760dedf31b7c691f5ce665f8aae0bda895c23595c834fecc2390a5bcc203b04afcacbb4280713287a2d0c37e23f7513fab898f2c1fefa00ec09a924c335d9b629f1d4fb71901c3e59611afbfea354d101324e894c788d1c01f00b3c251b2", true),
+    ("3cee90eb86eaa050036147a12d49004b6b9c72bd725d39d4785011fe190f0b4da73bd4903f0ce3b639bbbf6e8e80d16931ff4bcf5993d58468e8fb19086e8cac36dbcd03009df8c59286b162af3bd7fcc0450c9aa81be5d10d312af6c66b1d604aebd3099c618202fcfe16ae7770b0c49ab5eadf74b754204a3bb6060e44eff37618b065f9832de4ca6ca971a7a1adc826d0f7c00181a5fb2ddf79ae00b4e10e", false),
+    ("afec5769b5cf4e310a7d150508e82fb8e3eda1c2c94c61492d3bd8aea99e06c9e22466e928fdccef0de49e3503d2657d00494a00e764fd437bdafa05f5922b1fbbb77c6817ccf50748419477e843d5bac67e6a70e97dde5a57e0c983b777e1ad31a80482dadf89de6302b1988c82c29544c9c07bb910596158f6062517eb089a2f54c9a0f348752950094d3228d3b940258c75fe2a413cb70baa21dc2e352fc5", false),
+    ("f775723953ead4a90411a02908fd1a629db584bc600664c609061f221ef6bf7c440066c8626b49daaa7bf2bcc0b74be4f7a1e3dcf0e869f1542fe821498cbf2de73ad398194129f635de4424a07ca715838aefe8fe69d1a391cfa70470795a80dd056866e6e1125aff94413921880c437c9e2570a28ced7267c8beef7e9b2d8d1547d76dfcf4bee592f5fefe10ddfb6aeb0991c5b9dbbee6ec80d11b17c0eb1a", false),
+    ("c5a77e7a90aa14e0bf5f337f06f597148676424fae26e175c6e5621c34351955289f319789da424845c9eac935245fcddd805950e2f02506d09be7e411199556d262144475b1fa46ad85250728c600c53dfd10f8b3f4adf140e27241aec3c2da3a81046703fccf468b48b145f939efdbb96c3786db712b3113bb2488ef286cdcef8afe82d200a5bb36b5462166e8ce77f2d831a52ef2135b2af188110beaefb1", false),
+    ("958b991cfd78f16537fe6d1f4afd10273384db08bdfc843562a22b0626766686f6aec8247599f40bfe01bec0e0ecf17b4319559022d4d9bf007fe929943004eb4866760dedf31b7c691f5ce665f8aae0bda895c23595c834fecc2390a5bcc203b04afcacbb4280713287a2d0c37e23f7513fab898f2c1fefa00ec09a924c335d9b629f1d4fb71901c3e59611afbfea354d101324e894c788d1c01f00b3c251b2", false),
+    ("4cee90eb86eaa050036147a12d49004b6a", false),
+    ("4cee90eb86eaa050036147a12d49004b6a958b991cfd78f16537fe6d1f4afd10273384db08bdfc843562a22b0626766686f6aec8247599f40bfe01bec0e0ecf17b4319559022d4d9bf007fe929943004eb4866760dedf319", false),
+    ("4cee90eb86eaa050036147a12d49004b6b9c72bd725d39d4785011fe190f0b4da73bd4903f0ce3b639bbbf6e8e80d16931ff4bcf5993d58468e8fb19086e8cac36dbcd03009df8c59286b162af3bd7fcc0450c9aa81be5d10d312af6c66b1d604aebd3099c618202fcfe16ae7770b0c49ab5eadf74b754204a3bb6060e44eff37618b065f9832de4ca6ca971a7a1adc826d0f7c00181a5fb2ddf79ae00b4e10e00", false),
+    ("4cee90eb86eaa050036147a12d49004b6b9c72bd725d39d4785011fe190f0b4dffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff4aebd3099c618202fcfe16ae7770b0c49ab5eadf74b754204a3bb6060e44eff37618b065f9832de4ca6ca971a7a1adc826d0f7c00181a5fb2ddf79ae00b4e10e", false),
+    ("4cee90eb86eaa050036147a12d49004b6b9c72bd725d39d4785011fe190f0b4da73bd4903f0ce3b639bbbf6e8e80d16931ff4bcf5993d58468e8fb19086e8cac36dbcd03009df8c59286b162af3bd7fcc0450c9aa81be5d10d312af6c66b1d6000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000", false),
+    ("b5a77e7a90aa14e0bf5f337f06f597148676424fae26e175c6e5621c34351955289f319789da424845c9eac935245fcddd805950e2f02506d09be7e411199556d262144475b1fa46ad85250728c600c53dfd10f8b3f4adf140e27241aec3c2daaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaef8afe82d200a5bb36b5462166e8ce77f2d831a52ef2135b2af188110beaefb1", false),
+];
+
+#[test]
+fn test_p256_ec_ops() -> Result<()> {
+    let ec_add = KERNEL.global_labels["p256_add_valid_points"];
+    let ec_double = KERNEL.global_labels["p256_double"];
+    let identity = ("0x0", "0x0");
+    let point0 = (
+        "0x88bb9ff22ab291a74c86fc677ba897baadee370cc6129b82d170ba3fc26415c",
+        "0x442da9a716067956d91eaa02b93ad409490e87cd5e758ea6a331a1deb75ba846",
+    );
+    let point1 = (
+        "0x697d7b8eef74dd5796e02ed6480487a8d99a62d16022815be5be81bd7e9dbdba",
+        "0x33ee2b1e2c9964549d567e20aa16dbff7f63a92955654b5a513733841844abb1",
+    );
+    // point2 = point0 + point1
+    let point2 = (
+        "0x1ffaf33cc42d0989c520d5cfe90705084193c45dda56597ab6e1a3f495bccfd4",
+        "0xa6b49813f86847f2e52bce5824ed30b4619d7d1f0961d24dd5bf666b5a018128",
+    );
+    // point3 = 2 * point0
+    let point3 = (
+        "0xb0cdeeae5933a06851b5212af4b54cae6e3b786e9848c7ffa5e8775e139b10c6",
+        "0x1b4eaee3cd1de9f8ca671981aba8d93a68e0b616a89101be2e4754de0705da00",
+    );
+    // point4 = -point0
+    let point4 = (
+        point0.0,
+        "0xbbd25657e9f986aa26e155fd46c52bf6b6f17833a18a71595cce5e2148a457b9",
+    );
+
+    let cases = [
+        // Standard additions.
+        (ec_add, vec![point0, point1], point2),
+        (ec_add, vec![point1, point0], point2),
+        // Standard doublings.
+        (ec_add, vec![point0, point0], point3),
+        (ec_double, vec![point0], point3),
+        // Additions with the identity.
+        (ec_add, vec![identity, point1], point1),
+        (ec_add, vec![point1, identity], point1),
+        (ec_add, vec![identity, identity], identity),
+        (ec_double, vec![identity], identity),
+        // Addition of a point and its negation.
+        (ec_add, vec![point0, point4], identity),
+    ];
+    for (label, points, expected) in cases {
+        let mut initial_stack = vec!["0xdeadbeef"];
+        for (x, y) in points.into_iter().rev() {
+            initial_stack.extend([y, x]);
+        }
+        let stack = run_interpreter::<F>(label, u256ify(initial_stack)?)?
+            .stack()
+            .to_vec();
+        assert_eq!(stack, u256ify([expected.1, expected.0])?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_p256_ecdsa_verify() -> Result<()> {
+    let p256_ecdsa_verify = KERNEL.global_labels["p256_ecdsa_verify"];
+
+    for (input, is_valid) in P256_VERIFY_TEST_VECTORS {
+        let input = hex::decode(input)?;
+        if input.len() != 160 {
+            continue;
+        }
+        // stack: hash, r, s, Qx, Qy, retdest
+        let mut initial_stack = vec![U256::from(0xdeadbeefu32)];
+        initial_stack.extend(input.chunks(32).rev().map(U256::from_big_endian));
+        let stack = run_interpreter::<F>(p256_ecdsa_verify, initial_stack)?
+            .stack()
+            .to_vec();
+        assert_eq!(stack, vec![U256::from(is_valid as u8)]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_p256_verify_precompile() -> Result<()> {
+    for (input, is_valid) in P256_VERIFY_TEST_VECTORS {
+        let input = hex::decode(input)?;
+
+        let kexit_info = U256::one() << 32;
+        let mut interpreter: Interpreter<F> =
+            Interpreter::new(KERNEL.global_labels["p256_verify"], vec![kexit_info], None);
+        interpreter.halt_offsets = vec![KERNEL.global_labels["terminate_common"]];
+        interpreter.set_context_metadata_field(0, ContextMetadata::GasLimit, 100_000.into());
+        interpreter.set_context_metadata_field(
+            0,
+            ContextMetadata::CalldataSize,
+            input.len().into(),
+        );
+        interpreter.set_memory_segment_bytes(Segment::Calldata, input);
+
+        interpreter.run()?;
+
+        // Invalid inputs do not make the call fail, but return empty data.
+        let expected_returndata_size = if is_valid { 32 } else { 0 };
+        assert_eq!(
+            interpreter.get_context_metadata_field(0, ContextMetadata::ReturndataSize),
+            expected_returndata_size.into()
+        );
+        if is_valid {
+            let mut expected_returndata = [0; 32];
+            expected_returndata[31] = 1;
+            let returndata = interpreter.get_memory_segment_bytes(Segment::Returndata);
+            assert_eq!(returndata[..32], expected_returndata);
+        }
+    }
+
+    Ok(())
+}
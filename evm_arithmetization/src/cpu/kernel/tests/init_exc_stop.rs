@@ -10,9 +10,9 @@ use plonky2::field::goldilocks_field::GoldilocksField as F;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::interpreter::Interpreter;
 use crate::generation::state::State;
-use crate::generation::TrieInputs;
 use crate::generation::NUM_EXTRA_CYCLES_AFTER;
 use crate::generation::NUM_EXTRA_CYCLES_BEFORE;
+use crate::generation::{ChainProfile, TrieInputs};
 use crate::memory::segments::Segment;
 use crate::proof::BlockMetadata;
 use crate::proof::TrieRoots;
@@ -92,6 +92,7 @@ fn test_init_exc_stop() {
     let inputs = GenerationInputs {
         signed_txns: vec![],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals: vec![],
         tries: TrieInputs {
             state_trie: state_trie_before,
@@ -100,6 +101,7 @@ fn test_init_exc_stop() {
             storage_tries,
         },
         trie_roots_after,
+        expected_tries_after: None,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
         block_metadata,
@@ -120,6 +120,12 @@ impl<F: Field> Interpreter<F> {
             .set(field.unscale(), value)
     }
 
+    pub(crate) fn get_context_metadata_field(&self, ctx: usize, field: ContextMetadata) -> U256 {
+        // These fields are already scaled by their respective segment.
+        self.generation_state.memory.contexts[ctx].segments[Segment::ContextMetadata.unscale()]
+            .get(field.unscale())
+    }
+
     pub(crate) fn get_global_metadata_field(&self, field: GlobalMetadata) -> U256 {
         // These fields are already scaled by their respective segment.
         let field = field.unscale();
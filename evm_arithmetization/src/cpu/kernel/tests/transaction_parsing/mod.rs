@@ -10,6 +10,7 @@ mod parse_type_0_txn;
 mod parse_type_1_txn;
 mod parse_type_2_txn;
 mod parse_type_3_txn;
+mod parse_type_4_txn;
 
 pub(crate) fn prepare_interpreter_for_txn_parsing<F: Field>(
     interpreter: &mut Interpreter<F>,
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use ethereum_types::{Address, U256};
+use hex_literal::hex;
+use keccak_hash::H256;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+use NormalizedTxnField::*;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::constants::txn_fields::NormalizedTxnField;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::cpu::kernel::tests::account_code::prepare_interpreter;
+use crate::cpu::kernel::tests::transaction_parsing::prepare_interpreter_for_txn_parsing;
+use crate::generation::mpt::AccountRlp;
+use crate::memory::segments::Segment;
+use crate::testing_utils::EMPTY_NODE_HASH;
+
+#[test]
+fn process_type_4_txn() -> Result<()> {
+    let sender_address = Address::from_slice(&hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"));
+    let sender_account = AccountRlp {
+        nonce: 1.into(),
+        balance: 0x1000000.into(),
+        storage_root: EMPTY_NODE_HASH,
+        code_hash: H256::default(),
+    };
+
+    let mut interpreter: Interpreter<F> = Interpreter::new(0, vec![], None);
+    // Prepare the interpreter by inserting the sender account in the state trie.
+    prepare_interpreter(&mut interpreter, sender_address, &sender_account)?;
+
+    // A set code transaction with chain_id=1337, nonce=1,
+    // max_priority_fee_per_gas=500, max_fee_per_gas=1000, gas=100000,
+    // to=0x02, value=1, data=0x4242, access_list=[] and a single authorization
+    // tuple [chain_id=1337, address=0xaa, nonce=0, y_parity, r, s] signed by
+    // 0x71562b71999873db5b286df957af199ec94617f7.
+    // The transaction is signed with the private key
+    // 0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8.
+    let txn = hex!("04f8cb820539018201f48203e8830186a094000000000000000000000000000000000000000201824242c0f85ef85c8205399400000000000000000000000000000000000000aa8080a09377c312145a5afb911bf9e8c067bcf6094c533603687850df502b61290bbf5ea0321f659a4b2d0ea3c014fbea084c0911b8d5f8adc79507b7bfb058c131b735be01a018a060a121b19972b73b78bf2504935e0446c2557e4aba8e4d3c358a243edd37a06dcfdd77e9a44685dd2ac1ba5b189380f530066c5f2a4f6bec4c1307f19aed77").to_vec();
+
+    prepare_interpreter_for_txn_parsing(
+        &mut interpreter,
+        KERNEL.global_labels["process_type_4_txn"],
+        KERNEL.global_labels["process_normalized_txn"],
+        txn,
+    )?;
+
+    interpreter.run()?;
+
+    assert_eq!(interpreter.get_txn_field(ChainIdPresent), 1.into());
+    assert_eq!(interpreter.get_txn_field(ChainId), 1337.into());
+    assert_eq!(interpreter.get_txn_field(Nonce), 1.into());
+    assert_eq!(interpreter.get_txn_field(MaxPriorityFeePerGas), 500.into());
+    assert_eq!(interpreter.get_txn_field(MaxFeePerGas), 1000.into());
+    assert_eq!(interpreter.get_txn_field(GasLimit), 100000.into());
+    assert_eq!(interpreter.get_txn_field(To), 0x02.into());
+    assert_eq!(interpreter.get_txn_field(Value), 1.into());
+    assert_eq!(interpreter.get_txn_field(DataLen), 2.into());
+    assert_eq!(interpreter.get_txn_data(), &[0x42.into(), 0x42.into()]);
+    assert_eq!(interpreter.get_txn_field(YParity), 1.into());
+    assert_eq!(
+        interpreter.get_txn_field(R),
+        U256::from_big_endian(&hex!(
+            "18a060a121b19972b73b78bf2504935e0446c2557e4aba8e4d3c358a243edd37"
+        ))
+    );
+    assert_eq!(
+        interpreter.get_txn_field(S),
+        U256::from_big_endian(&hex!(
+            "6dcfdd77e9a44685dd2ac1ba5b189380f530066c5f2a4f6bec4c1307f19aed77"
+        ))
+    );
+    assert_eq!(
+        interpreter.get_txn_field(Origin),
+        U256::from_big_endian(&hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"))
+    );
+
+    assert_eq!(
+        interpreter.get_global_metadata_field(GlobalMetadata::AuthorizationListLen),
+        1.into()
+    );
+    assert_eq!(
+        interpreter.get_memory_segment(Segment::TxnAuthorizations)[..6],
+        [
+            1337.into(),
+            0xaa.into(),
+            0.into(),
+            0.into(),
+            U256::from_big_endian(&hex!(
+                "9377c312145a5afb911bf9e8c067bcf6094c533603687850df502b61290bbf5e"
+            )),
+            U256::from_big_endian(&hex!(
+                "321f659a4b2d0ea3c014fbea084c0911b8d5f8adc79507b7bfb058c131b735be"
+            )),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn process_type_4_txn_invalid_sig() -> Result<()> {
+    // Same transaction as `process_type_4_txn()`, with the exception that the `s`
+    // component in the signature is flipped (i.e. `s' = N - s`, where `N` is the
+    // order of the SECP256k1 prime subgroup).
+    // It should fail according to EIP-2 (`s` must be no greater than `N/2`).
+    let txn = hex!("04f8cb820539018201f48203e8830186a094000000000000000000000000000000000000000201824242c0f85ef85c8205399400000000000000000000000000000000000000aa8080a09377c312145a5afb911bf9e8c067bcf6094c533603687850df502b61290bbf5ea0321f659a4b2d0ea3c014fbea084c0911b8d5f8adc79507b7bfb058c131b735be01a018a060a121b19972b73b78bf2504935e0446c2557e4aba8e4d3c358a243edd37a092302288165bb97a22d53e45a4e76c7dc57ed67a501e50cfd3864b84de9b53ca").to_vec();
+
+    let mut interpreter = Interpreter::<F>::new(0, vec![], None);
+    prepare_interpreter_for_txn_parsing(
+        &mut interpreter,
+        KERNEL.global_labels["process_type_4_txn"],
+        KERNEL.global_labels["process_normalized_txn"],
+        txn,
+    )?;
+
+    let result = interpreter.run();
+    assert!(result.is_err());
+
+    Ok(())
+}
@@ -229,7 +229,7 @@ fn test_revert() -> Result<()> {
             if i < NUMBER_KERNEL_FILES {
                 KERNEL_FILES[i]
             } else {
-                include_str!("checkpoint_label.asm")
+                ("checkpoint_label.asm", include_str!("checkpoint_label.asm"))
             }
         }))
     });
@@ -0,0 +1,421 @@
+//! A tracer recording the execution of user code in the format of EIP-3155,
+//! to compare it against the traces of other EVM implementations, such as the
+//! struct logs returned by geth's `debug_traceTransaction`.
+//!
+//! Only user-mode instructions are traced. The costs of an instruction are
+//! known once the next instruction of its context is reached, so that the
+//! costs charged by the kernel on its behalf, e.g. in a system call, are
+//! accounted for. The instructions ending their context, as well as calls and
+//! creations, whose cost includes the gas consumed by the new context rather
+//! than the gas forwarded to it, are not compared on their cost.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use anyhow::{anyhow, Result};
+use ethereum_types::{H256, U256};
+use plonky2::field::types::Field;
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::interpreter::get_mnemonic;
+use crate::generation::state::GenerationState;
+use crate::memory::segments::Segment;
+use crate::witness::memory::MemoryAddress;
+use crate::witness::util::stack_peek;
+
+/// The opcodes whose cost differs from the one reported by other EVM
+/// implementations, or is unknown.
+const UNCOMPARED_COST_OPCODES: [u8; 11] = [
+    0x00, // STOP
+    0xf0, // CREATE
+    0xf1, // CALL
+    0xf2, // CALLCODE
+    0xf3, // RETURN
+    0xf4, // DELEGATECALL
+    0xf5, // CREATE2
+    0xfa, // STATICCALL
+    0xfd, // REVERT
+    0xfe, // INVALID
+    0xff, // SELFDESTRUCT
+];
+
+/// A user-mode instruction, along with the state it was executed in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pc: usize,
+    pub op: u8,
+    /// The gas remaining in the context before the instruction.
+    pub gas: u64,
+    /// The gas charged for the instruction, if it did not end its context.
+    pub gas_cost: u64,
+    /// The stack before the instruction, from the bottom to the top.
+    pub stack: Vec<U256>,
+    /// The depth of the context, starting from 1.
+    pub depth: usize,
+    /// The refund counter of the transaction.
+    pub refund: u64,
+    /// The size of the memory of the context, in bytes.
+    pub mem_size: usize,
+}
+
+/// A step as serialized in EIP-3155 traces.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Eip3155Step {
+    pc: usize,
+    op: u8,
+    gas: String,
+    gas_cost: String,
+    mem_size: usize,
+    stack: Vec<String>,
+    depth: usize,
+    refund: u64,
+    op_name: &'static str,
+}
+
+impl From<&TraceStep> for Eip3155Step {
+    fn from(step: &TraceStep) -> Self {
+        Self {
+            pc: step.pc,
+            op: step.op,
+            gas: format!("{:#x}", step.gas),
+            gas_cost: format!("{:#x}", step.gas_cost),
+            mem_size: step.mem_size,
+            stack: step.stack.iter().map(|v| format!("{v:#x}")).collect(),
+            depth: step.depth,
+            refund: step.refund,
+            op_name: get_mnemonic(step.op),
+        }
+    }
+}
+
+/// The trace of the user code executed by a transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionTrace {
+    /// The index of the transaction among the ones of the payload.
+    pub txn_index: usize,
+    pub txn_hash: H256,
+    pub steps: Vec<TraceStep>,
+}
+
+impl TransactionTrace {
+    /// Writes the trace as EIP-3155 JSON lines, one per step.
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for step in &self.steps {
+            serde_json::to_writer(&mut writer, &Eip3155Step::from(step))?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Compares the trace against geth's struct logs of the same transaction,
+    /// and returns the first difference, if any.
+    pub fn diff(&self, struct_logs: &[GethStructLog]) -> Option<TraceDivergence> {
+        for (idx, (ours, theirs)) in self.steps.iter().zip(struct_logs).enumerate() {
+            let divergence = |field, ours: String, theirs: String| TraceDivergence::Step {
+                idx,
+                field,
+                ours,
+                theirs,
+            };
+
+            let mnemonic = get_mnemonic(ours.op);
+            if ours.pc != theirs.pc {
+                return Some(divergence("pc", ours.pc.to_string(), theirs.pc.to_string()));
+            }
+            if mnemonic != theirs.op {
+                return Some(divergence("op", mnemonic.into(), theirs.op.clone()));
+            }
+            if ours.depth != theirs.depth {
+                return Some(divergence(
+                    "depth",
+                    ours.depth.to_string(),
+                    theirs.depth.to_string(),
+                ));
+            }
+            if ours.gas != theirs.gas {
+                return Some(divergence(
+                    "gas",
+                    ours.gas.to_string(),
+                    theirs.gas.to_string(),
+                ));
+            }
+            if !UNCOMPARED_COST_OPCODES.contains(&ours.op) && ours.gas_cost != theirs.gas_cost {
+                return Some(divergence(
+                    "gasCost",
+                    ours.gas_cost.to_string(),
+                    theirs.gas_cost.to_string(),
+                ));
+            }
+            if ours.refund != theirs.refund {
+                return Some(divergence(
+                    "refund",
+                    ours.refund.to_string(),
+                    theirs.refund.to_string(),
+                ));
+            }
+            if let Some(stack) = &theirs.stack {
+                let values = stack
+                    .iter()
+                    .map(|v| U256::from_str_radix(v.trim_start_matches("0x"), 16))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok();
+                if values.as_ref() != Some(&ours.stack) {
+                    let ours = ours
+                        .stack
+                        .iter()
+                        .map(|v| format!("{v:#x}"))
+                        .collect::<Vec<_>>();
+                    return Some(divergence(
+                        "stack",
+                        format!("[{}]", ours.join(", ")),
+                        format!("[{}]", stack.join(", ")),
+                    ));
+                }
+            }
+        }
+
+        (self.steps.len() != struct_logs.len()).then_some(TraceDivergence::Length {
+            ours: self.steps.len(),
+            theirs: struct_logs.len(),
+        })
+    }
+}
+
+/// A step of the struct logs returned by geth's `debug_traceTransaction`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GethStructLog {
+    pub pc: usize,
+    pub op: String,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: usize,
+    /// The stack from the bottom to the top, unless disabled in the tracer.
+    #[serde(default)]
+    pub stack: Option<Vec<String>>,
+    #[serde(default)]
+    pub refund: u64,
+}
+
+/// Parses geth's struct logs, given either the JSON-RPC response of
+/// `debug_traceTransaction`, its result, or the struct logs themselves.
+pub fn parse_geth_struct_logs(json: &[u8]) -> Result<Vec<GethStructLog>> {
+    let mut value: serde_json::Value = serde_json::from_slice(json)?;
+    for key in ["result", "structLogs"] {
+        if let Some(inner) = value.get_mut(key) {
+            value = inner.take();
+        }
+    }
+    if !value.is_array() {
+        return Err(anyhow!("Expected an array of struct logs"));
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// The first difference between two traces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceDivergence {
+    /// The traces differ on a field of the given step.
+    Step {
+        idx: usize,
+        field: &'static str,
+        ours: String,
+        theirs: String,
+    },
+    /// One of the traces is a prefix of the other.
+    Length { ours: usize, theirs: usize },
+}
+
+impl fmt::Display for TraceDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Step {
+                idx,
+                field,
+                ours,
+                theirs,
+            } => write!(f, "step {idx}: {field} is {ours}, expected {theirs}"),
+            Self::Length { ours, theirs } => {
+                write!(f, "trace has {ours} steps, expected {theirs}")
+            }
+        }
+    }
+}
+
+/// Records the user-mode instructions of an execution, to build the
+/// [`TransactionTrace`] of each transaction.
+#[derive(Debug, Default)]
+pub(crate) struct Tracer {
+    traces: Vec<TransactionTrace>,
+    /// The depth and index of the last step of each enclosing context, whose
+    /// cost is known once the context resumes.
+    open_steps: Vec<(usize, usize)>,
+}
+
+impl Tracer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn into_traces(self) -> Vec<TransactionTrace> {
+        self.traces
+    }
+
+    /// Records the user-mode instruction about to be executed in `state`.
+    pub(crate) fn step<F: Field>(&mut self, state: &GenerationState<F>, opcode: u8) {
+        let registers = &state.registers;
+        let context_metadata = |field: ContextMetadata| {
+            state.memory.get_with_init(MemoryAddress::new(
+                registers.context,
+                Segment::ContextMetadata,
+                field.unscale(),
+            ))
+        };
+
+        let gas_limit = context_metadata(ContextMetadata::GasLimit).low_u64();
+        let step = TraceStep {
+            pc: registers.program_counter,
+            op: opcode,
+            gas: gas_limit.saturating_sub(registers.gas_used),
+            gas_cost: 0,
+            stack: (0..registers.stack_len)
+                .rev()
+                .map(|i| stack_peek(state, i).expect("The stack is not empty"))
+                .collect(),
+            depth: state
+                .memory
+                .read_global_metadata(GlobalMetadata::CallStackDepth)
+                .as_usize()
+                + 1,
+            refund: state
+                .memory
+                .read_global_metadata(GlobalMetadata::RefundCounter)
+                .low_u64(),
+            mem_size: context_metadata(ContextMetadata::MemWords).as_usize() * 32,
+        };
+
+        // The transaction being executed was the last one fetched by the kernel.
+        let txn_index = state.next_txn_index.saturating_sub(1);
+        let txn_hash = state
+            .inputs
+            .txn_hashes
+            .get(txn_index)
+            .copied()
+            .unwrap_or_default();
+        self.push(txn_index, txn_hash, step);
+    }
+
+    fn push(&mut self, txn_index: usize, txn_hash: H256, step: TraceStep) {
+        if self.traces.last().map(|trace| trace.txn_index) != Some(txn_index) {
+            self.traces.push(TransactionTrace {
+                txn_index,
+                txn_hash,
+                steps: vec![],
+            });
+            self.open_steps.clear();
+        }
+        let steps = &mut self.traces.last_mut().expect("A trace was pushed").steps;
+
+        // The contexts deeper than the new step have ended, and the last step of
+        // its own context, if any, is complete.
+        while let Some(&(depth, idx)) = self.open_steps.last() {
+            if depth < step.depth {
+                break;
+            }
+            self.open_steps.pop();
+            if depth == step.depth {
+                steps[idx].gas_cost = steps[idx].gas.saturating_sub(step.gas);
+                break;
+            }
+        }
+
+        self.open_steps.push((step.depth, steps.len()));
+        steps.push(step);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(pc: usize, op: u8, gas: u64, depth: usize) -> TraceStep {
+        TraceStep {
+            pc,
+            op,
+            gas,
+            depth,
+            ..TraceStep::default()
+        }
+    }
+
+    #[test]
+    fn costs_across_calls() {
+        let mut tracer = Tracer::new();
+        for step in [
+            step(0, 0x60, 100, 1), // PUSH1
+            step(2, 0xf1, 97, 1),  // CALL
+            step(0, 0x60, 40, 2),  // PUSH1
+            step(2, 0x00, 37, 2),  // STOP
+            step(3, 0x00, 60, 1),  // STOP
+        ] {
+            tracer.push(0, H256::zero(), step);
+        }
+        tracer.push(1, H256::zero(), step(0, 0x00, 50, 1));
+
+        let traces = tracer.into_traces();
+        assert_eq!(traces.len(), 2);
+        let costs: Vec<_> = traces[0].steps.iter().map(|s| s.gas_cost).collect();
+        assert_eq!(costs, vec![3, 37, 3, 0, 0]);
+    }
+
+    #[test]
+    fn diff_against_struct_logs() {
+        let trace = TransactionTrace {
+            steps: vec![
+                TraceStep {
+                    gas_cost: 3,
+                    ..step(0, 0x60, 100, 1)
+                },
+                TraceStep {
+                    stack: vec![1.into()],
+                    ..step(2, 0x00, 97, 1)
+                },
+            ],
+            ..TransactionTrace::default()
+        };
+
+        let mut jsonl = vec![];
+        trace.write_jsonl(&mut jsonl).unwrap();
+        assert_eq!(
+            String::from_utf8(jsonl).unwrap().lines().nth(1),
+            Some(
+                r#"{"pc":2,"op":0,"gas":"0x61","gasCost":"0x0","memSize":0,"stack":["0x1"],"depth":1,"refund":0,"opName":"STOP"}"#
+            )
+        );
+
+        let struct_logs = parse_geth_struct_logs(
+            br#"{"jsonrpc":"2.0","id":1,"result":{"gas":3,"failed":false,"structLogs":[
+                {"pc":0,"op":"PUSH1","gas":100,"gasCost":3,"depth":1,"stack":[]},
+                {"pc":2,"op":"STOP","gas":97,"gasCost":0,"depth":1,"stack":["0x2"]}
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            trace.diff(&struct_logs),
+            Some(TraceDivergence::Step {
+                idx: 1,
+                field: "stack",
+                ours: "[0x1]".into(),
+                theirs: "[0x2]".into(),
+            })
+        );
+        assert_eq!(
+            trace.diff(&struct_logs[..1]),
+            Some(TraceDivergence::Length { ours: 2, theirs: 1 })
+        );
+    }
+}
@@ -476,6 +476,49 @@ impl<T: FieldExt> Div for Fp2<T> {
     }
 }
 
+impl Fp2<BLS381> {
+    fn exp(self, exp: U512) -> Self {
+        let mut current = self;
+        let mut product = Fp2::<BLS381>::UNIT;
+
+        for j in 0..exp.bits() {
+            if exp.bit(j) {
+                product = product * current;
+            }
+            current = current * current;
+        }
+        product
+    }
+
+    /// Computes a square root with Algorithm 9 of
+    /// <https://eprint.iacr.org/2012/685.pdf>, which applies as p = 3 (mod 4).
+    /// As for the base field, we check that we got the correct result at the
+    /// end, as the element may not be a quadratic residue.
+    pub fn sqrt(&self) -> Result<Self> {
+        let a1 = self.exp((BLS_BASE - 3) / 4);
+        let alpha = a1 * a1 * *self;
+        let x0 = a1 * *self;
+
+        let root = if alpha == -Fp2::<BLS381>::UNIT {
+            // Multiply x0 by i.
+            Fp2 {
+                re: -x0.im,
+                im: x0.re,
+            }
+        } else {
+            (alpha + Fp2::<BLS381>::UNIT).exp((BLS_BASE - 1) / 2) * x0
+        };
+
+        if root * root != *self {
+            return Err(anyhow!(
+                "The element does not have a square root in this field."
+            ));
+        }
+
+        Ok(root)
+    }
+}
+
 /// This trait defines the method which multiplies
 /// by the Fp2 element t^3 whose cube root we will
 /// adjoin in the subsequent cubic extension.
@@ -25,7 +25,7 @@ use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
 use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
 use plonky2::recursion::dummy_circuit::cyclic_base_proof;
 use plonky2::util::serialization::{
-    Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
+    Buffer, GateSerializer, IoError, IoResult, Read, WitnessGeneratorSerializer, Write,
 };
 use plonky2::util::timing::TimingTree;
 use plonky2_util::log2_ceil;
@@ -38,7 +38,7 @@ use starky::stark::Stark;
 use crate::all_stark::{all_cross_table_lookups, AllStark, Table, NUM_TABLES};
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::generation::segments::{GenerationSegmentData, SegmentDataIterator, SegmentError};
-use crate::generation::{GenerationInputs, TrimmedGenerationInputs};
+use crate::generation::{ChainProfile, GenerationInputs, TrimmedGenerationInputs};
 use crate::get_challenges::observe_public_values_target;
 use crate::proof::{
     AllProof, BlockHashesTarget, BlockMetadataTarget, BurnAddrTarget, ExtraBlockData,
@@ -53,9 +53,7 @@ use crate::recursive_verifier::{
     recursive_stark_circuit, set_final_public_value_targets, set_public_value_targets,
     PlonkWrapperCircuit, PublicInputs, StarkWrapperCircuit,
 };
-use crate::util::h256_limbs;
-#[cfg(feature = "cdk_erigon")]
-use crate::util::u256_limbs;
+use crate::util::{h256_limbs, u256_limbs};
 use crate::verifier::initial_memory_merkle_cap;
 
 /// The recursion threshold. We end a chain of recursive proofs once we reach
@@ -86,6 +84,9 @@ where
     C: GenericConfig<D, F = F>,
     C::Hasher: AlgebraicHasher<F>,
 {
+    /// The chain profile these circuits were built for. It fixes the layout of
+    /// the public values of all the proofs they generate.
+    pub chain_profile: ChainProfile,
     /// The EVM root circuit, which aggregates the (shrunk) per-table recursive
     /// proofs.
     pub root: RootCircuitData<F, C, D>,
@@ -272,9 +273,12 @@ impl<const D: usize> AggregationChildWithDummyTarget<D> {
     fn public_values<F: RichField + Extendable<D>>(
         &self,
         builder: &mut CircuitBuilder<F, D>,
+        chain_profile: ChainProfile,
     ) -> PublicValuesTarget {
-        let agg_pv = PublicValuesTarget::from_public_inputs(&self.agg_proof.public_inputs);
-        let segment_pv = PublicValuesTarget::from_public_inputs(&self.real_proof.public_inputs);
+        let agg_pv =
+            PublicValuesTarget::from_public_inputs(&self.agg_proof.public_inputs, chain_profile);
+        let segment_pv =
+            PublicValuesTarget::from_public_inputs(&self.real_proof.public_inputs, chain_profile);
 
         PublicValuesTarget::select(builder, self.is_agg, agg_pv, segment_pv)
     }
@@ -364,9 +368,12 @@ impl<const D: usize> AggregationChildTarget<D> {
     fn public_values<F: RichField + Extendable<D>>(
         &self,
         builder: &mut CircuitBuilder<F, D>,
+        chain_profile: ChainProfile,
     ) -> PublicValuesTarget {
-        let agg_pv = PublicValuesTarget::from_public_inputs(&self.agg_proof.public_inputs);
-        let base_pv = PublicValuesTarget::from_public_inputs(&self.base_proof.public_inputs);
+        let agg_pv =
+            PublicValuesTarget::from_public_inputs(&self.agg_proof.public_inputs, chain_profile);
+        let base_pv =
+            PublicValuesTarget::from_public_inputs(&self.base_proof.public_inputs, chain_profile);
         PublicValuesTarget::select(builder, self.is_agg, agg_pv, base_pv)
     }
 
@@ -567,6 +574,11 @@ where
         // TODO: would be better to initialize it dynamically based on the supported max
         // degree.
         let mut buffer = Vec::with_capacity(1 << 34);
+        let profile_index = ChainProfile::ALL
+            .iter()
+            .position(|profile| *profile == self.chain_profile)
+            .expect("All chain profiles are listed");
+        buffer.write_usize(profile_index)?;
         self.root
             .to_buffer(&mut buffer, gate_serializer, generator_serializer)?;
         self.segment_aggregation
@@ -607,6 +619,7 @@ where
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Self> {
         let mut buffer = Buffer::new(bytes);
+        let chain_profile = *ChainProfile::ALL.get(buffer.read_usize()?).ok_or(IoError)?;
         let root =
             RootCircuitData::from_buffer(&mut buffer, gate_serializer, generator_serializer)?;
         let segment_aggregation = SegmentAggregationCircuitData::from_buffer(
@@ -663,6 +676,7 @@ where
         };
 
         Ok(Self {
+            chain_profile,
             root,
             segment_aggregation,
             txn_aggregation,
@@ -691,10 +705,13 @@ where
     /// prover to cover all possible scenarios.
     /// - `stark_config`: the configuration to be used for the STARK prover. It
     ///   will usually be a fast one yielding large proofs.
+    /// - `chain_profile`: the chain these circuits will prove blocks of. It
+    ///   determines the layout of the public values.
     pub fn new(
         all_stark: &AllStark<F, D>,
         degree_bits_ranges: &[Range<usize>; NUM_TABLES],
         stark_config: &StarkConfig,
+        chain_profile: ChainProfile,
     ) -> Self {
         // Sanity check on the provided config
         assert_eq!(DEFAULT_CAP_LEN, 1 << stark_config.fri_config.cap_height);
@@ -774,14 +791,15 @@ where
             mem_before,
             mem_after,
         ];
-        let root = Self::create_segment_circuit(&by_table, stark_config);
-        let segment_aggregation = Self::create_segment_aggregation_circuit(&root);
+        let root = Self::create_segment_circuit(&by_table, stark_config, chain_profile);
+        let segment_aggregation = Self::create_segment_aggregation_circuit(&root, chain_profile);
         let txn_aggregation =
-            Self::create_txn_aggregation_circuit(&segment_aggregation, stark_config);
-        let block = Self::create_block_circuit(&txn_aggregation);
-        let block_wrapper = Self::create_block_wrapper_circuit(&block);
+            Self::create_txn_aggregation_circuit(&segment_aggregation, stark_config, chain_profile);
+        let block = Self::create_block_circuit(&txn_aggregation, chain_profile);
+        let block_wrapper = Self::create_block_wrapper_circuit(&block, chain_profile);
         let two_to_one_block = Self::create_two_to_one_block_circuit(&block_wrapper);
         Self {
+            chain_profile,
             root,
             segment_aggregation,
             txn_aggregation,
@@ -812,16 +830,23 @@ where
         self.block.circuit.verifier_data()
     }
 
+    /// Outputs the [`VerifierCircuitData`] needed to verify two-to-one block
+    /// aggregation proofs, as generated by [`Self::prove_two_to_one_block`].
+    pub fn two_to_one_block_verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.two_to_one_block.circuit.verifier_data()
+    }
+
     fn create_segment_circuit(
         by_table: &[RecursiveCircuitsForTable<F, C, D>; NUM_TABLES],
         stark_config: &StarkConfig,
+        chain_profile: ChainProfile,
     ) -> RootCircuitData<F, C, D> {
         let inner_common_data: [_; NUM_TABLES] =
             core::array::from_fn(|i| &by_table[i].final_circuits()[0].common);
 
         let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
 
-        let public_values = add_virtual_public_values_public_input(&mut builder);
+        let public_values = add_virtual_public_values_public_input(&mut builder, chain_profile);
 
         let recursive_proofs =
             core::array::from_fn(|i| builder.add_virtual_proof_with_pis(inner_common_data[i]));
@@ -886,6 +911,7 @@ where
                 get_memory_extra_looking_sum_circuit(
                     &mut builder,
                     &public_values,
+                    chain_profile,
                     ctl_challenges.challenges[c],
                 )
             })
@@ -960,9 +986,10 @@ where
 
     fn create_segment_aggregation_circuit(
         root: &RootCircuitData<F, C, D>,
+        chain_profile: ChainProfile,
     ) -> SegmentAggregationCircuitData<F, C, D> {
         let mut builder = CircuitBuilder::<F, D>::new(root.circuit.common.config.clone());
-        let public_values = add_virtual_public_values_public_input(&mut builder);
+        let public_values = add_virtual_public_values_public_input(&mut builder, chain_profile);
         let cyclic_vk = builder.add_verifier_data_public_inputs();
 
         // The right hand side child might be dummy.
@@ -973,8 +1000,8 @@ where
             lhs_segment.base_proof.clone(),
         );
 
-        let lhs_pv = lhs_segment.public_values(&mut builder);
-        let rhs_pv = rhs_segment.public_values(&mut builder);
+        let lhs_pv = lhs_segment.public_values(&mut builder, chain_profile);
+        let rhs_pv = rhs_segment.public_values(&mut builder, chain_profile);
 
         let is_dummy = rhs_segment.is_dummy;
         let one = builder.one();
@@ -1129,18 +1156,19 @@ where
     fn create_txn_aggregation_circuit(
         agg: &SegmentAggregationCircuitData<F, C, D>,
         stark_config: &StarkConfig,
+        chain_profile: ChainProfile,
     ) -> TxnAggregationCircuitData<F, C, D> {
         // Create a circuit for the aggregation of two transactions.
 
         let mut builder = CircuitBuilder::<F, D>::new(agg.circuit.common.config.clone());
-        let public_values = add_virtual_public_values_public_input(&mut builder);
+        let public_values = add_virtual_public_values_public_input(&mut builder, chain_profile);
         let cyclic_vk = builder.add_verifier_data_public_inputs();
 
         let lhs_txn_proof = Self::add_txn_agg_child(&mut builder, agg);
         let rhs_txn_proof = Self::add_txn_agg_child(&mut builder, agg);
 
-        let lhs_pv = lhs_txn_proof.public_values(&mut builder);
-        let rhs_pv = rhs_txn_proof.public_values(&mut builder);
+        let lhs_pv = lhs_txn_proof.public_values(&mut builder, chain_profile);
+        let rhs_pv = rhs_txn_proof.public_values(&mut builder, chain_profile);
 
         // Connect all block hash values
         BlockHashesTarget::connect(
@@ -1315,7 +1343,10 @@ where
         builder.connect(x.registers_before.program_counter, main_label);
     }
 
-    fn create_block_circuit(agg: &TxnAggregationCircuitData<F, C, D>) -> BlockCircuitData<F, C, D> {
+    fn create_block_circuit(
+        agg: &TxnAggregationCircuitData<F, C, D>,
+        chain_profile: ChainProfile,
+    ) -> BlockCircuitData<F, C, D> {
         // Here, we have two block proofs and we aggregate them together.
         // The block circuit is similar to the agg circuit; both verify two inner
         // proofs.
@@ -1328,16 +1359,25 @@ where
         };
 
         let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
-        let public_values = add_virtual_public_values_public_input(&mut builder);
+        let public_values = add_virtual_public_values_public_input(&mut builder, chain_profile);
         let has_parent_block = builder.add_virtual_bool_target_safe();
         let parent_block_proof = builder.add_virtual_proof_with_pis(&expected_common_data);
         let agg_root_proof = builder.add_virtual_proof_with_pis(&agg.circuit.common);
 
         // Connect block hashes
-        Self::connect_block_hashes(&mut builder, &parent_block_proof, &agg_root_proof);
+        Self::connect_block_hashes(
+            &mut builder,
+            &parent_block_proof,
+            &agg_root_proof,
+            chain_profile,
+        );
 
-        let parent_pv = PublicValuesTarget::from_public_inputs(&parent_block_proof.public_inputs);
-        let agg_pv = PublicValuesTarget::from_public_inputs(&agg_root_proof.public_inputs);
+        let parent_pv = PublicValuesTarget::from_public_inputs(
+            &parent_block_proof.public_inputs,
+            chain_profile,
+        );
+        let agg_pv =
+            PublicValuesTarget::from_public_inputs(&agg_root_proof.public_inputs, chain_profile);
 
         // Connect block `trie_roots_before` with parent_pv `trie_roots_before`.
         TrieRootsTarget::connect(
@@ -1527,11 +1567,15 @@ where
 
     fn create_block_wrapper_circuit(
         block: &BlockCircuitData<F, C, D>,
+        chain_profile: ChainProfile,
     ) -> BlockWrapperCircuitData<F, C, D> {
         let mut builder = CircuitBuilder::<F, D>::new(block.circuit.common.config.clone());
 
         let parent_block_proof = builder.add_virtual_proof_with_pis(&block.circuit.common);
-        let parent_pv = PublicValuesTarget::from_public_inputs(&parent_block_proof.public_inputs);
+        let parent_pv = PublicValuesTarget::from_public_inputs(
+            &parent_block_proof.public_inputs,
+            chain_profile,
+        );
 
         let final_pv = add_virtual_final_public_values_public_input(&mut builder);
 
@@ -1657,9 +1701,12 @@ where
         builder: &mut CircuitBuilder<F, D>,
         lhs: &ProofWithPublicInputsTarget<D>,
         rhs: &ProofWithPublicInputsTarget<D>,
+        chain_profile: ChainProfile,
     ) {
-        let lhs_public_values = PublicValuesTarget::from_public_inputs(&lhs.public_inputs);
-        let rhs_public_values = PublicValuesTarget::from_public_inputs(&rhs.public_inputs);
+        let lhs_public_values =
+            PublicValuesTarget::from_public_inputs(&lhs.public_inputs, chain_profile);
+        let rhs_public_values =
+            PublicValuesTarget::from_public_inputs(&rhs.public_inputs, chain_profile);
         for i in 0..255 {
             for j in 0..8 {
                 builder.connect(
@@ -1806,8 +1853,19 @@ where
         timing: &mut TimingTree,
         abort_signal: Option<Arc<AtomicBool>>,
     ) -> anyhow::Result<ProverOutputData<F, C, D>> {
-        if generation_inputs.burn_addr.is_some() && !cfg!(feature = "cdk_erigon") {
-            log::warn!("The burn address in the GenerationInputs will be ignored, as the `cdk_erigon` feature is not activated.")
+        if generation_inputs.chain_profile != self.chain_profile {
+            return Err(anyhow!(
+                "Cannot prove {} inputs with circuits built for the {} chain profile.",
+                generation_inputs.chain_profile,
+                self.chain_profile
+            ));
+        }
+        if generation_inputs.burn_addr.is_some() && !generation_inputs.chain_profile.has_burn_addr()
+        {
+            log::warn!(
+                "The burn address in the GenerationInputs will be ignored, as the {} chain profile does not use one.",
+                generation_inputs.chain_profile
+            )
         }
         let all_proof = prove::<F, C, D>(
             all_stark,
@@ -1939,6 +1997,7 @@ where
     ///     &all_stark,
     ///     &initial_ranges,
     ///     &config,
+    ///     ChainProfile::L1,
     /// );
     ///
     /// // Generate a proof from the provided inputs.
@@ -2328,20 +2387,12 @@ where
                 nonzero_pis.insert(key, value);
             }
 
-            let burn_addr_offset = match cfg!(feature = "cdk_erigon") {
-                true => BurnAddrTarget::get_size(),
-                false => 0,
-            };
+            let burn_addr_offset = BurnAddrTarget::get_size(self.chain_profile);
 
-            #[cfg(feature = "cdk_erigon")]
-            {
+            if let Some(burn_addr) = public_values.burn_addr {
                 let burn_addr_keys =
                     TrieRootsTarget::SIZE * 2..TrieRootsTarget::SIZE * 2 + burn_addr_offset;
-                for (key, &value) in burn_addr_keys.zip_eq(&u256_limbs(
-                    public_values
-                        .burn_addr
-                        .expect("We should have a burn addr when cdk_erigon is activated"),
-                )) {
+                for (key, &value) in burn_addr_keys.zip_eq(&u256_limbs(burn_addr)) {
                     nonzero_pis.insert(key, value);
                 }
             }
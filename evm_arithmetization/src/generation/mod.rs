@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
 use anyhow::anyhow;
 use ethereum_types::H160;
@@ -12,7 +13,7 @@ use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::timed;
 use plonky2::util::timing::TimingTree;
-use segments::GenerationSegmentData;
+use segments::{GenerationSegmentData, SegmentTraceStats};
 use serde::{Deserialize, Serialize};
 use starky::config::StarkConfig;
 use GlobalMetadata::{
@@ -24,15 +25,17 @@ use crate::all_stark::{AllStark, NUM_TABLES};
 use crate::cpu::columns::CpuColumnsView;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::interpreter::Interpreter;
 use crate::generation::state::{GenerationState, State};
+use crate::generation::state_diff::state_mismatch_report;
 use crate::generation::trie_extractor::{get_receipt_trie, get_state_trie, get_txn_trie};
 use crate::memory::segments::{Segment, PREINITIALIZED_SEGMENTS_INDICES};
 use crate::proof::{
     BlockHashes, BlockMetadata, ExtraBlockData, MemCap, PublicValues, RegistersData, TrieRoots,
 };
 use crate::util::{h2u, u256_to_usize};
+use crate::witness::errors::ExecutionErrorKind;
 use crate::witness::memory::{MemoryAddress, MemoryChannel, MemoryState};
-use crate::witness::state::RegistersState;
 
 pub(crate) mod linked_list;
 pub mod mpt;
@@ -40,6 +43,7 @@ pub(crate) mod prover_input;
 pub(crate) mod rlp;
 pub(crate) mod segments;
 pub(crate) mod state;
+pub(crate) mod state_diff;
 pub(crate) mod trie_extractor;
 
 use crate::witness::util::mem_write_log;
@@ -53,6 +57,78 @@ pub const NUM_EXTRA_CYCLES_BEFORE: usize = 64;
 /// Memory values used to initialize `MemBefore`.
 pub type MemBeforeValues = Vec<(MemoryAddress, U256)>;
 
+/// The flavour of chain being proven.
+///
+/// It affects how the base fee is handled: it is burnt on L1, while cdk-erigon
+/// credits it to a burn address which is then exposed in the public values.
+/// It also selects the set of active precompiles. Proofs of different profiles
+/// have different public value layouts, and hence need distinct recursive
+/// circuits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainProfile {
+    /// Ethereum L1. The base fee is burnt.
+    #[default]
+    L1,
+    /// Polygon's cdk-erigon. The base fee is sent to a burn address.
+    CdkErigon,
+}
+
+impl ChainProfile {
+    /// All the supported profiles.
+    pub const ALL: [Self; 2] = [Self::L1, Self::CdkErigon];
+
+    /// Returns `true` if the public values of this profile contain a burn
+    /// address.
+    pub const fn has_burn_addr(&self) -> bool {
+        matches!(self, Self::CdkErigon)
+    }
+
+    /// Returns `true` if the RIP-7212 `P256VERIFY` precompile is active on
+    /// chains of this profile.
+    pub const fn has_p256_verify(&self) -> bool {
+        matches!(self, Self::CdkErigon)
+    }
+
+    /// Returns `true` if the EIP-2537 BLS12-381 precompiles are active on
+    /// chains of this profile.
+    ///
+    /// They ship with Prague, which no profile targets yet: until then, their
+    /// addresses are those of plain accounts.
+    pub const fn has_bls12_381(&self) -> bool {
+        false
+    }
+
+    /// Returns the value stored at `GlobalMetadata::BurnAddr`, `U256::MAX`
+    /// signaling that the base fee should be directly burnt.
+    pub(crate) fn burn_addr_metadata(&self, burn_addr: Option<H160>) -> U256 {
+        match (self, burn_addr) {
+            (Self::CdkErigon, Some(addr)) => U256::from_big_endian(&addr.0),
+            _ => U256::MAX,
+        }
+    }
+}
+
+impl std::fmt::Display for ChainProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::L1 => "l1",
+            Self::CdkErigon => "cdk_erigon",
+        })
+    }
+}
+
+impl std::str::FromStr for ChainProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.to_string() == s)
+            .ok_or_else(|| anyhow!("unknown chain profile: {s}"))
+    }
+}
+
 /// Inputs needed for trace generation.
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct GenerationInputs {
@@ -72,8 +148,12 @@ pub struct GenerationInputs {
     /// Target address for the base fee to be 'burnt', if there is one. If
     /// `None`, then the base fee is directly burnt.
     ///
-    /// Note: this is only used  when feature `cdk_erigon` is activated.
+    /// Note: this is only used with [`ChainProfile::CdkErigon`].
     pub burn_addr: Option<H160>,
+    /// The chain being proven, which selects the kernel behaviour and the
+    /// layout of the public values.
+    #[serde(default)]
+    pub chain_profile: ChainProfile,
     /// Withdrawal pairs `(addr, amount)`. At the end of the txs, `amount` is
     /// added to `addr`'s balance. See EIP-4895.
     pub withdrawals: Vec<(Address, U256)>,
@@ -82,6 +162,12 @@ pub struct GenerationInputs {
     pub tries: TrieInputs,
     /// Expected trie roots after the transactions are executed.
     pub trie_roots_after: TrieRoots,
+    /// The state and storage tries expected after the transactions are
+    /// executed, covering the same accounts and slots as `tries`. They are not
+    /// proven, and only serve to report which accounts diverge should the final
+    /// state root not match `trie_roots_after`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_tries_after: Option<TrimmedTrieInputs>,
 
     /// State trie root of the checkpoint block.
     /// This could always be the genesis block of the chain, but it allows a
@@ -124,6 +210,10 @@ pub struct TrimmedGenerationInputs {
     pub trie_roots_before: TrieRoots,
     /// Expected trie roots after these transactions are executed.
     pub trie_roots_after: TrieRoots,
+    /// The state and storage tries expected after these transactions are
+    /// executed, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_tries_after: Option<TrimmedTrieInputs>,
 
     /// State trie root of the checkpoint block.
     /// This could always be the genesis block of the chain, but it allows a
@@ -138,10 +228,14 @@ pub struct TrimmedGenerationInputs {
     /// Information contained in the block header.
     pub block_metadata: BlockMetadata,
 
-    /// Address where the burnt fees are stored. Only used if the `cfg_erigon`
-    /// feature is activated.
+    /// Address where the burnt fees are stored. Only used with
+    /// [`ChainProfile::CdkErigon`].
     pub burn_addr: Option<H160>,
 
+    /// The chain being proven.
+    #[serde(default)]
+    pub chain_profile: ChainProfile,
+
     /// The hash of the current block, and a list of the 256 previous block
     /// hashes.
     pub block_hashes: BlockHashes,
@@ -213,9 +307,11 @@ impl GenerationInputs {
                 receipts_root: self.tries.receipts_trie.hash(),
             },
             trie_roots_after: self.trie_roots_after.clone(),
+            expected_tries_after: self.expected_tries_after.clone(),
             checkpoint_state_trie_root: self.checkpoint_state_trie_root,
             contract_code: self.contract_code.clone(),
             burn_addr: self.burn_addr,
+            chain_profile: self.chain_profile,
             block_metadata: self.block_metadata.clone(),
             block_hashes: self.block_hashes.clone(),
         }
@@ -230,10 +326,7 @@ fn apply_metadata_and_tries_memops<F: RichField + Extendable<D>, const D: usize>
 ) {
     let metadata = &inputs.block_metadata;
     let trie_roots_after = &inputs.trie_roots_after;
-    #[cfg(feature = "cdk_erigon")]
-    let burn_addr = inputs
-        .burn_addr
-        .map_or_else(U256::max_value, |addr| U256::from_big_endian(&addr.0));
+    let burn_addr = inputs.chain_profile.burn_addr_metadata(inputs.burn_addr);
     let fields = [
         (
             GlobalMetadata::BlockBeneficiary,
@@ -299,8 +392,15 @@ fn apply_metadata_and_tries_memops<F: RichField + Extendable<D>, const D: usize>
         ),
         (GlobalMetadata::KernelHash, h2u(KERNEL.code_hash)),
         (GlobalMetadata::KernelLen, KERNEL.code.len().into()),
-        #[cfg(feature = "cdk_erigon")]
         (GlobalMetadata::BurnAddr, burn_addr),
+        (
+            GlobalMetadata::P256VerifyEnabled,
+            U256::from(inputs.chain_profile.has_p256_verify() as u8),
+        ),
+        (
+            GlobalMetadata::Bls12381Enabled,
+            U256::from(inputs.chain_profile.has_bls12_381() as u8),
+        ),
     ];
 
     let channel = MemoryChannel::GeneralPurpose(0);
@@ -445,6 +545,7 @@ pub fn generate_traces<F: RichField + Extendable<D>, const D: usize>(
     segment_data: &mut GenerationSegmentData,
     timing: &mut TimingTree,
 ) -> anyhow::Result<TablesWithPVsAndFinalMem<F>> {
+    let start = Instant::now();
     let mut state = GenerationState::<F>::new_with_segment_data(inputs, segment_data)
         .map_err(|err| anyhow!("Failed to parse all the initial prover inputs: {:?}", err))?;
 
@@ -475,9 +576,12 @@ pub fn generate_traces<F: RichField + Extendable<D>, const D: usize>(
         "simulate CPU",
         simulate_cpu(&mut state, *max_cpu_len_log)
     );
-    if cpu_res.is_err() {
-        output_debug_tries(&state)?;
-        cpu_res?;
+    let cpu_cycles = match cpu_res {
+        Ok(cpu_cycles) => cpu_cycles,
+        Err(err) => {
+            output_debug_tries(&state)?;
+            return Err(with_state_mismatch_report(&state, err));
+        }
     };
 
     let trace_lengths = state.traces.get_lengths();
@@ -505,16 +609,10 @@ pub fn generate_traces<F: RichField + Extendable<D>, const D: usize>(
         gas_used_after,
     };
 
-    let burn_addr = match cfg!(feature = "cdk_erigon") {
-        true => {
-            if let Some(burn_addr) = inputs.burn_addr {
-                Some(U256::from_big_endian(&burn_addr.0))
-            } else {
-                Some(U256::MAX)
-            }
-        }
-        false => None,
-    };
+    let burn_addr = inputs
+        .chain_profile
+        .has_burn_addr()
+        .then(|| inputs.chain_profile.burn_addr_metadata(inputs.burn_addr));
 
     // `mem_before` and `mem_after` are initialized with an empty cap.
     // They will be set to the caps of `MemBefore` and `MemAfter`
@@ -532,7 +630,7 @@ pub fn generate_traces<F: RichField + Extendable<D>, const D: usize>(
         mem_after: MemCap::default(),
     };
 
-    let tables = timed!(
+    let (tables, unpadded_trace_lengths) = timed!(
         timing,
         "convert trace data to tables",
         state.traces.into_tables(
@@ -544,14 +642,27 @@ pub fn generate_traces<F: RichField + Extendable<D>, const D: usize>(
             timing
         )
     );
+
+    segment_data.trace_stats = Some(SegmentTraceStats {
+        generation_time: start.elapsed(),
+        cpu_cycles,
+        trace_lengths: unpadded_trace_lengths,
+        degree_bits: tables
+            .each_ref()
+            .map(|trace| trace.first().map_or(0, |poly| poly.len().ilog2() as usize)),
+    });
+
     Ok((tables, public_values))
 }
 
+/// Runs the CPU, then pads its trace to a power of two. Returns the number of
+/// cycles executed, before padding.
 fn simulate_cpu<F: Field>(
     state: &mut GenerationState<F>,
     max_cpu_len_log: Option<usize>,
-) -> anyhow::Result<(RegistersState, Option<MemoryState>)> {
-    let (final_registers, mem_after) = state.run_cpu(max_cpu_len_log)?;
+) -> anyhow::Result<usize> {
+    state.run_cpu(max_cpu_len_log)?;
+    let cpu_cycles = state.traces.clock();
 
     let pc = state.registers.program_counter;
     // Setting the values of padding rows.
@@ -574,7 +685,28 @@ fn simulate_cpu<F: Field>(
 
     log::info!("CPU trace padded to {} cycles", state.traces.clock());
 
-    Ok((final_registers, mem_after))
+    Ok(cpu_cycles)
+}
+
+/// Simulates the zkEVM CPU execution of `inputs`, without generating any
+/// trace, and returns the number of CPU cycles taken by each of its
+/// transactions.
+///
+/// The cycles spent before the first transaction, e.g. hashing the initial
+/// tries, and after the last one, e.g. processing the withdrawals, are not
+/// attributed to any transaction.
+pub fn txn_cpu_cycles<F: RichField>(inputs: &GenerationInputs) -> anyhow::Result<Vec<usize>> {
+    let mut interpreter: Interpreter<F> =
+        Interpreter::new_with_generation_inputs(KERNEL.global_labels["init"], vec![], inputs, None);
+    interpreter.txn_loop_clocks = Some(vec![]);
+    interpreter
+        .run()
+        .map_err(|err| with_state_mismatch_report(interpreter.get_generation_state(), err))?;
+
+    // The loop is entered once more after the last transaction, to process the
+    // withdrawals.
+    let clocks = interpreter.txn_loop_clocks.unwrap_or_default();
+    Ok(clocks.windows(2).map(|w| w[1] - w[0]).collect())
 }
 
 /// Outputs the tries that have been obtained post transaction execution, as
@@ -633,3 +765,28 @@ pub(crate) fn output_debug_tries<F: RichField>(state: &GenerationState<F>) -> an
 
     Ok(())
 }
+
+/// If the execution failed because of a mismatch of the final state root,
+/// wraps `err` in a `StateRootMismatch` error, along with a report of the
+/// accounts diverging from the expected ones.
+pub(crate) fn with_state_mismatch_report<F: RichField>(
+    state: &GenerationState<F>,
+    err: anyhow::Error,
+) -> anyhow::Error {
+    if state.registers.program_counter != KERNEL.global_labels["final_state_root_mismatch"] {
+        return err;
+    }
+    let err = err.context(state.execution_error(ExecutionErrorKind::StateRootMismatch));
+
+    match &state.inputs.expected_tries_after {
+        Some(expected) => match state_mismatch_report(state, expected) {
+            Ok(report) => err.context(report.to_string()),
+            Err(e) => err.context(format!(
+                "The final state root doesn't match the expected one, and the computed tries could not be extracted: {e:?}"
+            )),
+        },
+        None => err.context(
+            "The final state root doesn't match the expected one. Set `expected_tries_after` in the inputs to report the diverging accounts.",
+        ),
+    }
+}
@@ -20,15 +20,21 @@ use crate::cpu::kernel::constants::cancun_constants::{
 use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
 use crate::cpu::kernel::interpreter::simulate_cpu_and_get_user_jumps;
 use crate::curve_pairings::{bls381, CurveAff, CyclicGroup};
-use crate::extension_tower::{FieldExt, Fp12, Fp2, BLS381, BLS_BASE, BLS_SCALAR, BN254, BN_BASE};
+use crate::extension_tower::{
+    FieldExt, Fp12, Fp2, Fp6, BLS381, BLS_BASE, BLS_SCALAR, BN254, BN_BASE,
+};
 use crate::generation::prover_input::EvmField::{
     Bls381Base, Bls381Scalar, Bn254Base, Bn254Scalar, Secp256k1Base, Secp256k1Scalar,
+    Secp256r1Base, Secp256r1Scalar,
 };
 use crate::generation::prover_input::FieldOp::{Inverse, Sqrt};
 use crate::generation::state::GenerationState;
 use crate::memory::segments::Segment;
 use crate::memory::segments::Segment::BnPairing;
-use crate::util::{biguint_to_mem_vec, mem_vec_to_biguint, sha2, u256_to_u8, u256_to_usize};
+use crate::util::{
+    biguint_to_mem_vec, biguint_to_u256, mem_vec_to_biguint, sha2, u256_to_biguint, u256_to_u8,
+    u256_to_usize,
+};
 use crate::witness::errors::ProverInputError::*;
 use crate::witness::errors::{ProgramError, ProverInputError};
 use crate::witness::memory::MemoryAddress;
@@ -140,24 +146,40 @@ impl<F: Field> GenerationState<F> {
     }
 
     /// Special finite field operations.
+    ///
+    /// The operations on Fp2 elements are given one word at a time, and take
+    /// the number of words already pushed by the kernel as an offset into the
+    /// stack.
     fn run_sf(&self, input_fn: &ProverInputFn) -> Result<U256, ProgramError> {
         let field = EvmField::from_str(input_fn.0[1].as_str())
             .map_err(|_| ProgramError::ProverInputError(InvalidFunction))?;
-        let inputs: [U256; 4] = match field {
-            Bls381Base => (0..4)
+        if !matches!(field, Bls381Base) {
+            todo!()
+        }
+        let peek = |start: usize, n: usize| {
+            (start..start + n)
                 .map(|i| stack_peek(self, i))
-                .collect::<Result<Vec<U256>, _>>()?
-                .try_into()
-                .unwrap(),
-            _ => todo!(),
+                .collect::<Result<Vec<U256>, _>>()
         };
+        let peek_4 = || -> Result<[U256; 4], ProgramError> { Ok(peek(0, 4)?.try_into().unwrap()) };
+
         let res = match input_fn.0[2].as_str() {
-            "add_lo" => field.add_lo(inputs),
-            "add_hi" => field.add_hi(inputs),
-            "mul_lo" => field.mul_lo(inputs),
-            "mul_hi" => field.mul_hi(inputs),
-            "sub_lo" => field.sub_lo(inputs),
-            "sub_hi" => field.sub_hi(inputs),
+            "mul_lo" => field.mul_lo(peek_4()?),
+            "mul_hi" => field.mul_hi(peek_4()?),
+            "quo_lo" => field.quo_lo(peek_4()?),
+            "quo_hi" => field.quo_hi(peek_4()?),
+            "div_lo" => field.div_lo(peek_4()?),
+            "div_hi" => field.div_hi(peek_4()?),
+            "sqrt_lo" => field.sqrt_lo(peek(1, 2)?.try_into().unwrap()),
+            "sqrt_hi" => field.sqrt_hi(peek(0, 2)?.try_into().unwrap()),
+            "div_2_im_hi" => field.div_2(peek(0, 8)?.try_into().unwrap())[3],
+            "div_2_im_lo" => field.div_2(peek(1, 8)?.try_into().unwrap())[2],
+            "div_2_re_hi" => field.div_2(peek(2, 8)?.try_into().unwrap())[1],
+            "div_2_re_lo" => field.div_2(peek(3, 8)?.try_into().unwrap())[0],
+            "sqrt_2_im_hi" => field.sqrt_2(peek(0, 4)?.try_into().unwrap())[3],
+            "sqrt_2_im_lo" => field.sqrt_2(peek(1, 4)?.try_into().unwrap())[2],
+            "sqrt_2_re_hi" => field.sqrt_2(peek(2, 4)?.try_into().unwrap())[1],
+            "sqrt_2_re_lo" => field.sqrt_2(peek(3, 4)?.try_into().unwrap())[0],
             _ => return Err(ProgramError::ProverInputError(InvalidFunction)),
         };
 
@@ -168,20 +190,43 @@ impl<F: Field> GenerationState<F> {
     fn run_ffe(&self, input_fn: &ProverInputFn) -> Result<U256, ProgramError> {
         let field = EvmField::from_str(input_fn.0[1].as_str())
             .map_err(|_| ProgramError::ProverInputError(InvalidFunction))?;
-        let n = input_fn.0[2]
-            .as_str()
-            .split('_')
-            .nth(1)
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-        let ptr = stack_peek(self, 11 - n).map(u256_to_usize)??;
-
-        let f: [U256; 12] = match field {
-            Bn254Base => std::array::from_fn(|i| current_context_peek(self, BnPairing, ptr + i)),
+        match field {
+            Bn254Base => {
+                let n = input_fn.0[2]
+                    .as_str()
+                    .split('_')
+                    .nth(1)
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap();
+                let ptr = stack_peek(self, 11 - n).map(u256_to_usize)??;
+
+                let f: [U256; 12] =
+                    std::array::from_fn(|i| current_context_peek(self, BnPairing, ptr + i));
+                Ok(field.field_extension_inverse(n, f))
+            }
+            Bls381Base => {
+                // stack: i, addr
+                // Returns the i-th word of the inverse of the Fp12 element
+                // stored at addr.
+                if input_fn.0[2].as_str() != "inverse" {
+                    return Err(ProgramError::ProverInputError(InvalidFunction));
+                }
+                let i = stack_peek(self, 0).map(u256_to_usize)??;
+                let mut addr = MemoryAddress::new_bundle(stack_peek(self, 1)?)?;
+                let f: [U256; 24] = std::array::from_fn(|_| {
+                    let word = self.memory.get_with_init(addr);
+                    addr.increment();
+                    word
+                });
+                field
+                    .bls381_fp12_inverse(f)
+                    .get(i)
+                    .copied()
+                    .ok_or(ProgramError::ProverInputError(InvalidInput))
+            }
             _ => todo!(),
-        };
-        Ok(field.field_extension_inverse(n, f))
+        }
     }
 
     /// RLP data.
@@ -984,6 +1029,8 @@ enum EvmField {
     Bn254Scalar,
     Secp256k1Base,
     Secp256k1Scalar,
+    Secp256r1Base,
+    Secp256r1Scalar,
 }
 
 enum FieldOp {
@@ -1002,6 +1049,8 @@ impl FromStr for EvmField {
             "bn254_scalar" => Bn254Scalar,
             "secp256k1_base" => Secp256k1Base,
             "secp256k1_scalar" => Secp256k1Scalar,
+            "secp256r1_base" => Secp256r1Base,
+            "secp256r1_scalar" => Secp256r1Scalar,
             _ => bail!("Unrecognized field."),
         })
     }
@@ -1036,6 +1085,16 @@ impl EvmField {
                     .unwrap()
                     .into()
             }
+            EvmField::Secp256r1Base => {
+                U256::from_str("0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff")
+                    .unwrap()
+                    .into()
+            }
+            EvmField::Secp256r1Scalar => {
+                U256::from_str("0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551")
+                    .unwrap()
+                    .into()
+            }
         }
     }
 
@@ -1071,54 +1130,124 @@ impl EvmField {
         modexp(x, q, n)
     }
 
-    fn add_lo(&self, inputs: [U256; 4]) -> U256 {
+    fn mul_lo(&self, inputs: [U256; 4]) -> U256 {
         let [y1, x0, x1, y0] = inputs;
         let x = U512::from(x0) + (U512::from(x1) << 256);
         let y = U512::from(y0) + (U512::from(y1) << 256);
-        let z = BLS381 { val: x } + BLS381 { val: y };
+        let z = BLS381 { val: x } * BLS381 { val: y };
         z.lo()
     }
 
-    fn add_hi(&self, inputs: [U256; 4]) -> U256 {
+    fn mul_hi(&self, inputs: [U256; 4]) -> U256 {
         let [x0, x1, y0, y1] = inputs;
         let x = U512::from(x0) + (U512::from(x1) << 256);
         let y = U512::from(y0) + (U512::from(y1) << 256);
-        let z = BLS381 { val: x } + BLS381 { val: y };
+        let z = BLS381 { val: x } * BLS381 { val: y };
         z.hi()
     }
 
-    fn mul_lo(&self, inputs: [U256; 4]) -> U256 {
+    /// Returns the quotient of x * y by the modulus, which is smaller than
+    /// 2^381 for canonical inputs.
+    fn quo(x: [U256; 2], y: [U256; 2]) -> [U256; 2] {
+        let to_biguint = |[lo, hi]: [U256; 2]| u256_to_biguint(lo) + (u256_to_biguint(hi) << 256);
+        let p = BLS381 { val: BLS_BASE };
+        let q = to_biguint(x) * to_biguint(y) / to_biguint([p.lo(), p.hi()]);
+        let word_mask = (BigUint::from(1u8) << 256) - 1u8;
+        [&q & &word_mask, (&q >> 256) & &word_mask].map(biguint_to_u256)
+    }
+
+    fn quo_lo(&self, inputs: [U256; 4]) -> U256 {
         let [y1, x0, x1, y0] = inputs;
-        let x = U512::from(x0) + (U512::from(x1) << 256);
-        let y = U512::from(y0) + (U512::from(y1) << 256);
-        let z = BLS381 { val: x } * BLS381 { val: y };
-        z.lo()
+        Self::quo([x0, x1], [y0, y1])[0]
     }
 
-    fn mul_hi(&self, inputs: [U256; 4]) -> U256 {
+    fn quo_hi(&self, inputs: [U256; 4]) -> U256 {
         let [x0, x1, y0, y1] = inputs;
-        let x = U512::from(x0) + (U512::from(x1) << 256);
-        let y = U512::from(y0) + (U512::from(y1) << 256);
-        let z = BLS381 { val: x } * BLS381 { val: y };
-        z.hi()
+        Self::quo([x0, x1], [y0, y1])[1]
     }
 
-    fn sub_lo(&self, inputs: [U256; 4]) -> U256 {
+    fn div_lo(&self, inputs: [U256; 4]) -> U256 {
         let [y1, x0, x1, y0] = inputs;
         let x = U512::from(x0) + (U512::from(x1) << 256);
         let y = U512::from(y0) + (U512::from(y1) << 256);
-        let z = BLS381 { val: x } - BLS381 { val: y };
+        let z = BLS381 { val: x } / BLS381 { val: y };
         z.lo()
     }
 
-    fn sub_hi(&self, inputs: [U256; 4]) -> U256 {
+    fn div_hi(&self, inputs: [U256; 4]) -> U256 {
         let [x0, x1, y0, y1] = inputs;
         let x = U512::from(x0) + (U512::from(x1) << 256);
         let y = U512::from(y0) + (U512::from(y1) << 256);
-        let z = BLS381 { val: x } - BLS381 { val: y };
+        let z = BLS381 { val: x } / BLS381 { val: y };
         z.hi()
     }
 
+    /// Returns a square root of x, or zero if there is none. The kernel only
+    /// relies on a positive answer, which it checks.
+    fn sqrt_lo(&self, inputs: [U256; 2]) -> U256 {
+        let [x0, x1] = inputs;
+        let x = BLS381 {
+            val: U512::from(x0) + (U512::from(x1) << 256),
+        };
+        x.sqrt().map(|z| z.lo()).unwrap_or_default()
+    }
+
+    fn sqrt_hi(&self, inputs: [U256; 2]) -> U256 {
+        let [x0, x1] = inputs;
+        let x = BLS381 {
+            val: U512::from(x0) + (U512::from(x1) << 256),
+        };
+        x.sqrt().map(|z| z.hi()).unwrap_or_default()
+    }
+
+    /// Returns the words of x / y, given the words of x and y.
+    fn div_2(&self, inputs: [U256; 8]) -> [U256; 4] {
+        let [x, y] = [&inputs[..4], &inputs[4..]].map(bls381_fp2_from_words);
+        bls381_fp2_to_words(x / y)
+    }
+
+    /// Returns the words of a square root of x, or zero if there is none.
+    fn sqrt_2(&self, inputs: [U256; 4]) -> [U256; 4] {
+        bls381_fp2_from_words(&inputs)
+            .sqrt()
+            .map(bls381_fp2_to_words)
+            .unwrap_or_default()
+    }
+
+    /// Returns the words of the inverse of the given Fp12 element, laid out
+    /// as six Fp2 coefficients c00, c01, c02, c10, c11, c12.
+    fn bls381_fp12_inverse(&self, f: [U256; 24]) -> [U256; 24] {
+        let c: [Fp2<BLS381>; 6] = std::array::from_fn(|i| bls381_fp2_from_words(&f[4 * i..]));
+        let f = Fp12 {
+            z0: Fp6 {
+                t0: c[0],
+                t1: c[1],
+                t2: c[2],
+            },
+            z1: Fp6 {
+                t0: c[3],
+                t1: c[4],
+                t2: c[5],
+            },
+        };
+        let f_inv = f.inv();
+        let mut words = [U256::zero(); 24];
+        for (i, c) in [
+            f_inv.z0.t0,
+            f_inv.z0.t1,
+            f_inv.z0.t2,
+            f_inv.z1.t0,
+            f_inv.z1.t1,
+            f_inv.z1.t2,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            words[4 * i..4 * i + 4].copy_from_slice(&bls381_fp2_to_words(c));
+        }
+        words
+    }
+
     fn field_extension_inverse(&self, n: usize, f: [U256; 12]) -> U256 {
         let f: Fp12<BN254> = unsafe { transmute(f) };
         let f_inv: [U256; 12] = unsafe { transmute(f.inv()) };
@@ -1126,6 +1255,21 @@ impl EvmField {
     }
 }
 
+/// Reads an Fp2 element from its words re_lo, re_hi, im_lo, im_hi.
+fn bls381_fp2_from_words(words: &[U256]) -> Fp2<BLS381> {
+    let fp = |lo: U256, hi: U256| BLS381 {
+        val: U512::from(lo) + (U512::from(hi) << 256),
+    };
+    Fp2 {
+        re: fp(words[0], words[1]),
+        im: fp(words[2], words[3]),
+    }
+}
+
+fn bls381_fp2_to_words(x: Fp2<BLS381>) -> [U256; 4] {
+    [x.re.lo(), x.re.hi(), x.im.lo(), x.im.hi()]
+}
+
 fn modexp(x: U256, e: U256, n: U256) -> Result<U256, ProgramError> {
     let mut current = x;
     let mut product = U256::one();
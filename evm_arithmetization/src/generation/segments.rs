@@ -1,14 +1,27 @@
 //! Module defining the logic around proof segmentation into chunks,
 //! which allows what is commonly known as zk-continuations.
 
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
 use anyhow::Result;
+use ethereum_types::U256;
 use plonky2::hash::hash_types::RichField;
 use serde::{Deserialize, Serialize};
 
+use crate::all_stark::NUM_TABLES;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::interpreter::{set_registers_and_run, ExtraSegmentData, Interpreter};
+use crate::cpu::kernel::profiler::{KernelProfile, Profiler};
+use crate::cpu::kernel::tracer::{Tracer, TransactionTrace};
 use crate::generation::state::State;
-use crate::generation::{debug_inputs, GenerationInputs};
+use crate::generation::{
+    debug_inputs, with_state_mismatch_report, GenerationInputs, TrimmedGenerationInputs,
+};
+use crate::prover::check_abort_signal;
+use crate::witness::errors::{ErrorLocation, ExecutionError, ExecutionErrorKind};
 use crate::witness::memory::MemoryState;
 use crate::witness::state::RegistersState;
 use crate::AllData;
@@ -29,6 +42,10 @@ pub struct GenerationSegmentData {
     pub(crate) extra_data: ExtraSegmentData,
     /// Log of the maximal cpu length.
     pub(crate) max_cpu_len_log: Option<usize>,
+    /// Statistics about the traces of this segment, set once they have been
+    /// generated.
+    #[serde(skip)]
+    pub(crate) trace_stats: Option<SegmentTraceStats>,
 }
 
 impl GenerationSegmentData {
@@ -36,6 +53,25 @@ impl GenerationSegmentData {
     pub fn segment_index(&self) -> usize {
         self.segment_index
     }
+
+    /// Retrieves the statistics about the traces of this segment, if they have
+    /// been generated.
+    pub const fn trace_stats(&self) -> Option<&SegmentTraceStats> {
+        self.trace_stats.as_ref()
+    }
+}
+
+/// Statistics about the traces generated for a segment.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SegmentTraceStats {
+    /// The time taken to generate the traces.
+    pub generation_time: Duration,
+    /// The number of CPU cycles executed in the segment, before padding.
+    pub cpu_cycles: usize,
+    /// The length of each table trace, before padding.
+    pub trace_lengths: [usize; NUM_TABLES],
+    /// The log2 of the length of each table trace, after padding.
+    pub degree_bits: [usize; NUM_TABLES],
 }
 
 /// Builds a new `GenerationSegmentData`.
@@ -60,6 +96,7 @@ fn build_segment_data<F: RichField>(
             ..Default::default()
         }),
         max_cpu_len_log: interpreter.get_max_cpu_len_log(),
+        trace_stats: None,
         extra_data: ExtraSegmentData {
             bignum_modmul_result_limbs: interpreter
                 .generation_state
@@ -81,13 +118,71 @@ fn build_segment_data<F: RichField>(
 pub struct SegmentDataIterator<F: RichField> {
     interpreter: Interpreter<F>,
     partial_next_data: Option<GenerationSegmentData>,
+    abort_signal: Option<Arc<AtomicBool>>,
 }
 
 pub type SegmentRunResult = Option<Box<(GenerationSegmentData, Option<GenerationSegmentData>)>>;
 
-#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
-#[error("{}", .0)]
-pub struct SegmentError(pub String);
+/// An error raised while generating a segment, along with the payload and the
+/// point of the execution at which it occurred.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentError {
+    pub segment_index: usize,
+    pub block_number: U256,
+    /// The index of the first transaction of the payload within its block.
+    pub txn_number_before: U256,
+    /// The number of transactions of the payload, 0 for a dummy payload.
+    pub num_txns: usize,
+    /// What caused the failure, which failures can be grouped by.
+    pub kind: ExecutionErrorKind,
+    /// Where the execution failed, if known.
+    pub location: Option<ErrorLocation>,
+    /// A description of the failure, with all its context.
+    pub message: String,
+}
+
+impl SegmentError {
+    /// Describes the failure `err` of the segment `segment_index` of the
+    /// payload `inputs`, taking its kind and location from the
+    /// [`ExecutionError`] it wraps, if any.
+    pub fn new(segment_index: usize, inputs: &TrimmedGenerationInputs, err: anyhow::Error) -> Self {
+        let (kind, location) = match err.downcast_ref::<ExecutionError>() {
+            Some(err) => (err.kind.clone(), Some(err.location.clone())),
+            None => (ExecutionErrorKind::Other, None),
+        };
+
+        Self {
+            segment_index,
+            block_number: inputs.block_metadata.block_number,
+            txn_number_before: inputs.txn_number_before,
+            num_txns: inputs.txn_hashes.len(),
+            kind,
+            location,
+            message: format!("{err:?}"),
+        }
+    }
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let txn_range = match self.num_txns {
+            0 => "Dummy".to_string(),
+            1 => format!("{:?}", self.txn_number_before),
+            _ => format!(
+                "{:?}_{:?}",
+                self.txn_number_before,
+                self.txn_number_before + self.num_txns
+            ),
+        };
+        write!(
+            f,
+            "Segment generation {:?} for block {:?} ({}) failed with error {}",
+            self.segment_index, self.block_number, txn_range, self.message
+        )
+    }
+}
+
+impl std::error::Error for SegmentError {}
 
 impl<F: RichField> SegmentDataIterator<F> {
     pub fn new(inputs: &GenerationInputs, max_cpu_len_log: Option<usize>) -> Self {
@@ -103,9 +198,43 @@ impl<F: RichField> SegmentDataIterator<F> {
         Self {
             interpreter,
             partial_next_data: None,
+            abort_signal: None,
         }
     }
 
+    /// Stops the generation of segments once `abort_signal` is set, yielding
+    /// an [`ExecutionErrorKind::Aborted`] error instead of the next segment.
+    pub fn with_abort_signal(mut self, abort_signal: Arc<AtomicBool>) -> Self {
+        self.abort_signal = Some(abort_signal);
+        self
+    }
+
+    /// Enables profiling of the execution, attributing its costs to the kernel
+    /// routines being executed.
+    pub fn with_profiling(mut self) -> Self {
+        self.interpreter.profiler = Some(Profiler::new());
+        self
+    }
+
+    /// Returns the profile of the segments generated so far, if profiling was
+    /// enabled.
+    pub fn into_profile(self) -> Option<KernelProfile> {
+        self.interpreter.profiler.map(Profiler::into_profile)
+    }
+
+    /// Enables tracing of the user code executed by each transaction, in the
+    /// format of EIP-3155.
+    pub fn with_tracing(mut self) -> Self {
+        self.interpreter.tracer = Some(Tracer::new());
+        self
+    }
+
+    /// Returns the traces of the transactions executed so far, if tracing was
+    /// enabled.
+    pub fn into_traces(self) -> Option<Vec<TransactionTrace>> {
+        self.interpreter.tracer.map(Tracer::into_traces)
+    }
+
     /// Returns the data for the current segment, as well as the data -- except
     /// registers_after -- for the next segment.
     fn generate_next_segment(
@@ -129,6 +258,17 @@ impl<F: RichField> SegmentDataIterator<F> {
 
         let segment_index = segment_data.segment_index;
 
+        if let Err(err) = check_abort_signal(self.abort_signal.clone()) {
+            return Err(SegmentError {
+                kind: ExecutionErrorKind::Aborted,
+                ..SegmentError::new(
+                    segment_index,
+                    &self.interpreter.generation_state.inputs,
+                    err,
+                )
+            });
+        }
+
         // Run the interpreter to get `registers_after` and the partial data for the
         // next segment.
         let run = set_registers_and_run(segment_data.registers_after, &mut self.interpreter);
@@ -144,25 +284,13 @@ impl<F: RichField> SegmentDataIterator<F> {
             segment_data.registers_after = updated_registers;
             Ok(Some(Box::new((segment_data, partial_segment_data))))
         } else {
-            let inputs = &self.interpreter.get_generation_state().inputs;
-            let block = inputs.block_metadata.block_number;
-            let txn_range = match inputs.txn_hashes.len() {
-                0 => "Dummy".to_string(),
-                1 => format!("{:?}", inputs.txn_number_before),
-                _ => format!(
-                    "{:?}_{:?}",
-                    inputs.txn_number_before,
-                    inputs.txn_number_before + inputs.txn_hashes.len()
-                ),
-            };
-            let s = format!(
-                "Segment generation {:?} for block {:?} ({}) failed with error {:?}",
+            let err =
+                with_state_mismatch_report(&self.interpreter.generation_state, run.unwrap_err());
+            Err(SegmentError::new(
                 segment_index,
-                block,
-                txn_range,
-                run.unwrap_err()
-            );
-            Err(SegmentError(s))
+                &self.interpreter.generation_state.inputs,
+                err,
+            ))
         }
     }
 }
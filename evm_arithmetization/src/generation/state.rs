@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::mem::size_of;
 
-use anyhow::{anyhow, bail};
+use anyhow::bail;
 use ethereum_types::{Address, BigEndianHash, H160, H256, U256};
 use itertools::Itertools;
 use keccak_hash::keccak;
@@ -14,6 +14,7 @@ use super::{TrieInputs, TrimmedGenerationInputs, NUM_EXTRA_CYCLES_AFTER};
 use crate::byte_packing::byte_packing_stark::BytePackingOp;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::constants::DELEGATION_DESIGNATOR_LEN;
 use crate::cpu::stack::MAX_USER_STACK_SIZE;
 use crate::generation::mpt::load_linked_lists_and_txn_and_receipt_mpts;
 use crate::generation::rlp::all_rlp_prover_inputs_reversed;
@@ -23,7 +24,7 @@ use crate::keccak_sponge::columns::KECCAK_WIDTH_BYTES;
 use crate::keccak_sponge::keccak_sponge_stark::KeccakSpongeOp;
 use crate::memory::segments::Segment;
 use crate::util::u256_to_usize;
-use crate::witness::errors::ProgramError;
+use crate::witness::errors::{ErrorLocation, ExecutionError, ExecutionErrorKind, ProgramError};
 use crate::witness::memory::MemoryChannel::GeneralPurpose;
 use crate::witness::memory::MemoryOpKind;
 use crate::witness::memory::{MemoryAddress, MemoryOp, MemoryState};
@@ -239,14 +240,14 @@ pub(crate) trait State<F: Field> {
             ProgramError::InvalidJumpDestination => 3,
             ProgramError::InvalidJumpiDestination => 4,
             ProgramError::StackOverflow => 5,
-            _ => bail!("TODO: figure out what to do with this..."),
+            _ => bail!(self.execution_error(ExecutionErrorKind::Program(err))),
         };
 
         let checkpoint = self.checkpoint();
 
         let (row, _) = self.base_row();
         generate_exception(exc_code, self, row)
-            .map_err(|e| anyhow!("Exception handling failed with error: {:?}", e))?;
+            .map_err(|e| self.execution_error(ExecutionErrorKind::ExceptionHandling(e)))?;
 
         self.apply_ops(checkpoint);
 
@@ -273,17 +274,20 @@ pub(crate) trait State<F: Field> {
             }
             Err(e) => {
                 if self.get_registers().is_kernel {
-                    let offset_name = KERNEL.offset_name(self.get_registers().program_counter);
-                    bail!(
-                        "{:?} in kernel at pc={}, stack={:?}, memory={:?}",
-                        e,
-                        offset_name,
+                    let location = KERNEL.symbolize(self.get_registers().program_counter);
+                    let context = format!(
+                        "Failure in kernel at pc={}\nstack={:?}, memory={:?}",
+                        location,
                         self.get_stack(),
                         self.mem_get_kernel_content()
                             .iter()
                             .map(|c| c.unwrap_or_default())
                             .collect_vec(),
                     );
+                    return Err(anyhow::Error::new(
+                        self.execution_error(ExecutionErrorKind::Program(e)),
+                    )
+                    .context(context));
                 }
                 self.rollback(checkpoint);
                 self.handle_error(e)
@@ -293,6 +297,20 @@ pub(crate) trait State<F: Field> {
 
     fn try_perform_instruction(&mut self) -> Result<Operation, ProgramError>;
 
+    /// Returns an error of the given kind, located at the current point of the
+    /// execution.
+    fn execution_error(&self, kind: ExecutionErrorKind) -> ExecutionError {
+        let generation_state = self.get_generation_state();
+        ExecutionError {
+            kind,
+            location: ErrorLocation::new(
+                &self.get_registers(),
+                generation_state.inputs.txn_number_before,
+                generation_state.next_txn_index,
+            ),
+        }
+    }
+
     /// Row that has the correct values for system registers and the code
     /// channel, but is otherwise blank. It fulfills the constraints that
     /// are common to successful operations and the exception operation. It
@@ -462,6 +480,10 @@ impl<F: Field> GenerationState<F> {
             let tip_u256 = stack_peek(self, 0)?;
             let tip_h256 = H256::from_uint(&tip_u256);
             self.observe_contract(tip_h256)?;
+        } else if dst == KERNEL.global_labels["observe_delegation_designator"] {
+            let tip_u256 = stack_peek(self, 0)?;
+            let tip_h256 = H256::from_uint(&tip_u256);
+            self.observe_delegation_designator(tip_h256);
         }
 
         Ok(())
@@ -500,6 +522,24 @@ impl<F: Field> GenerationState<F> {
         Ok(())
     }
 
+    /// Observe the given code hash of an EIP-7702 delegation designator and
+    /// store the associated code. When called, the delegation designator
+    /// should be stored at the start of the kernel general segment.
+    pub(crate) fn observe_delegation_designator(&mut self, codehash: H256) {
+        if self.inputs.contract_code.contains_key(&codehash) {
+            return;
+        }
+
+        let code = self.memory.contexts[0].segments[Segment::KernelGeneral.unscale()].content
+            [..DELEGATION_DESIGNATOR_LEN]
+            .iter()
+            .map(|x| x.unwrap_or_default().low_u32() as u8)
+            .collect::<Vec<_>>();
+        debug_assert_eq!(keccak(&code), codehash);
+
+        self.inputs.contract_code.insert(codehash, code);
+    }
+
     pub(crate) fn rollback(&mut self, checkpoint: GenerationStateCheckpoint) {
         self.registers = checkpoint.registers;
         self.traces.rollback(checkpoint.traces);
@@ -0,0 +1,365 @@
+//! Reports how the state computed by the kernel diverges from the expected one,
+//! when the final state root doesn't match. This is intended only for
+//! debugging.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use ethereum_types::{Address, H256, U256};
+use mpt_trie::debug_tools::diff::{create_diff_between_tries, TrieDiff};
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use mpt_trie::trie_ops::ValOrHash;
+use plonky2::field::types::Field;
+
+use super::mpt::AccountRlp;
+use super::state::GenerationState;
+use super::trie_extractor::get_state_and_storage_tries;
+use super::TrimmedTrieInputs;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::util::u256_to_usize;
+use crate::witness::errors::ProgramError;
+
+/// How an account diverges from the expected one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Divergence {
+    /// The account is only present in one of the states.
+    Account {
+        computed: Option<AccountRlp>,
+        expected: Option<AccountRlp>,
+    },
+    /// A field of the account differs.
+    Field {
+        name: &'static str,
+        computed: String,
+        expected: String,
+    },
+    /// A storage slot, given by its hashed key, differs. `None` stands for an
+    /// absent slot.
+    Slot {
+        slot_hash: H256,
+        computed: Option<U256>,
+        expected: Option<U256>,
+    },
+}
+
+/// A divergence of the account with the given hashed address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct AccountDivergence {
+    pub(crate) address_hash: H256,
+    pub(crate) address: Option<Address>,
+    pub(crate) divergence: Divergence,
+}
+
+impl fmt::Display for AccountDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.address {
+            Some(address) => write!(f, "account {address:?}: ")?,
+            None => write!(f, "account with hashed address {:?}: ", self.address_hash)?,
+        }
+
+        match &self.divergence {
+            Divergence::Account { computed, expected } => {
+                write!(f, "is {computed:?}, expected {expected:?}")
+            }
+            Divergence::Field {
+                name,
+                computed,
+                expected,
+            } => write!(f, "{name} is {computed}, expected {expected}"),
+            Divergence::Slot {
+                slot_hash,
+                computed,
+                expected,
+            } => write!(
+                f,
+                "slot with hashed key {slot_hash:?} is {computed:?}, expected {expected:?}"
+            ),
+        }
+    }
+}
+
+/// The differences between the computed and expected states.
+#[derive(Clone, Debug)]
+pub(crate) struct StateMismatchReport {
+    /// The highest point at which the state tries diverge.
+    pub(crate) trie_diff: TrieDiff,
+    pub(crate) divergences: Vec<AccountDivergence>,
+}
+
+impl fmt::Display for StateMismatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "The final state root doesn't match the expected one.")?;
+        if self.trie_diff.latest_diff_res.is_some() {
+            writeln!(f, "The state tries first diverge at {}", self.trie_diff)?;
+        }
+        if self.divergences.is_empty() {
+            return write!(f, "No account of the expected tries diverges.");
+        }
+        write!(f, "Diverging accounts:")?;
+        for divergence in &self.divergences {
+            write!(f, "\n  {divergence}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Extracts the state and storage tries computed by the kernel in `state`,
+/// and compares them against the `expected` ones.
+pub(crate) fn state_mismatch_report<F: Field>(
+    state: &GenerationState<F>,
+    expected: &TrimmedTrieInputs,
+) -> Result<StateMismatchReport, ProgramError> {
+    let state_trie_ptr = u256_to_usize(
+        state
+            .memory
+            .read_global_metadata(GlobalMetadata::StateTrieRoot),
+    )?;
+    let (state_trie, storage_tries) = get_state_and_storage_tries(&state.memory, state_trie_ptr)?;
+
+    Ok(diff_states(
+        &state_trie,
+        &storage_tries,
+        expected,
+        &state.state_key_to_address,
+    ))
+}
+
+/// Compares the computed state and storage tries against the `expected` ones.
+/// Values hidden behind a hash node in either trie are not compared.
+pub(crate) fn diff_states(
+    state_trie: &HashedPartialTrie,
+    storage_tries: &HashMap<H256, HashedPartialTrie>,
+    expected: &TrimmedTrieInputs,
+    addresses: &HashMap<H256, Address>,
+) -> StateMismatchReport {
+    let expected_storage_tries: HashMap<_, _> = expected.storage_tries.iter().cloned().collect();
+    let mut divergences = vec![];
+
+    for (address_hash, computed, expected_account) in diff_values(state_trie, &expected.state_trie)
+    {
+        let mut push = |divergence| {
+            divergences.push(AccountDivergence {
+                address_hash,
+                address: addresses.get(&address_hash).copied(),
+                divergence,
+            })
+        };
+
+        let computed: Option<AccountRlp> = decode(computed);
+        let expected_account: Option<AccountRlp> = decode(expected_account);
+        let (Some(computed), Some(expected_account)) = (computed, expected_account) else {
+            push(Divergence::Account {
+                computed,
+                expected: expected_account,
+            });
+            continue;
+        };
+
+        let fields = [
+            (
+                "nonce",
+                computed.nonce.to_string(),
+                expected_account.nonce.to_string(),
+            ),
+            (
+                "balance",
+                computed.balance.to_string(),
+                expected_account.balance.to_string(),
+            ),
+            (
+                "code_hash",
+                format!("{:?}", computed.code_hash),
+                format!("{:?}", expected_account.code_hash),
+            ),
+        ];
+        for (name, computed, expected) in fields {
+            if computed != expected {
+                push(Divergence::Field {
+                    name,
+                    computed,
+                    expected,
+                });
+            }
+        }
+
+        if computed.storage_root == expected_account.storage_root {
+            continue;
+        }
+        let slots = match (
+            storage_tries.get(&address_hash),
+            expected_storage_tries.get(&address_hash),
+        ) {
+            (Some(computed), Some(expected)) => diff_values(computed, expected),
+            _ => vec![],
+        };
+        if slots.is_empty() {
+            push(Divergence::Field {
+                name: "storage_root",
+                computed: format!("{:?}", computed.storage_root),
+                expected: format!("{:?}", expected_account.storage_root),
+            });
+        }
+        for (slot_hash, computed, expected) in slots {
+            push(Divergence::Slot {
+                slot_hash,
+                computed: decode(computed),
+                expected: decode(expected),
+            });
+        }
+    }
+
+    StateMismatchReport {
+        trie_diff: create_diff_between_tries(state_trie, &expected.state_trie),
+        divergences,
+    }
+}
+
+/// A key whose values differ between two tries, along with these values.
+type ValueDiff = (H256, Option<Vec<u8>>, Option<Vec<u8>>);
+
+fn decode<T: rlp::Decodable>(value: Option<Vec<u8>>) -> Option<T> {
+    value.and_then(|value| rlp::decode(&value).ok())
+}
+
+/// Returns the keys whose values differ between two tries. Keys hidden behind a
+/// hash node in either trie are left out.
+fn diff_values(a: &HashedPartialTrie, b: &HashedPartialTrie) -> Vec<ValueDiff> {
+    let (a_values, a_hashes) = values_and_hash_prefixes(a);
+    let (b_values, b_hashes) = values_and_hash_prefixes(b);
+    let is_hidden = |key: H256| {
+        let key = Nibbles::from_h256_be(key);
+        a_hashes
+            .iter()
+            .chain(&b_hashes)
+            .any(|prefix| key.nibbles_are_identical_up_to_smallest_count(prefix))
+    };
+
+    let keys: BTreeSet<_> = a_values.keys().chain(b_values.keys()).copied().collect();
+    keys.into_iter()
+        .filter(|&key| a_values.get(&key) != b_values.get(&key) && !is_hidden(key))
+        .map(|key| {
+            (
+                key,
+                a_values.get(&key).cloned(),
+                b_values.get(&key).cloned(),
+            )
+        })
+        .collect()
+}
+
+fn values_and_hash_prefixes(trie: &HashedPartialTrie) -> (BTreeMap<H256, Vec<u8>>, Vec<Nibbles>) {
+    let mut values = BTreeMap::new();
+    let mut hash_prefixes = vec![];
+    for (key, value) in trie.items() {
+        match value {
+            ValOrHash::Val(value) => {
+                values.insert(H256::from(key), value);
+            }
+            ValOrHash::Hash(_) => hash_prefixes.push(key),
+        }
+    }
+    (values, hash_prefixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use mpt_trie::partial_trie::Node;
+
+    use super::*;
+
+    fn account(balance: u64, storage_root: H256) -> Vec<u8> {
+        rlp::encode(&AccountRlp {
+            balance: balance.into(),
+            storage_root,
+            ..AccountRlp::default()
+        })
+        .to_vec()
+    }
+
+    fn trie(values: &[(H256, Vec<u8>)]) -> HashedPartialTrie {
+        let mut trie = HashedPartialTrie::from(Node::Empty);
+        for (key, value) in values {
+            trie.insert(Nibbles::from_h256_be(*key), value.clone())
+                .unwrap();
+        }
+        trie
+    }
+
+    #[test]
+    fn diverging_fields_and_slots() {
+        let (a, b, c) = (
+            H256::repeat_byte(0x11),
+            H256::repeat_byte(0x22),
+            H256::repeat_byte(0x33),
+        );
+        let slot = H256::repeat_byte(0x44);
+        let computed_storage = trie(&[(slot, rlp::encode(&U256::from(1)).to_vec())]);
+        let expected_storage = trie(&[(slot, rlp::encode(&U256::from(2)).to_vec())]);
+
+        let computed_state = trie(&[
+            (a, account(1, computed_storage.hash())),
+            (b, account(2, AccountRlp::default().storage_root)),
+            (c, account(3, AccountRlp::default().storage_root)),
+        ]);
+        let expected = TrimmedTrieInputs {
+            state_trie: trie(&[
+                (a, account(1, expected_storage.hash())),
+                (b, account(5, AccountRlp::default().storage_root)),
+            ]),
+            storage_tries: vec![(a, expected_storage)],
+        };
+
+        let report = diff_states(
+            &computed_state,
+            &HashMap::from([(a, computed_storage)]),
+            &expected,
+            &HashMap::from([(b, Address::repeat_byte(0xbb))]),
+        );
+        let divergences: Vec<_> = report.divergences.iter().map(|d| &d.divergence).collect();
+        assert_eq!(
+            divergences,
+            vec![
+                &Divergence::Slot {
+                    slot_hash: slot,
+                    computed: Some(1.into()),
+                    expected: Some(2.into()),
+                },
+                &Divergence::Field {
+                    name: "balance",
+                    computed: "2".into(),
+                    expected: "5".into(),
+                },
+                &Divergence::Account {
+                    computed: Some(
+                        rlp::decode(&account(3, AccountRlp::default().storage_root)).unwrap()
+                    ),
+                    expected: None,
+                },
+            ]
+        );
+        assert_eq!(
+            report.divergences[1].address,
+            Some(Address::repeat_byte(0xbb))
+        );
+        assert!(report.trie_diff.latest_diff_res.is_some());
+    }
+
+    #[test]
+    fn hashed_values_are_not_compared() {
+        let (a, b) = (H256::repeat_byte(0x11), H256::repeat_byte(0x22));
+        let full = trie(&[
+            (a, account(1, AccountRlp::default().storage_root)),
+            (b, account(2, AccountRlp::default().storage_root)),
+        ]);
+        let mut partial = trie(&[(a, account(1, AccountRlp::default().storage_root))]);
+        partial
+            .insert(
+                Nibbles::from_h256_be(b),
+                ValOrHash::Hash(H256::repeat_byte(0xff)),
+            )
+            .unwrap();
+
+        assert_eq!(diff_values(&full, &partial), vec![]);
+    }
+}
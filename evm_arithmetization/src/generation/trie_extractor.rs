@@ -1,6 +1,8 @@
 //! Code for extracting trie data after witness generation. This is intended
 //! only for debugging.
 
+use std::collections::HashMap;
+
 use ethereum_types::{BigEndianHash, H256, U256};
 use mpt_trie::nibbles::{Nibbles, NibblesIntern};
 use mpt_trie::partial_trie::{HashedPartialTrie, Node, PartialTrie, WrappedNode};
@@ -89,14 +91,22 @@ pub(crate) fn read_logs(
         .collect()
 }
 
+pub(crate) fn read_storage_rlp_value(
+    _memory: &MemoryState,
+    slice: &MemoryValues,
+) -> Result<Vec<u8>, ProgramError> {
+    Ok(rlp::encode(&read_storage_trie_value(slice)).to_vec())
+}
+
 pub(crate) fn read_state_rlp_value(
     memory: &MemoryState,
     slice: &MemoryValues,
 ) -> Result<Vec<u8>, ProgramError> {
-    let storage_trie: HashedPartialTrie =
-        get_trie(memory, slice[2].unwrap_or_default().as_usize(), |_, x| {
-            Ok(rlp::encode(&read_storage_trie_value(x)).to_vec())
-        })?;
+    let storage_trie: HashedPartialTrie = get_trie(
+        memory,
+        slice[2].unwrap_or_default().as_usize(),
+        read_storage_rlp_value,
+    )?;
     let account = AccountRlp {
         nonce: slice[0].unwrap_or_default(),
         balance: slice[1].unwrap_or_default(),
@@ -137,6 +147,28 @@ pub(crate) fn get_state_trie<N: PartialTrie>(
     get_trie(memory, ptr, read_state_rlp_value)
 }
 
+/// Returns the state trie at `ptr`, along with the storage trie of each of its
+/// accounts, keyed by hashed address.
+pub(crate) fn get_state_and_storage_tries(
+    memory: &MemoryState,
+    ptr: usize,
+) -> Result<(HashedPartialTrie, HashMap<H256, HashedPartialTrie>), ProgramError> {
+    let state_trie = get_state_trie(memory, ptr)?;
+
+    let mut leaves = vec![];
+    get_leaf_value_ptrs(memory, ptr, Nibbles::default(), &mut leaves)?;
+    let storage_tries = leaves
+        .into_iter()
+        .map(|(key, value_ptr)| {
+            let storage_ptr = u256_to_usize(load_trie_data(memory, value_ptr + 2))?;
+            let storage_trie = get_trie(memory, storage_ptr, read_storage_rlp_value)?;
+            Ok((H256::from(key), storage_trie))
+        })
+        .collect::<Result<_, ProgramError>>()?;
+
+    Ok((state_trie, storage_tries))
+}
+
 pub(crate) fn get_txn_trie<N: PartialTrie>(
     memory: &MemoryState,
     ptr: usize,
@@ -240,3 +272,56 @@ pub(crate) fn get_trie_helper<N: PartialTrie>(
         }
     }
 }
+
+fn load_trie_data(memory: &MemoryState, offset: usize) -> U256 {
+    memory
+        .get(MemoryAddress {
+            context: 0,
+            segment: Segment::TrieData.unscale(),
+            virt: offset,
+        })
+        .unwrap_or_default()
+}
+
+/// Appends to `leaves` the key and value pointer of each value of the trie at
+/// `ptr`, whose keys start with `prefix`.
+fn get_leaf_value_ptrs(
+    memory: &MemoryState,
+    ptr: usize,
+    prefix: Nibbles,
+    leaves: &mut Vec<(Nibbles, usize)>,
+) -> Result<(), ProgramError> {
+    let load = |offset| load_trie_data(memory, offset);
+    let load_nibbles = |offset| -> Result<Nibbles, ProgramError> {
+        Ok(Nibbles {
+            count: u256_to_usize(load(offset))?,
+            packed: load(offset + 1).into(),
+        })
+    };
+
+    match PartialTrieType::all()[u256_to_usize(load(ptr))?] {
+        PartialTrieType::Empty | PartialTrieType::Hash => {}
+        PartialTrieType::Branch => {
+            for i in 0..16 {
+                let child_ptr = u256_to_usize(load(ptr + 1 + i as usize))?;
+                get_leaf_value_ptrs(memory, child_ptr, prefix.merge_nibble(i), leaves)?;
+            }
+            let value_ptr = u256_to_usize(load(ptr + 17))?;
+            if value_ptr != 0 {
+                leaves.push((prefix, value_ptr));
+            }
+        }
+        PartialTrieType::Extension => {
+            let nibbles = load_nibbles(ptr + 1)?;
+            let child_ptr = u256_to_usize(load(ptr + 3))?;
+            get_leaf_value_ptrs(memory, child_ptr, prefix.merge_nibbles(&nibbles), leaves)?;
+        }
+        PartialTrieType::Leaf => {
+            let nibbles = load_nibbles(ptr + 1)?;
+            let value_ptr = u256_to_usize(load(ptr + 3))?;
+            leaves.push((prefix.merge_nibbles(&nibbles), value_ptr));
+        }
+    }
+
+    Ok(())
+}
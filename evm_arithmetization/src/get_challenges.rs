@@ -138,17 +138,18 @@ fn observe_extra_block_data_target<
     challenger.observe_element(extra_data.gas_used_after);
 }
 
-#[cfg(feature = "cdk_erigon")]
+/// This is a no-op if no burn address was specified.
 fn observe_burn_addr<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     challenger: &mut Challenger<F, C::Hasher>,
-    burn_addr: U256,
+    burn_addr: Option<U256>,
 ) -> Result<(), ProgramError> {
-    challenger.observe_elements(&u256_limbs(burn_addr));
+    if let Some(burn_addr) = burn_addr {
+        challenger.observe_elements(&u256_limbs(burn_addr));
+    }
     Ok(())
 }
 
-#[cfg(feature = "cdk_erigon")]
-/// This will panic if no burn address was specified.  
+/// This is a no-op if no burn address was specified.
 fn observe_burn_addr_target<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -161,7 +162,7 @@ fn observe_burn_addr_target<
 {
     match burn_addr {
         BurnAddrTarget::BurnAddr(addr) => challenger.observe_elements(&addr),
-        BurnAddrTarget::Burnt() => panic!("There should be an address set in cdk_erigon."),
+        BurnAddrTarget::Burnt() => {}
     }
 }
 
@@ -207,15 +208,7 @@ pub(crate) fn observe_public_values<
     observe_block_hashes::<F, C, D>(challenger, &public_values.block_hashes);
     observe_extra_block_data::<F, C, D>(challenger, &public_values.extra_block_data)?;
 
-    #[cfg(feature = "cdk_erigon")]
-    {
-        observe_burn_addr::<F, C, D>(
-            challenger,
-            public_values
-                .burn_addr
-                .expect("There should be an address set in cdk_erigon."),
-        )?;
-    }
+    observe_burn_addr::<F, C, D>(challenger, public_values.burn_addr)?;
 
     Ok(())
 }
@@ -235,7 +228,6 @@ pub(crate) fn observe_public_values_target<
     observe_block_metadata_target::<F, C, D>(challenger, &public_values.block_metadata);
     observe_block_hashes_target::<F, C, D>(challenger, &public_values.block_hashes);
     observe_extra_block_data_target::<F, C, D>(challenger, &public_values.extra_block_data);
-    #[cfg(feature = "cdk_erigon")]
     observe_burn_addr_target::<F, C, D>(challenger, public_values.burn_addr.clone());
 }
 
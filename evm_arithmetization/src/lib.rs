@@ -27,10 +27,12 @@
 //!
 //! // Generate all the recursive circuits needed to generate succinct proofs for blocks.
 //! // The ranges correspond to the supported table sizes for each individual STARK component.
+//! // Circuits are specific to a `ChainProfile`, which fixes the layout of the public values.
 //! let prover_state = AllRecursiveCircuits::<F, C, D>::new(
 //!     &all_stark,
 //!     &[16..25, 10..20, 12..25, 14..25, 9..20, 12..20, 17..30],
 //!     &config,
+//!     ChainProfile::L1,
 //! );
 //! ```
 //!
@@ -65,7 +67,8 @@
 //! public inputs, as such:
 //!
 //! ```ignore
-//! let public_values = PublicValues::from_public_inputs(&proof.public_inputs);
+//! let public_values =
+//!     PublicValues::from_public_inputs(&proof.public_inputs, prover_state.chain_profile);
 //! ```
 //!
 //! ## Aggregation proofs
@@ -212,7 +215,6 @@ pub mod extension_tower;
 pub mod testing_utils;
 pub mod util;
 
-use generation::segments::SegmentError;
 use generation::TrimmedGenerationInputs;
 use mpt_trie::partial_trie::HashedPartialTrie;
 
@@ -224,8 +226,10 @@ pub type BlockHeight = u64;
 
 pub use all_stark::AllStark;
 pub use fixed_recursive_verifier::AllRecursiveCircuits;
-pub use generation::segments::{GenerationSegmentData, SegmentDataIterator};
-pub use generation::GenerationInputs;
+pub use generation::segments::{
+    GenerationSegmentData, SegmentDataIterator, SegmentError, SegmentTraceStats,
+};
+pub use generation::{ChainProfile, GenerationInputs};
 pub use starky::config::StarkConfig;
 
 /// Returned type from a `SegmentDataIterator`, needed to prove all segments in
@@ -86,6 +86,10 @@ pub(crate) enum Segment {
     CreatedContracts = 37 << SEGMENT_SCALING_FACTOR,
     /// Blob versioned hashes specified in a type-3 transaction.
     TxnBlobVersionedHashes = 38 << SEGMENT_SCALING_FACTOR,
+    /// Authorization tuples specified in a type-4 transaction.
+    TxnAuthorizations = 39 << SEGMENT_SCALING_FACTOR,
+    /// Scratch space for the BLS12-381 precompiles.
+    Bls12381 = 40 << SEGMENT_SCALING_FACTOR,
 }
 
 // These segments are not zero-initialized.
@@ -97,7 +101,7 @@ pub(crate) const PREINITIALIZED_SEGMENTS_INDICES: [usize; 4] = [
 ];
 
 impl Segment {
-    pub(crate) const COUNT: usize = 39;
+    pub(crate) const COUNT: usize = 41;
 
     /// Unscales this segment by `SEGMENT_SCALING_FACTOR`.
     pub(crate) const fn unscale(&self) -> usize {
@@ -145,6 +149,8 @@ impl Segment {
             Self::TransientStorage,
             Self::CreatedContracts,
             Self::TxnBlobVersionedHashes,
+            Self::TxnAuthorizations,
+            Self::Bls12381,
         ]
     }
 
@@ -190,6 +196,8 @@ impl Segment {
             Segment::TransientStorage => "SEGMENT_TRANSIENT_STORAGE",
             Segment::CreatedContracts => "SEGMENT_CREATED_CONTRACTS",
             Segment::TxnBlobVersionedHashes => "SEGMENT_TXN_BLOB_VERSIONED_HASHES",
+            Segment::TxnAuthorizations => "SEGMENT_TXN_AUTHORIZATIONS",
+            Segment::Bls12381 => "SEGMENT_BLS12_381",
         }
     }
 
@@ -234,6 +242,8 @@ impl Segment {
             Segment::TransientStorage => 256,
             Segment::CreatedContracts => 256,
             Segment::TxnBlobVersionedHashes => 256,
+            Segment::TxnAuthorizations => 256,
+            Segment::Bls12381 => 256,
         }
     }
 }
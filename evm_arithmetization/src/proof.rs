@@ -11,6 +11,7 @@ use starky::lookup::GrandProductChallengeSet;
 use starky::proof::{MultiProof, StarkProofChallenges};
 
 use crate::all_stark::NUM_TABLES;
+use crate::generation::ChainProfile;
 use crate::util::{get_h160, get_h256, get_u256, h2u};
 use crate::witness::state::RegistersState;
 
@@ -52,8 +53,8 @@ pub struct PublicValues {
     pub trie_roots_before: TrieRoots,
     /// Trie hashes after the execution of the local state transition.
     pub trie_roots_after: TrieRoots,
-    /// Address to store the base fee to be burnt: only used when feature
-    /// `cdk_erigon` is active.
+    /// Address to store the base fee to be burnt: only present with
+    /// [`ChainProfile::CdkErigon`].
     pub burn_addr: Option<U256>,
     /// Block metadata: it remains unchanged within a block.
     pub block_metadata: BlockMetadata,
@@ -75,8 +76,9 @@ impl PublicValues {
     /// Public values are always the first public inputs added to the circuit,
     /// so we can start extracting at index 0.
     /// `len_mem_cap` is the length of the `MemBefore` and `MemAfter` caps.
-    pub fn from_public_inputs<F: RichField>(pis: &[F]) -> Self {
-        assert!(pis.len() >= PublicValuesTarget::SIZE);
+    /// `chain_profile` is the profile of the circuit that generated `pis`.
+    pub fn from_public_inputs<F: RichField>(pis: &[F], chain_profile: ChainProfile) -> Self {
+        assert!(pis.len() >= PublicValuesTarget::size(chain_profile));
 
         let mut offset = 0;
         let trie_roots_before =
@@ -85,15 +87,11 @@ impl PublicValues {
         let trie_roots_after =
             TrieRoots::from_public_inputs(&pis[offset..offset + TrieRootsTarget::SIZE]);
         offset += TrieRootsTarget::SIZE;
-        let burn_addr = match cfg!(feature = "cdk_erigon") {
-            true => Some(get_u256(
-                &pis[offset..offset + BurnAddrTarget::get_size()]
-                    .try_into()
-                    .unwrap(),
-            )),
-            false => None,
-        };
-        offset += BurnAddrTarget::get_size();
+        let burn_addr_size = BurnAddrTarget::get_size(chain_profile);
+        let burn_addr = chain_profile
+            .has_burn_addr()
+            .then(|| get_u256(&pis[offset..offset + burn_addr_size].try_into().unwrap()));
+        offset += burn_addr_size;
         let block_metadata =
             BlockMetadata::from_public_inputs(&pis[offset..offset + BlockMetadataTarget::SIZE]);
         offset += BlockMetadataTarget::SIZE;
@@ -513,6 +511,13 @@ impl PublicValuesTarget {
         + BlockHashesTarget::SIZE
         + ExtraBlockDataTarget::SIZE
         + DEFAULT_CAP_HEIGHT * NUM_HASH_OUT_ELTS * 2;
+
+    /// Lower bound on the number of public inputs holding the public values of
+    /// a circuit with the given `chain_profile`.
+    pub(crate) fn size(chain_profile: ChainProfile) -> usize {
+        Self::SIZE + BurnAddrTarget::get_size(chain_profile)
+    }
+
     /// Serializes public value targets.
     pub(crate) fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
         let TrieRootsTarget {
@@ -535,6 +540,14 @@ impl PublicValuesTarget {
         buffer.write_target_array(&transactions_root_after)?;
         buffer.write_target_array(&receipts_root_after)?;
 
+        match self.burn_addr {
+            BurnAddrTarget::BurnAddr(addr) => {
+                buffer.write_bool(true)?;
+                buffer.write_target_array(&addr)?;
+            }
+            BurnAddrTarget::Burnt() => buffer.write_bool(false)?,
+        }
+
         let BlockMetadataTarget {
             block_beneficiary,
             block_timestamp,
@@ -633,7 +646,7 @@ impl PublicValuesTarget {
             receipts_root: buffer.read_target_array()?,
         };
 
-        let burn_addr = match cfg!(feature = "cdk_erigon") {
+        let burn_addr = match buffer.read_bool()? {
             true => BurnAddrTarget::BurnAddr(buffer.read_target_array()?),
             false => BurnAddrTarget::Burnt(),
         };
@@ -708,8 +721,8 @@ impl PublicValuesTarget {
     /// Extracts public value `Target`s from the given public input `Target`s.
     /// Public values are always the first public inputs added to the circuit,
     /// so we can start extracting at index 0.
-    pub(crate) fn from_public_inputs(pis: &[Target]) -> Self {
-        assert!(pis.len() >= Self::SIZE);
+    pub(crate) fn from_public_inputs(pis: &[Target], chain_profile: ChainProfile) -> Self {
+        assert!(pis.len() >= Self::size(chain_profile));
 
         let mut offset = 0;
         let trie_roots_before =
@@ -718,9 +731,12 @@ impl PublicValuesTarget {
         let trie_roots_after =
             TrieRootsTarget::from_public_inputs(&pis[offset..offset + TrieRootsTarget::SIZE]);
         offset += TrieRootsTarget::SIZE;
-        let burn_addr =
-            BurnAddrTarget::from_public_inputs(&pis[offset..offset + BurnAddrTarget::get_size()]);
-        offset += BurnAddrTarget::get_size();
+        let burn_addr_size = BurnAddrTarget::get_size(chain_profile);
+        let burn_addr = BurnAddrTarget::from_public_inputs(
+            &pis[offset..offset + burn_addr_size],
+            chain_profile,
+        );
+        offset += burn_addr_size;
         let block_metadata = BlockMetadataTarget::from_public_inputs(
             &pis[offset..offset + BlockMetadataTarget::SIZE],
         );
@@ -917,7 +933,8 @@ impl TrieRootsTarget {
 }
 
 /// Circuit version of `BurnAddr`.
-/// Address used to store the base fee to be burnt.
+/// Address used to store the base fee to be burnt. Only circuits of a
+/// [`ChainProfile`] with a burn address have the `BurnAddr` variant.
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub enum BurnAddrTarget {
     BurnAddr([Target; 8]),
@@ -925,8 +942,10 @@ pub enum BurnAddrTarget {
 }
 
 impl BurnAddrTarget {
-    pub fn get_size() -> usize {
-        match cfg!(feature = "cdk_erigon") {
+    /// Returns the number of public inputs used by the burn address of
+    /// circuits with the given `chain_profile`.
+    pub fn get_size(chain_profile: ChainProfile) -> usize {
+        match chain_profile.has_burn_addr() {
             true => 8,
             false => 0,
         }
@@ -934,8 +953,8 @@ impl BurnAddrTarget {
 
     /// Extracts the burn address from the provided public input
     /// `Target`s. The provided `pis` should start with the burn address.
-    pub(crate) fn from_public_inputs(pis: &[Target]) -> Self {
-        match cfg!(feature = "cdk_erigon") {
+    pub(crate) fn from_public_inputs(pis: &[Target], chain_profile: ChainProfile) -> Self {
+        match chain_profile.has_burn_addr() {
             true => BurnAddrTarget::BurnAddr(pis[0..8].try_into().unwrap()),
             false => BurnAddrTarget::Burnt(),
         }
@@ -943,84 +962,71 @@ impl BurnAddrTarget {
 
     /// If `condition`, returns the burn address in `ba0`,
     /// otherwise returns the burn address in `ba1`.
-    /// This is a no-op if `cdk_erigon` feature is not activated.  
-    ///  
-    /// This will panic if the `cdk_erigon` is activated and not both
-    /// `BurnAddrTarget`s are `BurnAddr` variants.
+    /// This is a no-op if both are `Burnt` variants.
+    ///
+    /// This will panic if `ba0` and `ba1` are not of the same variant.
     pub(crate) fn select<F: RichField + Extendable<D>, const D: usize>(
         builder: &mut CircuitBuilder<F, D>,
         condition: BoolTarget,
         ba0: Self,
         ba1: Self,
     ) -> Self {
-        match cfg!(feature = "cdk_erigon") {
-            // If the `cdk_erigon` feature is activated, both `ba0` and `ba1` should be of type
-            // `BurnAddr`.
-            true => match (ba0, ba1) {
-                (BurnAddrTarget::BurnAddr(a0), BurnAddrTarget::BurnAddr(a1)) => {
-                    BurnAddrTarget::BurnAddr(core::array::from_fn(|i| {
-                        builder.select(condition, a0[i], a1[i])
-                    }))
-                }
-                _ => panic!("We should have already set an address (or U256::MAX) before."),
-            },
-            false => BurnAddrTarget::Burnt(),
+        match (ba0, ba1) {
+            (BurnAddrTarget::BurnAddr(a0), BurnAddrTarget::BurnAddr(a1)) => {
+                BurnAddrTarget::BurnAddr(core::array::from_fn(|i| {
+                    builder.select(condition, a0[i], a1[i])
+                }))
+            }
+            (BurnAddrTarget::Burnt(), BurnAddrTarget::Burnt()) => BurnAddrTarget::Burnt(),
+            _ => panic!("Cannot mix public values of different chain profiles."),
         }
     }
 
     /// Connects the burn address in `ba0` to the burn address in `ba1`.
-    /// This is a no-op if `cdk_erigon` feature is not activated.  
-    ///  
-    /// This will panic if the `cdk_erigon` is activated and not both
-    /// `BurnAddrTarget`s are `BurnAddr` variants.
+    /// This is a no-op if both are `Burnt` variants.
+    ///
+    /// This will panic if `ba0` and `ba1` are not of the same variant.
     pub(crate) fn connect<F: RichField + Extendable<D>, const D: usize>(
         builder: &mut CircuitBuilder<F, D>,
         ba0: Self,
         ba1: Self,
     ) {
-        // There only are targets to connect if there is a burn address, i.e. when the
-        // `cdk_erigon` feature is active.
-        if cfg!(feature = "cdk_erigon") == true {
-            // If the `cdk_erigon` feature is activated, both `ba0` and `ba1` should be of
-            // type `BurnAddr`.
-            match (ba0, ba1) {
-                (BurnAddrTarget::BurnAddr(a0), BurnAddrTarget::BurnAddr(a1)) => {
-                    for i in 0..BurnAddrTarget::get_size() {
-                        builder.connect(a0[i], a1[i]);
-                    }
+        match (ba0, ba1) {
+            (BurnAddrTarget::BurnAddr(a0), BurnAddrTarget::BurnAddr(a1)) => {
+                for i in 0..8 {
+                    builder.connect(a0[i], a1[i]);
                 }
-                _ => panic!("We should have already set an address (or U256::MAX) before."),
             }
+            (BurnAddrTarget::Burnt(), BurnAddrTarget::Burnt()) => {}
+            _ => panic!("Cannot mix public values of different chain profiles."),
         }
     }
 
     /// If `condition`, asserts that `ba0 == ba1`.
-    /// This is a no-op if `cdk_erigon` feature is not activated.  
-    ///  
-    /// This will panic if the `cdk_erigon` is activated and not both
-    /// `BurnAddrTarget` are `BurnAddr` variants.
+    /// This is a no-op if both are `Burnt` variants.
+    ///
+    /// This will panic if `ba0` and `ba1` are not of the same variant.
     pub(crate) fn conditional_assert_eq<F: RichField + Extendable<D>, const D: usize>(
         builder: &mut CircuitBuilder<F, D>,
         condition: BoolTarget,
         ba0: Self,
         ba1: Self,
     ) {
-        if cfg!(feature = "cdk_erigon") {
-            match (ba0, ba1) {
-                (
-                    BurnAddrTarget::BurnAddr(addr_targets_0),
-                    BurnAddrTarget::BurnAddr(addr_targets_1),
-                ) => {
-                    for i in 0..BurnAddrTarget::get_size() {
-                        builder.conditional_assert_eq(
-                            condition.target,
-                            addr_targets_0[i],
-                            addr_targets_1[i],
-                        )
-                    }
+        match (ba0, ba1) {
+            (
+                BurnAddrTarget::BurnAddr(addr_targets_0),
+                BurnAddrTarget::BurnAddr(addr_targets_1),
+            ) => {
+                for i in 0..8 {
+                    builder.conditional_assert_eq(
+                        condition.target,
+                        addr_targets_0[i],
+                        addr_targets_1[i],
+                    )
                 }
-                _ => panic!("There should be an address set in cdk_erigon."),
             }
+            (BurnAddrTarget::Burnt(), BurnAddrTarget::Burnt()) => {}
+            _ => panic!("Cannot mix public values of different chain profiles."),
         }
     }
 }
@@ -23,7 +23,7 @@ use starky::stark::Stark;
 use crate::all_stark::{AllStark, Table, NUM_TABLES};
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::generation::segments::GenerationSegmentData;
-use crate::generation::{generate_traces, GenerationInputs, TrimmedGenerationInputs};
+use crate::generation::{generate_traces, ChainProfile, GenerationInputs, TrimmedGenerationInputs};
 use crate::get_challenges::observe_public_values;
 use crate::proof::{AllProof, MemCap, PublicValues, DEFAULT_CAP_LEN};
 
@@ -40,8 +40,11 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    if inputs.burn_addr.is_some() && !cfg!(feature = "cdk_erigon") {
-        log::warn!("The burn address in the GenerationInputs will be ignored, as the `cdk_erigon` feature is not activated.")
+    if inputs.burn_addr.is_some() && !inputs.chain_profile.has_burn_addr() {
+        log::warn!(
+            "The burn address in the GenerationInputs will be ignored, as the {} chain profile does not use one.",
+            inputs.chain_profile
+        )
     }
     // Sanity check on the provided config
     assert_eq!(DEFAULT_CAP_LEN, 1 << config.fri_config.cap_height);
@@ -61,6 +64,7 @@ where
         config,
         traces,
         &mut public_values,
+        inputs.chain_profile,
         timing,
         abort_signal,
     )?;
@@ -74,6 +78,7 @@ pub(crate) fn prove_with_traces<F, C, const D: usize>(
     config: &StarkConfig,
     trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES],
     public_values: &mut PublicValues,
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))] chain_profile: ChainProfile,
     timing: &mut TimingTree,
     abort_signal: Option<Arc<AtomicBool>>,
 ) -> Result<AllProof<F, C, D>>
@@ -192,7 +197,7 @@ where
         let mut extra_values = HashMap::new();
         extra_values.insert(
             *Table::Memory,
-            get_memory_extra_looking_values(public_values),
+            get_memory_extra_looking_values(public_values, chain_profile),
         );
         check_ctls(
             &trace_poly_values,
@@ -457,36 +462,95 @@ pub fn check_abort_signal(abort_signal: Option<Arc<AtomicBool>>) -> Result<()> {
 
 /// A utility module designed to test witness generation externally.
 pub mod testing {
+    use ethereum_types::{H256, U256};
+    use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+
     use super::*;
     use crate::{
-        cpu::kernel::interpreter::Interpreter,
+        cpu::kernel::{
+            constants::global_metadata::GlobalMetadata, interpreter::Interpreter,
+            profiler::KernelProfile, tracer::TransactionTrace,
+        },
         generation::{
             output_debug_tries,
             segments::{SegmentDataIterator, SegmentError},
             state::State,
+            trie_extractor::{get_receipt_trie, get_txn_trie},
+            with_state_mismatch_report,
         },
+        util::u256_to_usize,
     };
 
+    /// The values of a block which are only known once its transactions and
+    /// withdrawals have been executed.
+    #[derive(Clone, Debug)]
+    pub struct ExecutionOutcome {
+        pub gas_used: U256,
+        pub transactions_root: H256,
+        pub receipts_root: H256,
+    }
+
     /// Simulates the zkEVM CPU execution.
     /// It does not generate any trace or proof of correct state transition.
     pub fn simulate_execution<F: RichField>(inputs: GenerationInputs) -> Result<()> {
-        if inputs.burn_addr.is_some() && !cfg!(feature = "cdk_erigon") {
-            log::warn!("The burn address in the GenerationInputs will be ignored, as the `cdk_erigon` feature is not activated.")
+        if inputs.burn_addr.is_some() && !inputs.chain_profile.has_burn_addr() {
+            log::warn!(
+                "The burn address in the GenerationInputs will be ignored, as the {} chain profile does not use one.",
+                inputs.chain_profile
+            )
         }
         let initial_stack = vec![];
         let initial_offset = KERNEL.global_labels["init"];
         let mut interpreter: Interpreter<F> =
             Interpreter::new_with_generation_inputs(initial_offset, initial_stack, &inputs, None);
-        let result = interpreter.run();
-
-        if result.is_err() {
+        if let Err(err) = interpreter.run() {
             output_debug_tries(interpreter.get_generation_state())?;
+            return Err(with_state_mismatch_report(
+                interpreter.get_generation_state(),
+                err,
+            ));
         }
 
-        result?;
         Ok(())
     }
 
+    /// Simulates the zkEVM CPU execution of the transactions and withdrawals
+    /// of `inputs`, stopping right before the final checks of the kernel, and
+    /// returns the gas used along with the resulting transaction and receipt
+    /// trie roots. This allows completing inputs for which only the final
+    /// state root is known, like those of the `GeneralStateTests`.
+    pub fn simulate_execution_outcome<F: RichField>(
+        inputs: GenerationInputs,
+    ) -> Result<ExecutionOutcome> {
+        let initial_offset = KERNEL.global_labels["init"];
+        let mut interpreter: Interpreter<F> =
+            Interpreter::new_with_generation_inputs(initial_offset, vec![], &inputs, None);
+        interpreter.halt_offsets = vec![KERNEL.global_labels["perform_final_checks"]];
+        interpreter.run()?;
+
+        let state = interpreter.get_generation_state();
+        // stack: cum_gas, txn_counter, num_nibbles, txn_nb
+        let gas_used = state.stack()[0];
+        let trie_ptr = |field| {
+            u256_to_usize(state.memory.read_global_metadata(field))
+                .map_err(|_| anyhow!("{field:?} pointer is too large to fit in a usize."))
+        };
+        let transactions_trie: HashedPartialTrie = get_txn_trie(
+            &state.memory,
+            trie_ptr(GlobalMetadata::TransactionTrieRoot)?,
+        )
+        .map_err(|e| anyhow!("Failed to extract the transactions trie: {e:?}"))?;
+        let receipts_trie: HashedPartialTrie =
+            get_receipt_trie(&state.memory, trie_ptr(GlobalMetadata::ReceiptTrieRoot)?)
+                .map_err(|e| anyhow!("Failed to extract the receipts trie: {e:?}"))?;
+
+        Ok(ExecutionOutcome {
+            gas_used,
+            transactions_root: transactions_trie.hash(),
+            receipts_root: receipts_trie.hash(),
+        })
+    }
+
     pub fn prove_all_segments<F, C, const D: usize>(
         all_stark: &AllStark<F, D>,
         config: &StarkConfig,
@@ -535,4 +599,46 @@ pub mod testing {
 
         Ok(())
     }
+
+    /// Simulates the zkEVM CPU execution of all the segments, like
+    /// [`simulate_execution_all_segments`], and returns the profile of the
+    /// execution, attributing its costs to the kernel routines being executed.
+    pub fn profile_execution_all_segments<F>(
+        inputs: GenerationInputs,
+        max_cpu_len_log: usize,
+    ) -> Result<KernelProfile>
+    where
+        F: RichField,
+    {
+        let mut segment_data_iterator =
+            SegmentDataIterator::<F>::new(&inputs, Some(max_cpu_len_log)).with_profiling();
+        for segment in &mut segment_data_iterator {
+            segment.map_err(|e| anyhow::format_err!(e))?;
+        }
+
+        Ok(segment_data_iterator
+            .into_profile()
+            .expect("Profiling is enabled"))
+    }
+
+    /// Simulates the zkEVM CPU execution of all the segments, like
+    /// [`simulate_execution_all_segments`], and returns the EIP-3155 trace of
+    /// the user code executed by each transaction.
+    pub fn trace_execution_all_segments<F>(
+        inputs: GenerationInputs,
+        max_cpu_len_log: usize,
+    ) -> Result<Vec<TransactionTrace>>
+    where
+        F: RichField,
+    {
+        let mut segment_data_iterator =
+            SegmentDataIterator::<F>::new(&inputs, Some(max_cpu_len_log)).with_tracing();
+        for segment in &mut segment_data_iterator {
+            segment.map_err(|e| anyhow::format_err!(e))?;
+        }
+
+        Ok(segment_data_iterator
+            .into_traces()
+            .expect("Tracing is enabled"))
+    }
 }
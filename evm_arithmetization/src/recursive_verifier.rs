@@ -32,6 +32,7 @@ use starky::stark::Stark;
 use crate::all_stark::Table;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::generation::ChainProfile;
 use crate::memory::segments::Segment;
 use crate::memory::VALUE_LIMBS;
 use crate::proof::{
@@ -332,6 +333,7 @@ pub(crate) fn add_common_recursion_gates<F: RichField + Extendable<D>, const D:
 pub(crate) fn get_memory_extra_looking_sum_circuit<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut CircuitBuilder<F, D>,
     public_values: &PublicValuesTarget,
+    chain_profile: ChainProfile,
     challenge: GrandProductChallenge<Target>,
 ) -> Target {
     let mut sum = builder.zero();
@@ -428,11 +430,12 @@ pub(crate) fn get_memory_extra_looking_sum_circuit<F: RichField + Extendable<D>,
         );
     });
 
-    #[cfg(feature = "cdk_erigon")]
     {
+        // Chain profiles without a burn address still write `U256::MAX` in the
+        // kernel metadata, so that the base fee is directly burnt.
         let burn_addr = match public_values.burn_addr {
             BurnAddrTarget::BurnAddr(addr) => addr,
-            BurnAddrTarget::Burnt() => panic!("There should be an address set in cdk_erigon."),
+            BurnAddrTarget::Burnt() => u256_limbs::<F>(U256::MAX).map(|l| builder.constant(l)),
         };
         sum = add_data_write(
             builder,
@@ -444,6 +447,29 @@ pub(crate) fn get_memory_extra_looking_sum_circuit<F: RichField + Extendable<D>,
         );
     }
 
+    {
+        // The set of active precompiles is fixed by the chain profile of the
+        // circuit.
+        let p256_verify_enabled = builder.constant(F::from_bool(chain_profile.has_p256_verify()));
+        sum = add_data_write(
+            builder,
+            challenge,
+            sum,
+            metadata_segment,
+            GlobalMetadata::P256VerifyEnabled.unscale(),
+            &[p256_verify_enabled],
+        );
+        let bls12_381_enabled = builder.constant(F::from_bool(chain_profile.has_bls12_381()));
+        sum = add_data_write(
+            builder,
+            challenge,
+            sum,
+            metadata_segment,
+            GlobalMetadata::Bls12381Enabled.unscale(),
+            &[bls12_381_enabled],
+        );
+    }
+
     block_fields_arrays.map(|(field, targets)| {
         sum = add_data_write(
             builder,
@@ -648,10 +674,11 @@ pub(crate) fn add_virtual_public_values_public_input<
     const D: usize,
 >(
     builder: &mut CircuitBuilder<F, D>,
+    chain_profile: ChainProfile,
 ) -> PublicValuesTarget {
     let trie_roots_before = add_virtual_trie_roots_public_input(builder);
     let trie_roots_after = add_virtual_trie_roots_public_input(builder);
-    let burn_addr = add_virtual_burn_addr(builder);
+    let burn_addr = add_virtual_burn_addr(builder, chain_profile);
     let block_metadata = add_virtual_block_metadata_public_input(builder);
     let block_hashes = add_virtual_block_hashes_public_input(builder);
     let extra_block_data = add_virtual_extra_block_data_public_input(builder);
@@ -681,8 +708,9 @@ pub(crate) fn add_virtual_public_values_public_input<
 
 pub(crate) fn add_virtual_burn_addr<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut CircuitBuilder<F, D>,
+    chain_profile: ChainProfile,
 ) -> BurnAddrTarget {
-    match cfg!(feature = "cdk_erigon") {
+    match chain_profile.has_burn_addr() {
         true => BurnAddrTarget::BurnAddr(builder.add_virtual_public_input_arr()),
         false => BurnAddrTarget::Burnt(),
     }
@@ -846,13 +874,10 @@ where
         &public_values_target.extra_block_data,
         &public_values.extra_block_data,
     )?;
-    #[cfg(feature = "cdk_erigon")]
     set_burn_addr_target(
         witness,
         &public_values_target.burn_addr,
-        public_values
-            .burn_addr
-            .expect("There should be an address set in cdk_erigon."),
+        public_values.burn_addr,
     )?;
     set_registers_target(
         witness,
@@ -979,22 +1004,22 @@ pub(crate) fn set_trie_roots_target<F, W, const D: usize>(
     }
 }
 
-#[cfg(feature = "cdk_erigon")]
 pub(crate) fn set_burn_addr_target<F, W, const D: usize>(
     witness: &mut W,
     burn_addr_target: &BurnAddrTarget,
-    burn_addr: U256,
+    burn_addr: Option<U256>,
 ) -> Result<(), ProgramError>
 where
     F: RichField + Extendable<D>,
     W: Witness<F>,
 {
-    match burn_addr_target {
-        BurnAddrTarget::BurnAddr(addr_target) => {
+    match (burn_addr_target, burn_addr) {
+        (BurnAddrTarget::BurnAddr(addr_target), Some(burn_addr)) => {
             let burn_addr_limbs: [F; 8] = u256_limbs::<F>(burn_addr);
             witness.set_target_arr(addr_target, &burn_addr_limbs);
         }
-        BurnAddrTarget::Burnt() => panic!("There should be an address target set in cdk_erigon."),
+        (BurnAddrTarget::Burnt(), None) => {}
+        _ => panic!("The public values and their targets have different chain profiles."),
     }
 
     Ok(())
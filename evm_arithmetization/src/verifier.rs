@@ -18,6 +18,7 @@ use starky::verifier::verify_stark_proof_with_challenges;
 use crate::all_stark::{AllStark, Table, NUM_TABLES};
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::generation::ChainProfile;
 use crate::memory::segments::Segment;
 use crate::memory::VALUE_LIMBS;
 use crate::proof::{AllProof, AllProofChallenges, PublicValues};
@@ -120,6 +121,7 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     all_stark: &AllStark<F, D>,
     all_proof: AllProof<F, C, D>,
     config: &StarkConfig,
+    chain_profile: ChainProfile,
     is_initial: bool,
 ) -> Result<()> {
     let AllProofChallenges {
@@ -241,7 +243,13 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
 
     // Memory
     extra_looking_sums[Table::Memory as usize] = (0..config.num_challenges)
-        .map(|i| get_memory_extra_looking_sum(&public_values, ctl_challenges.challenges[i]))
+        .map(|i| {
+            get_memory_extra_looking_sum(
+                &public_values,
+                chain_profile,
+                ctl_challenges.challenges[i],
+            )
+        })
         .collect_vec();
 
     verify_cross_table_lookups::<F, D, NUM_TABLES>(
@@ -261,6 +269,7 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
 /// - trie roots writes.
 pub(crate) fn get_memory_extra_looking_sum<F, const D: usize>(
     public_values: &PublicValues,
+    chain_profile: ChainProfile,
     challenge: GrandProductChallenge<F>,
 ) -> F
 where
@@ -274,12 +283,17 @@ where
             GlobalMetadata::BlockBeneficiary,
             U256::from_big_endian(&public_values.block_metadata.block_beneficiary.0),
         ),
-        #[cfg(feature = "cdk_erigon")]
         (
             GlobalMetadata::BurnAddr,
-            public_values
-                .burn_addr
-                .expect("There should be an address set in cdk_erigon."),
+            public_values.burn_addr.unwrap_or(U256::MAX),
+        ),
+        (
+            GlobalMetadata::P256VerifyEnabled,
+            U256::from(chain_profile.has_p256_verify() as u8),
+        ),
+        (
+            GlobalMetadata::Bls12381Enabled,
+            U256::from(chain_profile.has_bls12_381() as u8),
         ),
         (
             GlobalMetadata::BlockTimestamp,
@@ -463,13 +477,20 @@ pub mod testing {
         all_stark: &AllStark<F, D>,
         all_proofs: &[AllProof<F, C, D>],
         config: &StarkConfig,
+        chain_profile: ChainProfile,
     ) -> Result<()> {
         assert!(!all_proofs.is_empty());
 
-        verify_proof(all_stark, all_proofs[0].clone(), config, true)?;
+        verify_proof(
+            all_stark,
+            all_proofs[0].clone(),
+            config,
+            chain_profile,
+            true,
+        )?;
 
         for all_proof in &all_proofs[1..] {
-            verify_proof(all_stark, all_proof.clone(), config, false)?;
+            verify_proof(all_stark, all_proof.clone(), config, chain_profile, false)?;
         }
 
         Ok(())
@@ -484,6 +505,7 @@ pub(crate) mod debug_utils {
     /// are necessary to correctly check the MemoryStark CTL.
     pub(crate) fn get_memory_extra_looking_values<F, const D: usize>(
         public_values: &PublicValues,
+        chain_profile: ChainProfile,
     ) -> Vec<Vec<F>>
     where
         F: RichField + Extendable<D>,
@@ -494,6 +516,18 @@ pub(crate) mod debug_utils {
                 GlobalMetadata::BlockBeneficiary,
                 U256::from_big_endian(&public_values.block_metadata.block_beneficiary.0),
             ),
+            (
+                GlobalMetadata::BurnAddr,
+                public_values.burn_addr.unwrap_or(U256::MAX),
+            ),
+            (
+                GlobalMetadata::P256VerifyEnabled,
+                U256::from(chain_profile.has_p256_verify() as u8),
+            ),
+            (
+                GlobalMetadata::Bls12381Enabled,
+                U256::from(chain_profile.has_bls12_381() as u8),
+            ),
             (
                 GlobalMetadata::BlockTimestamp,
                 public_values.block_metadata.block_timestamp,
@@ -1,6 +1,12 @@
+use std::fmt;
+
 use ethereum_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::witness::state::RegistersState;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ProgramError {
     OutOfGas,
     InvalidOpcode,
@@ -19,14 +25,14 @@ pub enum ProgramError {
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum MemoryError {
     ContextTooLarge { context: U256 },
     SegmentTooLarge { segment: U256 },
     VirtTooLarge { virt: U256 },
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ProverInputError {
     OutOfMptData,
     OutOfRlpData,
@@ -42,3 +48,84 @@ pub enum ProverInputError {
     KzgEvalFailure(String),
     Unimplemented,
 }
+
+/// The point of the execution at which an error occurred.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorLocation {
+    /// The index within its block of the transaction being executed, if any.
+    pub txn_index: Option<U256>,
+    pub context: usize,
+    pub program_counter: usize,
+    pub is_kernel: bool,
+    /// The closest kernel label at or before `program_counter`, followed by
+    /// its backtrace in the assembly source if known, in kernel mode.
+    pub kernel_symbol: Option<String>,
+}
+
+impl ErrorLocation {
+    pub(crate) fn new(
+        registers: &RegistersState,
+        txn_number_before: U256,
+        next_txn_index: usize,
+    ) -> Self {
+        Self {
+            txn_index: next_txn_index
+                .checked_sub(1)
+                .map(|idx| txn_number_before + idx),
+            context: registers.context,
+            program_counter: registers.program_counter,
+            is_kernel: registers.is_kernel,
+            kernel_symbol: registers
+                .is_kernel
+                .then(|| KERNEL.symbolize(registers.program_counter)),
+        }
+    }
+}
+
+impl fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (label, backtrace) = match self.kernel_symbol.as_deref() {
+            Some(symbol) => symbol
+                .split_once('\n')
+                .map_or((Some(symbol), None), |(label, bt)| (Some(label), Some(bt))),
+            None => (None, None),
+        };
+        match label {
+            Some(label) => write!(f, "kernel pc={label}")?,
+            None if self.is_kernel => write!(f, "kernel pc={}", self.program_counter)?,
+            None => write!(f, "user pc={}", self.program_counter)?,
+        }
+        write!(f, ", context={}", self.context)?;
+        if let Some(txn_index) = self.txn_index {
+            write!(f, ", txn={txn_index}")?;
+        }
+        if let Some(backtrace) = backtrace {
+            write!(f, "\n{backtrace}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The cause of a failed execution.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ExecutionErrorKind {
+    /// An instruction failed in kernel mode, or failed in user mode with an
+    /// error that cannot be turned into an exception.
+    Program(ProgramError),
+    /// Generating the exception raised by an instruction failed.
+    ExceptionHandling(ProgramError),
+    /// The final state trie doesn't hash to the expected state root.
+    StateRootMismatch,
+    /// The generation was stopped through its abort signal.
+    Aborted,
+    /// Any other failure, e.g. an invalid memory initialization.
+    Other,
+}
+
+/// An error raised during witness generation, along with where it occurred.
+#[derive(Clone, Debug, Serialize, Deserialize, thiserror::Error)]
+#[error("{kind:?} at {location}")]
+pub struct ExecutionError {
+    pub kind: ExecutionErrorKind,
+    pub location: ErrorLocation,
+}
@@ -1,4 +1,4 @@
-pub(crate) mod errors;
+pub mod errors;
 pub(crate) mod gas;
 pub(crate) mod memory;
 pub(crate) mod operation;
@@ -16,6 +16,29 @@ use crate::memory_continuation::memory_continuation_stark::mem_before_values_to_
 use crate::witness::memory::MemoryOp;
 use crate::{arithmetic, keccak, keccak_sponge, logic};
 
+/// Returns the number of rows of the arithmetic table taken by an operation.
+pub(crate) const fn arithmetic_op_rows(op: &Operation) -> usize {
+    match op {
+        Operation::TernaryOperation { .. } => 2,
+        Operation::BinaryOperation { operator, .. } => match operator {
+            BinaryOperator::Div
+            | BinaryOperator::Mod
+            | BinaryOperator::AddFp254
+            | BinaryOperator::SubFp254
+            | BinaryOperator::MulFp254
+            | BinaryOperator::Shr => 2,
+            _ => 1,
+        },
+        Operation::RangeCheckOperation { .. } => 1,
+    }
+}
+
+/// Returns the number of rows of the keccak sponge table taken by hashing
+/// `input_len` bytes, which is also the number of keccak permutations applied.
+pub(crate) const fn keccak_sponge_op_rows(input_len: usize) -> usize {
+    input_len / keccak_sponge::columns::KECCAK_RATE_BYTES + 1
+}
+
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct TraceCheckpoint {
     pub(self) arithmetic_len: usize,
@@ -55,30 +78,14 @@ impl<T: Copy> Traces<T> {
     //  Uses a `TraceCheckPoint` as return object for convenience.
     pub(crate) fn get_lengths(&self) -> TraceCheckpoint {
         TraceCheckpoint {
-            arithmetic_len: self
-                .arithmetic_ops
-                .iter()
-                .map(|op| match op {
-                    Operation::TernaryOperation { .. } => 2,
-                    Operation::BinaryOperation { operator, .. } => match operator {
-                        BinaryOperator::Div
-                        | BinaryOperator::Mod
-                        | BinaryOperator::AddFp254
-                        | BinaryOperator::SubFp254
-                        | BinaryOperator::MulFp254
-                        | BinaryOperator::Shr => 2,
-                        _ => 1,
-                    },
-                    Operation::RangeCheckOperation { .. } => 1,
-                })
-                .sum(),
+            arithmetic_len: self.arithmetic_ops.iter().map(arithmetic_op_rows).sum(),
             byte_packing_len: self.byte_packing_ops.len(),
             cpu_len: self.cpu.len(),
             keccak_len: self.keccak_inputs.len() * keccak::keccak_stark::NUM_ROUNDS,
             keccak_sponge_len: self
                 .keccak_sponge_ops
                 .iter()
-                .map(|op| op.input.len() / keccak_sponge::columns::KECCAK_RATE_BYTES + 1)
+                .map(|op| keccak_sponge_op_rows(op.input.len()))
                 .sum(),
             logic_len: self.logic_ops.len(),
             // This is technically a lower-bound, as we may fill gaps,
@@ -127,7 +134,7 @@ impl<T: Copy> Traces<T> {
         mut trace_lengths: TraceCheckpoint,
         config: &StarkConfig,
         timing: &mut TimingTree,
-    ) -> [Vec<PolynomialValues<T>>; NUM_TABLES]
+    ) -> ([Vec<PolynomialValues<T>>; NUM_TABLES], [usize; NUM_TABLES])
     where
         T: RichField + Extendable<D>,
     {
@@ -211,7 +218,7 @@ impl<T: Copy> Traces<T> {
             final_values.len()
         );
 
-        [
+        let tables = [
             arithmetic_trace,
             byte_packing_trace,
             cpu_trace,
@@ -221,7 +228,20 @@ impl<T: Copy> Traces<T> {
             memory_trace,
             mem_before_trace,
             mem_after_trace,
-        ]
+        ];
+        let unpadded_lengths = [
+            trace_lengths.arithmetic_len,
+            trace_lengths.byte_packing_len,
+            trace_lengths.cpu_len,
+            trace_lengths.keccak_len,
+            trace_lengths.keccak_sponge_len,
+            trace_lengths.logic_len,
+            trace_lengths.memory_len,
+            mem_before_values.len(),
+            final_values.len(),
+        ];
+
+        (tables, unpadded_lengths)
     }
 }
 
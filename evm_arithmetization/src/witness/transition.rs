@@ -12,7 +12,7 @@ use crate::cpu::stack::{
 };
 use crate::generation::state::State;
 use crate::memory::segments::Segment;
-use crate::witness::errors::ProgramError;
+use crate::witness::errors::{ExecutionErrorKind, ProgramError};
 use crate::witness::gas::gas_to_charge;
 use crate::witness::memory::MemoryAddress;
 use crate::witness::operation::*;
@@ -143,7 +143,7 @@ pub(crate) fn decode(registers: RegistersState, opcode: u8) -> Result<Operation,
         (0xa5, true) => {
             log::warn!(
                 "Kernel panic at {}",
-                KERNEL.offset_name(registers.program_counter),
+                KERNEL.symbolize(registers.program_counter),
             );
             Err(ProgramError::KernelPanic)
         }
@@ -313,7 +313,7 @@ where
         let (row, _) = self.base_row();
 
         generate_exception(EXC_STOP_CODE, self, row)
-            .map_err(|e| anyhow::anyhow!("Exception handling failed with error {:?}", e))?;
+            .map_err(|e| self.execution_error(ExecutionErrorKind::ExceptionHandling(e)))?;
 
         self.apply_ops(checkpoint);
         Ok(())
@@ -4,7 +4,7 @@ use std::time::Duration;
 
 use ethereum_types::{Address, BigEndianHash, H256};
 use evm_arithmetization::generation::mpt::{AccountRlp, LegacyReceiptRlp};
-use evm_arithmetization::generation::TrieInputs;
+use evm_arithmetization::generation::{ChainProfile, TrieInputs};
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
 use evm_arithmetization::prover::testing::prove_all_segments;
 use evm_arithmetization::testing_utils::{
@@ -188,10 +188,12 @@ fn get_generation_inputs() -> GenerationInputs {
     GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals: vec![],
         global_exit_roots: vec![],
         tries: tries_before,
         trie_roots_after,
+        expected_tries_after: None,
         contract_code,
         block_metadata,
         checkpoint_state_trie_root: state_trie_before.hash(),
@@ -228,5 +230,5 @@ fn add11_yml() -> anyhow::Result<()> {
 
     timing.filter(Duration::from_millis(100)).print();
 
-    verify_all_proofs(&all_stark, &proofs, &config)
+    verify_all_proofs(&all_stark, &proofs, &config, ChainProfile::L1)
 }
@@ -3,7 +3,7 @@ use std::time::Duration;
 
 use ethereum_types::{Address, BigEndianHash, H160, H256, U256};
 use evm_arithmetization::generation::mpt::{AccountRlp, LegacyReceiptRlp, LogRlp};
-use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::generation::{ChainProfile, GenerationInputs, TrieInputs};
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
 use evm_arithmetization::prover::testing::prove_all_segments;
 use evm_arithmetization::testing_utils::{
@@ -182,10 +182,12 @@ fn test_erc20() -> anyhow::Result<()> {
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals: vec![],
         global_exit_roots: vec![],
         tries: tries_before,
         trie_roots_after,
+        expected_tries_after: None,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
         block_metadata,
@@ -212,7 +214,7 @@ fn test_erc20() -> anyhow::Result<()> {
 
     timing.filter(Duration::from_millis(100)).print();
 
-    verify_all_proofs(&all_stark, &proofs, &config)
+    verify_all_proofs(&all_stark, &proofs, &config, ChainProfile::L1)
 }
 
 fn giver_bytecode() -> Vec<u8> {
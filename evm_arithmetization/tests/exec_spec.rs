@@ -0,0 +1,628 @@
+//! A conformance runner for the fixtures of the Ethereum execution spec tests
+//! (<https://github.com/ethereum/tests> and
+//! <https://github.com/ethereum/execution-spec-tests>).
+//!
+//! The runner looks for JSON fixtures under the directory given by the
+//! `EXEC_SPEC_FIXTURES_DIR` environment variable, builds [`GenerationInputs`]
+//! out of each test case and simulates its execution with
+//! [`simulate_execution_all_segments`]. The kernel checks the final state,
+//! transaction and receipt trie roots against the expected ones, and a
+//! diverging post-state root is reported as such for each test case.
+//!
+//! `BlockchainTests` fixtures, including the
+//! `BlockchainTests/GeneralStateTests` fillings, are run as a single block.
+//! Raw `GeneralStateTests` fixtures only carry the post-state root of each
+//! transaction, so the gas used and the transaction and receipt trie roots of
+//! their block are first obtained with [`simulate_execution_outcome`], before
+//! simulating the execution against the expected post-state root.
+//!
+//! Results are reported per fork. The test is ignored by default and can be
+//! run with:
+//!
+//! ```text
+//! EXEC_SPEC_FIXTURES_DIR=/path/to/fixtures \
+//!     cargo test --release --test exec_spec -- --ignored --nocapture
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use ethereum_types::{Address, H256, U256};
+use evm_arithmetization::generation::mpt::AccountRlp;
+use evm_arithmetization::generation::{ChainProfile, TrieInputs};
+use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
+use evm_arithmetization::prover::testing::{
+    simulate_execution_all_segments, simulate_execution_outcome,
+};
+use evm_arithmetization::testing_utils::{
+    create_account_storage, init_logger, BEACON_ROOTS_CONTRACT_ADDRESS_HASHED,
+};
+use evm_arithmetization::witness::errors::ExecutionErrorKind;
+use evm_arithmetization::{GenerationInputs, Node, SegmentError};
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use serde::Deserialize;
+
+type F = GoldilocksField;
+
+/// Environment variable pointing to the directory containing the fixtures.
+const FIXTURES_DIR_ENV: &str = "EXEC_SPEC_FIXTURES_DIR";
+
+/// The forks whose semantics are implemented by the kernel. Test cases
+/// targeting any other fork are skipped.
+const SUPPORTED_FORKS: [&str; 1] = ["Cancun"];
+
+/// The chain id used by all execution spec tests.
+const FIXTURES_CHAIN_ID: u64 = 1;
+
+const MAX_CPU_LEN_LOG: usize = 20;
+
+/// An account of the `pre` allocation of a fixture.
+#[derive(Deserialize)]
+struct FixtureAccount {
+    balance: String,
+    code: String,
+    nonce: String,
+    #[serde(default)]
+    storage: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureHeader {
+    coinbase: Address,
+    state_root: H256,
+    transactions_trie: H256,
+    receipt_trie: H256,
+    bloom: String,
+    difficulty: String,
+    number: String,
+    gas_limit: String,
+    gas_used: String,
+    timestamp: String,
+    mix_hash: H256,
+    hash: H256,
+    #[serde(default)]
+    base_fee_per_gas: Option<String>,
+    #[serde(default)]
+    blob_gas_used: Option<String>,
+    #[serde(default)]
+    excess_blob_gas: Option<String>,
+    #[serde(default)]
+    parent_beacon_block_root: Option<H256>,
+}
+
+#[derive(Deserialize)]
+struct FixtureWithdrawal {
+    address: Address,
+    /// The withdrawn amount, in Gwei.
+    amount: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureBlock {
+    rlp: String,
+    #[serde(default)]
+    block_header: Option<FixtureHeader>,
+    #[serde(default)]
+    withdrawals: Vec<FixtureWithdrawal>,
+    #[serde(default)]
+    expect_exception: Option<String>,
+}
+
+/// A test case of a `BlockchainTests` fixture.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockchainTest {
+    network: String,
+    genesis_block_header: FixtureHeader,
+    pre: BTreeMap<Address, FixtureAccount>,
+    blocks: Vec<FixtureBlock>,
+}
+
+/// The block environment of a `GeneralStateTests` case.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateTestEnv {
+    current_coinbase: Address,
+    current_difficulty: String,
+    current_gas_limit: String,
+    current_number: String,
+    current_timestamp: String,
+    #[serde(default)]
+    current_base_fee: Option<String>,
+    #[serde(default)]
+    current_random: Option<H256>,
+    #[serde(default)]
+    current_excess_blob_gas: Option<String>,
+    #[serde(default)]
+    current_beacon_root: Option<H256>,
+}
+
+/// The expected outcome of one of the transactions of a `GeneralStateTests`
+/// case, for a given fork.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatePost {
+    hash: H256,
+    #[serde(default)]
+    txbytes: Option<String>,
+    #[serde(default)]
+    expect_exception: Option<String>,
+}
+
+/// A test case of a `GeneralStateTests` fixture.
+#[derive(Deserialize)]
+struct StateTest {
+    env: StateTestEnv,
+    pre: BTreeMap<Address, FixtureAccount>,
+    post: BTreeMap<String, Vec<StatePost>>,
+}
+
+/// The outcome of a single test case.
+enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+#[derive(Default)]
+struct ForkTally {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+/// Pass / fail / skip counts of a run, grouped by fork.
+#[derive(Default)]
+struct ConformanceReport {
+    forks: BTreeMap<String, ForkTally>,
+    failures: Vec<(String, String)>,
+}
+
+impl ConformanceReport {
+    fn record(&mut self, fork: &str, name: &str, outcome: Outcome) {
+        let tally = self.forks.entry(fork.to_string()).or_default();
+        match outcome {
+            Outcome::Passed => tally.passed += 1,
+            Outcome::Failed(reason) => {
+                log::warn!("FAIL {name} ({fork}): {reason}");
+                tally.failed += 1;
+                self.failures.push((format!("{name} ({fork})"), reason));
+            }
+            Outcome::Skipped(reason) => {
+                log::debug!("SKIP {name} ({fork}): {reason}");
+                tally.skipped += 1;
+            }
+        }
+    }
+
+    fn num_failed(&self) -> usize {
+        self.forks.values().map(|tally| tally.failed).sum()
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<32} {:>8} {:>8} {:>8}",
+            "fork", "passed", "failed", "skipped"
+        )?;
+        for (fork, tally) in &self.forks {
+            writeln!(
+                f,
+                "{:<32} {:>8} {:>8} {:>8}",
+                fork, tally.passed, tally.failed, tally.skipped
+            )?;
+        }
+        for (name, reason) in &self.failures {
+            writeln!(f, "FAILED {name}: {reason}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a hexadecimal quantity, with or without its `0x` prefix.
+fn parse_u256(s: &str) -> anyhow::Result<U256> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    if digits.is_empty() {
+        return Ok(U256::zero());
+    }
+    U256::from_str_radix(digits, 16).with_context(|| format!("invalid quantity {s}"))
+}
+
+/// Parses hexadecimal data, with or without its `0x` prefix.
+fn parse_bytes(s: &str) -> anyhow::Result<Vec<u8>> {
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).with_context(|| format!("invalid data {s}"))
+}
+
+fn parse_bloom(s: &str) -> anyhow::Result<[U256; 8]> {
+    let bytes = parse_bytes(s)?;
+    if bytes.len() != 256 {
+        bail!("invalid bloom length {}", bytes.len());
+    }
+    let mut bloom = [U256::zero(); 8];
+    for (word, chunk) in bloom.iter_mut().zip(bytes.chunks_exact(32)) {
+        *word = U256::from_big_endian(chunk);
+    }
+    Ok(bloom)
+}
+
+/// Builds the initial tries and contract code of the provided allocation.
+fn build_tries(
+    alloc: &BTreeMap<Address, FixtureAccount>,
+) -> anyhow::Result<(TrieInputs, HashMap<H256, Vec<u8>>)> {
+    let mut state_trie = HashedPartialTrie::from(Node::Empty);
+    let mut storage_tries = vec![];
+    let mut contract_code = HashMap::new();
+    contract_code.insert(keccak(vec![]), vec![]);
+
+    for (address, account) in alloc {
+        let storage_pairs = account
+            .storage
+            .iter()
+            .map(|(slot, value)| Ok((parse_u256(slot)?, parse_u256(value)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let storage_trie = create_account_storage(&storage_pairs)?;
+
+        let code = parse_bytes(&account.code)?;
+        let code_hash = keccak(&code);
+        contract_code.insert(code_hash, code);
+
+        let account_rlp = AccountRlp {
+            nonce: parse_u256(&account.nonce)?,
+            balance: parse_u256(&account.balance)?,
+            storage_root: storage_trie.hash(),
+            code_hash,
+        };
+
+        let address_hashed = keccak(address);
+        state_trie.insert(
+            Nibbles::from_h256_be(address_hashed),
+            rlp::encode(&account_rlp).to_vec(),
+        )?;
+        storage_tries.push((address_hashed, storage_trie));
+    }
+
+    let tries = TrieInputs {
+        state_trie,
+        transactions_trie: Node::Empty.into(),
+        receipts_trie: Node::Empty.into(),
+        storage_tries,
+    };
+
+    Ok((tries, contract_code))
+}
+
+/// Extracts the individually encoded transactions out of an RLP-encoded
+/// block. Legacy transactions are kept as RLP lists, while typed transactions
+/// are unwrapped from their enclosing RLP string.
+fn block_transactions(block_rlp: &[u8]) -> anyhow::Result<Vec<Vec<u8>>> {
+    let block = rlp::Rlp::new(block_rlp);
+    block
+        .at(1)?
+        .iter()
+        .map(|txn| {
+            if txn.is_list() {
+                Ok(txn.as_raw().to_vec())
+            } else {
+                Ok(txn.data()?.to_vec())
+            }
+        })
+        .collect()
+}
+
+fn block_metadata(header: &FixtureHeader) -> anyhow::Result<BlockMetadata> {
+    let optional = |value: &Option<String>| value.as_deref().map_or(Ok(U256::zero()), parse_u256);
+
+    Ok(BlockMetadata {
+        block_beneficiary: header.coinbase,
+        block_timestamp: parse_u256(&header.timestamp)?,
+        block_number: parse_u256(&header.number)?,
+        block_difficulty: parse_u256(&header.difficulty)?,
+        block_random: header.mix_hash,
+        block_gaslimit: parse_u256(&header.gas_limit)?,
+        block_chain_id: FIXTURES_CHAIN_ID.into(),
+        block_base_fee: optional(&header.base_fee_per_gas)?,
+        block_gas_used: parse_u256(&header.gas_used)?,
+        block_blob_gas_used: optional(&header.blob_gas_used)?,
+        block_excess_blob_gas: optional(&header.excess_blob_gas)?,
+        parent_beacon_block_root: header.parent_beacon_block_root.unwrap_or_default(),
+        block_bloom: parse_bloom(&header.bloom)?,
+    })
+}
+
+/// The kernel unconditionally updates the EIP-4788 beacon roots contract,
+/// which therefore needs to be deployed for the post-state roots to agree.
+fn has_beacon_roots_contract(tries: &TrieInputs) -> bool {
+    tries
+        .state_trie
+        .get(Nibbles::from_h256_be(BEACON_ROOTS_CONTRACT_ADDRESS_HASHED))
+        .is_some()
+}
+
+/// Builds the [`GenerationInputs`] of a single-block `BlockchainTests` case,
+/// or returns the reason why the case cannot be run.
+fn blockchain_test_inputs(
+    test: &BlockchainTest,
+) -> anyhow::Result<Result<GenerationInputs, String>> {
+    let [block] = test.blocks.as_slice() else {
+        return Ok(Err(format!(
+            "{} blocks, only single-block tests are supported",
+            test.blocks.len()
+        )));
+    };
+    if let Some(exception) = &block.expect_exception {
+        return Ok(Err(format!("invalid block ({exception})")));
+    }
+    let Some(header) = &block.block_header else {
+        return Ok(Err("missing block header".to_string()));
+    };
+
+    let (tries, contract_code) = build_tries(&test.pre)?;
+    if !has_beacon_roots_contract(&tries) {
+        return Ok(Err(
+            "beacon roots contract missing from pre-state".to_string()
+        ));
+    }
+
+    let signed_txns = block_transactions(&parse_bytes(&block.rlp)?)?;
+    let withdrawals = block
+        .withdrawals
+        .iter()
+        .map(|withdrawal| {
+            // Withdrawal amounts are given in Gwei.
+            Ok((
+                withdrawal.address,
+                parse_u256(&withdrawal.amount)? * U256::exp10(9),
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let block_metadata = block_metadata(header)?;
+    let gas_used_after = block_metadata.block_gas_used;
+
+    let mut prev_hashes = vec![H256::default(); 256];
+    prev_hashes[255] = test.genesis_block_header.hash;
+
+    Ok(Ok(GenerationInputs {
+        signed_txns,
+        burn_addr: None,
+        chain_profile: ChainProfile::L1,
+        withdrawals,
+        global_exit_roots: vec![],
+        checkpoint_state_trie_root: tries.state_trie.hash(),
+        tries,
+        trie_roots_after: TrieRoots {
+            state_root: header.state_root,
+            transactions_root: header.transactions_trie,
+            receipts_root: header.receipt_trie,
+        },
+        expected_tries_after: None,
+        contract_code,
+        block_metadata,
+        txn_number_before: 0.into(),
+        gas_used_before: 0.into(),
+        gas_used_after,
+        block_hashes: BlockHashes {
+            prev_hashes,
+            cur_hash: header.hash,
+        },
+    }))
+}
+
+fn run_blockchain_test(test: &BlockchainTest) -> Outcome {
+    if !SUPPORTED_FORKS.contains(&test.network.as_str()) {
+        return Outcome::Skipped("unsupported fork".to_string());
+    }
+
+    match blockchain_test_inputs(test) {
+        Ok(Ok(inputs)) => simulate(inputs),
+        Ok(Err(reason)) => Outcome::Skipped(reason),
+        Err(e) => Outcome::Failed(format!("invalid fixture: {e:#}")),
+    }
+}
+
+/// Builds the [`GenerationInputs`] of a transaction of a `GeneralStateTests`
+/// case, or returns the reason why it cannot be run. The gas used and the
+/// transaction and receipt trie roots are left empty, to be filled in with
+/// [`complete_state_test_inputs`].
+fn state_test_inputs(
+    test: &StateTest,
+    post: &StatePost,
+) -> anyhow::Result<Result<GenerationInputs, String>> {
+    if let Some(exception) = &post.expect_exception {
+        return Ok(Err(format!("invalid transaction ({exception})")));
+    }
+    let Some(txbytes) = &post.txbytes else {
+        return Ok(Err("missing transaction bytes".to_string()));
+    };
+
+    let (tries, contract_code) = build_tries(&test.pre)?;
+    if !has_beacon_roots_contract(&tries) {
+        return Ok(Err(
+            "beacon roots contract missing from pre-state".to_string()
+        ));
+    }
+
+    let env = &test.env;
+    let optional = |value: &Option<String>| value.as_deref().map_or(Ok(U256::zero()), parse_u256);
+    let block_metadata = BlockMetadata {
+        block_beneficiary: env.current_coinbase,
+        block_timestamp: parse_u256(&env.current_timestamp)?,
+        block_number: parse_u256(&env.current_number)?,
+        block_difficulty: parse_u256(&env.current_difficulty)?,
+        block_random: env.current_random.unwrap_or_default(),
+        block_gaslimit: parse_u256(&env.current_gas_limit)?,
+        block_chain_id: FIXTURES_CHAIN_ID.into(),
+        block_base_fee: optional(&env.current_base_fee)?,
+        block_gas_used: U256::zero(),
+        block_blob_gas_used: U256::zero(),
+        block_excess_blob_gas: optional(&env.current_excess_blob_gas)?,
+        parent_beacon_block_root: env.current_beacon_root.unwrap_or_default(),
+        block_bloom: [U256::zero(); 8],
+    };
+
+    Ok(Ok(GenerationInputs {
+        signed_txns: vec![parse_bytes(txbytes)?],
+        burn_addr: None,
+        chain_profile: ChainProfile::L1,
+        withdrawals: vec![],
+        global_exit_roots: vec![],
+        checkpoint_state_trie_root: tries.state_trie.hash(),
+        tries,
+        trie_roots_after: TrieRoots {
+            state_root: post.hash,
+            transactions_root: H256::zero(),
+            receipts_root: H256::zero(),
+        },
+        expected_tries_after: None,
+        contract_code,
+        block_metadata,
+        txn_number_before: 0.into(),
+        gas_used_before: 0.into(),
+        gas_used_after: 0.into(),
+        block_hashes: BlockHashes {
+            prev_hashes: vec![H256::default(); 256],
+            cur_hash: H256::default(),
+        },
+    }))
+}
+
+/// Fills in the values of the block of a state test which are only known
+/// once its transaction has been executed.
+fn complete_state_test_inputs(inputs: &mut GenerationInputs) -> anyhow::Result<()> {
+    let outcome = simulate_execution_outcome::<F>(inputs.clone())?;
+    inputs.gas_used_after = outcome.gas_used;
+    inputs.block_metadata.block_gas_used = outcome.gas_used;
+    inputs.trie_roots_after.transactions_root = outcome.transactions_root;
+    inputs.trie_roots_after.receipts_root = outcome.receipts_root;
+
+    Ok(())
+}
+
+fn run_state_test(test: &StateTest, fork: &str, post: &StatePost) -> Outcome {
+    if !SUPPORTED_FORKS.contains(&fork) {
+        return Outcome::Skipped("unsupported fork".to_string());
+    }
+
+    let mut inputs = match state_test_inputs(test, post) {
+        Ok(Ok(inputs)) => inputs,
+        Ok(Err(reason)) => return Outcome::Skipped(reason),
+        Err(e) => return Outcome::Failed(format!("invalid fixture: {e:#}")),
+    };
+    match catch_unwind(AssertUnwindSafe(|| complete_state_test_inputs(&mut inputs))) {
+        Ok(Ok(())) => simulate(inputs),
+        Ok(Err(e)) => Outcome::Failed(format!("{e:#}")),
+        Err(_) => Outcome::Failed("panicked during execution".to_string()),
+    }
+}
+
+/// Simulates the execution of `inputs` and checks that it ends up in the
+/// expected post-state root.
+fn simulate(inputs: GenerationInputs) -> Outcome {
+    let expected_state_root = inputs.trie_roots_after.state_root;
+
+    match catch_unwind(AssertUnwindSafe(|| {
+        simulate_execution_all_segments::<F>(inputs, MAX_CPU_LEN_LOG)
+    })) {
+        Ok(Ok(())) => Outcome::Passed,
+        Ok(Err(e)) => match e.downcast_ref::<SegmentError>() {
+            Some(SegmentError {
+                kind: ExecutionErrorKind::StateRootMismatch,
+                ..
+            }) => Outcome::Failed(format!(
+                "post-state root doesn't match {expected_state_root:?}: {e:#}"
+            )),
+            _ => Outcome::Failed(format!("{e:#}")),
+        },
+        Err(_) => Outcome::Failed("panicked during execution".to_string()),
+    }
+}
+
+/// Runs all the test cases of a fixture file.
+fn run_fixture_file(path: &Path, report: &mut ConformanceReport) -> anyhow::Result<()> {
+    let contents = fs::read_to_string(path)?;
+    let cases: BTreeMap<String, serde_json::Value> = serde_json::from_str(&contents)?;
+
+    for (name, case) in cases {
+        let name = format!("{}::{name}", path.display());
+        if case.get("blocks").is_some() {
+            let test: BlockchainTest = serde_json::from_value(case)
+                .with_context(|| format!("invalid blockchain test {name}"))?;
+            let outcome = run_blockchain_test(&test);
+            report.record(&test.network, &name, outcome);
+        } else if case.get("transaction").is_some() {
+            let test: StateTest = serde_json::from_value(case)
+                .with_context(|| format!("invalid state test {name}"))?;
+            for (fork, entries) in &test.post {
+                for (i, post) in entries.iter().enumerate() {
+                    let outcome = run_state_test(&test, fork, post);
+                    report.record(fork, &format!("{name}[{i}]"), outcome);
+                }
+            }
+        } else {
+            bail!("unknown fixture format for {name}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Recursively collects all JSON files under `dir`, in a deterministic order.
+fn collect_fixture_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_fixture_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires the execution spec test fixtures, see the module documentation"]
+fn exec_spec_tests() -> anyhow::Result<()> {
+    init_logger();
+
+    let Some(dir) = std::env::var_os(FIXTURES_DIR_ENV) else {
+        log::warn!("{FIXTURES_DIR_ENV} is not set, skipping the execution spec tests");
+        return Ok(());
+    };
+
+    let mut files = vec![];
+    collect_fixture_files(Path::new(&dir), &mut files)?;
+
+    let mut report = ConformanceReport::default();
+    for file in &files {
+        if let Err(e) = run_fixture_file(file, &mut report) {
+            report.record(
+                "unknown",
+                &file.display().to_string(),
+                Outcome::Failed(format!("{e:#}")),
+            );
+        }
+    }
+
+    println!("{report}");
+
+    match report.num_failed() {
+        0 => Ok(()),
+        n => Err(anyhow!("{n} execution spec test(s) failed")),
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::time::Duration;
 
 use ethereum_types::{H256, U256};
-use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::generation::{ChainProfile, GenerationInputs, TrieInputs};
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
 use evm_arithmetization::prover::testing::prove_all_segments;
 use evm_arithmetization::testing_utils::{
@@ -79,6 +79,7 @@ fn test_global_exit_root() -> anyhow::Result<()> {
     let inputs = GenerationInputs {
         signed_txns: vec![],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals: vec![],
         global_exit_roots,
         tries: TrieInputs {
@@ -88,6 +89,7 @@ fn test_global_exit_root() -> anyhow::Result<()> {
             storage_tries,
         },
         trie_roots_after,
+        expected_tries_after: None,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
         block_metadata,
@@ -113,5 +115,5 @@ fn test_global_exit_root() -> anyhow::Result<()> {
     )?;
     timing.filter(Duration::from_millis(100)).print();
 
-    verify_all_proofs(&all_stark, &proofs, &config)
+    verify_all_proofs(&all_stark, &proofs, &config, ChainProfile::L1)
 }
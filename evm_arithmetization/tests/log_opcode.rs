@@ -8,7 +8,7 @@ use evm_arithmetization::generation::mpt::transaction_testing::{
     AddressOption, LegacyTransactionRlp,
 };
 use evm_arithmetization::generation::mpt::{AccountRlp, LegacyReceiptRlp, LogRlp};
-use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::generation::{ChainProfile, GenerationInputs, TrieInputs};
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
 use evm_arithmetization::prover::testing::prove_all_segments;
 use evm_arithmetization::testing_utils::{
@@ -33,6 +33,17 @@ type C = PoseidonGoldilocksConfig;
 /// Variation of `add11_yml` testing LOG opcodes.
 #[test]
 fn test_log_opcodes() -> anyhow::Result<()> {
+    log_opcodes(ChainProfile::L1)
+}
+
+/// Same as `test_log_opcodes`, with the base fee sent to the beneficiary as
+/// burn address.
+#[test]
+fn test_log_opcodes_cdk_erigon() -> anyhow::Result<()> {
+    log_opcodes(ChainProfile::CdkErigon)
+}
+
+fn log_opcodes(chain_profile: ChainProfile) -> anyhow::Result<()> {
     init_logger();
 
     let all_stark = AllStark::<F, D>::default();
@@ -156,15 +167,12 @@ fn test_log_opcodes() -> anyhow::Result<()> {
 
     // Update the state and receipt tries after the transaction, so that we have the
     // correct expected tries: Update accounts
-    #[cfg(feature = "cdk_erigon")]
-    let beneficiary_account_after = AccountRlp {
-        nonce: 1.into(),
-        balance: block_metadata.block_base_fee * gas_used,
-        ..AccountRlp::default()
-    };
-    #[cfg(not(feature = "cdk_erigon"))]
     let beneficiary_account_after = AccountRlp {
         nonce: 1.into(),
+        balance: match chain_profile.has_burn_addr() {
+            true => block_metadata.block_base_fee * gas_used,
+            false => 0.into(),
+        },
         ..AccountRlp::default()
     };
 
@@ -244,18 +252,19 @@ fn test_log_opcodes() -> anyhow::Result<()> {
         receipts_root: receipts_trie.hash(),
     };
 
-    let burn_addr = match cfg!(feature = "cdk_erigon") {
-        true => Some(Address::from(beneficiary)),
-        false => None,
-    };
+    let burn_addr = chain_profile
+        .has_burn_addr()
+        .then(|| Address::from(beneficiary));
 
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         burn_addr,
+        chain_profile,
         withdrawals: vec![],
         global_exit_roots: vec![],
         tries: tries_before,
         trie_roots_after,
+        expected_tries_after: None,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
         block_metadata,
@@ -283,7 +292,7 @@ fn test_log_opcodes() -> anyhow::Result<()> {
 
     timing.filter(Duration::from_millis(100)).print();
 
-    verify_all_proofs(&all_stark, &proofs, &config)
+    verify_all_proofs(&all_stark, &proofs, &config, chain_profile)
 }
 
 /// Values taken from the block 1000000 of Goerli: https://goerli.etherscan.io/txs?block=1000000
@@ -1,18 +1,11 @@
 use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
-use ethereum_types::{Address, BigEndianHash, H256};
 use evm_arithmetization::fixed_recursive_verifier::{
     extract_block_final_public_values, extract_two_to_one_block_hash,
 };
-use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
-use evm_arithmetization::proof::{BlockMetadata, FinalPublicValues, PublicValues, TrieRoots};
-use evm_arithmetization::testing_utils::{
-    beacon_roots_account_nibbles, beacon_roots_contract_from_storage, ger_account_nibbles,
-    preinitialized_state_and_storage_tries, update_beacon_roots_account_storage,
-    GLOBAL_EXIT_ROOT_ACCOUNT,
-};
-use evm_arithmetization::{AllRecursiveCircuits, AllStark, Node, StarkConfig};
-use hex_literal::hex;
-use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use evm_arithmetization::generation::ChainProfile;
+use evm_arithmetization::proof::{FinalPublicValues, PublicValues};
+use evm_arithmetization::testing_utils::dummy_payload;
+use evm_arithmetization::{AllRecursiveCircuits, AllStark, StarkConfig};
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::hash::poseidon::PoseidonHash;
 use plonky2::plonk::config::{Hasher, PoseidonGoldilocksConfig};
@@ -27,83 +20,6 @@ fn init_logger() {
     let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
 }
 
-/// Get `GenerationInputs` for a dummy payload, where the block has the given
-/// timestamp.
-fn dummy_payload(timestamp: u64, is_first_payload: bool) -> anyhow::Result<GenerationInputs> {
-    let beneficiary = hex!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeef");
-
-    let block_metadata = BlockMetadata {
-        block_beneficiary: Address::from(beneficiary),
-        block_timestamp: timestamp.into(),
-        block_number: 1.into(),
-        block_difficulty: 0x020000.into(),
-        block_random: H256::from_uint(&0x020000.into()),
-        block_gaslimit: 0xff112233u32.into(),
-        block_chain_id: 1.into(),
-        block_base_fee: 0xa.into(),
-        ..Default::default()
-    };
-
-    let (mut state_trie_before, mut storage_tries) = preinitialized_state_and_storage_tries()?;
-    let checkpoint_state_trie_root = state_trie_before.hash();
-    let mut beacon_roots_account_storage = storage_tries[0].1.clone();
-
-    update_beacon_roots_account_storage(
-        &mut beacon_roots_account_storage,
-        block_metadata.block_timestamp,
-        block_metadata.parent_beacon_block_root,
-    )?;
-    let updated_beacon_roots_account =
-        beacon_roots_contract_from_storage(&beacon_roots_account_storage);
-
-    if !is_first_payload {
-        // This isn't the first dummy payload being processed. We need to update the
-        // initial state trie to account for the update on the beacon roots contract.
-        state_trie_before.insert(
-            beacon_roots_account_nibbles(),
-            rlp::encode(&updated_beacon_roots_account).to_vec(),
-        )?;
-        storage_tries[0].1 = beacon_roots_account_storage;
-    }
-
-    let tries_before = TrieInputs {
-        state_trie: state_trie_before,
-        storage_tries,
-        ..Default::default()
-    };
-
-    let expected_state_trie_after: HashedPartialTrie = {
-        let mut state_trie_after = HashedPartialTrie::from(Node::Empty);
-        state_trie_after.insert(
-            beacon_roots_account_nibbles(),
-            rlp::encode(&updated_beacon_roots_account).to_vec(),
-        )?;
-        state_trie_after.insert(
-            ger_account_nibbles(),
-            rlp::encode(&GLOBAL_EXIT_ROOT_ACCOUNT).to_vec(),
-        )?;
-
-        state_trie_after
-    };
-
-    let trie_roots_after = TrieRoots {
-        state_root: expected_state_trie_after.hash(),
-        transactions_root: tries_before.transactions_trie.hash(),
-        receipts_root: tries_before.receipts_trie.hash(),
-    };
-
-    let inputs = GenerationInputs {
-        tries: tries_before.clone(),
-        burn_addr: None,
-        trie_roots_after,
-        checkpoint_state_trie_root,
-        block_metadata,
-        ..Default::default()
-    };
-
-    Ok(inputs)
-}
-
 fn get_test_block_proof(
     timestamp: u64,
     all_circuits: &AllRecursiveCircuits<GoldilocksField, PoseidonGoldilocksConfig, 2>,
@@ -140,7 +56,8 @@ fn get_test_block_proof(
     all_circuits.verify_txn_aggregation(&agg_proof)?;
 
     // Test retrieved public values from the proof public inputs.
-    let retrieved_public_values = PublicValues::from_public_inputs(&agg_proof.public_inputs);
+    let retrieved_public_values =
+        PublicValues::from_public_inputs(&agg_proof.public_inputs, all_circuits.chain_profile);
     assert_eq!(retrieved_public_values, pv);
     assert_eq!(
         pv.trie_roots_before.state_root,
@@ -156,7 +73,8 @@ fn get_test_block_proof(
     all_circuits.verify_block(&block_proof)?;
 
     // Test retrieved public values from the proof public inputs.
-    let retrieved_public_values = PublicValues::from_public_inputs(&block_proof.public_inputs);
+    let retrieved_public_values =
+        PublicValues::from_public_inputs(&block_proof.public_inputs, all_circuits.chain_profile);
     assert_eq!(retrieved_public_values, block_public_values);
 
     let (wrapped_block_proof, block_final_public_values) =
@@ -194,6 +112,7 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
             7..8,
         ],
         &config,
+        ChainProfile::L1,
     );
 
     let bp = some_timestamps
@@ -225,6 +144,11 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
         let aggproof0123 =
             all_circuits.prove_two_to_one_block(&aggproof01, true, &aggproof23, true)?;
         all_circuits.verify_two_to_one_block(&aggproof0123)?;
+        // The exported verifier data, used outside of the prover, must accept
+        // aggregation proofs too.
+        all_circuits
+            .two_to_one_block_verifier_data()
+            .verify(aggproof0123.clone())?;
 
         {
             // Compute Merkle root from public inputs of block proofs.
@@ -3,7 +3,7 @@ use std::time::Duration;
 
 use ethereum_types::{H160, H256, U256};
 use evm_arithmetization::generation::mpt::AccountRlp;
-use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::generation::{ChainProfile, GenerationInputs, TrieInputs};
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
 use evm_arithmetization::prover::testing::prove_all_segments;
 use evm_arithmetization::testing_utils::{
@@ -87,6 +87,7 @@ fn test_withdrawals() -> anyhow::Result<()> {
     let inputs = GenerationInputs {
         signed_txns: vec![],
         burn_addr: None,
+        chain_profile: ChainProfile::L1,
         withdrawals,
         global_exit_roots: vec![],
         tries: TrieInputs {
@@ -96,6 +97,7 @@ fn test_withdrawals() -> anyhow::Result<()> {
             storage_tries,
         },
         trie_roots_after,
+        expected_tries_after: None,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
         block_metadata,
@@ -122,5 +124,5 @@ fn test_withdrawals() -> anyhow::Result<()> {
 
     timing.filter(Duration::from_millis(100)).print();
 
-    verify_all_proofs(&all_stark, &proofs, &config)
+    verify_all_proofs(&all_stark, &proofs, &config, ChainProfile::L1)
 }
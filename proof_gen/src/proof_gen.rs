@@ -4,8 +4,8 @@
 use std::sync::{atomic::AtomicBool, Arc};
 
 use evm_arithmetization::{
-    fixed_recursive_verifier::ProverOutputData, generation::TrimmedGenerationInputs, AllStark,
-    GenerationSegmentData, StarkConfig,
+    fixed_recursive_verifier::ProverOutputData, generation::TrimmedGenerationInputs,
+    proof::PublicValues, AllStark, GenerationSegmentData, StarkConfig,
 };
 use hashbrown::HashMap;
 use plonky2::{
@@ -174,6 +174,30 @@ pub fn generate_block_proof(
     })
 }
 
+/// Wraps a block proof, compressing its public values so that it can be
+/// aggregated with other blocks through [`generate_agg_block_proof`].
+///
+/// The returned proof keeps the height of the wrapped block.
+pub fn generate_block_wrapper_proof(
+    p_state: &ProverState,
+    block_proof: &GeneratedBlockProof,
+) -> ProofGenResult<GeneratedBlockProof> {
+    let public_values = PublicValues::from_public_inputs(
+        &block_proof.intern.public_inputs,
+        p_state.state.chain_profile,
+    );
+
+    let (wrapped_proof_intern, _) = p_state
+        .state
+        .prove_block_wrapper(&block_proof.intern, public_values)
+        .map_err(|err| err.to_string())?;
+
+    Ok(GeneratedBlockProof {
+        b_height: block_proof.b_height,
+        intern: wrapped_proof_intern,
+    })
+}
+
 /// Generates an aggregation block proof from two child proofs.
 ///
 /// Note that the child proofs may be either block or aggregation proofs.
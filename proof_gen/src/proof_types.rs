@@ -177,7 +177,8 @@ impl From<SegmentAggregatableProof> for BatchAggregatableProof {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum AggregatableBlockProof {
-    /// The underlying proof is a single block proof.
+    /// The underlying proof is a single block proof, wrapped with
+    /// [`generate_block_wrapper_proof`](crate::proof_gen::generate_block_wrapper_proof).
     Block(GeneratedBlockProof),
     /// The underlying proof is an aggregated proof.
     Agg(GeneratedAggBlockProof),
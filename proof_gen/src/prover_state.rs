@@ -5,7 +5,7 @@
 
 use std::ops::Range;
 
-use evm_arithmetization::{AllStark, StarkConfig};
+use evm_arithmetization::{AllStark, ChainProfile, StarkConfig};
 use log::info;
 use paste::paste;
 
@@ -31,6 +31,7 @@ pub struct ProverStateBuilder {
     pub(crate) memory_circuit_size: Range<usize>,
     pub(crate) memory_before_circuit_size: Range<usize>,
     pub(crate) memory_after_circuit_size: Range<usize>,
+    pub(crate) chain_profile: ChainProfile,
 }
 
 impl Default for ProverStateBuilder {
@@ -52,6 +53,7 @@ impl Default for ProverStateBuilder {
             memory_circuit_size: DEFAULT_MEMORY_RANGE,
             memory_before_circuit_size: DEFAULT_MEMORY_BEFORE_RANGE,
             memory_after_circuit_size: DEFAULT_MEMORY_AFTER_RANGE,
+            chain_profile: ChainProfile::default(),
         }
     }
 }
@@ -80,6 +82,12 @@ impl ProverStateBuilder {
     define_set_circuit_size_method!(memory_before);
     define_set_circuit_size_method!(memory_after);
 
+    /// Specifies the chain whose blocks this prover state will prove.
+    pub const fn set_chain_profile(mut self, chain_profile: ChainProfile) -> Self {
+        self.chain_profile = chain_profile;
+        self
+    }
+
     // TODO: Consider adding async version?
     /// Instantiate the prover state from the builder. Note that this is a very
     /// expensive call!
@@ -100,6 +108,7 @@ impl ProverStateBuilder {
                 self.memory_after_circuit_size,
             ],
             &StarkConfig::standard_fast_config(),
+            self.chain_profile,
         );
 
         info!("Finished initializing Plonky2 aggregation prover state!");
@@ -20,6 +20,9 @@ pub struct VerifierState {
     /// The verification circuit data associated to the block proof layer of the
     /// plonky2 prover state.
     pub state: VerifierData,
+    /// The verification circuit data associated to the two-to-one block
+    /// aggregation layer of the plonky2 prover state.
+    pub two_to_one_block_state: VerifierData,
 }
 
 /// Builder for the verifier state.
@@ -38,6 +41,7 @@ impl VerifierStateBuilder {
 
         VerifierState {
             state: state.final_verifier_data(),
+            two_to_one_block_state: state.two_to_one_block_verifier_data(),
         }
     }
 }
@@ -45,8 +49,10 @@ impl VerifierStateBuilder {
 /// Extracts the verifier state from the entire prover state.
 impl<T: Borrow<ProverState>> From<T> for VerifierState {
     fn from(prover_state: T) -> Self {
+        let state = &prover_state.borrow().state;
         VerifierState {
-            state: prover_state.borrow().state.final_verifier_data(),
+            state: state.final_verifier_data(),
+            two_to_one_block_state: state.two_to_one_block_verifier_data(),
         }
     }
 }
@@ -54,19 +60,27 @@ impl<T: Borrow<ProverState>> From<T> for VerifierState {
 impl VerifierState {
     /// Verifies a `block_proof`.
     pub fn verify(&self, block_proof: &PlonkyProofIntern) -> ProofGenResult<()> {
-        // Proof verification
-        self.state
-            .verify(block_proof.clone())
-            .map_err(|err| err.to_string())?;
+        verify_cyclic_proof(&self.state, block_proof)
+    }
+
+    /// Verifies an `agg_block_proof`, aggregating a range of blocks.
+    pub fn verify_agg_block(&self, agg_block_proof: &PlonkyProofIntern) -> ProofGenResult<()> {
+        verify_cyclic_proof(&self.two_to_one_block_state, agg_block_proof)
+    }
+}
 
-        // Verifier data verification
-        check_cyclic_proof_verifier_data(
-            block_proof,
-            &self.state.verifier_only,
-            &self.state.common,
-        )
+fn verify_cyclic_proof(
+    verifier_data: &VerifierData,
+    proof: &PlonkyProofIntern,
+) -> ProofGenResult<()> {
+    // Proof verification
+    verifier_data
+        .verify(proof.clone())
         .map_err(|err| err.to_string())?;
 
-        Ok(())
-    }
+    // Verifier data verification
+    check_cyclic_proof_verifier_data(proof, &verifier_data.verifier_only, &verifier_data.common)
+        .map_err(|err| err.to_string())?;
+
+    Ok(())
 }
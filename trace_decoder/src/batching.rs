@@ -0,0 +1,189 @@
+//! Strategies to split the transactions of a block into the batches which are
+//! proven independently.
+
+use anyhow::{ensure, Context as _};
+use evm_arithmetization::generation::txn_cpu_cycles;
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+use crate::{BlockTrace, OtherBlockData, TxnInfo};
+
+/// How to split the transactions of a block into batches.
+///
+/// Batches are always made of consecutive transactions. Whatever the strategy,
+/// a block yields at least two payloads, padded with dummy ones if needed, and
+/// the withdrawals are processed in the last one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchingStrategy {
+    /// Batches of a fixed number of transactions, the last one possibly
+    /// smaller.
+    Fixed(usize),
+    /// Batches of transactions whose [`TxnMeta::gas_used`](crate::TxnMeta)
+    /// add up to at most the given budget.
+    ///
+    /// A transaction above the budget is in a batch of its own.
+    Gas(u64),
+    /// Batches of transactions whose CPU cycles add up to at most the given
+    /// budget.
+    ///
+    /// The CPU cycles of each transaction are measured by simulating the
+    /// whole block beforehand, which roughly doubles the cost of decoding.
+    Cycles(usize),
+    /// Batches of the given sizes, which must add up to the number of
+    /// transactions.
+    Partition(Vec<usize>),
+}
+
+impl BatchingStrategy {
+    /// Returns the sizes of the batches of `txn_info`.
+    ///
+    /// `cycles` are the estimated CPU cycles of each transaction, required by
+    /// [`BatchingStrategy::Cycles`].
+    pub(crate) fn batch_sizes(
+        &self,
+        txn_info: &[TxnInfo],
+        cycles: Option<&[usize]>,
+    ) -> anyhow::Result<Vec<usize>> {
+        let num_txns = txn_info.len();
+        let sizes = match self {
+            Self::Fixed(batch_size) => {
+                ensure!(*batch_size != 0, "the batch size must be positive");
+                // Make sure the batch size is smaller than the total number of
+                // transactions, or we would need to generate dummy proofs for the
+                // aggregation layers.
+                let batch_size = match *batch_size > num_txns {
+                    true => num_txns / 2 + 1,
+                    false => *batch_size,
+                };
+                return Ok(txn_info.chunks(batch_size).map(<[_]>::len).collect());
+            }
+            Self::Gas(budget) => by_budget(txn_info.iter().map(|it| it.meta.gas_used), *budget),
+            Self::Cycles(budget) => {
+                let cycles = cycles.context("missing CPU cycle estimates")?;
+                ensure!(
+                    cycles.len() == num_txns,
+                    "expected {num_txns} CPU cycle estimates, got {}",
+                    cycles.len()
+                );
+                by_budget(cycles.iter().map(|it| *it as u64), *budget as u64)
+            }
+            Self::Partition(sizes) => {
+                ensure!(
+                    !sizes.contains(&0),
+                    "the batches of a partition must not be empty"
+                );
+                ensure!(
+                    sizes.iter().sum::<usize>() == num_txns,
+                    "the partition has {} transactions, but the block has {num_txns}",
+                    sizes.iter().sum::<usize>()
+                );
+                return Ok(sizes.clone());
+            }
+        };
+
+        // Like for fixed batches, avoid a single batch of the whole block.
+        match sizes.len() {
+            1 => Self::Fixed(num_txns + 1).batch_sizes(txn_info, None),
+            _ => Ok(sizes),
+        }
+    }
+}
+
+/// Greedily fills batches with consecutive transactions of the given costs, up
+/// to `budget`.
+fn by_budget(costs: impl IntoIterator<Item = u64>, budget: u64) -> Vec<usize> {
+    let mut sizes = vec![];
+    let (mut size, mut total) = (0, 0);
+    for cost in costs {
+        if size != 0 && total + cost > budget {
+            sizes.push(size);
+            (size, total) = (0, 0);
+        }
+        size += 1;
+        total += cost;
+    }
+    if size != 0 {
+        sizes.push(size);
+    }
+    sizes
+}
+
+/// Measures the CPU cycles of each transaction, by simulating the block as a
+/// single batch.
+pub(crate) fn estimate_cycles(
+    trace: &BlockTrace,
+    other: &OtherBlockData,
+) -> anyhow::Result<Vec<usize>> {
+    let num_txns = trace.txn_info.len();
+    if num_txns == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut cycles = vec![];
+    for inputs in crate::entrypoint_with_batching(
+        trace.clone(),
+        other.clone(),
+        &BatchingStrategy::Partition(vec![num_txns]),
+    )? {
+        // Skip the dummy payloads.
+        if !inputs.signed_txns.is_empty() {
+            cycles.extend(
+                txn_cpu_cycles::<GoldilocksField>(&inputs)
+                    .context("couldn't simulate the block")?,
+            );
+        }
+    }
+    ensure!(
+        cycles.len() == num_txns,
+        "measured the CPU cycles of {} transactions, but the block has {num_txns}",
+        cycles.len()
+    );
+    Ok(cycles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TxnMeta;
+
+    fn txns(gas_used: &[u64]) -> Vec<TxnInfo> {
+        gas_used
+            .iter()
+            .map(|&gas_used| TxnInfo {
+                traces: Default::default(),
+                meta: TxnMeta {
+                    byte_code: vec![],
+                    new_receipt_trie_node_byte: vec![],
+                    gas_used,
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_sizes() {
+        let block = txns(&[500_000, 21_000, 21_000, 21_000, 21_000, 100_000, 21_000]);
+        let sizes = |strategy: BatchingStrategy| strategy.batch_sizes(&block, None).unwrap();
+
+        assert_eq!(sizes(BatchingStrategy::Fixed(3)), [3, 3, 1]);
+        assert_eq!(sizes(BatchingStrategy::Fixed(10)), [4, 3]);
+        assert_eq!(sizes(BatchingStrategy::Gas(150_000)), [1, 4, 2]);
+        assert_eq!(sizes(BatchingStrategy::Gas(10_000_000)), [4, 3]);
+        assert_eq!(sizes(BatchingStrategy::Partition(vec![2, 5])), [2, 5]);
+        assert!(BatchingStrategy::Partition(vec![2, 4])
+            .batch_sizes(&block, None)
+            .is_err());
+        assert!(BatchingStrategy::Cycles(1_000)
+            .batch_sizes(&block, None)
+            .is_err());
+        assert_eq!(
+            BatchingStrategy::Cycles(1_000)
+                .batch_sizes(&block, Some(&[900, 50, 50, 50, 300, 300, 300]))
+                .unwrap(),
+            [3, 4]
+        );
+        assert!(BatchingStrategy::Gas(1)
+            .batch_sizes(&[], None)
+            .unwrap()
+            .is_empty());
+    }
+}
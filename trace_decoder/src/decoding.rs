@@ -1,4 +1,4 @@
-use std::{cmp::min, collections::HashMap, ops::Range};
+use std::{collections::HashMap, ops::Range};
 
 use anyhow::{anyhow, Context as _};
 use ethereum_types::H160;
@@ -6,7 +6,7 @@ use ethereum_types::{Address, BigEndianHash, H256, U256, U512};
 use evm_arithmetization::{
     generation::{
         mpt::{decode_receipt, AccountRlp},
-        GenerationInputs, TrieInputs,
+        GenerationInputs, TrieInputs, TrimmedTrieInputs,
     },
     proof::{BlockMetadata, ExtraBlockData, TrieRoots},
     testing_utils::{
@@ -17,7 +17,7 @@ use mpt_trie::{
     nibbles::Nibbles,
     partial_trie::{HashedPartialTrie, PartialTrie as _},
     special_query::path_for_query,
-    trie_ops::TrieOpError,
+    trie_ops::{TrieOpError, ValOrHash},
     utils::{IntoTrieKey as _, TriePath},
 };
 
@@ -27,7 +27,7 @@ use crate::{
         NodesUsedByTxnBatch, ProcessedBlockTrace, ProcessedTxnBatchInfo, StateWrite, TxnMetaState,
     },
     typed_mpt::{ReceiptTrie, StateTrie, StorageTrie, TransactionTrie, TrieKey},
-    OtherBlockData, PartialTriePreImages, TryIntoExt as TryIntoBounds,
+    DecodingOptions, OtherBlockData, PartialTriePreImages, TryIntoExt as TryIntoBounds,
 };
 
 /// The current state of all tries as we process txn deltas. These are mutated
@@ -49,23 +49,37 @@ struct TrieDeltaApplicationOutput {
     additional_storage_trie_paths_to_not_hash: HashMap<H256, Vec<TrieKey>>,
 }
 
-pub fn into_txn_proof_gen_ir(
+/// Decodes the payloads of a block lazily: the deltas of each batch are only
+/// applied when its [`GenerationInputs`] are requested.
+pub(crate) struct TxnProofGenIr<StateTrieT> {
+    curr_block_tries: PartialTrieState<StateTrieT>,
+    extra_data: ExtraBlockData,
+    other_data: OtherBlockData,
+    txn_info: std::vec::IntoIter<ProcessedTxnBatchInfo>,
+    withdrawals: Vec<(Address, U256)>,
+    num_txns_before: usize,
+    options: DecodingOptions,
+}
+
+pub fn into_txn_proof_gen_ir<StateTrieT>(
     ProcessedBlockTrace {
         tries: PartialTriePreImages { state, storage },
         txn_info,
         withdrawals,
-    }: ProcessedBlockTrace,
+    }: ProcessedBlockTrace<StateTrieT>,
     other_data: OtherBlockData,
-    use_burn_addr: bool,
-    batch_size: usize,
-) -> anyhow::Result<Vec<GenerationInputs>> {
-    let mut curr_block_tries = PartialTrieState {
-        state: state.clone(),
+    options: DecodingOptions,
+) -> TxnProofGenIr<StateTrieT>
+where
+    StateTrieT: StateTrie + Clone + Default + TryIntoBounds<HashedPartialTrie>,
+{
+    let curr_block_tries = PartialTrieState {
+        state,
         storage: storage.iter().map(|(k, v)| (*k, v.clone())).collect(),
         ..Default::default()
     };
 
-    let mut extra_data = ExtraBlockData {
+    let extra_data = ExtraBlockData {
         checkpoint_state_trie_root: other_data.checkpoint_state_trie_root,
         txn_number_before: U256::zero(),
         txn_number_after: U256::zero(),
@@ -73,44 +87,76 @@ pub fn into_txn_proof_gen_ir(
         gas_used_after: U256::zero(),
     };
 
-    let num_txs = txn_info
-        .iter()
-        .map(|tx_info| tx_info.meta.len())
-        .sum::<usize>();
+    TxnProofGenIr {
+        curr_block_tries,
+        extra_data,
+        other_data,
+        txn_info: txn_info.into_iter(),
+        withdrawals,
+        num_txns_before: 0,
+        options,
+    }
+}
 
-    let mut txn_gen_inputs = txn_info
-        .into_iter()
-        .enumerate()
-        .map(|(txn_idx, txn_info)| {
-            let txn_range =
-                min(txn_idx * batch_size, num_txs)..min(txn_idx * batch_size + batch_size, num_txs);
-            let is_initial_payload = txn_range.start == 0;
-
-            process_txn_info(
-                txn_range.clone(),
-                is_initial_payload,
-                txn_info,
-                &mut curr_block_tries,
-                &mut extra_data,
-                &other_data,
-                use_burn_addr,
-            )
-            .context(format!(
-                "at transaction range {}..{}",
-                txn_range.start, txn_range.end
-            ))
-        })
-        .collect::<anyhow::Result<Vec<_>>>()
+impl<StateTrieT> TxnProofGenIr<StateTrieT>
+where
+    StateTrieT: StateTrie + Clone + Default + TryIntoBounds<HashedPartialTrie>,
+{
+    fn next_inputs(&mut self, txn_info: ProcessedTxnBatchInfo) -> anyhow::Result<GenerationInputs> {
+        let txn_range = self.num_txns_before..self.num_txns_before + txn_info.meta.len();
+        self.num_txns_before = txn_range.end;
+        let is_initial_payload = txn_range.start == 0;
+
+        let mut inputs = process_txn_info(
+            txn_range.clone(),
+            is_initial_payload,
+            txn_info,
+            &mut self.curr_block_tries,
+            &mut self.extra_data,
+            &self.other_data,
+            self.options,
+        )
         .context(format!(
-            "at block num {} with chain id {}",
-            other_data.b_data.b_meta.block_number, other_data.b_data.b_meta.block_chain_id
+            "at transaction range {}..{}",
+            txn_range.start, txn_range.end
         ))?;
 
-    if !withdrawals.is_empty() {
-        add_withdrawals_to_txns(&mut txn_gen_inputs, &mut curr_block_tries, withdrawals)?;
+        if self.txn_info.len() == 0 && !self.withdrawals.is_empty() {
+            add_withdrawals_to_txns(
+                &mut inputs,
+                &mut self.curr_block_tries,
+                std::mem::take(&mut self.withdrawals),
+            )?;
+        }
+
+        Ok(inputs)
     }
+}
+
+impl<StateTrieT> Iterator for TxnProofGenIr<StateTrieT>
+where
+    StateTrieT: StateTrie + Clone + Default + TryIntoBounds<HashedPartialTrie>,
+{
+    type Item = anyhow::Result<GenerationInputs>;
 
-    Ok(txn_gen_inputs)
+    fn next(&mut self) -> Option<Self::Item> {
+        let txn_info = self.txn_info.next()?;
+        let inputs = self.next_inputs(txn_info).context(format!(
+            "at block num {} with chain id {}",
+            self.other_data.b_data.b_meta.block_number,
+            self.other_data.b_data.b_meta.block_chain_id
+        ));
+        if inputs.is_err() {
+            // The tries are left halfway through the batch, so the following
+            // batches can't be decoded.
+            self.txn_info = Vec::new().into_iter();
+        }
+        Some(inputs)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.txn_info.size_hint()
+    }
 }
 
 /// Cancun HF specific: At the start of a block, prior txn execution, we
@@ -292,6 +338,39 @@ fn create_minimal_partial_tries_needed_by_txn(
     })
 }
 
+/// Trims the tries resulting from the execution of a txn batch to the nodes
+/// accessed by it.
+fn create_expected_tries_after(
+    curr_block_tries: &PartialTrieState<impl StateTrie + Clone + TryIntoBounds<HashedPartialTrie>>,
+    nodes_used_by_txn: &NodesUsedByTxnBatch,
+    delta_application_out: &TrieDeltaApplicationOutput,
+) -> anyhow::Result<TrimmedTrieInputs> {
+    let mut state_trie = curr_block_tries.state.clone();
+    state_trie.trim_to(
+        nodes_used_by_txn
+            .state_accesses
+            .iter()
+            .map(|it| TrieKey::from_address(*it))
+            .chain(
+                delta_application_out
+                    .additional_state_trie_paths_to_not_hash
+                    .iter()
+                    .copied(),
+            ),
+    )?;
+
+    let storage_tries = create_minimal_storage_partial_tries(
+        &curr_block_tries.storage,
+        &nodes_used_by_txn.storage_accesses,
+        &delta_application_out.additional_storage_trie_paths_to_not_hash,
+    )?;
+
+    Ok(TrimmedTrieInputs {
+        state_trie: state_trie.try_into()?,
+        storage_tries,
+    })
+}
+
 fn apply_deltas_to_trie_state(
     trie_state: &mut PartialTrieState<impl StateTrie>,
     deltas: &NodesUsedByTxnBatch,
@@ -434,7 +513,7 @@ fn node_deletion_resulted_in_a_branch_collapse(
 
 /// The withdrawals are always in the final ir payload.
 fn add_withdrawals_to_txns(
-    txn_ir: &mut [GenerationInputs],
+    last_inputs: &mut GenerationInputs,
     final_trie_state: &mut PartialTrieState<
         impl StateTrie + Clone + TryIntoBounds<HashedPartialTrie>,
     >,
@@ -451,10 +530,6 @@ fn add_withdrawals_to_txns(
             .map(|(addr, v)| (*addr, hash(addr.as_bytes()), *v))
     };
 
-    let last_inputs = txn_ir
-        .last_mut()
-        .expect("We cannot have an empty list of payloads.");
-
     if last_inputs.signed_txns.is_empty() {
         let mut state_trie = final_trie_state.state.clone();
         state_trie.trim_to(
@@ -479,12 +554,49 @@ fn add_withdrawals_to_txns(
         &mut final_trie_state.state,
     )?;
 
+    // The expected tries must also reflect the balances updated by withdrawals.
+    if let Some(expected_tries_after) = last_inputs.expected_tries_after.take() {
+        last_inputs.expected_tries_after = trim_state_trie_after_withdrawals(
+            &final_trie_state.state,
+            &expected_tries_after.state_trie,
+            &withdrawals,
+        )
+        .ok()
+        .map(|state_trie| TrimmedTrieInputs {
+            state_trie,
+            storage_tries: expected_tries_after.storage_tries,
+        });
+    }
+
     last_inputs.withdrawals = withdrawals;
     last_inputs.trie_roots_after.state_root = final_trie_state.state.clone().try_into()?.hash();
 
     Ok(())
 }
 
+/// Trims the final state trie to the accounts hydrated in the expected state
+/// trie of the last payload, along with the withdrawal addresses.
+fn trim_state_trie_after_withdrawals(
+    final_state_trie: &(impl StateTrie + Clone + TryIntoBounds<HashedPartialTrie>),
+    expected_state_trie: &HashedPartialTrie,
+    withdrawals: &[(Address, U256)],
+) -> anyhow::Result<HashedPartialTrie> {
+    let hydrated_keys = expected_state_trie
+        .items()
+        .filter(|(_, v)| matches!(v, ValOrHash::Val(_)))
+        .map(|(k, _)| TrieKey::from_nibbles(k));
+
+    let mut state_trie = final_state_trie.clone();
+    state_trie.trim_to(
+        hydrated_keys.chain(
+            withdrawals
+                .iter()
+                .map(|(addr, _)| TrieKey::from_address(*addr)),
+        ),
+    )?;
+    Ok(state_trie.try_into()?)
+}
+
 /// Withdrawals update balances in the account trie, so we need to update
 /// our local trie state.
 fn update_trie_state_from_withdrawals<'a>(
@@ -518,7 +630,7 @@ fn process_txn_info(
     >,
     extra_data: &mut ExtraBlockData,
     other_data: &OtherBlockData,
-    use_burn_target: bool,
+    options: DecodingOptions,
 ) -> anyhow::Result<GenerationInputs> {
     log::trace!(
         "Generating proof IR for txn {} through {}...",
@@ -570,6 +682,13 @@ fn process_txn_info(
         txn_info.nodes_used_by_txn
     };
 
+    // These are only used to report diverging accounts should the final state
+    // root not match, so failing to build them shouldn't fail decoding.
+    let expected_tries_after = match options.expected_tries_after {
+        true => create_expected_tries_after(curr_block_tries, &nodes_used_by_txn, &delta_out).ok(),
+        false => None,
+    };
+
     let tries = create_minimal_partial_tries_needed_by_txn(
         &tries_at_start_of_txn,
         &nodes_used_by_txn,
@@ -577,7 +696,7 @@ fn process_txn_info(
         delta_out,
     )?;
 
-    let burn_addr = match use_burn_target {
+    let burn_addr = match other_data.chain_profile.has_burn_addr() {
         // TODO: https://github.com/0xPolygonZero/zk_evm/issues/565
         //       Retrieve the actual burn address from `cdk-erigon`.
         true => Some(H160::zero()),
@@ -586,6 +705,7 @@ fn process_txn_info(
     let gen_inputs = GenerationInputs {
         txn_number_before: extra_data.txn_number_before,
         burn_addr,
+        chain_profile: other_data.chain_profile,
         gas_used_before: extra_data.gas_used_before,
         gas_used_after: extra_data.gas_used_after,
         signed_txns: txn_info
@@ -602,6 +722,7 @@ fn process_txn_info(
             transactions_root: curr_block_tries.txn.root(),
             receipts_root: curr_block_tries.receipt.root(),
         },
+        expected_tries_after,
         checkpoint_state_trie_root: extra_data.checkpoint_state_trie_root,
         contract_code: txn_info
             .contract_code_accessed
@@ -0,0 +1,363 @@
+//! Frontend for pre-images in the shape of an execution witness, as returned by
+//! e.g. `debug_executionWitness`: unordered pools of RLP-encoded trie nodes,
+//! contract codes and keys, each keyed by its hash.
+//!
+//! The state and storage tries are rebuilt by walking the node pool from the
+//! state root, much like [`mpt_trie::builder::PartialTrieBuilder`] does.
+//! Nodes missing from the pool become hash nodes.
+//! Codes may also come from the code db of the [`crate::BlockTrace`], so
+//! accounts whose code is missing from both are reported by
+//! [`crate::validate`] rather than rejected here.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{bail, ensure, Context as _};
+use evm_arithmetization::generation::mpt::AccountRlp;
+use keccak_hash::H256;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::OnOrphanedHashNode;
+use mpt_trie::trie_ops::ValOrHash;
+use rlp::Rlp;
+use zk_evm_common::EMPTY_TRIE_HASH;
+
+use crate::typed_mpt::{StateMpt, StateTrie as _, StorageTrie, TrieKey};
+use crate::ExecutionWitnessPreImages;
+
+#[derive(Debug, Clone)]
+pub struct Frontend {
+    pub state: StateMpt,
+    pub code: HashMap<H256, Vec<u8>>,
+    pub storage: BTreeMap<H256, StorageTrie>,
+}
+
+pub fn frontend(
+    ExecutionWitnessPreImages {
+        state_root,
+        state,
+        codes,
+        keys,
+    }: ExecutionWitnessPreImages,
+) -> anyhow::Result<Frontend> {
+    for (hash, code) in &codes {
+        ensure!(
+            crate::hash(code) == *hash,
+            "contract code keyed by {hash:x} hashes to {:x}",
+            crate::hash(code)
+        );
+    }
+    for (hash, key) in &keys {
+        ensure!(
+            crate::hash(key) == *hash,
+            "key 0x{} keyed by {hash:x} hashes to {:x}",
+            hex::encode(key),
+            crate::hash(key)
+        );
+    }
+
+    let mut pool = NodePool::new(state)?;
+
+    let mut frontend = Frontend {
+        state: StateMpt::new(OnOrphanedHashNode::Reject),
+        code: codes,
+        storage: BTreeMap::new(),
+    };
+    for (nibbles, value) in pool.leaves(state_root).context("invalid state trie")? {
+        let path = TrieKey::from_nibbles(nibbles);
+        match value {
+            ValOrHash::Val(bytes) => {
+                let haddr = path
+                    .into_hash()
+                    .context("invalid depth for leaf of state trie")?;
+                let account: AccountRlp =
+                    rlp::decode(&bytes).with_context(|| format!("invalid account at {haddr:x}"))?;
+
+                let mut storage = StorageTrie::new(OnOrphanedHashNode::Reject);
+                for (nibbles, value) in pool
+                    .leaves(account.storage_root)
+                    .with_context(|| format!("invalid storage trie of account {haddr:x}"))?
+                {
+                    let path = TrieKey::from_nibbles(nibbles);
+                    match value {
+                        ValOrHash::Val(value) => {
+                            storage.insert(path, value)?;
+                        }
+                        ValOrHash::Hash(hash) => storage.insert_hash(path, hash)?,
+                    }
+                }
+                ensure!(
+                    storage.root() == account.storage_root,
+                    "storage trie of account {haddr:x} has root {:x}, expected {:x}",
+                    storage.root(),
+                    account.storage_root
+                );
+                frontend.storage.insert(haddr, storage);
+
+                #[expect(deprecated)] // this is MPT-specific code
+                let clobbered = frontend.state.insert_by_hashed_address(haddr, account)?;
+                ensure!(clobbered.is_none(), "duplicate account");
+            }
+            ValOrHash::Hash(hash) => frontend.state.insert_hash_by_key(path, hash)?,
+        }
+    }
+    ensure!(
+        frontend.state.root() == state_root,
+        "state trie has root {:x}, expected {state_root:x}",
+        frontend.state.root()
+    );
+
+    let unreferenced = pool.unreferenced();
+    if unreferenced != 0 {
+        log::warn!("{unreferenced} trie nodes of the execution witness are not referenced");
+    }
+
+    Ok(frontend)
+}
+
+/// RLP-encoded trie nodes keyed by their hash, along with which of them have
+/// been visited.
+struct NodePool {
+    nodes: HashMap<H256, Vec<u8>>,
+    visited: HashSet<H256>,
+}
+
+impl NodePool {
+    fn new(nodes: HashMap<H256, Vec<u8>>) -> anyhow::Result<Self> {
+        for (hash, node) in &nodes {
+            ensure!(
+                crate::hash(node) == *hash,
+                "trie node keyed by {hash:x} hashes to {:x}",
+                crate::hash(node)
+            );
+        }
+        Ok(Self {
+            nodes,
+            visited: HashSet::new(),
+        })
+    }
+
+    /// Returns the values and hash nodes of the trie with the given root,
+    /// along with their paths.
+    fn leaves(&mut self, root: H256) -> anyhow::Result<Vec<(Nibbles, ValOrHash)>> {
+        let mut leaves = vec![];
+        if root != EMPTY_TRIE_HASH {
+            self.visit_hash(root, Nibbles::default(), &mut leaves)?;
+        }
+        Ok(leaves)
+    }
+
+    fn unreferenced(&self) -> usize {
+        self.nodes.len() - self.visited.len()
+    }
+
+    fn visit_hash(
+        &mut self,
+        hash: H256,
+        path: Nibbles,
+        leaves: &mut Vec<(Nibbles, ValOrHash)>,
+    ) -> anyhow::Result<()> {
+        match self.nodes.get(&hash).cloned() {
+            Some(node) => {
+                self.visited.insert(hash);
+                self.visit_node(&Rlp::new(&node), path, leaves)
+                    .with_context(|| format!("invalid trie node {hash:x}"))
+            }
+            None => {
+                leaves.push((path, ValOrHash::Hash(hash)));
+                Ok(())
+            }
+        }
+    }
+
+    fn visit_node(
+        &mut self,
+        node: &Rlp,
+        path: Nibbles,
+        leaves: &mut Vec<(Nibbles, ValOrHash)>,
+    ) -> anyhow::Result<()> {
+        match node.item_count()? {
+            17 => {
+                for ix in 0..16 {
+                    self.visit_child(&node.at(ix)?, path.merge_nibble(ix as u8), leaves)?;
+                }
+                ensure!(
+                    node.at(16)?.data()?.is_empty(),
+                    "unexpected value in branch node"
+                );
+            }
+            2 => {
+                let (key, is_leaf) = decode_path(node.at(0)?.data()?)?;
+                let path = path.merge_nibbles(&key);
+                match is_leaf {
+                    true => leaves.push((path, ValOrHash::Val(node.at(1)?.data()?.to_vec()))),
+                    false => self.visit_child(&node.at(1)?, path, leaves)?,
+                }
+            }
+            n => bail!("expected a node with 2 or 17 items, got {n}"),
+        }
+        Ok(())
+    }
+
+    fn visit_child(
+        &mut self,
+        child: &Rlp,
+        path: Nibbles,
+        leaves: &mut Vec<(Nibbles, ValOrHash)>,
+    ) -> anyhow::Result<()> {
+        // Nodes shorter than a hash are embedded in their parent.
+        if child.is_list() {
+            return self.visit_node(child, path, leaves);
+        }
+        match child.data()? {
+            [] => Ok(()),
+            hash if hash.len() == 32 => self.visit_hash(H256::from_slice(hash), path, leaves),
+            other => bail!("invalid child reference of {} bytes", other.len()),
+        }
+    }
+}
+
+/// Decodes a hex-prefix encoded path, along with whether it belongs to a leaf.
+fn decode_path(encoded: &[u8]) -> anyhow::Result<(Nibbles, bool)> {
+    let mut nibbles = Nibbles::from_bytes_be(encoded)?;
+    let flags = nibbles.pop_next_nibble_front();
+    ensure!(flags <= 3, "invalid hex-prefix flags {flags}");
+    if flags & 1 == 0 {
+        ensure!(
+            nibbles.pop_next_nibble_front() == 0,
+            "invalid hex-prefix padding"
+        );
+    }
+    Ok((nibbles, flags & 2 != 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use mpt_trie::partial_trie::{HashedPartialTrie, Node, PartialTrie as _};
+    use rlp::RlpStream;
+
+    use super::*;
+    use crate::typed_mpt::StateTrie as _;
+
+    /// Encodes `trie`, adding the nodes referenced by hash to `nodes`.
+    fn encode(trie: &HashedPartialTrie, nodes: &mut HashMap<H256, Vec<u8>>) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+        match &**trie {
+            Node::Empty => return rlp::NULL_RLP.to_vec(),
+            Node::Hash(_) => unreachable!(),
+            Node::Branch { children, value } => {
+                stream.begin_list(17);
+                for child in children {
+                    append_child(&mut stream, child, nodes);
+                }
+                stream.append(value);
+            }
+            Node::Extension { nibbles, child } => {
+                stream.begin_list(2);
+                stream.append(&nibbles.to_hex_prefix_encoding(false).as_ref());
+                append_child(&mut stream, child, nodes);
+            }
+            Node::Leaf { nibbles, value } => {
+                stream.begin_list(2);
+                stream.append(&nibbles.to_hex_prefix_encoding(true).as_ref());
+                stream.append(value);
+            }
+        }
+        stream.out().to_vec()
+    }
+
+    fn append_child(
+        stream: &mut RlpStream,
+        child: &HashedPartialTrie,
+        nodes: &mut HashMap<H256, Vec<u8>>,
+    ) {
+        let encoded = encode(child, nodes);
+        match encoded.len() < 32 {
+            true => {
+                stream.append_raw(&encoded, 1);
+            }
+            false => {
+                let hash = crate::hash(&encoded);
+                stream.append(&hash.as_bytes());
+                nodes.insert(hash, encoded);
+            }
+        }
+    }
+
+    fn node_pool(trie: &HashedPartialTrie) -> HashMap<H256, Vec<u8>> {
+        let mut nodes = HashMap::new();
+        let root = encode(trie, &mut nodes);
+        assert_eq!(crate::hash(&root), trie.hash());
+        nodes.insert(trie.hash(), root);
+        nodes
+    }
+
+    fn key(i: u64) -> H256 {
+        crate::hash(H256::from_low_u64_be(i))
+    }
+
+    #[test]
+    fn rebuild_tries() {
+        let mut storage = HashedPartialTrie::from(Node::Empty);
+        for slot in 1..=20u64 {
+            storage
+                .insert(
+                    Nibbles::from_h256_be(key(slot)),
+                    rlp::encode(&slot).to_vec(),
+                )
+                .unwrap();
+        }
+
+        let mut state = HashedPartialTrie::from(Node::Empty);
+        for i in 1..=20u64 {
+            let account = AccountRlp {
+                nonce: i.into(),
+                storage_root: match i {
+                    1 => storage.hash(),
+                    _ => EMPTY_TRIE_HASH,
+                },
+                ..Default::default()
+            };
+            state
+                .insert(
+                    Nibbles::from_h256_be(key(i)),
+                    rlp::encode(&account).to_vec(),
+                )
+                .unwrap();
+        }
+
+        let mut nodes = node_pool(&state);
+        nodes.extend(node_pool(&storage));
+        let witness = |nodes| ExecutionWitnessPreImages {
+            state_root: state.hash(),
+            state: nodes,
+            codes: HashMap::new(),
+            keys: HashMap::new(),
+        };
+
+        let full = frontend(witness(nodes.clone())).unwrap();
+        assert_eq!(full.state.root(), state.hash());
+        assert_eq!(full.state.iter().count(), 20);
+        assert_eq!(full.storage[&key(1)].root(), storage.hash());
+        assert_eq!(
+            full.storage[&key(1)]
+                .as_hashed_partial_trie()
+                .keys()
+                .count(),
+            20
+        );
+
+        // A missing node becomes a hash node.
+        let mut partial_nodes = nodes.clone();
+        partial_nodes.remove(&storage.hash());
+        let partial = frontend(witness(partial_nodes)).unwrap();
+        assert_eq!(partial.state.iter().count(), 20);
+        assert!(matches!(
+            **partial.storage[&key(1)].as_hashed_partial_trie(),
+            Node::Hash(hash) if hash == storage.hash()
+        ));
+
+        // A node which doesn't hash to its key is rejected.
+        let mut invalid_nodes = nodes;
+        invalid_nodes.insert(storage.hash(), vec![0xc0]);
+        assert!(frontend(witness(invalid_nodes)).is_err());
+    }
+}
@@ -82,8 +82,10 @@
 /// code.
 const _DEVELOPER_DOCS: () = ();
 
+mod batching;
 /// Defines the main functions used to generate the IR.
 mod decoding;
+mod execution_witness;
 /// Defines functions that processes a [BlockTrace] so that it is easier to turn
 /// the block transactions into IRs.
 mod processed_block_trace;
@@ -94,19 +96,22 @@ mod type1;
 #[allow(dead_code)]
 mod type2;
 mod typed_mpt;
+mod validate;
 mod wire;
 
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 
+pub use batching::BatchingStrategy;
 use ethereum_types::{Address, U256};
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
-use evm_arithmetization::GenerationInputs;
+use evm_arithmetization::{ChainProfile, GenerationInputs};
 use keccak_hash::keccak as hash;
 use keccak_hash::H256;
 use mpt_trie::partial_trie::{HashedPartialTrie, OnOrphanedHashNode};
-use processed_block_trace::ProcessedTxnBatchInfo;
+use processed_block_trace::{ProcessedBlockTracePreImages, ProcessedTxnBatchInfo};
 use serde::{Deserialize, Serialize};
-use typed_mpt::{StateMpt, StateTrie as _, StorageTrie, TrieKey};
+use typed_mpt::{StateMpt, StateTrie, StorageTrie, TrieKey};
+pub use validate::{validate, Problem};
 
 /// Core payload needed to generate proof for a block.
 /// Additional data retrievable from the blockchain node (using standard ETH RPC
@@ -139,6 +144,9 @@ pub enum BlockTraceTriePreImages {
     Separate(SeparateTriePreImages),
     /// The trie pre-image with combined state/storage tries.
     Combined(CombinedPreImages),
+    /// The trie pre-image as unordered pools of trie nodes, codes and keys, in
+    /// the format returned by e.g. `debug_executionWitness`.
+    ExecutionWitness(ExecutionWitnessPreImages),
 }
 
 /// State/Storage trie pre-images that are separate.
@@ -168,6 +176,45 @@ pub struct CombinedPreImages {
     pub compact: Vec<u8>,
 }
 
+impl CombinedPreImages {
+    /// Encodes the `state` trie, the `storage` tries keyed by hashed address
+    /// and the `code` of the accounts, for
+    /// [`BlockTraceTriePreImages::Combined`]. This is meant for building
+    /// witnesses in tests.
+    ///
+    /// Storage tries and code which are missing from `storage` and `code` are
+    /// hashed out.
+    pub fn encode_mpt(
+        state: &HashedPartialTrie,
+        storage: &HashMap<H256, HashedPartialTrie>,
+        code: impl IntoIterator<Item = Vec<u8>>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            compact: wire::serialize(type1::encode(state, storage, code)?),
+        })
+    }
+}
+
+/// State/Storage trie pre-images given as pools of RLP-encoded trie nodes, each
+/// keyed by its hash.
+///
+/// The tries are rebuilt by walking the pool from `state_root`. Nodes missing
+/// from the pool are hashed out.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExecutionWitnessPreImages {
+    /// Root of the state trie at the start of the block.
+    pub state_root: H256,
+    /// Nodes of the state and storage tries.
+    #[serde(default, with = "crate::hex_values")]
+    pub state: HashMap<H256, Vec<u8>>,
+    /// Contract codes.
+    #[serde(default, with = "crate::hex_values")]
+    pub codes: HashMap<H256, Vec<u8>>,
+    /// Pre-images of the hashed addresses and storage keys.
+    #[serde(default, with = "crate::hex_values")]
+    pub keys: HashMap<H256, Vec<u8>>,
+}
+
 /// A trie pre-image where state and storage are separate.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -268,6 +315,9 @@ pub struct OtherBlockData {
     pub b_data: BlockLevelData,
     /// State trie root hash at the checkpoint.
     pub checkpoint_state_trie_root: H256,
+    /// The chain the block belongs to.
+    #[serde(default)]
+    pub chain_profile: ChainProfile,
 }
 
 /// Data that is specific to a block and is constant for all txns in a given
@@ -282,25 +332,52 @@ pub struct BlockLevelData {
     pub withdrawals: Vec<(Address, U256)>,
 }
 
+/// Options for decoding a block, beyond what's needed to prove it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodingOptions {
+    /// Whether to decode the tries expected after each batch, so that witness
+    /// generation reports the diverging accounts and storage slots should the
+    /// final state root not match. This copies the state trie of every batch,
+    /// so it is meant for debugging.
+    pub expected_tries_after: bool,
+}
+
 /// TODO(0xaatif): <https://github.com/0xPolygonZero/zk_evm/issues/275>
 ///                document this once we have the API finalized
 pub fn entrypoint(
     trace: BlockTrace,
     other: OtherBlockData,
-    mut batch_size: usize,
-    use_burn_addr: bool,
+    batch_size: usize,
 ) -> anyhow::Result<Vec<GenerationInputs>> {
-    use anyhow::Context as _;
-    use mpt_trie::partial_trie::PartialTrie as _;
+    entrypoint_with_batching(trace, other, &BatchingStrategy::Fixed(batch_size))
+}
 
-    use crate::processed_block_trace::{
-        Hash2Code, ProcessedBlockTrace, ProcessedBlockTracePreImages,
-    };
-    use crate::PartialTriePreImages;
-    use crate::{
-        BlockTraceTriePreImages, CombinedPreImages, SeparateStorageTriesPreImage,
-        SeparateTriePreImage, SeparateTriePreImages,
+/// Like [`entrypoint`], with the transactions of the block split into batches
+/// according to `batching`.
+pub fn entrypoint_with_batching(
+    trace: BlockTrace,
+    other: OtherBlockData,
+    batching: &BatchingStrategy,
+) -> anyhow::Result<Vec<GenerationInputs>> {
+    entrypoint_iter(trace, other, batching, DecodingOptions::default())?.collect()
+}
+
+/// Like [`entrypoint_with_batching`], but the [`GenerationInputs`] of each
+/// batch are only decoded when the iterator reaches it, so that they can be
+/// proven while the following batches are being decoded.
+///
+/// Once the iterator yields an error, it yields nothing else.
+pub fn entrypoint_iter(
+    trace: BlockTrace,
+    other: OtherBlockData,
+    batching: &BatchingStrategy,
+    options: DecodingOptions,
+) -> anyhow::Result<GenerationInputsIter> {
+    let cycles = match batching {
+        BatchingStrategy::Cycles(_) => Some(batching::estimate_cycles(&trace, &other)?),
+        _ => None,
     };
+    let batch_sizes = batching.batch_sizes(&trace.txn_info, cycles.as_deref())?;
 
     let BlockTrace {
         trie_pre_images,
@@ -308,7 +385,41 @@ pub fn entrypoint(
         txn_info,
     } = trace;
 
-    let pre_images = match trie_pre_images {
+    Ok(GenerationInputsIter(Box::new(into_generation_inputs(
+        process_pre_images(trie_pre_images)?,
+        code_db,
+        txn_info,
+        other,
+        batch_sizes,
+        options,
+    )?)))
+}
+
+/// The [`GenerationInputs`] of the batches of a block, decoded one at a time.
+///
+/// See [`entrypoint_iter`].
+pub struct GenerationInputsIter(Box<dyn Iterator<Item = anyhow::Result<GenerationInputs>> + Send>);
+
+impl Iterator for GenerationInputsIter {
+    type Item = anyhow::Result<GenerationInputs>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// The frontends, one for each pre-image format.
+fn process_pre_images(
+    trie_pre_images: BlockTraceTriePreImages,
+) -> anyhow::Result<ProcessedBlockTracePreImages> {
+    use anyhow::Context as _;
+    use mpt_trie::partial_trie::PartialTrie as _;
+
+    Ok(match trie_pre_images {
         BlockTraceTriePreImages::Separate(SeparateTriePreImages {
             state: SeparateTriePreImage::Direct(state),
             storage: SeparateStorageTriesPreImage::MultipleTries(storage),
@@ -373,17 +484,53 @@ pub fn entrypoint(
                     state,
                     storage: storage.into_iter().collect(),
                 },
-                extra_code_hash_mappings: match code.is_empty() {
-                    true => None,
-                    false => Some(
-                        code.into_iter()
-                            .map(|it| (crate::hash(&it), it.into_vec()))
-                            .collect(),
-                    ),
+                extra_code_hash_mappings: code2hash_mappings(code),
+            }
+        }
+        BlockTraceTriePreImages::ExecutionWitness(witness) => {
+            let execution_witness::Frontend {
+                state,
+                code,
+                storage,
+            } = execution_witness::frontend(witness)
+                .context("couldn't rebuild tries from execution witness")?;
+            ProcessedBlockTracePreImages {
+                tries: PartialTriePreImages {
+                    state,
+                    storage: storage.into_iter().collect(),
                 },
+                extra_code_hash_mappings: (!code.is_empty()).then_some(code),
             }
         }
-    };
+    })
+}
+
+fn code2hash_mappings(
+    code: impl IntoIterator<Item = nunny::NonEmpty<Vec<u8>>>,
+) -> Option<HashMap<H256, Vec<u8>>> {
+    let mappings = code
+        .into_iter()
+        .map(|it| (crate::hash(&it), it.into_vec()))
+        .collect::<HashMap<_, _>>();
+    match mappings.is_empty() {
+        true => None,
+        false => Some(mappings),
+    }
+}
+
+/// The backend, shared by all state trie formats.
+fn into_generation_inputs<StateTrieT>(
+    pre_images: ProcessedBlockTracePreImages<StateTrieT>,
+    code_db: BTreeSet<Vec<u8>>,
+    txn_info: Vec<TxnInfo>,
+    other: OtherBlockData,
+    batch_sizes: Vec<usize>,
+    options: DecodingOptions,
+) -> anyhow::Result<decoding::TxnProofGenIr<StateTrieT>>
+where
+    StateTrieT: StateTrie + Clone + Default + TryIntoExt<HashedPartialTrie>,
+{
+    use crate::processed_block_trace::{Hash2Code, ProcessedBlockTrace};
 
     let all_accounts_in_pre_images = pre_images.tries.state.iter().collect::<Vec<_>>();
 
@@ -398,16 +545,18 @@ pub fn entrypoint(
         )
         .collect::<Hash2Code>();
 
-    // Make sure the batch size is smaller than the total number of transactions,
-    // or we would need to generate dummy proofs for the aggregation layers.
-    if batch_size > txn_info.len() {
-        batch_size = txn_info.len() / 2 + 1;
-    }
+    let last_tx_idx = batch_sizes.len().saturating_sub(1);
 
-    let last_tx_idx = txn_info.len().saturating_sub(1) / batch_size;
+    let mut batches = Vec::with_capacity(batch_sizes.len());
+    let mut rest = txn_info.as_slice();
+    for batch_size in batch_sizes {
+        let (batch, tail) = rest.split_at(batch_size);
+        batches.push(batch);
+        rest = tail;
+    }
 
-    let mut txn_info = txn_info
-        .chunks(batch_size)
+    let mut txn_info = batches
+        .into_iter()
         .enumerate()
         .map(|(i, t)| {
             let extra_state_accesses = if last_tx_idx == i {
@@ -429,6 +578,7 @@ pub fn entrypoint(
                 &all_accounts_in_pre_images,
                 &extra_state_accesses,
                 &mut hash2code,
+                other.chain_profile,
             )
         })
         .collect::<Result<Vec<_>, _>>()?;
@@ -437,21 +587,20 @@ pub fn entrypoint(
         txn_info.push(ProcessedTxnBatchInfo::default());
     }
 
-    decoding::into_txn_proof_gen_ir(
+    Ok(decoding::into_txn_proof_gen_ir(
         ProcessedBlockTrace {
             tries: pre_images.tries,
             txn_info,
             withdrawals: other.b_data.withdrawals.clone(),
         },
         other,
-        use_burn_addr,
-        batch_size,
-    )
+        options,
+    ))
 }
 
 #[derive(Debug, Default)]
-struct PartialTriePreImages {
-    pub state: StateMpt,
+struct PartialTriePreImages<StateTrieT = StateMpt> {
+    pub state: StateTrieT,
     pub storage: HashMap<H256, StorageTrie>,
 }
 
@@ -481,6 +630,32 @@ mod hex {
     }
 }
 
+/// Like [`hex`], for the values of a map keyed by hashes.
+mod hex_values {
+    use std::collections::HashMap;
+
+    use keccak_hash::H256;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(transparent)]
+    struct Hex(#[serde(with = "crate::hex")] Vec<u8>);
+
+    pub fn serialize<S: Serializer>(
+        map: &HashMap<H256, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(k, v)| (k, Hex(v.clone()))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<H256, Vec<u8>>, D::Error> {
+        let map = HashMap::<H256, Hex>::deserialize(deserializer)?;
+        Ok(map.into_iter().map(|(k, Hex(v))| (k, v)).collect())
+    }
+}
+
 trait TryIntoExt<T> {
     type Error: std::error::Error + Send + Sync + 'static;
     fn try_into(self) -> Result<T, Self::Error>;
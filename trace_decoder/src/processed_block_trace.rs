@@ -3,28 +3,33 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 use anyhow::{bail, Context as _};
 use ethereum_types::{Address, H256, U256};
 use evm_arithmetization::generation::mpt::{AccountRlp, LegacyReceiptRlp};
+use evm_arithmetization::ChainProfile;
 use itertools::Itertools;
 use zk_evm_common::EMPTY_TRIE_HASH;
 
-use crate::typed_mpt::{StateTrie as _, TrieKey};
+use crate::typed_mpt::{StateMpt, StateTrie, TrieKey};
 use crate::PartialTriePreImages;
 use crate::{hash, TxnTrace};
 use crate::{ContractCodeUsage, TxnInfo};
 
 const FIRST_PRECOMPILE_ADDRESS: U256 = U256([1, 0, 0, 0]);
 const LAST_PRECOMPILE_ADDRESS: U256 = U256([10, 0, 0, 0]);
+/// EIP-2537 precompiles, only enabled on some chains.
+const LAST_BLS12_381_PRECOMPILE_ADDRESS: U256 = U256([17, 0, 0, 0]);
+/// RIP-7212 precompile, only enabled on some chains.
+const P256_VERIFY_ADDRESS: U256 = U256([0x100, 0, 0, 0]);
 
 /// A processed block trace, ready to be used to generate prover input payloads.
 #[derive(Debug)]
-pub(crate) struct ProcessedBlockTrace {
-    pub tries: PartialTriePreImages,
+pub(crate) struct ProcessedBlockTrace<StateTrieT = StateMpt> {
+    pub tries: PartialTriePreImages<StateTrieT>,
     pub txn_info: Vec<ProcessedTxnBatchInfo>,
     pub withdrawals: Vec<(Address, U256)>,
 }
 
 #[derive(Debug)]
-pub(crate) struct ProcessedBlockTracePreImages {
-    pub tries: PartialTriePreImages,
+pub(crate) struct ProcessedBlockTracePreImages<StateTrieT = StateMpt> {
+    pub tries: PartialTriePreImages<StateTrieT>,
     pub extra_code_hash_mappings: Option<HashMap<H256, Vec<u8>>>,
 }
 
@@ -76,10 +81,11 @@ impl FromIterator<Vec<u8>> for Hash2Code {
 impl TxnInfo {
     pub(crate) fn into_processed_txn_info(
         tx_infos: &[Self],
-        tries: &PartialTriePreImages,
+        tries: &PartialTriePreImages<impl StateTrie>,
         all_accounts_in_pre_image: &[(H256, AccountRlp)],
         extra_state_accesses: &[Address],
         hash2code: &mut Hash2Code,
+        chain_profile: ChainProfile,
     ) -> anyhow::Result<ProcessedTxnBatchInfo> {
         let mut nodes_used_by_txn = NodesUsedByTxnBatch::default();
         let mut contract_code_accessed = HashSet::from([vec![]]); // we always "access" empty code
@@ -183,14 +189,13 @@ impl TxnInfo {
                     }
                 }
 
-                let is_precompile = (FIRST_PRECOMPILE_ADDRESS..LAST_PRECOMPILE_ADDRESS)
-                    .contains(&U256::from_big_endian(&addr.0));
-
                 // Trie witnesses will only include accessed precompile accounts as hash
                 // nodes if the transaction calling them reverted. If this is the case, we
                 // shouldn't include them in this transaction's `state_accesses` to allow the
                 // decoder to build a minimal state trie without hitting any hash node.
-                if !is_precompile || tries.state.get_by_address(*addr).is_some() {
+                if !is_precompile(*addr, chain_profile)
+                    || tries.state.get_by_address(*addr).is_some()
+                {
                     nodes_used_by_txn.state_accesses.insert(*addr);
                 }
 
@@ -254,7 +259,18 @@ impl TxnInfo {
     }
 }
 
-fn check_receipt_bytes(bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+/// Whether `addr` is that of a precompile enabled on the chain.
+pub(crate) fn is_precompile(addr: Address, chain_profile: ChainProfile) -> bool {
+    let addr_u256 = U256::from_big_endian(&addr.0);
+    let last_precompile_address = match chain_profile.has_bls12_381() {
+        true => LAST_BLS12_381_PRECOMPILE_ADDRESS,
+        false => LAST_PRECOMPILE_ADDRESS,
+    };
+    (FIRST_PRECOMPILE_ADDRESS..=last_precompile_address).contains(&addr_u256)
+        || (chain_profile.has_p256_verify() && addr_u256 == P256_VERIFY_ADDRESS)
+}
+
+pub(crate) fn check_receipt_bytes(bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
     match rlp::decode::<LegacyReceiptRlp>(&bytes) {
         Ok(_) => Ok(bytes),
         Err(_) => {
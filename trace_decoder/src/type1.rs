@@ -2,13 +2,13 @@
 //! Ethereum node (a.k.a "jerigon").
 
 use std::array;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use anyhow::{bail, ensure, Context as _};
 use either::Either;
 use evm_arithmetization::generation::mpt::AccountRlp;
 use keccak_hash::H256;
-use mpt_trie::partial_trie::OnOrphanedHashNode;
+use mpt_trie::partial_trie::{HashedPartialTrie, OnOrphanedHashNode};
 use nunny::NonEmpty;
 use u4::U4;
 
@@ -378,6 +378,160 @@ fn finish_stack(v: &mut Vec<Node>) -> anyhow::Result<Execution> {
     }
 }
 
+/// The inverse of [`frontend`], for building witnesses in tests.
+///
+/// `storage` tries are keyed by hashed address. Storage tries and code which
+/// are missing from `storage` and `code` are written as
+/// [`Instruction::Hash`]es.
+pub fn encode(
+    state: &HashedPartialTrie,
+    storage: &HashMap<H256, HashedPartialTrie>,
+    code: impl IntoIterator<Item = Vec<u8>>,
+) -> anyhow::Result<Vec<Instruction>> {
+    use mpt_trie::nibbles::Nibbles;
+    use mpt_trie::partial_trie::{Node, PartialTrie as _};
+    use zk_evm_common::EMPTY_TRIE_HASH;
+
+    /// Emits the instructions of `node` in execution order, that is children
+    /// first.
+    fn encode_node(
+        node: &HashedPartialTrie,
+        path: Nibbles,
+        on_leaf: &mut impl FnMut(
+            Nibbles,
+            NonEmpty<Vec<U4>>,
+            &[u8],
+            &mut Vec<Instruction>,
+        ) -> anyhow::Result<()>,
+        instructions: &mut Vec<Instruction>,
+    ) -> anyhow::Result<()> {
+        match &**node {
+            Node::Empty => instructions.push(Instruction::EmptyRoot),
+            Node::Hash(hash) => instructions.push(Instruction::Hash { raw_hash: hash.0 }),
+            Node::Leaf { nibbles, value } => on_leaf(
+                path.merge_nibbles(nibbles),
+                nibbles2key(*nibbles)?,
+                value,
+                instructions,
+            )?,
+            Node::Extension { nibbles, child } => {
+                encode_node(child, path.merge_nibbles(nibbles), on_leaf, instructions)?;
+                instructions.push(Instruction::Extension {
+                    key: nibbles2key(*nibbles)?,
+                });
+            }
+            Node::Branch { children, value } => {
+                ensure!(value.is_empty(), "unsupported value in branch node");
+                let mut mask = 0;
+                for (ix, child) in children.iter().enumerate() {
+                    let child: &HashedPartialTrie = child;
+                    if !matches!(**child, Node::Empty) {
+                        encode_node(child, path.merge_nibble(ix as u8), on_leaf, instructions)?;
+                        mask |= 1 << ix;
+                    }
+                }
+                instructions.push(Instruction::Branch { mask });
+            }
+        }
+        Ok(())
+    }
+
+    fn nibbles2key(mut nibbles: Nibbles) -> anyhow::Result<NonEmpty<Vec<U4>>> {
+        let mut key = vec![];
+        while !nibbles.is_empty() {
+            key.push(U4::new(nibbles.pop_next_nibble_front()).context("invalid nibble")?);
+        }
+        NonEmpty::<Vec<_>>::new(key)
+            .ok()
+            .context("empty key for leaf or extension")
+    }
+
+    fn storage_leaf(
+        _: Nibbles,
+        key: NonEmpty<Vec<U4>>,
+        value: &[u8],
+        instructions: &mut Vec<Instruction>,
+    ) -> anyhow::Result<()> {
+        let value = rlp::decode::<Vec<u8>>(value).context("invalid value in storage trie")?;
+        instructions.push(Instruction::Leaf {
+            key,
+            value: NonEmpty::<Vec<_>>::new(value)
+                .ok()
+                .context("empty value in storage trie")?,
+        });
+        Ok(())
+    }
+
+    let code = code
+        .into_iter()
+        .filter_map(|it| NonEmpty::<Vec<_>>::new(it).ok())
+        .map(|it| (crate::hash(&it), it))
+        .collect::<HashMap<_, _>>();
+    let mut state_leaf = |path: Nibbles,
+                          key: NonEmpty<Vec<U4>>,
+                          value: &[u8],
+                          instructions: &mut Vec<Instruction>|
+     -> anyhow::Result<()> {
+        let haddr = TrieKey::from_nibbles(path)
+            .into_hash()
+            .context("invalid depth for leaf of state trie")?;
+        let account: AccountRlp =
+            rlp::decode(value).with_context(|| format!("invalid account at {haddr:x}"))?;
+        ensure!(
+            account.nonce <= u64::MAX.into(),
+            "nonce of account {haddr:x} doesn't fit in a u64"
+        );
+
+        // the code is popped after the storage, so must be pushed before it
+        let has_code = account.code_hash != crate::hash([]);
+        if has_code {
+            instructions.push(match code.get(&account.code_hash) {
+                Some(raw_code) => Instruction::Code {
+                    raw_code: raw_code.clone(),
+                },
+                None => Instruction::Hash {
+                    raw_hash: account.code_hash.0,
+                },
+            });
+        }
+        let has_storage = account.storage_root != EMPTY_TRIE_HASH;
+        if has_storage {
+            match storage.get(&haddr) {
+                Some(storage) => {
+                    ensure!(
+                        storage.hash() == account.storage_root,
+                        "storage trie of account {haddr:x} has root {:x}, expected {:x}",
+                        storage.hash(),
+                        account.storage_root
+                    );
+                    encode_node(storage, Nibbles::default(), &mut storage_leaf, instructions)?
+                }
+                None => instructions.push(Instruction::Hash {
+                    raw_hash: account.storage_root.0,
+                }),
+            }
+        }
+
+        instructions.push(Instruction::AccountLeaf {
+            key,
+            nonce: Some(account.nonce.as_u64()),
+            balance: Some(account.balance),
+            has_code,
+            has_storage,
+        });
+        Ok(())
+    };
+
+    let mut instructions = vec![];
+    encode_node(
+        state,
+        Nibbles::default(),
+        &mut state_leaf,
+        &mut instructions,
+    )?;
+    Ok(instructions)
+}
+
 #[test]
 fn test_tries() {
     for (ix, case) in serde_json::from_str::<Vec<super::Case>>(include_str!(
@@ -399,3 +553,96 @@ fn test_tries() {
         }
     }
 }
+
+#[test]
+fn test_encode() {
+    #[track_caller]
+    fn assert_round_trips(expected: &Frontend) {
+        let bytes = crate::wire::serialize(
+            encode(
+                expected.state.as_hashed_partial_trie(),
+                &expected
+                    .storage
+                    .iter()
+                    .map(|(haddr, storage)| (*haddr, storage.as_hashed_partial_trie().clone()))
+                    .collect(),
+                expected.code.iter().map(|it| it.to_vec()),
+            )
+            .unwrap(),
+        );
+        let actual = frontend(crate::wire::parse(&bytes).unwrap()).unwrap();
+
+        assert_eq!(expected.state.root(), actual.state.root());
+        for (haddr, storage) in &expected.storage {
+            if storage.root() != StateMpt::default().root() {
+                assert_eq!(storage.root(), actual.storage[haddr].root());
+            }
+        }
+        // code which no account refers to is dropped
+        let code_hashes = expected
+            .state
+            .iter()
+            .map(|(_, acct)| acct.code_hash)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            expected
+                .code
+                .iter()
+                .filter(|it| code_hashes.contains(&crate::hash(it)))
+                .cloned()
+                .collect::<BTreeSet<_>>(),
+            actual.code
+        );
+    }
+
+    for (ix, case) in serde_json::from_str::<Vec<super::Case>>(include_str!(
+        "../tests/data/tries/zero_jerigon.json"
+    ))
+    .unwrap()
+    .into_iter()
+    .enumerate()
+    {
+        println!("case {}", ix);
+        let frontend = frontend(crate::wire::parse(&case.bytes).unwrap()).unwrap();
+        assert_round_trips(&frontend);
+    }
+
+    // A hand-crafted witness, with hashed out storage and code.
+    let mut minimal = Frontend::default();
+    assert_round_trips(&minimal);
+
+    let code = nunny::vec![0x60, 0x00];
+    let mut storage = StorageTrie::new(OnOrphanedHashNode::CollapseToExtension);
+    for slot in 1..=3u64 {
+        storage
+            .insert(
+                TrieKey::from_hash(crate::hash(H256::from_low_u64_be(slot))),
+                rlp::encode(&slot).to_vec(),
+            )
+            .unwrap();
+    }
+    let accounts = [
+        AccountRlp {
+            nonce: 1.into(),
+            balance: 100.into(),
+            storage_root: storage.root(),
+            code_hash: crate::hash(&code),
+        },
+        AccountRlp {
+            storage_root: H256::repeat_byte(0x22),
+            code_hash: H256::repeat_byte(0x33),
+            ..Default::default()
+        },
+        AccountRlp::default(),
+    ];
+    for (ix, account) in accounts.into_iter().enumerate() {
+        let address = ethereum_types::Address::from_low_u64_be(ix as u64);
+        minimal.state.insert_by_address(address, account).unwrap();
+    }
+    minimal.storage.insert(
+        crate::hash(ethereum_types::Address::from_low_u64_be(0)),
+        storage,
+    );
+    minimal.code.insert(code);
+    assert_round_trips(&minimal);
+}
@@ -160,18 +160,17 @@ fn node2trie(
     } in leaves
     {
         let address = ethereum_types::Address::from_slice(&address);
+        let key = leaf_key(address, &node_type);
         let collated = collated.entry(address).or_default();
         let value = ethereum_types::U256::from_big_endian(&value);
-        let key = match node_type {
+        match node_type {
             SmtLeafType::Balance => {
                 ensure!(collated.balance.is_none(), "double write of field");
                 collated.balance = Some(value);
-                smt_trie::keys::key_balance(address)
             }
             SmtLeafType::Nonce => {
                 ensure!(collated.nonce.is_none(), "double write of field");
                 collated.nonce = Some(value);
-                smt_trie::keys::key_nonce(address)
             }
             SmtLeafType::Code => {
                 ensure!(collated.code_hash.is_none(), "double write of field");
@@ -180,21 +179,35 @@ fn node2trie(
                     value.to_big_endian(it.as_bytes_mut());
                     it
                 });
-                smt_trie::keys::key_code(address)
             }
-            SmtLeafType::Storage(it) => {
+            SmtLeafType::Storage(_) => {
                 ensure!(collated.storage_root.is_none(), "double write of field");
                 // TODO(0xaatif): https://github.com/0xPolygonZero/zk_evm/issues/275
                 //                do we not do anything with the storage here?
-                smt_trie::keys::key_storage(address, ethereum_types::U256::from_big_endian(&it))
             }
-            SmtLeafType::CodeLength => smt_trie::keys::key_code_length(address),
-        };
+            SmtLeafType::CodeLength => {}
+        }
         trie.set(key, value)
     }
     Ok((trie, collated))
 }
 
+/// The key of a leaf in the [`SmtTrie`].
+///
+/// # Panics
+/// - if [`SmtLeafType::Storage`] is the wrong length.
+fn leaf_key(address: ethereum_types::Address, node_type: &SmtLeafType) -> smt_trie::smt::Key {
+    match node_type {
+        SmtLeafType::Balance => smt_trie::keys::key_balance(address),
+        SmtLeafType::Nonce => smt_trie::keys::key_nonce(address),
+        SmtLeafType::Code => smt_trie::keys::key_code(address),
+        SmtLeafType::Storage(it) => {
+            smt_trie::keys::key_storage(address, ethereum_types::U256::from_big_endian(it))
+        }
+        SmtLeafType::CodeLength => smt_trie::keys::key_code_length(address),
+    }
+}
+
 /// # Panics
 /// - on overcapacity
 fn bits2bits(ours: BitVec) -> smt_trie::bits::Bits {
@@ -224,6 +237,79 @@ fn iter_leaves(node: Node) -> Box<dyn Iterator<Item = (BitVec, Either<[u8; 32],
     }
 }
 
+/// The inverse of [`frontend`], for building witnesses in tests.
+///
+/// The `leaves` are laid out in a tree according to their keys in the
+/// [`SmtTrie`], after the `code`.
+pub fn encode(
+    leaves: impl IntoIterator<Item = SmtLeaf>,
+    code: impl IntoIterator<Item = NonEmpty<Vec<u8>>>,
+) -> anyhow::Result<Vec<Instruction>> {
+    use smt_trie::bits::Bits;
+
+    /// Emits the instructions of the subtree at `depth` holding `leaves`, in
+    /// pre-order.
+    fn encode_node(
+        leaves: Vec<(Bits, SmtLeaf)>,
+        depth: usize,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        match <[_; 1]>::try_from(leaves) {
+            Ok([(_, leaf)]) => instructions.push(Instruction::SmtLeaf(leaf)),
+            Err(leaves) => {
+                let (left, right) = leaves
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|(bits, _)| !bits.get_bit(depth));
+                instructions.push(Instruction::Branch {
+                    mask: u64::from(!left.is_empty()) | (u64::from(!right.is_empty()) << 1),
+                });
+                for side in [left, right] {
+                    if !side.is_empty() {
+                        encode_node(side, depth + 1, instructions)
+                    }
+                }
+            }
+        }
+    }
+
+    let mut keyed = std::collections::BTreeMap::new();
+    for leaf in leaves {
+        ensure!(
+            leaf.address.len() == 20,
+            "expected an address of 20 bytes, got {}",
+            leaf.address.len()
+        );
+        ensure!(
+            leaf.value.len() <= 32,
+            "expected a value of at most 32 bytes, got {}",
+            leaf.value.len()
+        );
+        if let SmtLeafType::Storage(slot) = &leaf.node_type {
+            ensure!(
+                slot.len() <= 32,
+                "expected a storage slot of at most 32 bytes, got {}",
+                slot.len()
+            );
+        }
+        let key = leaf_key(
+            ethereum_types::Address::from_slice(&leaf.address),
+            &leaf.node_type,
+        );
+        ensure!(
+            keyed.insert(key.split(), leaf).is_none(),
+            "duplicate leaf for key {key:?}"
+        );
+    }
+    ensure!(!keyed.is_empty(), "no leaves to encode");
+
+    let mut instructions = code
+        .into_iter()
+        .map(|raw_code| Instruction::Code { raw_code })
+        .collect();
+    encode_node(keyed.into_iter().collect(), 0, &mut instructions);
+    Ok(instructions)
+}
+
 #[test]
 fn test_tries() {
     for (ix, case) in serde_json::from_str::<Vec<super::Case>>(include_str!(
@@ -243,3 +329,52 @@ fn test_tries() {
         });
     }
 }
+
+#[test]
+fn test_encode() {
+    let leaf = |node_type, address, value: u64| SmtLeaf {
+        node_type,
+        address: NonEmpty::<Vec<_>>::new(
+            ethereum_types::Address::from_low_u64_be(address).0.to_vec(),
+        )
+        .unwrap(),
+        value: NonEmpty::<Vec<_>>::new(value.to_be_bytes().to_vec()).unwrap(),
+    };
+    let leaves = vec![
+        leaf(SmtLeafType::Balance, 1, 100),
+        leaf(SmtLeafType::Nonce, 1, 2),
+        leaf(SmtLeafType::Storage(nunny::vec![1]), 1, 3),
+        leaf(SmtLeafType::Balance, 2, 200),
+        leaf(SmtLeafType::Code, 2, 0xc0de),
+        leaf(SmtLeafType::CodeLength, 2, 2),
+    ];
+    let code = nunny::vec![0x60, 0x00];
+
+    let instructions = encode(leaves.clone(), [code.clone()]).unwrap();
+    let Frontend {
+        trie,
+        code: actual_code,
+        collation,
+    } = frontend(crate::wire::parse(&crate::wire::serialize(instructions)).unwrap()).unwrap();
+
+    let mut expected = SmtTrie::default();
+    for SmtLeaf {
+        node_type,
+        address,
+        value,
+    } in &leaves
+    {
+        expected.set(
+            leaf_key(ethereum_types::Address::from_slice(address), node_type),
+            ethereum_types::U256::from_big_endian(value),
+        );
+    }
+    assert_eq!(expected.root, trie.root);
+    assert_eq!(actual_code, HashSet::from([code]));
+    assert_eq!(
+        collation[&ethereum_types::Address::from_low_u64_be(1)].nonce,
+        Some(2.into())
+    );
+
+    assert!(encode([leaves[0].clone(), leaves[0].clone()], []).is_err());
+}
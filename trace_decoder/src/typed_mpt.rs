@@ -254,11 +254,6 @@ impl StateMpt {
     ) -> anyhow::Result<Option<AccountRlp>> {
         self.typed.insert(TrieKey::from_hash(key), account)
     }
-    pub fn iter(&self) -> impl Iterator<Item = (H256, AccountRlp)> + '_ {
-        self.typed
-            .iter()
-            .map(|(key, rlp)| (key.into_hash().expect("key is always H256"), rlp))
-    }
     pub fn as_hashed_partial_trie(&self) -> &mpt_trie::partial_trie::HashedPartialTrie {
         self.typed.as_hashed_partial_trie()
     }
@@ -310,6 +305,11 @@ impl StateTrie for StateMpt {
         };
         Ok(())
     }
+    fn iter(&self) -> impl Iterator<Item = (H256, AccountRlp)> + '_ {
+        self.typed
+            .iter()
+            .map(|(key, rlp)| (key.into_hash().expect("key is always H256"), rlp))
+    }
 }
 
 impl From<StateMpt> for HashedPartialTrie {
@@ -337,6 +337,10 @@ pub trait StateTrie {
     fn reporting_remove(&mut self, address: Address) -> anyhow::Result<Option<TrieKey>>;
     fn contains_address(&self, address: Address) -> bool;
     fn trim_to(&mut self, address: impl IntoIterator<Item = TrieKey>) -> anyhow::Result<()>;
+    /// Iterate over all hydrated accounts, keyed by hashed address.
+    ///
+    /// Note that this returns owned items.
+    fn iter(&self) -> impl Iterator<Item = (H256, AccountRlp)> + '_;
 }
 
 impl StateTrie for StateSmt {
@@ -365,6 +369,11 @@ impl StateTrie for StateSmt {
         let _ = address;
         Ok(())
     }
+    fn iter(&self) -> impl Iterator<Item = (H256, AccountRlp)> + '_ {
+        self.address2state
+            .iter()
+            .map(|(addr, acct)| (keccak_hash::keccak(addr), *acct))
+    }
 }
 
 /// Global, per-account.
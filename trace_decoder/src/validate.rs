@@ -0,0 +1,431 @@
+//! Pre-flight checks of a [`BlockTrace`] against the [`OtherBlockData`] of its
+//! block.
+//!
+//! The decoder assumes well-formed inputs, and malformed ones only fail late,
+//! often deep inside the kernel. [`validate`] catches the common
+//! inconsistencies up front, and reports all of them at once.
+
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use ethereum_types::{Address, H256, U256};
+use evm_arithmetization::generation::mpt::LegacyReceiptRlp;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, Node};
+use zk_evm_common::{EMPTY_CODE_HASH, EMPTY_TRIE_HASH};
+
+use crate::processed_block_trace::{check_receipt_bytes, is_precompile};
+use crate::typed_mpt::{StateMpt, StateTrie as _, TrieKey};
+use crate::{
+    hash, BlockTrace, BlockTraceTriePreImages, ContractCodeUsage, OtherBlockData,
+    PartialTriePreImages, TxnTrace,
+};
+
+/// An inconsistency found by [`validate`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Problem {
+    /// The pre-images couldn't be decoded.
+    #[error("invalid trie pre-images: {0}")]
+    InvalidPreImages(String),
+    /// A transaction reads code which is neither in the code db nor in the
+    /// pre-images, nor created by a previous transaction of the block.
+    #[error("txn {txn_index}: missing code {code_hash:x} read by {address:x}")]
+    MissingCode {
+        txn_index: usize,
+        address: Address,
+        code_hash: H256,
+    },
+    /// An account of an execution witness has code which is neither in the
+    /// witness nor in the code db.
+    #[error(
+        "code {code_hash:x} of account {hashed_address:x} is missing from the execution witness"
+    )]
+    MissingWitnessCode {
+        hashed_address: H256,
+        code_hash: H256,
+    },
+    /// An account touched by a transaction is behind a hash node.
+    #[error("txn {txn_index}: account {address:x} is hashed out in the pre-images")]
+    HashedOutAccount { txn_index: usize, address: Address },
+    /// A storage slot touched by a transaction is behind a hash node, or its
+    /// storage trie is missing.
+    #[error(
+        "txn {txn_index}: slot {slot:x} of account {address:x} is hashed out in the pre-images"
+    )]
+    HashedOutSlot {
+        txn_index: usize,
+        address: Address,
+        slot: H256,
+    },
+    /// The account of a withdrawal is behind a hash node.
+    #[error("withdrawal account {address:x} is hashed out in the pre-images")]
+    HashedOutWithdrawal { address: Address },
+    /// The receipt of a transaction couldn't be decoded.
+    #[error("txn {txn_index}: invalid receipt: {reason}")]
+    InvalidReceipt { txn_index: usize, reason: String },
+    /// The cumulative gas of a receipt doesn't match the gas used by the
+    /// transactions so far.
+    #[error("txn {txn_index}: receipt has cumulative gas {receipt}, but the txns used {traced}")]
+    CumulativeGasMismatch {
+        txn_index: usize,
+        receipt: U256,
+        traced: U256,
+    },
+    /// The gas used by all transactions doesn't match the block's.
+    #[error("block used {block} gas, but the txns used {traced}")]
+    BlockGasMismatch { block: U256, traced: U256 },
+}
+
+/// Checks that `trace` is consistent with itself and with `other`, returning
+/// every [`Problem`] found.
+pub fn validate(trace: &BlockTrace, other: &OtherBlockData) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    match crate::process_pre_images(trace.trie_pre_images.clone()) {
+        Ok(pre_images) => {
+            let codes = trace
+                .code_db
+                .iter()
+                .map(hash)
+                .chain(
+                    pre_images
+                        .extra_code_hash_mappings
+                        .iter()
+                        .flat_map(|it| it.keys().copied()),
+                )
+                .collect();
+            if let BlockTraceTriePreImages::ExecutionWitness(_) = trace.trie_pre_images {
+                check_witness_code(&pre_images.tries.state, &codes, &mut problems);
+            }
+            check_code(trace, codes, &mut problems);
+            check_accesses(trace, other, &pre_images.tries, &mut problems);
+        }
+        Err(e) => problems.push(Problem::InvalidPreImages(format!("{e:#}"))),
+    }
+    check_gas(trace, other, &mut problems);
+
+    problems
+}
+
+fn check_code(trace: &BlockTrace, mut codes: HashSet<H256>, problems: &mut Vec<Problem>) {
+    for (txn_index, txn) in trace.txn_info.iter().enumerate() {
+        for (address, TxnTrace { code_usage, .. }) in &txn.traces {
+            match code_usage {
+                Some(ContractCodeUsage::Read(code_hash)) if !codes.contains(code_hash) => problems
+                    .push(Problem::MissingCode {
+                        txn_index,
+                        address: *address,
+                        code_hash: *code_hash,
+                    }),
+                Some(ContractCodeUsage::Write(code)) => {
+                    codes.insert(hash(code));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Every account of an execution witness must have its code either in the
+/// witness or in the code db.
+fn check_witness_code(state: &StateMpt, codes: &HashSet<H256>, problems: &mut Vec<Problem>) {
+    for (hashed_address, account) in state.iter() {
+        if account.code_hash != EMPTY_CODE_HASH && !codes.contains(&account.code_hash) {
+            problems.push(Problem::MissingWitnessCode {
+                hashed_address,
+                code_hash: account.code_hash,
+            });
+        }
+    }
+}
+
+/// Tells which parts of the pre-images are hashed out.
+trait HashedOut {
+    fn account(&self, address: Address) -> bool;
+    fn slot(&self, address: Address, slot: H256) -> bool;
+}
+
+impl HashedOut for PartialTriePreImages<StateMpt> {
+    fn account(&self, address: Address) -> bool {
+        hash_node_on_path(
+            self.state.as_hashed_partial_trie(),
+            TrieKey::from_address(address).into_nibbles(),
+        )
+        .is_some()
+    }
+
+    fn slot(&self, address: Address, slot: H256) -> bool {
+        let Some(account) = self.state.get_by_address(address) else {
+            // The account is created by the transaction.
+            return false;
+        };
+        match self.storage.get(&hash(address)) {
+            Some(storage) => hash_node_on_path(
+                storage.as_hashed_partial_trie(),
+                TrieKey::from_hash(hash(slot)).into_nibbles(),
+            )
+            .is_some(),
+            None => account.storage_root != EMPTY_TRIE_HASH,
+        }
+    }
+}
+
+fn check_accesses(
+    trace: &BlockTrace,
+    other: &OtherBlockData,
+    tries: &impl HashedOut,
+    problems: &mut Vec<Problem>,
+) {
+    for (txn_index, txn) in trace.txn_info.iter().enumerate() {
+        for (
+            &address,
+            TxnTrace {
+                storage_read,
+                storage_written,
+                ..
+            },
+        ) in &txn.traces
+        {
+            // Precompiles may be hashed out, see `into_processed_txn_info`.
+            if is_precompile(address, other.chain_profile) {
+                continue;
+            }
+            if tries.account(address) {
+                problems.push(Problem::HashedOutAccount { txn_index, address });
+                continue;
+            }
+            for &slot in storage_read.iter().chain(storage_written.keys()) {
+                if tries.slot(address, slot) {
+                    problems.push(Problem::HashedOutSlot {
+                        txn_index,
+                        address,
+                        slot,
+                    });
+                }
+            }
+        }
+    }
+
+    for &(address, _) in &other.b_data.withdrawals {
+        if tries.account(address) {
+            problems.push(Problem::HashedOutWithdrawal { address });
+        }
+    }
+}
+
+fn check_gas(trace: &BlockTrace, other: &OtherBlockData, problems: &mut Vec<Problem>) {
+    let mut traced = U256::zero();
+    for (txn_index, txn) in trace.txn_info.iter().enumerate() {
+        traced += txn.meta.gas_used.into();
+        match decode_receipt(&txn.meta.new_receipt_trie_node_byte) {
+            Ok(receipt) if receipt.cum_gas_used != traced => {
+                problems.push(Problem::CumulativeGasMismatch {
+                    txn_index,
+                    receipt: receipt.cum_gas_used,
+                    traced,
+                })
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(Problem::InvalidReceipt {
+                txn_index,
+                reason: format!("{e:#}"),
+            }),
+        }
+    }
+
+    let block = other.b_data.b_meta.block_gas_used;
+    if traced != block {
+        problems.push(Problem::BlockGasMismatch { block, traced });
+    }
+}
+
+fn decode_receipt(bytes: &[u8]) -> anyhow::Result<LegacyReceiptRlp> {
+    let bytes = check_receipt_bytes(bytes.to_vec())?;
+    let (_, _, receipt) = evm_arithmetization::generation::mpt::decode_receipt(&bytes)
+        .map_err(|_| anyhow!("couldn't RLP-decode receipt node bytes"))?;
+    Ok(receipt)
+}
+
+/// Returns the hash of the hash node that `key` crosses into, if any.
+fn hash_node_on_path(node: &Node<HashedPartialTrie>, mut key: Nibbles) -> Option<H256> {
+    match node {
+        Node::Empty | Node::Leaf { .. } => None,
+        Node::Hash(hash) => Some(*hash),
+        Node::Branch { children, .. } => match key.count {
+            0 => None,
+            _ => {
+                let ix = key.pop_next_nibble_front();
+                hash_node_on_path(&children[ix as usize], key)
+            }
+        },
+        Node::Extension { nibbles, child } => {
+            match key.count >= nibbles.count && key.get_next_nibbles(nibbles.count) == *nibbles {
+                true => hash_node_on_path(child, key.truncate_n_nibbles_front(nibbles.count)),
+                false => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evm_arithmetization::generation::mpt::AccountRlp;
+    use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
+    use mpt_trie::partial_trie::PartialTrie as _;
+
+    use super::*;
+    use crate::{
+        BlockLevelData, ExecutionWitnessPreImages, SeparateStorageTriesPreImage,
+        SeparateTriePreImage, SeparateTriePreImages, TxnInfo, TxnMeta,
+    };
+
+    fn receipt(cum_gas_used: u64) -> Vec<u8> {
+        rlp::encode(&LegacyReceiptRlp {
+            status: true,
+            cum_gas_used: cum_gas_used.into(),
+            bloom: vec![0; 256].into(),
+            logs: vec![],
+        })
+        .to_vec()
+    }
+
+    fn txn(address: Address, txn_trace: TxnTrace, gas_used: u64, receipt: Vec<u8>) -> TxnInfo {
+        TxnInfo {
+            traces: [(address, txn_trace)].into(),
+            meta: TxnMeta {
+                byte_code: vec![],
+                new_receipt_trie_node_byte: receipt,
+                gas_used,
+            },
+        }
+    }
+
+    #[test]
+    fn report_problems() {
+        let (hashed_out, missing) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let mut state = HashedPartialTrie::from(Node::Empty);
+        state
+            .insert(
+                Nibbles::from_h256_be(hash(hashed_out)),
+                H256::repeat_byte(0xff),
+            )
+            .unwrap();
+
+        let trace = BlockTrace {
+            trie_pre_images: BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+                state: SeparateTriePreImage::Direct(state),
+                storage: SeparateStorageTriesPreImage::MultipleTries(Default::default()),
+            }),
+            code_db: Default::default(),
+            txn_info: vec![
+                txn(hashed_out, TxnTrace::default(), 21_000, receipt(21_000)),
+                txn(
+                    missing,
+                    TxnTrace {
+                        code_usage: Some(ContractCodeUsage::Read(H256::repeat_byte(0xcc))),
+                        ..Default::default()
+                    },
+                    30_000,
+                    receipt(50_000),
+                ),
+                txn(missing, TxnTrace::default(), 0, vec![0xc0]),
+            ],
+        };
+        let other = OtherBlockData {
+            b_data: BlockLevelData {
+                b_meta: BlockMetadata {
+                    block_gas_used: 50_000.into(),
+                    ..Default::default()
+                },
+                b_hashes: BlockHashes {
+                    prev_hashes: vec![H256::zero(); 256],
+                    cur_hash: H256::zero(),
+                },
+                withdrawals: vec![(hashed_out, 1.into())],
+            },
+            checkpoint_state_trie_root: H256::zero(),
+            chain_profile: Default::default(),
+        };
+
+        let mut problems = validate(&trace, &other);
+        assert!(matches!(
+            problems.remove(4),
+            Problem::InvalidReceipt { txn_index: 2, .. }
+        ));
+        assert_eq!(
+            problems,
+            vec![
+                Problem::MissingCode {
+                    txn_index: 1,
+                    address: missing,
+                    code_hash: H256::repeat_byte(0xcc),
+                },
+                Problem::HashedOutAccount {
+                    txn_index: 0,
+                    address: hashed_out,
+                },
+                Problem::HashedOutWithdrawal {
+                    address: hashed_out
+                },
+                Problem::CumulativeGasMismatch {
+                    txn_index: 1,
+                    receipt: 50_000.into(),
+                    traced: 51_000.into(),
+                },
+                Problem::BlockGasMismatch {
+                    block: 50_000.into(),
+                    traced: 51_000.into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn report_missing_witness_code() {
+        let code = vec![0x60, 0x00];
+        let account = AccountRlp {
+            code_hash: hash(&code),
+            ..Default::default()
+        };
+        let mut leaf = rlp::RlpStream::new_list(2);
+        leaf.append(
+            &Nibbles::from_h256_be(hash(Address::repeat_byte(0xaa)))
+                .to_hex_prefix_encoding(true)
+                .as_ref(),
+        );
+        leaf.append(&rlp::encode(&account).to_vec());
+        let leaf = leaf.out().to_vec();
+
+        let trace = |code_db| BlockTrace {
+            trie_pre_images: BlockTraceTriePreImages::ExecutionWitness(ExecutionWitnessPreImages {
+                state_root: hash(&leaf),
+                state: [(hash(&leaf), leaf.clone())].into(),
+                codes: Default::default(),
+                keys: Default::default(),
+            }),
+            code_db,
+            txn_info: vec![],
+        };
+        let other = OtherBlockData {
+            b_data: BlockLevelData {
+                b_meta: BlockMetadata::default(),
+                b_hashes: BlockHashes {
+                    prev_hashes: vec![H256::zero(); 256],
+                    cur_hash: H256::zero(),
+                },
+                withdrawals: vec![],
+            },
+            checkpoint_state_trie_root: H256::zero(),
+            chain_profile: Default::default(),
+        };
+
+        assert_eq!(
+            validate(&trace(Default::default()), &other),
+            vec![Problem::MissingWitnessCode {
+                hashed_address: hash(Address::repeat_byte(0xaa)),
+                code_hash: hash(&code),
+            }]
+        );
+        assert_eq!(validate(&trace([code].into()), &other), vec![]);
+    }
+}
@@ -134,15 +134,16 @@ fn instruction(input: &mut &[u8]) -> PResult<Instruction> {
 #[error("{}", .0)]
 struct Error(&'static str);
 
-fn account_leaf(input: &mut &[u8]) -> PResult<Instruction> {
-    bitflags::bitflags! {
-        struct AccountLeafFlags: u8 {
-            const HAS_CODE = 0b0000_0001;
-            const HAS_STORAGE = 0b0000_0010;
-            const ENCODES_NONCE = 0b0000_0100;
-            const ENCODES_BALANCE = 0b0000_1000;
-        }
+bitflags::bitflags! {
+    struct AccountLeafFlags: u8 {
+        const HAS_CODE = 0b0000_0001;
+        const HAS_STORAGE = 0b0000_0010;
+        const ENCODES_NONCE = 0b0000_0100;
+        const ENCODES_BALANCE = 0b0000_1000;
     }
+}
+
+fn account_leaf(input: &mut &[u8]) -> PResult<Instruction> {
     let key = key(input)?;
     let flags = any
         .try_map(|byte| {
@@ -230,13 +231,14 @@ fn cbor<T: DeserializeOwned + std::fmt::Debug>(input: &mut &[u8]) -> PResult<T>
     .parse_next(input)
 }
 
-fn decode_key(bytes: &NonEmpty<[u8]>) -> Result<NonEmpty<Vec<U4>>, Error> {
-    bitflags::bitflags! {
-        struct EncodeKeyFlags: u8 {
-            const ODD = 0b0000_0001;
-            const TERMINATED = 0b0000_0010;
-        }
+bitflags::bitflags! {
+    struct EncodeKeyFlags: u8 {
+        const ODD = 0b0000_0001;
+        const TERMINATED = 0b0000_0010;
     }
+}
+
+fn decode_key(bytes: &NonEmpty<[u8]>) -> Result<NonEmpty<Vec<U4>>, Error> {
     let v = match bytes.split_first() {
         // BUG(spec): the previous implementation said that Erigon does this
         (only, &[]) => nunny::vec![U4::new(*only).ok_or(Error("excess bits in single nibble"))?],
@@ -296,6 +298,118 @@ fn array<const N: usize>(input: &mut &[u8]) -> PResult<[u8; N]> {
         .parse_next(input)
 }
 
+/// The inverse of [`parse`], for building witnesses in tests.
+///
+/// BUG(spec): the code length of an [`Instruction::AccountLeaf`] is not kept
+///            by [`parse`], so is always written as zero.
+pub fn serialize(instructions: impl IntoIterator<Item = Instruction>) -> Vec<u8> {
+    fn cbor(out: &mut Vec<u8>, value: impl serde::Serialize) {
+        ciborium::into_writer(&value, out).expect("writing to a Vec can't fail")
+    }
+    // serde serializes a `Vec<u8>` as an array, but Erigon writes bytes
+    fn bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+        cbor(out, ciborium::value::Value::Bytes(bytes.to_vec()))
+    }
+
+    let mut out = vec![1]; // header
+    for instruction in instructions {
+        match instruction {
+            Instruction::Leaf { key, value } => {
+                out.push(0x00);
+                bytes(&mut out, &encode_key(&key));
+                bytes(&mut out, &value);
+            }
+            Instruction::Extension { key } => {
+                out.push(0x01);
+                bytes(&mut out, &encode_key(&key));
+            }
+            Instruction::Branch { mask } => {
+                out.push(0x02);
+                cbor(&mut out, mask);
+            }
+            Instruction::Hash { raw_hash } => {
+                out.push(0x03);
+                out.extend(raw_hash);
+            }
+            Instruction::Code { raw_code } => {
+                out.push(0x04);
+                bytes(&mut out, &raw_code);
+            }
+            Instruction::AccountLeaf {
+                key,
+                nonce,
+                balance,
+                has_code,
+                has_storage,
+            } => {
+                let mut flags = AccountLeafFlags::empty();
+                flags.set(AccountLeafFlags::HAS_CODE, has_code);
+                flags.set(AccountLeafFlags::HAS_STORAGE, has_storage);
+                flags.set(AccountLeafFlags::ENCODES_NONCE, nonce.is_some());
+                flags.set(AccountLeafFlags::ENCODES_BALANCE, balance.is_some());
+
+                out.push(0x05);
+                bytes(&mut out, &encode_key(&key));
+                out.push(flags.bits());
+                if let Some(nonce) = nonce {
+                    cbor(&mut out, nonce);
+                }
+                if let Some(balance) = balance {
+                    let mut be = [0; 32];
+                    balance.to_big_endian(&mut be);
+                    let leading_zeros = be.iter().take_while(|it| **it == 0).count();
+                    bytes(&mut out, &be[leading_zeros..]);
+                }
+                if has_code {
+                    cbor(&mut out, 0u64); // code_length
+                }
+            }
+            Instruction::SmtLeaf(SmtLeaf {
+                node_type,
+                address,
+                value,
+            }) => {
+                out.push(0x07);
+                out.push(match node_type {
+                    SmtLeafType::Balance => 0,
+                    SmtLeafType::Nonce => 1,
+                    SmtLeafType::Code => 2,
+                    SmtLeafType::Storage(_) => 3,
+                    SmtLeafType::CodeLength => 4,
+                });
+                bytes(&mut out, &address);
+                if let SmtLeafType::Storage(slot) = node_type {
+                    bytes(&mut out, &slot);
+                }
+                bytes(&mut out, &value);
+            }
+            Instruction::EmptyRoot => out.push(0x06),
+            Instruction::NewTrie => out.push(0xBB),
+        }
+    }
+    out
+}
+
+/// The inverse of [`decode_key`].
+fn encode_key(key: &[U4]) -> Vec<u8> {
+    match key {
+        [only] => vec![*only as u8],
+        _ => {
+            let flags = match key.len() % 2 {
+                0 => EncodeKeyFlags::empty(),
+                _ => EncodeKeyFlags::ODD,
+            };
+            iter::once(flags.bits())
+                .chain(key.chunks(2).map(|chunk| match chunk {
+                    [left, right] => ((*left as u8) << 4) | *right as u8,
+                    [left] => (*left as u8) << 4,
+                    _ => unreachable!("chunks are of 1 or 2 nibbles"),
+                }))
+                .collect()
+        }
+    }
+}
+
 #[cfg(test)]
 #[track_caller]
 fn do_test<'a, T: PartialEq + core::fmt::Debug>(
@@ -315,3 +429,39 @@ fn cbor_test_cases() {
     do_test(b"\x0a", 10, cbor);
     do_test(b"\x17", 23, cbor);
 }
+
+#[test]
+fn round_trip() {
+    for cases in [
+        include_str!("../tests/data/tries/zero_jerigon.json"),
+        include_str!("../tests/data/tries/hermez_cdk_erigon.json"),
+    ] {
+        for (ix, case) in serde_json::from_str::<Vec<super::Case>>(cases)
+            .unwrap()
+            .into_iter()
+            .enumerate()
+        {
+            println!("case {}", ix);
+            let instructions = parse(&case.bytes).unwrap();
+            assert_eq!(
+                parse(&serialize(instructions.clone())).unwrap(),
+                instructions
+            );
+        }
+    }
+
+    // odd and even keys, and Erigon's single nibble special case
+    for key in [&[0x1][..], &[0x1, 0x2], &[0x1, 0x2, 0x3], &[0xf; 64]] {
+        let key = key
+            .iter()
+            .map(|it| U4::new(*it).unwrap())
+            .collect::<Vec<_>>();
+        let instructions = nunny::vec![Instruction::Extension {
+            key: NonEmpty::<Vec<_>>::new(key).unwrap()
+        }];
+        assert_eq!(
+            parse(&serialize(instructions.clone())).unwrap(),
+            instructions
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Property tests of the compact pre-image format, on witnesses built with
+//! the encoder of [`CombinedPreImages`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use ethereum_types::{Address, H256, U256};
+use evm_arithmetization::generation::mpt::AccountRlp;
+use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
+use evm_arithmetization::testing_utils::BEACON_ROOTS_CONTRACT_ADDRESS;
+use evm_arithmetization::{ChainProfile, GenerationInputs};
+use itertools::Itertools as _;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, Node, PartialTrie as _};
+use proptest::prelude::*;
+use trace_decoder::{
+    BlockLevelData, BlockTrace, BlockTraceTriePreImages, CombinedPreImages, OtherBlockData,
+    SeparateStorageTriesPreImage, SeparateTriePreImage, SeparateTriePreImages,
+};
+
+#[derive(Clone, Debug)]
+struct Account {
+    nonce: u64,
+    balance: u128,
+    storage: BTreeMap<H256, u64>,
+    code: Vec<u8>,
+    /// Whether the storage and code are left out of the witness.
+    hashed_out: bool,
+}
+
+fn account() -> impl Strategy<Value = Account> {
+    (
+        any::<u64>(),
+        any::<u128>(),
+        prop::collection::btree_map(any::<[u8; 32]>().prop_map(H256), 1..u64::MAX, 0..4),
+        prop::collection::vec(any::<u8>(), 0..16),
+        any::<bool>(),
+    )
+        .prop_map(|(nonce, balance, storage, code, hashed_out)| Account {
+            nonce,
+            balance,
+            storage,
+            code,
+            hashed_out,
+        })
+}
+
+/// Accounts, always including the beacon roots contract with some storage, as
+/// it is written to at the start of every block.
+fn accounts() -> impl Strategy<Value = BTreeMap<Address, Account>> {
+    (
+        prop::collection::btree_map(any::<[u8; 20]>().prop_map(Address), account(), 0..8),
+        prop::collection::btree_map(any::<[u8; 32]>().prop_map(H256), 1..u64::MAX, 1..4),
+    )
+        .prop_map(|(mut accounts, beacon_roots_storage)| {
+            accounts.insert(
+                BEACON_ROOTS_CONTRACT_ADDRESS,
+                Account {
+                    nonce: 1,
+                    balance: 0,
+                    storage: beacon_roots_storage,
+                    code: vec![],
+                    hashed_out: false,
+                },
+            );
+            accounts
+        })
+}
+
+struct Witness {
+    state: HashedPartialTrie,
+    storage: HashMap<H256, HashedPartialTrie>,
+    code: Vec<Vec<u8>>,
+}
+
+fn witness(accounts: &BTreeMap<Address, Account>) -> Witness {
+    let mut state = HashedPartialTrie::new(Node::Empty);
+    let mut storage = HashMap::new();
+    let mut code = vec![];
+    for (address, account) in accounts {
+        let mut storage_trie = HashedPartialTrie::new(Node::Empty);
+        for (slot, value) in &account.storage {
+            storage_trie
+                .insert(
+                    Nibbles::from_h256_be(keccak(slot)),
+                    rlp::encode(&U256::from(*value)).to_vec(),
+                )
+                .unwrap();
+        }
+        let account_rlp = AccountRlp {
+            nonce: account.nonce.into(),
+            balance: account.balance.into(),
+            storage_root: storage_trie.hash(),
+            code_hash: keccak(&account.code),
+        };
+        state
+            .insert(
+                Nibbles::from_h256_be(keccak(address)),
+                rlp::encode(&account_rlp).to_vec(),
+            )
+            .unwrap();
+        if !account.hashed_out {
+            if !account.storage.is_empty() {
+                storage.insert(keccak(address), storage_trie);
+            }
+            if !account.code.is_empty() {
+                code.push(account.code.clone());
+            }
+        }
+    }
+    Witness {
+        state,
+        storage,
+        code,
+    }
+}
+
+/// An empty block, with a withdrawal to each of `accounts`.
+fn other_block_data(
+    checkpoint_state_trie_root: H256,
+    accounts: impl IntoIterator<Item = Address>,
+) -> OtherBlockData {
+    OtherBlockData {
+        b_data: BlockLevelData {
+            b_meta: BlockMetadata {
+                block_number: 1.into(),
+                block_timestamp: 1.into(),
+                parent_beacon_block_root: H256::repeat_byte(0x11),
+                ..Default::default()
+            },
+            b_hashes: BlockHashes {
+                prev_hashes: vec![H256::zero(); 256],
+                cur_hash: H256::repeat_byte(0x22),
+            },
+            withdrawals: accounts
+                .into_iter()
+                .enumerate()
+                .map(|(ix, address)| (address, (ix + 1).into()))
+                .collect(),
+        },
+        checkpoint_state_trie_root,
+        chain_profile: ChainProfile::L1,
+    }
+}
+
+fn decode(
+    trie_pre_images: BlockTraceTriePreImages,
+    code: &[Vec<u8>],
+    other: &OtherBlockData,
+) -> Vec<GenerationInputs> {
+    let trace = BlockTrace {
+        trie_pre_images,
+        code_db: code.iter().cloned().collect(),
+        txn_info: vec![],
+    };
+    let problems = trace_decoder::validate(&trace, other);
+    assert!(problems.is_empty(), "{problems:?}");
+    trace_decoder::entrypoint(trace, other.clone(), 1).unwrap()
+}
+
+/// The hashes of the tries of `inputs`, which must not depend on the format of
+/// the pre-images.
+fn trie_hashes(inputs: &GenerationInputs) -> (H256, Vec<(H256, H256)>) {
+    (
+        inputs.tries.state_trie.hash(),
+        inputs
+            .tries
+            .storage_tries
+            .iter()
+            .map(|(haddr, storage)| (*haddr, storage.hash()))
+            .sorted()
+            .collect(),
+    )
+}
+
+proptest! {
+    /// Encoded type 1 pre-images decode to the same payloads as the tries they
+    /// were encoded from.
+    #[test]
+    fn combined_pre_images_decode_like_separate_ones(accounts in accounts()) {
+        let Witness { state, storage, code } = witness(&accounts);
+        let other = other_block_data(state.hash(), accounts.keys().copied());
+
+        let combined = decode(
+            BlockTraceTriePreImages::Combined(
+                CombinedPreImages::encode_mpt(&state, &storage, code.clone()).unwrap(),
+            ),
+            &[],
+            &other,
+        );
+        let separate = decode(
+            BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+                state: SeparateTriePreImage::Direct(state.clone()),
+                storage: SeparateStorageTriesPreImage::MultipleTries(
+                    storage
+                        .into_iter()
+                        .map(|(haddr, storage)| (haddr, SeparateTriePreImage::Direct(storage)))
+                        .collect(),
+                ),
+            }),
+            &code,
+            &other,
+        );
+
+        prop_assert_eq!(combined[0].tries.state_trie.hash(), state.hash());
+        prop_assert_eq!(combined.len(), separate.len());
+        for (combined, separate) in combined.iter().zip(&separate) {
+            prop_assert_eq!(trie_hashes(combined), trie_hashes(separate));
+            prop_assert_eq!(&combined.trie_roots_after, &separate.trie_roots_after);
+            prop_assert_eq!(&combined.withdrawals, &separate.withdrawals);
+        }
+    }
+}
@@ -10,7 +10,7 @@ use std::{
 use alloy::rpc::types::eth::Header;
 use anyhow::Context as _;
 use evm_arithmetization::prover::testing::simulate_execution_all_segments;
-use evm_arithmetization::GenerationInputs;
+use evm_arithmetization::{ChainProfile, GenerationInputs};
 use itertools::Itertools;
 use log::info;
 use mpt_trie::partial_trie::PartialTrie;
@@ -20,7 +20,7 @@ use plonky2_maybe_rayon::*;
 use pretty_env_logger::env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
 use prover::BlockProverInput;
 use rstest::rstest;
-use trace_decoder::OtherBlockData;
+use trace_decoder::{BatchingStrategy, DecodingOptions, OtherBlockData};
 
 type F = GoldilocksField;
 
@@ -77,21 +77,21 @@ fn derive_header_file_path(witness_file_path: &Path) -> Result<PathBuf, anyhow::
 
 fn decode_generation_inputs(
     block_prover_input: BlockProverInput,
-    use_burn_addr: bool,
+    chain_profile: ChainProfile,
 ) -> anyhow::Result<Vec<GenerationInputs>> {
     let block_num = block_prover_input.other_data.b_data.b_meta.block_number;
-    let trace_decoder_output = trace_decoder::entrypoint(
-        block_prover_input.block_trace,
-        block_prover_input.other_data.clone(),
-        3,
-        use_burn_addr,
-    )
-    .context(format!(
-        "Failed to execute trace decoder on block {}",
-        block_num
-    ))?
-    .into_iter()
-    .collect::<Vec<GenerationInputs>>();
+    let other_data = OtherBlockData {
+        chain_profile,
+        ..block_prover_input.other_data.clone()
+    };
+    let trace_decoder_output =
+        trace_decoder::entrypoint(block_prover_input.block_trace, other_data, 3)
+            .context(format!(
+                "Failed to execute trace decoder on block {}",
+                block_num
+            ))?
+            .into_iter()
+            .collect::<Vec<GenerationInputs>>();
     Ok(trace_decoder_output)
 }
 
@@ -172,8 +172,11 @@ fn test_parsing_decoding_proving(#[case] test_witness_directory: &str) {
 
     // TODO: https://github.com/0xPolygonZero/zk_evm/issues/565
     //       Once CDK_ERIGON_WITNESS_DIR is available, change this so
-    //       `use_burn_addr` is only true in that case.
-    let use_burn_addr = test_witness_directory != JERIGON_WITNESS_DIR;
+    //       `chain_profile` is only `CdkErigon` in that case.
+    let chain_profile = match test_witness_directory != JERIGON_WITNESS_DIR {
+        true => ChainProfile::CdkErigon,
+        false => ChainProfile::L1,
+    };
     let results = find_witness_data_files(test_witness_directory)
         .expect("valid json data files found")
         .into_iter()
@@ -187,7 +190,7 @@ fn test_parsing_decoding_proving(#[case] test_witness_directory: &str) {
             block_prover_inputs.into_iter().map(|block_prover_input| {
                 // Run trace decoder, create list of generation inputs
                 let block_generation_inputs =
-                    decode_generation_inputs(block_prover_input, use_burn_addr)?;
+                    decode_generation_inputs(block_prover_input, chain_profile)?;
                 block_generation_inputs
                     .into_par_iter()
                     .map(|generation_inputs| {
@@ -234,8 +237,11 @@ fn test_generation_inputs_consistency(#[case] test_witness_directory: &str) {
 
     // TODO: https://github.com/0xPolygonZero/zk_evm/issues/565
     //       Once CDK_ERIGON_WITNESS_DIR is available, change this so
-    //       `use_burn_addr` is only true in that case.
-    let use_burn_addr = test_witness_directory != JERIGON_WITNESS_DIR;
+    //       `chain_profile` is only `CdkErigon` in that case.
+    let chain_profile = match test_witness_directory != JERIGON_WITNESS_DIR {
+        true => ChainProfile::CdkErigon,
+        false => ChainProfile::L1,
+    };
     let result: Vec<Result<(), anyhow::Error>> = find_witness_data_files(test_witness_directory)
         .expect("valid json data files found")
         .into_iter()
@@ -266,7 +272,7 @@ fn test_generation_inputs_consistency(#[case] test_witness_directory: &str) {
             let other_block_data = block_prover_input.other_data.clone();
             // Run trace decoder, create generation inputs for this block
             let block_generation_inputs =
-                decode_generation_inputs(block_prover_input, use_burn_addr)?;
+                decode_generation_inputs(block_prover_input, chain_profile)?;
             // Verify generation inputs for this block
             verify_generation_inputs(&block_header, &other_block_data, block_generation_inputs)
         })
@@ -279,3 +285,119 @@ fn test_generation_inputs_consistency(#[case] test_witness_directory: &str) {
         }
     });
 }
+
+/// This test checks that the block witnesses received from Jerigon pass the
+/// pre-flight validation of the trace decoder.
+#[rstest]
+#[case(JERIGON_WITNESS_DIR)]
+fn test_witnesses_are_valid(#[case] test_witness_directory: &str) {
+    init_logger();
+
+    for file_path in
+        find_witness_data_files(test_witness_directory).expect("valid json data files found")
+    {
+        for block_prover_input in read_witness_file(&file_path).expect("valid witness file") {
+            let problems = trace_decoder::validate(
+                &block_prover_input.block_trace,
+                &block_prover_input.other_data,
+            );
+            assert!(
+                problems.is_empty(),
+                "Block {} of {} is invalid: {problems:#?}",
+                block_prover_input.other_data.b_data.b_meta.block_number,
+                file_path.display()
+            );
+        }
+    }
+}
+
+/// This test checks that the tries expected after each batch are only decoded
+/// when requested, and that they hash to the state root after the batch.
+#[rstest]
+#[case(JERIGON_WITNESS_DIR)]
+fn test_expected_tries_after_are_opt_in(#[case] test_witness_directory: &str) {
+    init_logger();
+
+    for file_path in
+        find_witness_data_files(test_witness_directory).expect("valid json data files found")
+    {
+        for block_prover_input in read_witness_file(&file_path).expect("valid witness file") {
+            let decoded = decode_generation_inputs(block_prover_input.clone(), ChainProfile::L1)
+                .expect("valid block");
+            assert!(decoded
+                .iter()
+                .all(|inputs| inputs.expected_tries_after.is_none()));
+
+            let decoded = trace_decoder::entrypoint_iter(
+                block_prover_input.block_trace,
+                block_prover_input.other_data,
+                &BatchingStrategy::Fixed(3),
+                DecodingOptions {
+                    expected_tries_after: true,
+                },
+            )
+            .and_then(|batches| batches.collect::<anyhow::Result<Vec<_>>>())
+            .expect("valid block");
+            for inputs in decoded {
+                let expected_tries_after = inputs
+                    .expected_tries_after
+                    .expect("expected tries are decoded");
+                assert_eq!(
+                    expected_tries_after.state_trie.hash(),
+                    inputs.trie_roots_after.state_root
+                );
+            }
+        }
+    }
+}
+
+/// This test checks that uneven batches, given explicitly or by a gas budget,
+/// are padded with dummy payloads when the block has less than two of them,
+/// and that the withdrawals are only processed in the last payload.
+#[rstest]
+#[case::partition("b19807080_main", BatchingStrategy::Partition(vec![2, 5, 2]), &[2, 5, 2])]
+#[case::gas("b19807080_main", BatchingStrategy::Gas(350_000), &[1, 2, 2, 3, 1])]
+#[case::empty_partition("b19840104_main", BatchingStrategy::Partition(vec![]), &[0, 0])]
+#[case::empty_gas("b19840104_main", BatchingStrategy::Gas(350_000), &[0, 0])]
+#[case::single_txn("b28_dev", BatchingStrategy::Gas(350_000), &[1, 0])]
+fn test_uneven_batches_with_withdrawals(
+    #[case] block: &str,
+    #[case] batching: BatchingStrategy,
+    #[case] expected_batch_sizes: &[usize],
+) {
+    init_logger();
+
+    let file_path = Path::new(JERIGON_WITNESS_DIR).join(format!("{block}.json"));
+    let header_file_path = derive_header_file_path(&file_path).expect("valid header file path");
+    let header = serde_json::from_reader::<_, Vec<Header>>(std::io::BufReader::new(
+        fs::File::open(header_file_path).expect("header file exists"),
+    ))
+    .expect("valid header file")
+    .remove(0);
+    let block_prover_input = read_witness_file(&file_path)
+        .expect("valid witness file")
+        .remove(0);
+    let other = block_prover_input.other_data.clone();
+
+    let generation_inputs = trace_decoder::entrypoint_with_batching(
+        block_prover_input.block_trace,
+        block_prover_input.other_data,
+        &batching,
+    )
+    .expect("valid block");
+
+    assert_eq!(
+        generation_inputs
+            .iter()
+            .map(|inputs| inputs.signed_txns.len())
+            .collect::<Vec<_>>(),
+        expected_batch_sizes
+    );
+    let (last, rest) = generation_inputs
+        .split_last()
+        .expect("at least one payload");
+    assert!(rest.iter().all(|inputs| inputs.withdrawals.is_empty()));
+    assert_eq!(last.withdrawals.len(), other.b_data.withdrawals.len());
+
+    verify_generation_inputs(&header, &other, generation_inputs).expect("consistent batches");
+}
@@ -5,3 +5,16 @@ pub fn generate_block_proof_file_name(directory: &Option<&str>, block_height: u6
     path.push(format!("b{}.zkproof", block_height));
     path
 }
+
+pub fn generate_agg_block_proof_file_name(
+    directory: &Option<&str>,
+    first_block_height: u64,
+    last_block_height: u64,
+) -> PathBuf {
+    let mut path = PathBuf::from(directory.unwrap_or(""));
+    path.push(format!(
+        "b{}_{}.agg.zkproof",
+        first_block_height, last_block_height
+    ));
+    path
+}
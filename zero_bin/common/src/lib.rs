@@ -1,6 +1,7 @@
 pub mod block_interval;
 pub mod debug_utils;
 pub mod fs;
+pub mod metrics;
 pub mod parsing;
 pub mod pre_checks;
 pub mod prover_state;
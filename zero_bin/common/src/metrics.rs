@@ -0,0 +1,303 @@
+//! Prometheus metrics shared by the leader and the worker.
+//!
+//! Metrics are recorded in the default [`prometheus`] registry, and are
+//! exposed in the Prometheus text format on the `/metrics` endpoint of the
+//! server started by [`CliMetricsConfig::spawn_server`].
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::{http::header, routing::get, Router};
+use clap::Args;
+use evm_arithmetization::SegmentTraceStats;
+use once_cell::sync::Lazy;
+use prometheus::{
+    exponential_buckets, linear_buckets, register_histogram, register_histogram_vec,
+    register_int_gauge_vec, Encoder, Histogram, HistogramTimer, HistogramVec, IntGaugeVec,
+    TextEncoder,
+};
+use tracing::{error, info};
+
+use crate::prover_state::circuit::{Circuit, NUM_TABLES};
+
+/// Buckets for durations, ranging from 100ms to 30min.
+const DURATION_BUCKETS: [f64; 12] = [
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 1800.0,
+];
+
+static PROOF_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "zero_proof_duration_seconds",
+        "Time taken to generate a proof, by kind of proof.",
+        &["kind"],
+        DURATION_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+static WITNESS_GENERATION_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "zero_witness_generation_duration_seconds",
+        "Time taken to generate the traces of a segment.",
+        DURATION_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+static SEGMENT_CPU_CYCLES: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "zero_segment_cpu_cycles",
+        "Number of CPU cycles executed in a segment.",
+        exponential_buckets(1024.0, 2.0, 16).unwrap()
+    )
+    .unwrap()
+});
+
+static TRACE_LENGTH: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "zero_trace_length",
+        "Length of the trace of a table in a segment, before padding.",
+        &["table"],
+        exponential_buckets(16.0, 2.0, 24).unwrap()
+    )
+    .unwrap()
+});
+
+static DEGREE_BITS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "zero_trace_degree_bits",
+        "Log2 of the length of the trace of a table in a segment, after padding.",
+        &["table"],
+        linear_buckets(4.0, 1.0, 25).unwrap()
+    )
+    .unwrap()
+});
+
+static RPC_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "zero_rpc_duration_seconds",
+        "Time taken by requests to the node RPC, by kind of request.",
+        &["request"],
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .unwrap()
+});
+
+static QUEUE_DEPTH: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "zero_queue_depth",
+        "Number of items waiting to be processed, by queue.",
+        &["queue"]
+    )
+    .unwrap()
+});
+
+/// The kinds of proofs whose generation time is recorded.
+#[derive(Clone, Copy, Debug)]
+pub enum ProofKind {
+    Segment,
+    SegmentAgg,
+    BatchAgg,
+    Block,
+    BlockWrapper,
+    BlockAgg,
+}
+
+impl ProofKind {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            ProofKind::Segment => "segment",
+            ProofKind::SegmentAgg => "segment_agg",
+            ProofKind::BatchAgg => "batch_agg",
+            ProofKind::Block => "block",
+            ProofKind::BlockWrapper => "block_wrapper",
+            ProofKind::BlockAgg => "block_agg",
+        }
+    }
+}
+
+/// The queues whose depth is recorded.
+#[derive(Clone, Copy, Debug)]
+pub enum Queue {
+    /// Proving jobs submitted to the leader in HTTP mode, waiting to be run.
+    Jobs,
+    /// Segments generated by the leader, not yet proven.
+    Segments,
+}
+
+impl Queue {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Queue::Jobs => "jobs",
+            Queue::Segments => "segments",
+        }
+    }
+}
+
+/// Records the time taken to generate a proof.
+pub fn observe_proof_duration(kind: ProofKind, duration: Duration) {
+    PROOF_DURATION
+        .with_label_values(&[kind.as_str()])
+        .observe(duration.as_secs_f64());
+}
+
+/// Starts a timer recording the time taken to generate a proof when dropped.
+pub fn proof_timer(kind: ProofKind) -> HistogramTimer {
+    PROOF_DURATION
+        .with_label_values(&[kind.as_str()])
+        .start_timer()
+}
+
+/// Starts a timer recording the time taken by an RPC request when dropped.
+pub fn rpc_timer(request: &str) -> HistogramTimer {
+    RPC_DURATION.with_label_values(&[request]).start_timer()
+}
+
+/// Awaits an RPC request, recording the time it takes.
+pub async fn timed_rpc<F: Future>(request: &str, fut: F) -> F::Output {
+    let _timer = rpc_timer(request);
+    fut.await
+}
+
+/// Records the statistics of the traces generated for a segment.
+pub fn observe_segment_trace_stats(stats: &SegmentTraceStats) {
+    WITNESS_GENERATION_DURATION.observe(stats.generation_time.as_secs_f64());
+    SEGMENT_CPU_CYCLES.observe(stats.cpu_cycles as f64);
+    for table in 0..NUM_TABLES {
+        let label = [Circuit::from(table).as_str()];
+        TRACE_LENGTH
+            .with_label_values(&label)
+            .observe(stats.trace_lengths[table] as f64);
+        DEGREE_BITS
+            .with_label_values(&label)
+            .observe(stats.degree_bits[table] as f64);
+    }
+}
+
+/// Adds `delta` to the depth of `queue`.
+pub fn add_queue_depth(queue: Queue, delta: i64) {
+    QUEUE_DEPTH.with_label_values(&[queue.as_str()]).add(delta);
+}
+
+/// Tracks the items added to a queue by a unit of work, removing those still
+/// in the queue when dropped, e.g. when the work fails or is cancelled.
+#[derive(Debug)]
+pub struct QueueDepthGuard {
+    queue: Queue,
+    depth: AtomicI64,
+}
+
+impl QueueDepthGuard {
+    pub const fn new(queue: Queue) -> Self {
+        Self {
+            queue,
+            depth: AtomicI64::new(0),
+        }
+    }
+
+    /// Records an item added to the queue.
+    pub fn increment(&self) {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        add_queue_depth(self.queue, 1);
+    }
+
+    /// Records an item removed from the queue.
+    pub fn decrement(&self) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+        add_queue_depth(self.queue, -1);
+    }
+}
+
+impl Drop for QueueDepthGuard {
+    fn drop(&mut self) {
+        add_queue_depth(self.queue, -*self.depth.get_mut());
+    }
+}
+
+/// CLI arguments for exposing metrics.
+#[derive(Args, Debug)]
+pub struct CliMetricsConfig {
+    /// The port on which to expose Prometheus metrics. Metrics are not exposed
+    /// if unset.
+    #[arg(long, env = "ZERO_BIN_METRICS_PORT")]
+    pub metrics_port: Option<u16>,
+}
+
+impl CliMetricsConfig {
+    /// Starts serving the metrics in the background, if a port was provided.
+    pub fn spawn_server(&self) {
+        if let Some(port) = self.metrics_port {
+            tokio::spawn(async move {
+                if let Err(e) = serve(port).await {
+                    error!("Metrics server failed: {e:#}");
+                }
+            });
+        }
+    }
+}
+
+async fn serve(port: u16) -> Result<()> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    info!("serving metrics on {}", addr);
+
+    let app = Router::new().route("/metrics", get(render));
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    Ok(axum::serve(listener, app).await?)
+}
+
+async fn render() -> ([(header::HeaderName, &'static str); 1], Vec<u8>) {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        error!("Failed to encode metrics: {e}");
+    }
+
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments_depth() -> i64 {
+        QUEUE_DEPTH
+            .with_label_values(&[Queue::Segments.as_str()])
+            .get()
+    }
+
+    #[test]
+    fn queue_depth_guard_removes_remaining_items_on_drop() {
+        let depth = segments_depth();
+        let proven = QueueDepthGuard::new(Queue::Segments);
+        let failed = QueueDepthGuard::new(Queue::Segments);
+        for guard in [&proven, &failed] {
+            guard.increment();
+            guard.increment();
+        }
+        assert_eq!(segments_depth(), depth + 4);
+
+        proven.decrement();
+        proven.decrement();
+        drop(proven);
+        assert_eq!(segments_depth(), depth + 2);
+
+        // The work failed after one item was processed.
+        failed.decrement();
+        drop(failed);
+        assert_eq!(segments_depth(), depth);
+    }
+
+    #[tokio::test]
+    async fn timed_rpc_records_the_request() {
+        let samples = || {
+            RPC_DURATION
+                .with_label_values(&["eth_test"])
+                .get_sample_count()
+        };
+        assert_eq!(samples(), 0);
+        assert_eq!(timed_rpc("eth_test", async { 42 }).await, 42);
+        assert_eq!(samples(), 1);
+    }
+}
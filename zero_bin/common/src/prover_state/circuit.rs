@@ -5,7 +5,7 @@ use std::{
     str::FromStr,
 };
 
-use evm_arithmetization::{AllStark, StarkConfig};
+use evm_arithmetization::{AllStark, ChainProfile, StarkConfig};
 use proof_gen::types::AllRecursiveCircuits;
 
 use crate::parsing::{parse_range_exclusive, RangeParseError};
@@ -233,12 +233,14 @@ impl CircuitConfig {
             })
     }
 
-    /// Build the circuits from the current config.
-    pub fn as_all_recursive_circuits(&self) -> AllRecursiveCircuits {
+    /// Build the circuits of the given [`ChainProfile`] from the current
+    /// config.
+    pub fn as_all_recursive_circuits(&self, chain_profile: ChainProfile) -> AllRecursiveCircuits {
         AllRecursiveCircuits::new(
             &AllStark::default(),
             self.as_degree_bits_ranges(),
             &StarkConfig::standard_fast_config(),
+            chain_profile,
         )
     }
 }
@@ -3,6 +3,7 @@
 use std::fmt::Display;
 
 use clap::{Args, ValueEnum};
+use evm_arithmetization::ChainProfile;
 
 use super::{
     circuit::{Circuit, CircuitConfig, CircuitSize},
@@ -62,6 +63,14 @@ macro_rules! gen_prover_state_config {
             pub persistence: CircuitPersistence,
             #[clap(long, help_heading = HEADING, default_value_t = TableLoadStrategy::OnDemand)]
             pub load_strategy: TableLoadStrategy,
+            /// The chain profiles whose circuits are loaded at start-up.
+            #[clap(
+                long,
+                help_heading = HEADING,
+                value_delimiter = ',',
+                default_value = "l1",
+            )]
+            pub chain_profiles: Vec<ChainProfile>,
 
             $(
                 #[clap(
@@ -114,6 +123,7 @@ impl CliProverStateConfig {
     pub fn into_prover_state_manager(self) -> ProverStateManager {
         ProverStateManager {
             persistence: self.persistence.with_load_strategy(self.load_strategy),
+            chain_profiles: self.chain_profiles.clone(),
             circuit_config: self.into_circuit_config(),
         }
     }
@@ -9,15 +9,17 @@
 //!     - Allows the circuit sizes to be specified via environment variables.
 //! - Persistence utilities for saving and loading
 //!   [`evm_arithmetization::fixed_recursive_verifier::AllRecursiveCircuits`].
-//! - Global prover state management via the [`P_STATE`] static and the
-//!   [`set_prover_state_from_config`] function.
-use std::{fmt::Display, sync::OnceLock};
+//! - Global prover state management via the [`P_STATE`] static, holding one
+//!   prover state per [`ChainProfile`], and the
+//!   [`ProverStateManager::initialize`] function.
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
 
 use clap::ValueEnum;
 use evm_arithmetization::{
     fixed_recursive_verifier::ProverOutputData, generation::TrimmedGenerationInputs,
-    proof::AllProof, prover::prove, AllStark, GenerationSegmentData, StarkConfig,
+    proof::AllProof, prover::prove, AllStark, ChainProfile, GenerationSegmentData, StarkConfig,
 };
+use once_cell::sync::Lazy;
 use plonky2::{
     field::goldilocks_field::GoldilocksField, plonk::config::PoseidonGoldilocksConfig,
     util::timing::TimingTree,
@@ -26,9 +28,10 @@ use proof_gen::{proof_types::GeneratedSegmentProof, prover_state::ProverState, V
 use tracing::info;
 
 use self::circuit::{CircuitConfig, NUM_TABLES};
+use crate::metrics;
 use crate::prover_state::persistence::{
     BaseProverResource, DiskResource, MonolithicProverResource, RecursiveCircuitResource,
-    VerifierResource,
+    TwoToOneBlockVerifierResource, VerifierResource,
 };
 
 pub mod circuit;
@@ -46,16 +49,24 @@ pub(crate) type RecursiveCircuitsForTableSize =
         SIZE,
     >;
 
-/// The global prover state.
+/// The global prover states, one per [`ChainProfile`].
 ///
-/// It is specified as a `OnceLock` for the following reasons:
-/// - It is initialized once at start-up and never changed.
+/// Each of them is specified as a `OnceLock` for the following reasons:
+/// - It is initialized once and never changed.
 /// - It is accessible from multiple threads (particularly important when
 ///   running the leader in in-memory mode).
 /// - This scheme works for both a cluster and a single machine. In particular,
 ///   whether imported from a worker node or a thread in the leader node
 ///   (in-memory mode), the prover state is initialized only once.
-static P_STATE: OnceLock<ProverState> = OnceLock::new();
+///
+/// The states of the profiles listed in [`ProverStateManager::chain_profiles`]
+/// are initialized at start-up, the other ones on first use.
+static P_STATE: Lazy<HashMap<ChainProfile, OnceLock<ProverState>>> = Lazy::new(|| {
+    ChainProfile::ALL
+        .into_iter()
+        .map(|chain_profile| (chain_profile, OnceLock::new()))
+        .collect()
+});
 
 /// The global prover state manager.
 ///
@@ -67,8 +78,12 @@ static P_STATE: OnceLock<ProverState> = OnceLock::new();
 /// It's specified as a `OnceLock` for the same reasons as the prover state.
 static MANAGER: OnceLock<ProverStateManager> = OnceLock::new();
 
-pub fn p_state() -> &'static ProverState {
-    P_STATE.get().expect("Prover state is not initialized")
+pub fn p_state(chain_profile: ChainProfile) -> &'static ProverState {
+    P_STATE[&chain_profile].get_or_init(|| {
+        p_manager()
+            .load_prover_state(chain_profile)
+            .expect("Prover state could not be initialized")
+    })
 }
 
 pub fn p_manager() -> &'static ProverStateManager {
@@ -119,19 +134,31 @@ impl Default for CircuitPersistence {
 ///
 /// Provides helper utilities for interacting with the prover state in
 /// accordance with the specified configuration and persistence strategy.
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct ProverStateManager {
     pub circuit_config: CircuitConfig,
     pub persistence: CircuitPersistence,
+    /// The chain profiles whose prover state is initialized at start-up.
+    pub chain_profiles: Vec<ChainProfile>,
+}
+
+impl Default for ProverStateManager {
+    fn default() -> Self {
+        Self {
+            circuit_config: CircuitConfig::default(),
+            persistence: CircuitPersistence::default(),
+            chain_profiles: vec![ChainProfile::default()],
+        }
+    }
 }
 
 impl ProverStateManager {
-    pub const fn with_load_strategy(self, load_strategy: TableLoadStrategy) -> Self {
+    pub fn with_load_strategy(self, load_strategy: TableLoadStrategy) -> Self {
         match self.persistence {
             CircuitPersistence::None => self,
             CircuitPersistence::Disk(_) => Self {
-                circuit_config: self.circuit_config,
                 persistence: CircuitPersistence::Disk(load_strategy),
+                ..self
             },
         }
     }
@@ -198,6 +225,7 @@ impl ProverStateManager {
     ) -> anyhow::Result<GeneratedSegmentProof> {
         let config = StarkConfig::standard_fast_config();
         let all_stark = AllStark::default();
+        let input_chain_profile = input.chain_profile;
 
         let all_proof = prove(
             &all_stark,
@@ -210,10 +238,9 @@ impl ProverStateManager {
 
         let table_circuits = self.load_table_circuits(&config, &all_proof)?;
 
-        let (intern, p_vals) =
-            p_state()
-                .state
-                .prove_segment_after_initial_stark(all_proof, &table_circuits, None)?;
+        let (intern, p_vals) = p_state(input_chain_profile)
+            .state
+            .prove_segment_after_initial_stark(all_proof, &table_circuits, None)?;
 
         Ok(GeneratedSegmentProof { p_vals, intern })
     }
@@ -225,7 +252,7 @@ impl ProverStateManager {
         input: TrimmedGenerationInputs,
         segment_data: &mut GenerationSegmentData,
     ) -> anyhow::Result<GeneratedSegmentProof> {
-        let p_out = p_state().state.prove_segment(
+        let p_out = p_state(input.chain_profile).state.prove_segment(
             &AllStark::default(),
             &StarkConfig::standard_fast_config(),
             input,
@@ -258,7 +285,7 @@ impl ProverStateManager {
     ) -> anyhow::Result<GeneratedSegmentProof> {
         let (generation_inputs, mut segment_data) = input;
 
-        match self.persistence {
+        let proof = match self.persistence {
             CircuitPersistence::None | CircuitPersistence::Disk(TableLoadStrategy::Monolithic) => {
                 info!("using monolithic circuit {:?}", self);
                 self.segment_proof_monolithic(generation_inputs, &mut segment_data)
@@ -267,28 +294,34 @@ impl ProverStateManager {
                 info!("using on demand circuit {:?}", self);
                 self.segment_proof_on_demand(generation_inputs, &mut segment_data)
             }
+        };
+
+        if let Some(stats) = segment_data.trace_stats() {
+            metrics::observe_segment_trace_stats(stats);
         }
+
+        proof
     }
 
-    /// Initialize global prover state from the configuration.
-    pub fn initialize(&self) -> anyhow::Result<()> {
-        info!("initializing prover state...");
+    /// Load or generate the prover state of the given [`ChainProfile`],
+    /// according to the configuration.
+    fn load_prover_state(&self, chain_profile: ChainProfile) -> anyhow::Result<ProverState> {
+        info!("initializing {chain_profile} prover state...");
 
         let state = match self.persistence {
             CircuitPersistence::None => {
                 info!("generating circuits...");
                 ProverState {
-                    state: self.circuit_config.as_all_recursive_circuits(),
+                    state: self.circuit_config.as_all_recursive_circuits(chain_profile),
                 }
             }
             CircuitPersistence::Disk(strategy) => {
                 info!("attempting to load preprocessed circuits from disk...");
 
+                let key = (self.circuit_config.clone(), chain_profile);
                 let disk_state = match strategy {
-                    TableLoadStrategy::OnDemand => BaseProverResource::get(&self.circuit_config),
-                    TableLoadStrategy::Monolithic => {
-                        MonolithicProverResource::get(&self.circuit_config)
-                    }
+                    TableLoadStrategy::OnDemand => BaseProverResource::get(&key),
+                    TableLoadStrategy::Monolithic => MonolithicProverResource::get(&key),
                 };
 
                 match disk_state {
@@ -299,7 +332,7 @@ impl ProverStateManager {
                     Err(_) => {
                         info!("failed to load preprocessed circuits from disk. generating circuits...");
                         let all_recursive_circuits =
-                            self.circuit_config.as_all_recursive_circuits();
+                            self.circuit_config.as_all_recursive_circuits(chain_profile);
                         info!("saving preprocessed circuits to disk");
                         persistence::persist_all_to_disk(
                             &all_recursive_circuits,
@@ -313,12 +346,15 @@ impl ProverStateManager {
             }
         };
 
-        P_STATE.set(state).map_err(|_| {
-            anyhow::Error::msg(
-                "prover state already set. check the program logic to ensure it is only set once",
-            )
-            .context("setting prover state")
-        })?;
+        Ok(state)
+    }
+
+    /// Initialize global prover state from the configuration.
+    ///
+    /// The prover states of [`Self::chain_profiles`] are loaded eagerly, the
+    /// other ones will be loaded when first requested.
+    pub fn initialize(&self) -> anyhow::Result<()> {
+        info!("initializing prover state...");
 
         MANAGER.set(self.clone()).map_err(|_| {
             anyhow::Error::msg(
@@ -327,38 +363,63 @@ impl ProverStateManager {
             .context("setting prover state manager")
         })?;
 
+        for &chain_profile in &self.chain_profiles {
+            let state = self.load_prover_state(chain_profile)?;
+            P_STATE[&chain_profile].set(state).map_err(|_| {
+                anyhow::Error::msg(
+                    "prover state already set. check the program logic to ensure it is only set once",
+                )
+                .context(format!("setting {chain_profile} prover state"))
+            })?;
+        }
+
         Ok(())
     }
 
-    /// Loads a verifier state from disk or generate it.
-    pub fn verifier(&self) -> anyhow::Result<VerifierState> {
-        info!("initializing verifier state...");
+    /// Loads the verifier state of the given [`ChainProfile`] from disk or
+    /// generate it.
+    pub fn verifier(&self, chain_profile: ChainProfile) -> anyhow::Result<VerifierState> {
+        info!("initializing {chain_profile} verifier state...");
         match self.persistence {
             CircuitPersistence::None => {
                 info!("generating circuit...");
-                let prover_state = self.circuit_config.as_all_recursive_circuits();
+                let prover_state = self.circuit_config.as_all_recursive_circuits(chain_profile);
                 Ok(VerifierState {
                     state: prover_state.final_verifier_data(),
+                    two_to_one_block_state: prover_state.two_to_one_block_verifier_data(),
                 })
             }
             CircuitPersistence::Disk(_) => {
                 info!("attempting to load preprocessed verifier circuit from disk...");
-                let disk_state = VerifierResource::get(&self.circuit_config);
+                let key = (self.circuit_config.clone(), chain_profile);
+                let disk_state = VerifierResource::get(&key).and_then(|state| {
+                    TwoToOneBlockVerifierResource::get(&key)
+                        .map(|two_to_one_block_state| (state, two_to_one_block_state))
+                });
 
                 match disk_state {
-                    Ok(state) => {
+                    Ok((state, two_to_one_block_state)) => {
                         info!("successfully loaded preprocessed verifier circuit from disk");
-                        Ok(VerifierState { state })
+                        Ok(VerifierState {
+                            state,
+                            two_to_one_block_state,
+                        })
                     }
                     Err(_) => {
                         info!("failed to load preprocessed verifier circuit from disk. generating it...");
-                        let prover_state = self.circuit_config.as_all_recursive_circuits();
+                        let prover_state =
+                            self.circuit_config.as_all_recursive_circuits(chain_profile);
 
                         info!("saving preprocessed verifier circuit to disk");
                         let state = prover_state.final_verifier_data();
-                        VerifierResource::put(&self.circuit_config, &state)?;
-
-                        Ok(VerifierState { state })
+                        let two_to_one_block_state = prover_state.two_to_one_block_verifier_data();
+                        VerifierResource::put(&key, &state)?;
+                        TwoToOneBlockVerifierResource::put(&key, &two_to_one_block_state)?;
+
+                        Ok(VerifierState {
+                            state,
+                            two_to_one_block_state,
+                        })
                     }
                 }
             }
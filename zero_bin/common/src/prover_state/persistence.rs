@@ -9,6 +9,7 @@ use alloy::hex;
 use anyhow::anyhow;
 use directories::ProjectDirs;
 use evm_arithmetization::cpu::kernel::aggregator::KERNEL;
+use evm_arithmetization::ChainProfile;
 use once_cell::sync::Lazy;
 use plonky2::util::serialization::{
     Buffer, DefaultGateSerializer, DefaultGeneratorSerializer, IoError,
@@ -23,6 +24,7 @@ use super::{
 
 const PROVER_STATE_FILE_PREFIX: &str = "prover_state";
 const VERIFIER_STATE_FILE_PREFIX: &str = "verifier_state";
+const TWO_TO_ONE_BLOCK_VERIFIER_STATE_FILE_PREFIX: &str = "verifier_state_two_to_one_block";
 const ZK_EVM_CACHE_DIR_NAME: &str = "zk_evm_circuit_cache";
 const ZK_EVM_CACHE_DIR_ENV: &str = "ZK_EVM_CACHE_DIR";
 
@@ -113,21 +115,24 @@ pub(crate) trait DiskResource {
 /// Pre-generated circuits containing just the three higher-level circuits.
 /// These are sufficient for generating aggregation proofs and block
 /// proofs, but not for transaction proofs.
+///
+/// As their public values differ, these are stored per [`ChainProfile`].
 #[derive(Debug, Default)]
 pub(crate) struct BaseProverResource;
 
 impl DiskResource for BaseProverResource {
     type Resource = AllRecursiveCircuits;
     type Error = IoError;
-    type PathConstrutor = CircuitConfig;
+    type PathConstrutor = (CircuitConfig, ChainProfile);
 
-    fn path(p: &Self::PathConstrutor) -> impl AsRef<Path> {
+    fn path((config, chain_profile): &Self::PathConstrutor) -> impl AsRef<Path> {
         format!(
-            "{}/{}_base_{}_{}",
+            "{}/{}_base_{}_{}_{}",
             circuit_dir(),
             PROVER_STATE_FILE_PREFIX,
+            chain_profile,
             *CIRCUIT_VERSION,
-            p.get_configuration_digest()
+            config.get_configuration_digest()
         )
     }
 
@@ -148,22 +153,24 @@ impl DiskResource for BaseProverResource {
     }
 }
 
-/// Pre-generated circuits containing all circuits.
+/// Pre-generated circuits containing all circuits, stored per
+/// [`ChainProfile`].
 #[derive(Debug, Default)]
 pub(crate) struct MonolithicProverResource;
 
 impl DiskResource for MonolithicProverResource {
     type Resource = AllRecursiveCircuits;
     type Error = IoError;
-    type PathConstrutor = CircuitConfig;
+    type PathConstrutor = (CircuitConfig, ChainProfile);
 
-    fn path(p: &Self::PathConstrutor) -> impl AsRef<Path> {
+    fn path((config, chain_profile): &Self::PathConstrutor) -> impl AsRef<Path> {
         format!(
-            "{}/{}_monolithic_{}_{}",
+            "{}/{}_monolithic_{}_{}_{}",
             circuit_dir(),
             PROVER_STATE_FILE_PREFIX,
+            chain_profile,
             *CIRCUIT_VERSION,
-            p.get_configuration_digest()
+            config.get_configuration_digest()
         )
     }
 
@@ -227,22 +234,23 @@ impl DiskResource for RecursiveCircuitResource {
     }
 }
 
-/// An individual circuit table with a specific size.
+/// The verifier data of the block circuit of a given [`ChainProfile`].
 #[derive(Debug, Default)]
 pub(crate) struct VerifierResource;
 
 impl DiskResource for VerifierResource {
     type Resource = VerifierData;
     type Error = IoError;
-    type PathConstrutor = CircuitConfig;
+    type PathConstrutor = (CircuitConfig, ChainProfile);
 
-    fn path(p: &Self::PathConstrutor) -> impl AsRef<Path> {
+    fn path((config, chain_profile): &Self::PathConstrutor) -> impl AsRef<Path> {
         format!(
-            "{}/{}_{}_{}",
+            "{}/{}_{}_{}_{}",
             circuit_dir(),
             VERIFIER_STATE_FILE_PREFIX,
+            chain_profile,
             *CIRCUIT_VERSION,
-            p.get_configuration_digest()
+            config.get_configuration_digest()
         )
     }
 
@@ -259,14 +267,52 @@ impl DiskResource for VerifierResource {
     }
 }
 
+/// The verifier data of the two-to-one block aggregation circuit of a given
+/// [`ChainProfile`].
+#[derive(Debug, Default)]
+pub(crate) struct TwoToOneBlockVerifierResource;
+
+impl DiskResource for TwoToOneBlockVerifierResource {
+    type Resource = VerifierData;
+    type Error = IoError;
+    type PathConstrutor = (CircuitConfig, ChainProfile);
+
+    fn path((config, chain_profile): &Self::PathConstrutor) -> impl AsRef<Path> {
+        format!(
+            "{}/{}_{}_{}_{}",
+            circuit_dir(),
+            TWO_TO_ONE_BLOCK_VERIFIER_STATE_FILE_PREFIX,
+            chain_profile,
+            *CIRCUIT_VERSION,
+            config.get_configuration_digest()
+        )
+    }
+
+    fn serialize(r: &Self::Resource) -> Result<Vec<u8>, DiskResourceError<Self::Error>> {
+        VerifierResource::serialize(r)
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self::Resource, DiskResourceError<Self::Error>> {
+        VerifierResource::deserialize(bytes)
+    }
+}
+
 /// Writes the provided [`AllRecursiveCircuits`] to disk with all
-/// configurations, along with the associated [`VerifierData`].
+/// configurations, along with the associated block and two-to-one block
+/// aggregation [`VerifierData`].
 pub fn persist_all_to_disk(
     circuits: &AllRecursiveCircuits,
     circuit_config: &CircuitConfig,
 ) -> anyhow::Result<()> {
     prover_to_disk(circuit_config, circuits)?;
-    VerifierResource::put(circuit_config, &circuits.final_verifier_data())?;
+    VerifierResource::put(
+        &(circuit_config.clone(), circuits.chain_profile),
+        &circuits.final_verifier_data(),
+    )?;
+    TwoToOneBlockVerifierResource::put(
+        &(circuit_config.clone(), circuits.chain_profile),
+        &circuits.two_to_one_block_verifier_data(),
+    )?;
 
     Ok(())
 }
@@ -303,11 +349,13 @@ fn prover_to_disk(
     circuit_config: &CircuitConfig,
     circuits: &AllRecursiveCircuits,
 ) -> Result<(), DiskResourceError<IoError>> {
-    BaseProverResource::put(circuit_config, circuits)?;
-    MonolithicProverResource::put(circuit_config, circuits)?;
+    let key = (circuit_config.clone(), circuits.chain_profile);
+    BaseProverResource::put(&key, circuits)?;
+    MonolithicProverResource::put(&key, circuits)?;
 
     // Write individual circuit tables to disk, by circuit type and size. This
-    // allows us to load only the necessary tables when needed.
+    // allows us to load only the necessary tables when needed. They do not
+    // depend on the chain profile, hence are shared by all of them.
     for (circuit_type, tables) in circuits.by_table.iter().enumerate() {
         let circuit_type: Circuit = circuit_type.into();
         for (size, table) in tables.by_stark_size.iter() {
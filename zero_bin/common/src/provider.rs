@@ -7,6 +7,8 @@ use alloy::{providers::Provider, transports::Transport};
 use anyhow::Context;
 use tokio::sync::{Mutex, Semaphore, SemaphorePermit};
 
+use crate::metrics;
+
 const CACHE_SIZE: usize = 1024;
 const MAX_NUMBER_OF_PARALLEL_REQUESTS: usize = 128;
 
@@ -99,11 +101,13 @@ where
         if let Some(block) = cached_block {
             Ok(block)
         } else {
+            let timer = metrics::rpc_timer("get_block");
             let block = self
                 .provider
                 .get_block(id, kind)
                 .await?
                 .context(format!("target block {:?} does not exist", id))?;
+            timer.observe_duration();
 
             self.blocks_by_number
                 .lock()
@@ -2,8 +2,10 @@ use std::path::PathBuf;
 
 use alloy::transports::http::reqwest::Url;
 use clap::{Parser, Subcommand, ValueHint};
+use evm_arithmetization::ChainProfile;
 use prover::cli::CliProverConfig;
 use rpc::RpcType;
+use zero_bin_common::metrics::CliMetricsConfig;
 use zero_bin_common::prover_state::cli::CliProverStateConfig;
 
 /// zero-bin leader config
@@ -22,6 +24,9 @@ pub(crate) struct Cli {
     // mode.
     #[clap(flatten)]
     pub(crate) prover_state_config: CliProverStateConfig,
+
+    #[clap(flatten)]
+    pub(crate) metrics_config: CliMetricsConfig,
 }
 
 #[derive(Subcommand)]
@@ -42,6 +47,9 @@ pub(crate) enum Command {
         // The node RPC type (jerigon / native).
         #[arg(long, short = 't', default_value = "jerigon")]
         rpc_type: RpcType,
+        /// The chain profile of the blocks to prove.
+        #[arg(long, default_value_t = ChainProfile::L1)]
+        chain_profile: ChainProfile,
         /// The block interval for which to generate a proof.
         #[arg(long, short = 'i')]
         block_interval: String,
@@ -74,6 +82,24 @@ pub(crate) enum Command {
         /// The maximum number of retries
         #[arg(long, default_value_t = 0)]
         max_retries: u32,
+        /// If provided, record every completed proof in a journal located in
+        /// this directory, so that an interrupted run can be resumed.
+        #[arg(long, env = "ZERO_BIN_JOURNAL_DIR", value_hint = ValueHint::DirPath)]
+        journal_dir: Option<PathBuf>,
+        /// Resume from the proofs already recorded in the journal instead of
+        /// proving the whole interval again.
+        #[arg(long, requires = "journal_dir", default_value_t = false)]
+        resume: bool,
+        /// Prove the blocks of the interval independently and in parallel,
+        /// and aggregate them into a single two-to-one block aggregation proof
+        /// instead of chaining them. Each block is checkpointed at its parent,
+        /// so no checkpoint or previous proof can be given.
+        #[arg(
+            long,
+            conflicts_with_all = ["checkpoint_block_number", "previous_proof", "journal_dir", "resume"],
+            default_value_t = false
+        )]
+        aggregate_blocks: bool,
     },
     /// Reads input from HTTP and writes output to a directory.
     Http {
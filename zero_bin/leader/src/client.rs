@@ -4,20 +4,23 @@ use std::sync::Arc;
 
 use alloy::rpc::types::{BlockId, BlockNumberOrTag, BlockTransactionsKind};
 use alloy::transports::http::reqwest::Url;
-use anyhow::Result;
+use anyhow::{Context as _, Result};
+use evm_arithmetization::ChainProfile;
 use paladin::runtime::Runtime;
 use proof_gen::proof_types::GeneratedBlockProof;
+use prover::journal::{JournalManifest, ProvingJournal};
 use prover::ProverConfig;
 use rpc::{retry::build_http_retry_provider, RpcType};
 use tracing::{error, info, warn};
 use zero_bin_common::block_interval::BlockInterval;
-use zero_bin_common::fs::generate_block_proof_file_name;
+use zero_bin_common::fs::{generate_agg_block_proof_file_name, generate_block_proof_file_name};
 use zero_bin_common::pre_checks::check_previous_proof_and_checkpoint;
 
 #[derive(Debug)]
 pub struct RpcParams {
     pub rpc_url: Url,
     pub rpc_type: RpcType,
+    pub chain_profile: ChainProfile,
     pub backoff: u64,
     pub max_retries: u32,
 }
@@ -29,6 +32,9 @@ pub struct ProofParams {
     pub proof_output_dir: Option<PathBuf>,
     pub prover_config: ProverConfig,
     pub keep_intermediate_proofs: bool,
+    pub journal_dir: Option<PathBuf>,
+    pub resume: bool,
+    pub aggregate_blocks: bool,
 }
 
 /// The main function for the client.
@@ -47,36 +53,114 @@ pub(crate) async fn client_main(
             rpc_params.max_retries,
         )?,
     ));
-    check_previous_proof_and_checkpoint(
-        params.checkpoint_block_number,
-        &params.previous_proof,
-        block_interval.get_start_block()?,
-    )?;
-    // Grab interval checkpoint block state trie.
-    let checkpoint_state_trie_root = cached_provider
-        .get_block(
-            params.checkpoint_block_number.into(),
-            BlockTransactionsKind::Hashes,
-        )
-        .await?
-        .header
-        .state_root;
+    let aggregate_blocks = params.aggregate_blocks;
+    // Grab interval checkpoint block state trie. Blocks proven independently for
+    // aggregation are their own checkpoint instead.
+    let checkpoint_state_trie_root = match aggregate_blocks {
+        true => {
+            anyhow::ensure!(
+                params.previous_proof.is_none() && params.journal_dir.is_none() && !params.resume,
+                "blocks proven for aggregation cannot resume from a previous proof or a journal"
+            );
+            None
+        }
+        false => {
+            check_previous_proof_and_checkpoint(
+                params.checkpoint_block_number,
+                &params.previous_proof,
+                block_interval.get_start_block()?,
+            )?;
+            let checkpoint_block = cached_provider
+                .get_block(
+                    params.checkpoint_block_number.into(),
+                    BlockTransactionsKind::Hashes,
+                )
+                .await?;
+            Some(checkpoint_block.header.state_root)
+        }
+    };
 
+    let mut block_numbers = Vec::new();
     let mut block_prover_inputs = Vec::new();
     let mut block_interval = block_interval.into_bounded_stream()?;
     while let Some(block_num) = block_interval.next().await {
         let block_id = BlockId::Number(BlockNumberOrTag::Number(block_num));
+        let checkpoint_state_trie_root = match checkpoint_state_trie_root {
+            Some(it) => it,
+            None => {
+                let parent_block_num = block_num
+                    .checked_sub(1)
+                    .context("the genesis block cannot be proven")?;
+                cached_provider
+                    .get_block(parent_block_num.into(), BlockTransactionsKind::Hashes)
+                    .await?
+                    .header
+                    .state_root
+            }
+        };
         // Get future of prover input for particular block.
         let block_prover_input = rpc::block_prover_input(
             cached_provider.clone(),
             block_id,
             checkpoint_state_trie_root,
             rpc_params.rpc_type,
+            rpc_params.chain_profile,
         )
         .boxed();
+        block_numbers.push(block_num);
         block_prover_inputs.push(block_prover_input);
     }
 
+    if aggregate_blocks {
+        let (Some(&first_block), Some(&last_block)) = (block_numbers.first(), block_numbers.last())
+        else {
+            anyhow::bail!("no blocks to aggregate");
+        };
+
+        let agg_proof = prover::prove_aggregated(
+            block_prover_inputs,
+            &runtime,
+            params.prover_config,
+            rpc_params.chain_profile,
+        )
+        .await;
+        runtime.close().await?;
+        let agg_proof = agg_proof?;
+
+        info!("Blocks {first_block}..={last_block} have been proven and aggregated successfully.");
+
+        if let Some(proof_output_dir) = params.proof_output_dir.as_ref() {
+            let path = generate_agg_block_proof_file_name(
+                &proof_output_dir.to_str(),
+                first_block,
+                last_block,
+            );
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, serde_json::to_vec(&agg_proof)?)?;
+            info!("Aggregated proof written to {}", path.display());
+        } else {
+            std::io::stdout().write_all(&serde_json::to_vec(&agg_proof)?)?;
+        }
+
+        return Ok(());
+    }
+
+    // The journal is only relevant when generating actual proofs.
+    let journal = match params.journal_dir.clone() {
+        Some(journal_dir) if !params.prover_config.test_only => {
+            let manifest = JournalManifest::new(
+                rpc_params.chain_profile,
+                &params.prover_config,
+                params.checkpoint_block_number,
+                params.previous_proof.as_ref(),
+            )?;
+            Some(ProvingJournal::open(journal_dir, &manifest, params.resume)?)
+        }
+        _ => None,
+    };
+
     // If `keep_intermediate_proofs` is not set we only keep the last block
     // proof from the interval. It contains all the necessary information to
     // verify the whole sequence.
@@ -86,6 +170,7 @@ pub(crate) async fn client_main(
         params.previous_proof.take(),
         params.prover_config,
         params.proof_output_dir.clone(),
+        journal.as_ref(),
     )
     .await;
     runtime.close().await?;
@@ -1,15 +1,20 @@
 use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 
-use alloy::primitives::U256;
-use anyhow::{bail, Result};
-use axum::{http::StatusCode, routing::post, Json, Router};
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
 use paladin::runtime::Runtime;
-use proof_gen::proof_types::GeneratedBlockProof;
-use prover::{BlockProverInput, ProverConfig};
-use serde::{Deserialize, Serialize};
-use serde_json::to_writer;
+use prover::ProverConfig;
+use serde_json::json;
 use tracing::{debug, error, info};
 
+use crate::jobs::{JobId, JobInput, JobProof, JobQueue, JobStatus};
+
 /// The main function for the HTTP mode.
 pub(crate) async fn http_main(
     runtime: Runtime,
@@ -20,91 +25,90 @@ pub(crate) async fn http_main(
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     debug!("listening on {}", addr);
 
-    let runtime = Arc::new(runtime);
-    let app = Router::new().route(
-        "/prove",
-        post({
-            let runtime = runtime.clone();
-            move |body| prove(body, runtime, output_dir.clone(), prover_config)
-        }),
+    let (queue, receiver) = JobQueue::open(output_dir).await?;
+    tokio::spawn(
+        queue
+            .clone()
+            .run(receiver, Arc::new(runtime), prover_config),
     );
+
+    let app = Router::new()
+        .route("/prove", post(prove))
+        .route("/jobs", post(submit))
+        .route("/jobs/:id", get(status).delete(cancel))
+        .route("/jobs/:id/proof", get(proof))
+        .with_state(queue);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     Ok(axum::serve(listener, app).await?)
 }
 
-/// Writes the generated block proof to a file.
-///
-/// Returns the fully qualified file name.
-fn write_to_file(
-    output_dir: PathBuf,
-    block_number: U256,
-    generated_block_proof: &GeneratedBlockProof,
-) -> Result<PathBuf> {
-    let file_name = format!("proof-{}.json", block_number);
-    let fully_qualified_file_name = output_dir.join(file_name);
-    let file = std::fs::File::create(fully_qualified_file_name.clone());
+/// Proves a block through the job queue, responding once its job is finished.
+async fn prove(State(queue): State<Arc<JobQueue>>, Json(payload): Json<JobInput>) -> Response {
+    debug!("Received payload: {:#?}", payload);
 
-    match file {
-        Ok(file) => {
-            to_writer(file, &generated_block_proof)?;
-            Ok(fully_qualified_file_name)
-        }
+    let id = match queue.submit(payload).await {
+        Ok(id) => id,
         Err(e) => {
-            bail!("Error while writing to file: {e:#?}");
+            error!("Error while queuing job: {e:#}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
-    }
-}
+    };
+    info!("Queued job {id}, waiting for it to finish");
 
-#[derive(Serialize, Deserialize, Debug)]
-struct HttpProverInput {
-    prover_input: BlockProverInput,
-    previous: Option<GeneratedBlockProof>,
+    match queue.wait(id).await {
+        Some(report) if report.status == JobStatus::Succeeded => {
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Some(report) => (StatusCode::INTERNAL_SERVER_ERROR, Json(report)).into_response(),
+        None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
 }
 
-async fn prove(
-    Json(payload): Json<HttpProverInput>,
-    runtime: Arc<Runtime>,
-    output_dir: PathBuf,
-    prover_config: ProverConfig,
-) -> StatusCode {
+async fn submit(State(queue): State<Arc<JobQueue>>, Json(payload): Json<JobInput>) -> Response {
     debug!("Received payload: {:#?}", payload);
 
-    let block_number = payload.prover_input.get_block_number();
+    match queue.submit(payload).await {
+        Ok(id) => {
+            info!("Queued job {id}");
+            (StatusCode::ACCEPTED, Json(json!({ "id": id }))).into_response()
+        }
+        Err(e) => {
+            error!("Error while queuing job: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
 
-    let proof_res = if prover_config.test_only {
-        payload
-            .prover_input
-            .prove_test(
-                &runtime,
-                payload.previous.map(futures::future::ok),
-                prover_config,
-            )
-            .await
-    } else {
-        payload
-            .prover_input
-            .prove(
-                &runtime,
-                payload.previous.map(futures::future::ok),
-                prover_config,
-            )
-            .await
-    };
+async fn status(State(queue): State<Arc<JobQueue>>, Path(id): Path<JobId>) -> Response {
+    match queue.report(id).await {
+        Some(report) => Json(report).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn proof(State(queue): State<Arc<JobQueue>>, Path(id): Path<JobId>) -> Response {
+    match queue.proof(id).await {
+        Ok(Some(JobProof::Ready(proof))) => {
+            ([(header::CONTENT_TYPE, "application/json")], proof).into_response()
+        }
+        Ok(Some(JobProof::NotReady(status))) => {
+            (StatusCode::CONFLICT, Json(json!({ "status": status }))).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Error while reading the proof of job {id}: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
 
-    match proof_res {
-        Ok(b_proof) => match write_to_file(output_dir, block_number, &b_proof) {
-            Ok(file) => {
-                info!("Successfully wrote proof to {}", file.display());
-                StatusCode::OK
-            }
-            Err(e) => {
-                error!("{e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
-        },
+async fn cancel(State(queue): State<Arc<JobQueue>>, Path(id): Path<JobId>) -> Response {
+    match queue.cancel(id).await {
+        Ok(Some(report)) => Json(report).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
         Err(e) => {
-            error!("Error while proving block {block_number}: {e:#?}");
-            StatusCode::INTERNAL_SERVER_ERROR
+            error!("Error while cancelling job {id}: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
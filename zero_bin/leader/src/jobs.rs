@@ -0,0 +1,545 @@
+//! A persistent queue of proving jobs submitted through the HTTP mode.
+//!
+//! Jobs are processed concurrently, as they are submitted. Each job is
+//! recorded in `<output_dir>/jobs/<id>.json` along with its input until it
+//! completes, so that jobs which were queued or running when the leader
+//! stopped are resumed on restart, unless they were cancelled.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use alloy::primitives::U256;
+use anyhow::{bail, Context, Result};
+use paladin::runtime::Runtime;
+use proof_gen::proof_types::GeneratedBlockProof;
+use prover::progress::{Aborted, ProvingProgress};
+use prover::{BlockProverInput, ProverConfig};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch, Mutex};
+use tracing::{error, info, warn};
+use zero_bin_common::metrics::{self, Queue};
+
+pub(crate) type JobId = u64;
+
+const JOBS_DIR_NAME: &str = "jobs";
+
+/// The input of a proving job.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct JobInput {
+    pub(crate) prover_input: BlockProverInput,
+    pub(crate) previous: Option<GeneratedBlockProof>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    const fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// The persisted state of a job.
+#[derive(Debug, Deserialize, Serialize)]
+struct JobRecord {
+    id: JobId,
+    block_number: U256,
+    status: JobStatus,
+    /// The input of the job, dropped once the job is finished.
+    input: Option<JobInput>,
+    /// The file the proof was written to, once the job has succeeded.
+    proof_path: Option<PathBuf>,
+    /// The reason for which the job failed, if it did.
+    error: Option<String>,
+    /// Whether the job has been cancelled while running, in which case it is
+    /// not resumed on restart.
+    #[serde(default)]
+    cancel_requested: bool,
+}
+
+struct Job {
+    record: JobRecord,
+    progress: Arc<ProvingProgress>,
+    /// Broadcasts the status of the job, for clients to wait for it to finish.
+    status: watch::Sender<JobStatus>,
+}
+
+impl Job {
+    fn new(record: JobRecord) -> Self {
+        let (status, _) = watch::channel(record.status);
+        Self {
+            record,
+            progress: Arc::default(),
+            status,
+        }
+    }
+
+    fn set_status(&mut self, status: JobStatus) {
+        self.record.status = status;
+        self.status.send_replace(status);
+    }
+
+    fn report(&self) -> JobReport {
+        JobReport {
+            id: self.record.id,
+            block_number: self.record.block_number,
+            status: self.record.status,
+            cancel_requested: self.record.cancel_requested,
+            segments_proven: self.progress.segments_proven(),
+            segments_total: self.progress.segments_total(),
+            error: self.record.error.clone(),
+        }
+    }
+}
+
+/// The status of a job, as reported to clients.
+#[derive(Debug, Serialize)]
+pub(crate) struct JobReport {
+    id: JobId,
+    block_number: U256,
+    pub(crate) status: JobStatus,
+    /// Whether the job has been cancelled while running, and is waiting for
+    /// the work already dispatched to complete.
+    cancel_requested: bool,
+    /// The number of segments proven so far.
+    segments_proven: usize,
+    /// The number of segments the block execution has been divided into so
+    /// far.
+    segments_total: usize,
+    error: Option<String>,
+}
+
+/// The outcome of a proof request for a job.
+pub(crate) enum JobProof {
+    Ready(Vec<u8>),
+    NotReady(JobStatus),
+}
+
+pub(crate) struct JobQueue {
+    jobs_dir: PathBuf,
+    output_dir: PathBuf,
+    jobs: Mutex<BTreeMap<JobId, Job>>,
+    next_id: AtomicU64,
+    sender: mpsc::UnboundedSender<JobId>,
+}
+
+impl JobQueue {
+    /// Opens the job queue persisted in `output_dir`, re-enqueuing all the jobs
+    /// which did not finish, and completing the cancellation of the jobs which
+    /// were cancelled while running. Returns the queue along with the
+    /// receiving end of the ids of the jobs to process, to be given to
+    /// [`JobQueue::run`].
+    pub(crate) async fn open(
+        output_dir: PathBuf,
+    ) -> Result<(Arc<Self>, mpsc::UnboundedReceiver<JobId>)> {
+        let jobs_dir = output_dir.join(JOBS_DIR_NAME);
+        tokio::fs::create_dir_all(&jobs_dir).await?;
+
+        let mut jobs = BTreeMap::new();
+        let mut entries = tokio::fs::read_dir(&jobs_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            let record: JobRecord = serde_json::from_slice(&tokio::fs::read(&path).await?)
+                .with_context(|| format!("invalid job record {}", path.display()))?;
+            jobs.insert(record.id, Job::new(record));
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let next_id = jobs.keys().next_back().map_or(0, |id| id + 1);
+        for job in jobs.values_mut() {
+            if job.record.status.is_finished() {
+                continue;
+            }
+
+            if job.record.cancel_requested {
+                info!("Job {} was cancelled while running", job.record.id);
+                job.set_status(JobStatus::Cancelled);
+                job.record.input = None;
+                write_record(&jobs_dir, &job.record).await?;
+                continue;
+            }
+
+            info!(
+                "Resuming job {} for block {}",
+                job.record.id, job.record.block_number
+            );
+            job.set_status(JobStatus::Queued);
+            write_record(&jobs_dir, &job.record).await?;
+            sender.send(job.record.id)?;
+            metrics::add_queue_depth(Queue::Jobs, 1);
+        }
+
+        let queue = Self {
+            jobs_dir,
+            output_dir,
+            jobs: Mutex::new(jobs),
+            next_id: AtomicU64::new(next_id),
+            sender,
+        };
+
+        Ok((Arc::new(queue), receiver))
+    }
+
+    /// Enqueues a new job, returning its id.
+    pub(crate) async fn submit(&self, input: JobInput) -> Result<JobId> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let record = JobRecord {
+            id,
+            block_number: input.prover_input.get_block_number(),
+            status: JobStatus::Queued,
+            input: Some(input),
+            proof_path: None,
+            error: None,
+            cancel_requested: false,
+        };
+        write_record(&self.jobs_dir, &record).await?;
+
+        self.jobs.lock().await.insert(id, Job::new(record));
+        self.sender.send(id)?;
+        metrics::add_queue_depth(Queue::Jobs, 1);
+
+        Ok(id)
+    }
+
+    pub(crate) async fn report(&self, id: JobId) -> Option<JobReport> {
+        self.jobs.lock().await.get(&id).map(Job::report)
+    }
+
+    /// Waits for a job to finish, returning its final report.
+    pub(crate) async fn wait(&self, id: JobId) -> Option<JobReport> {
+        let mut status = self.jobs.lock().await.get(&id)?.status.subscribe();
+        // Jobs are never removed, so the sender is never dropped.
+        let _ = status.wait_for(JobStatus::is_finished).await;
+        self.report(id).await
+    }
+
+    /// Returns the serialized proof of a job, if it has succeeded.
+    pub(crate) async fn proof(&self, id: JobId) -> Result<Option<JobProof>> {
+        let proof_path = {
+            let jobs = self.jobs.lock().await;
+            let Some(job) = jobs.get(&id) else {
+                return Ok(None);
+            };
+            match (&job.record.status, &job.record.proof_path) {
+                (JobStatus::Succeeded, Some(proof_path)) => proof_path.clone(),
+                (status, _) => return Ok(Some(JobProof::NotReady(*status))),
+            }
+        };
+
+        Ok(Some(JobProof::Ready(tokio::fs::read(proof_path).await?)))
+    }
+
+    /// Cancels a job. Queued jobs are cancelled right away, while running jobs
+    /// are aborted before their next unit of work. The cancellation of running
+    /// jobs is persisted, so that they are not resumed on restart.
+    pub(crate) async fn cancel(&self, id: JobId) -> Result<Option<JobReport>> {
+        let mut jobs = self.jobs.lock().await;
+        let Some(job) = jobs.get_mut(&id) else {
+            return Ok(None);
+        };
+
+        match job.record.status {
+            JobStatus::Queued => {
+                job.set_status(JobStatus::Cancelled);
+                job.record.input = None;
+                write_record(&self.jobs_dir, &job.record).await?;
+                metrics::add_queue_depth(Queue::Jobs, -1);
+            }
+            JobStatus::Running if !job.record.cancel_requested => {
+                job.record.cancel_requested = true;
+                write_record(&self.jobs_dir, &job.record).await?;
+                job.progress.abort();
+            }
+            JobStatus::Running
+            | JobStatus::Succeeded
+            | JobStatus::Failed
+            | JobStatus::Cancelled => (),
+        }
+
+        Ok(Some(job.report()))
+    }
+
+    /// Processes the jobs concurrently, as they are enqueued.
+    pub(crate) async fn run(
+        self: Arc<Self>,
+        mut receiver: mpsc::UnboundedReceiver<JobId>,
+        runtime: Arc<Runtime>,
+        prover_config: ProverConfig,
+    ) {
+        while let Some(id) = receiver.recv().await {
+            let queue = self.clone();
+            let runtime = runtime.clone();
+            tokio::spawn(async move {
+                if let Err(e) = queue.process(id, &runtime, prover_config).await {
+                    error!("Failed to process job {id}: {e:#}");
+                }
+            });
+        }
+    }
+
+    async fn process(
+        &self,
+        id: JobId,
+        runtime: &Runtime,
+        prover_config: ProverConfig,
+    ) -> Result<()> {
+        let (input, progress) = {
+            let mut jobs = self.jobs.lock().await;
+            let Some(job) = jobs.get_mut(&id) else {
+                return Ok(());
+            };
+            // The job may have been cancelled while queued.
+            let (JobStatus::Queued, Some(input)) = (job.record.status, &job.record.input) else {
+                return Ok(());
+            };
+            let input = input.clone();
+
+            job.set_status(JobStatus::Running);
+            write_record(&self.jobs_dir, &job.record).await?;
+            metrics::add_queue_depth(Queue::Jobs, -1);
+
+            (input, job.progress.clone())
+        };
+
+        let block_number = input.prover_input.get_block_number();
+        info!("Running job {id} for block {block_number}");
+
+        let previous = input.previous.map(futures::future::ok);
+        let proof_res = if prover_config.test_only {
+            input
+                .prover_input
+                .prove_test(runtime, previous, prover_config, Some(&progress))
+                .await
+        } else {
+            input
+                .prover_input
+                .prove(runtime, previous, prover_config, None, Some(&progress))
+                .await
+        };
+
+        let outcome = match proof_res {
+            Ok(proof) => write_to_file(&self.output_dir, id, block_number, &proof).await,
+            Err(e) => Err(e),
+        };
+
+        let mut jobs = self.jobs.lock().await;
+        let Some(job) = jobs.get_mut(&id) else {
+            return Ok(());
+        };
+        match outcome {
+            Ok(proof_path) => {
+                info!(
+                    "Job {id} succeeded, proof written to {}",
+                    proof_path.display()
+                );
+                job.set_status(JobStatus::Succeeded);
+                job.record.proof_path = Some(proof_path);
+            }
+            Err(e) if Aborted::is_cause_of(&e) => {
+                warn!("Job {id} was cancelled");
+                job.set_status(JobStatus::Cancelled);
+            }
+            Err(e) => {
+                error!("Job {id} failed while proving block {block_number}: {e:#}");
+                job.set_status(JobStatus::Failed);
+                job.record.error = Some(format!("{e:#}"));
+            }
+        }
+        job.record.input = None;
+
+        write_record(&self.jobs_dir, &job.record).await
+    }
+}
+
+/// Durably writes the record of a job, going through a temporary file so that
+/// a crash never leaves a partially written record behind.
+async fn write_record(jobs_dir: &Path, record: &JobRecord) -> Result<()> {
+    let path = jobs_dir.join(format!("{}.json", record.id));
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, serde_json::to_vec(record)?).await?;
+    tokio::fs::rename(&tmp_path, &path)
+        .await
+        .with_context(|| format!("failed to write job record {}", path.display()))
+}
+
+/// Writes the generated block proof to a file.
+///
+/// Returns the fully qualified file name.
+async fn write_to_file(
+    output_dir: &Path,
+    id: JobId,
+    block_number: U256,
+    generated_block_proof: &GeneratedBlockProof,
+) -> Result<PathBuf> {
+    // Several jobs may prove the same block.
+    let file_name = format!("proof-{block_number}-{id}.json");
+    let fully_qualified_file_name = output_dir.join(file_name);
+    let proof = serde_json::to_vec(generated_block_proof)?;
+
+    match tokio::fs::write(&fully_qualified_file_name, proof).await {
+        Ok(()) => Ok(fully_qualified_file_name),
+        Err(e) => {
+            bail!("Error while writing to file: {e:#?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty output directory for the test `name`.
+    fn output_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zero_bin_jobs_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn job_input() -> JobInput {
+        let prover_inputs: Vec<BlockProverInput> =
+            serde_json::from_str(include_str!("../../tools/artifacts/witness_b3_b6.json")).unwrap();
+        JobInput {
+            prover_input: prover_inputs.into_iter().next().unwrap(),
+            previous: None,
+        }
+    }
+
+    async fn status(queue: &JobQueue, id: JobId) -> JobStatus {
+        queue.report(id).await.unwrap().status
+    }
+
+    /// Marks a job as picked by [`JobQueue::process`], without proving it.
+    async fn start(queue: &JobQueue, id: JobId) -> Result<()> {
+        let mut jobs = queue.jobs.lock().await;
+        let job = jobs.get_mut(&id).unwrap();
+        job.set_status(JobStatus::Running);
+        write_record(&queue.jobs_dir, &job.record).await
+    }
+
+    #[tokio::test]
+    async fn queued_jobs_are_resumed_on_open() -> Result<()> {
+        let dir = output_dir("resume_queued");
+        let (queue, mut receiver) = JobQueue::open(dir.clone()).await?;
+        assert_eq!(queue.submit(job_input()).await?, 0);
+        assert_eq!(queue.submit(job_input()).await?, 1);
+        assert_eq!(queue.submit(job_input()).await?, 2);
+        start(&queue, 1).await?;
+
+        let cancelled = queue.cancel(2).await?.unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert_eq!(queue.wait(2).await.unwrap().status, JobStatus::Cancelled);
+        assert_eq!(
+            std::iter::from_fn(|| receiver.try_recv().ok()).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        drop((queue, receiver));
+
+        // Both the queued and the running jobs are resumed, but not the cancelled one.
+        let (queue, mut receiver) = JobQueue::open(dir.clone()).await?;
+        assert_eq!(
+            std::iter::from_fn(|| receiver.try_recv().ok()).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(status(&queue, 0).await, JobStatus::Queued);
+        assert_eq!(status(&queue, 1).await, JobStatus::Queued);
+        assert_eq!(status(&queue, 2).await, JobStatus::Cancelled);
+        assert!(queue.jobs.lock().await[&1].record.input.is_some());
+        assert!(queue.jobs.lock().await[&2].record.input.is_none());
+        assert!(matches!(
+            queue.proof(0).await?,
+            Some(JobProof::NotReady(JobStatus::Queued))
+        ));
+        assert!(queue.proof(3).await?.is_none());
+        // Ids are not reused.
+        assert_eq!(queue.submit(job_input()).await?, 3);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn running_jobs_stay_cancelled_on_open() -> Result<()> {
+        let dir = output_dir("cancel_running");
+        let (queue, receiver) = JobQueue::open(dir.clone()).await?;
+        let id = queue.submit(job_input()).await?;
+        start(&queue, id).await?;
+
+        // The job keeps running until the work already dispatched completes.
+        let report = queue.cancel(id).await?.unwrap();
+        assert_eq!(report.status, JobStatus::Running);
+        assert!(report.cancel_requested);
+        let progress = queue.jobs.lock().await[&id].progress.clone();
+        assert!(progress.check_abort().is_err());
+        drop((queue, receiver));
+
+        // The leader stopped before the job did, so the cancellation completes on
+        // restart rather than the job being resumed.
+        let (queue, mut receiver) = JobQueue::open(dir.clone()).await?;
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(status(&queue, id).await, JobStatus::Cancelled);
+        assert!(queue.jobs.lock().await[&id].record.input.is_none());
+        assert_eq!(queue.wait(id).await.unwrap().status, JobStatus::Cancelled);
+        drop((queue, receiver));
+
+        let (queue, mut receiver) = JobQueue::open(dir.clone()).await?;
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(status(&queue, id).await, JobStatus::Cancelled);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wait_returns_once_the_job_finishes() -> Result<()> {
+        let dir = output_dir("wait");
+        let (queue, _receiver) = JobQueue::open(dir.clone()).await?;
+        let id = queue.submit(job_input()).await?;
+        assert!(queue.wait(id + 1).await.is_none());
+
+        let waiter = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.wait(id).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        queue.cancel(id).await?;
+        assert_eq!(waiter.await?.unwrap().status, JobStatus::Cancelled);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn proofs_of_the_same_block_are_kept_apart() -> Result<()> {
+        let dir = output_dir("same_block");
+        std::fs::create_dir_all(&dir)?;
+        let proof = GeneratedBlockProof {
+            b_height: 3,
+            intern: proof_gen::proof_gen::dummy_proof()?,
+        };
+
+        let first = write_to_file(&dir, 0, U256::from(3), &proof).await?;
+        let second = write_to_file(&dir, 1, U256::from(3), &proof).await?;
+        assert_ne!(first, second);
+        assert!(first.exists());
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use std::{env, io};
 use std::{fs::File, path::PathBuf};
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory as _, Parser};
 use cli::Command;
 use client::RpcParams;
 use dotenvy::dotenv;
@@ -22,6 +22,7 @@ mod cli;
 mod client;
 mod http;
 mod init;
+mod jobs;
 mod stdio;
 
 fn get_previous_proof(path: Option<PathBuf>) -> Result<Option<GeneratedBlockProof>> {
@@ -54,11 +55,30 @@ async fn main() -> Result<()> {
     }
 
     let args = cli::Cli::parse();
+    args.metrics_config.spawn_server();
 
     let runtime = Runtime::from_config(&args.paladin, register()).await?;
 
     let prover_config: ProverConfig = args.prover_config.into();
 
+    // Aggregation proofs cannot be generated without the underlying block proofs.
+    if prover_config.test_only
+        && matches!(
+            args.command,
+            Command::Rpc {
+                aggregate_blocks: true,
+                ..
+            }
+        )
+    {
+        cli::Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the argument '--aggregate-blocks' cannot be used with '--test-only'",
+            )
+            .exit();
+    }
+
     // If not in test_only mode and running in emulation mode, we'll need to
     // initialize the prover state here.
     if !prover_config.test_only {
@@ -90,6 +110,7 @@ async fn main() -> Result<()> {
         Command::Rpc {
             rpc_url,
             rpc_type,
+            chain_profile,
             block_interval,
             checkpoint_block_number,
             previous_proof,
@@ -98,6 +119,9 @@ async fn main() -> Result<()> {
             keep_intermediate_proofs,
             backoff,
             max_retries,
+            journal_dir,
+            resume,
+            aggregate_blocks,
         } => {
             let runtime = Runtime::from_config(&args.paladin, register()).await?;
             let previous_proof = get_previous_proof(previous_proof)?;
@@ -117,6 +141,7 @@ async fn main() -> Result<()> {
                 RpcParams {
                     rpc_url,
                     rpc_type,
+                    chain_profile,
                     backoff,
                     max_retries,
                 },
@@ -127,6 +152,9 @@ async fn main() -> Result<()> {
                     proof_output_dir,
                     prover_config,
                     keep_intermediate_proofs,
+                    journal_dir,
+                    resume,
+                    aggregate_blocks,
                 },
             )
             .await?;
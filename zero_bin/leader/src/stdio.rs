@@ -21,8 +21,15 @@ pub(crate) async fn stdio_main(
         .map(Into::into)
         .collect::<Vec<BlockProverInputFuture>>();
 
-    let proved_blocks =
-        prover::prove(block_prover_inputs, &runtime, previous, prover_config, None).await;
+    let proved_blocks = prover::prove(
+        block_prover_inputs,
+        &runtime,
+        previous,
+        prover_config,
+        None,
+        None,
+    )
+    .await;
     runtime.close().await?;
     let proved_blocks = proved_blocks?;
 
@@ -2,21 +2,28 @@ use std::time::Instant;
 
 use evm_arithmetization::generation::TrimmedGenerationInputs;
 use evm_arithmetization::proof::PublicValues;
-use evm_arithmetization::{prover::testing::simulate_execution_all_segments, GenerationInputs};
+use evm_arithmetization::{
+    prover::testing::simulate_execution_all_segments, ChainProfile, GenerationInputs, SegmentError,
+};
 use paladin::{
     operation::{FatalError, FatalStrategy, Monoid, Operation, Result},
     registry, RemoteExecute,
 };
 use proof_gen::types::Field;
 use proof_gen::{
-    proof_gen::{generate_block_proof, generate_segment_agg_proof, generate_transaction_agg_proof},
+    proof_gen::{
+        generate_agg_block_proof, generate_block_proof, generate_block_wrapper_proof,
+        generate_segment_agg_proof, generate_transaction_agg_proof,
+    },
     proof_types::{
-        BatchAggregatableProof, GeneratedBlockProof, GeneratedTxnAggProof, SegmentAggregatableProof,
+        AggregatableBlockProof, BatchAggregatableProof, GeneratedBlockProof, GeneratedTxnAggProof,
+        SegmentAggregatableProof,
     },
 };
 use serde::{Deserialize, Serialize};
 use tracing::error;
 use tracing::{event, info_span, Level};
+use zero_bin_common::metrics::{self, ProofKind};
 use zero_bin_common::{debug_utils::save_inputs_to_disk, prover_state::p_state};
 
 registry!();
@@ -31,8 +38,7 @@ impl Operation for SegmentProof {
     type Output = proof_gen::proof_types::SegmentAggregatableProof;
 
     fn execute(&self, all_data: Self::Input) -> Result<Self::Output> {
-        let all_data =
-            all_data.map_err(|err| FatalError::from_str(&err.0, FatalStrategy::Terminate))?;
+        let all_data = all_data.map_err(|err| segment_failure(&err))?;
 
         let input = all_data.0.clone();
         let segment_index = all_data.1.segment_index();
@@ -41,6 +47,7 @@ impl Operation for SegmentProof {
             zero_bin_common::prover_state::p_manager()
                 .generate_segment_proof(all_data)
                 .map_err(|err| {
+                    let err = SegmentError::new(segment_index, &input, err);
                     if let Err(write_err) = save_inputs_to_disk(
                         format!(
                             "b{}_txns_{}..{}-({})_input.json",
@@ -54,18 +61,28 @@ impl Operation for SegmentProof {
                         error!("Failed to save txn proof input to disk: {:?}", write_err);
                     }
 
-                    FatalError::from_anyhow(err, FatalStrategy::Terminate)
+                    segment_failure(&err)
                 })?
         } else {
             zero_bin_common::prover_state::p_manager()
                 .generate_segment_proof(all_data)
-                .map_err(|err| FatalError::from_anyhow(err, FatalStrategy::Terminate))?
+                .map_err(|err| segment_failure(&SegmentError::new(segment_index, &input, err)))?
         };
 
         Ok(proof.into())
     }
 }
 
+/// Reports the failure of a segment as a fatal error.
+///
+/// The error is sent as JSON, so that leaders can group failures by their kind
+/// and location.
+fn segment_failure(err: &SegmentError) -> FatalError {
+    error!("{err}");
+    let err = serde_json::to_string(err).unwrap_or_else(|_| err.to_string());
+    FatalError::from_str(&err, FatalStrategy::Terminate)
+}
+
 #[derive(Deserialize, Serialize, RemoteExecute)]
 pub struct SegmentProofTestOnly {
     pub save_inputs_on_error: bool,
@@ -104,7 +121,7 @@ impl Operation for SegmentProofTestOnly {
 /// RAII struct to measure the time taken by a transaction proof.
 ///
 /// - When created, it starts a span with the transaction proof id.
-/// - When dropped, it logs the time taken by the transaction proof.
+/// - When dropped, it logs and records the time taken by the transaction proof.
 struct SegmentProofSpan {
     _span: tracing::span::EnteredSpan,
     start: Instant,
@@ -172,18 +189,21 @@ impl SegmentProofSpan {
 
 impl Drop for SegmentProofSpan {
     fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
         event!(
             Level::INFO,
             "segment proof ({}) took {:?}",
             self.descriptor,
-            self.start.elapsed()
+            elapsed
         );
+        metrics::observe_proof_duration(ProofKind::Segment, elapsed);
     }
 }
 
 #[derive(Deserialize, Serialize, RemoteExecute)]
 pub struct SegmentAggProof {
     pub save_inputs_on_error: bool,
+    pub chain_profile: ChainProfile,
 }
 
 fn get_seg_agg_proof_public_values(elem: SegmentAggregatableProof) -> PublicValues {
@@ -197,25 +217,27 @@ impl Monoid for SegmentAggProof {
     type Elem = SegmentAggregatableProof;
 
     fn combine(&self, a: Self::Elem, b: Self::Elem) -> Result<Self::Elem> {
-        let result = generate_segment_agg_proof(p_state(), &a, &b, false).map_err(|e| {
-            if self.save_inputs_on_error {
-                let pv = vec![
-                    get_seg_agg_proof_public_values(a),
-                    get_seg_agg_proof_public_values(b),
-                ];
-                if let Err(write_err) = save_inputs_to_disk(
-                    format!(
-                        "b{}_agg_lhs_rhs_inputs.log",
-                        pv[0].block_metadata.block_number
-                    ),
-                    pv,
-                ) {
-                    error!("Failed to save agg proof inputs to disk: {:?}", write_err);
+        let _timer = metrics::proof_timer(ProofKind::SegmentAgg);
+        let result = generate_segment_agg_proof(p_state(self.chain_profile), &a, &b, false)
+            .map_err(|e| {
+                if self.save_inputs_on_error {
+                    let pv = vec![
+                        get_seg_agg_proof_public_values(a),
+                        get_seg_agg_proof_public_values(b),
+                    ];
+                    if let Err(write_err) = save_inputs_to_disk(
+                        format!(
+                            "b{}_agg_lhs_rhs_inputs.log",
+                            pv[0].block_metadata.block_number
+                        ),
+                        pv,
+                    ) {
+                        error!("Failed to save agg proof inputs to disk: {:?}", write_err);
+                    }
                 }
-            }
 
-            FatalError::from(e)
-        })?;
+                FatalError::from(e)
+            })?;
 
         Ok(result.into())
     }
@@ -229,6 +251,7 @@ impl Monoid for SegmentAggProof {
 #[derive(Deserialize, Serialize, RemoteExecute)]
 pub struct BatchAggProof {
     pub save_inputs_on_error: bool,
+    pub chain_profile: ChainProfile,
 }
 fn get_agg_proof_public_values(elem: BatchAggregatableProof) -> PublicValues {
     match elem {
@@ -242,10 +265,11 @@ impl Monoid for BatchAggProof {
     type Elem = BatchAggregatableProof;
 
     fn combine(&self, a: Self::Elem, b: Self::Elem) -> Result<Self::Elem> {
+        let _timer = metrics::proof_timer(ProofKind::BatchAgg);
         let lhs = match a {
             BatchAggregatableProof::Segment(segment) => BatchAggregatableProof::from(
                 generate_segment_agg_proof(
-                    p_state(),
+                    p_state(self.chain_profile),
                     &SegmentAggregatableProof::from(segment.clone()),
                     &SegmentAggregatableProof::from(segment),
                     true,
@@ -258,7 +282,7 @@ impl Monoid for BatchAggProof {
         let rhs = match b {
             BatchAggregatableProof::Segment(segment) => BatchAggregatableProof::from(
                 generate_segment_agg_proof(
-                    p_state(),
+                    p_state(self.chain_profile),
                     &SegmentAggregatableProof::from(segment.clone()),
                     &SegmentAggregatableProof::from(segment),
                     true,
@@ -268,25 +292,26 @@ impl Monoid for BatchAggProof {
             _ => b,
         };
 
-        let result = generate_transaction_agg_proof(p_state(), &lhs, &rhs).map_err(|e| {
-            if self.save_inputs_on_error {
-                let pv = vec![
-                    get_agg_proof_public_values(lhs),
-                    get_agg_proof_public_values(rhs),
-                ];
-                if let Err(write_err) = save_inputs_to_disk(
-                    format!(
-                        "b{}_agg_lhs_rhs_inputs.json",
-                        pv[0].block_metadata.block_number
-                    ),
-                    pv,
-                ) {
-                    error!("Failed to save agg proof inputs to disk: {:?}", write_err);
+        let result = generate_transaction_agg_proof(p_state(self.chain_profile), &lhs, &rhs)
+            .map_err(|e| {
+                if self.save_inputs_on_error {
+                    let pv = vec![
+                        get_agg_proof_public_values(lhs),
+                        get_agg_proof_public_values(rhs),
+                    ];
+                    if let Err(write_err) = save_inputs_to_disk(
+                        format!(
+                            "b{}_agg_lhs_rhs_inputs.json",
+                            pv[0].block_metadata.block_number
+                        ),
+                        pv,
+                    ) {
+                        error!("Failed to save agg proof inputs to disk: {:?}", write_err);
+                    }
                 }
-            }
 
-            FatalError::from(e)
-        })?;
+                FatalError::from(e)
+            })?;
 
         Ok(result.into())
     }
@@ -301,6 +326,7 @@ impl Monoid for BatchAggProof {
 pub struct BlockProof {
     pub prev: Option<GeneratedBlockProof>,
     pub save_inputs_on_error: bool,
+    pub chain_profile: ChainProfile,
 }
 
 impl Operation for BlockProof {
@@ -308,22 +334,67 @@ impl Operation for BlockProof {
     type Output = GeneratedBlockProof;
 
     fn execute(&self, input: Self::Input) -> Result<Self::Output> {
+        let _timer = metrics::proof_timer(ProofKind::Block);
         Ok(
-            generate_block_proof(p_state(), self.prev.as_ref(), &input).map_err(|e| {
-                if self.save_inputs_on_error {
-                    if let Err(write_err) = save_inputs_to_disk(
-                        format!(
-                            "b{}_block_input.json",
-                            input.p_vals.block_metadata.block_number
-                        ),
-                        input.p_vals,
-                    ) {
-                        error!("Failed to save block proof input to disk: {:?}", write_err);
+            generate_block_proof(p_state(self.chain_profile), self.prev.as_ref(), &input).map_err(
+                |e| {
+                    if self.save_inputs_on_error {
+                        if let Err(write_err) = save_inputs_to_disk(
+                            format!(
+                                "b{}_block_input.json",
+                                input.p_vals.block_metadata.block_number
+                            ),
+                            input.p_vals,
+                        ) {
+                            error!("Failed to save block proof input to disk: {:?}", write_err);
+                        }
                     }
-                }
 
-                FatalError::from(e)
-            })?,
+                    FatalError::from(e)
+                },
+            )?,
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, RemoteExecute)]
+pub struct BlockWrapperProof {
+    pub chain_profile: ChainProfile,
+}
+
+impl Operation for BlockWrapperProof {
+    type Input = GeneratedBlockProof;
+    type Output = AggregatableBlockProof;
+
+    fn execute(&self, input: Self::Input) -> Result<Self::Output> {
+        let _timer = metrics::proof_timer(ProofKind::BlockWrapper);
+        Ok(
+            generate_block_wrapper_proof(p_state(self.chain_profile), &input)
+                .map_err(FatalError::from)?
+                .into(),
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, RemoteExecute)]
+pub struct BlockAggProof {
+    pub chain_profile: ChainProfile,
+}
+
+impl Monoid for BlockAggProof {
+    type Elem = AggregatableBlockProof;
+
+    fn combine(&self, a: Self::Elem, b: Self::Elem) -> Result<Self::Elem> {
+        let _timer = metrics::proof_timer(ProofKind::BlockAgg);
+        Ok(
+            generate_agg_block_proof(p_state(self.chain_profile), &a, &b)
+                .map_err(FatalError::from)?
+                .into(),
         )
     }
+
+    fn empty(&self) -> Self::Elem {
+        // Expect that block intervals are never empty.
+        unimplemented!("empty block agg proof")
+    }
 }
@@ -0,0 +1,125 @@
+//! Proves blocks with the leader operations, then wraps and aggregates them
+//! as in the two-to-one block aggregation mode of the leader.
+
+use evm_arithmetization::testing_utils::{dummy_payload, init_logger};
+use evm_arithmetization::{ChainProfile, SegmentDataIterator};
+use ops::{
+    BatchAggProof, BlockAggProof, BlockProof, BlockWrapperProof, SegmentAggProof, SegmentProof,
+};
+use paladin::operation::{Monoid, Operation};
+use proof_gen::proof_types::{AggregatableBlockProof, BatchAggregatableProof};
+use proof_gen::types::Field;
+use zero_bin_common::prover_state::circuit::{Circuit, CircuitConfig};
+use zero_bin_common::prover_state::{p_state, CircuitPersistence, ProverStateManager};
+
+const CHAIN_PROFILE: ChainProfile = ChainProfile::L1;
+const MAX_CPU_LEN_LOG: usize = 20;
+
+fn circuit_config() -> CircuitConfig {
+    let mut config = CircuitConfig::default();
+    for (circuit, size) in [
+        (Circuit::Arithmetic, 16..17),
+        (Circuit::BytePacking, 8..9),
+        (Circuit::Cpu, 14..15),
+        (Circuit::Keccak, 9..10),
+        (Circuit::KeccakSponge, 8..9),
+        (Circuit::Logic, 7..8),
+        (Circuit::Memory, 17..18),
+        (Circuit::MemoryBefore, 17..18),
+        (Circuit::MemoryAfter, 7..8),
+    ] {
+        config.set_circuit_size(circuit, size);
+    }
+    config
+}
+
+/// Proves a block made of two dummy payloads, and wraps its proof for
+/// aggregation.
+fn wrapped_block_proof(timestamp: u64) -> anyhow::Result<AggregatableBlockProof> {
+    let seg_agg_ops = SegmentAggProof {
+        save_inputs_on_error: false,
+        chain_profile: CHAIN_PROFILE,
+    };
+    let batch_agg_ops = BatchAggProof {
+        save_inputs_on_error: false,
+        chain_profile: CHAIN_PROFILE,
+    };
+
+    let mut batch_proofs = vec![];
+    for is_first_payload in [true, false] {
+        let inputs = dummy_payload(timestamp, is_first_payload)?;
+        let segment_proofs = SegmentDataIterator::<Field>::new(&inputs, Some(MAX_CPU_LEN_LOG))
+            .map(|segment| {
+                SegmentProof {
+                    save_inputs_on_error: false,
+                }
+                .execute(segment)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let segment_agg_proof = segment_proofs
+            .into_iter()
+            .map(Ok)
+            .reduce(|a, b| seg_agg_ops.combine(a?, b?))
+            .expect("a payload has at least one segment")?;
+        batch_proofs.push(BatchAggregatableProof::from(segment_agg_proof));
+    }
+
+    let [lhs, rhs] = <[_; 2]>::try_from(batch_proofs).unwrap();
+    let BatchAggregatableProof::Agg(txn_agg_proof) = batch_agg_ops.combine(lhs, rhs)? else {
+        anyhow::bail!("batch aggregation did not output a GeneratedTxnAggProof");
+    };
+
+    let block_proof = BlockProof {
+        prev: None,
+        save_inputs_on_error: false,
+        chain_profile: CHAIN_PROFILE,
+    }
+    .execute(txn_agg_proof)?;
+
+    let wrapped_block_proof = BlockWrapperProof {
+        chain_profile: CHAIN_PROFILE,
+    }
+    .execute(block_proof.clone())?;
+    let AggregatableBlockProof::Block(wrapped) = &wrapped_block_proof else {
+        anyhow::bail!("block wrapping did not output a block proof");
+    };
+    assert_eq!(wrapped.b_height, block_proof.b_height);
+
+    Ok(wrapped_block_proof)
+}
+
+#[ignore]
+#[test]
+fn test_block_wrapper_and_aggregation_ops() -> anyhow::Result<()> {
+    init_logger();
+    ProverStateManager {
+        circuit_config: circuit_config(),
+        persistence: CircuitPersistence::None,
+        chain_profiles: vec![CHAIN_PROFILE],
+    }
+    .initialize()?;
+
+    let block_agg_ops = BlockAggProof {
+        chain_profile: CHAIN_PROFILE,
+    };
+    let all_circuits = &p_state(CHAIN_PROFILE).state;
+
+    let proofs = [42, 43, 44]
+        .into_iter()
+        .map(wrapped_block_proof)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Aggregate the blocks as a left fold, mixing block and aggregation proofs.
+    let agg01 = block_agg_ops.combine(proofs[0].clone(), proofs[1].clone())?;
+    let agg012 = block_agg_ops.combine(agg01.clone(), proofs[2].clone())?;
+    for agg_proof in [agg01, agg012] {
+        let AggregatableBlockProof::Agg(agg_proof) = agg_proof else {
+            anyhow::bail!("block aggregation did not output a GeneratedAggBlockProof");
+        };
+        all_circuits
+            .two_to_one_block_verifier_data()
+            .verify(agg_proof.intern)?;
+    }
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use clap::Args;
 
+use crate::Batching;
+
 const HELP_HEADING: &str = "Prover options";
 
 /// Represents the main configuration structure for the runtime.
@@ -8,9 +10,13 @@ pub struct CliProverConfig {
     /// The log of the max number of CPU cycles per proof.
     #[arg(short, long, help_heading = HELP_HEADING, default_value_t = 19)]
     max_cpu_len_log: usize,
-    /// Number of transactions in a batch to process at once.
+    /// The budget of a batch of transactions to process at once: a number of
+    /// transactions, of gas or of CPU cycles depending on `--batching`.
     #[arg(short, long, help_heading = HELP_HEADING, default_value_t = 10)]
     batch_size: usize,
+    /// How to split the transactions of a block into batches.
+    #[arg(long, help_heading = HELP_HEADING, value_enum, default_value_t = Batching::Fixed)]
+    batching: Batching,
     /// If true, save the public inputs to disk on error.
     #[arg(short='i', long, help_heading = HELP_HEADING, default_value_t = false)]
     save_inputs_on_error: bool,
@@ -18,15 +24,27 @@ pub struct CliProverConfig {
     /// generating a proof.
     #[arg(long, help_heading = HELP_HEADING, default_value_t = false)]
     test_only: bool,
+    /// If true, report the diverging accounts and storage slots when the final
+    /// state root of a batch doesn't match. This slows down decoding.
+    #[arg(long, help_heading = HELP_HEADING, default_value_t = false)]
+    report_state_mismatch: bool,
+    /// If true, check each block's input for inconsistencies before decoding
+    /// it, and reject the block if any is found. This decodes the pre-images
+    /// once more.
+    #[arg(long, help_heading = HELP_HEADING, default_value_t = false)]
+    validate_inputs: bool,
 }
 
 impl From<CliProverConfig> for crate::ProverConfig {
     fn from(cli: CliProverConfig) -> Self {
         Self {
             batch_size: cli.batch_size,
+            batching: cli.batching,
             max_cpu_len_log: cli.max_cpu_len_log,
             save_inputs_on_error: cli.save_inputs_on_error,
             test_only: cli.test_only,
+            report_state_mismatch: cli.report_state_mismatch,
+            validate_inputs: cli.validate_inputs,
         }
     }
 }
@@ -0,0 +1,435 @@
+//! An on-disk journal of the proofs completed while proving a range of blocks.
+//!
+//! Each completed unit of work, i.e. the aggregated segment proof of a batch,
+//! the aggregated batch proof of a block and the final block proof, is
+//! persisted as soon as it is generated. This allows an interrupted run to
+//! resume from the last completed unit instead of starting over.
+//!
+//! The journal is laid out as follows:
+//!
+//! ```text
+//! <journal_dir>/manifest.json
+//! <journal_dir>/b<block>/batch_<batch>.segment_agg.json
+//! <journal_dir>/b<block>/txn_agg.json
+//! <journal_dir>/b<block>/block.json
+//! ```
+//!
+//! Entries are written to a temporary file first and then atomically renamed,
+//! so that a crash never leaves a partially written entry behind.
+
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::{keccak256, BlockNumber, B256};
+use anyhow::{bail, Context, Result};
+use evm_arithmetization::ChainProfile;
+use proof_gen::proof_types::{GeneratedBlockProof, GeneratedTxnAggProof, SegmentAggregatableProof};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+use zero_bin_common::prover_state::persistence::CIRCUIT_VERSION;
+
+use crate::{Batching, ProverConfig};
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const TMP_FILE_EXTENSION: &str = "tmp";
+
+/// The parameters a journal was created with. Proofs from a journal can only
+/// be reused if they were generated with the same circuits, for the same
+/// chain, with the same segmentation and batching of transactions, and on top
+/// of the same checkpoint and previous proof.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JournalManifest {
+    circuit_version: String,
+    chain_profile: ChainProfile,
+    batch_size: usize,
+    /// Journals predating this field were always batched by a fixed number of
+    /// transactions.
+    #[serde(default)]
+    batching: Batching,
+    max_cpu_len_log: usize,
+    checkpoint_block_number: u64,
+    /// The digest of the proof the first block of the range is proven on top
+    /// of, if any.
+    previous_proof: Option<B256>,
+}
+
+impl JournalManifest {
+    /// Returns the manifest of a journal for proving blocks with the current
+    /// circuits and the provided parameters.
+    pub fn new(
+        chain_profile: ChainProfile,
+        prover_config: &ProverConfig,
+        checkpoint_block_number: u64,
+        previous_proof: Option<&GeneratedBlockProof>,
+    ) -> Result<Self> {
+        let previous_proof = previous_proof
+            .map(|proof| serde_json::to_vec(proof).map(keccak256))
+            .transpose()?;
+
+        Ok(Self {
+            circuit_version: CIRCUIT_VERSION.to_string(),
+            chain_profile,
+            batch_size: prover_config.batch_size,
+            batching: prover_config.batching,
+            max_cpu_len_log: prover_config.max_cpu_len_log,
+            checkpoint_block_number,
+            previous_proof,
+        })
+    }
+
+    /// Describes the parameters of `self` which differ from the `expected`
+    /// ones.
+    fn mismatches(&self, expected: &Self) -> Vec<String> {
+        let fields = [
+            (
+                "circuit version",
+                self.circuit_version.clone(),
+                expected.circuit_version.clone(),
+            ),
+            (
+                "chain profile",
+                self.chain_profile.to_string(),
+                expected.chain_profile.to_string(),
+            ),
+            (
+                "batch size",
+                self.batch_size.to_string(),
+                expected.batch_size.to_string(),
+            ),
+            (
+                "batching",
+                format!("{:?}", self.batching),
+                format!("{:?}", expected.batching),
+            ),
+            (
+                "max CPU length log",
+                self.max_cpu_len_log.to_string(),
+                expected.max_cpu_len_log.to_string(),
+            ),
+            (
+                "checkpoint block number",
+                self.checkpoint_block_number.to_string(),
+                expected.checkpoint_block_number.to_string(),
+            ),
+            (
+                "previous proof",
+                format!("{:?}", self.previous_proof),
+                format!("{:?}", expected.previous_proof),
+            ),
+        ];
+
+        fields
+            .into_iter()
+            .filter(|(_, found, expected)| found != expected)
+            .map(|(name, found, expected)| format!("{name} {found} instead of {expected}"))
+            .collect()
+    }
+}
+
+/// Identifies a unit of work recorded in the journal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JournalKey {
+    /// The [`SegmentAggregatableProof`] of all the segments of a batch.
+    SegmentAgg { block: BlockNumber, batch: usize },
+    /// The [`GeneratedTxnAggProof`] of all the batches of a block.
+    TxnAgg { block: BlockNumber },
+    /// The [`GeneratedBlockProof`] of a block.
+    Block { block: BlockNumber },
+}
+
+impl JournalKey {
+    fn relative_path(&self) -> PathBuf {
+        match self {
+            JournalKey::SegmentAgg { block, batch } => {
+                PathBuf::from(format!("b{block}/batch_{batch}.segment_agg.json"))
+            }
+            JournalKey::TxnAgg { block } => PathBuf::from(format!("b{block}/txn_agg.json")),
+            JournalKey::Block { block } => PathBuf::from(format!("b{block}/block.json")),
+        }
+    }
+
+    /// Parses the key of a journal entry from its path relative to the
+    /// journal directory.
+    fn from_relative_path(path: &Path) -> Option<Self> {
+        let block = path.parent()?.to_str()?.strip_prefix('b')?.parse().ok()?;
+        match path.file_name()?.to_str()? {
+            "txn_agg.json" => Some(JournalKey::TxnAgg { block }),
+            "block.json" => Some(JournalKey::Block { block }),
+            file_name => {
+                let batch = file_name
+                    .strip_prefix("batch_")?
+                    .strip_suffix(".segment_agg.json")?
+                    .parse()
+                    .ok()?;
+                Some(JournalKey::SegmentAgg { block, batch })
+            }
+        }
+    }
+}
+
+/// A crash-safe, on-disk record of completed proofs.
+#[derive(Debug)]
+pub struct ProvingJournal {
+    dir: PathBuf,
+}
+
+impl ProvingJournal {
+    /// Opens the journal located in `dir`, creating it if needed.
+    ///
+    /// An existing journal is only reused when `resume` is set, in which case
+    /// it must have been created with the `expected_manifest`, and all its
+    /// entries must be readable.
+    pub fn open(dir: PathBuf, expected_manifest: &JournalManifest, resume: bool) -> Result<Self> {
+        let journal = Self { dir };
+
+        let manifest_path = journal.dir.join(MANIFEST_FILE_NAME);
+        if manifest_path.exists() {
+            if !resume {
+                bail!(
+                    "a proving journal already exists at {}, pass `--resume` to continue from it \
+                     or remove it",
+                    journal.dir.display()
+                );
+            }
+
+            let manifest: JournalManifest = serde_json::from_slice(&std::fs::read(&manifest_path)?)
+                .with_context(|| format!("invalid journal manifest {}", manifest_path.display()))?;
+            let mismatches = manifest.mismatches(expected_manifest);
+            if !mismatches.is_empty() {
+                bail!(
+                    "proving journal {} was created with different parameters: {}",
+                    journal.dir.display(),
+                    mismatches.join(", ")
+                );
+            }
+
+            let num_entries = journal.validate_entries()?;
+            info!(
+                "Resuming from proving journal {} with {num_entries} completed proofs",
+                journal.dir.display()
+            );
+        } else {
+            if resume {
+                warn!(
+                    "No proving journal found at {}, starting from scratch",
+                    journal.dir.display()
+                );
+            }
+
+            std::fs::create_dir_all(&journal.dir)?;
+            std::fs::write(&manifest_path, serde_json::to_vec(expected_manifest)?)?;
+        }
+
+        Ok(journal)
+    }
+
+    /// Checks that every entry of the journal can be deserialized, removing
+    /// the leftovers of interrupted writes. Returns the number of entries.
+    fn validate_entries(&self) -> Result<usize> {
+        let mut num_entries = 0;
+        for block_dir in std::fs::read_dir(&self.dir)? {
+            let block_dir = block_dir?.path();
+            if !block_dir.is_dir() {
+                continue;
+            }
+
+            for entry in std::fs::read_dir(&block_dir)? {
+                let path = entry?.path();
+                if path
+                    .extension()
+                    .is_some_and(|ext| ext == TMP_FILE_EXTENSION)
+                {
+                    std::fs::remove_file(&path)?;
+                    continue;
+                }
+
+                let key = path
+                    .strip_prefix(&self.dir)
+                    .ok()
+                    .and_then(JournalKey::from_relative_path)
+                    .with_context(|| format!("unexpected journal entry {}", path.display()))?;
+                let contents = std::fs::read(&path)?;
+                let parsed = match key {
+                    JournalKey::SegmentAgg { .. } => {
+                        serde_json::from_slice::<SegmentAggregatableProof>(&contents).map(|_| ())
+                    }
+                    JournalKey::TxnAgg { .. } => {
+                        serde_json::from_slice::<GeneratedTxnAggProof>(&contents).map(|_| ())
+                    }
+                    JournalKey::Block { .. } => {
+                        serde_json::from_slice::<GeneratedBlockProof>(&contents).map(|_| ())
+                    }
+                };
+                parsed.with_context(|| format!("corrupted journal entry {}", path.display()))?;
+                num_entries += 1;
+            }
+        }
+
+        Ok(num_entries)
+    }
+
+    /// Returns the proof recorded under `key`, if any.
+    pub async fn load<T: DeserializeOwned>(&self, key: JournalKey) -> Result<Option<T>> {
+        let path = self.dir.join(key.relative_path());
+        match tokio::fs::read(&path).await {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .with_context(|| format!("corrupted journal entry {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Durably records `proof` under `key`.
+    pub async fn record<T: Serialize>(&self, key: JournalKey, proof: &T) -> Result<()> {
+        let path = self.dir.join(key.relative_path());
+        let tmp_path = path.with_extension(TMP_FILE_EXTENSION);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut f = tokio::fs::File::create(&tmp_path).await?;
+        f.write_all(&serde_json::to_vec(proof)?).await?;
+        f.sync_all().await?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .with_context(|| format!("failed to write journal entry {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROVER_CONFIG: ProverConfig = ProverConfig {
+        batch_size: 1,
+        batching: Batching::Fixed,
+        max_cpu_len_log: 20,
+        save_inputs_on_error: false,
+        test_only: false,
+        report_state_mismatch: false,
+        validate_inputs: false,
+    };
+
+    /// Returns an empty directory for the journal of the test `name`.
+    fn journal_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("zero_bin_journal_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn block_proof(b_height: BlockNumber) -> GeneratedBlockProof {
+        GeneratedBlockProof {
+            b_height,
+            intern: proof_gen::proof_gen::dummy_proof().unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn journal_record_then_resume() -> Result<()> {
+        let dir = journal_dir("record_then_resume");
+        let previous_proof = block_proof(9);
+        let manifest =
+            JournalManifest::new(ChainProfile::L1, &PROVER_CONFIG, 8, Some(&previous_proof))?;
+
+        let journal = ProvingJournal::open(dir.clone(), &manifest, false)?;
+        let key = JournalKey::Block { block: 10 };
+        let proof = block_proof(10);
+        journal.record(key, &proof).await?;
+        // Leftover of a write interrupted by a crash.
+        std::fs::create_dir_all(dir.join("b11"))?;
+        std::fs::write(dir.join("b11/block.tmp"), b"{")?;
+        drop(journal);
+
+        // An existing journal is only reused when resuming.
+        assert!(ProvingJournal::open(dir.clone(), &manifest, false).is_err());
+
+        let journal = ProvingJournal::open(dir.clone(), &manifest, true)?;
+        assert!(!dir.join("b11/block.tmp").exists());
+        let loaded = journal.load::<GeneratedBlockProof>(key).await?.unwrap();
+        assert_eq!(serde_json::to_vec(&loaded)?, serde_json::to_vec(&proof)?);
+        assert!(journal
+            .load::<GeneratedBlockProof>(JournalKey::Block { block: 11 })
+            .await?
+            .is_none());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn journal_resume_rejects_mismatched_manifest() -> Result<()> {
+        let dir = journal_dir("resume_rejects_mismatched_manifest");
+        let previous_proof = block_proof(9);
+        let manifest =
+            JournalManifest::new(ChainProfile::L1, &PROVER_CONFIG, 8, Some(&previous_proof))?;
+        ProvingJournal::open(dir.clone(), &manifest, false)?;
+
+        let mismatched_manifests = [
+            JournalManifest::new(
+                ChainProfile::CdkErigon,
+                &PROVER_CONFIG,
+                8,
+                Some(&previous_proof),
+            )?,
+            JournalManifest::new(
+                ChainProfile::L1,
+                &ProverConfig {
+                    batching: Batching::Gas,
+                    ..PROVER_CONFIG
+                },
+                8,
+                Some(&previous_proof),
+            )?,
+            JournalManifest::new(
+                ChainProfile::L1,
+                &ProverConfig {
+                    max_cpu_len_log: 19,
+                    ..PROVER_CONFIG
+                },
+                8,
+                Some(&previous_proof),
+            )?,
+            JournalManifest::new(ChainProfile::L1, &PROVER_CONFIG, 7, Some(&previous_proof))?,
+            JournalManifest::new(ChainProfile::L1, &PROVER_CONFIG, 8, None)?,
+            JournalManifest::new(ChainProfile::L1, &PROVER_CONFIG, 8, Some(&block_proof(8)))?,
+        ];
+        for mismatched_manifest in &mismatched_manifests {
+            assert!(ProvingJournal::open(dir.clone(), mismatched_manifest, true).is_err());
+        }
+        ProvingJournal::open(dir.clone(), &manifest, true)?;
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn journal_key_path_roundtrip() {
+        for key in [
+            JournalKey::SegmentAgg {
+                block: 19_240_650,
+                batch: 3,
+            },
+            JournalKey::TxnAgg { block: 1 },
+            JournalKey::Block { block: 0 },
+        ] {
+            assert_eq!(
+                JournalKey::from_relative_path(&key.relative_path()),
+                Some(key)
+            );
+        }
+    }
+
+    #[test]
+    fn journal_key_rejects_unknown_entries() {
+        for path in [
+            "b1/unknown.json",
+            "block.json",
+            "bx/block.json",
+            "b1/batch_x.segment_agg.json",
+        ] {
+            assert_eq!(JournalKey::from_relative_path(Path::new(path)), None);
+        }
+    }
+}
@@ -1,27 +1,74 @@
 pub mod cli;
+pub mod journal;
+pub mod progress;
 
 use std::future::Future;
 use std::path::PathBuf;
 
 use alloy::primitives::{BlockNumber, U256};
 use anyhow::{Context, Result};
+use clap::ValueEnum;
+use evm_arithmetization::witness::errors::ExecutionErrorKind;
+use evm_arithmetization::{AllData, ChainProfile};
 use futures::{future::BoxFuture, stream::FuturesOrdered, FutureExt, TryFutureExt, TryStreamExt};
+use journal::{JournalKey, ProvingJournal};
 use num_traits::ToPrimitive as _;
 use paladin::runtime::Runtime;
-use proof_gen::proof_types::GeneratedBlockProof;
+use progress::{Aborted, ProvingProgress};
+use proof_gen::proof_types::{
+    AggregatableBlockProof, BatchAggregatableProof, GeneratedAggBlockProof, GeneratedBlockProof,
+    GeneratedTxnAggProof, SegmentAggregatableProof,
+};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 use tokio::sync::oneshot;
-use trace_decoder::{BlockTrace, OtherBlockData};
+use trace_decoder::{BatchingStrategy, BlockTrace, DecodingOptions, OtherBlockData};
 use tracing::info;
 use zero_bin_common::fs::generate_block_proof_file_name;
+use zero_bin_common::metrics::{Queue, QueueDepthGuard};
+
+/// Whether the generation of a segment was stopped by the abort signal of the
+/// proving process.
+fn is_aborted(segment: &AllData) -> bool {
+    matches!(segment, Err(err) if matches!(err.kind, ExecutionErrorKind::Aborted))
+}
+
+/// How the transactions of a block are split into batches, see
+/// [`BatchingStrategy`].
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ValueEnum,
+)]
+pub enum Batching {
+    /// Batches of at most `batch_size` transactions.
+    #[default]
+    Fixed,
+    /// Batches of transactions using at most `batch_size` gas.
+    Gas,
+    /// Batches of transactions taking at most `batch_size` CPU cycles.
+    Cycles,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct ProverConfig {
     pub batch_size: usize,
+    pub batching: Batching,
     pub max_cpu_len_log: usize,
     pub save_inputs_on_error: bool,
     pub test_only: bool,
+    pub report_state_mismatch: bool,
+    pub validate_inputs: bool,
+}
+
+impl ProverConfig {
+    /// Returns the strategy splitting the transactions of a block into
+    /// batches, with `batch_size` as the budget of each batch.
+    pub fn batching_strategy(&self) -> BatchingStrategy {
+        match self.batching {
+            Batching::Fixed => BatchingStrategy::Fixed(self.batch_size),
+            Batching::Gas => BatchingStrategy::Gas(self.batch_size as u64),
+            Batching::Cycles => BatchingStrategy::Cycles(self.batch_size),
+        }
+    }
 }
 
 pub type BlockProverInputFuture = std::pin::Pin<
@@ -48,98 +95,277 @@ impl BlockProverInput {
         self.other_data.b_data.b_meta.block_number.into()
     }
 
+    /// Checks the input for inconsistencies which would otherwise only fail
+    /// proving late, see [`trace_decoder::validate`].
+    pub fn validate(&self) -> Result<()> {
+        let problems = trace_decoder::validate(&self.block_trace, &self.other_data);
+        anyhow::ensure!(
+            problems.is_empty(),
+            "Invalid input for block {}:\n{}",
+            self.get_block_number(),
+            problems
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        Ok(())
+    }
+
     pub async fn prove(
         self,
         runtime: &Runtime,
         previous: Option<impl Future<Output = Result<GeneratedBlockProof>>>,
         prover_config: ProverConfig,
+        journal: Option<&ProvingJournal>,
+        progress: Option<&ProvingProgress>,
     ) -> Result<GeneratedBlockProof> {
         use anyhow::Context as _;
         use evm_arithmetization::SegmentDataIterator;
-        use futures::{stream::FuturesUnordered, FutureExt};
+        use futures::StreamExt as _;
         use paladin::directive::{Directive, IndexedStream};
 
         let ProverConfig {
             max_cpu_len_log,
-            batch_size,
+            batch_size: _,
+            batching: _,
             save_inputs_on_error,
             test_only: _,
+            report_state_mismatch,
+            validate_inputs,
         } = prover_config;
+        let batching = prover_config.batching_strategy();
+
+        let block_number = self
+            .get_block_number()
+            .to_u64()
+            .context("block number overflows u64")?;
+        let chain_profile = self.other_data.chain_profile;
+
+        if let Some(journal) = journal {
+            if let Some(block_proof) = journal
+                .load::<GeneratedBlockProof>(JournalKey::Block {
+                    block: block_number,
+                })
+                .await?
+            {
+                // The previous block proof still needs to be consumed, for its sender not to
+                // fail.
+                if let Some(it) = previous {
+                    it.await?;
+                }
+                info!("Block {block_number} already proved, loaded from the proving journal");
+
+                return Ok(block_proof);
+            }
+        }
 
-        let block_number = self.get_block_number();
+        let journaled_batch_proof = match journal {
+            Some(journal) => {
+                journal
+                    .load::<GeneratedTxnAggProof>(JournalKey::TxnAgg {
+                        block: block_number,
+                    })
+                    .await?
+            }
+            None => None,
+        };
 
-        let use_burn_addr = cfg!(feature = "cdk_erigon");
-        let block_generation_inputs = trace_decoder::entrypoint(
-            self.block_trace,
-            self.other_data,
-            batch_size,
-            use_burn_addr,
-        )?;
+        let final_batch_proof = match journaled_batch_proof {
+            Some(proof) => {
+                info!("Block {block_number} batches loaded from the proving journal");
+                proof
+            }
+            None => {
+                // Decode the batches on a blocking thread, so that the first ones are
+                // proven while the following ones are still being decoded.
+                let (batch_sender, batch_receiver) = tokio::sync::mpsc::channel(1);
+                let decoder = tokio::task::spawn_blocking(move || {
+                    let validation = match validate_inputs {
+                        true => self.validate(),
+                        false => Ok(()),
+                    };
+                    let batches = match validation.and_then(|()| {
+                        trace_decoder::entrypoint_iter(
+                            self.block_trace,
+                            self.other_data,
+                            &batching,
+                            DecodingOptions {
+                                expected_tries_after: report_state_mismatch,
+                            },
+                        )
+                    }) {
+                        Ok(batches) => batches,
+                        Err(e) => {
+                            let _ = batch_sender.blocking_send(Err(e));
+                            return;
+                        }
+                    };
+                    for batch in batches {
+                        // The receiver is dropped if proving failed.
+                        if batch_sender.blocking_send(batch).is_err() {
+                            return;
+                        }
+                    }
+                });
+                let block_generation_inputs =
+                    futures::stream::unfold(batch_receiver, |mut receiver| async move {
+                        receiver.recv().await.map(|batch| (batch, receiver))
+                    });
+
+                // Create segment proof.
+                let seg_prove_ops = &ops::SegmentProof {
+                    save_inputs_on_error,
+                };
 
-        // Create segment proof.
-        let seg_prove_ops = ops::SegmentProof {
-            save_inputs_on_error,
-        };
+                // Aggregate multiple segment proofs to resulting segment proof.
+                let seg_agg_ops = &ops::SegmentAggProof {
+                    save_inputs_on_error,
+                    chain_profile,
+                };
 
-        // Aggregate multiple segment proofs to resulting segment proof.
-        let seg_agg_ops = ops::SegmentAggProof {
-            save_inputs_on_error,
-        };
+                // Aggregate batch proofs to a single proof.
+                let batch_agg_ops = ops::BatchAggProof {
+                    save_inputs_on_error,
+                    chain_profile,
+                };
+
+                // Segment the batches, prove segments and aggregate them to resulting batch
+                // proofs, unless they have already been recorded in the journal.
+                let batch_proof_futs = block_generation_inputs
+                    .enumerate()
+                    .map(|(idx, txn_batch)| async move {
+                        let txn_batch = txn_batch?;
+                        let key = JournalKey::SegmentAgg {
+                            block: block_number,
+                            batch: idx,
+                        };
+                        if let Some(journal) = journal {
+                            if let Some(proof) =
+                                journal.load::<SegmentAggregatableProof>(key).await?
+                            {
+                                return Ok((idx, BatchAggregatableProof::from(proof)));
+                            }
+                        }
 
-        // Aggregate batch proofs to a single proof.
-        let batch_agg_ops = ops::BatchAggProof {
-            save_inputs_on_error,
-        };
+                        if let Some(progress) = progress {
+                            progress.check_abort()?;
+                        }
+
+                        let mut segment_data_iterator = SegmentDataIterator::<
+                            proof_gen::types::Field,
+                        >::new(
+                            &txn_batch, Some(max_cpu_len_log)
+                        );
+                        if let Some(progress) = progress {
+                            segment_data_iterator =
+                                segment_data_iterator.with_abort_signal(progress.abort_signal());
+                        }
+                        // Stop dispatching segments once aborted, an incomplete batch being
+                        // rejected below. The first segment is checked eagerly, not to fold an
+                        // empty stream of segment proofs.
+                        let mut segment_data_iterator = segment_data_iterator.peekable();
+                        if segment_data_iterator.next_if(is_aborted).is_some() {
+                            return Err(Aborted.into());
+                        }
+                        // Segments left unproven if the batch fails are removed from the queue
+                        // when it is dropped.
+                        let queued_segments = &QueueDepthGuard::new(Queue::Segments);
+                        let segment_data_iterator = segment_data_iterator
+                            .map_while(|segment| (!is_aborted(&segment)).then_some(segment))
+                            .inspect(|_| {
+                                queued_segments.increment();
+                                if let Some(progress) = progress {
+                                    progress.segment_generated();
+                                }
+                            });
+
+                        let segment_proofs = Directive::map(
+                            IndexedStream::from(segment_data_iterator),
+                            seg_prove_ops,
+                        )
+                        .run(runtime)
+                        .await?
+                        .and_then(|segment_proof| async move {
+                            queued_segments.decrement();
+                            if let Some(progress) = progress {
+                                progress.segment_proven();
+                            }
+                            Ok(segment_proof)
+                        });
+
+                        let proof =
+                            Directive::fold(IndexedStream::new(segment_proofs), seg_agg_ops)
+                                .run(runtime)
+                                .await?;
+                        if let Some(progress) = progress {
+                            progress.check_abort()?;
+                        }
+
+                        if let Some(journal) = journal {
+                            journal.record(key, &proof).await?;
+                        }
 
-        // Segment the batches, prove segments and aggregate them to resulting batch
-        // proofs.
-        let batch_proof_futs: FuturesUnordered<_> = block_generation_inputs
-            .iter()
-            .enumerate()
-            .map(|(idx, txn_batch)| {
-                let segment_data_iterator = SegmentDataIterator::<proof_gen::types::Field>::new(
-                    txn_batch,
-                    Some(max_cpu_len_log),
-                );
-
-                Directive::map(IndexedStream::from(segment_data_iterator), &seg_prove_ops)
-                    .fold(&seg_agg_ops)
-                    .run(runtime)
-                    .map(move |e| {
-                        e.map(|p| (idx, proof_gen::proof_types::BatchAggregatableProof::from(p)))
+                        Ok::<_, anyhow::Error>((idx, BatchAggregatableProof::from(proof)))
                     })
-            })
-            .collect();
+                    .buffer_unordered(usize::MAX);
 
-        // Fold the batch aggregated proof stream into a single proof.
-        let final_batch_proof =
-            Directive::fold(IndexedStream::new(batch_proof_futs), &batch_agg_ops)
+                // Fold the batch aggregated proof stream into a single proof.
+                let final_batch_proof = Directive::fold(
+                    IndexedStream::new(Box::pin(batch_proof_futs)),
+                    &batch_agg_ops,
+                )
                 .run(runtime)
                 .await?;
+                decoder.await.context("batch decoding panicked")?;
+
+                let BatchAggregatableProof::Agg(proof) = final_batch_proof else {
+                    anyhow::bail!("AggProof is is not GeneratedAggProof")
+                };
+
+                if let Some(journal) = journal {
+                    journal
+                        .record(
+                            JournalKey::TxnAgg {
+                                block: block_number,
+                            },
+                            &proof,
+                        )
+                        .await?;
+                }
+
+                proof
+            }
+        };
 
-        if let proof_gen::proof_types::BatchAggregatableProof::Agg(proof) = final_batch_proof {
-            let block_number = block_number
-                .to_u64()
-                .context("block number overflows u64")?;
-            let prev = match previous {
-                Some(it) => Some(it.await?),
-                None => None,
-            };
+        let prev = match previous {
+            Some(it) => Some(it.await?),
+            None => None,
+        };
 
-            let block_proof = paladin::directive::Literal(proof)
-                .map(&ops::BlockProof {
-                    prev,
-                    save_inputs_on_error,
-                })
-                .run(runtime)
+        let block_proof = paladin::directive::Literal(final_batch_proof)
+            .map(&ops::BlockProof {
+                prev,
+                save_inputs_on_error,
+                chain_profile,
+            })
+            .run(runtime)
+            .await?;
+
+        if let Some(journal) = journal {
+            journal
+                .record(
+                    JournalKey::Block {
+                        block: block_number,
+                    },
+                    &block_proof.0,
+                )
                 .await?;
+        }
 
-            info!("Successfully proved block {block_number}");
+        info!("Successfully proved block {block_number}");
 
-            Ok(block_proof.0)
-        } else {
-            anyhow::bail!("AggProof is is not GeneratedAggProof")
-        }
+        Ok(block_proof.0)
     }
 
     pub async fn prove_test(
@@ -147,6 +373,7 @@ impl BlockProverInput {
         runtime: &Runtime,
         previous: Option<impl Future<Output = Result<GeneratedBlockProof>>>,
         prover_config: ProverConfig,
+        progress: Option<&ProvingProgress>,
     ) -> Result<GeneratedBlockProof> {
         use std::iter::repeat;
 
@@ -155,21 +382,35 @@ impl BlockProverInput {
 
         let ProverConfig {
             max_cpu_len_log,
-            batch_size,
+            batch_size: _,
+            batching: _,
             save_inputs_on_error,
             test_only: _,
+            report_state_mismatch,
+            validate_inputs,
         } = prover_config;
+        let batching = prover_config.batching_strategy();
 
         let block_number = self.get_block_number();
         info!("Testing witness generation for block {block_number}.");
+        let check_abort = move || match progress {
+            Some(progress) => progress.check_abort(),
+            None => Ok(()),
+        };
 
-        let use_burn_addr = cfg!(feature = "cdk_erigon");
-        let block_generation_inputs = trace_decoder::entrypoint(
+        if validate_inputs {
+            self.validate()?;
+        }
+        let block_generation_inputs = trace_decoder::entrypoint_iter(
             self.block_trace,
             self.other_data,
-            batch_size,
-            use_burn_addr,
-        )?;
+            &batching,
+            DecodingOptions {
+                expected_tries_after: report_state_mismatch,
+            },
+        )?
+        .collect::<Result<Vec<_>>>()?;
+        check_abort()?;
 
         let seg_ops = ops::SegmentProofTestOnly {
             save_inputs_on_error,
@@ -179,7 +420,9 @@ impl BlockProverInput {
             IndexedStream::from(
                 block_generation_inputs
                     .into_iter()
-                    .zip(repeat(max_cpu_len_log)),
+                    .zip(repeat(max_cpu_len_log))
+                    // Stop dispatching batches once aborted.
+                    .map_while(|batch| check_abort().is_ok().then_some(batch)),
             ),
             &seg_ops,
         );
@@ -187,8 +430,10 @@ impl BlockProverInput {
         simulation
             .run(runtime)
             .await?
-            .try_for_each(|_| future::ok(()))
+            .try_for_each(|_| future::ready(check_abort()))
             .await?;
+        // The batches left undispatched don't fail the simulation.
+        check_abort()?;
 
         info!("Successfully generated witness for block {block_number}.");
 
@@ -212,12 +457,17 @@ impl BlockProverInput {
 /// the selected prover configuration. Return the list of block numbers that are
 /// proved and if the proof data is not saved to disk, return the generated
 /// block proofs as well.
+///
+/// If a proving `journal` is provided, all completed proofs are recorded in it,
+/// and the ones it already contains are reused instead of being generated
+/// again.
 pub async fn prove(
     block_prover_inputs: Vec<BlockProverInputFuture>,
     runtime: &Runtime,
     previous_proof: Option<GeneratedBlockProof>,
     prover_config: ProverConfig,
     proof_output_dir: Option<PathBuf>,
+    journal: Option<&ProvingJournal>,
 ) -> Result<Vec<(BlockNumber, Option<GeneratedBlockProof>)>> {
     let mut prev: Option<BoxFuture<Result<GeneratedBlockProof>>> =
         previous_proof.map(|proof| Box::pin(futures::future::ok(proof)) as BoxFuture<_>);
@@ -236,7 +486,7 @@ pub async fn prove(
             // Prove the block
             let block_proof = if prover_config.test_only {
                 block
-                    .prove_test(runtime, previous_block_proof, prover_config)
+                    .prove_test(runtime, previous_block_proof, prover_config, None)
                     .then(move |proof| async move {
                         let proof = proof?;
                         let block_number = proof.b_height;
@@ -260,7 +510,7 @@ pub async fn prove(
                     .await?
             } else {
                 block
-                    .prove(runtime, previous_block_proof, prover_config)
+                    .prove(runtime, previous_block_proof, prover_config, journal, None)
                     .then(move |proof| async move {
                         let proof = proof?;
                         let block_number = proof.b_height;
@@ -294,6 +544,81 @@ pub async fn prove(
     results.try_collect().await
 }
 
+/// Prove all the blocks in the input independently and in parallel, and fold
+/// the resulting block proofs into a binary tree of two-to-one block
+/// aggregation proofs. Return the single aggregation proof covering all the
+/// blocks of the input.
+///
+/// As blocks are not chained to each other, each block input is expected to
+/// use the state root of its parent block as checkpoint. At least two blocks
+/// are required, as a single block proof is not an aggregation proof. All the
+/// blocks must be of `chain_profile`, which the aggregation circuits are built
+/// for.
+pub async fn prove_aggregated(
+    block_prover_inputs: Vec<BlockProverInputFuture>,
+    runtime: &Runtime,
+    prover_config: ProverConfig,
+    chain_profile: ChainProfile,
+) -> Result<GeneratedAggBlockProof> {
+    use futures::stream::FuturesUnordered;
+    use paladin::directive::{Directive, IndexedStream, Literal};
+
+    if block_prover_inputs.len() < 2 {
+        anyhow::bail!(
+            "block aggregation requires at least two blocks, got {}",
+            block_prover_inputs.len()
+        );
+    }
+
+    let block_wrapper_ops = &ops::BlockWrapperProof { chain_profile };
+    let block_agg_ops = ops::BlockAggProof { chain_profile };
+
+    let block_proof_futs: FuturesUnordered<_> = block_prover_inputs
+        .into_iter()
+        .enumerate()
+        .map(|(idx, block_prover_input)| async move {
+            let block = block_prover_input.await?;
+            anyhow::ensure!(
+                block.other_data.chain_profile == chain_profile,
+                "block {} has chain profile {}, but the blocks are aggregated with profile {}",
+                block.get_block_number(),
+                block.other_data.chain_profile,
+                chain_profile
+            );
+            info!("Proving block {}", block.get_block_number());
+
+            let block_proof = block
+                .prove(
+                    runtime,
+                    None::<futures::future::Ready<Result<GeneratedBlockProof>>>,
+                    prover_config,
+                    None,
+                    None,
+                )
+                .await?;
+
+            // Wrap the block proof for it to be aggregated with other blocks.
+            let wrapped_block_proof = Literal(block_proof)
+                .map(block_wrapper_ops)
+                .run(runtime)
+                .await?;
+
+            Ok::<_, anyhow::Error>((idx, wrapped_block_proof.0))
+        })
+        .collect();
+
+    let agg_proof = Directive::fold(IndexedStream::new(block_proof_futs), &block_agg_ops)
+        .run(runtime)
+        .await?;
+
+    match agg_proof {
+        AggregatableBlockProof::Agg(proof) => Ok(proof),
+        AggregatableBlockProof::Block(_) => {
+            anyhow::bail!("AggregatableBlockProof is not GeneratedAggBlockProof")
+        }
+    }
+}
+
 /// Write the proof to the `output_dir` directory.
 async fn write_proof_to_dir(output_dir: PathBuf, proof: GeneratedBlockProof) -> Result<()> {
     let block_proof_file_path =
@@ -0,0 +1,107 @@
+//! Progress reporting and cancellation of an ongoing proving job.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+/// Tracks the number of segments proven while proving blocks, and allows
+/// aborting the proving process.
+///
+/// Aborting is cooperative: it stops the generation of segments and is checked
+/// between units of work, so segment proofs already dispatched to workers
+/// still complete, but no further work is scheduled.
+#[derive(Debug, Default)]
+pub struct ProvingProgress {
+    segments_total: AtomicUsize,
+    segments_proven: AtomicUsize,
+    abort_signal: Arc<AtomicBool>,
+}
+
+/// The error returned by a proving process which has been aborted.
+#[derive(Clone, Copy, Debug)]
+pub struct Aborted;
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the proving process has been aborted")
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+impl Aborted {
+    /// Returns whether `err` was caused by aborting the proving process, as
+    /// opposed to a failure of the proving process itself.
+    pub fn is_cause_of(err: &anyhow::Error) -> bool {
+        err.chain().any(|cause| cause.is::<Aborted>())
+    }
+}
+
+impl ProvingProgress {
+    /// The number of segments generated so far. This only becomes the final
+    /// number of segments once the execution of all blocks has been
+    /// segmented.
+    pub fn segments_total(&self) -> usize {
+        self.segments_total.load(Ordering::Relaxed)
+    }
+
+    /// The number of segments proven so far.
+    pub fn segments_proven(&self) -> usize {
+        self.segments_proven.load(Ordering::Relaxed)
+    }
+
+    /// Requests the proving process to stop as soon as possible.
+    pub fn abort(&self) {
+        self.abort_signal.store(true, Ordering::Relaxed);
+    }
+
+    /// The signal set by [`ProvingProgress::abort`], to be given to the
+    /// witness generation.
+    pub fn abort_signal(&self) -> Arc<AtomicBool> {
+        self.abort_signal.clone()
+    }
+
+    /// Returns an [`Aborted`] error if the proving process has been aborted.
+    pub fn check_abort(&self) -> Result<()> {
+        match self.abort_signal.load(Ordering::Relaxed) {
+            true => Err(Aborted.into()),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn segment_generated(&self) {
+        self.segments_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn segment_proven(&self) {
+        self.segments_proven.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context as _;
+
+    use super::*;
+
+    #[test]
+    fn only_abort_errors_are_aborted() {
+        let progress = ProvingProgress::default();
+        assert!(progress.check_abort().is_ok());
+
+        progress.abort();
+        assert!(progress.abort_signal().load(Ordering::Relaxed));
+        let err = progress
+            .check_abort()
+            .context("proving block 3")
+            .unwrap_err();
+        assert!(Aborted::is_cause_of(&err));
+
+        // Failures are not mistaken for aborts, even once an abort was requested.
+        assert!(!Aborted::is_cause_of(&anyhow::anyhow!(
+            "segment proof failed"
+        )));
+    }
+}
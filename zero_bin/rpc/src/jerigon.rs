@@ -2,11 +2,12 @@ use alloy::{
     primitives::B256, providers::Provider, rpc::types::eth::BlockId, transports::Transport,
 };
 use anyhow::Context as _;
+use evm_arithmetization::ChainProfile;
 use prover::BlockProverInput;
 use serde::Deserialize;
 use serde_json::json;
 use trace_decoder::{BlockTrace, BlockTraceTriePreImages, CombinedPreImages, TxnInfo};
-use zero_bin_common::provider::CachedProvider;
+use zero_bin_common::{metrics, provider::CachedProvider};
 
 use super::fetch_other_block_data;
 
@@ -22,32 +23,43 @@ pub async fn block_prover_input<ProviderT, TransportT>(
     cached_provider: std::sync::Arc<CachedProvider<ProviderT, TransportT>>,
     target_block_id: BlockId,
     checkpoint_state_trie_root: B256,
+    chain_profile: ChainProfile,
 ) -> anyhow::Result<BlockProverInput>
 where
     ProviderT: Provider<TransportT>,
     TransportT: Transport + Clone,
 {
     // Grab trace information
-    let tx_results = cached_provider
-        .get_provider()
-        .await?
-        .raw_request::<_, Vec<ZeroTxResult>>(
-            "debug_traceBlockByNumber".into(),
-            (target_block_id, json!({"tracer": "zeroTracer"})),
-        )
-        .await?;
+    let tx_results = metrics::timed_rpc(
+        "debug_traceBlockByNumber",
+        cached_provider
+            .get_provider()
+            .await?
+            .raw_request::<_, Vec<ZeroTxResult>>(
+                "debug_traceBlockByNumber".into(),
+                (target_block_id, json!({"tracer": "zeroTracer"})),
+            ),
+    )
+    .await?;
 
     // Grab block witness info (packed as combined trie pre-images)
 
-    let block_witness = cached_provider
-        .get_provider()
-        .await?
-        .raw_request::<_, String>("eth_getWitness".into(), vec![target_block_id])
-        .await?;
+    let block_witness = metrics::timed_rpc(
+        "eth_getWitness",
+        cached_provider
+            .get_provider()
+            .await?
+            .raw_request::<_, String>("eth_getWitness".into(), vec![target_block_id]),
+    )
+    .await?;
 
-    let other_data =
-        fetch_other_block_data(cached_provider, target_block_id, checkpoint_state_trie_root)
-            .await?;
+    let other_data = fetch_other_block_data(
+        cached_provider,
+        target_block_id,
+        checkpoint_state_trie_root,
+        chain_profile,
+    )
+    .await?;
 
     // Assemble
     Ok(BlockProverInput {
@@ -3,13 +3,14 @@ use std::sync::Arc;
 use alloy::{
     primitives::{Bytes, FixedBytes, B256},
     providers::Provider,
-    rpc::types::eth::{BlockId, BlockTransactionsKind, Withdrawal},
+    rpc::types::eth::{Block, BlockId, BlockTransactionsKind, Withdrawal},
     transports::Transport,
 };
 use anyhow::{anyhow, Context as _};
 use clap::ValueEnum;
 use compat::Compat;
 use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
+use evm_arithmetization::ChainProfile;
 use futures::{StreamExt as _, TryStreamExt as _};
 use prover::BlockProverInput;
 use serde_json::json;
@@ -20,7 +21,7 @@ pub mod jerigon;
 pub mod native;
 pub mod retry;
 
-use zero_bin_common::provider::CachedProvider;
+use zero_bin_common::{metrics, provider::CachedProvider};
 
 pub(crate) type PreviousBlockHashes = [FixedBytes<32>; 256];
 
@@ -39,6 +40,7 @@ pub struct RpcParams {
     pub end_block: u64,
     pub checkpoint_block_number: Option<u64>,
     pub rpc_type: RpcType,
+    pub chain_profile: ChainProfile,
 }
 
 /// Obtain the prover input for one block
@@ -47,6 +49,7 @@ pub async fn block_prover_input<ProviderT, TransportT>(
     block_id: BlockId,
     checkpoint_state_trie_root: B256,
     rpc_type: RpcType,
+    chain_profile: ChainProfile,
 ) -> Result<BlockProverInput, anyhow::Error>
 where
     ProviderT: Provider<TransportT>,
@@ -54,10 +57,22 @@ where
 {
     match rpc_type {
         RpcType::Jerigon => {
-            jerigon::block_prover_input(cached_provider, block_id, checkpoint_state_trie_root).await
+            jerigon::block_prover_input(
+                cached_provider,
+                block_id,
+                checkpoint_state_trie_root,
+                chain_profile,
+            )
+            .await
         }
         RpcType::Native => {
-            native::block_prover_input(cached_provider, block_id, checkpoint_state_trie_root).await
+            native::block_prover_input(
+                cached_provider,
+                block_id,
+                checkpoint_state_trie_root,
+                chain_profile,
+            )
+            .await
         }
     }
 }
@@ -152,17 +167,17 @@ where
     // empty, hence the node executes this call as a contract creation function.
     // We use that execution not to produce a new contract bytecode - instead, we
     // return hashes. To look at the code use `cast disassemble <bytecode>`.
-    let bytes = cached_provider
-        .get_provider()
-        .await?
-        .raw_request::<_, Bytes>(
+    let bytes = metrics::timed_rpc(
+        "eth_call",
+        cached_provider.get_provider().await?.raw_request::<_, Bytes>(
             "eth_call".into(),
             (
                 json!({"data": "0x60005B60010180430340816020025280610101116300000002576120205FF3"}),
                 &format!("{:#x}", target_block_number),
             ),
-        )
-        .await?;
+        ),
+    )
+    .await?;
 
     let prev_hashes = bytes
         .chunks(32)
@@ -207,6 +222,7 @@ async fn fetch_other_block_data<ProviderT, TransportT>(
     cached_provider: Arc<CachedProvider<ProviderT, TransportT>>,
     target_block_id: BlockId,
     checkpoint_state_trie_root: B256,
+    chain_profile: ChainProfile,
 ) -> anyhow::Result<OtherBlockData>
 where
     ProviderT: Provider<TransportT>,
@@ -215,10 +231,33 @@ where
     let target_block = cached_provider
         .get_block(target_block_id, BlockTransactionsKind::Hashes)
         .await?;
-    let target_block_number = target_block.header.number;
-    let chain_id = cached_provider.get_provider().await?.get_chain_id().await?;
-    let prev_hashes = fetch_previous_block_hashes(cached_provider, target_block_number).await?;
+    let chain_id = metrics::timed_rpc(
+        "eth_chainId",
+        cached_provider.get_provider().await?.get_chain_id(),
+    )
+    .await?;
+    let prev_hashes =
+        fetch_previous_block_hashes(cached_provider, target_block.header.number).await?;
 
+    other_block_data(
+        &target_block,
+        chain_id,
+        prev_hashes,
+        checkpoint_state_trie_root,
+        chain_profile,
+    )
+}
+
+/// Builds the other block data from the target block, the chain id and the
+/// hashes of the blocks preceding it.
+pub(crate) fn other_block_data(
+    target_block: &Block,
+    chain_id: u64,
+    prev_hashes: PreviousBlockHashes,
+    checkpoint_state_trie_root: B256,
+    chain_profile: ChainProfile,
+) -> anyhow::Result<OtherBlockData> {
+    let target_block_number = target_block.header.number;
     let other_data = OtherBlockData {
         b_data: BlockLevelData {
             b_meta: BlockMetadata {
@@ -262,16 +301,17 @@ where
             },
             withdrawals: target_block
                 .withdrawals
-                .into_iter()
+                .iter()
                 .flatten()
                 .map(
                     |Withdrawal {
                          address, amount, ..
-                     }| { (address.compat(), amount.into()) },
+                     }| { (address.compat(), (*amount).into()) },
                 )
                 .collect(),
         },
         checkpoint_state_trie_root: checkpoint_state_trie_root.compat(),
+        chain_profile,
     };
     Ok(other_data)
 }
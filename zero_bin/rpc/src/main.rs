@@ -8,11 +8,13 @@ use alloy::rpc::types::{BlockNumberOrTag, BlockTransactionsKind};
 use alloy::transports::Transport;
 use anyhow::anyhow;
 use clap::{Args, Parser, Subcommand, ValueHint};
+use evm_arithmetization::ChainProfile;
 use futures::StreamExt;
 use prover::BlockProverInput;
 use rpc::{retry::build_http_retry_provider, RpcParams, RpcType};
 use tracing_subscriber::{prelude::*, EnvFilter};
 use url::Url;
+use zero_bin_common::metrics;
 use zero_bin_common::pre_checks::check_previous_proof_and_checkpoint;
 use zero_bin_common::provider::CachedProvider;
 use zero_bin_common::version;
@@ -32,6 +34,9 @@ pub(crate) struct RpcConfig {
     /// The maximum number of retries.
     #[arg(long, default_value_t = 0)]
     max_retries: u32,
+    /// The chain profile of the blocks to fetch.
+    #[arg(long, default_value_t = ChainProfile::L1)]
+    chain_profile: ChainProfile,
 }
 
 #[derive(Subcommand)]
@@ -102,6 +107,7 @@ where
             block_id,
             checkpoint_state_trie_root,
             params.rpc_type,
+            params.chain_profile,
         )
         .await?;
 
@@ -130,6 +136,7 @@ impl Cli {
                     end_block,
                     checkpoint_block_number,
                     rpc_type: self.config.rpc_type,
+                    chain_profile: self.config.chain_profile,
                 };
 
                 let block_prover_inputs =
@@ -139,12 +146,15 @@ impl Cli {
             Command::Extract { tx, batch_size } => {
                 let tx_hash: B256 = tx.parse()?;
                 // Get transaction info.
-                match cached_provider
-                    .clone()
-                    .get_provider()
-                    .await?
-                    .get_transaction_by_hash(tx_hash)
-                    .await?
+                match metrics::timed_rpc(
+                    "eth_getTransactionByHash",
+                    cached_provider
+                        .clone()
+                        .get_provider()
+                        .await?
+                        .get_transaction_by_hash(tx_hash),
+                )
+                .await?
                 {
                     Some(tx_info) => {
                         let block_number = tx_info.block_number.ok_or(anyhow!(
@@ -156,6 +166,7 @@ impl Cli {
                             end_block: block_number,
                             checkpoint_block_number: None,
                             rpc_type: self.config.rpc_type,
+                            chain_profile: self.config.chain_profile,
                         };
 
                         let block_prover_inputs =
@@ -167,12 +178,10 @@ impl Cli {
                                 block_number
                             ))?;
 
-                        let use_burn_addr = cfg!(feature = "cdk_erigon");
                         let generation_inputs = trace_decoder::entrypoint(
                             block_prover_input.block_trace,
                             block_prover_input.other_data,
                             batch_size,
-                            use_burn_addr,
                         )?;
 
                         if let Some(index) = tx_info.transaction_index {
@@ -8,11 +8,13 @@ use alloy::{
     rpc::types::eth::{BlockId, BlockTransactionsKind},
     transports::Transport,
 };
+use evm_arithmetization::ChainProfile;
 use futures::try_join;
 use prover::BlockProverInput;
 use trace_decoder::BlockTrace;
 use zero_bin_common::provider::CachedProvider;
 
+pub mod offline;
 mod state;
 mod txn;
 
@@ -23,6 +25,7 @@ pub async fn block_prover_input<ProviderT, TransportT>(
     provider: Arc<CachedProvider<ProviderT, TransportT>>,
     block_number: BlockId,
     checkpoint_state_trie_root: B256,
+    chain_profile: ChainProfile,
 ) -> anyhow::Result<BlockProverInput>
 where
     ProviderT: Provider<TransportT>,
@@ -30,7 +33,12 @@ where
 {
     let (block_trace, other_data) = try_join!(
         process_block_trace(provider.clone(), block_number),
-        crate::fetch_other_block_data(provider.clone(), block_number, checkpoint_state_trie_root,)
+        crate::fetch_other_block_data(
+            provider.clone(),
+            block_number,
+            checkpoint_state_trie_root,
+            chain_profile,
+        )
     )?;
 
     Ok(BlockProverInput {
@@ -0,0 +1,253 @@
+//! Builds the prover input of a block from archived node responses, rather
+//! than from a live node.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use alloy::{
+    primitives::{keccak256, Address, B256},
+    rpc::types::{
+        eth::{Block, EIP1186AccountProofResponse, TransactionReceipt},
+        trace::geth::{DiffMode, PreStateMode},
+    },
+};
+use anyhow::{bail, ensure, Context as _};
+use evm_arithmetization::ChainProfile;
+use prover::BlockProverInput;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use trace_decoder::BlockTrace;
+
+use super::{state, txn};
+use crate::{PreviousBlockHashes, PREVIOUS_HASHES_COUNT};
+
+/// Paths to the node responses for a block, from which its prover input can be
+/// built offline.
+#[derive(Clone, Debug)]
+pub struct BlockFiles {
+    /// The `eth_getBlockByNumber` response, with full transactions.
+    pub block: PathBuf,
+    /// The `eth_getBlockReceipts` response.
+    pub receipts: PathBuf,
+    /// The `debug_traceBlockByNumber` response with the `prestateTracer`.
+    pub prestate_traces: PathBuf,
+    /// The `debug_traceBlockByNumber` response with the `prestateTracer` in
+    /// diff mode.
+    pub diff_traces: PathBuf,
+    /// A list of `eth_getProof` responses at the parent block, one for each
+    /// account accessed by the block along with its accessed storage slots.
+    pub proofs_before: PathBuf,
+    /// The same list of `eth_getProof` responses at the block itself.
+    pub proofs_after: PathBuf,
+    /// A list of the hashes of the blocks preceding this one, the most recent
+    /// last. At most 256 are used.
+    pub prev_hashes: PathBuf,
+}
+
+/// The node responses for a block. The receipts and traces are in the order
+/// of the block transactions.
+#[derive(Clone, Debug)]
+pub struct BlockResponses {
+    pub block: Block,
+    pub receipts: Vec<TransactionReceipt>,
+    pub prestate_traces: Vec<PreStateMode>,
+    pub diff_traces: Vec<DiffMode>,
+    pub proofs_before: Vec<EIP1186AccountProofResponse>,
+    pub proofs_after: Vec<EIP1186AccountProofResponse>,
+    pub prev_hashes: PreviousBlockHashes,
+}
+
+/// The trace of a transaction, as returned by `debug_traceBlockByNumber`.
+#[derive(Debug, Deserialize)]
+struct TraceResult<T> {
+    result: T,
+}
+
+impl BlockResponses {
+    /// Reads the node responses at `files`.
+    pub fn read(files: &BlockFiles) -> anyhow::Result<Self> {
+        let prestate_traces: Vec<TraceResult<PreStateMode>> = read_json(&files.prestate_traces)?;
+        let diff_traces: Vec<TraceResult<DiffMode>> = read_json(&files.diff_traces)?;
+
+        Ok(Self {
+            block: read_json(&files.block)?,
+            receipts: read_json(&files.receipts)?,
+            prestate_traces: prestate_traces.into_iter().map(|it| it.result).collect(),
+            diff_traces: diff_traces.into_iter().map(|it| it.result).collect(),
+            proofs_before: read_json(&files.proofs_before)?,
+            proofs_after: read_json(&files.proofs_after)?,
+            prev_hashes: prev_hashes(read_json(&files.prev_hashes)?),
+        })
+    }
+}
+
+/// Reads the node responses at `files`, and builds the prover input of the
+/// block from them.
+pub fn block_prover_input_from_files(
+    files: &BlockFiles,
+    chain_id: u64,
+    checkpoint_state_trie_root: B256,
+    chain_profile: ChainProfile,
+) -> anyhow::Result<BlockProverInput> {
+    block_prover_input_from_responses(
+        BlockResponses::read(files)?,
+        chain_id,
+        checkpoint_state_trie_root,
+        chain_profile,
+    )
+}
+
+/// Builds the prover input of a block from the node responses for it.
+pub fn block_prover_input_from_responses(
+    BlockResponses {
+        block,
+        receipts,
+        prestate_traces,
+        diff_traces,
+        proofs_before,
+        proofs_after,
+        prev_hashes,
+    }: BlockResponses,
+    chain_id: u64,
+    checkpoint_state_trie_root: B256,
+    chain_profile: ChainProfile,
+) -> anyhow::Result<BlockProverInput> {
+    let transactions = block
+        .transactions
+        .as_transactions()
+        .context("No transactions in block")?;
+    ensure!(
+        receipts.len() == transactions.len()
+            && prestate_traces.len() == transactions.len()
+            && diff_traces.len() == transactions.len(),
+        "Expected {} receipts and traces, got {} receipts, {} prestate traces and {} diff traces",
+        transactions.len(),
+        receipts.len(),
+        prestate_traces.len(),
+        diff_traces.len()
+    );
+
+    let mut code_db = BTreeSet::new();
+    let mut txn_info = Vec::with_capacity(transactions.len());
+    for (((tx, receipt), read_trace), diff_trace) in transactions
+        .iter()
+        .zip(receipts)
+        .zip(prestate_traces)
+        .zip(diff_traces)
+    {
+        ensure!(
+            receipt.transaction_hash == tx.hash,
+            "Receipt of transaction {} found instead of {}",
+            receipt.transaction_hash,
+            tx.hash
+        );
+        let (tx_code_db, info) = txn::process_transaction_data(tx, receipt, read_trace, diff_trace)
+            .with_context(|| format!("Failed to process transaction {}", tx.hash))?;
+        code_db.extend(tx_code_db);
+        txn_info.push(info);
+    }
+
+    // Every account accessed by the block must come with its proofs.
+    let proofs_before = by_address(proofs_before);
+    let proofs_after = by_address(proofs_after);
+    let state_access = state::process_states_access(&txn_info, &block)?;
+    let mut account_proofs = Vec::with_capacity(state_access.len());
+    let mut next_account_proofs = Vec::with_capacity(state_access.len());
+    for address in state_access.keys() {
+        let (Some(before), Some(after)) = (proofs_before.get(address), proofs_after.get(address))
+        else {
+            bail!("Missing proofs for account {address}");
+        };
+        account_proofs.push((*address, before.clone()));
+        next_account_proofs.push((*address, after.clone()));
+    }
+
+    // The root of the account proofs at the parent block is its state root.
+    let prev_state_root = account_proofs
+        .first()
+        .and_then(|(_, proof)| proof.account_proof.first())
+        .map(keccak256)
+        .context("Empty account proofs")?;
+
+    let other_data = crate::other_block_data(
+        &block,
+        chain_id,
+        prev_hashes,
+        checkpoint_state_trie_root,
+        chain_profile,
+    )?;
+
+    Ok(BlockProverInput {
+        block_trace: BlockTrace {
+            txn_info,
+            code_db,
+            trie_pre_images: state::state_witness_from_proofs(
+                prev_state_root,
+                account_proofs,
+                next_account_proofs,
+            ),
+        },
+        other_data,
+    })
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn by_address(
+    proofs: Vec<EIP1186AccountProofResponse>,
+) -> HashMap<Address, EIP1186AccountProofResponse> {
+    proofs
+        .into_iter()
+        .map(|proof| (proof.address, proof))
+        .collect()
+}
+
+/// Keeps the last 256 hashes, padding them with zeros at the front for the
+/// first blocks of the chain.
+fn prev_hashes(hashes: Vec<B256>) -> PreviousBlockHashes {
+    let mut prev_hashes = [B256::ZERO; PREVIOUS_HASHES_COUNT];
+    let used = hashes.len().min(PREVIOUS_HASHES_COUNT);
+    prev_hashes[PREVIOUS_HASHES_COUNT - used..].copy_from_slice(&hashes[hashes.len() - used..]);
+    prev_hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prev_hashes_are_padded_at_the_front() {
+        let padded = prev_hashes((1..=3).map(B256::with_last_byte).collect());
+        assert!(padded[..PREVIOUS_HASHES_COUNT - 3]
+            .iter()
+            .all(|hash| *hash == B256::ZERO));
+        assert_eq!(
+            padded[PREVIOUS_HASHES_COUNT - 3..],
+            [1, 2, 3].map(B256::with_last_byte)
+        );
+
+        let truncated = prev_hashes((0..=255).chain([0]).map(B256::with_last_byte).collect());
+        assert_eq!(truncated[0], B256::with_last_byte(1));
+        assert_eq!(truncated[PREVIOUS_HASHES_COUNT - 1], B256::ZERO);
+    }
+
+    #[test]
+    fn parse_block_traces() {
+        let prestate = r#"[{"txHash": "0x01", "result": {
+            "0x0000000000000000000000000000000000000001": {"balance": "0x1", "nonce": 2}
+        }}]"#;
+        let diff = r#"[{"result": {"pre": {}, "post": {
+            "0x0000000000000000000000000000000000000001": {"balance": "0x2"}
+        }}}]"#;
+
+        let prestate: Vec<TraceResult<PreStateMode>> = serde_json::from_str(prestate).unwrap();
+        let diff: Vec<TraceResult<DiffMode>> = serde_json::from_str(diff).unwrap();
+        assert_eq!(prestate[0].result.0.len(), 1);
+        assert_eq!(diff[0].result.post.len(), 1);
+        assert!(diff[0].result.pre.is_empty());
+    }
+}
@@ -15,7 +15,7 @@ use trace_decoder::{
     BlockTraceTriePreImages, SeparateStorageTriesPreImage, SeparateTriePreImage,
     SeparateTriePreImages, TxnInfo,
 };
-use zero_bin_common::provider::CachedProvider;
+use zero_bin_common::{metrics, provider::CachedProvider};
 
 use crate::Compat;
 
@@ -38,11 +38,27 @@ where
         .header
         .state_root;
 
+    let (account_proofs, next_account_proofs) =
+        fetch_proof_data(state_access, cached_provider, block_number).await?;
+
+    Ok(state_witness_from_proofs(
+        prev_state_root,
+        account_proofs,
+        next_account_proofs,
+    ))
+}
+
+/// Builds the state witness from the account proofs at the parent block, and
+/// those at the block itself.
+pub(super) fn state_witness_from_proofs(
+    prev_state_root: B256,
+    account_proofs: Vec<(Address, EIP1186AccountProofResponse)>,
+    next_account_proofs: Vec<(Address, EIP1186AccountProofResponse)>,
+) -> BlockTraceTriePreImages {
     let (state, storage_proofs) =
-        generate_state_witness(prev_state_root, state_access, cached_provider, block_number)
-            .await?;
+        generate_state_witness(prev_state_root, account_proofs, next_account_proofs);
 
-    Ok(BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+    BlockTraceTriePreImages::Separate(SeparateTriePreImages {
         state: SeparateTriePreImage::Direct(state.build()),
         storage: SeparateStorageTriesPreImage::MultipleTries(
             storage_proofs
@@ -50,7 +66,7 @@ where
                 .map(|(a, m)| (a.compat(), SeparateTriePreImage::Direct(m.build())))
                 .collect(),
         ),
-    }))
+    })
 }
 
 /// Iterate over the tx_infos and process the state access for each address.
@@ -107,25 +123,17 @@ fn insert_beacon_roots_update(
 }
 
 /// Generates the state witness for the given block.
-async fn generate_state_witness<ProviderT, TransportT>(
+fn generate_state_witness(
     prev_state_root: B256,
-    accounts_state: HashMap<Address, HashSet<StorageKey>>,
-    cached_provider: Arc<CachedProvider<ProviderT, TransportT>>,
-    block_number: u64,
-) -> anyhow::Result<(
+    account_proofs: Vec<(Address, EIP1186AccountProofResponse)>,
+    next_account_proofs: Vec<(Address, EIP1186AccountProofResponse)>,
+) -> (
     PartialTrieBuilder<HashedPartialTrie>,
     HashMap<B256, PartialTrieBuilder<HashedPartialTrie>>,
-)>
-where
-    ProviderT: Provider<TransportT>,
-    TransportT: Transport + Clone,
-{
+) {
     let mut state = PartialTrieBuilder::new(prev_state_root.compat(), Default::default());
     let mut storage_proofs = HashMap::<B256, PartialTrieBuilder<HashedPartialTrie>>::new();
 
-    let (account_proofs, next_account_proofs) =
-        fetch_proof_data(accounts_state, cached_provider, block_number).await?;
-
     // Insert account proofs
     for (address, proof) in account_proofs.into_iter() {
         state.insert_proof(proof.account_proof.compat());
@@ -153,7 +161,7 @@ where
         }
     }
 
-    Ok((state, storage_proofs))
+    (state, storage_proofs)
 }
 
 /// Fetches the proof data for the given accounts and associated storage keys.
@@ -175,13 +183,16 @@ where
         .map(|(address, keys)| {
             let provider = provider.clone();
             async move {
-                let proof = provider
-                    .get_provider()
-                    .await?
-                    .get_proof(address, keys.into_iter().collect())
-                    .block_id((block_number - 1).into())
-                    .await
-                    .context("Failed to get proof for account")?;
+                let proof = metrics::timed_rpc(
+                    "eth_getProof",
+                    provider
+                        .get_provider()
+                        .await?
+                        .get_proof(address, keys.into_iter().collect())
+                        .block_id((block_number - 1).into()),
+                )
+                .await
+                .context("Failed to get proof for account")?;
                 anyhow::Result::Ok((address, proof))
             }
         })
@@ -190,13 +201,16 @@ where
     let next_account_proofs_fut = accounts_state.into_iter().map(|(address, keys)| {
         let provider = provider.clone();
         async move {
-            let proof = provider
-                .get_provider()
-                .await?
-                .get_proof(address, keys.into_iter().collect())
-                .block_id(block_number.into())
-                .await
-                .context("Failed to get proof for account")?;
+            let proof = metrics::timed_rpc(
+                "eth_getProof",
+                provider
+                    .get_provider()
+                    .await?
+                    .get_proof(address, keys.into_iter().collect())
+                    .block_id(block_number.into()),
+            )
+            .await
+            .context("Failed to get proof for account")?;
             anyhow::Result::Ok((address, proof))
         }
     });
@@ -22,6 +22,7 @@ use alloy::{
 use anyhow::Context as _;
 use futures::stream::{FuturesOrdered, TryStreamExt};
 use trace_decoder::{ContractCodeUsage, TxnInfo, TxnMeta, TxnTrace};
+use zero_bin_common::metrics;
 
 use super::CodeDb;
 use crate::Compat;
@@ -64,6 +65,25 @@ where
     TransportT: Transport + Clone,
 {
     let (tx_receipt, pre_trace, diff_trace) = fetch_tx_data(provider, &tx.hash).await?;
+    let (read, diff) = match (pre_trace, diff_trace) {
+        (
+            GethTrace::PreStateTracer(PreStateFrame::Default(read)),
+            GethTrace::PreStateTracer(PreStateFrame::Diff(diff)),
+        ) => (read, diff),
+        _ => unreachable!(),
+    };
+
+    process_transaction_data(tx, tx_receipt, read, diff)
+}
+
+/// Processes the given transaction from its receipt and its `prestateTracer`
+/// traces, in default and diff modes.
+pub(super) fn process_transaction_data(
+    tx: &Transaction,
+    tx_receipt: <Ethereum as Network>::ReceiptResponse,
+    read_trace: PreStateMode,
+    diff_trace: DiffMode,
+) -> anyhow::Result<(CodeDb, TxnInfo)> {
     let tx_status = tx_receipt.status();
     let tx_receipt = tx_receipt.map_inner(rlp::map_receipt_envelope);
     let access_list = parse_access_list(tx.access_list.as_ref());
@@ -74,13 +94,7 @@ where
         gas_used: tx_receipt.gas_used as u64,
     };
 
-    let (code_db, mut tx_traces) = match (pre_trace, diff_trace) {
-        (
-            GethTrace::PreStateTracer(PreStateFrame::Default(read)),
-            GethTrace::PreStateTracer(PreStateFrame::Diff(diff)),
-        ) => process_tx_traces(access_list, read, diff).await?,
-        _ => unreachable!(),
-    };
+    let (code_db, mut tx_traces) = process_tx_traces(access_list, read_trace, diff_trace);
 
     // Handle case when transaction failed and a contract creation was reverted
     if !tx_status && tx_receipt.contract_address.is_some() {
@@ -108,9 +122,18 @@ where
     ProviderT: Provider<TransportT>,
     TransportT: Transport + Clone,
 {
-    let tx_receipt_fut = provider.get_transaction_receipt(*tx_hash);
-    let pre_trace_fut = provider.debug_trace_transaction(*tx_hash, prestate_tracing_options(false));
-    let diff_trace_fut = provider.debug_trace_transaction(*tx_hash, prestate_tracing_options(true));
+    let tx_receipt_fut = metrics::timed_rpc(
+        "eth_getTransactionReceipt",
+        provider.get_transaction_receipt(*tx_hash),
+    );
+    let pre_trace_fut = metrics::timed_rpc(
+        "debug_traceTransaction",
+        provider.debug_trace_transaction(*tx_hash, prestate_tracing_options(false)),
+    );
+    let diff_trace_fut = metrics::timed_rpc(
+        "debug_traceTransaction",
+        provider.debug_trace_transaction(*tx_hash, prestate_tracing_options(true)),
+    );
 
     let (tx_receipt, pre_trace, diff_trace) =
         futures::try_join!(tx_receipt_fut, pre_trace_fut, diff_trace_fut,)?;
@@ -139,11 +162,11 @@ fn parse_access_list(access_list: Option<&AccessList>) -> HashMap<Address, HashS
 }
 
 /// Processes the transaction traces and updates the accounts state.
-async fn process_tx_traces(
+fn process_tx_traces(
     mut access_list: HashMap<Address, HashSet<H256>>,
     read_trace: PreStateMode,
     diff_trace: DiffMode,
-) -> anyhow::Result<(CodeDb, BTreeMap<Address, TxnTrace>)> {
+) -> (CodeDb, BTreeMap<Address, TxnTrace>) {
     let DiffMode {
         pre: pre_trace,
         post: post_trace,
@@ -173,7 +196,7 @@ async fn process_tx_traces(
             post_state,
             pre_state,
         );
-        let code = process_code(post_state, read_state, &mut code_db).await;
+        let code = process_code(post_state, read_state, &mut code_db);
         let nonce = process_nonce(post_state, &code);
         let self_destructed = process_self_destruct(post_state, pre_state);
 
@@ -189,7 +212,7 @@ async fn process_tx_traces(
         traces.insert(address, result);
     }
 
-    Ok((code_db, traces))
+    (code_db, traces)
 }
 
 /// Processes the nonce for the given account state.
@@ -271,7 +294,7 @@ fn process_storage(
 }
 
 /// Processes the code usage for the given account state.
-async fn process_code(
+fn process_code(
     post_state: Option<&AccountState>,
     read_state: Option<&AccountState>,
     code_db: &mut CodeDb,
@@ -0,0 +1,64 @@
+//! Decodes a block whose prover input is built from archived node responses.
+//!
+//! The fixtures in `tests/data/offline` are the responses of a node for a
+//! Cancun block with a single EIP-1559 transfer: the sender, the recipient,
+//! the block author and the beacon roots contract make up the whole state.
+
+use std::path::PathBuf;
+
+use alloy::primitives::B256;
+use evm_arithmetization::ChainProfile;
+use mpt_trie::partial_trie::PartialTrie as _;
+use rpc::native::offline::{block_prover_input_from_responses, BlockFiles, BlockResponses};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/offline")
+        .join(format!("{name}.json"))
+}
+
+fn block_files() -> BlockFiles {
+    BlockFiles {
+        block: fixture("block"),
+        receipts: fixture("receipts"),
+        prestate_traces: fixture("prestate_traces"),
+        diff_traces: fixture("diff_traces"),
+        proofs_before: fixture("proofs_before"),
+        proofs_after: fixture("proofs_after"),
+        prev_hashes: fixture("prev_hashes"),
+    }
+}
+
+#[test]
+fn decode_block_from_archived_responses() {
+    let responses = BlockResponses::read(&block_files()).unwrap();
+    let header = responses.block.header.clone();
+    let parent_state_root = responses.proofs_before[0].account_proof[0].clone();
+
+    let input =
+        block_prover_input_from_responses(responses, 1, B256::ZERO, ChainProfile::L1).unwrap();
+    assert_eq!(input.get_block_number(), header.number.into());
+    assert_eq!(input.block_trace.txn_info.len(), 1);
+
+    let gen_inputs = trace_decoder::entrypoint(input.block_trace, input.other_data, 1).unwrap();
+
+    // The transaction is padded with a dummy payload.
+    assert_eq!(gen_inputs.len(), 2);
+    assert_eq!(
+        gen_inputs
+            .iter()
+            .map(|it| it.signed_txns.len())
+            .sum::<usize>(),
+        1
+    );
+    assert_eq!(
+        gen_inputs[0].tries.state_trie.hash().0,
+        alloy::primitives::keccak256(parent_state_root).0
+    );
+
+    // Applying the traces yields the tries of the block header.
+    let roots_after = &gen_inputs.last().unwrap().trie_roots_after;
+    assert_eq!(roots_after.state_root.0, header.state_root.0);
+    assert_eq!(roots_after.transactions_root.0, header.transactions_root.0);
+    assert_eq!(roots_after.receipts_root.0, header.receipts_root.0);
+}
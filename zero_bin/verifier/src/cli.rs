@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use clap::{Parser, ValueHint};
+use evm_arithmetization::ChainProfile;
 use zero_bin_common::prover_state::cli::CliProverStateConfig;
 
 #[derive(Parser)]
@@ -8,6 +9,9 @@ pub(crate) struct Cli {
     /// The file containing the proof to verify
     #[arg(short, long, value_hint = ValueHint::FilePath)]
     pub(crate) file_path: PathBuf,
+    /// The chain profile of the proven blocks.
+    #[arg(long, default_value_t = ChainProfile::L1)]
+    pub(crate) chain_profile: ChainProfile,
     /// The prover configuration used to generate the preprocessed circuits
     /// and the verifier state.
     #[clap(flatten)]
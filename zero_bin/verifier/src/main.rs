@@ -4,7 +4,8 @@ use std::fs::File;
 use anyhow::Result;
 use clap::Parser;
 use dotenvy::dotenv;
-use proof_gen::proof_types::GeneratedBlockProof;
+use proof_gen::proof_types::{GeneratedAggBlockProof, GeneratedBlockProof};
+use serde::Deserialize;
 use serde_json::Deserializer;
 use tracing::info;
 use zero_bin_common::{
@@ -15,6 +16,16 @@ use zero_bin_common::{
 mod cli;
 mod init;
 
+/// The proofs that can be verified, as output by the leader.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum InputProofs {
+    /// A sequence of chained block proofs.
+    Blocks(Vec<GeneratedBlockProof>),
+    /// A single two-to-one aggregation proof of a range of blocks.
+    Aggregated(GeneratedAggBlockProof),
+}
+
 fn main() -> Result<()> {
     dotenv().ok();
     init::tracing();
@@ -34,23 +45,31 @@ fn main() -> Result<()> {
 
     let file = File::open(args.file_path)?;
     let des = &mut Deserializer::from_reader(&file);
-    let input_proofs: Vec<GeneratedBlockProof> = serde_path_to_error::deserialize(des)?;
+    let input_proofs: InputProofs = serde_path_to_error::deserialize(des)?;
 
     let verifier = args
         .prover_state_config
         .into_prover_state_manager()
-        .verifier()?;
-
-    if input_proofs.into_iter().all(|block_proof| {
-        verifier
-            .verify(&block_proof.intern)
-            .map_err(|e| {
-                info!("Proof verification failed with error: {:?}", e);
-            })
-            .is_ok()
-    }) {
-        info!("All proofs verified successfully!");
-    };
+        .verifier(args.chain_profile)?;
+
+    match input_proofs {
+        InputProofs::Blocks(block_proofs) => {
+            if block_proofs.into_iter().all(|block_proof| {
+                verifier
+                    .verify(&block_proof.intern)
+                    .map_err(|e| {
+                        info!("Proof verification failed with error: {:?}", e);
+                    })
+                    .is_ok()
+            }) {
+                info!("All proofs verified successfully!");
+            };
+        }
+        InputProofs::Aggregated(agg_proof) => match verifier.verify_agg_block(&agg_proof.intern) {
+            Ok(()) => info!("Aggregated proof verified successfully!"),
+            Err(e) => info!("Aggregated proof verification failed with error: {:?}", e),
+        },
+    }
 
     Ok(())
 }
@@ -5,6 +5,7 @@ use clap::Parser;
 use dotenvy::dotenv;
 use ops::register;
 use paladin::runtime::WorkerRuntime;
+use zero_bin_common::metrics::CliMetricsConfig;
 use zero_bin_common::prover_state::{
     cli::CliProverStateConfig,
     persistence::{set_circuit_cache_dir_env_if_not_set, CIRCUIT_VERSION},
@@ -25,6 +26,8 @@ struct Cli {
     paladin: paladin::config::Config,
     #[clap(flatten)]
     prover_state_config: CliProverStateConfig,
+    #[clap(flatten)]
+    metrics_config: CliMetricsConfig,
 }
 
 #[tokio::main]
@@ -43,6 +46,7 @@ async fn main() -> Result<()> {
     init::tracing();
     set_circuit_cache_dir_env_if_not_set()?;
     let args = Cli::parse();
+    args.metrics_config.spawn_server();
 
     args.prover_state_config
         .into_prover_state_manager()